@@ -0,0 +1,41 @@
+// Lexing and parsing benchmarks over the standard library sources, which mix
+// functions, structs, loops and string handling like typical programs do.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use veyra_compiler::{lexer, parser};
+
+const SOURCES: &[(&str, &str)] = &[
+    ("core", include_str!("../../stdlib/core.vey")),
+    ("string", include_str!("../../stdlib/string.vey")),
+    ("net", include_str!("../../stdlib/net.vey")),
+];
+
+fn lexing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lex");
+    for (name, source) in SOURCES {
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), source, |b, s| {
+            b.iter(|| lexer::tokenize(s).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, source) in SOURCES {
+        let tokens = lexer::tokenize(source).unwrap();
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &tokens, |b, t| {
+            b.iter_batched(
+                || t.clone(),
+                |tokens| parser::parse(tokens).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lexing, parsing);
+criterion_main!(benches);
@@ -0,0 +1,73 @@
+// Interpreter benchmarks: function calls (recursive fib), string building and
+// conversion, and dictionary lookups. Each program is parsed once; only
+// running it is measured.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use veyra_compiler::{lexer, parser, Interpreter, Program};
+
+fn program(source: &str) -> Program {
+    parser::parse(lexer::tokenize(source).unwrap()).unwrap()
+}
+
+fn run(program: &Program) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_echo_output(false);
+    interpreter.interpret(program).unwrap();
+}
+
+fn fib(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fib");
+    for n in [15, 20] {
+        let fib = program(&format!(
+            "fn fib(n) {{\n    if n < 2 {{\n        return n\n    }}\n    return fib(n - 1) + fib(n - 2)\n}}\nlet result = fib({})\n",
+            n
+        ));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &fib, |b, p| {
+            b.iter(|| run(p))
+        });
+    }
+    group.finish();
+}
+
+/// Many short-lived strings: concatenation, number formatting, case
+/// conversion and comparison
+fn string_churn(c: &mut Criterion) {
+    let churn = program(
+        "let matches = 0
+let i = 0
+while i < 2000 {
+    let name = \"user_\" + str(i % 50)
+    let upper = string_to_upper(name)
+    let key = string_to_lower(upper) + \":\" + str(i)
+    if string_trim(\"  \" + name + \"  \") == \"user_7\" {
+        matches = matches + 1
+    }
+    i = i + 1
+}
+",
+    );
+    c.bench_function("string_churn", |b| b.iter(|| run(&churn)));
+}
+
+/// Building a 500-entry dictionary and looking keys up in it
+fn dict_heavy(c: &mut Criterion) {
+    let entries: Vec<String> = (0..500).map(|i| format!("\"key{}\": {}", i, i)).collect();
+    let lookups = program(&format!(
+        "let table = {{{}}}
+let total = 0
+let i = 0
+while i < 1000 {{
+    let key = \"key\" + str(i % 700)
+    if key in table {{
+        total = total + table[key]
+    }}
+    i = i + 1
+}}
+",
+        entries.join(", ")
+    ));
+    c.bench_function("dict_heavy", |b| b.iter(|| run(&lookups)));
+}
+
+criterion_group!(benches, fib, string_churn, dict_heavy);
+criterion_main!(benches);
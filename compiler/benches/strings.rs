@@ -0,0 +1,44 @@
+// String-building benchmarks: assembling a log line by line with `+=` and
+// with `s = s + ...`, which should both take linear time.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use veyra_compiler::{lexer, parser, Interpreter, Program};
+
+fn log_program(lines: usize, statement: &str) -> Program {
+    let source = format!(
+        "let log = \"\"\nlet i = 0\nwhile i < {} {{\n    {}\n    i = i + 1\n}}\n",
+        lines, statement
+    );
+    parser::parse(lexer::tokenize(&source).unwrap()).unwrap()
+}
+
+fn run(program: &Program) {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_echo_output(false);
+    interpreter.interpret(program).unwrap();
+}
+
+fn log_assembly(c: &mut Criterion) {
+    let mut group = c.benchmark_group("log_assembly");
+    for lines in [1_000, 10_000] {
+        let append = log_program(
+            lines,
+            "log += \"[INFO] request \" + str(i) + \" handled\\n\"",
+        );
+        group.bench_with_input(BenchmarkId::new("add_assign", lines), &append, |b, p| {
+            b.iter(|| run(p))
+        });
+
+        let concat = log_program(
+            lines,
+            "log = log + \"[INFO] request \" + str(i) + \" handled\\n\"",
+        );
+        group.bench_with_input(BenchmarkId::new("concat", lines), &concat, |b, p| {
+            b.iter(|| run(p))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, log_assembly);
+criterion_main!(benches);
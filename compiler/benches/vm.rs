@@ -0,0 +1,85 @@
+// The bytecode VM against the tree-walking interpreter on the example
+// programs, and on a loop and recursion heavy script where the cost of
+// looking up variables by name dominates. Programs are parsed and compiled
+// up front, so only running them is timed.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use std::path::Path;
+use veyra_compiler::bytecode::{self, Module};
+use veyra_compiler::{parser, vm, Interpreter, Lexer, Program};
+
+/// Examples that run without input, imports or sleeping
+const EXAMPLES: &[&str] = &[
+    "advanced_calculator",
+    "advanced_features",
+    "arithmetic",
+    "arrays",
+    "data_analysis",
+    "destructuring",
+    "error_handling",
+    "functions",
+    "loops",
+    "options_results",
+    "traits",
+];
+
+const WORKLOAD: &str = "\
+fn fib(n) {
+    if n < 2 {
+        return n
+    }
+    return fib(n - 1) + fib(n - 2)
+}
+let mut total = 0
+for i in range(20000) {
+    total += i % 7
+}
+print(fib(16) + total)
+";
+
+fn parsed(source: &str) -> Program {
+    parser::parse(Lexer::new(source).tokenize().unwrap()).unwrap()
+}
+
+/// An interpreter that keeps the program's output to itself
+fn quiet() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_echo_output(false);
+    interpreter
+}
+
+fn programs() -> Vec<(String, Program)> {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let mut programs: Vec<(String, Program)> = EXAMPLES
+        .iter()
+        .map(|name| {
+            let path = examples.join(name).with_extension("vey");
+            let source = std::fs::read_to_string(&path).unwrap();
+            (name.to_string(), parsed(&source))
+        })
+        .collect();
+    programs.push(("loops_and_calls".to_string(), parsed(WORKLOAD)));
+    programs
+}
+
+fn engines(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engine");
+    for (name, program) in programs() {
+        let module: Module = bytecode::compile(&program).unwrap();
+        group.bench_with_input(BenchmarkId::new("interpreter", &name), &program, |b, p| {
+            b.iter(|| quiet().interpret(p).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("vm", &name), &module, |b, m| {
+            b.iter_batched(
+                || m.clone(),
+                |module| vm::run(&mut quiet(), module).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
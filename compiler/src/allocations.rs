@@ -0,0 +1,179 @@
+// Allocation tracking
+//
+// Reference targets, made by `&`, `&mut` and `deep_copy`, are the values the
+// interpreter keeps on its heap. They are freed when the last strong
+// reference goes away, so the only way a program leaks them is a cycle of
+// references it can no longer reach, or values such a cycle keeps alive.
+//
+// `Interpreter::track_allocations` turns on recording where each target is
+// created: the chain of Veyra functions running at the time. From then on
+// `allocation_counts` gives the targets created and still alive at each
+// site, and `leaks` the live targets the program cannot reach, grouped by
+// site. `veyra-pkg test --leaks` fails tests that leave any behind and
+// prints the report.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where tracked values were created: the functions running at the time,
+/// outermost first. Top-level code runs in none.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AllocationSite {
+    pub functions: Vec<String>,
+}
+
+impl fmt::Display for AllocationSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.functions.split_last() {
+            None => write!(f, "top-level code"),
+            Some((innermost, [])) => write!(f, "{}", innermost),
+            Some((innermost, callers)) => {
+                let callers: Vec<&str> = callers.iter().rev().map(String::as_str).collect();
+                write!(f, "{} (called from {})", innermost, callers.join(" < "))
+            }
+        }
+    }
+}
+
+/// Targets created at one site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteCount {
+    pub site: AllocationSite,
+    pub allocated: usize,
+    /// Those still alive
+    pub live: usize,
+}
+
+/// The sites seen so far and the functions running now
+#[derive(Debug, Default)]
+pub(crate) struct AllocationTracker {
+    stack: Vec<String>,
+    sites: Vec<AllocationSite>,
+    ids: HashMap<Vec<String>, usize>,
+    allocated: Vec<usize>,
+}
+
+impl AllocationTracker {
+    pub(crate) fn enter(&mut self, function: &str) {
+        self.stack.push(function.to_string());
+    }
+
+    pub(crate) fn leave(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Swap the functions running with those of another task
+    pub(crate) fn swap_stack(&mut self, stack: &mut Vec<String>) {
+        std::mem::swap(&mut self.stack, stack);
+    }
+
+    /// Count an allocation made now and return its site
+    pub(crate) fn record(&mut self) -> usize {
+        let id = match self.ids.get(&self.stack) {
+            Some(&id) => id,
+            None => {
+                let id = self.sites.len();
+                self.sites.push(AllocationSite {
+                    functions: self.stack.clone(),
+                });
+                self.ids.insert(self.stack.clone(), id);
+                self.allocated.push(0);
+                id
+            }
+        };
+        self.allocated[id] += 1;
+        id
+    }
+
+    pub(crate) fn site(&self, id: usize) -> &AllocationSite {
+        &self.sites[id]
+    }
+
+    /// Counts for every site, given the live targets of each
+    pub(crate) fn counts(&self, live: &[usize]) -> Vec<SiteCount> {
+        let mut counts: Vec<SiteCount> = self
+            .sites
+            .iter()
+            .zip(&self.allocated)
+            .enumerate()
+            .map(|(id, (site, &allocated))| SiteCount {
+                site: site.clone(),
+                allocated,
+                live: live.iter().filter(|&&l| l == id).count(),
+            })
+            .collect();
+        counts.sort_by(|a, b| b.live.cmp(&a.live).then(b.allocated.cmp(&a.allocated)));
+        counts
+    }
+}
+
+/// Live values the program can no longer reach
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeakReport {
+    /// Leaked values by site, most first
+    pub leaks: Vec<Leak>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leak {
+    /// None for values created before tracking started
+    pub site: Option<AllocationSite>,
+    /// Short descriptions of the values, such as `Node` or `array of 2`
+    pub values: Vec<String>,
+}
+
+/// Descriptions shown for each site
+const SHOWN_VALUES: usize = 3;
+
+impl LeakReport {
+    /// Group leaked values by where they were created
+    pub(crate) fn new(leaked: Vec<(Option<AllocationSite>, String)>) -> Self {
+        let mut leaks: Vec<Leak> = Vec::new();
+        for (site, value) in leaked {
+            match leaks.iter_mut().find(|leak| leak.site == site) {
+                Some(leak) => leak.values.push(value),
+                None => leaks.push(Leak {
+                    site,
+                    values: vec![value],
+                }),
+            }
+        }
+        leaks.sort_by_key(|leak| std::cmp::Reverse(leak.values.len()));
+        Self { leaks }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.leaks.is_empty()
+    }
+
+    /// Number of values leaked
+    pub fn count(&self) -> usize {
+        self.leaks.iter().map(|leak| leak.values.len()).sum()
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.count();
+        write!(
+            f,
+            "{} value{} leaked",
+            count,
+            if count == 1 { "" } else { "s" }
+        )?;
+        for leak in &self.leaks {
+            write!(f, "\n  {} created in ", leak.values.len())?;
+            match &leak.site {
+                Some(site) => write!(f, "{}", site)?,
+                None => write!(f, "code run before tracking started")?,
+            }
+            let shown = &leak.values[..leak.values.len().min(SHOWN_VALUES)];
+            write!(f, ": {}", shown.join(", "))?;
+            if leak.values.len() > SHOWN_VALUES {
+                write!(f, ", ...")?;
+            }
+        }
+        Ok(())
+    }
+}
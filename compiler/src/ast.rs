@@ -1,8 +1,63 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Program {
     pub items: Vec<Item>,
+    /// The line each item starts on
+    #[serde(default)]
+    pub lines: Lines,
+}
+
+/// The lines of source that the items of a program or the statements of a
+/// block start on, for the line tables of compiled code. Empty for code
+/// built rather than parsed. Where code was written is not part of what it
+/// means, so lines never make two trees unequal.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lines(Vec<Option<u32>>);
+
+impl Lines {
+    /// The line of the item or statement at `index`, if known
+    pub fn get(&self, index: usize) -> Option<u32> {
+        self.0.get(index).copied().flatten()
+    }
+
+    /// Record the line of the item or statement at `index`, forgetting the
+    /// lines of any after it
+    pub fn set(&mut self, index: usize, line: Option<u32>) {
+        if line.is_some() || index < self.0.len() {
+            self.0.resize(index, None);
+            self.0.push(line);
+        }
+    }
+
+    /// Replace the lines of the items or statements in `range` with those
+    /// of the `count` taking their place
+    #[allow(dead_code)]
+    pub fn splice(&mut self, range: std::ops::Range<usize>, mut lines: Lines, count: usize) {
+        if self.0.is_empty() && lines.0.is_empty() {
+            return;
+        }
+        self.0.resize(self.0.len().max(range.end), None);
+        lines.0.resize(count, None);
+        self.0.splice(range, lines.0);
+    }
+
+    /// Move the lines of the items or statements from `index` on by
+    /// `offset`
+    #[allow(dead_code)]
+    pub fn shift_from(&mut self, index: usize, offset: i64) {
+        for line in self.0.iter_mut().skip(index).flatten() {
+            *line = (*line as i64 + offset) as u32;
+        }
+    }
+}
+
+impl PartialEq for Lines {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -10,6 +65,7 @@ pub enum Item {
     Function(Function),
     Struct(Struct),
     Impl(Impl),
+    Trait(Trait),
     Import(Import),
     Actor(Actor),
     Statement(Statement),
@@ -22,6 +78,13 @@ pub struct Function {
     pub return_type: Option<Type>,
     pub body: Block,
     pub is_async: bool,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    /// Whether the parameter list ends with a comma, for the formatter
+    #[serde(default)]
+    pub trailing_comma: bool,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -35,18 +98,63 @@ pub struct Parameter {
 pub struct Struct {
     pub name: String,
     pub fields: Vec<Field>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    /// Whether the last field is followed by a comma, for the formatter
+    #[serde(default)]
+    pub trailing_comma: bool,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Comments after the last field
+    #[serde(default)]
+    pub end_comments: Vec<Comment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
     pub name: String,
     pub field_type: Type,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Impl {
     pub target: String,
+    /// The trait in `impl Trait for Type`
+    #[serde(default)]
+    pub trait_name: Option<String>,
     pub methods: Vec<Function>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Comments after the last member
+    #[serde(default)]
+    pub end_comments: Vec<Comment>,
+}
+
+/// `trait Name { ... }`: the methods a type provides by implementing it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trait {
+    pub name: String,
+    pub methods: Vec<TraitMethod>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Comments after the last method
+    #[serde(default)]
+    pub end_comments: Vec<Comment>,
+}
+
+/// A method of a trait: a signature that implementations must define, or
+/// a method with a default body that they may override
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraitMethod {
+    /// The method; a required method has an empty body
+    pub function: Function,
+    pub has_default: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -61,6 +169,233 @@ pub struct Actor {
     pub name: String,
     pub fields: Vec<Field>,
     pub methods: Vec<Function>,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Comments after the last member
+    #[serde(default)]
+    pub end_comments: Vec<Comment>,
+}
+
+/// A comment, kept when the source is parsed for tools such as the
+/// formatter. Declarations and fields hold the comments on the lines before
+/// them and at the end of their last line; elsewhere a comment is a
+/// statement of its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    /// The comment as written, from its `#`
+    pub text: String,
+    /// Whether it follows code on the same line
+    pub trailing: bool,
+}
+
+impl Comment {
+    /// The text without the `#` or `#[[ ]]#` around it
+    #[allow(dead_code)]
+    pub fn content(&self) -> &str {
+        match self.text.strip_prefix("#[[") {
+            Some(block) => block.strip_suffix("]]#").unwrap_or(block).trim(),
+            None => self.text.trim_start_matches('#').trim(),
+        }
+    }
+}
+
+/// An annotation such as `@cfg(os = "linux")` written before an item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<AttributeArg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AttributeArg {
+    Literal(Literal),          // "message"
+    KeyValue(String, Literal), // key = "value"
+    Nested(Attribute),         // name or name(args)
+}
+
+impl Attribute {
+    /// The first argument when it is a string, as in `@deprecated("note")`
+    #[allow(dead_code)]
+    pub fn string_arg(&self) -> Option<&str> {
+        match self.args.first() {
+            Some(AttributeArg::Literal(Literal::String(value))) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value of a `key = value` argument
+    #[allow(dead_code)]
+    pub fn value(&self, key: &str) -> Option<&Literal> {
+        self.args.iter().find_map(|arg| match arg {
+            AttributeArg::KeyValue(name, value) if name == key => Some(value),
+            _ => None,
+        })
+    }
+}
+
+/// The size of an actor's mailbox, from its `@mailbox` attribute
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MailboxConfig {
+    /// Messages it holds; `None` means unbounded
+    pub capacity: Option<usize>,
+    /// What sending to a full mailbox does
+    pub overflow: Overflow,
+}
+
+/// What happens to a message sent to a full mailbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Overflow {
+    /// The sender waits until the actor has handled a message
+    #[default]
+    Block,
+    /// The oldest queued message is dropped to make room
+    DropOldest,
+    /// The send fails
+    Error,
+}
+
+impl Overflow {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Overflow::Block),
+            "drop_oldest" => Some(Overflow::DropOldest),
+            "error" => Some(Overflow::Error),
+            _ => None,
+        }
+    }
+}
+
+impl MailboxConfig {
+    /// The mailbox an actor declares with `@mailbox`; unbounded without one
+    pub fn of(actor: &Actor) -> Self {
+        actor
+            .attributes
+            .iter()
+            .find(|attribute| attribute.name == "mailbox")
+            .and_then(|attribute| Self::from_attribute(attribute).ok())
+            .unwrap_or_default()
+    }
+
+    /// Read `@mailbox(capacity = 100, overflow = "error")`; the parser
+    /// reports the message of an invalid one
+    pub fn from_attribute(attribute: &Attribute) -> std::result::Result<Self, String> {
+        let mut config = Self::default();
+        for arg in &attribute.args {
+            match arg {
+                AttributeArg::KeyValue(key, Literal::Integer(n)) if key == "capacity" && *n > 0 => {
+                    config.capacity = Some(*n as usize);
+                }
+                AttributeArg::KeyValue(key, Literal::String(name)) if key == "overflow" => {
+                    config.overflow = Overflow::from_name(name).ok_or_else(|| {
+                        format!(
+                            "Unknown overflow strategy '{}'; use \"block\", \"drop_oldest\" or \"error\"",
+                            name
+                        )
+                    })?;
+                }
+                _ => {
+                    return Err("@mailbox takes a positive capacity and an overflow strategy, e.g. @mailbox(capacity = 100, overflow = \"drop_oldest\")".to_string())
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Source form of an attribute without the leading `@`, e.g. `cfg(os = "linux")`
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        f.write_str("(")?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match arg {
+                AttributeArg::Literal(value) => write_literal(f, value)?,
+                AttributeArg::KeyValue(key, value) => {
+                    write!(f, "{} = ", key)?;
+                    write_literal(f, value)?;
+                }
+                AttributeArg::Nested(nested) => write!(f, "{}", nested)?,
+            }
+        }
+        f.write_str(")")
+    }
+}
+
+fn write_literal(f: &mut fmt::Formatter<'_>, literal: &Literal) -> fmt::Result {
+    match literal {
+        Literal::Integer(n) => write!(f, "{}", n),
+        Literal::Float(x) => write!(f, "{:?}", x),
+        Literal::String(s) => write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        Literal::Char(c) => write!(f, "'{}'", c),
+        Literal::Boolean(b) => write!(f, "{}", b),
+        Literal::None => f.write_str("None"),
+    }
+}
+
+/// Items that can carry `@name(...)` attributes
+pub trait Attributed {
+    fn attributes(&self) -> &[Attribute];
+
+    /// The first attribute called `name`
+    fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes().iter().find(|a| a.name == name)
+    }
+
+    #[allow(dead_code)]
+    fn has_attribute(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+}
+
+impl Attributed for Function {
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+impl Attributed for Struct {
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+impl Attributed for Impl {
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+impl Attributed for Trait {
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+impl Attributed for Actor {
+    fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+impl Attributed for Item {
+    fn attributes(&self) -> &[Attribute] {
+        match self {
+            Item::Function(func) => &func.attributes,
+            Item::Struct(s) => &s.attributes,
+            Item::Impl(imp) => &imp.attributes,
+            Item::Trait(tr) => &tr.attributes,
+            Item::Actor(actor) => &actor.attributes,
+            Item::Import(_) | Item::Statement(_) => &[],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -96,9 +431,20 @@ pub enum PrimitiveType {
     String,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Block {
     pub statements: Vec<Statement>,
+    /// The line each statement starts on
+    #[serde(default)]
+    pub lines: Lines,
+}
+
+impl Block {
+    /// Add a statement that starts on `line`, if known
+    pub fn push(&mut self, statement: Statement, line: Option<u32>) {
+        self.lines.set(self.statements.len(), line);
+        self.statements.push(statement);
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -111,9 +457,16 @@ pub enum Statement {
     For(ForStatement),
     Match(MatchStatement),
     Return(ReturnStatement),
+    /// `try { ... } catch error { ... } finally { ... }`
+    Try(TryStatement),
+    /// `throw value`: unwinds to the nearest enclosing `catch`
+    Throw(ThrowStatement),
     Break,
     Continue,
     Block(Block),
+    /// `task_group { ... }`: waits for every task spawned inside the block
+    TaskGroup(Block),
+    Comment(Comment),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -123,10 +476,25 @@ pub struct ExpressionStatement {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VariableDeclaration {
+    /// The variable declared, or empty when `pattern` destructures the value
     pub name: String,
     pub var_type: Option<Type>,
     pub initializer: Expression,
     pub mutable: bool,
+    /// The pattern of `let (a, b) = pair`, which binds several variables
+    #[serde(default)]
+    pub pattern: Option<Pattern>,
+}
+
+impl VariableDeclaration {
+    /// The variables the declaration defines
+    #[allow(dead_code)]
+    pub fn names(&self) -> Vec<&str> {
+        match &self.pattern {
+            Some(pattern) => pattern.bindings(),
+            None => vec![self.name.as_str()],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -143,6 +511,7 @@ pub enum AssignmentOperator {
     SubAssign,        // -=
     MulAssign,        // *=
     DivAssign,        // /=
+    FloorDivAssign,   // //=
     ModAssign,        // %=
     BitwiseAndAssign, // &=
     BitwiseOrAssign,  // |=
@@ -181,6 +550,10 @@ pub struct MatchStatement {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MatchArm {
     pub pattern: Pattern,
+    /// `pattern if condition -> body`: the arm is taken only when the
+    /// condition holds, with the pattern's variables bound
+    #[serde(default)]
+    pub guard: Option<Expression>,
     pub body: Statement,
 }
 
@@ -189,6 +562,83 @@ pub enum Pattern {
     Identifier(String),
     Literal(Literal),
     Wildcard,
+    /// `(a, b)`
+    Tuple(Vec<Pattern>),
+    /// `[first, second]`, or `[first, ..rest]` to match longer arrays and
+    /// bind the elements after those listed; `..` alone is a wildcard rest
+    Array {
+        elements: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+    /// `Point { x, y: 0 }`: a struct's fields, each matched by a pattern.
+    /// A field written alone binds a variable of its name; fields left out
+    /// match anything.
+    Struct {
+        name: String,
+        fields: Vec<(String, Pattern)>,
+    },
+    /// `Some(x)`, `Ok(x)` or `Err(e)`: an option or result whose value
+    /// matches the inner pattern
+    Variant {
+        variant: Variant,
+        value: Box<Pattern>,
+    },
+}
+
+/// The constructors of options and results that hold a value; an empty
+/// option is `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variant {
+    Some,
+    Ok,
+    Err,
+}
+
+impl Variant {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Some" => Some(Variant::Some),
+            "Ok" => Some(Variant::Ok),
+            "Err" => Some(Variant::Err),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Some => "Some",
+            Variant::Ok => "Ok",
+            Variant::Err => "Err",
+        }
+    }
+}
+
+impl Pattern {
+    /// The variables the pattern binds, in order
+    #[allow(dead_code)]
+    pub fn bindings(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Pattern::Identifier(name) => names.push(name),
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+            Pattern::Tuple(elements) => elements.iter().for_each(|p| p.collect_bindings(names)),
+            Pattern::Array { elements, rest } => {
+                elements.iter().for_each(|p| p.collect_bindings(names));
+                if let Some(rest) = rest {
+                    rest.collect_bindings(names);
+                }
+            }
+            Pattern::Struct { fields, .. } => {
+                fields.iter().for_each(|(_, p)| p.collect_bindings(names))
+            }
+            Pattern::Variant { value, .. } => value.collect_bindings(names),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -196,6 +646,26 @@ pub struct ReturnStatement {
     pub value: Option<Expression>,
 }
 
+/// A `try` block with a `catch` clause, a `finally` block or both
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TryStatement {
+    pub body: Block,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatchClause {
+    /// The variable bound to the thrown value, if the clause names one
+    pub variable: Option<String>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrowStatement {
+    pub value: Expression,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
     Literal(Literal),
@@ -218,6 +688,11 @@ pub enum Expression {
     Await(AwaitExpression),
     Spawn(SpawnExpression),
     Cast(CastExpression),
+    InterpolatedString(InterpolatedStringExpression),
+    Lambda(LambdaExpression),
+    /// `value?`: the value inside `Some` or `Ok`; on `None` or `Err`, the
+    /// enclosing function returns it instead
+    Propagate(PropagateExpression),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -240,12 +715,13 @@ pub struct BinaryExpression {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     // Arithmetic
-    Add,      // +
-    Subtract, // -
-    Multiply, // *
-    Divide,   // /
-    Modulo,   // %
-    Power,    // **
+    Add,         // +
+    Subtract,    // -
+    Multiply,    // *
+    Divide,      // /
+    FloorDivide, // //
+    Modulo,      // %
+    Power,       // **
 
     // Comparison
     Equal,        // ==
@@ -255,6 +731,10 @@ pub enum BinaryOperator {
     Greater,      // >
     GreaterEqual, // >=
 
+    // Membership
+    In,    // in
+    NotIn, // not in
+
     // Logical
     And, // and
     Or,  // or
@@ -317,16 +797,22 @@ pub struct ModuleAccessExpression {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ArrayExpression {
     pub elements: Vec<Expression>,
+    #[serde(default)]
+    pub trailing_comma: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DictionaryExpression {
     pub pairs: Vec<(Expression, Expression)>,
+    #[serde(default)]
+    pub trailing_comma: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SetExpression {
     pub elements: Vec<Expression>,
+    #[serde(default)]
+    pub trailing_comma: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -338,6 +824,8 @@ pub struct TupleExpression {
 pub struct StructInitExpression {
     pub struct_name: String,
     pub fields: Vec<(String, Expression)>,
+    #[serde(default)]
+    pub trailing_comma: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -351,11 +839,15 @@ pub struct IfExpression {
 pub struct MatchExpression {
     pub expression: Box<Expression>,
     pub arms: Vec<MatchExpressionArm>,
+    #[serde(default)]
+    pub trailing_comma: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MatchExpressionArm {
     pub pattern: Pattern,
+    #[serde(default)]
+    pub guard: Option<Expression>,
     pub expression: Expression,
 }
 
@@ -371,6 +863,11 @@ pub struct AwaitExpression {
     pub expression: Box<Expression>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PropagateExpression {
+    pub expression: Box<Expression>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpawnExpression {
     pub expression: Box<Expression>,
@@ -381,3 +878,634 @@ pub struct CastExpression {
     pub expression: Box<Expression>,
     pub target_type: Type,
 }
+
+/// A string literal with embedded expressions, `"Hello, {name}!"`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterpolatedStringExpression {
+    pub parts: Vec<StringPart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StringPart {
+    Literal(String),
+    Expression(Expression),
+}
+
+/// An anonymous function, `|x| x * 2` or `fn(x) { return x * 2 }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LambdaExpression {
+    pub parameters: Vec<Parameter>,
+    /// Only the `fn` form declares one
+    pub return_type: Option<Type>,
+    pub body: LambdaBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LambdaBody {
+    /// `|x| x * 2`, which returns the expression's value
+    Expression(Box<Expression>),
+    /// `fn(x) { ... }`, which returns what its `return` gives
+    Block(Block),
+}
+
+// AST traversal
+//
+// `Visitor` walks a program by reference and `VisitorMut` by mutable
+// reference. Every method defaults to the matching `walk_*` function, which
+// visits the node's children, so an analysis only overrides the nodes it
+// cares about and calls `walk_*` to keep descending.
+//
+// The walk reaches every expression, statement, pattern and type
+// annotation, including the field types of structs and actors. Required
+// trait methods have no body and are skipped.
+
+#[allow(dead_code)]
+pub trait Visitor: Sized {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item);
+    }
+
+    /// Functions, including impl and actor methods
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function);
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        walk_type(self, ty);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_program<V: Visitor>(visitor: &mut V, program: &Program) {
+    for item in &program.items {
+        visitor.visit_item(item);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_item<V: Visitor>(visitor: &mut V, item: &Item) {
+    match item {
+        Item::Function(function) => visitor.visit_function(function),
+        Item::Impl(imp) => imp.methods.iter().for_each(|m| visitor.visit_function(m)),
+        Item::Trait(tr) => tr
+            .methods
+            .iter()
+            .filter(|m| m.has_default)
+            .for_each(|m| visitor.visit_function(&m.function)),
+        Item::Actor(actor) => {
+            for field in &actor.fields {
+                visitor.visit_type(&field.field_type);
+            }
+            actor.methods.iter().for_each(|m| visitor.visit_function(m));
+        }
+        Item::Struct(s) => s
+            .fields
+            .iter()
+            .for_each(|f| visitor.visit_type(&f.field_type)),
+        Item::Statement(statement) => visitor.visit_statement(statement),
+        Item::Import(_) => {}
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_function<V: Visitor>(visitor: &mut V, function: &Function) {
+    walk_signature(visitor, &function.parameters, function.return_type.as_ref());
+    visitor.visit_block(&function.body);
+}
+
+/// Parameter types and defaults, then the return type, of a function or
+/// lambda
+fn walk_signature<V: Visitor>(visitor: &mut V, parameters: &[Parameter], returns: Option<&Type>) {
+    for parameter in parameters {
+        if let Some(ty) = &parameter.param_type {
+            visitor.visit_type(ty);
+        }
+        if let Some(default) = &parameter.default {
+            visitor.visit_expression(default);
+        }
+    }
+    if let Some(ty) = returns {
+        visitor.visit_type(ty);
+    }
+}
+
+pub fn walk_block<V: Visitor>(visitor: &mut V, block: &Block) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Expression(stmt) => visitor.visit_expression(&stmt.expression),
+        Statement::VariableDeclaration(var) => {
+            if let Some(ty) = &var.var_type {
+                visitor.visit_type(ty);
+            }
+            visitor.visit_expression(&var.initializer);
+            if let Some(pattern) = &var.pattern {
+                visitor.visit_pattern(pattern);
+            }
+        }
+        Statement::Assignment(assign) => {
+            visitor.visit_expression(&assign.target);
+            visitor.visit_expression(&assign.value);
+        }
+        Statement::If(stmt) => {
+            visitor.visit_expression(&stmt.condition);
+            visitor.visit_block(&stmt.then_branch);
+            for (condition, block) in &stmt.elif_branches {
+                visitor.visit_expression(condition);
+                visitor.visit_block(block);
+            }
+            if let Some(block) = &stmt.else_branch {
+                visitor.visit_block(block);
+            }
+        }
+        Statement::While(stmt) => {
+            visitor.visit_expression(&stmt.condition);
+            visitor.visit_block(&stmt.body);
+        }
+        Statement::For(stmt) => {
+            visitor.visit_expression(&stmt.iterable);
+            visitor.visit_block(&stmt.body);
+        }
+        Statement::Match(stmt) => {
+            visitor.visit_expression(&stmt.expression);
+            for arm in &stmt.arms {
+                visitor.visit_pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    visitor.visit_expression(guard);
+                }
+                visitor.visit_statement(&arm.body);
+            }
+        }
+        Statement::Return(ret) => {
+            if let Some(value) = &ret.value {
+                visitor.visit_expression(value);
+            }
+        }
+        Statement::Try(stmt) => {
+            visitor.visit_block(&stmt.body);
+            if let Some(catch) = &stmt.catch {
+                visitor.visit_block(&catch.body);
+            }
+            if let Some(block) = &stmt.finally {
+                visitor.visit_block(block);
+            }
+        }
+        Statement::Throw(throw) => visitor.visit_expression(&throw.value),
+        Statement::Block(block) | Statement::TaskGroup(block) => visitor.visit_block(block),
+        Statement::Break | Statement::Continue | Statement::Comment(_) => {}
+    }
+}
+
+pub fn walk_expression<V: Visitor>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::ModuleAccess(_) => {}
+        Expression::Binary(bin) => {
+            visitor.visit_expression(&bin.left);
+            visitor.visit_expression(&bin.right);
+        }
+        Expression::Unary(unary) => visitor.visit_expression(&unary.operand),
+        Expression::Call(call) => {
+            visitor.visit_expression(&call.callee);
+            call.arguments
+                .iter()
+                .for_each(|a| visitor.visit_expression(a));
+        }
+        Expression::Index(index) => {
+            visitor.visit_expression(&index.object);
+            visitor.visit_expression(&index.index);
+        }
+        Expression::FieldAccess(field) => visitor.visit_expression(&field.object),
+        Expression::MethodCall(call) => {
+            visitor.visit_expression(&call.object);
+            call.arguments
+                .iter()
+                .for_each(|a| visitor.visit_expression(a));
+        }
+        Expression::Array(array) => array
+            .elements
+            .iter()
+            .for_each(|e| visitor.visit_expression(e)),
+        Expression::Dictionary(dict) => {
+            for (key, value) in &dict.pairs {
+                visitor.visit_expression(key);
+                visitor.visit_expression(value);
+            }
+        }
+        Expression::Set(set) => set
+            .elements
+            .iter()
+            .for_each(|e| visitor.visit_expression(e)),
+        Expression::Tuple(tuple) => tuple
+            .elements
+            .iter()
+            .for_each(|e| visitor.visit_expression(e)),
+        Expression::StructInit(init) => init
+            .fields
+            .iter()
+            .for_each(|(_, value)| visitor.visit_expression(value)),
+        Expression::If(if_expr) => {
+            visitor.visit_expression(&if_expr.condition);
+            visitor.visit_expression(&if_expr.then_expr);
+            visitor.visit_expression(&if_expr.else_expr);
+        }
+        Expression::Match(match_expr) => {
+            visitor.visit_expression(&match_expr.expression);
+            for arm in &match_expr.arms {
+                visitor.visit_pattern(&arm.pattern);
+                if let Some(guard) = &arm.guard {
+                    visitor.visit_expression(guard);
+                }
+                visitor.visit_expression(&arm.expression);
+            }
+        }
+        Expression::Range(range) => {
+            visitor.visit_expression(&range.start);
+            visitor.visit_expression(&range.end);
+        }
+        Expression::Await(inner) => visitor.visit_expression(&inner.expression),
+        Expression::Propagate(inner) => visitor.visit_expression(&inner.expression),
+        Expression::Spawn(inner) => visitor.visit_expression(&inner.expression),
+        Expression::Cast(cast) => {
+            visitor.visit_expression(&cast.expression);
+            visitor.visit_type(&cast.target_type);
+        }
+        Expression::InterpolatedString(string) => {
+            for part in &string.parts {
+                if let StringPart::Expression(e) = part {
+                    visitor.visit_expression(e);
+                }
+            }
+        }
+        Expression::Lambda(lambda) => {
+            walk_signature(visitor, &lambda.parameters, lambda.return_type.as_ref());
+            match &lambda.body {
+                LambdaBody::Expression(body) => visitor.visit_expression(body),
+                LambdaBody::Block(body) => visitor.visit_block(body),
+            }
+        }
+    }
+}
+
+/// The patterns nested in `pattern`
+pub fn walk_pattern<V: Visitor>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Identifier(_) | Pattern::Literal(_) | Pattern::Wildcard => {}
+        Pattern::Tuple(elements) => elements.iter().for_each(|p| visitor.visit_pattern(p)),
+        Pattern::Array { elements, rest } => {
+            elements.iter().for_each(|p| visitor.visit_pattern(p));
+            if let Some(rest) = rest {
+                visitor.visit_pattern(rest);
+            }
+        }
+        Pattern::Struct { fields, .. } => fields.iter().for_each(|(_, p)| visitor.visit_pattern(p)),
+        Pattern::Variant { value, .. } => visitor.visit_pattern(value),
+    }
+}
+
+/// The types nested in `ty`, such as an array's element type
+pub fn walk_type<V: Visitor>(visitor: &mut V, ty: &Type) {
+    match ty {
+        Type::Primitive(_) | Type::Custom(_) => {}
+        Type::Array { element_type, .. } => visitor.visit_type(element_type),
+        Type::Optional(inner) => visitor.visit_type(inner),
+        Type::Reference { target, .. } => visitor.visit_type(target),
+        Type::Function {
+            parameters,
+            return_type,
+        } => {
+            parameters.iter().for_each(|t| visitor.visit_type(t));
+            visitor.visit_type(return_type);
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub trait VisitorMut: Sized {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item);
+    }
+
+    /// Functions, including impl and actor methods
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        walk_function_mut(self, function);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        walk_type_mut(self, ty);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_program_mut<V: VisitorMut>(visitor: &mut V, program: &mut Program) {
+    for item in &mut program.items {
+        visitor.visit_item_mut(item);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_item_mut<V: VisitorMut>(visitor: &mut V, item: &mut Item) {
+    match item {
+        Item::Function(function) => visitor.visit_function_mut(function),
+        Item::Impl(imp) => imp
+            .methods
+            .iter_mut()
+            .for_each(|m| visitor.visit_function_mut(m)),
+        Item::Trait(tr) => tr
+            .methods
+            .iter_mut()
+            .filter(|m| m.has_default)
+            .for_each(|m| visitor.visit_function_mut(&mut m.function)),
+        Item::Actor(actor) => {
+            for field in &mut actor.fields {
+                visitor.visit_type_mut(&mut field.field_type);
+            }
+            actor
+                .methods
+                .iter_mut()
+                .for_each(|m| visitor.visit_function_mut(m));
+        }
+        Item::Struct(s) => s
+            .fields
+            .iter_mut()
+            .for_each(|f| visitor.visit_type_mut(&mut f.field_type)),
+        Item::Statement(statement) => visitor.visit_statement_mut(statement),
+        Item::Import(_) => {}
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_function_mut<V: VisitorMut>(visitor: &mut V, function: &mut Function) {
+    walk_signature_mut(
+        visitor,
+        &mut function.parameters,
+        function.return_type.as_mut(),
+    );
+    visitor.visit_block_mut(&mut function.body);
+}
+
+fn walk_signature_mut<V: VisitorMut>(
+    visitor: &mut V,
+    parameters: &mut [Parameter],
+    returns: Option<&mut Type>,
+) {
+    for parameter in parameters {
+        if let Some(ty) = &mut parameter.param_type {
+            visitor.visit_type_mut(ty);
+        }
+        if let Some(default) = &mut parameter.default {
+            visitor.visit_expression_mut(default);
+        }
+    }
+    if let Some(ty) = returns {
+        visitor.visit_type_mut(ty);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_block_mut<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+    for statement in &mut block.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_statement_mut<V: VisitorMut>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::Expression(stmt) => visitor.visit_expression_mut(&mut stmt.expression),
+        Statement::VariableDeclaration(var) => {
+            if let Some(ty) = &mut var.var_type {
+                visitor.visit_type_mut(ty);
+            }
+            visitor.visit_expression_mut(&mut var.initializer);
+            if let Some(pattern) = &mut var.pattern {
+                visitor.visit_pattern_mut(pattern);
+            }
+        }
+        Statement::Assignment(assign) => {
+            visitor.visit_expression_mut(&mut assign.target);
+            visitor.visit_expression_mut(&mut assign.value);
+        }
+        Statement::If(stmt) => {
+            visitor.visit_expression_mut(&mut stmt.condition);
+            visitor.visit_block_mut(&mut stmt.then_branch);
+            for (condition, block) in &mut stmt.elif_branches {
+                visitor.visit_expression_mut(condition);
+                visitor.visit_block_mut(block);
+            }
+            if let Some(block) = &mut stmt.else_branch {
+                visitor.visit_block_mut(block);
+            }
+        }
+        Statement::While(stmt) => {
+            visitor.visit_expression_mut(&mut stmt.condition);
+            visitor.visit_block_mut(&mut stmt.body);
+        }
+        Statement::For(stmt) => {
+            visitor.visit_expression_mut(&mut stmt.iterable);
+            visitor.visit_block_mut(&mut stmt.body);
+        }
+        Statement::Match(stmt) => {
+            visitor.visit_expression_mut(&mut stmt.expression);
+            for arm in &mut stmt.arms {
+                visitor.visit_pattern_mut(&mut arm.pattern);
+                if let Some(guard) = &mut arm.guard {
+                    visitor.visit_expression_mut(guard);
+                }
+                visitor.visit_statement_mut(&mut arm.body);
+            }
+        }
+        Statement::Return(ret) => {
+            if let Some(value) = &mut ret.value {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        Statement::Try(stmt) => {
+            visitor.visit_block_mut(&mut stmt.body);
+            if let Some(catch) = &mut stmt.catch {
+                visitor.visit_block_mut(&mut catch.body);
+            }
+            if let Some(block) = &mut stmt.finally {
+                visitor.visit_block_mut(block);
+            }
+        }
+        Statement::Throw(throw) => visitor.visit_expression_mut(&mut throw.value),
+        Statement::Block(block) | Statement::TaskGroup(block) => visitor.visit_block_mut(block),
+        Statement::Break | Statement::Continue | Statement::Comment(_) => {}
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_expression_mut<V: VisitorMut>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Literal(_) | Expression::Identifier(_) | Expression::ModuleAccess(_) => {}
+        Expression::Binary(bin) => {
+            visitor.visit_expression_mut(&mut bin.left);
+            visitor.visit_expression_mut(&mut bin.right);
+        }
+        Expression::Unary(unary) => visitor.visit_expression_mut(&mut unary.operand),
+        Expression::Call(call) => {
+            visitor.visit_expression_mut(&mut call.callee);
+            call.arguments
+                .iter_mut()
+                .for_each(|a| visitor.visit_expression_mut(a));
+        }
+        Expression::Index(index) => {
+            visitor.visit_expression_mut(&mut index.object);
+            visitor.visit_expression_mut(&mut index.index);
+        }
+        Expression::FieldAccess(field) => visitor.visit_expression_mut(&mut field.object),
+        Expression::MethodCall(call) => {
+            visitor.visit_expression_mut(&mut call.object);
+            call.arguments
+                .iter_mut()
+                .for_each(|a| visitor.visit_expression_mut(a));
+        }
+        Expression::Array(array) => array
+            .elements
+            .iter_mut()
+            .for_each(|e| visitor.visit_expression_mut(e)),
+        Expression::Dictionary(dict) => {
+            for (key, value) in &mut dict.pairs {
+                visitor.visit_expression_mut(key);
+                visitor.visit_expression_mut(value);
+            }
+        }
+        Expression::Set(set) => set
+            .elements
+            .iter_mut()
+            .for_each(|e| visitor.visit_expression_mut(e)),
+        Expression::Tuple(tuple) => tuple
+            .elements
+            .iter_mut()
+            .for_each(|e| visitor.visit_expression_mut(e)),
+        Expression::StructInit(init) => init
+            .fields
+            .iter_mut()
+            .for_each(|(_, value)| visitor.visit_expression_mut(value)),
+        Expression::If(if_expr) => {
+            visitor.visit_expression_mut(&mut if_expr.condition);
+            visitor.visit_expression_mut(&mut if_expr.then_expr);
+            visitor.visit_expression_mut(&mut if_expr.else_expr);
+        }
+        Expression::Match(match_expr) => {
+            visitor.visit_expression_mut(&mut match_expr.expression);
+            for arm in &mut match_expr.arms {
+                visitor.visit_pattern_mut(&mut arm.pattern);
+                if let Some(guard) = &mut arm.guard {
+                    visitor.visit_expression_mut(guard);
+                }
+                visitor.visit_expression_mut(&mut arm.expression);
+            }
+        }
+        Expression::Range(range) => {
+            visitor.visit_expression_mut(&mut range.start);
+            visitor.visit_expression_mut(&mut range.end);
+        }
+        Expression::Await(inner) => visitor.visit_expression_mut(&mut inner.expression),
+        Expression::Propagate(inner) => visitor.visit_expression_mut(&mut inner.expression),
+        Expression::Spawn(inner) => visitor.visit_expression_mut(&mut inner.expression),
+        Expression::Cast(cast) => {
+            visitor.visit_expression_mut(&mut cast.expression);
+            visitor.visit_type_mut(&mut cast.target_type);
+        }
+        Expression::InterpolatedString(string) => {
+            for part in &mut string.parts {
+                if let StringPart::Expression(e) = part {
+                    visitor.visit_expression_mut(e);
+                }
+            }
+        }
+        Expression::Lambda(lambda) => {
+            walk_signature_mut(visitor, &mut lambda.parameters, lambda.return_type.as_mut());
+            match &mut lambda.body {
+                LambdaBody::Expression(body) => visitor.visit_expression_mut(body),
+                LambdaBody::Block(body) => visitor.visit_block_mut(body),
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_pattern_mut<V: VisitorMut>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Identifier(_) | Pattern::Literal(_) | Pattern::Wildcard => {}
+        Pattern::Tuple(elements) => elements
+            .iter_mut()
+            .for_each(|p| visitor.visit_pattern_mut(p)),
+        Pattern::Array { elements, rest } => {
+            elements
+                .iter_mut()
+                .for_each(|p| visitor.visit_pattern_mut(p));
+            if let Some(rest) = rest {
+                visitor.visit_pattern_mut(rest);
+            }
+        }
+        Pattern::Struct { fields, .. } => fields
+            .iter_mut()
+            .for_each(|(_, p)| visitor.visit_pattern_mut(p)),
+        Pattern::Variant { value, .. } => visitor.visit_pattern_mut(value),
+    }
+}
+
+#[allow(dead_code)]
+pub fn walk_type_mut<V: VisitorMut>(visitor: &mut V, ty: &mut Type) {
+    match ty {
+        Type::Primitive(_) | Type::Custom(_) => {}
+        Type::Array { element_type, .. } => visitor.visit_type_mut(element_type),
+        Type::Optional(inner) => visitor.visit_type_mut(inner),
+        Type::Reference { target, .. } => visitor.visit_type_mut(target),
+        Type::Function {
+            parameters,
+            return_type,
+        } => {
+            parameters
+                .iter_mut()
+                .for_each(|t| visitor.visit_type_mut(t));
+            visitor.visit_type_mut(return_type);
+        }
+    }
+}
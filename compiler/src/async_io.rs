@@ -0,0 +1,151 @@
+// Background I/O for the async builtins
+//
+// `read_file_async` and `http_get_async` hand their work to a small pool of
+// I/O threads and return a task at once, so a program can start many reads
+// or requests and await them later while they run side by side.
+// `sleep_async` needs no thread: its task is a deadline. Values are not
+// Send, so the work produces a string, which the interpreter turns into a
+// value when the task is awaited.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Threads shared by all background I/O
+const IO_THREADS: usize = 8;
+
+/// What background work produced: text, nothing, or an error message
+pub type Outcome = Result<Option<String>, String>;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Work a task is waiting on
+pub enum Pending {
+    Io(Receiver<Outcome>),
+    Timer(Instant),
+}
+
+impl Pending {
+    /// Run `work` on an I/O thread
+    pub fn start(work: impl FnOnce() -> Outcome + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            // The task may have been dropped without being awaited
+            let _ = sender.send(work());
+        });
+        if let Err(mpsc::SendError(job)) = pool().send(job) {
+            job();
+        }
+        Pending::Io(receiver)
+    }
+
+    /// A task that finishes after `duration`
+    pub fn timer(duration: Duration) -> Self {
+        Pending::Timer(Instant::now() + duration)
+    }
+
+    /// The outcome, if the work finishes within `timeout`
+    pub fn wait(&self, timeout: Duration) -> Option<Outcome> {
+        match self {
+            Pending::Io(receiver) => match receiver.recv_timeout(timeout) {
+                Ok(outcome) => Some(outcome),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    Some(Err("the I/O thread stopped".to_string()))
+                }
+            },
+            Pending::Timer(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                std::thread::sleep(left.min(timeout));
+                (Instant::now() >= *deadline).then_some(Ok(None))
+            }
+        }
+    }
+}
+
+/// The queue of the I/O threads, started on first use
+fn pool() -> &'static Sender<Job> {
+    static POOL: OnceLock<Sender<Job>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..IO_THREADS {
+            let receiver = receiver.clone();
+            let _ = std::thread::Builder::new()
+                .name(format!("veyra-io-{}", i))
+                .spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                });
+        }
+        sender
+    })
+}
+
+pub fn read_file(path: &str) -> Outcome {
+    std::fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("Cannot read '{}': {}", path, e))
+}
+
+/// The parts of an `http://host[:port]/path` URL: host, port and path
+pub fn parse_http_url(url: &str) -> Result<(&str, u16, &str), String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        format!(
+            "'{}' is not an http:// URL; https is not supported yet",
+            url
+        )
+    })?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .map_err(|_| format!("Invalid port in '{}'", url))?;
+            (host, port)
+        }
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("No host in '{}'", url));
+    }
+    Ok((host, port, path))
+}
+
+/// The body of the response to a GET request; statuses other than 2xx are
+/// errors. HTTP/1.0 keeps the body in one piece, with no chunked encoding.
+pub fn http_get(url: &str) -> Outcome {
+    let (host, port, path) = parse_http_url(url)?;
+    let failed = |e: std::io::Error| format!("Request to '{}' failed: {}", url, e);
+
+    let mut stream = TcpStream::connect((host, port)).map_err(failed)?;
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: veyra\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes()).map_err(failed)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(failed)?;
+    let response = String::from_utf8_lossy(&response);
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| format!("Malformed response from '{}'", url))?;
+    let status = head.lines().next().unwrap_or_default();
+    let code = status.split_whitespace().nth(1).unwrap_or_default();
+    if !code.starts_with('2') {
+        return Err(format!("Request to '{}' failed: {}", url, status));
+    }
+    Ok(Some(body.to_string()))
+}
@@ -0,0 +1,655 @@
+// Documentation registry for the interpreter's builtin functions
+// Used by `help()` at runtime and by tools such as the REPL's `:doc`.
+
+use crate::ast::{Expression, ExpressionStatement, Function, Literal, Statement};
+
+/// Parameter name and description
+pub type ParamDoc = (&'static str, &'static str);
+
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinDoc {
+    pub name: &'static str,
+    pub signature: &'static str,
+    pub summary: &'static str,
+    pub params: &'static [ParamDoc],
+    pub example: &'static str,
+}
+
+pub const BUILTINS: &[BuiltinDoc] = &[
+    BuiltinDoc {
+        name: "print",
+        signature: "print(value, args)",
+        summary: "Print a value followed by a newline. Given more arguments, a first value with `{}` placeholders is a format string filled in as by `format`; otherwise the values are printed separated by spaces.",
+        params: &[
+            ("value", "Value to print"),
+            ("args", "Optional values for the placeholders of a format string, or to print after the value"),
+        ],
+        example: "print(\"{} of {}\", done, total)",
+    },
+    BuiltinDoc {
+        name: "format",
+        signature: "format(template, args) -> string",
+        summary: "Fill in the placeholders of a format string: `{}` takes the next argument, `{1}` the one at that index, and a specifier such as `{:>8.2}` sets the fill, alignment, width and decimals.",
+        params: &[
+            ("template", "Text with `{}` placeholders; `{{` and `}}` are literal braces"),
+            ("args", "Values for the placeholders, each of which must be used"),
+        ],
+        example: "format(\"{:<6}|{:>7.2}\", \"total\", 3.14159)  # \"total |   3.14\"",
+    },
+    BuiltinDoc {
+        name: "str",
+        signature: "str(value) -> string",
+        summary: "Convert a value to its string representation.",
+        params: &[("value", "Value to convert")],
+        example: "str(42)  # \"42\"",
+    },
+    BuiltinDoc {
+        name: "repr",
+        signature: "repr(value) -> string",
+        summary: "Unambiguous representation of a value, with strings quoted and escaped.",
+        params: &[("value", "Value to represent")],
+        example: "print(repr(\"a\\tb\"))  # \"a\\tb\"",
+    },
+    BuiltinDoc {
+        name: "copy",
+        signature: "copy(value) -> value",
+        summary: "Shallow copy: a reference gets a new target with the same value; references inside collections and structs stay shared.",
+        params: &[("value", "Value to copy")],
+        example: "let r2 = copy(r)  # r2 != r",
+    },
+    BuiltinDoc {
+        name: "deep_copy",
+        signature: "deep_copy(value) -> value",
+        summary: "Copy that shares no references with the original; references that alias each other still do in the copy.",
+        params: &[("value", "Value to copy")],
+        example: "let rows = deep_copy([&mut a, &mut b])",
+    },
+    BuiltinDoc {
+        name: "weak_ref",
+        signature: "weak_ref(reference) -> weak",
+        summary: "Weak reference to the target of a reference, which does not keep it alive; use it for back links that would otherwise form a cycle.",
+        params: &[("reference", "Reference made with & or &mut")],
+        example: "let parent = weak_ref(node)",
+    },
+    BuiltinDoc {
+        name: "upgrade",
+        signature: "upgrade(weak) -> reference | None",
+        summary: "The reference a weak reference was made from, or None if its target has been dropped.",
+        params: &[("weak", "Weak reference from weak_ref")],
+        example: "let node = upgrade(parent)",
+    },
+    BuiltinDoc {
+        name: "memory_cycles",
+        signature: "memory_cycles() -> array",
+        summary: "Groups of reference targets that keep each other alive but can no longer be reached, each a list of short descriptions.",
+        params: &[],
+        example: "assert(len(memory_cycles()) == 0)",
+    },
+    BuiltinDoc {
+        name: "len",
+        signature: "len(collection) -> int",
+        summary: "Number of elements in an array, dictionary, set or tuple, or bytes in a string.",
+        params: &[("collection", "Array, string, dictionary, set or tuple")],
+        example: "len([1, 2, 3])  # 3",
+    },
+    BuiltinDoc {
+        name: "push",
+        signature: "push(array, value) -> array",
+        summary: "Return a copy of the array with the value appended.",
+        params: &[("array", "Array to extend"), ("value", "Value to append")],
+        example: "let xs = push([1, 2], 3)  # [1, 2, 3]",
+    },
+    BuiltinDoc {
+        name: "pop",
+        signature: "pop(array) -> value",
+        summary: "Return the last element of an array, or None if it is empty.",
+        params: &[("array", "Array to read from")],
+        example: "pop([1, 2, 3])  # 3",
+    },
+    BuiltinDoc {
+        name: "type_of",
+        signature: "type_of(value) -> string",
+        summary: "Name of the value's runtime type.",
+        params: &[("value", "Value to inspect")],
+        example: "type_of(3.14)  # \"float\"",
+    },
+    BuiltinDoc {
+        name: "implements",
+        signature: "implements(value, trait_name) -> bool",
+        summary: "Whether the value's type has an `impl` of the named trait.",
+        params: &[
+            ("value", "Value to inspect"),
+            ("trait_name", "Name of a defined trait"),
+        ],
+        example: "implements(Point { x: 1, y: 2 }, \"Show\")  # true",
+    },
+    BuiltinDoc {
+        name: "int",
+        signature: "int(value) -> int",
+        summary: "Convert a value to an integer.",
+        params: &[("value", "Number, string, boolean or char")],
+        example: "int(\"42\")  # 42",
+    },
+    BuiltinDoc {
+        name: "float",
+        signature: "float(value) -> float",
+        summary: "Convert a value to a float.",
+        params: &[("value", "Number or string")],
+        example: "float(1)  # 1.0",
+    },
+    BuiltinDoc {
+        name: "bool",
+        signature: "bool(value) -> bool",
+        summary: "Convert a value to a boolean using its truthiness.",
+        params: &[("value", "Value to convert")],
+        example: "bool(0)  # false",
+    },
+    BuiltinDoc {
+        name: "char",
+        signature: "char(value) -> char",
+        summary: "Convert an integer code point or one-character string to a char.",
+        params: &[("value", "Integer or string")],
+        example: "char(65)  # 'A'",
+    },
+    BuiltinDoc {
+        name: "is_int",
+        signature: "is_int(value) -> bool",
+        summary: "Whether the value is an integer.",
+        params: &[("value", "Value to test")],
+        example: "is_int(1)  # true",
+    },
+    BuiltinDoc {
+        name: "is_float",
+        signature: "is_float(value) -> bool",
+        summary: "Whether the value is a float.",
+        params: &[("value", "Value to test")],
+        example: "is_float(1.5)  # true",
+    },
+    BuiltinDoc {
+        name: "is_string",
+        signature: "is_string(value) -> bool",
+        summary: "Whether the value is a string.",
+        params: &[("value", "Value to test")],
+        example: "is_string(\"hi\")  # true",
+    },
+    BuiltinDoc {
+        name: "is_bool",
+        signature: "is_bool(value) -> bool",
+        summary: "Whether the value is a boolean.",
+        params: &[("value", "Value to test")],
+        example: "is_bool(false)  # true",
+    },
+    BuiltinDoc {
+        name: "is_char",
+        signature: "is_char(value) -> bool",
+        summary: "Whether the value is a char.",
+        params: &[("value", "Value to test")],
+        example: "is_char('a')  # true",
+    },
+    BuiltinDoc {
+        name: "is_array",
+        signature: "is_array(value) -> bool",
+        summary: "Whether the value is an array.",
+        params: &[("value", "Value to test")],
+        example: "is_array([1])  # true",
+    },
+    BuiltinDoc {
+        name: "is_dict",
+        signature: "is_dict(value) -> bool",
+        summary: "Whether the value is a dictionary.",
+        params: &[("value", "Value to test")],
+        example: "is_dict({\"a\": 1})  # true",
+    },
+    BuiltinDoc {
+        name: "is_none",
+        signature: "is_none(value) -> bool",
+        summary: "Whether the value is None.",
+        params: &[("value", "Value to test")],
+        example: "is_none(None)  # true",
+    },
+    BuiltinDoc {
+        name: "Some",
+        signature: "Some(value) -> option",
+        summary: "Option holding a value; the empty option is None.",
+        params: &[("value", "Value to hold")],
+        example: "let found = Some(3)",
+    },
+    BuiltinDoc {
+        name: "Ok",
+        signature: "Ok(value) -> result",
+        summary: "Result of an operation that succeeded.",
+        params: &[("value", "Value it produced")],
+        example: "return Ok(n)",
+    },
+    BuiltinDoc {
+        name: "Err",
+        signature: "Err(error) -> result",
+        summary: "Result of an operation that failed.",
+        params: &[("error", "What went wrong, usually a string")],
+        example: "return Err(\"not a number\")",
+    },
+    BuiltinDoc {
+        name: "is_some",
+        signature: "is_some(value) -> bool",
+        summary: "Whether the value is Some(...).",
+        params: &[("value", "Value to test")],
+        example: "is_some(Some(1))  # true",
+    },
+    BuiltinDoc {
+        name: "is_ok",
+        signature: "is_ok(value) -> bool",
+        summary: "Whether the value is Ok(...).",
+        params: &[("value", "Value to test")],
+        example: "is_ok(Err(\"no\"))  # false",
+    },
+    BuiltinDoc {
+        name: "is_err",
+        signature: "is_err(value) -> bool",
+        summary: "Whether the value is Err(...).",
+        params: &[("value", "Value to test")],
+        example: "is_err(Err(\"no\"))  # true",
+    },
+    BuiltinDoc {
+        name: "unwrap",
+        signature: "unwrap(value) -> value",
+        summary: "The value inside Some or Ok; a runtime error for None or Err.",
+        params: &[("value", "Option or result")],
+        example: "unwrap(Ok(2))  # 2",
+    },
+    BuiltinDoc {
+        name: "unwrap_or",
+        signature: "unwrap_or(value, default) -> value",
+        summary: "The value inside Some or Ok, or the default for None or Err.",
+        params: &[
+            ("value", "Option or result"),
+            ("default", "Value to use when there is none"),
+        ],
+        example: "unwrap_or(None, 0)  # 0",
+    },
+    BuiltinDoc {
+        name: "sqrt",
+        signature: "sqrt(x) -> float",
+        summary: "Square root of a number.",
+        params: &[("x", "Non-negative number")],
+        example: "sqrt(16)  # 4.0",
+    },
+    BuiltinDoc {
+        name: "pow",
+        signature: "pow(base, exponent) -> number",
+        summary: "Raise a number to a power.",
+        params: &[
+            ("base", "Number to raise"),
+            ("exponent", "Power to raise it to"),
+        ],
+        example: "pow(2, 10)  # 1024",
+    },
+    BuiltinDoc {
+        name: "abs",
+        signature: "abs(x) -> number",
+        summary: "Absolute value of a number.",
+        params: &[("x", "Integer or float")],
+        example: "abs(-5)  # 5",
+    },
+    BuiltinDoc {
+        name: "min",
+        signature: "min(a, b) -> number",
+        summary: "The smaller of two numbers.",
+        params: &[("a", "First number"), ("b", "Second number")],
+        example: "min(3, 7)  # 3",
+    },
+    BuiltinDoc {
+        name: "max",
+        signature: "max(a, b) -> number",
+        summary: "The larger of two numbers.",
+        params: &[("a", "First number"), ("b", "Second number")],
+        example: "max(3, 7)  # 7",
+    },
+    BuiltinDoc {
+        name: "floor",
+        signature: "floor(x) -> number",
+        summary: "Round a number down to the nearest integer.",
+        params: &[("x", "Integer or float")],
+        example: "floor(2.7)  # 2.0",
+    },
+    BuiltinDoc {
+        name: "ceil",
+        signature: "ceil(x) -> number",
+        summary: "Round a number up to the nearest integer.",
+        params: &[("x", "Integer or float")],
+        example: "ceil(2.1)  # 3.0",
+    },
+    BuiltinDoc {
+        name: "round",
+        signature: "round(x) -> number",
+        summary: "Round a number to the nearest integer.",
+        params: &[("x", "Integer or float")],
+        example: "round(2.5)  # 3.0",
+    },
+    BuiltinDoc {
+        name: "string_to_upper",
+        signature: "string_to_upper(s) -> string",
+        summary: "Convert a string to uppercase.",
+        params: &[("s", "String to convert")],
+        example: "string_to_upper(\"veyra\")  # \"VEYRA\"",
+    },
+    BuiltinDoc {
+        name: "string_to_lower",
+        signature: "string_to_lower(s) -> string",
+        summary: "Convert a string to lowercase.",
+        params: &[("s", "String to convert")],
+        example: "string_to_lower(\"VEYRA\")  # \"veyra\"",
+    },
+    BuiltinDoc {
+        name: "string_trim",
+        signature: "string_trim(s) -> string",
+        summary: "Remove leading and trailing whitespace from a string.",
+        params: &[("s", "String to trim")],
+        example: "string_trim(\"  hi  \")  # \"hi\"",
+    },
+    BuiltinDoc {
+        name: "clamp",
+        signature: "clamp(value, min, max) -> number",
+        summary: "Limit a number to the range [min, max].",
+        params: &[
+            ("value", "Number to clamp"),
+            ("min", "Lower bound"),
+            ("max", "Upper bound"),
+        ],
+        example: "clamp(15, 0, 10)  # 10",
+    },
+    BuiltinDoc {
+        name: "array_sum",
+        signature: "array_sum(array) -> number",
+        summary: "Sum of the numbers in an array.",
+        params: &[("array", "Array of numbers")],
+        example: "array_sum([1, 2, 3])  # 6",
+    },
+    BuiltinDoc {
+        name: "array_avg",
+        signature: "array_avg(array) -> float",
+        summary: "Average of the numbers in an array.",
+        params: &[("array", "Non-empty array of numbers")],
+        example: "array_avg([1, 2, 3])  # 2.0",
+    },
+    BuiltinDoc {
+        name: "map",
+        signature: "map(array, function) -> array",
+        summary: "Array of the results of calling a function on each element.",
+        params: &[
+            ("array", "Array to transform"),
+            ("function", "Function or lambda taking one element"),
+        ],
+        example: "map([1, 2, 3], |x| x * 2)  # [2, 4, 6]",
+    },
+    BuiltinDoc {
+        name: "filter",
+        signature: "filter(array, function) -> array",
+        summary: "Array of the elements for which a function returns a truthy value.",
+        params: &[
+            ("array", "Array to filter"),
+            ("function", "Function or lambda taking one element"),
+        ],
+        example: "filter([1, 2, 3, 4], |x| x % 2 == 0)  # [2, 4]",
+    },
+    BuiltinDoc {
+        name: "reduce",
+        signature: "reduce(array, function, initial) -> value",
+        summary: "Combine the elements into one value, calling a function with the result so far and each element in turn.",
+        params: &[
+            ("array", "Array to combine"),
+            ("function", "Function or lambda taking the result so far and an element"),
+            ("initial", "Result before the first element"),
+        ],
+        example: "reduce([1, 2, 3], |sum, x| sum + x, 0)  # 6",
+    },
+    BuiltinDoc {
+        name: "now",
+        signature: "now() -> int",
+        summary: "Current Unix timestamp in seconds.",
+        params: &[],
+        example: "let start = now()",
+    },
+    BuiltinDoc {
+        name: "read_file",
+        signature: "read_file(path) -> string",
+        summary: "Contents of a text file.",
+        params: &[("path", "File to read")],
+        example: "let config = read_file(\"app.conf\")",
+    },
+    BuiltinDoc {
+        name: "read_file_async",
+        signature: "read_file_async(path) -> task",
+        summary: "Start reading a text file in the background; awaiting the task gives its contents.",
+        params: &[("path", "File to read")],
+        example: "let config = await read_file_async(\"app.conf\")",
+    },
+    BuiltinDoc {
+        name: "write_file",
+        signature: "write_file(path, content)",
+        summary: "Write a value's text to a file, replacing what it held.",
+        params: &[("path", "File to write"), ("content", "Value to write")],
+        example: "write_file(\"out.txt\", report)",
+    },
+    BuiltinDoc {
+        name: "env_var",
+        signature: "env_var(name) -> string",
+        summary: "Value of an environment variable, or None when it is not set.",
+        params: &[("name", "Variable name")],
+        example: "let home = env_var(\"HOME\")",
+    },
+    BuiltinDoc {
+        name: "args",
+        signature: "args() -> array",
+        summary: "Arguments given to the program after its file name, as strings.",
+        params: &[],
+        example: "let [input, ..rest] = args()",
+    },
+    BuiltinDoc {
+        name: "exit",
+        signature: "exit(code)",
+        summary: "End the program with an exit code, 0 if none is given. No catch stops it, and tasks and actors left running are not waited for.",
+        params: &[("code", "Optional exit code")],
+        example: "exit(2)",
+    },
+    BuiltinDoc {
+        name: "exec",
+        signature: "exec(program, args) -> string",
+        summary: "Run a program and return its output, raising an error if it fails.",
+        params: &[
+            ("program", "Program name or path"),
+            ("args", "Optional array of arguments"),
+        ],
+        example: "exec(\"git\", [\"rev-parse\", \"HEAD\"])",
+    },
+    BuiltinDoc {
+        name: "tcp_request",
+        signature: "tcp_request(address, data) -> string",
+        summary: "Send data over a TCP connection and return everything the peer sends back.",
+        params: &[
+            ("address", "\"host:port\" to connect to"),
+            ("data", "Value to send"),
+        ],
+        example: "tcp_request(\"localhost:7000\", \"PING\")",
+    },
+    BuiltinDoc {
+        name: "http_get_async",
+        signature: "http_get_async(url) -> task",
+        summary: "Start an HTTP GET request in the background; awaiting the task gives the response body.",
+        params: &[("url", "http:// URL to fetch")],
+        example: "let page = await http_get_async(\"http://localhost:8080/status\")",
+    },
+    BuiltinDoc {
+        name: "sleep_async",
+        signature: "sleep_async(ms) -> task",
+        summary: "A task that finishes after a number of milliseconds, without blocking until awaited.",
+        params: &[("ms", "Milliseconds to wait")],
+        example: "let timer = sleep_async(500)",
+    },
+    BuiltinDoc {
+        name: "task_id",
+        signature: "task_id() -> int",
+        summary: "Id of the running task, the number in its handle; none in the main program.",
+        params: &[],
+        example: "print(\"task \" + str(task_id()))",
+    },
+    BuiltinDoc {
+        name: "task_name",
+        signature: "task_name() | task_name(task) -> string",
+        summary: "Name of the running task, or of a task handle. Tasks are named task-<id> until renamed; the main program is main.",
+        params: &[("task", "Task to name (default: the running one)")],
+        example: "print(\"[\" + task_name() + \"] started\")",
+    },
+    BuiltinDoc {
+        name: "set_task_name",
+        signature: "set_task_name(name)",
+        summary: "Rename the running task, for logs and task_info.",
+        params: &[("name", "New name")],
+        example: "set_task_name(\"worker-\" + str(n))",
+    },
+    BuiltinDoc {
+        name: "task_info",
+        signature: "task_info() | task_info(task) -> dictionary",
+        summary: "The id, name, parent id and status (running, pending, done, failed or awaited) of a task.",
+        params: &[("task", "Task to describe (default: the running one)")],
+        example: "print(task_info(handle)[\"status\"])",
+    },
+    BuiltinDoc {
+        name: "task_local_set",
+        signature: "task_local_set(key, value)",
+        summary: "Set a variable of the running task. Tasks spawned afterwards start with a copy of its variables.",
+        params: &[("key", "Hashable key"), ("value", "Value to store")],
+        example: "task_local_set(\"request_id\", id)",
+    },
+    BuiltinDoc {
+        name: "task_local_get",
+        signature: "task_local_get(key, default?) -> any",
+        summary: "A variable of the running task set by task_local_set, or default (none) when unset.",
+        params: &[
+            ("key", "Key to look up"),
+            ("default", "Value when the key is unset (default: none)"),
+        ],
+        example: "let id = task_local_get(\"request_id\", \"-\")",
+    },
+    BuiltinDoc {
+        name: "range",
+        signature: "range(end) | range(start, end) | range(start, end, step) -> array",
+        summary: "Array of integers from start (default 0) up to but excluding end.",
+        params: &[
+            ("start", "First value (default 0)"),
+            ("end", "Stop before this value"),
+            ("step", "Increment, may be negative (default 1)"),
+        ],
+        example: "range(0, 10, 2)  # [0, 2, 4, 6, 8]",
+    },
+    BuiltinDoc {
+        name: "assert",
+        signature: "assert(condition, message)",
+        summary: "Raise an error unless the condition is truthy.",
+        params: &[
+            ("condition", "Value that should be truthy"),
+            ("message", "Optional text for the error"),
+        ],
+        example: "assert(len(xs) > 0, \"xs is empty\")",
+    },
+    BuiltinDoc {
+        name: "assert_eq",
+        signature: "assert_eq(left, right)",
+        summary: "Raise an error showing both values unless they are equal.",
+        params: &[("left", "Actual value"), ("right", "Expected value")],
+        example: "assert_eq(1 + 1, 2)",
+    },
+    BuiltinDoc {
+        name: "trace",
+        signature: "trace(value, label)",
+        summary: "Record a value with the expression that produced it and the time, and return it unchanged. The last 1000 records are kept for `trace_dump` and the debugger.",
+        params: &[
+            ("value", "Expression to record"),
+            ("label", "Optional name to record instead of the expression"),
+        ],
+        example: "let total = trace(price * count)",
+    },
+    BuiltinDoc {
+        name: "trace_dump",
+        signature: "trace_dump()",
+        summary: "Print the values recorded by `trace`, oldest first.",
+        params: &[],
+        example: "trace_dump()",
+    },
+    BuiltinDoc {
+        name: "send",
+        signature: "send(actor, message)",
+        summary: "Queue a message for an actor without waiting for it to be handled.",
+        params: &[
+            ("actor", "Handle from `spawn`"),
+            ("message", "Value to send"),
+        ],
+        example: "send(counter, 1)",
+    },
+    BuiltinDoc {
+        name: "ask",
+        signature: "ask(actor, message, timeout_ms) -> value",
+        summary:
+            "Send a message and wait for the actor's reply, or raise an error after the timeout.",
+        params: &[
+            ("actor", "Handle from `spawn`"),
+            ("message", "Value to send"),
+            ("timeout_ms", "Milliseconds to wait for the reply"),
+        ],
+        example: "ask(counter, \"get\", 100)",
+    },
+    BuiltinDoc {
+        name: "help",
+        signature: "help(name)",
+        summary: "Print documentation for a builtin or a function with a docstring.",
+        params: &[("name", "Function name, bare or as a string")],
+        example: "help(print)",
+    },
+];
+
+/// Look up the documentation for a builtin function
+pub fn lookup(name: &str) -> Option<&'static BuiltinDoc> {
+    BUILTINS.iter().find(|doc| doc.name == name)
+}
+
+/// A function's docstring: a string literal as the first statement of its body
+pub fn docstring(function: &Function) -> Option<&str> {
+    match function.body.statements.first() {
+        Some(Statement::Expression(ExpressionStatement {
+            expression: Expression::Literal(Literal::String(doc)),
+        })) => Some(doc),
+        _ => None,
+    }
+}
+
+/// Signature line for a user-defined function, e.g. `fn area(w, h)`
+pub fn signature(function: &Function) -> String {
+    let params: Vec<&str> = function
+        .parameters
+        .iter()
+        .map(|param| param.name.as_str())
+        .collect();
+    format!("fn {}({})", function.name, params.join(", "))
+}
+
+/// Plain-text documentation for a builtin
+pub fn format_builtin(doc: &BuiltinDoc) -> String {
+    let mut out = format!("{}\n    {}\n", doc.signature, doc.summary);
+    if !doc.params.is_empty() {
+        out.push_str("\nParameters:\n");
+        for (name, description) in doc.params {
+            out.push_str(&format!("    {:<12} {}\n", name, description));
+        }
+    }
+    out.push_str(&format!("\nExample:\n    {}", doc.example));
+    out
+}
+
+/// Plain-text documentation for a user-defined function
+pub fn format_function(function: &Function) -> String {
+    let doc = docstring(function).unwrap_or("No documentation available.");
+    let body: Vec<String> = doc
+        .lines()
+        .map(|line| format!("    {}", line.trim()))
+        .collect();
+    format!("{}\n{}", signature(function), body.join("\n"))
+}
@@ -0,0 +1,1512 @@
+// Bytecode
+//
+// `compile` lowers a program to a `Module` of instructions for a stack
+// machine. Every function, method and lambda becomes a chunk of code, and
+// the program's imports and top-level statements become the entry chunk,
+// which runs once the declarations are registered. `veyc compile` writes the
+// module to a `.veyb` file.
+//
+// Variables follow the interpreter's scopes. Function bodies, blocks,
+// `try`, `catch` and `finally` blocks and match arms open a scope; `if`
+// branches and loop bodies declare into the scope around them. A variable
+// declared in a function, or in a nested scope of the top level, lives in a
+// numbered slot of its frame. Variables declared at the top level are
+// globals, and names no enclosing scope declares, such as functions and
+// builtins, are looked up by name when the code runs.
+//
+// Lambdas and spawned expressions copy the variables of the enclosing code
+// that they use when they are created, into slots of their own frame.
+// `return`, `break` and `continue` leave `try` blocks by popping their
+// handlers and running their `finally` blocks inline; an error runs the
+// `finally` block from its handler and is raised again after it.
+
+use crate::ast::{
+    self, Actor, AssignmentOperator, BinaryOperator, Block, Expression, Function, Import, Item,
+    LambdaBody, Literal, MailboxConfig, Overflow, Program, Statement, StringPart, Type,
+    UnaryOperator, Variant,
+};
+use crate::error::{Result, Unwind, VeyraError};
+use crate::printer;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Extension of compiled modules
+pub const EXTENSION: &str = "veyb";
+
+/// The first bytes of a compiled module
+pub const MAGIC: [u8; 4] = *b"VEYB";
+
+/// Version of the format, bumped whenever a change to the instructions or
+/// the module layout makes older files unreadable
+pub const FORMAT_VERSION: u16 = 4;
+
+/// A compiled program
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Module {
+    /// Literal values the code pushes, by index
+    pub constants: Vec<Literal>,
+    /// Names of globals, functions, fields and methods the code uses
+    pub names: Vec<String>,
+    pub chunks: Vec<Chunk>,
+    /// The chunk of the top-level statements
+    pub entry: u32,
+    /// Chunks of the top-level functions
+    pub functions: Vec<u32>,
+    pub structs: Vec<StructDef>,
+    pub traits: Vec<TraitDef>,
+    pub impls: Vec<ImplDef>,
+    pub actors: Vec<ActorDef>,
+    pub imports: Vec<Import>,
+    /// Targets of `as` casts
+    pub types: Vec<Type>,
+    /// Field lists of struct literals
+    pub shapes: Vec<Shape>,
+    pub patterns: Vec<Pattern>,
+    /// The file the module was compiled from, which the line tables of its
+    /// chunks refer to
+    pub source: Option<String>,
+}
+
+/// The code of one function, method, lambda or spawned expression
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub name: String,
+    pub kind: ChunkKind,
+    /// Parameter names, bound to the first slots; a method's receiver is
+    /// bound to slot 0 before them, whether or not it declares `self`
+    pub parameters: Vec<String>,
+    /// Variables of the enclosing frame, or globals, that a lambda or
+    /// spawned expression copies when it is created, and the slots of its
+    /// own they go to
+    pub captures: Vec<Capture>,
+    /// Slots the frame needs
+    pub slots: u32,
+    pub code: Vec<Instruction>,
+    /// The line table: the source line of each run of instructions, as the
+    /// offset of its first instruction and the line, in order. Code compiled
+    /// from a tree without lines has none.
+    pub lines: Vec<(u32, u32)>,
+}
+
+impl Chunk {
+    /// The source line of the instruction at `offset`, if known
+    pub fn line(&self, offset: usize) -> Option<u32> {
+        let runs = self
+            .lines
+            .partition_point(|&(start, _)| start as usize <= offset);
+        runs.checked_sub(1).map(|run| self.lines[run].1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChunkKind {
+    /// The top-level statements
+    Entry,
+    Function,
+    /// A method of an `impl` block or a trait's default method
+    Method,
+    /// A method of an actor, whose fields take the slots after its
+    /// parameters, in declaration order
+    ActorMethod,
+    Lambda,
+    /// The expression of a `spawn`
+    Task,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capture {
+    pub from: CaptureFrom,
+    pub to: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaptureFrom {
+    /// A slot of the enclosing frame
+    Slot(u32),
+    /// A top-level variable, by its name
+    Global(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructDef {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraitDef {
+    pub name: String,
+    pub methods: Vec<TraitMethodDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraitMethodDef {
+    pub name: String,
+    /// Parameters besides `self`
+    pub arity: u32,
+    /// The chunk of the default body, if the trait has one
+    pub default: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImplDef {
+    pub target: String,
+    pub trait_name: Option<String>,
+    pub methods: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActorDef {
+    pub name: String,
+    /// Fields, which start as the zero value of their type
+    pub fields: Vec<(String, Type)>,
+    pub methods: Vec<u32>,
+    pub mailbox_capacity: Option<usize>,
+    pub overflow: Overflow,
+}
+
+/// The struct a literal builds and the fields it gives, in the order their
+/// values are pushed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// A pattern of `match` or a destructuring `let`, with its variables
+/// resolved to slots or globals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    Wildcard,
+    Bind(u32),
+    BindGlobal(u32),
+    /// Equal to the constant
+    Constant(u32),
+    Tuple(Vec<Pattern>),
+    Array {
+        elements: Vec<Pattern>,
+        rest: Option<Box<Pattern>>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<(String, Pattern)>,
+    },
+    Variant {
+        variant: Variant,
+        value: Box<Pattern>,
+    },
+}
+
+/// One step of a chunk. Operands index the module's tables or, for jumps,
+/// the chunk's code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Instruction {
+    Constant(u32),
+    Pop,
+    LoadLocal(u32),
+    /// Pop into a slot
+    StoreLocal(u32),
+    /// Push the global called `name`, or the function or builtin of that
+    /// name as a value
+    LoadName(u32),
+    /// Pop into a new global
+    DefineGlobal(u32),
+    /// Pop into an existing global
+    StoreGlobal(u32),
+    Binary(BinaryOperator),
+    Unary(UnaryOperator),
+    Jump(u32),
+    /// Pop a condition and jump if it is not truthy
+    JumpIfFalse(u32),
+    /// Call the function or builtin `name`, or a function stored in a
+    /// global of that name, with the arguments on top of the stack
+    Call(u32, u32),
+    /// Call the function value below the arguments
+    CallValue(u32),
+    /// Call a method of the receiver below the arguments
+    CallMethod(u32, u32),
+    /// Call `module::item`
+    CallModule {
+        module: u32,
+        item: u32,
+        arguments: u32,
+    },
+    /// Push `module::item`
+    ModuleItem {
+        module: u32,
+        item: u32,
+    },
+    /// Pop an index and an object and push the element
+    Index,
+    /// Replace a struct with one of its fields
+    Field(u32),
+    /// Pop an index, an object and a value, and push the object with the
+    /// element at the index set to the value
+    SetIndex,
+    /// Pop a struct and a value, and push the struct with the field set to
+    /// the value
+    SetField(u32),
+    /// Pop a mutable reference and a value, and store the value in its
+    /// target
+    StoreDeref,
+    Array(u32),
+    Tuple(u32),
+    /// Build a dictionary from that many key and value pairs
+    Dictionary(u32),
+    Set(u32),
+    /// Build a struct from the field values of a shape
+    Struct(u32),
+    Range {
+        inclusive: bool,
+    },
+    Cast(u32),
+    /// Join that many values into a string, converting them as `str()`
+    Interpolate(u32),
+    /// Push a lambda running the chunk, with the variables it captures
+    Closure(u32),
+    /// Run the chunk of a spawned expression as a task and push the task
+    Spawn(u32),
+    SpawnActor(u32),
+    Await,
+    /// Unwrap `Some` or `Ok`, or return a `None` or `Err` from the chunk
+    Propagate,
+    Return,
+    Throw,
+    /// Until the matching `PopHandler`, an error that `catch` can stop
+    /// jumps to the target with the value a `catch` clause binds
+    SetupCatch(u32),
+    /// Until the matching `PopHandler`, any error jumps to the target,
+    /// which ends with `Reraise`
+    SetupFinally(u32),
+    PopHandler,
+    /// Raise the error that entered the innermost `finally` handler again
+    Reraise,
+    /// Pop the value of a `for` loop, which must be an array, keeping it in
+    /// the slot and the position in the next one
+    IterStart(u32),
+    /// Push the next element of the loop in the slot, or jump once there
+    /// are no more
+    IterNext {
+        slot: u32,
+        exit: u32,
+    },
+    /// Pop a value and bind the pattern's variables if it matches it, or
+    /// jump if it does not
+    Match {
+        pattern: u32,
+        otherwise: u32,
+    },
+    /// Pop a value and bind the pattern's variables, failing if it does not
+    /// match
+    Destructure(u32),
+    /// Fail because no arm matched the popped value
+    NoMatch,
+    /// Open a task group, which `EndTaskGroup` closes by waiting for the
+    /// tasks spawned in it and raising the first error among them
+    TaskGroup,
+    EndTaskGroup,
+    /// Close the innermost task group when leaving it early or with an
+    /// error, waiting for its tasks but ignoring their errors
+    LeaveTaskGroup,
+    Import(u32),
+}
+
+/// Compile a program to bytecode
+pub fn compile(program: &Program) -> Result<Module> {
+    let mut compiler = Compiler::default();
+    compiler.program(program)?;
+    Ok(compiler.module)
+}
+
+impl Module {
+    /// The module in the `.veyb` format: the magic bytes, the format
+    /// version and the bincode encoding of the module
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(FORMAT_VERSION.to_le_bytes());
+        bincode::DefaultOptions::new()
+            .serialize_into(&mut bytes, self)
+            .expect("modules serialize");
+        bytes
+    }
+
+    /// Read a module written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Module> {
+        let invalid =
+            |detail: &str| VeyraError::InternalError(format!("Invalid bytecode: {}", detail));
+        let rest = bytes
+            .strip_prefix(&MAGIC)
+            .ok_or_else(|| invalid("not a compiled Veyra module"))?;
+        let (version, rest) = rest
+            .split_first_chunk::<2>()
+            .ok_or_else(|| invalid("missing format version"))?;
+        let version = u16::from_le_bytes(*version);
+        if version != FORMAT_VERSION {
+            return Err(invalid(&format!(
+                "format version {} is not supported (expected {}); compile the source again",
+                version, FORMAT_VERSION
+            )));
+        }
+        bincode::DefaultOptions::new()
+            .deserialize(rest)
+            .map_err(|e| invalid(&e.to_string()))
+    }
+
+    pub fn name(&self, index: u32) -> &str {
+        &self.names[index as usize]
+    }
+
+    pub fn chunk(&self, index: u32) -> &Chunk {
+        &self.chunks[index as usize]
+    }
+}
+
+/// A listing of the module's chunks, one instruction per line, with the
+/// constants and names that operands refer to
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{} {:?}", chunk.name, chunk.kind)?;
+            if !chunk.parameters.is_empty() {
+                write!(f, " ({})", chunk.parameters.join(", "))?;
+            }
+            writeln!(f, ", {} slots", chunk.slots)?;
+            for (offset, instruction) in chunk.code.iter().enumerate() {
+                write!(f, "{:>5}  ", offset)?;
+                match instruction {
+                    Instruction::Constant(index) => {
+                        write!(f, "Constant {:?}", self.constants[*index as usize])?
+                    }
+                    Instruction::LoadName(name) => write!(f, "LoadName {}", self.name(*name))?,
+                    Instruction::DefineGlobal(name) => {
+                        write!(f, "DefineGlobal {}", self.name(*name))?
+                    }
+                    Instruction::StoreGlobal(name) => {
+                        write!(f, "StoreGlobal {}", self.name(*name))?
+                    }
+                    Instruction::Call(name, arguments) => {
+                        write!(f, "Call {} {}", self.name(*name), arguments)?
+                    }
+                    Instruction::CallMethod(name, arguments) => {
+                        write!(f, "CallMethod {} {}", self.name(*name), arguments)?
+                    }
+                    Instruction::Field(name) => write!(f, "Field {}", self.name(*name))?,
+                    Instruction::SetField(name) => write!(f, "SetField {}", self.name(*name))?,
+                    Instruction::Closure(chunk) => {
+                        write!(f, "Closure {}", self.chunk(*chunk).name)?
+                    }
+                    Instruction::Spawn(chunk) => write!(f, "Spawn {}", self.chunk(*chunk).name)?,
+                    other => write!(f, "{:?}", other)?,
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a name resolves in the code being compiled
+#[derive(Clone, Copy)]
+enum Place {
+    Local(u32),
+    /// A global, function or builtin, looked up by name
+    Global,
+}
+
+/// One step from a variable to the element or field an assignment changes
+#[derive(Clone, Copy)]
+enum Step {
+    /// An index, kept in the slot
+    Index(u32),
+    Field(u32),
+    Deref,
+}
+
+/// Code that must run when control leaves a protected region early
+#[derive(Clone, Copy)]
+enum Cleanup<'a> {
+    /// A `catch` handler, which only needs popping
+    None,
+    Finally(&'a Block),
+    TaskGroup,
+}
+
+struct Loop {
+    /// Where `continue` jumps
+    start: u32,
+    /// Jumps to patch with the end of the loop
+    breaks: Vec<usize>,
+    /// Handlers in place when the loop started
+    handlers: usize,
+}
+
+/// The chunk being compiled and its scopes
+struct Frame<'a> {
+    chunk: Chunk,
+    scopes: Vec<HashMap<String, u32>>,
+    /// Whether the outermost scope holds globals, as in the entry chunk
+    globals: bool,
+    /// Whether names of the enclosing frame are captured
+    captures: bool,
+    handlers: Vec<Cleanup<'a>>,
+    loops: Vec<Loop>,
+}
+
+impl<'a> Frame<'a> {
+    fn new(name: &str, kind: ChunkKind) -> Self {
+        Self {
+            chunk: Chunk {
+                name: name.to_string(),
+                kind,
+                parameters: Vec::new(),
+                captures: Vec::new(),
+                slots: 0,
+                code: Vec::new(),
+                lines: Vec::new(),
+            },
+            scopes: vec![HashMap::new()],
+            globals: kind == ChunkKind::Entry,
+            captures: matches!(kind, ChunkKind::Lambda | ChunkKind::Task),
+            handlers: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    fn slot(&mut self) -> u32 {
+        self.chunk.slots += 1;
+        self.chunk.slots - 1
+    }
+
+    /// Whether declarations go to globals
+    fn at_global_scope(&self) -> bool {
+        self.globals && self.scopes.len() == 1
+    }
+
+    fn lookup(&self, name: &str) -> Option<u32> {
+        // The global scope of the entry chunk has no slots
+        let scopes = if self.globals {
+            &self.scopes[1..]
+        } else {
+            &self.scopes[..]
+        };
+        scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+}
+
+#[derive(Default)]
+struct Compiler<'a> {
+    module: Module,
+    constants: HashMap<String, u32>,
+    names: HashMap<String, u32>,
+    /// Actors by name, for `spawn`
+    actors: HashMap<String, u32>,
+    /// The chunks being compiled, innermost last
+    frames: Vec<Frame<'a>>,
+    /// Top-level variables declared so far, which lambdas capture like
+    /// local ones
+    globals: HashSet<String>,
+}
+
+impl<'a> Compiler<'a> {
+    fn program(&mut self, program: &'a Program) -> Result<()> {
+        for item in &program.items {
+            if let Item::Actor(actor) = item {
+                let index = self.module.actors.len() as u32;
+                self.actors.insert(actor.name.clone(), index);
+                self.module.actors.push(ActorDef {
+                    name: actor.name.clone(),
+                    fields: Vec::new(),
+                    methods: Vec::new(),
+                    mailbox_capacity: None,
+                    overflow: Overflow::Block,
+                });
+            }
+        }
+
+        // The entry chunk is compiled alongside the declarations, so it is
+        // pushed last
+        self.frames.push(Frame::new("<main>", ChunkKind::Entry));
+        let mut actor = 0;
+        for (index, item) in program.items.iter().enumerate() {
+            match item {
+                Item::Function(func) => {
+                    let chunk = self.function(func, ChunkKind::Function)?;
+                    self.module.functions.push(chunk);
+                }
+                Item::Struct(definition) => self.module.structs.push(StructDef {
+                    name: definition.name.clone(),
+                    fields: definition.fields.iter().map(|f| f.name.clone()).collect(),
+                }),
+                Item::Impl(block) => {
+                    let mut methods = Vec::new();
+                    for method in &block.methods {
+                        methods.push(self.function(method, ChunkKind::Method)?);
+                    }
+                    self.module.impls.push(ImplDef {
+                        target: block.target.clone(),
+                        trait_name: block.trait_name.clone(),
+                        methods,
+                    });
+                }
+                Item::Trait(definition) => {
+                    let mut methods = Vec::new();
+                    for method in &definition.methods {
+                        let func = &method.function;
+                        let default = match method.has_default {
+                            true => Some(self.function(func, ChunkKind::Method)?),
+                            false => None,
+                        };
+                        methods.push(TraitMethodDef {
+                            name: func.name.clone(),
+                            arity: func.parameters.iter().filter(|p| p.name != "self").count()
+                                as u32,
+                            default,
+                        });
+                    }
+                    self.module.traits.push(TraitDef {
+                        name: definition.name.clone(),
+                        methods,
+                    });
+                }
+                Item::Actor(definition) => {
+                    self.actor(actor, definition)?;
+                    actor += 1;
+                }
+                Item::Import(import) => {
+                    self.line(program.lines.get(index));
+                    let index = self.module.imports.len() as u32;
+                    self.module.imports.push(import.clone());
+                    self.emit(Instruction::Import(index));
+                }
+                Item::Statement(statement) => {
+                    self.line(program.lines.get(index));
+                    self.statement(statement)?;
+                }
+            }
+        }
+        self.finish_chunk()?;
+        self.module.entry = self.module.chunks.len() as u32 - 1;
+        Ok(())
+    }
+
+    fn actor(&mut self, index: u32, actor: &'a Actor) -> Result<()> {
+        let mut methods = Vec::new();
+        for method in &actor.methods {
+            let mut frame = Frame::new(&method.name, ChunkKind::ActorMethod);
+            for param in &method.parameters {
+                frame.chunk.parameters.push(param.name.clone());
+            }
+            // Parameters hide fields of the same name
+            let parameters: Vec<u32> = method.parameters.iter().map(|_| frame.slot()).collect();
+            for field in &actor.fields {
+                let slot = frame.slot();
+                frame.scopes[0].insert(field.name.clone(), slot);
+            }
+            for (param, slot) in method.parameters.iter().zip(parameters) {
+                frame.scopes[0].insert(param.name.clone(), slot);
+            }
+            methods.push(self.body(frame, &method.body)?);
+        }
+        let mailbox = MailboxConfig::of(actor);
+        self.module.actors[index as usize] = ActorDef {
+            name: actor.name.clone(),
+            fields: actor
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field.field_type.clone()))
+                .collect(),
+            methods,
+            mailbox_capacity: mailbox.capacity,
+            overflow: mailbox.overflow,
+        };
+        Ok(())
+    }
+
+    /// Compile a function or method, returning its chunk
+    fn function(&mut self, func: &'a Function, kind: ChunkKind) -> Result<u32> {
+        let mut frame = Frame::new(&func.name, kind);
+        let mut parameters = &func.parameters[..];
+        if kind == ChunkKind::Method {
+            let slot = frame.slot();
+            frame.scopes[0].insert("self".to_string(), slot);
+            if parameters.first().is_some_and(|p| p.name == "self") {
+                parameters = &parameters[1..];
+            }
+        }
+        for param in parameters {
+            frame.chunk.parameters.push(param.name.clone());
+            let slot = frame.slot();
+            frame.scopes[0].insert(param.name.clone(), slot);
+        }
+        self.body(frame, &func.body)
+    }
+
+    /// Compile the body of a frame, which returns None if it falls off the
+    /// end
+    fn body(&mut self, frame: Frame<'a>, body: &'a Block) -> Result<u32> {
+        self.frames.push(frame);
+        self.block(body)?;
+        self.finish_chunk()
+    }
+
+    /// End the innermost chunk with a return of None and add it to the
+    /// module
+    fn finish_chunk(&mut self) -> Result<u32> {
+        let none = self.constant(&Literal::None);
+        self.emit(Instruction::Constant(none));
+        self.emit(Instruction::Return);
+        let frame = self.frames.pop().expect("a chunk being compiled");
+        self.module.chunks.push(frame.chunk);
+        Ok(self.module.chunks.len() as u32 - 1)
+    }
+
+    fn frame(&mut self) -> &mut Frame<'a> {
+        self.frames.last_mut().expect("a chunk being compiled")
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let code = &mut self.frame().chunk.code;
+        code.push(instruction);
+        code.len() - 1
+    }
+
+    /// Attribute the instructions emitted next to `line`, if known
+    fn line(&mut self, line: Option<u32>) {
+        let Some(line) = line else {
+            return;
+        };
+        let here = self.here();
+        let lines = &mut self.frame().chunk.lines;
+        match lines.last_mut() {
+            Some((_, last)) if *last == line => {}
+            Some((start, last)) if *start == here => *last = line,
+            _ => lines.push((here, line)),
+        }
+    }
+
+    /// The line the instructions emitted next are attributed to, if known
+    fn current_line(&self) -> Option<u32> {
+        let (_, line) = self.frames.last()?.chunk.lines.last()?;
+        Some(*line)
+    }
+
+    /// The offset of the next instruction
+    fn here(&mut self) -> u32 {
+        self.frame().chunk.code.len() as u32
+    }
+
+    /// Point the jump at `at` to the next instruction
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.frame().chunk.code[at] {
+            Instruction::Jump(to)
+            | Instruction::JumpIfFalse(to)
+            | Instruction::SetupCatch(to)
+            | Instruction::SetupFinally(to)
+            | Instruction::IterNext { exit: to, .. }
+            | Instruction::Match { otherwise: to, .. } => *to = target,
+            other => unreachable!("patching {:?}", other),
+        }
+    }
+
+    fn constant(&mut self, literal: &Literal) -> u32 {
+        // Debug output tells 1 and 1.0 apart, and floats by their digits
+        let key = format!("{:?}", literal);
+        if let Some(&index) = self.constants.get(&key) {
+            return index;
+        }
+        let index = self.module.constants.len() as u32;
+        self.module.constants.push(literal.clone());
+        self.constants.insert(key, index);
+        index
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        if let Some(&index) = self.names.get(name) {
+            return index;
+        }
+        let index = self.module.names.len() as u32;
+        self.module.names.push(name.to_string());
+        self.names.insert(name.to_string(), index);
+        index
+    }
+
+    fn push_scope(&mut self) {
+        self.frame().scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.frame().scopes.pop();
+    }
+
+    /// Where `name` is stored, capturing it into lambdas from the frames
+    /// around them
+    fn resolve(&mut self, name: &str) -> Place {
+        match self.resolve_in(self.frames.len() - 1, name) {
+            Some(slot) => Place::Local(slot),
+            None => Place::Global,
+        }
+    }
+
+    fn resolve_in(&mut self, depth: usize, name: &str) -> Option<u32> {
+        if let Some(slot) = self.frames[depth].lookup(name) {
+            return Some(slot);
+        }
+        if !self.frames[depth].captures {
+            return None;
+        }
+        let from = match self.resolve_in(depth - 1, name) {
+            Some(slot) => CaptureFrom::Slot(slot),
+            None if self.globals.contains(name) => CaptureFrom::Global(self.name(name)),
+            None => return None,
+        };
+        let frame = &mut self.frames[depth];
+        let to = frame.slot();
+        frame.chunk.captures.push(Capture { from, to });
+        // Captures belong to the whole chunk, whichever scope uses them
+        frame.scopes[0].insert(name.to_string(), to);
+        Some(to)
+    }
+
+    /// Declare a variable in the innermost scope and store the value on top
+    /// of the stack in it
+    fn define(&mut self, name: &str) {
+        if self.frame().at_global_scope() {
+            self.globals.insert(name.to_string());
+            let name = self.name(name);
+            self.emit(Instruction::DefineGlobal(name));
+            return;
+        }
+        let slot = self.declare(name);
+        self.emit(Instruction::StoreLocal(slot));
+    }
+
+    /// A new slot for a variable of the innermost scope
+    fn declare(&mut self, name: &str) -> u32 {
+        let frame = self.frame();
+        let slot = frame.slot();
+        frame
+            .scopes
+            .last_mut()
+            .expect("a scope")
+            .insert(name.to_string(), slot);
+        slot
+    }
+
+    fn load(&mut self, name: &str) {
+        match self.resolve(name) {
+            Place::Local(slot) => self.emit(Instruction::LoadLocal(slot)),
+            Place::Global => {
+                let name = self.name(name);
+                self.emit(Instruction::LoadName(name))
+            }
+        };
+    }
+
+    fn store(&mut self, name: &str) {
+        match self.resolve(name) {
+            Place::Local(slot) => self.emit(Instruction::StoreLocal(slot)),
+            Place::Global => {
+                let name = self.name(name);
+                self.emit(Instruction::StoreGlobal(name))
+            }
+        };
+    }
+
+    /// Assignment to an element or field, such as `a[i] = x`: the value and
+    /// then the indexes are kept in slots, and each container on the way,
+    /// from the innermost out, is loaded again and replaced by a copy with
+    /// the new element. A reference on the way is written through instead.
+    fn assign_element(&mut self, assign: &'a ast::Assignment) -> Result<()> {
+        self.expression(&assign.value)?;
+        let value = self.frame().slot();
+        self.emit(Instruction::StoreLocal(value));
+        let mut steps = Vec::new();
+        let variable = self.assignment_steps(&assign.target, &mut steps)?;
+
+        if let Some(operator) = compound_operator(&assign.operator) {
+            self.load_element(variable, &steps);
+            self.emit(Instruction::LoadLocal(value));
+            self.emit(Instruction::Binary(operator));
+        } else {
+            self.emit(Instruction::LoadLocal(value));
+        }
+        for (i, step) in steps.iter().enumerate().rev() {
+            self.load_element(variable, &steps[..i]);
+            match *step {
+                Step::Index(slot) => {
+                    self.emit(Instruction::LoadLocal(slot));
+                    self.emit(Instruction::SetIndex)
+                }
+                Step::Field(name) => self.emit(Instruction::SetField(name)),
+                Step::Deref => {
+                    self.emit(Instruction::StoreDeref);
+                    return Ok(());
+                }
+            };
+        }
+        self.store(variable);
+        Ok(())
+    }
+
+    /// The variable an assignment target starts from, storing the indexes
+    /// on the way to the element in slots
+    fn assignment_steps(
+        &mut self,
+        target: &'a Expression,
+        steps: &mut Vec<Step>,
+    ) -> Result<&'a str> {
+        let variable = match target {
+            Expression::Identifier(name) => return Ok(name),
+            Expression::Index(index) => {
+                let variable = self.assignment_steps(&index.object, steps)?;
+                self.expression(&index.index)?;
+                let slot = self.frame().slot();
+                self.emit(Instruction::StoreLocal(slot));
+                steps.push(Step::Index(slot));
+                variable
+            }
+            Expression::FieldAccess(access) => {
+                let variable = self.assignment_steps(&access.object, steps)?;
+                steps.push(Step::Field(self.name(&access.field)));
+                variable
+            }
+            Expression::Unary(unary) if unary.operator == UnaryOperator::Dereference => {
+                let variable = self.assignment_steps(&unary.operand, steps)?;
+                steps.push(Step::Deref);
+                variable
+            }
+            _ => return Err(VeyraError::runtime_error("Invalid assignment target")),
+        };
+        Ok(variable)
+    }
+
+    /// Push the element `steps` lead to from `variable`
+    fn load_element(&mut self, variable: &str, steps: &[Step]) {
+        self.load(variable);
+        for step in steps {
+            match *step {
+                Step::Index(slot) => {
+                    self.emit(Instruction::LoadLocal(slot));
+                    self.emit(Instruction::Index)
+                }
+                Step::Field(name) => self.emit(Instruction::Field(name)),
+                Step::Deref => self.emit(Instruction::Unary(UnaryOperator::Dereference)),
+            };
+        }
+    }
+
+    /// Statements of a block, in the current scope
+    fn block(&mut self, block: &'a Block) -> Result<()> {
+        for (index, statement) in block.statements.iter().enumerate() {
+            self.line(block.lines.get(index));
+            self.statement(statement)?;
+        }
+        Ok(())
+    }
+
+    /// A block in a scope of its own
+    fn scoped(&mut self, block: &'a Block) -> Result<()> {
+        self.push_scope();
+        let result = self.block(block);
+        self.pop_scope();
+        result
+    }
+
+    fn statement(&mut self, statement: &'a Statement) -> Result<()> {
+        match statement {
+            Statement::Expression(stmt) => {
+                self.expression(&stmt.expression)?;
+                self.emit(Instruction::Pop);
+            }
+            Statement::VariableDeclaration(var) => {
+                self.expression(&var.initializer)?;
+                match &var.pattern {
+                    Some(pattern) => {
+                        let pattern = self.pattern(pattern);
+                        self.emit(Instruction::Destructure(pattern));
+                    }
+                    None => self.define(&var.name),
+                }
+            }
+            Statement::Assignment(assign) => {
+                let Expression::Identifier(name) = &assign.target else {
+                    return self.assign_element(assign);
+                };
+                match compound_operator(&assign.operator) {
+                    Some(operator) => {
+                        self.load(name);
+                        self.expression(&assign.value)?;
+                        self.emit(Instruction::Binary(operator));
+                    }
+                    None => self.expression(&assign.value)?,
+                }
+                self.store(name);
+            }
+            Statement::If(stmt) => {
+                let branches = std::iter::once((&stmt.condition, &stmt.then_branch)).chain(
+                    stmt.elif_branches
+                        .iter()
+                        .map(|(condition, block)| (condition, block)),
+                );
+                let mut ends = Vec::new();
+                for (condition, block) in branches {
+                    self.expression(condition)?;
+                    let next = self.emit(Instruction::JumpIfFalse(0));
+                    self.block(block)?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    self.patch(next);
+                }
+                if let Some(block) = &stmt.else_branch {
+                    self.block(block)?;
+                }
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            Statement::While(stmt) => {
+                let start = self.here();
+                self.expression(&stmt.condition)?;
+                let exit = self.emit(Instruction::JumpIfFalse(0));
+                self.loop_body(start, &stmt.body)?;
+                self.emit(Instruction::Jump(start));
+                self.patch(exit);
+                self.end_loop();
+            }
+            Statement::For(stmt) => {
+                self.expression(&stmt.iterable)?;
+                let frame = self.frame();
+                let iterator = frame.slot();
+                frame.slot();
+                self.emit(Instruction::IterStart(iterator));
+                let start = self.here();
+                let exit = self.emit(Instruction::IterNext {
+                    slot: iterator,
+                    exit: 0,
+                });
+                // The variable is declared again on every pass, in the
+                // scope around the loop
+                self.define(&stmt.variable);
+                self.loop_body(start, &stmt.body)?;
+                self.emit(Instruction::Jump(start));
+                self.patch(exit);
+                self.end_loop();
+            }
+            Statement::Match(stmt) => {
+                self.expression(&stmt.expression)?;
+                let value = self.frame().slot();
+                self.emit(Instruction::StoreLocal(value));
+                let mut ends = Vec::new();
+                for arm in &stmt.arms {
+                    self.push_scope();
+                    let result =
+                        self.arm(value, &arm.pattern, arm.guard.as_ref())
+                            .and_then(|next| {
+                                self.statement(&arm.body)?;
+                                Ok(next)
+                            });
+                    self.pop_scope();
+                    let next = result?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    for jump in next {
+                        self.patch(jump);
+                    }
+                }
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            Statement::Return(ret) => {
+                match &ret.value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        let none = self.constant(&Literal::None);
+                        self.emit(Instruction::Constant(none));
+                    }
+                }
+                // The value stays on the stack while `finally` blocks run
+                self.leave(0)?;
+                self.emit(Instruction::Return);
+            }
+            Statement::Try(stmt) => self.try_statement(stmt)?,
+            Statement::Throw(throw) => {
+                self.expression(&throw.value)?;
+                self.emit(Instruction::Throw);
+            }
+            Statement::Break | Statement::Continue => {
+                let unwind = match statement {
+                    Statement::Break => Unwind::Break,
+                    _ => Unwind::Continue,
+                };
+                let Some(handlers) = self.frame().loops.last().map(|l| l.handlers) else {
+                    return Err(VeyraError::Unwind(unwind));
+                };
+                self.leave(handlers)?;
+                match unwind {
+                    Unwind::Break => {
+                        let jump = self.emit(Instruction::Jump(0));
+                        let innermost = self.frame().loops.last_mut().expect("a loop");
+                        innermost.breaks.push(jump);
+                    }
+                    _ => {
+                        let start = self.frame().loops.last().expect("a loop").start;
+                        self.emit(Instruction::Jump(start));
+                    }
+                }
+            }
+            Statement::Block(block) => self.scoped(block)?,
+            Statement::TaskGroup(block) => {
+                self.emit(Instruction::TaskGroup);
+                let handler = self.emit(Instruction::SetupFinally(0));
+                self.frame().handlers.push(Cleanup::TaskGroup);
+                let result = self.scoped(block);
+                self.frame().handlers.pop();
+                result?;
+                self.emit(Instruction::PopHandler);
+                self.emit(Instruction::EndTaskGroup);
+                let end = self.emit(Instruction::Jump(0));
+                self.patch(handler);
+                self.emit(Instruction::LeaveTaskGroup);
+                self.emit(Instruction::Reraise);
+                self.patch(end);
+            }
+            Statement::Comment(_) => {}
+        }
+        Ok(())
+    }
+
+    fn loop_body(&mut self, start: u32, body: &'a Block) -> Result<()> {
+        let handlers = self.frame().handlers.len();
+        self.frame().loops.push(Loop {
+            start,
+            breaks: Vec::new(),
+            handlers,
+        });
+        self.block(body)
+    }
+
+    /// Point the breaks of the innermost loop past its end
+    fn end_loop(&mut self) {
+        let innermost = self.frame().loops.pop().expect("a loop");
+        for jump in innermost.breaks {
+            self.patch(jump);
+        }
+    }
+
+    /// Leave the handlers above the first `keep`, innermost first, running
+    /// their cleanup code
+    fn leave(&mut self, keep: usize) -> Result<()> {
+        let handlers = self.frame().handlers.clone();
+        for depth in (keep..handlers.len()).rev() {
+            self.emit(Instruction::PopHandler);
+            // Cleanup code runs outside the handler it belongs to
+            let inner = self.frame().handlers.split_off(depth);
+            let result = match handlers[depth] {
+                Cleanup::None => Ok(()),
+                Cleanup::Finally(block) => self.scoped(block),
+                Cleanup::TaskGroup => {
+                    self.emit(Instruction::LeaveTaskGroup);
+                    Ok(())
+                }
+            };
+            self.frame().handlers.extend(inner);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn try_statement(&mut self, stmt: &'a ast::TryStatement) -> Result<()> {
+        let finally = match &stmt.finally {
+            Some(block) => {
+                let handler = self.emit(Instruction::SetupFinally(0));
+                self.frame().handlers.push(Cleanup::Finally(block));
+                Some((handler, block))
+            }
+            None => None,
+        };
+
+        match &stmt.catch {
+            Some(catch) => {
+                let handler = self.emit(Instruction::SetupCatch(0));
+                self.frame().handlers.push(Cleanup::None);
+                let result = self.scoped(&stmt.body);
+                self.frame().handlers.pop();
+                result?;
+                self.emit(Instruction::PopHandler);
+                let end = self.emit(Instruction::Jump(0));
+
+                // The handler starts with the caught value on the stack
+                self.patch(handler);
+                self.push_scope();
+                match &catch.variable {
+                    Some(variable) => {
+                        let slot = self.declare(variable);
+                        self.emit(Instruction::StoreLocal(slot));
+                    }
+                    None => {
+                        self.emit(Instruction::Pop);
+                    }
+                }
+                let result = self.block(&catch.body);
+                self.pop_scope();
+                result?;
+                self.patch(end);
+            }
+            None => self.scoped(&stmt.body)?,
+        }
+
+        if let Some((handler, block)) = finally {
+            self.frame().handlers.pop();
+            self.emit(Instruction::PopHandler);
+            self.scoped(block)?;
+            let end = self.emit(Instruction::Jump(0));
+            self.patch(handler);
+            self.scoped(block)?;
+            self.emit(Instruction::Reraise);
+            self.patch(end);
+        }
+        Ok(())
+    }
+
+    /// Match the value in `slot` against an arm's pattern and guard in the
+    /// current scope, returning the jumps to take when the arm does not
+    /// apply
+    fn arm(
+        &mut self,
+        slot: u32,
+        pattern: &ast::Pattern,
+        guard: Option<&'a Expression>,
+    ) -> Result<Vec<usize>> {
+        self.emit(Instruction::LoadLocal(slot));
+        let pattern = self.pattern(pattern);
+        let mut next = vec![self.emit(Instruction::Match {
+            pattern,
+            otherwise: 0,
+        })];
+        if let Some(guard) = guard {
+            self.expression(guard)?;
+            next.push(self.emit(Instruction::JumpIfFalse(0)));
+        }
+        Ok(next)
+    }
+
+    /// Add a pattern to the module, declaring its variables in the
+    /// innermost scope
+    fn pattern(&mut self, pattern: &ast::Pattern) -> u32 {
+        let pattern = self.lower_pattern(pattern);
+        self.module.patterns.push(pattern);
+        self.module.patterns.len() as u32 - 1
+    }
+
+    fn lower_pattern(&mut self, pattern: &ast::Pattern) -> Pattern {
+        match pattern {
+            ast::Pattern::Wildcard => Pattern::Wildcard,
+            ast::Pattern::Identifier(name) if self.frame().at_global_scope() => {
+                Pattern::BindGlobal(self.name(name))
+            }
+            ast::Pattern::Identifier(name) => Pattern::Bind(self.declare(name)),
+            ast::Pattern::Literal(literal) => Pattern::Constant(self.constant(literal)),
+            ast::Pattern::Tuple(elements) => {
+                Pattern::Tuple(elements.iter().map(|p| self.lower_pattern(p)).collect())
+            }
+            ast::Pattern::Array { elements, rest } => Pattern::Array {
+                elements: elements.iter().map(|p| self.lower_pattern(p)).collect(),
+                rest: rest.as_ref().map(|rest| Box::new(self.lower_pattern(rest))),
+            },
+            ast::Pattern::Struct { name, fields } => Pattern::Struct {
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|(field, p)| (field.clone(), self.lower_pattern(p)))
+                    .collect(),
+            },
+            ast::Pattern::Variant { variant, value } => Pattern::Variant {
+                variant: *variant,
+                value: Box::new(self.lower_pattern(value)),
+            },
+        }
+    }
+
+    fn expressions(&mut self, expressions: &'a [Expression]) -> Result<u32> {
+        for expression in expressions {
+            self.expression(expression)?;
+        }
+        Ok(expressions.len() as u32)
+    }
+
+    fn expression(&mut self, expression: &'a Expression) -> Result<()> {
+        match expression {
+            Expression::Literal(literal) => {
+                let index = self.constant(literal);
+                self.emit(Instruction::Constant(index));
+            }
+            Expression::Identifier(name) => self.load(name),
+            Expression::Binary(binary) => {
+                self.expression(&binary.left)?;
+                self.expression(&binary.right)?;
+                self.emit(Instruction::Binary(binary.operator.clone()));
+            }
+            Expression::Unary(unary) => {
+                self.expression(&unary.operand)?;
+                self.emit(Instruction::Unary(unary.operator.clone()));
+            }
+            Expression::Call(call) => self.call(&call.callee, &call.arguments)?,
+            Expression::Index(index) => {
+                self.expression(&index.object)?;
+                self.expression(&index.index)?;
+                self.emit(Instruction::Index);
+            }
+            Expression::FieldAccess(access) => {
+                self.expression(&access.object)?;
+                let field = self.name(&access.field);
+                self.emit(Instruction::Field(field));
+            }
+            Expression::MethodCall(call) => {
+                self.expression(&call.object)?;
+                let arguments = self.expressions(&call.arguments)?;
+                let method = self.name(&call.method);
+                self.emit(Instruction::CallMethod(method, arguments));
+            }
+            Expression::ModuleAccess(access) => {
+                let module = self.name(&access.module);
+                let item = self.name(&access.item);
+                self.emit(Instruction::ModuleItem { module, item });
+            }
+            Expression::Array(array) => {
+                let count = self.expressions(&array.elements)?;
+                self.emit(Instruction::Array(count));
+            }
+            Expression::Dictionary(dict) => {
+                for (key, value) in &dict.pairs {
+                    self.expression(key)?;
+                    self.expression(value)?;
+                }
+                self.emit(Instruction::Dictionary(dict.pairs.len() as u32));
+            }
+            Expression::Set(set) => {
+                let count = self.expressions(&set.elements)?;
+                self.emit(Instruction::Set(count));
+            }
+            Expression::Tuple(tuple) => {
+                let count = self.expressions(&tuple.elements)?;
+                self.emit(Instruction::Tuple(count));
+            }
+            Expression::StructInit(init) => {
+                for (_, value) in &init.fields {
+                    self.expression(value)?;
+                }
+                self.module.shapes.push(Shape {
+                    name: init.struct_name.clone(),
+                    fields: init.fields.iter().map(|(name, _)| name.clone()).collect(),
+                });
+                let shape = self.module.shapes.len() as u32 - 1;
+                self.emit(Instruction::Struct(shape));
+            }
+            Expression::If(expr) => {
+                self.expression(&expr.condition)?;
+                let otherwise = self.emit(Instruction::JumpIfFalse(0));
+                self.expression(&expr.then_expr)?;
+                let end = self.emit(Instruction::Jump(0));
+                self.patch(otherwise);
+                self.expression(&expr.else_expr)?;
+                self.patch(end);
+            }
+            Expression::Match(expr) => {
+                self.expression(&expr.expression)?;
+                let value = self.frame().slot();
+                self.emit(Instruction::StoreLocal(value));
+                let mut ends = Vec::new();
+                for arm in &expr.arms {
+                    self.push_scope();
+                    let result =
+                        self.arm(value, &arm.pattern, arm.guard.as_ref())
+                            .and_then(|next| {
+                                self.expression(&arm.expression)?;
+                                Ok(next)
+                            });
+                    self.pop_scope();
+                    let next = result?;
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    for jump in next {
+                        self.patch(jump);
+                    }
+                }
+                self.emit(Instruction::LoadLocal(value));
+                self.emit(Instruction::NoMatch);
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            Expression::Range(range) => {
+                self.expression(&range.start)?;
+                self.expression(&range.end)?;
+                self.emit(Instruction::Range {
+                    inclusive: range.inclusive,
+                });
+            }
+            Expression::Await(expr) => {
+                self.expression(&expr.expression)?;
+                self.emit(Instruction::Await);
+            }
+            Expression::Spawn(spawn) => {
+                // `spawn Name` starts an actor unless a variable hides it
+                if let Expression::Identifier(name) = spawn.expression.as_ref() {
+                    let actor = self.actors.get(name).copied();
+                    if let (Some(actor), Place::Global) = (actor, self.resolve(name)) {
+                        self.emit(Instruction::SpawnActor(actor));
+                        return Ok(());
+                    }
+                }
+                let frame = Frame::new("<spawn>", ChunkKind::Task);
+                let chunk = self.nested(frame, |this| {
+                    this.expression(&spawn.expression)?;
+                    this.emit(Instruction::Return);
+                    Ok(())
+                })?;
+                self.emit(Instruction::Spawn(chunk));
+            }
+            Expression::Cast(cast) => {
+                self.expression(&cast.expression)?;
+                self.module.types.push(cast.target_type.clone());
+                let ty = self.module.types.len() as u32 - 1;
+                self.emit(Instruction::Cast(ty));
+            }
+            Expression::InterpolatedString(string) => {
+                for part in &string.parts {
+                    match part {
+                        StringPart::Literal(text) => {
+                            let text = self.constant(&Literal::String(text.clone()));
+                            self.emit(Instruction::Constant(text));
+                        }
+                        StringPart::Expression(expression) => self.expression(expression)?,
+                    }
+                }
+                self.emit(Instruction::Interpolate(string.parts.len() as u32));
+            }
+            Expression::Lambda(lambda) => {
+                let mut frame = Frame::new("<lambda>", ChunkKind::Lambda);
+                for param in &lambda.parameters {
+                    frame.chunk.parameters.push(param.name.clone());
+                    let slot = frame.slot();
+                    frame.scopes[0].insert(param.name.clone(), slot);
+                }
+                let chunk = self.nested(frame, |this| match &lambda.body {
+                    LambdaBody::Expression(body) => {
+                        this.expression(body)?;
+                        this.emit(Instruction::Return);
+                        Ok(())
+                    }
+                    LambdaBody::Block(body) => this.block(body),
+                })?;
+                self.emit(Instruction::Closure(chunk));
+            }
+            Expression::Propagate(propagate) => {
+                self.expression(&propagate.expression)?;
+                self.emit(Instruction::Propagate);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile a chunk nested in the current one, such as a lambda, which
+    /// starts on the line of the code it is nested in
+    fn nested(
+        &mut self,
+        frame: Frame<'a>,
+        body: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<u32> {
+        let line = self.current_line();
+        self.frames.push(frame);
+        self.line(line);
+        if let Err(e) = body(self) {
+            self.frames.pop();
+            return Err(e);
+        }
+        self.finish_chunk()
+    }
+
+    fn call(&mut self, callee: &'a Expression, arguments: &'a [Expression]) -> Result<()> {
+        match callee {
+            Expression::Identifier(name) => {
+                // help(name) documents the function itself rather than
+                // evaluating it
+                if let (true, [Expression::Identifier(target)]) = (name == "help", arguments) {
+                    if let Place::Global = self.resolve(target) {
+                        let target = self.constant(&Literal::String(target.clone()));
+                        self.emit(Instruction::Constant(target));
+                        let help = self.name(name);
+                        self.emit(Instruction::Call(help, 1));
+                        return Ok(());
+                    }
+                }
+                // trace(expr) labels the value with the expression's source
+                if let (true, [argument]) = (name == "trace", arguments) {
+                    if let Place::Global = self.resolve(name) {
+                        self.expression(argument)?;
+                        let label = printer::expression_to_source(argument);
+                        let label = self.constant(&Literal::String(label));
+                        self.emit(Instruction::Constant(label));
+                        let trace = self.name(name);
+                        self.emit(Instruction::Call(trace, 2));
+                        return Ok(());
+                    }
+                }
+                match self.resolve(name) {
+                    Place::Local(slot) => {
+                        self.emit(Instruction::LoadLocal(slot));
+                        let arguments = self.expressions(arguments)?;
+                        self.emit(Instruction::CallValue(arguments));
+                    }
+                    Place::Global => {
+                        let arguments = self.expressions(arguments)?;
+                        let name = self.name(name);
+                        self.emit(Instruction::Call(name, arguments));
+                    }
+                }
+            }
+            Expression::ModuleAccess(access) => {
+                let arguments = self.expressions(arguments)?;
+                let module = self.name(&access.module);
+                let item = self.name(&access.item);
+                self.emit(Instruction::CallModule {
+                    module,
+                    item,
+                    arguments,
+                });
+            }
+            callee => {
+                self.expression(callee)?;
+                let arguments = self.expressions(arguments)?;
+                self.emit(Instruction::CallValue(arguments));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The operator of `target op= value`, or None for plain assignment
+pub(crate) fn compound_operator(operator: &AssignmentOperator) -> Option<BinaryOperator> {
+    use AssignmentOperator::*;
+    Some(match operator {
+        Assign => return None,
+        AddAssign => BinaryOperator::Add,
+        SubAssign => BinaryOperator::Subtract,
+        MulAssign => BinaryOperator::Multiply,
+        DivAssign => BinaryOperator::Divide,
+        FloorDivAssign => BinaryOperator::FloorDivide,
+        ModAssign => BinaryOperator::Modulo,
+        BitwiseAndAssign => BinaryOperator::BitwiseAnd,
+        BitwiseOrAssign => BinaryOperator::BitwiseOr,
+        BitwiseXorAssign => BinaryOperator::BitwiseXor,
+        LeftShiftAssign => BinaryOperator::LeftShift,
+        RightShiftAssign => BinaryOperator::RightShift,
+    })
+}
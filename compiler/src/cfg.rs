@@ -0,0 +1,197 @@
+// Conditional compilation
+//
+// Items annotated with `@cfg(predicate)` are removed from the program before
+// it is interpreted unless the predicate holds for the current configuration.
+// Predicates are `os = "..."`, `family = "unix" | "windows"` and
+// `feature = "..."`, combined with `not(p)`, `any(p, ...)` and `all(p, ...)`.
+// Features are declared in the `[features]` table of veyra.toml and enabled
+// with `veyc --features`.
+
+use crate::ast::{Attribute, AttributeArg, Attributed, Item, Lines, Literal, Program};
+use crate::error::{Result, VeyraError};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Keys accepted in `key = "value"` predicates
+const KEYS: &[&str] = &["os", "family", "feature"];
+
+/// The configuration `@cfg` predicates are evaluated against
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfgOptions {
+    pub os: String,
+    pub family: String,
+    pub features: BTreeSet<String>,
+}
+
+impl Default for CfgOptions {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl CfgOptions {
+    /// The platform veyc is running on, with no features enabled
+    pub fn host() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            family: std::env::consts::FAMILY.to_string(),
+            features: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_features(mut self, features: impl IntoIterator<Item = String>) -> Self {
+        self.features.extend(features);
+        self
+    }
+
+    fn matches(&self, key: &str, value: &str) -> bool {
+        match key {
+            "os" => self.os == value,
+            "family" => self.family == value,
+            "feature" => self.features.contains(value),
+            _ => false,
+        }
+    }
+}
+
+/// Check that a `@cfg` attribute holds a single well-formed predicate
+pub fn check_attribute(attribute: &Attribute) -> std::result::Result<(), String> {
+    match attribute.args.as_slice() {
+        [predicate] => check_predicate(predicate),
+        _ => Err("@cfg expects exactly one predicate, e.g. @cfg(os = \"linux\")".to_string()),
+    }
+}
+
+fn check_predicate(arg: &AttributeArg) -> std::result::Result<(), String> {
+    match arg {
+        AttributeArg::KeyValue(key, Literal::String(_)) if KEYS.contains(&key.as_str()) => Ok(()),
+        AttributeArg::KeyValue(key, Literal::String(_)) => Err(format!(
+            "Unknown cfg key '{}' (expected one of: {})",
+            key,
+            KEYS.join(", ")
+        )),
+        AttributeArg::KeyValue(key, _) => Err(format!("cfg value for '{}' must be a string", key)),
+        AttributeArg::Nested(nested) => match nested.name.as_str() {
+            "not" if nested.args.len() != 1 => Err("not() takes exactly one predicate".to_string()),
+            "not" | "any" | "all" => nested.args.iter().try_for_each(check_predicate),
+            other => Err(format!(
+                "Unknown cfg predicate '{}' (expected not, any or all)",
+                other
+            )),
+        },
+        AttributeArg::Literal(_) => Err(
+            "cfg predicates are written as key = \"value\", not(..), any(..) or all(..)"
+                .to_string(),
+        ),
+    }
+}
+
+/// Whether every `@cfg` attribute in `attributes` holds
+pub fn is_enabled(attributes: &[Attribute], options: &CfgOptions) -> bool {
+    attributes
+        .iter()
+        .filter(|attribute| attribute.name == "cfg")
+        .all(|attribute| attribute.args.iter().all(|arg| evaluate(arg, options)))
+}
+
+fn evaluate(arg: &AttributeArg, options: &CfgOptions) -> bool {
+    match arg {
+        AttributeArg::KeyValue(key, Literal::String(value)) => options.matches(key, value),
+        AttributeArg::Nested(nested) => match nested.name.as_str() {
+            "not" => !nested.args.iter().all(|arg| evaluate(arg, options)),
+            "any" => nested.args.iter().any(|arg| evaluate(arg, options)),
+            _ => nested.args.iter().all(|arg| evaluate(arg, options)),
+        },
+        _ => false,
+    }
+}
+
+/// Remove items and methods whose `@cfg` predicates do not hold
+pub fn configure(program: Program, options: &CfgOptions) -> Program {
+    let mut lines = Lines::default();
+    let items = program
+        .items
+        .into_iter()
+        .enumerate()
+        .filter(|(_, item)| is_enabled(item.attributes(), options))
+        .enumerate()
+        .map(|(kept, (index, item))| {
+            lines.set(kept, program.lines.get(index));
+            item
+        })
+        .map(|item| match item {
+            Item::Impl(mut imp) => {
+                imp.methods
+                    .retain(|method| is_enabled(method.attributes(), options));
+                Item::Impl(imp)
+            }
+            Item::Trait(mut tr) => {
+                tr.methods
+                    .retain(|method| is_enabled(method.function.attributes(), options));
+                Item::Trait(tr)
+            }
+            Item::Actor(mut actor) => {
+                actor
+                    .methods
+                    .retain(|method| is_enabled(method.attributes(), options));
+                Item::Actor(actor)
+            }
+            item => item,
+        })
+        .collect();
+
+    Program { items, lines }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+}
+
+/// The `[features]` table of `<root>/veyra.toml`: each feature maps to the
+/// features it also enables, and `default` lists those on unless disabled
+pub fn declared_features(root: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let path = root.join("veyra.toml");
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| VeyraError::IoError(format!("Failed to read '{}': {}", path.display(), e)))?;
+    let manifest: Manifest = toml::from_str(&content)
+        .map_err(|e| VeyraError::ConfigError(format!("Invalid '{}': {}", path.display(), e)))?;
+    Ok(manifest.features)
+}
+
+/// Expand the requested features (plus `default`, unless disabled) through
+/// the declared table. Requesting an undeclared feature is an error.
+pub fn resolve_features(
+    declared: &BTreeMap<String, Vec<String>>,
+    requested: &[String],
+    default_features: bool,
+) -> Result<BTreeSet<String>> {
+    let mut pending: Vec<String> = requested.to_vec();
+    if default_features {
+        pending.extend(declared.get("default").into_iter().flatten().cloned());
+    }
+
+    let mut enabled = BTreeSet::new();
+    while let Some(feature) = pending.pop() {
+        let Some(implied) = declared.get(&feature) else {
+            let names: Vec<&str> = declared
+                .keys()
+                .map(String::as_str)
+                .filter(|name| *name != "default")
+                .collect();
+            return Err(VeyraError::ConfigError(format!(
+                "Unknown feature '{}'{}",
+                feature,
+                crate::suggest::did_you_mean(&crate::suggest::similar_names(&feature, names))
+            )));
+        };
+        if enabled.insert(feature) {
+            pending.extend(implied.iter().cloned());
+        }
+    }
+
+    enabled.remove("default");
+    Ok(enabled)
+}
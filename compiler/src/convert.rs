@@ -0,0 +1,337 @@
+// Conversions between Rust values and interpreter values
+//
+// Embedders pass arguments to `Interpreter::call` and read what Veyra code
+// returns. `Value` implements `From` for Rust numbers, strings, chars,
+// bools, vectors, string-keyed maps, sets of strings, options and tuples,
+// and `TryFrom<Value>` converts back, failing with a type error that names
+// what was found instead.
+//
+// Other types go through serde: `to_value` and `from_value` convert
+// anything that implements `Serialize` or `Deserialize`, such as a struct
+// of the embedder's, using the same data model as JSON. Rust structs and
+// maps become dictionaries, and dictionaries and Veyra structs fill in Rust
+// structs. As in JSON, `Some(x)` is just `x`, and results are `{"Ok": x}`
+// or `{"Err": e}`.
+
+use crate::ast::Variant;
+use crate::error::{Result, VeyraError};
+use crate::interpreter::{Interpreter, Value};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+fn mismatch(expected: &str, found: &Value) -> VeyraError {
+    VeyraError::type_error(format!("Expected {}, got {}", expected, found.type_name()))
+}
+
+macro_rules! integer_conversions {
+    ($($int:ty),*) => {$(
+        impl TryFrom<Value> for $int {
+            type Error = VeyraError;
+
+            fn try_from(value: Value) -> Result<Self> {
+                match value {
+                    Value::Integer(n) => n.try_into().map_err(|_| {
+                        VeyraError::type_error(format!(
+                            "{} does not fit in {}",
+                            n,
+                            stringify!($int)
+                        ))
+                    }),
+                    other => Err(mismatch("int", &other)),
+                }
+            }
+        }
+    )*};
+}
+
+integer_conversions!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+macro_rules! lossless_integers {
+    ($($int:ty),*) => {$(
+        impl From<$int> for Value {
+            fn from(n: $int) -> Self {
+                Value::Integer(n.into())
+            }
+        }
+    )*};
+}
+
+lossless_integers!(i8, i16, i32, i64, u8, u16, u32);
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Value::Float(f.into())
+    }
+}
+
+/// Integers widen to floats, as they do in arithmetic
+impl TryFrom<Value> for f64 {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Float(f) => Ok(f),
+            Value::Integer(n) => Ok(n as f64),
+            other => Err(mismatch("float", &other)),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Boolean(b)
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            other => Err(mismatch("bool", &other)),
+        }
+    }
+}
+
+impl From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::Char(c)
+    }
+}
+
+impl TryFrom<Value> for char {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Char(c) => Ok(c),
+            other => Err(mismatch("char", &other)),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => Err(mismatch("string", &other)),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Tuples convert too, since they hold their items in order
+impl<T: TryFrom<Value, Error = VeyraError>> TryFrom<Value> for Vec<T> {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Array(items) | Value::Tuple(items) => {
+                items.into_iter().map(T::try_from).collect()
+            }
+            other => Err(mismatch("array", &other)),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(entries: HashMap<String, T>) -> Self {
+        Value::Dictionary(entries.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+impl<T: TryFrom<Value, Error = VeyraError>> TryFrom<Value> for HashMap<String, T> {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Dictionary(entries) => entries
+                .into_iter()
+                .map(|(k, v)| Ok((Interpreter::value_to_string(&k), T::try_from(v)?)))
+                .collect(),
+            other => Err(mismatch("dictionary", &other)),
+        }
+    }
+}
+
+impl From<HashSet<String>> for Value {
+    fn from(items: HashSet<String>) -> Self {
+        Value::Set(items.into_iter().map(|k| (k, ())).collect())
+    }
+}
+
+impl TryFrom<Value> for HashSet<String> {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Set(items) => Ok(items.keys().map(Interpreter::value_to_string).collect()),
+            other => Err(mismatch("set", &other)),
+        }
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(value) => Value::Variant(Variant::Some, Box::new(value.into())),
+            None => Value::None,
+        }
+    }
+}
+
+/// `None` is `None` and `Some(x)` is `Some(x)`. Any other value is taken as
+/// present, since Veyra code often returns either a value or `None`.
+impl<T: TryFrom<Value, Error = VeyraError>> TryFrom<Value> for Option<T> {
+    type Error = VeyraError;
+
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::None => Ok(None),
+            Value::Variant(Variant::Some, value) => T::try_from(*value).map(Some),
+            other => T::try_from(other).map(Some),
+        }
+    }
+}
+
+macro_rules! tuple_conversions {
+    ($len:literal: $($name:ident),*) => {
+        impl<$($name: Into<Value>),*> From<($($name,)*)> for Value {
+            #[allow(non_snake_case)]
+            fn from(($($name,)*): ($($name,)*)) -> Self {
+                Value::Tuple(vec![$($name.into()),*])
+            }
+        }
+
+        impl<$($name: TryFrom<Value, Error = VeyraError>),*> TryFrom<Value> for ($($name,)*) {
+            type Error = VeyraError;
+
+            #[allow(non_snake_case)]
+            fn try_from(value: Value) -> Result<Self> {
+                match value {
+                    Value::Tuple(items) if items.len() == $len => {
+                        let mut items = items.into_iter();
+                        $(let $name = $name::try_from(items.next().unwrap())?;)*
+                        Ok(($($name,)*))
+                    }
+                    other => Err(mismatch(concat!("tuple of ", $len), &other)),
+                }
+            }
+        }
+    };
+}
+
+tuple_conversions!(2: A, B);
+tuple_conversions!(3: A, B, C);
+
+/// Convert anything serde can serialize
+pub fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    let json = serde_json::to_value(value)
+        .map_err(|e| VeyraError::type_error(format!("Cannot convert to a value: {}", e)))?;
+    Ok(from_json(json))
+}
+
+/// Convert to anything serde can deserialize
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T> {
+    serde_json::from_value(to_json(value)?)
+        .map_err(|e| VeyraError::type_error(format!("Cannot convert value: {}", e)))
+}
+
+fn from_json(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::None,
+        serde_json::Value::Bool(b) => Value::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(n) => Value::Integer(n),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => Value::Array(items.into_iter().map(from_json).collect()),
+        serde_json::Value::Object(entries) => Value::Dictionary(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k, from_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn to_json(value: Value) -> Result<serde_json::Value> {
+    let array = |items: Vec<Value>| {
+        items
+            .into_iter()
+            .map(to_json)
+            .collect::<Result<Vec<_>>>()
+            .map(serde_json::Value::Array)
+    };
+    let object = |entries: Vec<(String, Value)>| {
+        entries
+            .into_iter()
+            .map(|(k, v)| Ok((k, to_json(v)?)))
+            .collect::<Result<serde_json::Map<_, _>>>()
+            .map(serde_json::Value::Object)
+    };
+
+    Ok(match value {
+        Value::Integer(n) => n.into(),
+        Value::Float(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| VeyraError::type_error(format!("Cannot convert {}", f)))?,
+        Value::String(s) => s.into(),
+        Value::Char(c) => c.to_string().into(),
+        Value::Boolean(b) => b.into(),
+        Value::None => serde_json::Value::Null,
+        Value::Array(items) | Value::Tuple(items) => array(items)?,
+        Value::Set(items) => {
+            let mut items: Vec<String> = items.keys().map(Interpreter::value_to_string).collect();
+            items.sort();
+            items.into()
+        }
+        Value::Dictionary(entries) => object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (Interpreter::value_to_string(&k), v))
+                .collect(),
+        )?,
+        Value::Struct(instance) => object(instance.fields)?,
+        Value::Reference(reference) => to_json(reference.value.borrow().clone())?,
+        Value::Variant(Variant::Some, value) => to_json(*value)?,
+        Value::Variant(variant, value) => {
+            let name = if variant == Variant::Ok { "Ok" } else { "Err" };
+            object(vec![(name.to_string(), *value)])?
+        }
+        other @ (Value::Weak(_) | Value::Task(_) | Value::Actor(_) | Value::Function(_)) => {
+            return Err(VeyraError::type_error(format!(
+                "Cannot convert a {}",
+                other.type_name()
+            )))
+        }
+    })
+}
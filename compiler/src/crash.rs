@@ -0,0 +1,232 @@
+// Crash reports
+//
+// A panic in veyc is a bug in Veyra, not in the program it was given.
+// `install` replaces Rust's panic message with a short explanation and
+// writes a report with what is needed to reproduce the crash: the version,
+// the command line, the backtrace and a hash of the input file. When the
+// input alone also crashes the parser, `minimize_parser_crash` shrinks it to
+// a small file that crashes it in the same place, which is far easier to
+// attach to an issue than the original program.
+
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use sha2::{Digest, Sha256};
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ISSUES_URL: &str = "https://github.com/k6w/veyra/issues";
+
+/// Upper bound on the parses tried by each shrinking pass, so minimizing a
+/// large file cannot take long
+const MAX_ATTEMPTS: usize = 2000;
+
+/// Set while candidate inputs are tried; their panics are expected and only
+/// their location is recorded
+static MINIMIZING: AtomicBool = AtomicBool::new(false);
+static LAST_LOCATION: Mutex<Option<String>> = Mutex::new(None);
+
+/// The report written for the first crash
+static REPORT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Report panics instead of printing Rust's panic message. `input` is the
+/// file being processed, if any.
+pub fn install(input: Option<PathBuf>) {
+    let command = std::env::args().collect::<Vec<_>>().join(" ");
+    panic::set_hook(Box::new(move |info| {
+        let location = location(info);
+        if MINIMIZING.load(Ordering::SeqCst) {
+            if let Ok(mut last) = LAST_LOCATION.lock() {
+                *last = Some(location);
+            }
+            return;
+        }
+
+        let message = message(info);
+        let report = report(&command, input.as_deref(), &message, &location);
+        eprintln!("error: veyc crashed: {} ({})", message, location);
+        match write_report(&report) {
+            Ok(path) => {
+                eprintln!("This is a bug in Veyra. A crash report was written to");
+                eprintln!("  {}", path.display());
+                eprintln!("Please open an issue at {} and attach it.", ISSUES_URL);
+                if let Ok(mut written) = REPORT.lock() {
+                    written.get_or_insert(path);
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "This is a bug in Veyra. The crash report could not be saved ({}):",
+                    e
+                );
+                eprintln!("{}", report);
+                eprintln!("Please open an issue at {} and include it.", ISSUES_URL);
+            }
+        }
+    }));
+}
+
+fn message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+fn location(info: &PanicHookInfo) -> String {
+    info.location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown location".to_string())
+}
+
+fn report(command: &str, input: Option<&Path>, message: &str, location: &str) -> String {
+    let thread = std::thread::current();
+    let mut report = String::new();
+    let _ = writeln!(report, "veyc {} crashed", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "command: {}", command);
+    let _ = writeln!(
+        report,
+        "platform: {}-{}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    if let Some(input) = input {
+        let hash = fs::read(input)
+            .map(|bytes| hex(&Sha256::digest(bytes)))
+            .unwrap_or_else(|_| "unreadable".to_string());
+        let _ = writeln!(report, "input: {} (sha256 {})", input.display(), hash);
+    }
+    let _ = writeln!(
+        report,
+        "panic: {} at {} in thread '{}'",
+        message,
+        location,
+        thread.name().unwrap_or("<unnamed>")
+    );
+    let _ = writeln!(report, "\nbacktrace:\n{}", Backtrace::force_capture());
+    report
+}
+
+/// Crash reports go to the user's cache directory, or the temporary
+/// directory where there is none
+fn crash_dir() -> PathBuf {
+    dirs::cache_dir()
+        .map(|dir| dir.join("veyra"))
+        .unwrap_or_else(|| std::env::temp_dir().join("veyra"))
+        .join("crashes")
+}
+
+fn write_report(report: &str) -> std::io::Result<PathBuf> {
+    let dir = crash_dir();
+    fs::create_dir_all(&dir)?;
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("veyc-{}-{}.txt", seconds, std::process::id()));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// After a crash: if parsing `input` on its own crashes, save the smallest
+/// source found that crashes the parser at the same place next to the
+/// report
+pub fn minimize_parser_crash(input: &Path) {
+    minimize_crash(input, |source| {
+        if let Ok(tokens) = Lexer::new(source).tokenize() {
+            let _ = Parser::new(tokens).parse();
+        }
+    });
+}
+
+/// After a crash: if `run` panics on the contents of `input`, save the
+/// smallest source found that makes it panic at the same place next to the
+/// report, and give its path
+pub fn minimize_crash(input: &Path, run: impl Fn(&str)) -> Option<PathBuf> {
+    let report = report_path()?;
+    let source = fs::read_to_string(input).ok()?;
+
+    MINIMIZING.store(true, Ordering::SeqCst);
+    let minimized = crash_site(&source, &run).map(|site| {
+        let crashes = |candidate: &str| crash_site(candidate, &run).as_ref() == Some(&site);
+        let lines = shrink(source.lines().collect(), |lines| crashes(&lines.join("\n")));
+        let chars = shrink(lines.join("\n").chars().collect(), |chars| {
+            crashes(&chars.iter().collect::<String>())
+        });
+        chars.into_iter().collect::<String>()
+    });
+    MINIMIZING.store(false, Ordering::SeqCst);
+
+    let minimized = minimized?;
+    let path = report.with_extension("min.vey");
+    fs::write(&path, &minimized).ok()?;
+    let _ = fs::OpenOptions::new()
+        .append(true)
+        .open(&report)
+        .and_then(|mut file| writeln!(file, "\nminimized input: {}", path.display()));
+    eprintln!(
+        "The crash also happens on a {}-byte version of the input, saved to",
+        minimized.len()
+    );
+    eprintln!("  {}", path.display());
+    Some(path)
+}
+
+/// The report written for the first crash since `install`, if any
+pub fn report_path() -> Option<PathBuf> {
+    REPORT.lock().ok().and_then(|report| report.clone())
+}
+
+/// Where `run` panics on `source`, if it does
+fn crash_site(source: &str, run: &impl Fn(&str)) -> Option<String> {
+    if let Ok(mut last) = LAST_LOCATION.lock() {
+        *last = None;
+    }
+    let crashed = panic::catch_unwind(AssertUnwindSafe(|| run(source))).is_err();
+    if !crashed {
+        return None;
+    }
+    LAST_LOCATION.lock().ok().and_then(|last| last.clone())
+}
+
+/// Remove ever smaller runs of items for as long as what is left still
+/// crashes
+fn shrink<T: Clone>(mut items: Vec<T>, crashes: impl Fn(&[T]) -> bool) -> Vec<T> {
+    let mut chunk = items.len() / 2;
+    let mut attempts = 0;
+    while chunk > 0 && attempts < MAX_ATTEMPTS {
+        let mut removed = false;
+        let mut start = 0;
+        while start < items.len() && attempts < MAX_ATTEMPTS {
+            attempts += 1;
+            let end = (start + chunk).min(items.len());
+            let candidate: Vec<T> = items[..start]
+                .iter()
+                .chain(&items[end..])
+                .cloned()
+                .collect();
+            if crashes(&candidate) {
+                items = candidate;
+                removed = true;
+            } else {
+                start = end;
+            }
+        }
+        if !removed {
+            chunk /= 2;
+        }
+    }
+    items
+}
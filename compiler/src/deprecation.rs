@@ -0,0 +1,87 @@
+// Deprecation annotations
+//
+// `@deprecated` or `@deprecated("use new_fn")` on a function or struct marks
+// it as deprecated. The program still runs unchanged; the linter and the
+// language server report each place the item is used.
+
+use crate::ast::{Attribute, AttributeArg, Attributed, Item, Literal, Program};
+use crate::lexer::{RangedToken, TokenKind};
+use std::ops::Range;
+
+/// A deprecated function or struct and its optional note
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deprecation {
+    pub name: String,
+    pub kind: &'static str,
+    pub note: Option<String>,
+}
+
+impl Deprecation {
+    /// Message for a use of the item, e.g. `Function 'old' is deprecated: use new`
+    #[allow(dead_code)]
+    pub fn message(&self) -> String {
+        let mut message = format!("{} '{}' is deprecated", self.kind, self.name);
+        if let Some(note) = &self.note {
+            message.push_str(": ");
+            message.push_str(note);
+        }
+        message
+    }
+}
+
+/// Check that `@deprecated` has at most one argument, a string note
+pub fn check_attribute(attribute: &Attribute) -> std::result::Result<(), String> {
+    match attribute.args.as_slice() {
+        [] | [AttributeArg::Literal(Literal::String(_))] => Ok(()),
+        _ => {
+            Err("@deprecated takes an optional note, e.g. @deprecated(\"use new_fn\")".to_string())
+        }
+    }
+}
+
+/// Top-level functions and structs marked `@deprecated`
+#[allow(dead_code)]
+pub fn deprecations(program: &Program) -> Vec<Deprecation> {
+    program
+        .items
+        .iter()
+        .filter_map(|item| {
+            let (name, kind) = match item {
+                Item::Function(func) => (&func.name, "Function"),
+                Item::Struct(s) => (&s.name, "Struct"),
+                _ => return None,
+            };
+            let attribute = item.attribute("deprecated")?;
+            Some(Deprecation {
+                name: name.clone(),
+                kind,
+                note: attribute.string_arg().map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+/// Uses of deprecated items in a ranged token stream, as the index of the
+/// deprecation and the character range of the identifier. Declarations and
+/// `.name` members are not uses.
+#[allow(dead_code)]
+pub fn uses(tokens: &[RangedToken], deprecations: &[Deprecation]) -> Vec<(usize, Range<usize>)> {
+    let mut found = Vec::new();
+    for (i, (token, range)) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Identifier {
+            continue;
+        }
+        let Some(index) = deprecations.iter().position(|d| d.name == token.lexeme) else {
+            continue;
+        };
+        let previous = i.checked_sub(1).map(|p| &tokens[p].0.kind);
+        if matches!(
+            previous,
+            Some(TokenKind::Fn | TokenKind::Struct | TokenKind::Dot | TokenKind::QuestionDot)
+        ) {
+            continue;
+        }
+        found.push((index, range.clone()));
+    }
+    found
+}
@@ -0,0 +1,272 @@
+// Structured diagnostics for machine-readable compiler output
+// Each diagnostic serializes to a single JSON object so build systems and
+// editors can consume `--message-format json` line by line. For people,
+// `render` lays the same diagnostic out the way rustc does, under the line
+// of source it points at.
+
+use crate::error::{Found, VeyraError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[allow(dead_code)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// Source lines longer than this many characters are cut around the caret
+const MAX_LINE_WIDTH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Span {
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// How many characters from the column the span covers, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: String,
+    /// The stable error code of `code`, such as E0002, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+    /// What the span holds, shown next to the caret under it, such as the
+    /// token a parse error found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    /// An error with the diagnostic name `code`. Names suggested at the end
+    /// of `message`, as in `'totl' is not defined (did you mean 'total'?)`,
+    /// become the suggestion.
+    pub fn error(code: impl Into<String>, message: impl Into<String>) -> Self {
+        let code = code.into();
+        let mut message = message.into();
+        let suggestion = match message.rsplit_once(" (did you mean ") {
+            Some((rest, names)) if names.ends_with(')') => {
+                let suggestion = format!("did you mean {}", &names[..names.len() - 1]);
+                message.truncate(rest.len());
+                Some(suggestion)
+            }
+            _ => None,
+        };
+        Self {
+            severity: Severity::Error,
+            error_code: crate::explain::code_for(&code),
+            code,
+            message,
+            span: None,
+            label: None,
+            suggestion,
+        }
+    }
+
+    /// Attach the source location; line and column may be unknown
+    pub fn with_span(mut self, file: &Path, line: Option<usize>, column: Option<usize>) -> Self {
+        self.span = Some(Span {
+            file: file.display().to_string(),
+            line,
+            column,
+            width: None,
+        });
+        self
+    }
+
+    /// Label what the span holds, which is `width` characters wide
+    pub fn with_label(mut self, label: impl Into<String>, width: usize) -> Self {
+        self.label = Some(label.into());
+        if let Some(span) = &mut self.span {
+            span.width = Some(width);
+        }
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn with_suggestion(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+
+    /// Build a diagnostic from a compiler error raised while processing `file`
+    pub fn from_error(error: &VeyraError, file: &Path) -> Self {
+        let (message, line, column) = match error {
+            VeyraError::LexError {
+                line,
+                column,
+                message,
+            } => (message.clone(), Some(*line), Some(*column)),
+            // Parse errors name the token they stopped at, which reads
+            // better under the caret than at the end of the message
+            VeyraError::ParseError {
+                line,
+                column,
+                message,
+                found,
+            } => {
+                let diagnostic = Self::error(error.name(), message.clone()).with_span(
+                    file,
+                    Some(*line),
+                    Some(*column),
+                );
+                let width = match found {
+                    Some(Found::Token(lexeme)) => lexeme.chars().count(),
+                    _ => 1,
+                };
+                return match found {
+                    Some(found) => diagnostic.with_label(format!("found {}", found), width),
+                    None => diagnostic,
+                };
+            }
+            VeyraError::TypeError { message }
+            | VeyraError::RuntimeError { message }
+            | VeyraError::Halted { message }
+            | VeyraError::Thrown { message } => (message.clone(), None, None),
+            VeyraError::InModule { file, error } => return Self::from_error(error, file),
+            VeyraError::AtLine { line, error } => {
+                let mut diagnostic = Self::from_error(error, file);
+                if let Some(span) = &mut diagnostic.span {
+                    span.line = Some(*line);
+                }
+                return diagnostic;
+            }
+            VeyraError::Unwind(_) | VeyraError::Exit(_) => (error.to_string(), None, None),
+            VeyraError::IoError(message)
+            | VeyraError::ConfigError(message)
+            | VeyraError::InternalError(message) => (message.clone(), None, None),
+        };
+
+        Self::error(error.name(), message).with_span(file, line, column)
+    }
+
+    /// Render for a terminal: the message, where it happened, the offending
+    /// line of `source` with a caret under the position, and any notes.
+    /// Without a position or source, only the message and file are shown.
+    pub fn render(&self, source: Option<&str>) -> String {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+
+        let mut out = match self.error_code {
+            Some(code) => format!("{}[{}]: {}\n", severity, code, self.message),
+            None => format!("{}: {}\n", severity, self.message),
+        };
+        // Runtime errors know their line but not their column
+        let position = self
+            .span
+            .as_ref()
+            .and_then(|span| Some((span.line?, span.column)));
+        // The line number gutter is as wide as the number
+        let gutter = " ".repeat(position.map_or(1, |(line, _)| line.to_string().len()));
+
+        if let Some(span) = self.span.as_ref().filter(|span| !span.file.is_empty()) {
+            out.push_str(&format!("{}--> {}", gutter, span.file));
+            match position {
+                Some((line, Some(column))) => out.push_str(&format!(":{}:{}", line, column)),
+                Some((line, None)) => out.push_str(&format!(":{}", line)),
+                None => {}
+            }
+            out.push('\n');
+        }
+
+        let snippet = position.zip(source).and_then(|((line, column), source)| {
+            let text = source.split('\n').nth(line.checked_sub(1)?)?;
+            Some((line, column, text.trim_end_matches('\r')))
+        });
+        if let Some((line, column, text)) = snippet {
+            let (column, width) = match column {
+                Some(column) => (column, self.span.as_ref().and_then(|span| span.width)),
+                // Without a column, the code on the line is underlined
+                None => {
+                    let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+                    (indent + 1, Some(text.trim().chars().count()))
+                }
+            };
+            // Tabs are shown as four spaces, so the caret moves with them
+            let before: String = text.chars().take(column.saturating_sub(1)).collect();
+            let caret =
+                before.chars().filter(|&c| c == '\t').count() * 3 + column.saturating_sub(1);
+            let shown: Vec<char> = text.replace('\t', "    ").chars().collect();
+
+            // A long line is cut to the part around the caret
+            let (mut start, mut end) = (0, shown.len());
+            if end > MAX_LINE_WIDTH {
+                start = caret
+                    .saturating_sub(MAX_LINE_WIDTH / 2)
+                    .min(end - MAX_LINE_WIDTH);
+                end = start + MAX_LINE_WIDTH;
+            }
+            let mut code: String = shown[start..end].iter().collect();
+            let mut indent = caret - start.min(caret);
+            if start > 0 {
+                code.insert_str(0, "...");
+                indent += 3;
+            }
+            if end < shown.len() {
+                code.push_str("...");
+            }
+            let width = width.unwrap_or(1).min(end.saturating_sub(caret)).max(1);
+
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} | {}\n", line, code));
+            out.push_str(
+                format!(
+                    "{} | {}{} {}",
+                    gutter,
+                    " ".repeat(indent),
+                    "^".repeat(width),
+                    self.label.as_deref().unwrap_or_default()
+                )
+                .trim_end(),
+            );
+            out.push('\n');
+        }
+
+        if self.code == "uncaught-exception" {
+            out.push_str(&format!(
+                "{} = note: the value was thrown and no try block caught it\n",
+                gutter
+            ));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            out.push_str(&format!("{} = help: {}\n", gutter, suggestion));
+        }
+        out
+    }
+
+    /// Serialize as a single line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(span) = &self.span {
+            write!(f, "{}", span.file)?;
+            match (span.line, span.column) {
+                (Some(line), Some(column)) => write!(f, ":{}:{}", line, column)?,
+                (Some(line), None) => write!(f, ":{}", line)?,
+                _ => {}
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (help: {})", suggestion)?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,11 +12,16 @@ pub enum VeyraError {
         message: String,
     },
 
-    #[error("Parser error at line {line}, column {column}: {message}")]
+    #[error(
+        "Parser error at line {line}, column {column}: {message}{}",
+        .found.as_ref().map(|found| format!(", found {}", found)).unwrap_or_default()
+    )]
     ParseError {
         line: usize,
         column: usize,
         message: String,
+        /// What the parser stopped at
+        found: Option<Found>,
     },
 
     #[error("Type Error: {message}")]
@@ -24,15 +30,101 @@ pub enum VeyraError {
     #[error("Runtime Error: {message}")]
     RuntimeError { message: String },
 
+    /// A resource limit was exceeded or the program was interrupted. Unlike
+    /// other runtime errors, `catch` does not stop it.
+    #[error("Runtime Error: {message}")]
+    Halted { message: String },
+
+    /// A value thrown with `throw` that no `catch` handled. The interpreter
+    /// keeps the value; this holds how it prints.
+    #[error("Uncaught exception: {message}")]
+    Thrown { message: String },
+
+    /// `exit(code)` ending the program. Like `Halted`, `catch` does not stop
+    /// it.
+    #[error("Program exited with code {0}")]
+    Exit(i32),
+
+    /// `return`, `break` or `continue` on its way to the call or loop that
+    /// ends it. It only surfaces as an error outside a function or loop.
+    #[error("'{0}' outside of a {scope}", scope = .0.scope())]
+    Unwind(Unwind),
+
+    /// An error in the source of a module other than the file being run,
+    /// such as a syntax error in an imported module, which diagnostics point
+    /// at instead
+    #[error("{error} in {}", file.display())]
+    InModule {
+        file: PathBuf,
+        error: Box<VeyraError>,
+    },
+
+    /// A runtime error with the source line of the statement, or in
+    /// compiled code of the instruction, that raised it. It prints as the
+    /// error alone; diagnostics point at the line.
+    #[error("{error}")]
+    AtLine { line: usize, error: Box<VeyraError> },
+
     #[error("IO Error: {0}")]
     IoError(String),
 
+    #[error("Configuration Error: {0}")]
+    ConfigError(String),
+
     #[error("Compiler Error: {0}")]
     InternalError(String),
 }
 
 pub type Result<T> = std::result::Result<T, VeyraError>;
 
+/// What a parser stopped at, named under the caret of its error
+#[derive(Debug, Clone, PartialEq)]
+pub enum Found {
+    /// A token, as written in the source
+    Token(String),
+    EndOfLine,
+    EndOfFile,
+}
+
+impl std::fmt::Display for Found {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Found::Token(lexeme) => write!(f, "'{}'", lexeme),
+            Found::EndOfLine => write!(f, "end of line"),
+            Found::EndOfFile => write!(f, "end of file"),
+        }
+    }
+}
+
+/// The statements that leave a function or loop early
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unwind {
+    Return,
+    Break,
+    Continue,
+}
+
+impl Unwind {
+    /// What the statement must be inside of
+    fn scope(self) -> &'static str {
+        match self {
+            Unwind::Return => "function",
+            Unwind::Break | Unwind::Continue => "loop",
+        }
+    }
+}
+
+impl std::fmt::Display for Unwind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keyword = match self {
+            Unwind::Return => "return",
+            Unwind::Break => "break",
+            Unwind::Continue => "continue",
+        };
+        write!(f, "{}", keyword)
+    }
+}
+
 impl VeyraError {
     pub fn lex_error(line: usize, column: usize, message: impl Into<String>) -> Self {
         VeyraError::LexError {
@@ -47,6 +139,7 @@ impl VeyraError {
             line,
             column,
             message: message.into(),
+            found: None,
         }
     }
 
@@ -62,4 +155,69 @@ impl VeyraError {
             message: message.into(),
         }
     }
+
+    pub fn halted(message: impl Into<String>) -> Self {
+        VeyraError::Halted {
+            message: message.into(),
+        }
+    }
+
+    /// The diagnostic name the error is reported with, such as `parse-error`
+    pub fn name(&self) -> &'static str {
+        match self {
+            VeyraError::LexError { .. } => "lex-error",
+            VeyraError::ParseError { .. } => "parse-error",
+            VeyraError::TypeError { .. } => "type-error",
+            VeyraError::RuntimeError { .. } => "runtime-error",
+            VeyraError::Halted { .. } => "halted",
+            VeyraError::Thrown { .. } => "uncaught-exception",
+            VeyraError::Exit(_) => "exit",
+            VeyraError::Unwind(_) => "misplaced-control-flow",
+            VeyraError::InModule { error, .. } | VeyraError::AtLine { error, .. } => error.name(),
+            VeyraError::IoError(_) => "io-error",
+            VeyraError::ConfigError(_) => "config-error",
+            VeyraError::InternalError(_) => "internal-error",
+        }
+    }
+
+    /// The stable error code, such as E0002, that `veyc explain` describes
+    pub fn code(&self) -> &'static str {
+        crate::explain::code_for(self.name()).unwrap_or("E0011")
+    }
+
+    /// The error located at `line`, if it is a runtime error and `line` is
+    /// known. An error that already has a line keeps it: it is the line of
+    /// the innermost statement, inside a call the code at `line` made.
+    pub fn at_line(self, line: Option<u32>) -> Self {
+        let locatable = matches!(
+            self,
+            VeyraError::TypeError { .. }
+                | VeyraError::RuntimeError { .. }
+                | VeyraError::Halted { .. }
+                | VeyraError::Thrown { .. }
+                | VeyraError::IoError(_)
+        );
+        match line {
+            Some(line) if locatable => VeyraError::AtLine {
+                line: line as usize,
+                error: Box::new(self),
+            },
+            _ => self,
+        }
+    }
+
+    /// The message a `catch` clause sees for this error, or None if `catch`
+    /// cannot stop it
+    pub fn catchable_message(&self) -> Option<&str> {
+        match self {
+            VeyraError::TypeError { message }
+            | VeyraError::RuntimeError { message }
+            | VeyraError::Thrown { message } => Some(message),
+            VeyraError::IoError(message) => Some(message),
+            VeyraError::InModule { error, .. } | VeyraError::AtLine { error, .. } => {
+                error.catchable_message()
+            }
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,90 @@
+// Event log shared by the Veyra tools
+//
+// With `--log-json`, veyc, veyra-lint, `veyra-fmt --check` and `veyra-pkg
+// test` report as events instead of their usual output: one JSON object per
+// line on stdout, with the same fields whichever tool wrote it, so a CI
+// pipeline can collect every tool's output into one log and aggregate it
+// without parsing each tool's messages.
+//
+// An event names the tool, the file it concerns, where in the file, how
+// severe it is and what happened. Events that finish a piece of work, such
+// as a test or a whole run of a tool, also say how long it took. Fields
+// that do not apply are null.
+
+use crate::diagnostic::{Diagnostic, Severity};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// The tool that wrote the event, such as `veyc` or `veyra-lint`
+    pub tool: String,
+    pub file: Option<String>,
+    pub span: Option<EventSpan>,
+    pub severity: Severity,
+    pub message: String,
+    /// Milliseconds the work took
+    pub duration_ms: Option<f64>,
+}
+
+/// A 1-based position in the event's file
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EventSpan {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Event {
+    pub fn new(tool: &str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            tool: tool.to_string(),
+            file: None,
+            span: None,
+            severity,
+            message: message.into(),
+            duration_ms: None,
+        }
+    }
+
+    pub fn in_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.display().to_string());
+        self
+    }
+
+    pub fn at(mut self, line: usize, column: usize) -> Self {
+        self.span = Some(EventSpan { line, column });
+        self
+    }
+
+    pub fn took(mut self, duration: Duration) -> Self {
+        self.duration_ms = Some(duration.as_secs_f64() * 1000.0);
+        self
+    }
+
+    /// The event for a diagnostic `tool` reported
+    pub fn from_diagnostic(tool: &str, diagnostic: &Diagnostic) -> Self {
+        let mut message = diagnostic.message.clone();
+        if let Some(suggestion) = &diagnostic.suggestion {
+            message = format!("{} (help: {})", message, suggestion);
+        }
+        let mut event = Self::new(tool, diagnostic.severity, message);
+        if let Some(span) = &diagnostic.span {
+            event.file = Some(span.file.clone());
+            if let (Some(line), Some(column)) = (span.line, span.column) {
+                event = event.at(line, column);
+            }
+        }
+        event
+    }
+
+    /// Serialize as a single line of JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Write the event to the log on stdout
+    pub fn emit(&self) {
+        println!("{}", self.to_json());
+    }
+}
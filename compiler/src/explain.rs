@@ -0,0 +1,298 @@
+// Stable error codes, explained by `veyc explain`
+//
+// Every kind of error veyc reports has a code, E0001 and up, shown in its
+// messages as `error[E0002]` and in JSON diagnostics as `error_code`, so
+// documentation, editors and CI can refer to an error without matching its
+// message. Each code stands for one diagnostic name (`parse-error`,
+// `undefined-name`, ...), the name `--message-format json` and the editor
+// settings already use. Codes are only ever added: a number is never reused
+// or given to another kind of error.
+
+pub struct ErrorCode {
+    pub code: &'static str,
+    /// The diagnostic name the error is reported with
+    pub name: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    /// A program that stops with, or is checked to have, this error
+    pub example: Option<&'static str>,
+    /// The example with the mistake fixed
+    pub fixed: Option<&'static str>,
+}
+
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        name: "lex-error",
+        title: "the source contains text that is not a token",
+        description: "Before parsing, the source is split into tokens: names, numbers, strings, \
+                      operators and punctuation. This error means some text could not be read as \
+                      any of them, usually a string or character literal missing its closing \
+                      quote, an invalid escape sequence or a character the language does not \
+                      use. The position is where reading stopped, which for an unclosed string \
+                      can be the end of the file.",
+        example: Some("print(\"hello)\n"),
+        fixed: Some("print(\"hello\")\n"),
+    },
+    ErrorCode {
+        code: "E0002",
+        name: "parse-error",
+        title: "the program does not follow the grammar",
+        description: "The tokens are valid but not in an order the grammar accepts, such as a \
+                      missing closing bracket, a keyword where a name belongs or an operator \
+                      with nothing after it. The message says what was expected and the caret \
+                      points at what was found instead; the mistake is often just before it. \
+                      `veyc check` reports every syntax error in a file at once, and `veyc \
+                      grammar` prints the full grammar.",
+        example: Some("fn add(a, b {\n    return a + b\n}\n"),
+        fixed: Some("fn add(a, b) {\n    return a + b\n}\n"),
+    },
+    ErrorCode {
+        code: "E0003",
+        name: "type-error",
+        title: "a value could not be converted for the program embedding Veyra",
+        description: "Rust programs that embed the interpreter convert Veyra values to Rust \
+                      types, for instance the result of `Interpreter::call`. This error means \
+                      the value had another type, or did not fit: a string where an integer was \
+                      expected, or 300 converted to a `u8`. Veyra programs themselves report \
+                      mismatched types as E0004 when they run, or as E0013 from `veyc check`.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0004",
+        name: "runtime-error",
+        title: "the program stopped with an error while running",
+        description: "An operation failed while the program ran: an index past the end of an \
+                      array, a missing dictionary key, a name that is not defined, an \
+                      arithmetic operation on values of the wrong type, or a builtin refusing \
+                      its arguments. `try` and `catch` can handle these errors; uncaught, they \
+                      end the program with exit code 1. Many are found without running the \
+                      program by `veyc check`.",
+        example: Some("let items = [1, 2, 3]\nprint(items[3])\n"),
+        fixed: Some("let items = [1, 2, 3]\nprint(items[len(items) - 1])\n"),
+    },
+    ErrorCode {
+        code: "E0005",
+        name: "halted",
+        title: "the program ran out of time, steps, call depth or output, or was interrupted",
+        description: "Where programs run with resource limits, such as in `veyc serve \
+                      --playground` or `veyc learn`, a program that exceeds one is stopped \
+                      with this error. An endless loop such as\n\n    \
+                      while true {}\n\n\
+                      runs out of time. Code interrupted from a REPL kernel stops with it too. \
+                      Unlike other runtime errors, `catch` cannot handle it. Look for a loop \
+                      whose condition never becomes false or a recursive function that never \
+                      reaches its base case.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0006",
+        name: "uncaught-exception",
+        title: "a value was thrown and nothing caught it",
+        description: "`throw` raises a value as an error. It travels up through the calls in \
+                      progress until a `try` block catches it; if none does, the program ends \
+                      with the thrown value as the message. Catch it where the program can \
+                      recover, or let it end the program on purpose.",
+        example: Some(
+            "fn divide(a, b) {\n    if b == 0 {\n        throw \"cannot divide by zero\"\n    }\n    \
+             return a / b\n}\n\nprint(divide(1, 0))\n",
+        ),
+        fixed: Some(
+            "fn divide(a, b) {\n    if b == 0 {\n        throw \"cannot divide by zero\"\n    }\n    \
+             return a / b\n}\n\ntry {\n    print(divide(1, 0))\n} catch error {\n    print(error)\n}\n",
+        ),
+    },
+    ErrorCode {
+        code: "E0007",
+        name: "misplaced-control-flow",
+        title: "`return`, `break` or `continue` outside of a function or loop",
+        description: "`return` leaves the function it is written in, and `break` and `continue` \
+                      the innermost loop. Used where there is no such function or loop, at the \
+                      top level of a file or in a function but outside any loop, they have \
+                      nothing to leave. To end the program early, use `exit()`.",
+        example: Some("let names = [\"Ada\", \"Grace\"]\nprint(names[0])\nbreak\n"),
+        fixed: Some(
+            "let names = [\"Ada\", \"Grace\"]\nfor name in names {\n    print(name)\n    break\n}\n",
+        ),
+    },
+    ErrorCode {
+        code: "E0008",
+        name: "exit",
+        title: "the program called `exit()`",
+        description: "`exit(code)` ends the program with that exit code. It is not a mistake: \
+                      `veyc run` exits with the code given and reports nothing. Programs that \
+                      run Veyra code themselves, such as a Rust program embedding the \
+                      interpreter, see it as an error carrying the code, so that they can stop \
+                      too; `interpreter::exit_code` turns it back into the exit code.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0009",
+        name: "io-error",
+        title: "a file could not be read or written",
+        description: "veyc could not read a source file, a module it imports or an included \
+                      file, or could not write its output, such as the bytecode of `veyc \
+                      compile`. The message includes the path and the reason the system gave: \
+                      check that the file exists and that veyc may read or write it.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0010",
+        name: "config-error",
+        title: "the project configuration is invalid",
+        description: "`veyra.toml`, or a setting given on the command line, could not be used: \
+                      the file is not valid TOML, a field has the wrong type or a feature \
+                      named in `--features` is not declared. The message names the setting.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0011",
+        name: "internal-error",
+        title: "the compiler failed",
+        description: "Something went wrong inside veyc rather than in the program, such as \
+                      bytecode it cannot read or a value it cannot serialize. Please report it \
+                      with the program that causes it.",
+        example: None,
+        fixed: None,
+    },
+    ErrorCode {
+        code: "E0012",
+        name: "undefined-name",
+        title: "a name is used but never defined",
+        description: "`veyc check` found a variable or function that is not defined where it \
+                      is used: a typo, a variable used outside the block that declares it, or \
+                      a missing import. The message suggests similar names that are in scope. \
+                      Running the program reaches the same mistake as E0004.",
+        example: Some("let total = 3\nprint(totl)\n"),
+        fixed: Some("let total = 3\nprint(total)\n"),
+    },
+    ErrorCode {
+        code: "E0013",
+        name: "type-mismatch",
+        title: "a value does not match its type annotation",
+        description: "`veyc check` found a value whose type differs from the type written for \
+                      it: in a `let` with a type, a parameter with a type or a function's \
+                      return type. Change the value, or the annotation if the value is what \
+                      was meant. Values whose type is not known are not checked.",
+        example: Some("let count: int = \"three\"\n"),
+        fixed: Some("let count: int = 3\n"),
+    },
+    ErrorCode {
+        code: "E0014",
+        name: "unknown-field",
+        title: "a struct has no field of that name",
+        description: "`veyc check` found a field read from a struct that does not declare it. \
+                      The message suggests similar field names.",
+        example: Some(
+            "struct Point {\n    x: int\n    y: int\n}\n\n\
+             let p = Point { x: 1, y: 2 }\nprint(p.z)\n",
+        ),
+        fixed: Some(
+            "struct Point {\n    x: int\n    y: int\n}\n\n\
+             let p = Point { x: 1, y: 2 }\nprint(p.y)\n",
+        ),
+    },
+    ErrorCode {
+        code: "E0015",
+        name: "wrong-arguments",
+        title: "a function is called with the wrong number of arguments",
+        description: "`veyc check` found a call to one of the program's functions or methods \
+                      with more arguments than it has parameters, or fewer than it has \
+                      parameters without a default value.",
+        example: Some("fn add(a, b) {\n    return a + b\n}\n\nprint(add(1))\n"),
+        fixed: Some("fn add(a, b) {\n    return a + b\n}\n\nprint(add(1, 2))\n"),
+    },
+    ErrorCode {
+        code: "E0016",
+        name: "unresolved-import",
+        title: "an imported module was not found",
+        description: "`veyc check --project` found an `import` naming a module it could not \
+                      find. `import shapes.circle` looks for `shapes/circle.vey` next to the \
+                      importing file, then under the project's `src/` and then among its \
+                      installed packages; modules under `std` are built in.",
+        example: None,
+        fixed: None,
+    },
+];
+
+/// The error code `code`, such as `E0007`, `e7` or `0007`, or with the
+/// diagnostic name `code`
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    let digits = code.trim_start_matches(['E', 'e']);
+    ERROR_CODES.iter().find(|error| {
+        error.name == code
+            || (!digits.is_empty()
+                && digits.chars().all(|c| c.is_ascii_digit())
+                && digits.parse::<u32>().ok() == error.code[1..].parse().ok())
+    })
+}
+
+/// The code of errors reported with the diagnostic name `name`
+pub fn code_for(name: &str) -> Option<&'static str> {
+    ERROR_CODES
+        .iter()
+        .find(|error| error.name == name)
+        .map(|error| error.code)
+}
+
+impl ErrorCode {
+    /// The explanation `veyc explain` prints
+    pub fn explanation(&self) -> String {
+        let indent = |source: &str| {
+            source
+                .lines()
+                .map(|line| match line {
+                    "" => "\n".to_string(),
+                    line => format!("    {}\n", line),
+                })
+                .collect::<String>()
+        };
+
+        let mut out = format!("{}: {}\n\n", self.code, self.title);
+        out.push_str(&wrap(self.description, 79));
+        if let Some(example) = self.example {
+            out.push_str("\nErroneous code example:\n\n");
+            out.push_str(&indent(example));
+        }
+        if let Some(fixed) = self.fixed {
+            out.push_str("\nFixed:\n\n");
+            out.push_str(&indent(fixed));
+        }
+        out
+    }
+}
+
+/// Wrap the paragraphs of `text` at `width` columns, leaving indented code
+/// lines as they are
+fn wrap(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    for paragraph in text.split("\n\n") {
+        if paragraph.starts_with("    ") {
+            out.push_str(paragraph);
+            out.push_str("\n\n");
+            continue;
+        }
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        out.push_str(&line);
+        out.push_str("\n\n");
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
@@ -0,0 +1,133 @@
+// Stacks of their own for spawned tasks
+//
+// The interpreter evaluates by recursion, so a task that awaits work that is
+// not finished can only let other tasks run if its calls in progress are
+// kept somewhere. Each task runs as a fiber: a coroutine on a stack of its
+// own, on the interpreter's thread. The code that resumes a fiber gets
+// control back when the fiber suspends at an `await` or finishes, so only
+// one runs at a time and values never leave the thread.
+//
+// A fiber's stack is made the first time it is resumed, so tasks spawned
+// but not started cost only their closure.
+//
+// The fiber's code holds `&mut Interpreter` in the frames of its calls in
+// progress, and the code resuming it holds one too, so the two overlap while
+// the fiber is suspended. Rust's aliasing rules cannot say this is sound, and
+// Miri cannot check it, since it does not run the stack switch. What keeps
+// it correct: only one side runs at a time, each resume hands the fiber the
+// interpreter, and each suspend hands it back, so every write from the other
+// side happens inside the switch, which the compiler has to assume reads and
+// writes anything reachable from the pointer passed through it. A resume must
+// pass the interpreter the fiber started with: `Interpreter::suspend` checks
+// that the pointer it is resumed with is where its frames point.
+
+use crate::error::{Result, VeyraError};
+use crate::interpreter::{Interpreter, Value};
+use corosensei::stack::DefaultStack;
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+
+/// Stack of each fiber, as much as a program's main thread usually has
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// What a suspended fiber waits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wait {
+    /// Another task, which may be waiting too
+    Task,
+    /// Background I/O or a timer, which finishes by itself
+    Io,
+}
+
+/// The outcome of resuming a fiber
+pub(crate) enum Step {
+    Suspended(Wait),
+    Finished(Result<Value>),
+}
+
+/// What a fiber suspends with, and the interpreter it used, handed over so
+/// the compiler knows the code resuming it may change the interpreter
+type Yield = (Wait, *mut Interpreter);
+
+type Body = Box<dyn FnOnce(&mut Interpreter, Suspender) -> Result<Value>>;
+
+pub(crate) struct Fiber {
+    state: State,
+}
+
+enum State {
+    NotStarted(Body),
+    Started(Coroutine<*mut Interpreter, Yield, Result<Value>, DefaultStack>),
+    Finished,
+}
+
+impl Fiber {
+    /// A fiber running `body`, which is given the interpreter and the way
+    /// to suspend the fiber
+    pub(crate) fn new(
+        body: impl FnOnce(&mut Interpreter, Suspender) -> Result<Value> + 'static,
+    ) -> Self {
+        Self {
+            state: State::NotStarted(Box::new(body)),
+        }
+    }
+
+    pub(crate) fn started(&self) -> bool {
+        !matches!(self.state, State::NotStarted(_))
+    }
+
+    /// Run the fiber until it suspends or finishes. Panics in it go on
+    /// unwinding here.
+    pub(crate) fn resume(&mut self, host: &mut Interpreter) -> Step {
+        if let State::NotStarted(_) = self.state {
+            let State::NotStarted(body) = std::mem::replace(&mut self.state, State::Finished)
+            else {
+                unreachable!()
+            };
+            let stack = match DefaultStack::new(STACK_SIZE) {
+                Ok(stack) => stack,
+                Err(e) => {
+                    return Step::Finished(Err(VeyraError::runtime_error(format!(
+                        "Cannot start a task: {}",
+                        e
+                    ))))
+                }
+            };
+            self.state = State::Started(Coroutine::with_stack(
+                stack,
+                move |yielder: &Yielder<*mut Interpreter, Yield>, host: *mut Interpreter| {
+                    // SAFETY: the interpreter resuming the fiber outlives it,
+                    // and only the code running on this stack uses it until
+                    // the fiber suspends, which hands it back (see above)
+                    let host = unsafe { &mut *host };
+                    body(host, Suspender(yielder))
+                },
+            ));
+        }
+        let State::Started(coroutine) = &mut self.state else {
+            return Step::Finished(Err(VeyraError::runtime_error("Task has already finished")));
+        };
+        match coroutine.resume(host) {
+            CoroutineResult::Yield((wait, _)) => Step::Suspended(wait),
+            CoroutineResult::Return(result) => {
+                self.state = State::Finished;
+                Step::Finished(result)
+            }
+        }
+    }
+}
+
+/// How a fiber's own code hands control back to the code that resumed it.
+/// The interpreter keeps it while the fiber's task is the running one.
+#[derive(Clone, Copy)]
+pub(crate) struct Suspender(*const Yielder<*mut Interpreter, Yield>);
+
+impl Suspender {
+    /// Suspend the fiber until it is resumed, handing the interpreter back,
+    /// and return the interpreter it was resumed with. Only the fiber's own
+    /// code may call this, while it runs.
+    pub(crate) fn suspend(self, host: &mut Interpreter, wait: Wait) -> *mut Interpreter {
+        // SAFETY: the yielder lives on the fiber's stack, which exists as
+        // long as the fiber can run, and the caller is that fiber's code
+        unsafe { (*self.0).suspend((wait, host as *mut Interpreter)) }
+    }
+}
@@ -0,0 +1,359 @@
+// Control-flow graphs
+//
+// A `ControlFlowGraph` splits a function body (or top-level code) into basic
+// blocks: runs of straight-line statements ending in a terminator that says
+// where control goes next. `if`, `while`, `for` and `match` become branches,
+// `return` jumps to the exit block, and `break`/`continue` jump to the block
+// after or the head of the innermost loop. A `try` branches to its body and
+// to where an error in it goes, and `throw` jumps there too; `return`,
+// `break` and `continue` inside a `try` are not routed through its finally
+// block.
+//
+// Statements that can never run (after a `return`, or after an `if` whose
+// branches all return) are still placed in blocks, which have no
+// predecessors, and are listed in `unreachable`.
+
+use crate::ast::*;
+
+pub type BlockId = usize;
+
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub entry: BlockId,
+    /// Block reached when the function returns or falls off its end
+    pub exit: BlockId,
+    pub unreachable: Vec<Unreachable<'a>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BasicBlock<'a> {
+    /// Declarations, assignments and expression statements, in order
+    pub statements: Vec<&'a Statement>,
+    pub terminator: Terminator<'a>,
+    pub successors: Vec<BlockId>,
+}
+
+/// How control leaves a basic block
+#[derive(Debug, Clone, Copy)]
+pub enum Terminator<'a> {
+    /// Continues to the single successor
+    Goto,
+    /// `if`/`elif`/`while` condition; successors are the true and false targets
+    Branch(&'a Expression),
+    /// Next iteration of a `for` loop; successors are the body and the block
+    /// after the loop
+    Iterate(&'a ForStatement),
+    /// `match` scrutinee; one successor per arm, then the block after the
+    /// match when no arm matches everything
+    Match(&'a Expression),
+    /// Jumps to the exit block
+    Return(Option<&'a Expression>),
+    /// Start of a `try`; successors are the body and where an error in it
+    /// goes: the catch clause, or else the finally block
+    Try(&'a TryStatement),
+    /// Jumps to the catch clause or finally block of the innermost `try`,
+    /// or leaves the function when there is none
+    Throw(&'a Expression),
+    /// The exit block, or a `break`/`continue` outside any loop
+    End,
+}
+
+/// Statements that can never run
+#[derive(Debug, Clone, Copy)]
+pub struct Unreachable<'a> {
+    /// The statement that control never gets past, such as a `return`
+    pub after: &'a Statement,
+    /// The first statement that does not run
+    pub statement: &'a Statement,
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    pub fn function(function: &'a Function) -> Self {
+        Self::new(&function.body.statements)
+    }
+
+    /// Graph of a sequence of statements, like the top-level code of a program
+    pub fn new(statements: impl IntoIterator<Item = &'a Statement>) -> Self {
+        let mut builder = Builder {
+            blocks: Vec::new(),
+            loops: Vec::new(),
+            handlers: Vec::new(),
+            unreachable: Vec::new(),
+            exit: 0,
+        };
+        let entry = builder.new_block();
+        let exit = builder.new_block();
+        builder.exit = exit;
+        builder.blocks[exit].terminator = Terminator::End;
+
+        if let Some(last) = builder.statements(statements, Some(entry)) {
+            builder.goto(last, exit);
+        }
+
+        Self {
+            blocks: builder.blocks,
+            entry,
+            exit,
+            unreachable: builder.unreachable,
+        }
+    }
+
+    pub fn predecessors(&self, block: BlockId) -> Vec<BlockId> {
+        (0..self.blocks.len())
+            .filter(|id| self.blocks[*id].successors.contains(&block))
+            .collect()
+    }
+
+    /// Whether each block can run, indexed by block id
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![self.entry];
+        while let Some(id) = stack.pop() {
+            if !std::mem::replace(&mut reachable[id], true) {
+                stack.extend(&self.blocks[id].successors);
+            }
+        }
+        reachable
+    }
+
+    /// Whether control can reach the end of the body without a `return`
+    pub fn falls_through(&self) -> bool {
+        let reachable = self.reachable();
+        self.blocks.iter().enumerate().any(|(id, block)| {
+            reachable[id]
+                && matches!(block.terminator, Terminator::Goto)
+                && block.successors == [self.exit]
+        })
+    }
+
+    /// The values of reachable `return` statements; `None` for a bare `return`
+    pub fn returns(&self) -> Vec<Option<&'a Expression>> {
+        let reachable = self.reachable();
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(id, block)| match block.terminator {
+                Terminator::Return(value) if reachable[id] => Some(value),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+struct Builder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    /// `continue` and `break` targets of the enclosing loops
+    loops: Vec<(BlockId, BlockId)>,
+    /// Where a `throw` goes in each enclosing `try`
+    handlers: Vec<BlockId>,
+    unreachable: Vec<Unreachable<'a>>,
+    exit: BlockId,
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock {
+            statements: Vec::new(),
+            terminator: Terminator::Goto,
+            successors: Vec::new(),
+        });
+        self.blocks.len() - 1
+    }
+
+    fn goto(&mut self, from: BlockId, to: BlockId) {
+        self.terminate(from, Terminator::Goto, vec![to]);
+    }
+
+    fn terminate(&mut self, block: BlockId, terminator: Terminator<'a>, successors: Vec<BlockId>) {
+        self.blocks[block].terminator = terminator;
+        self.blocks[block].successors = successors;
+    }
+
+    fn has_predecessors(&self, block: BlockId) -> bool {
+        self.blocks.iter().any(|b| b.successors.contains(&block))
+    }
+
+    /// Add statements starting in `current` (`None` once control can no
+    /// longer get there). Returns the block control is in afterwards.
+    fn statements(
+        &mut self,
+        statements: impl IntoIterator<Item = &'a Statement>,
+        mut current: Option<BlockId>,
+    ) -> Option<BlockId> {
+        let mut previous = None;
+        for statement in statements {
+            let block = match (current, previous) {
+                (Some(block), _) => block,
+                (None, after) => {
+                    if let Some(after) = after {
+                        self.unreachable.push(Unreachable { after, statement });
+                    }
+                    self.new_block()
+                }
+            };
+            current = self.statement(statement, block);
+            previous = Some(statement);
+        }
+        current
+    }
+
+    fn block(&mut self, block: &'a Block, current: BlockId) -> Option<BlockId> {
+        self.statements(&block.statements, Some(current))
+    }
+
+    fn statement(&mut self, statement: &'a Statement, current: BlockId) -> Option<BlockId> {
+        match statement {
+            Statement::Expression(_)
+            | Statement::VariableDeclaration(_)
+            | Statement::Assignment(_) => {
+                self.blocks[current].statements.push(statement);
+                Some(current)
+            }
+            Statement::Comment(_) => Some(current),
+            Statement::Block(block) | Statement::TaskGroup(block) => self.block(block, current),
+            Statement::Return(ret) => {
+                self.terminate(
+                    current,
+                    Terminator::Return(ret.value.as_ref()),
+                    vec![self.exit],
+                );
+                None
+            }
+            Statement::Throw(throw) => {
+                let successors = self.handlers.last().copied().into_iter().collect();
+                self.terminate(current, Terminator::Throw(&throw.value), successors);
+                None
+            }
+            Statement::Try(stmt) => {
+                let body = self.new_block();
+                let catch = stmt.catch.as_ref().map(|c| (c, self.new_block()));
+                let finally = stmt.finally.as_ref().map(|f| (f, self.new_block()));
+                let join = self.new_block();
+                let after_catch = finally.map_or(join, |(_, block)| block);
+                let handler = catch.map_or(after_catch, |(_, block)| block);
+                self.terminate(current, Terminator::Try(stmt), vec![body, handler]);
+
+                self.handlers.push(handler);
+                if let Some(end) = self.block(&stmt.body, body) {
+                    self.goto(end, after_catch);
+                }
+                self.handlers.pop();
+                if let Some((clause, block)) = catch {
+                    // A throw in the catch clause still runs the finally block
+                    if finally.is_some() {
+                        self.handlers.push(after_catch);
+                    }
+                    if let Some(end) = self.block(&clause.body, block) {
+                        self.goto(end, after_catch);
+                    }
+                    if finally.is_some() {
+                        self.handlers.pop();
+                    }
+                }
+                if let Some((finally, block)) = finally {
+                    if let Some(end) = self.block(finally, block) {
+                        self.goto(end, join);
+                    }
+                }
+                self.has_predecessors(join).then_some(join)
+            }
+            Statement::Break | Statement::Continue => {
+                let target = self.loops.last().map(|(head, after)| {
+                    if matches!(statement, Statement::Break) {
+                        *after
+                    } else {
+                        *head
+                    }
+                });
+                match target {
+                    Some(target) => self.goto(current, target),
+                    None => self.terminate(current, Terminator::End, Vec::new()),
+                }
+                None
+            }
+            Statement::If(stmt) => {
+                let join = self.new_block();
+                let mut condition = current;
+                let branches = std::iter::once((&stmt.condition, &stmt.then_branch))
+                    .chain(stmt.elif_branches.iter().map(|(c, b)| (c, b)));
+                for (test, branch) in branches {
+                    let then = self.new_block();
+                    let otherwise = self.new_block();
+                    self.terminate(condition, Terminator::Branch(test), vec![then, otherwise]);
+                    if let Some(end) = self.block(branch, then) {
+                        self.goto(end, join);
+                    }
+                    condition = otherwise;
+                }
+                let end = match &stmt.else_branch {
+                    Some(branch) => self.block(branch, condition),
+                    None => Some(condition),
+                };
+                if let Some(end) = end {
+                    self.goto(end, join);
+                }
+                self.has_predecessors(join).then_some(join)
+            }
+            Statement::While(stmt) => {
+                let head = self.new_block();
+                let body = self.new_block();
+                let after = self.new_block();
+                self.goto(current, head);
+
+                // `while true` only ends through `break`
+                let successors = match stmt.condition {
+                    Expression::Literal(Literal::Boolean(true)) => vec![body],
+                    _ => vec![body, after],
+                };
+                self.terminate(head, Terminator::Branch(&stmt.condition), successors);
+                self.body(&stmt.body, body, head, after)
+            }
+            Statement::For(stmt) => {
+                let head = self.new_block();
+                let body = self.new_block();
+                let after = self.new_block();
+                self.goto(current, head);
+                self.terminate(head, Terminator::Iterate(stmt), vec![body, after]);
+                self.body(&stmt.body, body, head, after)
+            }
+            Statement::Match(stmt) => {
+                let join = self.new_block();
+                let mut successors = Vec::new();
+                for arm in &stmt.arms {
+                    let block = self.new_block();
+                    successors.push(block);
+                    if let Some(end) = self.statements([&arm.body], Some(block)) {
+                        self.goto(end, join);
+                    }
+                }
+                let exhaustive = stmt.arms.iter().any(|arm| {
+                    arm.guard.is_none()
+                        && matches!(arm.pattern, Pattern::Wildcard | Pattern::Identifier(_))
+                });
+                if !exhaustive {
+                    successors.push(join);
+                }
+                self.terminate(current, Terminator::Match(&stmt.expression), successors);
+                self.has_predecessors(join).then_some(join)
+            }
+        }
+    }
+
+    /// A loop body from `body` back to `head`; returns the block after the
+    /// loop if anything reaches it
+    fn body(
+        &mut self,
+        block: &'a Block,
+        body: BlockId,
+        head: BlockId,
+        after: BlockId,
+    ) -> Option<BlockId> {
+        self.loops.push((head, after));
+        if let Some(end) = self.block(block, body) {
+            self.goto(end, head);
+        }
+        self.loops.pop();
+        self.has_predecessors(after).then_some(after)
+    }
+}
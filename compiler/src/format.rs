@@ -0,0 +1,209 @@
+// printf-style formatting for the `format` and `print` builtins
+//
+// Each placeholder in a template is replaced by an argument converted to
+// text as `str()` would: `{}` takes the next argument and `{1}` the one at
+// that index. Either may end with a specifier after a colon,
+//
+//     {:[[fill]align][0][width][.precision]}
+//
+// where align is `<` (left), `>` (right) or `^` (centered). Numbers are
+// right-aligned by default and everything else left-aligned, and `0` pads a
+// number with zeros after its sign. Precision is the number of decimals of
+// a number, integers included, and the most characters kept of anything
+// else. `{{` and `}}` are literal braces.
+//
+// The lexer only starts interpolation at `{` followed by a name or `(`, so
+// placeholders reach here as written in a string literal.
+
+use crate::error::{Result, VeyraError};
+use crate::interpreter::{Interpreter, Value};
+
+/// `template` with its placeholders replaced by `args`, each of which must
+/// be used
+pub fn format(template: &str, args: &[Value]) -> Result<String> {
+    let mut text = String::new();
+    let mut used = vec![false; args.len()];
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '}' => {
+                return Err(VeyraError::runtime_error(
+                    "Unmatched '}' in format string; write '}}' for a literal brace",
+                ))
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return Err(VeyraError::runtime_error(
+                                "Unclosed '{' in format string; write '{{' for a literal brace",
+                            ))
+                        }
+                    }
+                }
+                let invalid = || {
+                    VeyraError::runtime_error(format!(
+                        "Invalid placeholder '{{{}}}' in format string",
+                        placeholder
+                    ))
+                };
+                let (index, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+                let index = match index {
+                    "" => {
+                        next += 1;
+                        next - 1
+                    }
+                    index => index.parse().map_err(|_| invalid())?,
+                };
+                let Some(value) = args.get(index) else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Format string uses {} but {} given",
+                        match index + 1 {
+                            1 => "1 argument".to_string(),
+                            n => format!("{} arguments", n),
+                        },
+                        match args.len() {
+                            1 => "1 was".to_string(),
+                            n => format!("{} were", n),
+                        }
+                    )));
+                };
+                used[index] = true;
+                let spec = Spec::parse(spec).ok_or_else(invalid)?;
+                text.push_str(&spec.apply(value)?);
+            }
+            c => text.push(c),
+        }
+    }
+    if let Some(unused) = used.iter().position(|used| !used) {
+        return Err(VeyraError::runtime_error(format!(
+            "Argument {} has no placeholder in the format string",
+            unused
+        )));
+    }
+    Ok(text)
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        }
+    }
+}
+
+/// What follows the colon in a placeholder
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Option<Self> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut rest = chars.as_slice();
+        let (fill, align) = match rest {
+            [fill, align, ..] if Align::from_char(*align).is_some() => {
+                rest = &rest[2..];
+                (*fill, Align::from_char(*align))
+            }
+            [align, ..] if Align::from_char(*align).is_some() => {
+                rest = &rest[1..];
+                (' ', Align::from_char(*align))
+            }
+            _ => (' ', None),
+        };
+        let zero = rest.first() == Some(&'0');
+        if zero {
+            rest = &rest[1..];
+        }
+        let width = digits(&mut rest).unwrap_or(0);
+        let precision = match rest.first() {
+            Some('.') => {
+                rest = &rest[1..];
+                Some(digits(&mut rest)?)
+            }
+            _ => None,
+        };
+        rest.is_empty().then_some(Spec {
+            fill,
+            align,
+            zero,
+            width,
+            precision,
+        })
+    }
+
+    fn apply(&self, value: &Value) -> Result<String> {
+        let number = matches!(value, Value::Integer(_) | Value::Float(_));
+        let text = match (value, self.precision) {
+            (Value::Integer(n), Some(precision)) => format!("{:.*}", precision, *n as f64),
+            (Value::Float(f), Some(precision)) => format!("{:.*}", precision, f),
+            (_, Some(precision)) => Interpreter::value_to_string(value)
+                .chars()
+                .take(precision)
+                .collect(),
+            (_, None) => Interpreter::value_to_string(value),
+        };
+
+        if self.zero {
+            if !number {
+                return Err(VeyraError::runtime_error(format!(
+                    "Zero padding needs a number, got {}",
+                    value.type_name()
+                )));
+            }
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+            let width = self.width.saturating_sub(sign.len());
+            return Ok(format!("{}{:0>width$}", sign, digits, width = width));
+        }
+
+        let padding = self.width.saturating_sub(text.chars().count());
+        let align = self
+            .align
+            .unwrap_or(if number { Align::Right } else { Align::Left });
+        let (before, after) = match align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        let fill = |n| self.fill.to_string().repeat(n);
+        Ok(format!("{}{}{}", fill(before), text, fill(after)))
+    }
+}
+
+/// Read the digits at the start of `chars`, if there are any
+fn digits(chars: &mut &[char]) -> Option<usize> {
+    let count = chars.iter().take_while(|c| c.is_ascii_digit()).count();
+    let (digits, rest) = chars.split_at(count);
+    *chars = rest;
+    digits.iter().collect::<String>().parse().ok()
+}
@@ -0,0 +1,637 @@
+// The grammar veyc accepts, as EBNF
+//
+// `veyc grammar` prints it for editor plugin authors and the language
+// documentation; spec/GRAMMAR.ebnf is its output. The rules are written by
+// hand next to the parser and change with it. Each rule lists small programs
+// that use it, and tests/grammar.rs checks that they all still parse, that
+// every rule a definition mentions exists and that the spec is up to date.
+// Tree-sitter grammars for editors name their nodes after these rules, which
+// lets veyra-grammar-check compare them with the parser.
+
+/// One production: `name = alternative | alternative ... .`
+pub struct Rule {
+    pub name: &'static str,
+    pub alternatives: &'static [&'static str],
+    /// Printed as a comment above the rule
+    pub note: Option<&'static str>,
+    /// Programs using the rule, which must parse
+    #[allow(dead_code)]
+    pub examples: &'static [&'static str],
+}
+
+pub struct Section {
+    pub title: &'static str,
+    pub rules: &'static [Rule],
+}
+
+const fn rule(
+    name: &'static str,
+    alternatives: &'static [&'static str],
+    examples: &'static [&'static str],
+) -> Rule {
+    Rule {
+        name,
+        alternatives,
+        note: None,
+        examples,
+    }
+}
+
+const fn noted(
+    name: &'static str,
+    note: &'static str,
+    alternatives: &'static [&'static str],
+    examples: &'static [&'static str],
+) -> Rule {
+    Rule {
+        name,
+        alternatives,
+        note: Some(note),
+        examples,
+    }
+}
+
+pub const GRAMMAR: &[Section] = &[
+    Section {
+        title: "Lexical elements",
+        rules: &[
+            noted(
+                "identifier",
+                "\"loop\", \"pub\" and \"unsafe\" are reserved but not used yet",
+                &["( letter { letter | digit } ) - keyword"],
+                &["let user_name2 = 1", "let größe = 2"],
+            ),
+            rule(
+                "letter",
+                &["? any Unicode letter ?", "\"_\""],
+                &["let _x = 1"],
+            ),
+            rule("digit", &["\"0\" ... \"9\""], &["let x = 7"]),
+            rule(
+                "keyword",
+                &[
+                    "\"actor\" | \"and\" | \"as\" | \"async\" | \"await\" | \"break\" | \"catch\"",
+                    "\"continue\" | \"elif\" | \"else\" | \"false\" | \"finally\" | \"fn\" | \"for\"",
+                    "\"if\" | \"impl\" | \"import\" | \"in\" | \"let\" | \"loop\" | \"match\"",
+                    "\"mut\" | \"None\" | \"not\" | \"or\" | \"pub\" | \"return\" | \"Some\"",
+                    "\"spawn\" | \"struct\" | \"task_group\" | \"throw\" | \"trait\" | \"true\"",
+                    "\"try\" | \"unsafe\" | \"while\"",
+                ],
+                &["let found = true and not false"],
+            ),
+            rule(
+                "literal",
+                &[
+                    "integer_literal | float_literal | string_literal | char_literal",
+                    "\"true\" | \"false\" | \"None\"",
+                ],
+                &["let values = [1, 2.5, \"three\", 'c', true, None]"],
+            ),
+            rule(
+                "integer_literal",
+                &[
+                    "digit { digit | \"_\" }",
+                    "\"0b\" ( \"0\" | \"1\" | \"_\" ) { \"0\" | \"1\" | \"_\" }",
+                    "\"0o\" ( octal_digit | \"_\" ) { octal_digit | \"_\" }",
+                    "\"0x\" ( hex_digit | \"_\" ) { hex_digit | \"_\" }",
+                ],
+                &["let big = 1_000_000", "let mask = 0b1010 | 0o17 | 0xFF"],
+            ),
+            rule("octal_digit", &["\"0\" ... \"7\""], &["let mode = 0o755"]),
+            rule(
+                "hex_digit",
+                &["digit | \"a\" ... \"f\" | \"A\" ... \"F\""],
+                &["let color = 0xFFa0c3"],
+            ),
+            rule(
+                "float_literal",
+                &["digit { digit | \"_\" } \".\" digit { digit | \"_\" } [ exponent ]"],
+                &["let pi = 3.141_59", "let tiny = 1.5e-10"],
+            ),
+            rule(
+                "exponent",
+                &["( \"e\" | \"E\" ) [ \"+\" | \"-\" ] digit { digit }"],
+                &["let avogadro = 6.022E+23"],
+            ),
+            rule(
+                "string_literal",
+                &[
+                    "'\"' { string_char } '\"'",
+                    "'\"\"\"' { multiline_char } '\"\"\"'",
+                    "'r\"' { raw_string_char } '\"'",
+                ],
+                &[
+                    "let s = \"plain\"",
+                    "let s = \"\"\"\nfirst\nsecond\n\"\"\"",
+                    "let s = r\"C:\\path\"",
+                ],
+            ),
+            noted(
+                "string_char",
+                "Any character but '\"' or a backslash",
+                &["? character ? | escape_sequence | interpolation"],
+                &["let s = \"tab\\there {1 + 2}\""],
+            ),
+            noted(
+                "multiline_char",
+                "Any character but a backslash, or three quotes in a row",
+                &["? character ? | escape_sequence"],
+                &["let s = \"\"\"\nsaid \"hi\"\n\"\"\""],
+            ),
+            noted(
+                "raw_string_char",
+                "Any character but '\"', backslashes included",
+                &["? character ?"],
+                &["let pattern = r\"\\d+\\.\\d+\""],
+            ),
+            rule(
+                "escape_sequence",
+                &[
+                    "\"\\\" ( \"n\" | \"r\" | \"t\" | \"\\\" | '\"' | \"'\" | \"0\" | \"{\" | \"}\" )",
+                    "\"\\x\" hex_digit hex_digit",
+                    "\"\\u{\" hex_digit { hex_digit } \"}\"",
+                ],
+                &["let s = \"\\n\\t\\\\\\\"\\{\\x41\\u{1F600}\""],
+            ),
+            noted(
+                "interpolation",
+                "The expression starts with a letter, \"_\" or \"(\" and stays on one line",
+                &["\"{\" expression \"}\""],
+                &["let s = \"{name} is {(age)} years old\""],
+            ),
+            rule(
+                "char_literal",
+                &["\"'\" ( ? character ? | char_escape ) \"'\""],
+                &["let c = 'a'", "let c = '\\n'"],
+            ),
+            rule(
+                "char_escape",
+                &["\"\\\" ( \"n\" | \"r\" | \"t\" | \"\\\" | \"'\" | '\"' | \"0\" | \"x\" hex_digit hex_digit )"],
+                &["let c = '\\''", "let c = '\\x41'"],
+            ),
+            noted(
+                "comment",
+                "Comments are kept for tools but are not part of the program",
+                &["\"#\" { ? character ? }", "\"#[[\" { ? character ? } \"]]#\""],
+                &["# line\nlet x = 1 # trailing", "#[[ block\ncomment ]]#\nlet x = 1"],
+            ),
+        ],
+    },
+    Section {
+        title: "Items",
+        rules: &[
+            noted(
+                "program",
+                "Items and statements are separated by line breaks",
+                &["{ item }"],
+                &["", "fn main() {\n    print(1)\n}\n\nmain()"],
+            ),
+            rule(
+                "item",
+                &[
+                    "{ attribute } ( function_item | struct_item | impl_item | trait_item | actor_item )",
+                    "import_item",
+                    "statement",
+                ],
+                &["@test\nfn check() {\n    assert(true)\n}", "import std.math\nprint(1)"],
+            ),
+            noted(
+                "attribute",
+                "Attribute names and keys may be keywords, as in @cfg(not(windows))",
+                &["\"@\" identifier [ \"(\" [ attribute_argument { \",\" attribute_argument } [ \",\" ] ] \")\" ]"],
+                &["@cfg(not(feature = \"slow\"))\nfn fast() {\n    print(1)\n}"],
+            ),
+            rule(
+                "attribute_argument",
+                &["identifier \"=\" literal", "attribute", "literal"],
+                &["@bench(warmup = 3, \"fast\", tags(io),)\nfn f() {\n    print(1)\n}"],
+            ),
+            rule(
+                "function_item",
+                &["[ \"async\" ] \"fn\" identifier \"(\" [ parameter_list ] \")\" [ \"->\" type ] block"],
+                &[
+                    "fn add(a: int, b: int) -> int {\n    return a + b\n}",
+                    "async fn load() {\n    return 1\n}",
+                    "fn double(x) return x * 2",
+                ],
+            ),
+            noted(
+                "parameter_list",
+                "Long parameter lists may span lines",
+                &["parameter { \",\" parameter } [ \",\" ]"],
+                &["fn f(\n    first: int,\n    second: int,\n) {\n    print(first)\n}"],
+            ),
+            rule(
+                "parameter",
+                &["identifier [ \":\" type ] [ \"=\" expression ]"],
+                &["fn greet(name: string, greeting = \"Hello\") {\n    print(greeting)\n}"],
+            ),
+            rule(
+                "struct_item",
+                &["\"struct\" identifier \"{\" { field [ \",\" ] } \"}\""],
+                &["struct Point {\n    x: int,\n    y: int,\n}", "struct Empty {}"],
+            ),
+            rule("field", &["identifier \":\" type"], &["struct User {\n    name: string\n}"]),
+            rule(
+                "impl_item",
+                &["\"impl\" identifier [ \"for\" identifier ] \"{\" { method } \"}\""],
+                &[
+                    "impl Point {\n    fn sum(self) -> int {\n        return self.x + self.y\n    }\n}",
+                    "trait Show {\n    fn show(self) -> string\n}\n\nimpl Show for int {\n    fn show(self) -> string {\n        return str(self)\n    }\n}",
+                ],
+            ),
+            rule(
+                "method",
+                &["{ attribute } [ \"async\" ] \"fn\" identifier \"(\" [ parameter_list ] \")\" [ \"->\" type ] block"],
+                &["impl Point {\n    @inline\n    async fn area(self) {\n        return 0\n    }\n}"],
+            ),
+            rule(
+                "trait_item",
+                &["\"trait\" identifier \"{\" { trait_method } \"}\""],
+                &["trait Shape {\n    fn area(self) -> float\n\n    fn describe(self) {\n        print(self.area())\n    }\n}"],
+            ),
+            noted(
+                "trait_method",
+                "A method with a block is a default that implementations may override",
+                &["{ attribute } [ \"async\" ] \"fn\" identifier \"(\" [ parameter_list ] \")\" [ \"->\" type ] [ block ]"],
+                &["trait Named {\n    @inline\n    async fn name(self) -> string\n}", "trait Empty {}"],
+            ),
+            rule(
+                "actor_item",
+                &["\"actor\" identifier \"{\" { field [ \",\" ] | method } \"}\""],
+                &["actor Counter {\n    count: int\n\n    fn receive(message) {\n        count += 1\n    }\n}"],
+            ),
+            rule(
+                "import_item",
+                &[
+                    "\"import\" identifier { \".\" identifier } [ \"as\" identifier ]",
+                    "\"import\" identifier { \".\" identifier } \".\" \"{\" identifier { \",\" identifier } \"}\"",
+                ],
+                &["import std.math as m", "import std.collections.{HashMap, Vec}"],
+            ),
+        ],
+    },
+    Section {
+        title: "Types",
+        rules: &[
+            noted(
+                "type",
+                "int, i32, i64, u32, u64, f32, f64, bool, char and string name the primitive types",
+                &[
+                    "identifier [ \"?\" ]",
+                    "\"[\" type [ \";\" integer_literal ] \"]\" [ \"?\" ]",
+                    "\"fn\" \"(\" [ type { \",\" type } ] \")\" [ \"->\" type ] [ \"?\" ]",
+                ],
+                &[
+                    "let x: int = 1",
+                    "let name: string? = None",
+                    "let grid: [[f64; 3]; 3] = []",
+                    "let callback: fn(int, string) -> bool = check",
+                ],
+            ),
+        ],
+    },
+    Section {
+        title: "Statements",
+        rules: &[
+            noted(
+                "statement",
+                "A statement ends at a line break, a '}' or the start of the next statement",
+                &[
+                    "variable_declaration | assignment | expression_statement",
+                    "if_statement | while_statement | for_statement | match_statement",
+                    "return_statement | break_statement | continue_statement",
+                    "try_statement | throw_statement | task_group | braced_block",
+                ],
+                &["let x = 1\nx = 2\nprint(x)\n{\n    print(x)\n}"],
+            ),
+            noted(
+                "block",
+                "A body without braces is a single statement",
+                &["braced_block", "statement"],
+                &["if ready {\n    go()\n}", "if ready go()", "while busy wait()"],
+            ),
+            rule(
+                "braced_block",
+                &["\"{\" { statement } \"}\""],
+                &["{\n    let x = 1\n    print(x)\n}"],
+            ),
+            rule(
+                "variable_declaration",
+                &["\"let\" [ \"mut\" ] ( identifier | destructuring_pattern ) [ \":\" type ] \"=\" expression"],
+                &["let mut count: int = 0\nlet (x, y) = (1, 2)"],
+            ),
+            rule(
+                "assignment",
+                &["expression assignment_operator expression"],
+                &["total += 1\nitems[0] = 2\npoint.x = 3"],
+            ),
+            rule(
+                "assignment_operator",
+                &[
+                    "\"=\" | \"+=\" | \"-=\" | \"*=\" | \"/=\" | \"//=\" | \"%=\"",
+                    "\"&=\" | \"|=\" | \"^=\" | \"<<=\" | \">>=\"",
+                ],
+                &["x -= 1\nx *= 2\nx /= 3\nx //= 4\nx %= 5\nx &= 6\nx |= 7\nx ^= 8\nx <<= 1\nx >>= 1"],
+            ),
+            rule(
+                "if_statement",
+                &["\"if\" expression block { \"elif\" expression block } [ \"else\" block ]"],
+                &["if x > 0 {\n    print(1)\n} elif x < 0 {\n    print(-1)\n} else {\n    print(0)\n}"],
+            ),
+            rule(
+                "while_statement",
+                &["\"while\" expression block"],
+                &["while i < 10 {\n    i += 1\n}"],
+            ),
+            rule(
+                "for_statement",
+                &["\"for\" identifier \"in\" expression block"],
+                &["for item in items {\n    print(item)\n}"],
+            ),
+            rule(
+                "match_statement",
+                &["\"match\" expression \"{\" { pattern [ match_guard ] \"->\" statement [ \",\" ] } \"}\""],
+                &["match code {\n    200 -> print(\"ok\")\n    404 -> {\n        print(\"missing\")\n    }\n    _ -> return\n}"],
+            ),
+            rule(
+                "return_statement",
+                &["\"return\" [ expression ]"],
+                &["fn f(x) {\n    if x {\n        return\n    }\n    return 2\n}"],
+            ),
+            rule(
+                "break_statement",
+                &["\"break\""],
+                &["while true {\n    break\n}"],
+            ),
+            rule(
+                "continue_statement",
+                &["\"continue\""],
+                &["for x in xs {\n    continue\n}"],
+            ),
+            noted(
+                "try_statement",
+                "The identifier before a braced or indented catch block names the error; for a runtime error, that is its message",
+                &["\"try\" block [ \"catch\" [ identifier ] block ] [ \"finally\" block ]"],
+                &["try {\n    risky()\n} catch error {\n    print(error)\n} finally {\n    done()\n}", "try risky() catch print(\"failed\")", "try {\n    risky()\n} finally {\n    done()\n}"],
+            ),
+            rule(
+                "throw_statement",
+                &["\"throw\" expression"],
+                &["if x < 0 {\n    throw \"negative\"\n}"],
+            ),
+            noted(
+                "expression_statement",
+                "Usually a call; the value is discarded",
+                &["expression"],
+                &["print(\"hi\")\nitems.push(1)"],
+            ),
+            rule(
+                "task_group",
+                &["\"task_group\" block"],
+                &["task_group {\n    spawn work(1)\n    spawn work(2)\n}"],
+            ),
+        ],
+    },
+    Section {
+        title: "Expressions",
+        rules: &[
+            noted(
+                "expression",
+                "From the loosest binding to the tightest; all binary operators group to the left but \"**\"",
+                &["or_expression"],
+                &["let x = a or b and c == d | e ^ f & g << h < i + j * k ** l"],
+            ),
+            rule(
+                "or_expression",
+                &["and_expression { \"or\" and_expression }"],
+                &["let x = a or b or c"],
+            ),
+            rule(
+                "and_expression",
+                &["equality_expression { \"and\" equality_expression }"],
+                &["let x = a and b"],
+            ),
+            rule(
+                "equality_expression",
+                &["bitwise_or_expression { ( \"==\" | \"!=\" | \"in\" | \"not\" \"in\" ) bitwise_or_expression }"],
+                &["let x = a == b\nlet y = a != b\nlet z = a in items\nlet w = a not in items"],
+            ),
+            rule(
+                "bitwise_or_expression",
+                &["bitwise_xor_expression { \"|\" bitwise_xor_expression }"],
+                &["let x = a | b"],
+            ),
+            rule(
+                "bitwise_xor_expression",
+                &["bitwise_and_expression { \"^\" bitwise_and_expression }"],
+                &["let x = a ^ b"],
+            ),
+            rule(
+                "bitwise_and_expression",
+                &["shift_expression { \"&\" shift_expression }"],
+                &["let x = a & b"],
+            ),
+            rule(
+                "shift_expression",
+                &["comparison_expression { ( \"<<\" | \">>\" ) comparison_expression }"],
+                &["let x = a << 2 >> 1"],
+            ),
+            rule(
+                "comparison_expression",
+                &["additive_expression { ( \"<\" | \"<=\" | \">\" | \">=\" ) additive_expression }"],
+                &["let x = a < b\nlet y = a <= b\nlet z = a > b\nlet w = a >= b"],
+            ),
+            rule(
+                "additive_expression",
+                &["multiplicative_expression { ( \"+\" | \"-\" ) multiplicative_expression }"],
+                &["let x = a + b - c"],
+            ),
+            rule(
+                "multiplicative_expression",
+                &["power_expression { ( \"*\" | \"/\" | \"//\" | \"%\" ) power_expression }"],
+                &["let x = a * b / c // d % e"],
+            ),
+            rule(
+                "power_expression",
+                &["unary_expression [ \"**\" power_expression ]"],
+                &["let x = 2 ** 3 ** 2"],
+            ),
+            rule(
+                "unary_expression",
+                &[
+                    "( \"-\" | \"not\" | \"~\" | \"&\" [ \"mut\" ] | \"*\" ) unary_expression",
+                    "cast_expression",
+                ],
+                &["let x = -a\nlet y = not b\nlet z = ~c\nlet r = &d\nlet m = &mut e\nlet v = *r"],
+            ),
+            rule(
+                "cast_expression",
+                &["postfix_expression { \"as\" type }"],
+                &["let x = 3.7 as int as string"],
+            ),
+            noted(
+                "postfix_expression",
+                "A line starting with \".\" continues a method chain; \"?\" returns a None or Err from the function and unwraps Some or Ok",
+                &["primary_expression { arguments | \"[\" expression \"]\" | \".\" identifier [ arguments ] | \"::\" identifier | \"?\" }"],
+                &[
+                    "let x = f(1)(2)\nlet y = items[0][1]\nlet z = user.name.len()\nlet w = math::pi",
+                    "fn total(s) {\n    let n = parse(s)?\n    return Ok(n + lookup(s)?.count)\n}",
+                    "let names = users\n    .filter(active)\n    .map(name)",
+                ],
+            ),
+            rule(
+                "arguments",
+                &["\"(\" [ expression { \",\" expression } ] \")\""],
+                &["print()\nprint(1, 2, 3)"],
+            ),
+            rule(
+                "primary_expression",
+                &[
+                    "literal | identifier | \"Some\" | struct_literal | parenthesized_expression",
+                    "array_expression | dictionary_expression | set_expression",
+                    "if_expression | match_expression | lambda_expression",
+                    "( \"await\" | \"spawn\" ) unary_expression",
+                ],
+                &["let t = spawn fetch(1)\nlet v = await t"],
+            ),
+            noted(
+                "struct_literal",
+                "An identifier followed by '{', a name and ':' starts a struct literal rather than a block",
+                &["identifier \"{\" identifier \":\" expression { \",\" identifier \":\" expression } [ \",\" ] \"}\""],
+                &["let p = Point { x: 1, y: 2 }", "let p = Point {\n    x: 1,\n    y: 2,\n}"],
+            ),
+            noted(
+                "parenthesized_expression",
+                "A grouped expression, or a tuple when there is a comma",
+                &[
+                    "\"(\" \")\"",
+                    "\"(\" expression \")\"",
+                    "\"(\" expression \",\" [ expression { \",\" expression } [ \",\" ] ] \")\"",
+                ],
+                &["let unit = ()\nlet grouped = (1 + 2) * 3\nlet single = (1,)\nlet pair = (1, \"a\")"],
+            ),
+            rule(
+                "array_expression",
+                &["\"[\" [ expression { \",\" expression } [ \",\" ] ] \"]\""],
+                &["let empty = []\nlet xs = [\n    1,\n    2,\n]"],
+            ),
+            noted(
+                "dictionary_expression",
+                "\"{}\" is an empty dictionary",
+                &[
+                    "\"{\" \"}\"",
+                    "\"{\" expression \":\" expression { \",\" expression \":\" expression } [ \",\" ] \"}\"",
+                ],
+                &["let empty = {}\nlet ages = {\"ann\": 31, \"bob\": 27}"],
+            ),
+            rule(
+                "set_expression",
+                &["\"{\" expression { \",\" expression } [ \",\" ] \"}\""],
+                &["let primes = {2, 3, 5, 7}"],
+            ),
+            noted(
+                "if_expression",
+                "\"then\" is only special here and can name variables elsewhere",
+                &["\"if\" expression \"then\" expression \"else\" expression"],
+                &["let sign = if x < 0 then -1 else 1"],
+            ),
+            rule(
+                "match_expression",
+                &["\"match\" expression \"{\" [ match_arm { \",\" match_arm } [ \",\" ] ] \"}\""],
+                &["let name = match code {\n    200 -> \"ok\",\n    _ -> \"error\",\n}"],
+            ),
+            rule(
+                "match_arm",
+                &["pattern [ match_guard ] \"->\" expression"],
+                &["let x = match y { 1 -> \"one\", _ -> \"many\" }"],
+            ),
+            noted(
+                "match_guard",
+                "The arm is taken only if the condition holds, with the pattern's names bound",
+                &["\"if\" expression"],
+                &["match n {\n    x if x < 0 -> print(\"negative\")\n    _ -> print(\"other\")\n}"],
+            ),
+            noted(
+                "pattern",
+                "\"_\" matches anything and an identifier binds the value",
+                &["identifier | integer_literal | float_literal | string_literal | \"true\" | \"false\" | \"None\"", "destructuring_pattern | variant_pattern"],
+                &["match v {\n    0 -> print(0)\n    1.5 -> print(1)\n    \"s\" -> print(2)\n    true -> print(3)\n    None -> print(4)\n    other -> print(other)\n}"],
+            ),
+            noted(
+                "destructuring_pattern",
+                "\"(a,)\" is a tuple of one and \"(a)\" just \"a\"; \"..rest\" binds the remaining elements",
+                &[
+                    "\"(\" [ pattern { \",\" pattern } [ \",\" ] ] \")\"",
+                    "\"[\" [ pattern { \",\" pattern } [ \",\" ] ] \"]\"",
+                    "\"[\" { pattern \",\" } \"..\" [ identifier ] [ \",\" ] \"]\"",
+                    "identifier \"{\" [ field_pattern { \",\" field_pattern } [ \",\" ] ] \"}\"",
+                ],
+                &["match point {\n    (0, y) -> print(y)\n    [first, ..rest] -> print(rest)\n    Point { x, y: 0 } -> print(x)\n    _ -> print(\"other\")\n}"],
+            ),
+            rule(
+                "variant_pattern",
+                &["( \"Some\" | \"Ok\" | \"Err\" ) \"(\" pattern \")\""],
+                &["match parse(s) {\n    Ok(Some(n)) -> print(n)\n    Ok(None) -> print(\"blank\")\n    Err(e) -> print(e)\n}"],
+            ),
+            rule(
+                "field_pattern",
+                &["identifier [ \":\" pattern ]"],
+                &["let Point { x, y: height } = point"],
+            ),
+            noted(
+                "lambda_expression",
+                "The body of \"|x| body\" extends as far as an expression can",
+                &[
+                    "\"|\" [ lambda_parameter { \",\" lambda_parameter } ] \"|\" expression",
+                    "\"||\" expression",
+                    "\"fn\" \"(\" [ parameter_list ] \")\" [ \"->\" type ] block",
+                ],
+                &[
+                    "let double = |x| x * 2\nlet answer = || 42",
+                    "let add = fn(a: int, b: int) -> int {\n    return a + b\n}",
+                ],
+            ),
+            rule(
+                "lambda_parameter",
+                &["identifier [ \":\" type ]"],
+                &["let add = |a: int, b| a + b"],
+            ),
+        ],
+    },
+];
+
+/// All rules, in the order they are printed
+#[allow(dead_code)]
+pub fn rules() -> impl Iterator<Item = &'static Rule> {
+    GRAMMAR.iter().flat_map(|section| section.rules)
+}
+
+/// The grammar as EBNF: `name = a | b .`, with `{ }` for repetition, `[ ]`
+/// for an optional part and `? ?` for what is described in words
+pub fn ebnf() -> String {
+    let mut out = String::new();
+    out.push_str("(* Veyra grammar, as accepted by veyc. Printed by `veyc grammar`. *)\n");
+    out.push_str(
+        "(* include!(\"file\") and include_str!(\"file\") are expanded before parsing. *)\n",
+    );
+    for section in GRAMMAR {
+        out.push_str(&format!("\n(* {} *)\n", section.title));
+        for rule in section.rules {
+            out.push('\n');
+            if let Some(note) = rule.note {
+                out.push_str(&format!("(* {} *)\n", note));
+            }
+            let indent = " ".repeat(rule.name.len() + 1);
+            for (i, alternative) in rule.alternatives.iter().enumerate() {
+                if i == 0 {
+                    out.push_str(&format!("{} = {}", rule.name, alternative));
+                } else {
+                    out.push_str(&format!("\n{}| {}", indent, alternative));
+                }
+            }
+            out.push_str(" .\n");
+        }
+    }
+    out
+}
@@ -0,0 +1,286 @@
+// Syntax highlighting definitions for editors
+//
+// `veyc grammar --format tmlanguage` prints a TextMate grammar, the JSON
+// form that Sublime Text, VS Code, TextMate and many other editors read, and
+// `veyc grammar --format vim` a Vim syntax file. Both are built from the
+// lexer's keyword and operator tables, the parser's primitive types and the
+// builtin functions, so they highlight the words the compiler treats
+// specially and pick up new ones when the compiler does. Editors with a
+// language server get the same from its semantic tokens; these are for the
+// rest.
+
+use crate::builtins::BUILTINS;
+use crate::lexer::{TokenClass, KEYWORDS, OPERATORS};
+use crate::parser::PRIMITIVE_TYPES;
+use serde_json::{json, Value};
+
+impl TokenClass {
+    /// The classes of keywords, which the grammars list word by word
+    const KEYWORDS: [TokenClass; 10] = [
+        TokenClass::Conditional,
+        TokenClass::Loop,
+        TokenClass::Flow,
+        TokenClass::Exception,
+        TokenClass::Declaration,
+        TokenClass::Modifier,
+        TokenClass::Import,
+        TokenClass::Operator,
+        TokenClass::Boolean,
+        TokenClass::Constant,
+    ];
+
+    /// TextMate scope
+    fn scope(self) -> &'static str {
+        match self {
+            TokenClass::Conditional => "keyword.control.conditional.veyra",
+            TokenClass::Loop => "keyword.control.loop.veyra",
+            TokenClass::Flow => "keyword.control.flow.veyra",
+            TokenClass::Exception => "keyword.control.exception.veyra",
+            TokenClass::Declaration => "keyword.declaration.veyra",
+            TokenClass::Modifier => "storage.modifier.veyra",
+            TokenClass::Import => "keyword.control.import.veyra",
+            TokenClass::Operator => "keyword.operator.word.veyra",
+            TokenClass::Boolean => "constant.language.boolean.veyra",
+            TokenClass::Constant => "constant.language.veyra",
+            TokenClass::Number => "constant.numeric.veyra",
+            TokenClass::Text => "string.quoted.double.veyra",
+            TokenClass::Comment => "comment.line.number-sign.veyra",
+            TokenClass::Attribute => "entity.other.attribute-name.veyra",
+            TokenClass::Call => "entity.name.function.veyra",
+        }
+    }
+
+    /// Vim highlight group, linked to a standard group of the same name
+    fn vim_group(self) -> &'static str {
+        match self {
+            TokenClass::Conditional => "Conditional",
+            TokenClass::Loop => "Repeat",
+            TokenClass::Flow => "Statement",
+            TokenClass::Exception => "Exception",
+            TokenClass::Declaration => "Keyword",
+            TokenClass::Modifier => "StorageClass",
+            TokenClass::Import => "Include",
+            TokenClass::Operator => "Operator",
+            TokenClass::Boolean => "Boolean",
+            TokenClass::Constant => "Constant",
+            TokenClass::Number => "Number",
+            TokenClass::Text => "String",
+            TokenClass::Comment => "Comment",
+            TokenClass::Attribute => "PreProc",
+            TokenClass::Call => "Function",
+        }
+    }
+
+    fn keywords(self) -> Vec<&'static str> {
+        KEYWORDS
+            .iter()
+            .filter(|(_, kind)| TokenClass::of(kind, None) == Some(self))
+            .map(|(keyword, _)| *keyword)
+            .collect()
+    }
+}
+
+fn primitive_types() -> Vec<&'static str> {
+    PRIMITIVE_TYPES.iter().map(|(name, _)| *name).collect()
+}
+
+/// Builtin functions, apart from `Some` and the conversions named after
+/// a type, which are highlighted as those
+fn builtins() -> Vec<&'static str> {
+    BUILTINS
+        .iter()
+        .map(|builtin| builtin.name)
+        .filter(|name| {
+            !KEYWORDS.iter().any(|(keyword, _)| keyword == name)
+                && !PRIMITIVE_TYPES
+                    .iter()
+                    .any(|(type_name, _)| type_name == name)
+        })
+        .collect()
+}
+
+/// Operators, longest first so that `<<=` is not taken for `<` and `<=`
+fn operators() -> Vec<&'static str> {
+    let mut operators: Vec<&str> = OPERATORS.iter().map(|(symbol, _)| *symbol).collect();
+    operators.sort_by_key(|symbol| std::cmp::Reverse(symbol.len()));
+    operators
+}
+
+/// Oniguruma pattern matching any of `words` as a whole word
+fn words(words: &[&str]) -> String {
+    format!("\\b({})\\b", words.join("|"))
+}
+
+fn escape_regex(symbol: &str) -> String {
+    symbol
+        .chars()
+        .flat_map(|c| match c {
+            '\\' | '^' | '$' | '.' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}' => {
+                vec!['\\', c]
+            }
+            _ => vec![c],
+        })
+        .collect()
+}
+
+const NUMBER: &str =
+    "\\b(0x[0-9a-fA-F_]+|0b[01_]+|0o[0-7_]+|[0-9][0-9_]*(\\.[0-9][0-9_]*)?([eE][+-]?[0-9]+)?)\\b";
+
+/// A TextMate grammar, as JSON
+pub fn tmlanguage() -> String {
+    let keywords: Vec<Value> = TokenClass::KEYWORDS
+        .iter()
+        .filter(|class| !class.keywords().is_empty())
+        .map(|class| json!({ "name": class.scope(), "match": words(&class.keywords()) }))
+        .collect();
+    let operators: Vec<String> = operators().iter().map(|op| escape_regex(op)).collect();
+
+    let grammar = json!({
+        "$schema": "https://raw.githubusercontent.com/martinring/tmlanguage/master/tmlanguage.json",
+        "name": "Veyra",
+        "scopeName": "source.veyra",
+        "fileTypes": ["vey"],
+        "patterns": [
+            { "include": "#comments" },
+            { "include": "#strings" },
+            { "include": "#attributes" },
+            { "include": "#function-declaration" },
+            { "include": "#keywords" },
+            { "include": "#types" },
+            { "include": "#builtins" },
+            { "include": "#numbers" },
+            { "include": "#operators" }
+        ],
+        "repository": {
+            "comments": {
+                "patterns": [
+                    {
+                        "name": "comment.block.veyra",
+                        "begin": "#\\[\\[",
+                        "end": "\\]\\]#",
+                        "patterns": [{ "include": "#comments" }]
+                    },
+                    { "name": "comment.line.number-sign.veyra", "match": "#.*$" }
+                ]
+            },
+            "strings": {
+                "patterns": [
+                    {
+                        "name": "string.quoted.double.veyra",
+                        "begin": "\"",
+                        "end": "\"",
+                        "patterns": [
+                            { "name": "constant.character.escape.veyra", "match": "\\\\." },
+                            {
+                                "name": "meta.interpolation.veyra",
+                                "begin": "\\{",
+                                "end": "\\}",
+                                "beginCaptures": { "0": { "name": "punctuation.section.interpolation.begin.veyra" } },
+                                "endCaptures": { "0": { "name": "punctuation.section.interpolation.end.veyra" } },
+                                "patterns": [{ "include": "$self" }]
+                            }
+                        ]
+                    },
+                    { "name": "string.quoted.single.veyra", "match": "'(\\\\.|[^'\\\\])'" }
+                ]
+            },
+            "attributes": {
+                "patterns": [{ "name": "entity.other.attribute-name.veyra", "match": "@[A-Za-z_][A-Za-z0-9_]*" }]
+            },
+            "function-declaration": {
+                "patterns": [{
+                    "match": "\\b(fn)\\s+([A-Za-z_][A-Za-z0-9_]*)",
+                    "captures": {
+                        "1": { "name": TokenClass::Declaration.scope() },
+                        "2": { "name": "entity.name.function.veyra" }
+                    }
+                }]
+            },
+            "keywords": { "patterns": keywords },
+            "types": {
+                "patterns": [{ "name": "support.type.primitive.veyra", "match": words(&primitive_types()) }]
+            },
+            "builtins": {
+                "patterns": [{
+                    "name": "support.function.builtin.veyra",
+                    "match": format!("{}(?=\\s*\\()", words(&builtins()))
+                }]
+            },
+            "numbers": {
+                "patterns": [{ "name": "constant.numeric.veyra", "match": NUMBER }]
+            },
+            "operators": {
+                "patterns": [{ "name": "keyword.operator.veyra", "match": operators.join("|") }]
+            }
+        }
+    });
+    serde_json::to_string_pretty(&grammar).unwrap_or_default() + "\n"
+}
+
+/// A Vim syntax file
+pub fn vim() -> String {
+    let mut out = String::new();
+    out.push_str("\" Vim syntax file\n");
+    out.push_str("\" Language: Veyra\n");
+    out.push_str("\" Printed by `veyc grammar --format vim`; save it as syntax/veyra.vim\n\n");
+    out.push_str("if exists(\"b:current_syntax\")\n  finish\nendif\n\n");
+
+    let mut links = Vec::new();
+    for class in TokenClass::KEYWORDS {
+        let keywords = class.keywords();
+        if keywords.is_empty() {
+            continue;
+        }
+        let group = format!("veyra{}", class.vim_group());
+        out.push_str(&format!("syn keyword {} {}\n", group, keywords.join(" ")));
+        links.push((group, class.vim_group()));
+    }
+    out.push_str(&format!(
+        "syn keyword veyraType {}\n",
+        primitive_types().join(" ")
+    ));
+    out.push_str(&format!(
+        "syn keyword veyraBuiltin {}\n\n",
+        builtins().join(" ")
+    ));
+
+    // Very nomagic patterns: only a backslash is special
+    let operators: Vec<String> = operators()
+        .iter()
+        .map(|op| op.replace('\\', "\\\\"))
+        .collect();
+    out.push_str(&format!(
+        "syn match veyraOperator '\\V\\({}\\)'\n",
+        operators.join("\\|")
+    ));
+    out.push_str("syn match veyraNumber '\\v<(0x[0-9a-fA-F_]+|0b[01_]+|0o[0-7_]+|[0-9][0-9_]*(\\.[0-9][0-9_]*)?([eE][+-]?[0-9]+)?)>'\n");
+    out.push_str("syn match veyraAttribute '@\\h\\w*'\n");
+    out.push_str("syn match veyraChar +'\\(\\\\.\\|[^'\\\\]\\)'+\n");
+    out.push_str("syn match veyraEscape +\\\\.+ contained\n");
+    out.push_str("syn region veyraInterpolation matchgroup=veyraDelimiter start=+{+ end=+}+ contained contains=TOP\n");
+    out.push_str("syn region veyraString start=+\"+ skip=+\\\\.+ end=+\"+ contains=veyraEscape,veyraInterpolation\n");
+    out.push_str("syn match veyraComment '#.*$'\n");
+    out.push_str(
+        "syn region veyraBlockComment start='#\\[\\[' end='\\]\\]#' contains=veyraBlockComment\n\n",
+    );
+
+    for (group, target) in &links {
+        out.push_str(&format!("hi def link {} {}\n", group, target));
+    }
+    for (group, target) in [
+        ("veyraType", "Type"),
+        ("veyraBuiltin", "Function"),
+        ("veyraNumber", "Number"),
+        ("veyraAttribute", "PreProc"),
+        ("veyraChar", "Character"),
+        ("veyraEscape", "SpecialChar"),
+        ("veyraDelimiter", "Delimiter"),
+        ("veyraString", "String"),
+        ("veyraComment", "Comment"),
+        ("veyraBlockComment", "Comment"),
+    ] {
+        out.push_str(&format!("hi def link {} {}\n", group, target));
+    }
+    out.push_str("\nlet b:current_syntax = \"veyra\"\n");
+    out
+}
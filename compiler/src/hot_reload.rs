@@ -0,0 +1,90 @@
+// Hot reloading of a running program's source
+//
+// `HotReload` watches the program's source file and the files of the
+// modules it imports. While the interpreter runs, it polls their
+// modification times a few times a second; when a file changes it is parsed
+// again and the interpreter swaps in the new function and actor definitions.
+// Globals keep their values, and top-level code is not run again, so a game
+// or server loop picks up edited functions on its next call.
+
+use crate::ast::*;
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the source files' modification times are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Parses a source file into a program ready to run
+pub type Loader = Box<dyn Fn(&Path) -> Result<Program>>;
+
+pub struct HotReload {
+    /// The watched files, the entry file first, with when each last changed
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    next_poll: Instant,
+    load: Loader,
+}
+
+/// What reloading a changed file did
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Reloaded {
+    /// Definitions swapped in
+    pub updated: usize,
+    /// Why changed definitions were kept, one line each
+    pub warnings: Vec<String>,
+}
+
+impl HotReload {
+    pub fn new(path: impl Into<PathBuf>, load: Loader) -> Self {
+        let path = path.into();
+        Self {
+            files: vec![(path.clone(), modified(&path))],
+            next_poll: Instant::now() + POLL_INTERVAL,
+            load,
+        }
+    }
+
+    /// Watch another file, such as an imported module's
+    pub fn watch(&mut self, path: &Path) {
+        if self.files.iter().all(|(file, _)| file != path) {
+            self.files.push((path.to_path_buf(), modified(path)));
+        }
+    }
+
+    /// The files parsed again, for each watched file that changed since the
+    /// last poll. Errors reading or parsing a file are returned once per
+    /// change.
+    pub fn poll(&mut self) -> Vec<(PathBuf, Result<Program>)> {
+        let now = Instant::now();
+        if now < self.next_poll {
+            return Vec::new();
+        }
+        self.next_poll = now + POLL_INTERVAL;
+
+        let mut changed = Vec::new();
+        for (path, last) in &mut self.files {
+            let modified = modified(path);
+            if modified.is_some() && modified != *last {
+                *last = modified;
+                changed.push((path.clone(), (self.load)(path)));
+            }
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Whether a new definition of a function can replace the old one: callers
+/// pass the same parameters and get the same kind of result
+pub fn same_signature(old: &Function, new: &Function) -> bool {
+    old.return_type == new.return_type
+        && old.parameters.len() == new.parameters.len()
+        && old
+            .parameters
+            .iter()
+            .zip(&new.parameters)
+            .all(|(a, b)| a.name == b.name && a.param_type == b.param_type)
+}
@@ -0,0 +1,135 @@
+// Compile-time file inclusion
+//
+// `include!("path.vey")` splices the tokens of another source file in place
+// of the invocation, and `include_str!("path")` becomes a string literal with
+// the file's contents (handy for embedding SQL, templates or config).
+// Paths are resolved relative to the including file. Expansion happens on
+// the token stream, before parsing.
+
+use crate::error::{Result, VeyraError};
+use crate::lexer::{self, Token, TokenKind};
+use std::path::{Path, PathBuf};
+
+/// Maximum nesting of `include!` before it is treated as runaway recursion
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Read and tokenize a source file, expanding includes relative to it
+pub fn tokenize_file(path: &Path) -> Result<Vec<Token>> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        VeyraError::IoError(format!("Failed to read file '{}': {}", path.display(), e))
+    })?;
+    let tokens = lexer::tokenize(&source)?;
+
+    let mut stack = vec![canonical(path)];
+    expand(tokens, parent_dir(path), &mut stack)
+}
+
+/// Expand `include!` and `include_str!` invocations in a token stream,
+/// resolving paths against `base_dir`
+#[allow(dead_code)]
+pub fn expand_includes(tokens: Vec<Token>, base_dir: &Path) -> Result<Vec<Token>> {
+    expand(tokens, base_dir, &mut Vec::new())
+}
+
+/// Whether a token stream has any `include!` or `include_str!` to expand
+#[allow(dead_code)]
+pub fn has_includes(tokens: &[Token]) -> bool {
+    (0..tokens.len()).any(|i| invocation(&tokens[i..]).is_some())
+}
+
+fn expand(tokens: Vec<Token>, base_dir: &Path, stack: &mut Vec<PathBuf>) -> Result<Vec<Token>> {
+    let mut output = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let Some((macro_name, relative)) = invocation(&tokens[i..]) else {
+            output.push(tokens[i].clone());
+            i += 1;
+            continue;
+        };
+
+        let site = &tokens[i];
+        let path = base_dir.join(relative);
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            VeyraError::parse_error(
+                site.line,
+                site.column,
+                format!("{}!: cannot read '{}': {}", macro_name, path.display(), e),
+            )
+        })?;
+
+        if macro_name == "include_str" {
+            output.push(Token {
+                kind: TokenKind::String(content.clone()),
+                lexeme: content,
+                line: site.line,
+                column: site.column,
+            });
+        } else {
+            let key = canonical(&path);
+            if stack.contains(&key) {
+                return Err(VeyraError::parse_error(
+                    site.line,
+                    site.column,
+                    format!("include!: '{}' includes itself", path.display()),
+                ));
+            }
+            if stack.len() >= MAX_INCLUDE_DEPTH {
+                return Err(VeyraError::parse_error(
+                    site.line,
+                    site.column,
+                    format!("include!: nesting deeper than {} files", MAX_INCLUDE_DEPTH),
+                ));
+            }
+
+            let included = lexer::tokenize(&content).map_err(|e| {
+                VeyraError::parse_error(
+                    site.line,
+                    site.column,
+                    format!("in file included from here ('{}'): {}", path.display(), e),
+                )
+            })?;
+
+            stack.push(key);
+            let expanded = expand(included, parent_dir(&path), stack)?;
+            stack.pop();
+
+            output.extend(
+                expanded
+                    .into_iter()
+                    .filter(|token| token.kind != TokenKind::Eof),
+            );
+        }
+
+        i += 5;
+    }
+
+    Ok(output)
+}
+
+/// Match `name ! ( "path" )` at the start of `tokens`
+fn invocation(tokens: &[Token]) -> Option<(&str, &str)> {
+    match tokens {
+        [name, bang, open, path, close, ..]
+            if name.kind == TokenKind::Identifier
+                && matches!(name.lexeme.as_str(), "include" | "include_str")
+                && bang.kind == TokenKind::Bang
+                && open.kind == TokenKind::LeftParen
+                && close.kind == TokenKind::RightParen =>
+        {
+            match &path.kind {
+                TokenKind::String(path) => Some((name.lexeme.as_str(), path.as_str())),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new("."))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
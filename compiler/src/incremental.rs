@@ -0,0 +1,229 @@
+// Incremental reparsing for editors
+//
+// An editor changes a few characters at a time, and parsing the whole
+// document again on every keystroke costs more the longer the document
+// gets. A `Document` keeps the program parsed from its text together with
+// where the text splits into pieces that parse on their own: the line
+// breaks that end top-level items. An edit only re-lexes and re-parses the
+// pieces it touches, plus one on either side, since an edit can join an item
+// to the one before or after it, and splices the new items into the
+// program in place of the old ones.
+//
+// Positions are counted in characters, as the lexer counts them. The result
+// is always the program a full parse of the new text gives.
+
+use crate::ast::{walk_block_mut, Block, Program, VisitorMut};
+use crate::error::Result;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parser::Parser;
+use std::ops::Range;
+
+/// Source text and the program parsed from it, kept in step through edits
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    program: Program,
+    pieces: Vec<Piece>,
+    /// Set when an edit left the text unparseable; the program is the last
+    /// one that parsed, and the next edit parses the whole text again
+    stale: bool,
+}
+
+/// Text that parses on its own into consecutive items of the program
+#[derive(Debug, Clone, Copy)]
+struct Piece {
+    /// Character offset where it starts; it ends where the next one starts
+    start: usize,
+    items: usize,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Result<Self> {
+        let (program, pieces) = parse_pieces(text, 0, 1, 1)?;
+        Ok(Self {
+            text: text.to_string(),
+            program,
+            pieces,
+            stale: false,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The program parsed from the text, or from the last text that parsed
+    /// if the latest edit left a syntax error
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Whether the latest edit left a syntax error, so that the program
+    /// is out of date
+    pub fn has_error(&self) -> bool {
+        self.stale
+    }
+
+    /// Replace the characters in `range` with `new_text` and reparse the
+    /// items around the edit. Returns the indices of the program's items
+    /// that were parsed again; the rest are unchanged. A range past the end
+    /// of the text is cut short at the end.
+    ///
+    /// When the items around the edit do not parse on their own, the whole
+    /// text is parsed again. On a syntax error the text is still edited, but
+    /// the program stays as it was until an edit makes the text parse again.
+    pub fn edit(&mut self, range: Range<usize>, new_text: &str) -> Result<Range<usize>> {
+        let length = self.text.chars().count();
+        let end = range.end.min(length);
+        let start = range.start.min(end);
+        let inserted = new_text.chars().count();
+        let (start_byte, end_byte) = (byte_offset(&self.text, start), byte_offset(&self.text, end));
+        let added_lines = new_text.matches('\n').count() as i64
+            - self.text[start_byte..end_byte].matches('\n').count() as i64;
+        self.text.replace_range(start_byte..end_byte, new_text);
+
+        if self.stale {
+            return self.reparse();
+        }
+
+        // The pieces the edit touches, with one more on each side. An edit
+        // right at the start of a piece changes the end of the line before.
+        let containing = |offset: usize| {
+            self.pieces
+                .iter()
+                .rposition(|piece| piece.start < offset)
+                .unwrap_or(0)
+        };
+        let first = containing(start).saturating_sub(1);
+        let last = (containing(end.max(1)) + 1).min(self.pieces.len() - 1);
+
+        let old_start = self.pieces[first].start;
+        let old_end = self.pieces.get(last + 1).map_or(length, |p| p.start);
+        let new_end = old_end - (end - start) + inserted;
+
+        let from = byte_offset(&self.text, old_start);
+        let to = from + byte_offset(&self.text[from..], new_end - old_start);
+        let (line, column) = advance((1, 1), &self.text[..from]);
+        let Ok((parsed, pieces)) = parse_pieces(&self.text[from..to], old_start, line, column)
+        else {
+            // The edit may reach past the pieces around it, as quotes
+            // opened here and closed further down do
+            return self.reparse();
+        };
+
+        let first_item: usize = self.pieces[..first].iter().map(|p| p.items).sum();
+        let old_items: usize = self.pieces[first..=last].iter().map(|p| p.items).sum();
+        let new_items = parsed.items.len();
+        let replaced = first_item..first_item + old_items;
+        self.program.items.splice(replaced.clone(), parsed.items);
+        self.program.lines.splice(replaced, parsed.lines, new_items);
+
+        // Items after the edit move down or up by the lines it added or
+        // removed
+        let after = first_item + new_items;
+        if added_lines != 0 {
+            let mut shift = Shift(added_lines);
+            for index in after..self.program.items.len() {
+                shift.visit_item_mut(&mut self.program.items[index]);
+            }
+            self.program.lines.shift_from(after, added_lines);
+        }
+
+        for piece in &mut self.pieces[last + 1..] {
+            piece.start = piece.start + inserted - (end - start);
+        }
+        self.pieces.splice(first..=last, pieces);
+        Ok(first_item..first_item + new_items)
+    }
+
+    /// Parse the whole text again, keeping the program as it was if that
+    /// fails
+    fn reparse(&mut self) -> Result<Range<usize>> {
+        match Self::parse(&self.text) {
+            Ok(document) => {
+                *self = document;
+                Ok(0..self.program.items.len())
+            }
+            Err(e) => {
+                self.stale = true;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Parse text found at character `offset`, `line` and `column` of the
+/// document into its items and the pieces they split into
+fn parse_pieces(
+    text: &str,
+    offset: usize,
+    line: usize,
+    column: usize,
+) -> Result<(Program, Vec<Piece>)> {
+    let (ranged, error) = Lexer::at(text, line, column).tokenize_with_ranges();
+    if let Some((error, _)) = error {
+        return Err(error);
+    }
+
+    // Where each token the parser keeps starts, to place the line breaks
+    // it reports
+    let starts: Vec<usize> = ranged
+        .iter()
+        .filter(|(token, _)| token.kind != TokenKind::Comment)
+        .map(|(_, range)| offset + range.start)
+        .collect();
+    let mut tokens: Vec<Token> = ranged.into_iter().map(|(token, _)| token).collect();
+    let (line, column) = advance((line, column), text);
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        lexeme: String::new(),
+        line,
+        column,
+    });
+
+    let (program, boundaries) = Parser::new(tokens).parse_with_boundaries()?;
+    let mut pieces = vec![Piece {
+        start: offset,
+        items: program.items.len(),
+    }];
+    let mut before = 0;
+    for (newline, items) in boundaries {
+        let last = pieces.last_mut().unwrap();
+        last.items = items - before;
+        pieces.push(Piece {
+            start: starts[newline],
+            items: program.items.len() - items,
+        });
+        before = items;
+    }
+    Ok((program, pieces))
+}
+
+/// Moves the lines recorded in the blocks of items by a number of lines
+struct Shift(i64);
+
+impl VisitorMut for Shift {
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        block.lines.shift_from(0, self.0);
+        walk_block_mut(self, block);
+    }
+}
+
+fn byte_offset(text: &str, chars: usize) -> usize {
+    text.char_indices()
+        .nth(chars)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// The line and column after `text`, starting from `line` and `column`
+fn advance((mut line, mut column): (usize, usize), text: &str) -> (usize, usize) {
+    for c in text.chars() {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
@@ -1,8 +1,27 @@
+use crate::allocations::{AllocationTracker, LeakReport, SiteCount};
 use crate::ast::*;
-use crate::error::{Result, VeyraError};
+use crate::async_io::{self, Pending};
+use crate::builtins;
+use crate::bytecode;
+use crate::error::{Result, Unwind, VeyraError};
+use crate::fiber::{self, Fiber, Suspender, Wait};
+use crate::format;
+use crate::hot_reload::{self, HotReload, Reloaded};
+use crate::modules::ModuleLoader;
+use crate::printer;
+use crate::sandbox::{Capability, Policy};
+use crate::suggest;
+use crate::table::{Dictionary, Set, Table};
+use crate::vm;
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // Reference type for implementing borrowing
 #[derive(Debug, Clone)]
@@ -17,6 +36,19 @@ impl PartialEq for Reference {
     }
 }
 
+/// Reference from `weak_ref` that does not keep its target alive
+#[derive(Debug, Clone)]
+pub struct WeakReference {
+    pub value: Weak<RefCell<Value>>,
+    pub mutable: bool,
+}
+
+impl PartialEq for WeakReference {
+    fn eq(&self, other: &Self) -> bool {
+        self.value.ptr_eq(&other.value)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer(i64),
@@ -26,14 +58,69 @@ pub enum Value {
     Boolean(bool),
     None,
     Array(Vec<Value>),
-    Dictionary(HashMap<String, Value>),
-    Set(std::collections::HashSet<String>),
+    Dictionary(Dictionary),
+    Set(Set),
     Tuple(Vec<Value>),
     Reference(Reference),
+    Weak(WeakReference),
+    /// Handle of a task started with `spawn`
+    Task(usize),
+    /// Handle of an actor started with `spawn`
+    Actor(usize),
+    Struct(StructValue),
+    /// A named function or a lambda, passed around as a value
+    Function(FunctionValue),
+    /// `Some(x)`, `Ok(x)` or `Err(e)`; an empty option is `None`
+    Variant(Variant, Box<Value>),
+}
+
+/// An instance of a user-defined struct, with its fields in declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructValue {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum FunctionValue {
+    /// A user-defined function or builtin, called by name
+    Named(String),
+    Lambda(Rc<Closure>),
+    /// A lambda of bytecode run by the VM
+    Compiled(Rc<vm::Closure>),
+}
+
+/// Two function values are equal when they name the same function or are
+/// the same lambda value
+impl PartialEq for FunctionValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FunctionValue::Named(a), FunctionValue::Named(b)) => a == b,
+            (FunctionValue::Lambda(a), FunctionValue::Lambda(b)) => Rc::ptr_eq(a, b),
+            (FunctionValue::Compiled(a), FunctionValue::Compiled(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// A lambda with copies of the variables it uses, taken when it was created
+#[derive(Debug)]
+pub struct Closure {
+    pub lambda: LambdaExpression,
+    pub captured: HashMap<String, Value>,
+}
+
+impl StructValue {
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, v)| v)
+    }
 }
 
 impl Value {
-    fn type_name(&self) -> &'static str {
+    pub(crate) fn type_name(&self) -> &'static str {
         match self {
             Value::Integer(_) => "int",
             Value::Float(_) => "float",
@@ -52,12 +139,28 @@ impl Value {
                     "&"
                 }
             }
+            Value::Weak(_) => "weak",
+            Value::Task(_) => "task",
+            Value::Actor(_) => "actor",
+            Value::Struct(_) => "struct",
+            Value::Function(_) => "function",
+            Value::Variant(Variant::Some, _) => "option",
+            Value::Variant(Variant::Ok | Variant::Err, _) => "result",
+        }
+    }
+
+    /// The type name that `impl` blocks for the value use: a struct's own
+    /// name, or the name `type_of()` gives for other values
+    pub(crate) fn impl_name(&self) -> &str {
+        match self {
+            Value::Struct(instance) => &instance.name,
+            other => other.type_name(),
         }
     }
 }
 
 impl Value {
-    fn is_truthy(&self) -> bool {
+    pub(crate) fn is_truthy(&self) -> bool {
         match self {
             Value::Boolean(b) => *b,
             Value::None => false,
@@ -70,10 +173,59 @@ impl Value {
             Value::Set(set) => !set.is_empty(),
             Value::Tuple(tuple) => !tuple.is_empty(),
             Value::Reference(r) => r.value.borrow().is_truthy(),
+            // A weak reference is truthy while its target is alive
+            Value::Weak(w) => w.value.strong_count() > 0,
+            Value::Task(_) | Value::Actor(_) | Value::Struct(_) | Value::Function(_) => true,
+            Value::Variant(variant, _) => *variant != Variant::Err,
+        }
+    }
+
+    /// Unambiguous rendering, as `repr()` gives it: strings and chars are
+    /// quoted and escaped, and floats always show a decimal point or exponent
+    pub fn repr(&self) -> String {
+        match self {
+            Value::Float(f) => format!("{:?}", f),
+            Value::String(s) => quote(s, '"'),
+            Value::Char(c) => quote(&c.to_string(), '\''),
+            Value::Reference(r) => {
+                let prefix = if r.mutable { "&mut " } else { "&" };
+                format!("{}{}", prefix, r.value.borrow().repr())
+            }
+            _ => Interpreter::value_to_string(self),
         }
     }
 }
 
+/// Error for a dictionary literal with a key that cannot be hashed
+pub(crate) const UNHASHABLE_KEY: &str =
+    "Dictionary keys must be hashable (string, int, float, bool, char, struct, or an array or tuple of those)";
+/// Error for a set literal with an element that cannot be hashed
+pub(crate) const UNHASHABLE_ELEMENT: &str =
+    "Set elements must be hashable (string, int, float, bool, char, struct, or an array or tuple of those)";
+
+/// A string literal for `text` in the given quotes, with escapes the lexer reads
+pub(crate) fn quote(text: &str, delimiter: char) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push(delimiter);
+    for c in text.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\0' => quoted.push_str("\\0"),
+            '\\' => quoted.push_str("\\\\"),
+            c if c == delimiter => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push(delimiter);
+    quoted
+}
+
 pub struct Environment {
     scopes: Vec<HashMap<String, Value>>,
 }
@@ -95,13 +247,13 @@ impl Environment {
         }
     }
 
-    fn define(&mut self, name: String, value: Value) {
+    pub(crate) fn define(&mut self, name: String, value: Value) {
         if let Some(scope) = self.scopes.last_mut() {
             scope.insert(name, value);
         }
     }
 
-    fn get(&self, name: &str) -> Option<&Value> {
+    pub(crate) fn get(&self, name: &str) -> Option<&Value> {
         for scope in self.scopes.iter().rev() {
             if let Some(value) = scope.get(name) {
                 return Some(value);
@@ -110,23 +262,229 @@ impl Environment {
         None
     }
 
-    fn set(&mut self, name: &str, value: Value) -> Result<()> {
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    pub(crate) fn set(&mut self, name: &str, value: Value) -> Result<()> {
         for scope in self.scopes.iter_mut().rev() {
             if scope.contains_key(name) {
                 scope.insert(name.to_string(), value);
                 return Ok(());
             }
         }
-        Err(VeyraError::runtime_error(format!(
-            "Undefined variable '{}'",
-            name
-        )))
+        Err(self.undefined_variable(name))
+    }
+
+    /// Error for an unknown variable, suggesting similarly named ones in scope
+    pub(crate) fn undefined_variable(&self, name: &str) -> VeyraError {
+        let names = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.keys())
+            .map(String::as_str);
+        VeyraError::runtime_error(format!(
+            "Undefined variable '{}'{}",
+            name,
+            suggest::did_you_mean(&suggest::similar_names(name, names))
+        ))
     }
 }
 
-pub struct Interpreter {
+/// Resource limits for running untrusted code; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    /// Statements and blocks executed
+    pub max_steps: Option<u64>,
+    /// Nested calls of user-defined functions
+    pub max_call_depth: Option<usize>,
+    /// Wall-clock time from `set_limits`
+    pub timeout: Option<Duration>,
+    /// Bytes of output printed; lines past the limit are dropped
+    pub max_output: Option<usize>,
+}
+
+/// How many `trace` entries are kept; older ones are dropped first
+pub const TRACE_CAPACITY: usize = 1000;
+
+/// What `now()` returns in deterministic mode: 2000-01-01T00:00:00Z
+pub const DETERMINISTIC_NOW: i64 = 946_684_800;
+
+/// How long to wait between looks at tasks waiting on background I/O or
+/// timers when nothing else can run
+const IDLE: Duration = Duration::from_millis(1);
+
+/// A value recorded by `trace`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    /// Source of the traced expression, or the label passed to `trace`
+    pub expression: String,
+    pub value: Value,
+    /// Time since the interpreter was created
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:>9.3}ms] {} = {}",
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.expression,
+            self.value.repr()
+        )
+    }
+}
+
+/// A running actor: its state and the messages sent to it but not yet
+/// handled
+struct ActorInstance {
+    actor: Actor,
+    /// Its definition in the bytecode the VM runs, whose methods handle its
+    /// messages in place of the syntax tree's
+    compiled: Option<usize>,
+    state: HashMap<String, Value>,
+    mailbox: VecDeque<Value>,
+    mailbox_config: MailboxConfig,
+}
+
+/// A spawned task: finished, waiting on background I/O or a timer, or code
+/// that has not finished, which is running while it is taken out
+enum Task {
+    Done(Result<Value>),
+    Pending(Pending),
+    Fiber(Box<TaskFiber>),
+    Running,
+}
+
+/// A task's code, with what it keeps to itself while other code runs
+struct TaskFiber {
+    fiber: Fiber,
+    context: Context,
+    /// The module whose code spawned it, or None for the program's, whose
+    /// functions and globals it runs with
+    module: Option<PathBuf>,
+}
+
+/// The state of the running code that belongs to one task: swapped into
+/// the interpreter while its fiber runs, and out when it suspends
+#[derive(Default)]
+struct Context {
+    /// Scopes above the global one
+    scopes: Vec<HashMap<String, Value>>,
+    call_depth: usize,
+    /// Functions running, for the sites of tracked allocations
+    calls: Vec<String>,
+    current_task: Option<usize>,
+    task_groups: Vec<Vec<usize>>,
+    return_value: Option<Value>,
+    thrown: Option<Value>,
+    suspender: Option<(Suspender, usize)>,
+}
+
+/// What running the ready tasks once achieved
+enum Round {
+    /// A task started or finished
+    Progress,
+    /// Only waits on background I/O or timers, which will end
+    Waiting,
+    /// Every task waits on another task
+    Stuck,
+}
+
+/// What a program can find out about a task besides its result
+struct TaskInfo {
+    name: String,
+    /// The task that spawned it, or None for the main program
+    parent: Option<usize>,
+    /// Its `task_local_set` variables, copied from the parent when spawned
+    /// and dropped once it has run
+    locals: HashMap<String, Value>,
+}
+
+/// A module loaded by `import`, with its own functions, globals and
+/// imports. While its code runs they are swapped with the interpreter's, so
+/// a running module holds those of the code that called into it.
+struct Module {
+    name: String,
+    functions: HashMap<String, Function>,
     environment: Environment,
+    imports: HashMap<String, PathBuf>,
+}
+
+pub struct Interpreter {
+    pub(crate) environment: Environment,
     functions: HashMap<String, Function>,
+    /// Modules the running code imported, by the name they are bound to
+    imports: HashMap<String, PathBuf>,
+    /// Modules loaded so far, by file, except those running
+    modules: HashMap<PathBuf, Module>,
+    /// Modules whose code is running, innermost last
+    running_modules: Vec<(PathBuf, Module)>,
+    module_loader: ModuleLoader,
+    structs: HashMap<String, Struct>,
+    /// Methods from `impl` blocks, by type name and method name
+    methods: HashMap<String, HashMap<String, Function>>,
+    traits: HashMap<String, Trait>,
+    /// The traits each type implements
+    pub(crate) implementations: HashMap<String, HashSet<String>>,
+    /// Value of the `return` being unwound to the enclosing call
+    pub(crate) return_value: Option<Value>,
+    /// Value of the `throw` being unwound to the nearest `catch`
+    pub(crate) thrown: Option<Value>,
+    /// Targets of the references created so far, for finding leaked cycles,
+    /// with where they were created while allocations are tracked
+    cells: Vec<(Weak<RefCell<Value>>, Option<usize>)>,
+    allocations: Option<AllocationTracker>,
+    actor_types: HashMap<String, Actor>,
+    actors: Vec<ActorInstance>,
+    /// Bytecode run by the VM, whose functions, methods and actors the
+    /// interpreter calls when the running code names them
+    pub(crate) code: Option<Rc<vm::Code>>,
+    /// Deadlines of the `ask` calls in progress, with the timeout and actor
+    reply_deadlines: Vec<(Instant, Duration, String)>,
+    captured_output: Option<String>,
+    echo_output: bool,
+    limits: Limits,
+    deadline: Option<Instant>,
+    steps: u64,
+    /// Bytes printed, counted against `Limits::max_output`
+    output_bytes: usize,
+    call_depth: usize,
+    /// Outcome of each spawned task, until it is awaited or its group ends
+    tasks: Vec<Option<Task>>,
+    /// Metadata of each spawned task, by id
+    task_info: Vec<TaskInfo>,
+    /// The task whose code is running, or None for the main program
+    current_task: Option<usize>,
+    /// Task-local variables of the main program
+    main_locals: HashMap<String, Value>,
+    /// Tasks spawned in each enclosing `task_group`; the first entry holds
+    /// tasks spawned outside any group, which end with the program
+    pub(crate) task_groups: Vec<Vec<usize>>,
+    /// Tasks with code to run, in the order they take turns
+    ready: VecDeque<usize>,
+    /// Tasks spawned and awaits that got their value, which tell whether
+    /// a task's turn moved it forward
+    advances: usize,
+    /// How the running task hands control back, or None on the main stack,
+    /// with the number of modules running when it was resumed
+    suspender: Option<(Suspender, usize)>,
+    hot_reload: Option<HotReload>,
+    policy: Policy,
+    /// What `args()` returns
+    args: Vec<String>,
+    /// The most recent values recorded by `trace`, oldest first
+    traces: VecDeque<TraceEntry>,
+    created: Instant,
+    /// Set from another thread to stop the running code
+    interrupt: Option<Arc<AtomicBool>>,
+    /// Whether the clock is fixed, so the same program prints the same
+    /// output on every run
+    deterministic: bool,
 }
 
 impl Default for Interpreter {
@@ -140,53 +498,1152 @@ impl Interpreter {
         Self {
             environment: Environment::new(),
             functions: HashMap::new(),
+            imports: HashMap::new(),
+            modules: HashMap::new(),
+            running_modules: Vec::new(),
+            module_loader: ModuleLoader::in_dir(Path::new(".")),
+            structs: HashMap::new(),
+            methods: HashMap::new(),
+            traits: HashMap::new(),
+            implementations: HashMap::new(),
+            return_value: None,
+            thrown: None,
+            cells: Vec::new(),
+            allocations: None,
+            actor_types: HashMap::new(),
+            actors: Vec::new(),
+            code: None,
+            reply_deadlines: Vec::new(),
+            captured_output: None,
+            echo_output: true,
+            limits: Limits::default(),
+            deadline: None,
+            steps: 0,
+            output_bytes: 0,
+            call_depth: 0,
+            tasks: Vec::new(),
+            task_info: Vec::new(),
+            current_task: None,
+            main_locals: HashMap::new(),
+            task_groups: vec![Vec::new()],
+            ready: VecDeque::new(),
+            advances: 0,
+            suspender: None,
+            hot_reload: None,
+            policy: Policy::default(),
+            args: Vec::new(),
+            traces: VecDeque::new(),
+            created: Instant::now(),
+            interrupt: None,
+            deterministic: false,
         }
     }
 
+    /// Keep a copy of everything the program prints until `take_output`
+    #[allow(dead_code)]
+    pub fn capture_output(&mut self) {
+        self.captured_output = Some(String::new());
+    }
+
+    /// Stop capturing and return the output printed since `capture_output`
+    #[allow(dead_code)]
+    pub fn take_output(&mut self) -> String {
+        self.captured_output.take().unwrap_or_default()
+    }
+
+    /// Whether program output goes to stdout (the default). Turn it off to
+    /// only capture it.
+    #[allow(dead_code)]
+    pub fn set_echo_output(&mut self, echo: bool) {
+        self.echo_output = echo;
+    }
+
+    /// Apply resource limits. The step count and time limit start now.
+    #[allow(dead_code)]
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+        self.steps = 0;
+        self.output_bytes = 0;
+    }
+
+    /// Restrict what builtins may access outside the interpreter
+    #[allow(dead_code)]
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+    }
+
+    /// Make runs repeatable for snapshot tests: `now()` returns
+    /// `DETERMINISTIC_NOW` and `trace` records no time. Dictionaries and sets
+    /// are printed in sorted order either way.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// The program's arguments, which `args()` returns
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    /// Values recorded by `trace`, oldest first
+    #[allow(dead_code)]
+    pub fn traces(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.traces.iter()
+    }
+
+    /// Find and compile imported modules with `loader` rather than relative
+    /// to the current directory
+    #[allow(dead_code)]
+    pub fn set_module_loader(&mut self, loader: ModuleLoader) {
+        self.module_loader = loader;
+    }
+
+    /// Swap in definitions from the program's files as they are edited
+    #[allow(dead_code)]
+    pub fn set_hot_reload(&mut self, reload: HotReload) {
+        self.hot_reload = Some(reload);
+    }
+
+    /// Stop the running code with an error whenever `flag` is set, which
+    /// clears it again. Lets another thread interrupt long computations.
+    #[allow(dead_code)]
+    pub fn set_interrupt(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    /// Count one step of execution against the limits
+    pub(crate) fn tick(&mut self) -> Result<()> {
+        self.steps += 1;
+        self.check_limits()?;
+        if !self.reply_deadlines.is_empty() {
+            let now = Instant::now();
+            if let Some((_, timeout, actor)) = self
+                .reply_deadlines
+                .iter()
+                .find(|(deadline, _, _)| now >= *deadline)
+            {
+                return Err(VeyraError::runtime_error(format!(
+                    "Actor '{}' did not reply within {}ms",
+                    actor,
+                    timeout.as_millis()
+                )));
+            }
+        }
+        // Reloading swaps definitions of the entry file and of modules not
+        // running, so wait until only the entry file's code is running
+        let changed = match &mut self.hot_reload {
+            Some(reload) if self.running_modules.is_empty() => reload.poll(),
+            _ => Vec::new(),
+        };
+        for (file, program) in changed {
+            let program = match program {
+                Ok(program) => program,
+                Err(e) => {
+                    eprintln!(
+                        "Hot reload of '{}' failed, keeping the running code: {}",
+                        file.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let reloaded = self.reload(&file, &program);
+            for warning in &reloaded.warnings {
+                eprintln!("Hot reload: {}", warning);
+            }
+            if reloaded.updated > 0 {
+                eprintln!(
+                    "Hot reload: updated {} definition(s) from '{}'",
+                    reloaded.updated,
+                    file.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap in the function and actor definitions of a changed file: a
+    /// loaded module's, or else the running program's. Definitions whose
+    /// signature or fields changed are kept, since running code and state
+    /// depend on them. Only the entry file's code may be running.
+    pub fn reload(&mut self, file: &Path, program: &Program) -> Reloaded {
+        let mut reloaded = Reloaded::default();
+        let functions = match self.modules.get_mut(file) {
+            Some(module) => &mut module.functions,
+            None => &mut self.functions,
+        };
+        for item in &program.items {
+            match item {
+                Item::Function(func) => match functions.get(&func.name) {
+                    Some(old) if !hot_reload::same_signature(old, func) => reloaded.warnings.push(
+                        format!("signature of '{}' changed; restart to apply it", func.name),
+                    ),
+                    Some(old) if old == func => {}
+                    _ => {
+                        functions.insert(func.name.clone(), func.clone());
+                        reloaded.updated += 1;
+                    }
+                },
+                Item::Actor(actor) => match self.actor_types.get(&actor.name) {
+                    Some(old) if old.fields != actor.fields => reloaded.warnings.push(format!(
+                        "fields of actor '{}' changed; restart to apply them",
+                        actor.name
+                    )),
+                    Some(old) if old == actor => {}
+                    _ => {
+                        for instance in &mut self.actors {
+                            if instance.actor.name == actor.name {
+                                instance.actor = actor.clone();
+                            }
+                        }
+                        self.actor_types.insert(actor.name.clone(), actor.clone());
+                        reloaded.updated += 1;
+                    }
+                },
+                _ => {}
+            }
+        }
+        reloaded
+    }
+
+    /// Stop with an error if out of steps, or as `check_stop` does
+    fn check_limits(&mut self) -> Result<()> {
+        if let Some(max) = self.limits.max_steps {
+            if self.steps > max {
+                return Err(VeyraError::halted(format!(
+                    "Step limit of {} exceeded",
+                    max
+                )));
+            }
+        }
+        self.check_stop()
+    }
+
+    /// Stop with an error if interrupted, out of time or out of output
+    fn check_stop(&mut self) -> Result<()> {
+        if let Some(flag) = &self.interrupt {
+            if flag.swap(false, atomic::Ordering::Relaxed) {
+                return Err(VeyraError::halted("Interrupted"));
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                let timeout = self.limits.timeout.unwrap_or_default();
+                return Err(VeyraError::halted(format!(
+                    "Time limit of {}ms exceeded",
+                    timeout.as_millis()
+                )));
+            }
+        }
+        if let Some(max) = self.limits.max_output {
+            if self.output_bytes > max {
+                return Err(VeyraError::halted(format!(
+                    "Output limit of {} bytes exceeded",
+                    max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a line of program output
+    fn emit(&mut self, line: &str) {
+        self.output_bytes += line.len() + 1;
+        if self
+            .limits
+            .max_output
+            .is_some_and(|max| self.output_bytes > max)
+        {
+            return;
+        }
+        if self.echo_output {
+            println!("{}", line);
+        }
+        if let Some(captured) = &mut self.captured_output {
+            captured.push_str(line);
+            captured.push('\n');
+        }
+    }
+
+    /// Look up a user-defined function by name
+    #[allow(dead_code)]
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    /// Names of the user-defined functions
+    #[allow(dead_code)]
+    pub fn function_names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    /// The variables in the global scope, such as those defined at the top
+    /// level of a REPL session
+    #[allow(dead_code)]
+    pub fn globals(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.environment.scopes[0]
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// Error for an unknown function, suggesting similarly named builtins and
+    /// user-defined functions
+    fn undefined_function(&self, name: &str) -> VeyraError {
+        let names = builtins::BUILTINS
+            .iter()
+            .map(|doc| doc.name)
+            .chain(self.functions.keys().map(String::as_str));
+        VeyraError::runtime_error(format!(
+            "Undefined function '{}'{}",
+            name,
+            suggest::did_you_mean(&suggest::similar_names(name, names))
+        ))
+    }
+
+    /// Documentation for a user-defined function or builtin, as shown by `help()`
+    pub fn describe(&self, name: &str) -> Option<String> {
+        match self.functions.get(name) {
+            Some(function) => Some(builtins::format_function(function)),
+            None => builtins::lookup(name).map(builtins::format_builtin),
+        }
+    }
+
+    /// Run a program: its top-level code, then `main` if it takes no
+    /// arguments. Returns what `main` returned, or None.
     #[allow(dead_code)]
     pub fn interpret(&mut self, program: &Program) -> Result<Value> {
-        self.interpret_program(program)?;
-        Ok(Value::None)
+        self.interpret_program(program)
     }
 
-    fn interpret_program(&mut self, program: &Program) -> Result<()> {
-        // First pass: collect all function definitions
+    /// Register a program's functions and imports and run its top-level
+    /// `let` declarations, without running other statements or `main`.
+    /// Test runners use this to call functions one at a time.
+    #[allow(dead_code)]
+    pub fn load_definitions(&mut self, program: &Program) -> Result<()> {
         for item in &program.items {
-            if let Item::Function(func) = item {
-                self.functions.insert(func.name.clone(), func.clone());
+            match item {
+                Item::Function(func) => {
+                    self.functions.insert(func.name.clone(), func.clone());
+                }
+                Item::Actor(actor) => {
+                    self.actor_types.insert(actor.name.clone(), actor.clone());
+                }
+                Item::Import(import) => self.handle_import(import)?,
+                _ => {}
             }
         }
+        self.define_types(&program.items)?;
 
-        // Execute statements and expressions at module level
         for item in &program.items {
+            if let Item::Statement(statement @ Statement::VariableDeclaration(_)) = item {
+                self.execute_statement(statement)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Storage for a new reference
+    fn new_cell(&mut self, value: Value) -> Rc<RefCell<Value>> {
+        let cell = Rc::new(RefCell::new(value));
+        self.track(&cell);
+        cell
+    }
+
+    fn track(&mut self, cell: &Rc<RefCell<Value>>) {
+        // Forget dropped targets before the list has to grow
+        if self.cells.len() == self.cells.capacity() {
+            self.cells.retain(|(cell, _)| cell.strong_count() > 0);
+        }
+        let site = self.allocations.as_mut().map(AllocationTracker::record);
+        self.cells.push((Rc::downgrade(cell), site));
+    }
+
+    /// Record where reference targets are created from now on, for
+    /// `allocation_counts` and `leaks`
+    #[allow(dead_code)]
+    pub fn track_allocations(&mut self) {
+        self.allocations
+            .get_or_insert_with(AllocationTracker::default);
+    }
+
+    /// Reference targets created and still alive at each site since
+    /// `track_allocations`, the most live first
+    #[allow(dead_code)]
+    pub fn allocation_counts(&self) -> Vec<SiteCount> {
+        let Some(tracker) = &self.allocations else {
+            return Vec::new();
+        };
+        let live: Vec<usize> = self
+            .cells
+            .iter()
+            .filter(|(cell, _)| cell.strong_count() > 0)
+            .filter_map(|(_, site)| *site)
+            .collect();
+        tracker.counts(&live)
+    }
+
+    /// Reference targets still alive that the program can no longer reach,
+    /// by where they were created. Targets only held outside the program,
+    /// such as by a value `call` returned, count as leaked.
+    #[allow(dead_code)]
+    pub fn leaks(&self) -> LeakReport {
+        let (cells, _, reachable) = self.reference_graph();
+        let leaked = cells
+            .iter()
+            .zip(reachable)
+            .filter(|(_, reachable)| !reachable)
+            .map(|((cell, site), _)| {
+                let site = site.and_then(|id| Some(self.allocations.as_ref()?.site(id).clone()));
+                (site, describe_cell(&cell.borrow()))
+            })
+            .collect();
+        LeakReport::new(leaked)
+    }
+
+    /// Live reference targets with their sites, the targets each one refers
+    /// to, and whether the program can reach it
+    #[allow(clippy::type_complexity)]
+    fn reference_graph(
+        &self,
+    ) -> (
+        Vec<(Rc<RefCell<Value>>, Option<usize>)>,
+        Vec<Vec<usize>>,
+        Vec<bool>,
+    ) {
+        let cells: Vec<(Rc<RefCell<Value>>, Option<usize>)> = self
+            .cells
+            .iter()
+            .filter_map(|(cell, site)| Some((cell.upgrade()?, *site)))
+            .collect();
+        let index: HashMap<*const RefCell<Value>, usize> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, (cell, _))| (Rc::as_ptr(cell), i))
+            .collect();
+        // Sorted, as dictionaries and closures list their values in no
+        // particular order and the cycles found should not depend on it
+        let indices = |value: &Value| {
+            let mut targets = Vec::new();
+            strong_targets(value, &mut targets);
+            let mut indices: Vec<usize> = targets
+                .iter()
+                .filter_map(|target| index.get(&Rc::as_ptr(target)).copied())
+                .collect();
+            indices.sort_unstable();
+            indices
+        };
+        let edges: Vec<Vec<usize>> = cells
+            .iter()
+            .map(|(cell, _)| indices(&cell.borrow()))
+            .collect();
+
+        let mut reachable = vec![false; cells.len()];
+        let mut stack: Vec<usize> = self.roots().flat_map(indices).collect();
+        while let Some(i) = stack.pop() {
+            if !std::mem::replace(&mut reachable[i], true) {
+                stack.extend(&edges[i]);
+            }
+        }
+        (cells, edges, reachable)
+    }
+
+    /// Groups of reference targets that keep each other alive through
+    /// strong references although the program can no longer reach them.
+    /// Each group lists a short description of every target in it.
+    pub fn memory_cycles(&self) -> Vec<Vec<String>> {
+        let (cells, edges, reachable) = self.reference_graph();
+
+        // A component is reachable as a whole or not at all
+        strongly_connected(&edges)
+            .into_iter()
+            .filter(|group| {
+                !reachable[group[0]] && (group.len() > 1 || edges[group[0]].contains(&group[0]))
+            })
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|&i| describe_cell(&cells[i].0.borrow()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Values the program can still use: variables, including those of
+    /// modules and of callers in other modules, actor state and messages,
+    /// finished tasks and a return in progress
+    fn roots(&self) -> impl Iterator<Item = &Value> {
+        let modules = self
+            .modules
+            .values()
+            .chain(self.running_modules.iter().map(|(_, module)| module));
+        let variables = std::iter::once(&self.environment)
+            .chain(modules.map(|module| &module.environment))
+            .flat_map(|environment| environment.scopes.iter())
+            .flat_map(|scope| scope.values());
+        let actors = self
+            .actors
+            .iter()
+            .flat_map(|actor| actor.state.values().chain(&actor.mailbox));
+        let tasks = self.tasks.iter().filter_map(|task| match task {
+            Some(Task::Done(Ok(value))) => Some(value),
+            _ => None,
+        });
+        let locals = self
+            .task_info
+            .iter()
+            .flat_map(|info| info.locals.values())
+            .chain(self.main_locals.values());
+        variables
+            .chain(actors)
+            .chain(tasks)
+            .chain(locals)
+            .chain(self.return_value.as_ref())
+            .chain(self.thrown.as_ref())
+    }
+
+    /// Register the structs, traits and `impl` blocks of a program. Traits
+    /// come first, so that an impl may precede the trait it implements.
+    fn define_types(&mut self, items: &[Item]) -> Result<()> {
+        for item in items {
             match item {
-                Item::Function(_) => {
-                    // Already handled in first pass
+                Item::Struct(definition) => {
+                    self.structs
+                        .insert(definition.name.clone(), definition.clone());
                 }
-                Item::Statement(statement) => {
-                    // Execute top-level statements
-                    self.execute_statement(statement)?;
+                Item::Trait(definition) => {
+                    self.traits
+                        .insert(definition.name.clone(), definition.clone());
                 }
-                Item::Import(import) => {
-                    self.handle_import(import)?;
+                _ => {}
+            }
+        }
+        for item in items {
+            if let Item::Impl(block) = item {
+                self.define_impl(block)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Register the methods of an `impl` block. For `impl Trait for Type`
+    /// the methods must match the trait's, and the trait's default methods
+    /// fill in those the block leaves out.
+    fn define_impl(&mut self, block: &Impl) -> Result<()> {
+        let mut methods: Vec<Function> = block.methods.clone();
+        if let Some(trait_name) = &block.trait_name {
+            let definition = self.traits.get(trait_name).ok_or_else(|| {
+                let names = self.traits.keys().map(String::as_str);
+                VeyraError::runtime_error(format!(
+                    "Undefined trait '{}'{}",
+                    trait_name,
+                    suggest::did_you_mean(&suggest::similar_names(trait_name, names))
+                ))
+            })?;
+            for method in &block.methods {
+                let Some(required) = definition
+                    .methods
+                    .iter()
+                    .find(|m| m.function.name == method.name)
+                else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Method '{}' is not a member of trait '{}'",
+                        method.name, trait_name
+                    )));
+                };
+                // `self` is bound whether or not it is declared
+                let arity = |function: &Function| {
+                    function
+                        .parameters
+                        .iter()
+                        .filter(|p| p.name != "self")
+                        .count()
+                };
+                if arity(&required.function) != arity(method) {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Method '{}' of trait '{}' takes {} arguments, but the impl for '{}' takes {}",
+                        method.name,
+                        trait_name,
+                        arity(&required.function),
+                        block.target,
+                        arity(method)
+                    )));
+                }
+            }
+            for method in &definition.methods {
+                if block.methods.iter().any(|m| m.name == method.function.name) {
+                    continue;
+                }
+                if !method.has_default {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Missing method '{}' in impl of trait '{}' for '{}'",
+                        method.function.name, trait_name, block.target
+                    )));
+                }
+                methods.push(method.function.clone());
+            }
+            self.implementations
+                .entry(block.target.clone())
+                .or_default()
+                .insert(trait_name.clone());
+        }
+        let defined = self.methods.entry(block.target.clone()).or_default();
+        for method in methods {
+            defined.insert(method.name.clone(), method);
+        }
+        Ok(())
+    }
+
+    /// Call a builtin or user-defined function by name
+    #[allow(dead_code)]
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Value> {
+        let result = self
+            .call_function(name, args)
+            .and_then(|value| self.join_tasks(0).map(|()| value));
+        if result.is_err() {
+            self.cancel_tasks();
+        }
+        result
+    }
+
+    /// Run `expression` as a task, or start the actor it names. The task
+    /// keeps copies of the variables the expression uses, as a lambda does.
+    fn spawn(&mut self, expression: &Expression) -> Result<Value> {
+        if let Expression::Identifier(name) = expression {
+            if self.environment.get(name).is_none() {
+                if let Some(actor) = self.actor_types.get(name) {
+                    return Ok(self.start_actor(actor.clone()));
+                }
+            }
+        }
+        let mut names = UsedNames::default();
+        names.visit_expression(expression);
+        let scope = names
+            .0
+            .into_iter()
+            .filter_map(|name| {
+                let value = self.environment.get(&name)?.clone();
+                Some((name, value))
+            })
+            .collect();
+        let expression = expression.clone();
+        Ok(self.run_task(scope, move |this| this.evaluate_expression(&expression)))
+    }
+
+    /// Start code as a task in the innermost task group, with `scope` over
+    /// the globals. It runs when the code that spawned it awaits something
+    /// not finished, its group ends or the program does, and tasks then take
+    /// turns, each running until it awaits something not finished. A task
+    /// starts with a copy of its parent's task-local variables, and ends
+    /// the tasks it spawned outside any group before it finishes.
+    pub(crate) fn run_task(
+        &mut self,
+        scope: HashMap<String, Value>,
+        run: impl FnOnce(&mut Self) -> Result<Value> + 'static,
+    ) -> Value {
+        let id = self.reserve_task();
+        self.task_info[id].locals = self.locals().clone();
+        let fiber = Fiber::new(move |this, suspender| {
+            this.suspender = Some((suspender, this.running_modules.len()));
+            let result = match run(this) {
+                // Spawning an async builtin waits for its task
+                Ok(Value::Task(task)) => this.await_task(task),
+                result => result,
+            };
+            let joined = this.join_tasks(0);
+            result.and_then(|value| joined.map(|()| value))
+        });
+        let context = Context {
+            scopes: vec![scope],
+            current_task: Some(id),
+            task_groups: vec![Vec::new()],
+            ..Context::default()
+        };
+        self.tasks[id] = Some(Task::Fiber(Box::new(TaskFiber {
+            fiber,
+            context,
+            module: self.running_module().map(Path::to_path_buf),
+        })));
+        self.ready.push_back(id);
+        self.add_to_group(id)
+    }
+
+    /// The file of the module whose code is running, or None for the
+    /// program's
+    fn running_module(&self) -> Option<&Path> {
+        self.running_modules.last().map(|(file, _)| file.as_path())
+    }
+
+    /// Hand control back to the code that resumed the running task, until
+    /// it resumes it again. False, doing nothing, on the main stack, and
+    /// while the task runs code of another module than the one it started
+    /// in, whose functions and globals are swapped in.
+    fn suspend(&mut self, wait: Wait) -> bool {
+        match self.suspender {
+            Some((suspender, modules)) if modules == self.running_modules.len() => {
+                let resumed = suspender.suspend(self, wait);
+                // The frames of the task's calls in progress go on using
+                // `self`, so it must be the interpreter that resumed it
+                assert!(
+                    std::ptr::eq(resumed, self),
+                    "a task was resumed by another interpreter than the one it ran on"
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add a task to the innermost task group
+    fn new_task(&mut self, task: Task) -> Value {
+        let id = self.reserve_task();
+        self.tasks[id] = Some(task);
+        self.add_to_group(id)
+    }
+
+    /// The id of a new task, spawned by the current one, whose result is
+    /// not there yet
+    fn reserve_task(&mut self) -> usize {
+        self.advances += 1;
+        let id = self.tasks.len();
+        self.tasks.push(None);
+        self.task_info.push(TaskInfo {
+            name: format!("task-{}", id),
+            parent: self.current_task,
+            locals: HashMap::new(),
+        });
+        id
+    }
+
+    fn add_to_group(&mut self, id: usize) -> Value {
+        if let Some(group) = self.task_groups.last_mut() {
+            group.push(id);
+        }
+        Value::Task(id)
+    }
+
+    /// Task-local variables of the running task
+    fn locals(&self) -> &HashMap<String, Value> {
+        match self.current_task {
+            Some(id) => &self.task_info[id].locals,
+            None => &self.main_locals,
+        }
+    }
+
+    fn locals_mut(&mut self) -> &mut HashMap<String, Value> {
+        match self.current_task {
+            Some(id) => &mut self.task_info[id].locals,
+            None => &mut self.main_locals,
+        }
+    }
+
+    /// `task_info()`: a dictionary describing a task, or the running code
+    /// when `task` is None
+    fn describe_task(&self, task: Option<usize>) -> Value {
+        let id = |task: Option<usize>| task.map_or(Value::None, |id| Value::Integer(id as i64));
+        let (name, parent, status) = match task {
+            None => ("main".to_string(), None, "running"),
+            Some(task) => {
+                let info = &self.task_info[task];
+                let status = match &self.tasks[task] {
+                    _ if self.current_task == Some(task) => "running",
+                    Some(Task::Done(Ok(_))) => "done",
+                    Some(Task::Done(Err(_))) => "failed",
+                    Some(Task::Pending(_) | Task::Fiber(_)) => "pending",
+                    Some(Task::Running) => "running",
+                    None => "awaited",
+                };
+                (info.name.clone(), info.parent, status)
+            }
+        };
+        let mut map = Dictionary::new();
+        map.insert_str("id".to_string(), id(task));
+        map.insert_str("name".to_string(), Value::String(name));
+        map.insert_str("parent".to_string(), id(parent));
+        map.insert_str("status".to_string(), Value::String(status.to_string()));
+        Value::Dictionary(map)
+    }
+
+    /// Start background work for an async builtin
+    fn start_io(&mut self, work: impl FnOnce() -> async_io::Outcome + Send + 'static) -> Value {
+        self.new_task(Task::Pending(Pending::start(work)))
+    }
+
+    fn start_actor(&mut self, actor: Actor) -> Value {
+        let mailbox_config = MailboxConfig::of(&actor);
+        self.add_actor(actor, None, mailbox_config)
+    }
+
+    /// Start an actor with each field set to its type's zero value
+    pub(crate) fn add_actor(
+        &mut self,
+        actor: Actor,
+        compiled: Option<usize>,
+        mailbox_config: MailboxConfig,
+    ) -> Value {
+        let state = actor
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), zero_value(&field.field_type)))
+            .collect();
+        self.actors.push(ActorInstance {
+            actor,
+            compiled,
+            state,
+            mailbox: VecDeque::new(),
+            mailbox_config,
+        });
+        Value::Actor(self.actors.len() - 1)
+    }
+
+    /// Queue a message, applying the actor's overflow strategy when its
+    /// mailbox is full. Blocking hands the oldest message to the actor
+    /// first, so the sender waits while it is handled.
+    fn send(&mut self, actor: usize, message: Value) -> Result<()> {
+        let instance = &mut self.actors[actor];
+        let config = instance.mailbox_config;
+        if let Some(capacity) = config.capacity {
+            if instance.mailbox.len() >= capacity {
+                match config.overflow {
+                    Overflow::Block => {
+                        if let Some(oldest) = instance.mailbox.pop_front() {
+                            self.deliver(actor, oldest)?;
+                        }
+                    }
+                    Overflow::DropOldest => {
+                        instance.mailbox.pop_front();
+                    }
+                    Overflow::Error => {
+                        return Err(VeyraError::runtime_error(format!(
+                            "Mailbox of actor '{}' is full (capacity {})",
+                            instance.actor.name, capacity
+                        )))
+                    }
+                }
+            }
+        }
+        self.actors[actor].mailbox.push_back(message);
+        Ok(())
+    }
+
+    fn actor_arg(&self, function: &str, value: &Value) -> Result<usize> {
+        match value {
+            Value::Actor(actor) if *actor < self.actors.len() => Ok(*actor),
+            other => Err(VeyraError::runtime_error(format!(
+                "{}() expects an actor, got {}",
+                function,
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Handle the messages already sent to an actor, in order
+    fn drain_mailbox(&mut self, actor: usize) -> Result<()> {
+        while let Some(message) = self.actors[actor].mailbox.pop_front() {
+            self.deliver(actor, message)?;
+        }
+        Ok(())
+    }
+
+    /// Run an actor's `receive(message)` method. The actor's fields are
+    /// variables in the method, and their final values are kept.
+    fn deliver(&mut self, actor: usize, message: Value) -> Result<Value> {
+        let instance = &self.actors[actor];
+        if let Some(compiled) = instance.compiled {
+            let state = instance.state.clone();
+            let (result, state) = vm::receive(self, compiled, state, message);
+            self.actors[actor].state = state;
+            return result;
+        }
+        let name = instance.actor.name.clone();
+        let receive = instance
+            .actor
+            .methods
+            .iter()
+            .find(|method| method.name == "receive")
+            .filter(|method| method.parameters.len() == 1)
+            .cloned()
+            .ok_or_else(|| {
+                VeyraError::runtime_error(format!(
+                    "Actor '{}' has no receive(message) method",
+                    name
+                ))
+            })?;
+
+        let function = format!("{}.receive", name);
+        self.begin_call(&function)?;
+        self.environment.push_scope();
+        for (field, value) in &self.actors[actor].state {
+            self.environment.define(field.clone(), value.clone());
+        }
+        self.environment
+            .define(receive.parameters[0].name.clone(), message);
+
+        let result = self.run_body(&receive.body);
+
+        let fields: Vec<String> = self.actors[actor].state.keys().cloned().collect();
+        for field in fields {
+            if let Some(value) = self.environment.get(&field).cloned() {
+                self.actors[actor].state.insert(field, value);
+            }
+        }
+        self.environment.pop_scope();
+        self.end_call();
+
+        result
+    }
+
+    /// Send a message and wait for the reply, failing if the actor takes
+    /// longer than `timeout`. Messages sent earlier are handled first.
+    fn ask(&mut self, actor: usize, message: Value, timeout: Duration) -> Result<Value> {
+        let name = self.actors[actor].actor.name.clone();
+        self.reply_deadlines
+            .push((Instant::now() + timeout, timeout, name));
+        let result = self
+            .drain_mailbox(actor)
+            .and_then(|_| self.deliver(actor, message));
+        self.reply_deadlines.pop();
+        result
+    }
+
+    /// The value of a task, or its error. Until it is there, the running
+    /// task suspends, or else the tasks that can run here take turns.
+    pub(crate) fn await_task(&mut self, task: usize) -> Result<Value> {
+        loop {
+            self.check_limits()?;
+            let wait = match self.tasks.get_mut(task).and_then(Option::take) {
+                Some(Task::Done(result)) => {
+                    self.advances += 1;
+                    return result;
+                }
+                Some(Task::Pending(pending)) => match pending.wait(Duration::ZERO) {
+                    Some(outcome) => {
+                        self.advances += 1;
+                        return outcome_value(outcome);
+                    }
+                    None => {
+                        self.tasks[task] = Some(Task::Pending(pending));
+                        Wait::Io
+                    }
+                },
+                Some(running) => {
+                    self.tasks[task] = Some(running);
+                    Wait::Task
+                }
+                None => {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Task {} has already been awaited",
+                        task
+                    )))
+                }
+            };
+            if self.suspend(wait) {
+                continue;
+            }
+            match self.run_ready_tasks()? {
+                Round::Progress => {}
+                Round::Waiting => std::thread::sleep(IDLE),
+                Round::Stuck if wait == Wait::Io => std::thread::sleep(IDLE),
+                Round::Stuck => {
+                    let waiter = match self.current_task {
+                        Some(id) => format!("task {}", id),
+                        None => "the program".to_string(),
+                    };
+                    return Err(VeyraError::runtime_error(format!(
+                        "Deadlock: {} waits on tasks that are all waiting",
+                        waiter
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Give each task that can run here a turn, in the order they were
+    /// spawned or last suspended, including tasks spawned during the round.
+    /// Tasks of a module run with its functions and globals, unless its
+    /// code is already running further out.
+    fn run_ready_tasks(&mut self) -> Result<Round> {
+        let mut round = Round::Stuck;
+        let mut turns = self.ready.len();
+        while turns > 0 {
+            turns -= 1;
+            let Some(id) = self.ready.pop_front() else {
+                break;
+            };
+            let module = match &self.tasks[id] {
+                Some(Task::Fiber(fiber)) => fiber.module.clone(),
+                _ => continue,
+            };
+            let (advances, spawned) = (self.advances, self.tasks.len());
+            let step = match module {
+                _ if module.as_deref() == self.running_module() => self.resume_task(id),
+                Some(file) if self.modules.contains_key(&file) => {
+                    self.in_module(&file, |this| Ok(this.resume_task(id)))?
                 }
                 _ => {
-                    // For now, just skip other items like structs, impls, etc.
+                    self.ready.push_back(id);
+                    continue;
+                }
+            };
+            // Tasks spawned during the turn get theirs in this round
+            turns += self.tasks.len() - spawned;
+            match step {
+                _ if self.advances != advances => round = Round::Progress,
+                (true, _) | (_, None) => round = Round::Progress,
+                (false, Some(Wait::Io)) if !matches!(round, Round::Progress) => {
+                    round = Round::Waiting
                 }
+                (false, Some(_)) => {}
+            }
+        }
+        Ok(round)
+    }
+
+    /// Run a task's fiber until it suspends or finishes. Whether it had
+    /// not started, and what it waits on if it suspended.
+    fn resume_task(&mut self, id: usize) -> (bool, Option<Wait>) {
+        let Some(Task::Fiber(mut task)) = self.tasks[id].replace(Task::Running) else {
+            unreachable!("resuming a task without code to run");
+        };
+        let started = task.fiber.started();
+        self.swap_context(&mut task.context);
+        if let Some((suspender, _)) = self.suspender {
+            self.suspender = Some((suspender, self.running_modules.len()));
+        }
+        let step = task.fiber.resume(self);
+        self.swap_context(&mut task.context);
+        match step {
+            fiber::Step::Suspended(wait) => {
+                self.tasks[id] = Some(Task::Fiber(task));
+                self.ready.push_back(id);
+                (!started, Some(wait))
+            }
+            fiber::Step::Finished(result) => {
+                self.task_info[id].locals.clear();
+                self.tasks[id] = Some(Task::Done(result));
+                (true, None)
             }
         }
+    }
+
+    /// Swap the state of the running code with a task's
+    fn swap_context(&mut self, context: &mut Context) {
+        let scopes = self.environment.scopes.split_off(1);
+        self.environment.scopes.append(&mut context.scopes);
+        context.scopes = scopes;
+        std::mem::swap(&mut self.call_depth, &mut context.call_depth);
+        if let Some(tracker) = &mut self.allocations {
+            tracker.swap_stack(&mut context.calls);
+        }
+        std::mem::swap(&mut self.current_task, &mut context.current_task);
+        std::mem::swap(&mut self.task_groups, &mut context.task_groups);
+        std::mem::swap(&mut self.return_value, &mut context.return_value);
+        std::mem::swap(&mut self.thrown, &mut context.thrown);
+        std::mem::swap(&mut self.suspender, &mut context.suspender);
+    }
+
+    /// Drop the tasks that started and did not finish, once the code that
+    /// would have awaited them has stopped with an error
+    pub(crate) fn cancel_tasks(&mut self) {
+        self.ready.clear();
+        for task in &mut self.tasks {
+            if matches!(task, Some(Task::Fiber(fiber)) if fiber.fiber.started()) {
+                *task = Some(Task::Done(Err(VeyraError::runtime_error(
+                    "Task was cancelled",
+                ))));
+            }
+        }
+    }
+
+    /// End the tasks of a group, raising the first error that was not
+    /// already observed through `await`
+    pub(crate) fn join_tasks(&mut self, group: usize) -> Result<()> {
+        let tasks = std::mem::take(&mut self.task_groups[group]);
+        let mut first_error = None;
+        for task in tasks {
+            if self.tasks[task].is_none() {
+                continue;
+            }
+            if let Err(e) = self.await_task(task) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn interpret_program(&mut self, program: &Program) -> Result<Value> {
+        let result = self.run_program(program);
+        if result.is_err() {
+            self.cancel_tasks();
+        }
+        result
+    }
+
+    fn run_program(&mut self, program: &Program) -> Result<Value> {
+        self.run_top_level(program)?;
 
         // Look for a main function and execute it
+        let mut returned = Value::None;
         if let Some(main_func) = self.functions.get("main") {
             if main_func.parameters.is_empty() {
-                self.call_function("main", &[])?;
+                returned = self.call_function("main", &[])?;
             }
         }
 
+        self.finish_program()?;
+        Ok(returned)
+    }
+
+    /// Handle the messages left in actor mailboxes and end the tasks
+    /// spawned outside any task group, once a program's code has run
+    pub(crate) fn finish_program(&mut self) -> Result<()> {
+        for actor in 0..self.actors.len() {
+            self.drain_mailbox(actor)?;
+        }
+        self.join_tasks(0)
+    }
+
+    /// Define a program's functions and types, then run its imports and
+    /// top-level statements in order
+    fn run_top_level(&mut self, program: &Program) -> Result<()> {
+        // First pass: collect all function and actor definitions
+        for item in &program.items {
+            match item {
+                Item::Function(func) => {
+                    self.functions.insert(func.name.clone(), func.clone());
+                }
+                Item::Actor(actor) => {
+                    self.actor_types.insert(actor.name.clone(), actor.clone());
+                }
+                _ => {}
+            }
+        }
+        self.define_types(&program.items)?;
+
+        // Execute statements and expressions at module level
+        for (index, item) in program.items.iter().enumerate() {
+            match item {
+                Item::Function(_) => {
+                    // Already handled in first pass
+                }
+                Item::Statement(statement) => {
+                    // Execute top-level statements
+                    self.execute_statement(statement)
+                        .map_err(|e| e.at_line(program.lines.get(index)))?;
+                }
+                Item::Import(import) => {
+                    self.handle_import(import)
+                        .map_err(|e| e.at_line(program.lines.get(index)))?;
+                }
+                _ => {
+                    // For now, just skip other items like structs, impls, etc.
+                }
+            }
+        }
         Ok(())
     }
 
-    fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value> {
+    pub(crate) fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value> {
         // Handle module functions (e.g., math::abs)
         if name.contains("::") {
             return self.call_module_function(name, args);
@@ -195,14 +1652,43 @@ impl Interpreter {
         // Built-in functions
         match name {
             "print" => {
-                if args.len() != 1 {
-                    return Err(VeyraError::runtime_error(
-                        "print() takes exactly one argument",
-                    ));
-                }
-                println!("{}", Self::value_to_string(&args[0]));
+                let line = match args {
+                    [] => {
+                        return Err(VeyraError::runtime_error(
+                            "print() takes at least one argument",
+                        ))
+                    }
+                    [value] => Self::value_to_string(value),
+                    // A first argument with placeholders is a format string;
+                    // otherwise the values are printed side by side
+                    [Value::String(template), args @ ..] if template.contains('{') => {
+                        format::format(template, args)?
+                    }
+                    values => values
+                        .iter()
+                        .map(Self::value_to_string)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                };
+                self.emit(&line);
                 return Ok(Value::None);
             }
+            "format" => match args {
+                [Value::String(template), args @ ..] => {
+                    return Ok(Value::String(format::format(template, args)?))
+                }
+                [] => {
+                    return Err(VeyraError::runtime_error(
+                        "format() takes at least one argument",
+                    ))
+                }
+                [template, ..] => {
+                    return Err(VeyraError::runtime_error(format!(
+                        "format() requires a format string, got {}",
+                        template.type_name()
+                    )))
+                }
+            },
             "str" => {
                 if args.len() != 1 {
                     return Err(VeyraError::runtime_error(
@@ -211,6 +1697,80 @@ impl Interpreter {
                 }
                 return Ok(Value::String(Self::value_to_string(&args[0])));
             }
+            "repr" => {
+                if args.len() != 1 {
+                    return Err(VeyraError::runtime_error(
+                        "repr() takes exactly one argument",
+                    ));
+                }
+                return Ok(Value::String(args[0].repr()));
+            }
+            "copy" | "deep_copy" => {
+                let [value] = args else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "{}() takes exactly one argument",
+                        name
+                    )));
+                };
+                if name == "copy" {
+                    let copy = shallow_copy(value);
+                    if let Value::Reference(r) = &copy {
+                        self.track(&r.value);
+                    }
+                    return Ok(copy);
+                }
+                let mut copies = HashMap::new();
+                let copy = deep_copy(value, &mut copies);
+                for cell in copies.values() {
+                    self.track(cell);
+                }
+                return Ok(copy);
+            }
+            "weak_ref" => {
+                return match args {
+                    [Value::Reference(r)] => Ok(Value::Weak(WeakReference {
+                        value: Rc::downgrade(&r.value),
+                        mutable: r.mutable,
+                    })),
+                    [other] => Err(VeyraError::runtime_error(format!(
+                        "weak_ref() requires a reference, got {}",
+                        other.type_name()
+                    ))),
+                    _ => Err(VeyraError::runtime_error(
+                        "weak_ref() takes exactly one argument",
+                    )),
+                };
+            }
+            "upgrade" => {
+                return match args {
+                    [Value::Weak(w)] => Ok(w.value.upgrade().map_or(Value::None, |value| {
+                        Value::Reference(Reference {
+                            value,
+                            mutable: w.mutable,
+                        })
+                    })),
+                    [other] => Err(VeyraError::runtime_error(format!(
+                        "upgrade() requires a weak reference, got {}",
+                        other.type_name()
+                    ))),
+                    _ => Err(VeyraError::runtime_error(
+                        "upgrade() takes exactly one argument",
+                    )),
+                };
+            }
+            "memory_cycles" => {
+                if !args.is_empty() {
+                    return Err(VeyraError::runtime_error(
+                        "memory_cycles() takes no arguments",
+                    ));
+                }
+                let cycles = self
+                    .memory_cycles()
+                    .into_iter()
+                    .map(|group| Value::Array(group.into_iter().map(Value::String).collect()))
+                    .collect();
+                return Ok(Value::Array(cycles));
+            }
             "len" => {
                 if args.len() != 1 {
                     return Err(VeyraError::runtime_error(
@@ -264,7 +1824,29 @@ impl Interpreter {
                         "type_of() takes exactly one argument",
                     ));
                 }
-                return Ok(Value::String(args[0].type_name().to_string()));
+                return Ok(Value::String(args[0].type_name().to_string()));
+            }
+            "implements" => {
+                let [value, Value::String(trait_name)] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "implements() takes a value and the name of a trait",
+                    ));
+                };
+                let compiled = self
+                    .code
+                    .as_ref()
+                    .is_some_and(|code| code.has_trait(trait_name));
+                if !self.traits.contains_key(trait_name) && !compiled {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Undefined trait '{}'",
+                        trait_name
+                    )));
+                }
+                let implemented = self
+                    .implementations
+                    .get(value.impl_name())
+                    .is_some_and(|traits| traits.contains(trait_name));
+                return Ok(Value::Boolean(implemented));
             }
             "int" => {
                 if args.len() != 1 {
@@ -362,6 +1944,61 @@ impl Interpreter {
                 }
                 return Ok(Value::Boolean(matches!(args[0], Value::None)));
             }
+            "Some" | "Ok" | "Err" => {
+                let variant = Variant::from_name(name).unwrap_or(Variant::Some);
+                let [value] = args else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "{}() takes exactly one argument",
+                        name
+                    )));
+                };
+                return Ok(Value::Variant(variant, Box::new(value.clone())));
+            }
+            "is_some" | "is_ok" | "is_err" => {
+                let [value] = args else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "{}() takes exactly one argument",
+                        name
+                    )));
+                };
+                let expected = match name {
+                    "is_some" => Variant::Some,
+                    "is_ok" => Variant::Ok,
+                    _ => Variant::Err,
+                };
+                return Ok(Value::Boolean(
+                    matches!(value, Value::Variant(variant, _) if *variant == expected),
+                ));
+            }
+            "unwrap" => {
+                return match args {
+                    [Value::Variant(Variant::Some | Variant::Ok, value)] => Ok(*value.clone()),
+                    [Value::None] => Err(VeyraError::runtime_error("unwrap() called on None")),
+                    [Value::Variant(Variant::Err, error)] => Err(VeyraError::runtime_error(
+                        format!("unwrap() called on Err({})", error.repr()),
+                    )),
+                    [other] => Err(VeyraError::runtime_error(format!(
+                        "unwrap() requires an option or result, got {}",
+                        other.type_name()
+                    ))),
+                    _ => Err(VeyraError::runtime_error(
+                        "unwrap() takes exactly one argument",
+                    )),
+                };
+            }
+            "unwrap_or" => {
+                return match args {
+                    [Value::Variant(Variant::Some | Variant::Ok, value), _] => Ok(*value.clone()),
+                    [Value::None | Value::Variant(Variant::Err, _), default] => Ok(default.clone()),
+                    [other, _] => Err(VeyraError::runtime_error(format!(
+                        "unwrap_or() requires an option or result, got {}",
+                        other.type_name()
+                    ))),
+                    _ => Err(VeyraError::runtime_error(
+                        "unwrap_or() takes exactly two arguments",
+                    )),
+                };
+            }
             "sqrt" => {
                 if args.len() != 1 {
                     return Err(VeyraError::runtime_error(
@@ -602,10 +2239,43 @@ impl Interpreter {
                     _ => return Err(VeyraError::runtime_error("array_avg() requires an array")),
                 }
             }
+            "map" | "filter" => {
+                let [Value::Array(items), Value::Function(function)] = args else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "{}() takes an array and a function",
+                        name
+                    )));
+                };
+                let mut result = Vec::with_capacity(items.len());
+                for item in items {
+                    let value = self.call_value(function, std::slice::from_ref(item))?;
+                    if name == "map" {
+                        result.push(value);
+                    } else if value.is_truthy() {
+                        result.push(item.clone());
+                    }
+                }
+                return Ok(Value::Array(result));
+            }
+            "reduce" => {
+                let [Value::Array(items), Value::Function(function), initial] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "reduce() takes an array, a function and an initial value",
+                    ));
+                };
+                let mut accumulator = initial.clone();
+                for item in items {
+                    accumulator = self.call_value(function, &[accumulator, item.clone()])?;
+                }
+                return Ok(accumulator);
+            }
             "now" => {
                 if !args.is_empty() {
                     return Err(VeyraError::runtime_error("now() takes no arguments"));
                 }
+                if self.deterministic {
+                    return Ok(Value::Integer(DETERMINISTIC_NOW));
+                }
                 // Return current Unix timestamp in seconds
                 use std::time::{SystemTime, UNIX_EPOCH};
                 let timestamp = SystemTime::now()
@@ -614,6 +2284,246 @@ impl Interpreter {
                     .as_secs();
                 return Ok(Value::Integer(timestamp as i64));
             }
+            "read_file" => {
+                let [Value::String(path)] = args else {
+                    return Err(VeyraError::runtime_error("read_file() takes a path string"));
+                };
+                self.policy.check(Capability::Read(path.as_ref()))?;
+                return std::fs::read_to_string(path)
+                    .map(Value::String)
+                    .map_err(|e| {
+                        VeyraError::runtime_error(format!("Cannot read '{}': {}", path, e))
+                    });
+            }
+            "read_file_async" => {
+                let [Value::String(path)] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "read_file_async() takes a path string",
+                    ));
+                };
+                self.policy.check(Capability::Read(path.as_ref()))?;
+                let path = path.clone();
+                return Ok(self.start_io(move || async_io::read_file(&path)));
+            }
+            "http_get_async" => {
+                let [Value::String(url)] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "http_get_async() takes a URL string",
+                    ));
+                };
+                let (host, _, _) =
+                    async_io::parse_http_url(url).map_err(VeyraError::runtime_error)?;
+                self.policy.check(Capability::Network(host))?;
+                let url = url.clone();
+                return Ok(self.start_io(move || async_io::http_get(&url)));
+            }
+            "sleep_async" => {
+                let [Value::Integer(ms)] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "sleep_async() takes a number of milliseconds",
+                    ));
+                };
+                let duration = Duration::from_millis((*ms).max(0) as u64);
+                return Ok(self.new_task(Task::Pending(Pending::timer(duration))));
+            }
+            "task_id" => {
+                if !args.is_empty() {
+                    return Err(VeyraError::runtime_error("task_id() takes no arguments"));
+                }
+                return Ok(self
+                    .current_task
+                    .map_or(Value::None, |id| Value::Integer(id as i64)));
+            }
+            "task_name" => {
+                let name = match args {
+                    [] => match self.current_task {
+                        Some(id) => self.task_info[id].name.clone(),
+                        None => "main".to_string(),
+                    },
+                    [Value::Task(task)] => self.task_info[*task].name.clone(),
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "task_name() takes no arguments or a task",
+                        ))
+                    }
+                };
+                return Ok(Value::String(name));
+            }
+            "set_task_name" => {
+                let [Value::String(name)] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "set_task_name() takes a name string",
+                    ));
+                };
+                let Some(id) = self.current_task else {
+                    return Err(VeyraError::runtime_error(
+                        "set_task_name() can only be called in a spawned task",
+                    ));
+                };
+                self.task_info[id].name = name.clone();
+                return Ok(Value::None);
+            }
+            "task_info" => {
+                return match args {
+                    [] => Ok(self.describe_task(self.current_task)),
+                    [Value::Task(task)] => Ok(self.describe_task(Some(*task))),
+                    _ => Err(VeyraError::runtime_error(
+                        "task_info() takes no arguments or a task",
+                    )),
+                };
+            }
+            "task_local_set" => {
+                let [key, value] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "task_local_set() takes a key and a value",
+                    ));
+                };
+                let Some(key) = self.typed_key(key)? else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "A {} cannot be a task-local key",
+                        key.type_name()
+                    )));
+                };
+                self.locals_mut().insert(key, value.clone());
+                return Ok(Value::None);
+            }
+            "task_local_get" => {
+                let (key, default) = match args {
+                    [key] => (key, Value::None),
+                    [key, default] => (key, default.clone()),
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "task_local_get() takes a key and an optional default",
+                        ))
+                    }
+                };
+                let value = match self.typed_key(key)? {
+                    Some(key) => self.locals().get(&key).cloned(),
+                    None => None,
+                };
+                return Ok(value.unwrap_or(default));
+            }
+            "write_file" => {
+                let [Value::String(path), content] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "write_file() takes a path string and the content",
+                    ));
+                };
+                self.policy.check(Capability::Write(path.as_ref()))?;
+                std::fs::write(path, Self::value_to_string(content)).map_err(|e| {
+                    VeyraError::runtime_error(format!("Cannot write '{}': {}", path, e))
+                })?;
+                return Ok(Value::None);
+            }
+            "env_var" => {
+                let [Value::String(name)] = args else {
+                    return Err(VeyraError::runtime_error("env_var() takes a variable name"));
+                };
+                self.policy.check(Capability::Env(name))?;
+                return Ok(std::env::var(name).map_or(Value::None, Value::String));
+            }
+            "args" => {
+                if !args.is_empty() {
+                    return Err(VeyraError::runtime_error("args() takes no arguments"));
+                }
+                return Ok(Value::Array(
+                    self.args.iter().cloned().map(Value::String).collect(),
+                ));
+            }
+            "exit" => {
+                let code = match args {
+                    [] => 0,
+                    [Value::Integer(code)] => i32::try_from(*code).map_err(|_| {
+                        VeyraError::runtime_error(format!("exit() code {} is out of range", code))
+                    })?,
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "exit() takes an optional integer exit code",
+                        ))
+                    }
+                };
+                return Err(VeyraError::Exit(code));
+            }
+            "trace" => {
+                let (value, expression) = match args {
+                    [value] => (value, value.repr()),
+                    [value, Value::String(label)] => (value, label.clone()),
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "trace() takes a value and an optional label",
+                        ))
+                    }
+                };
+                if self.traces.len() == TRACE_CAPACITY {
+                    self.traces.pop_front();
+                }
+                self.traces.push_back(TraceEntry {
+                    expression,
+                    value: value.clone(),
+                    elapsed: match self.deterministic {
+                        true => Duration::ZERO,
+                        false => self.created.elapsed(),
+                    },
+                });
+                return Ok(value.clone());
+            }
+            "trace_dump" => {
+                if !args.is_empty() {
+                    return Err(VeyraError::runtime_error("trace_dump() takes no arguments"));
+                }
+                let lines: Vec<String> = self.traces.iter().map(|t| t.to_string()).collect();
+                for line in lines {
+                    self.emit(&line);
+                }
+                return Ok(Value::None);
+            }
+            "exec" => {
+                let (program, arguments) = match args {
+                    [Value::String(program)] => (program, &[][..]),
+                    [Value::String(program), Value::Array(arguments)] => (program, &arguments[..]),
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "exec() takes a program and an optional array of arguments",
+                        ))
+                    }
+                };
+                self.policy.check(Capability::Process(program))?;
+                let output = std::process::Command::new(program)
+                    .args(arguments.iter().map(Self::value_to_string))
+                    .output()
+                    .map_err(|e| {
+                        VeyraError::runtime_error(format!("Cannot run '{}': {}", program, e))
+                    })?;
+                if !output.status.success() {
+                    return Err(VeyraError::runtime_error(format!(
+                        "'{}' failed ({}): {}",
+                        program,
+                        output.status,
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                return Ok(Value::String(
+                    String::from_utf8_lossy(&output.stdout).into_owned(),
+                ));
+            }
+            "tcp_request" => {
+                let [Value::String(address), data] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "tcp_request() takes a \"host:port\" address and the data to send",
+                    ));
+                };
+                let host = address
+                    .rsplit_once(':')
+                    .map_or(address.as_str(), |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                self.policy.check(Capability::Network(host))?;
+                return tcp_request(address, &Self::value_to_string(data))
+                    .map(Value::String)
+                    .map_err(|e| {
+                        VeyraError::runtime_error(format!("Request to '{}' failed: {}", address, e))
+                    });
+            }
             "range" => {
                 if args.is_empty() || args.len() > 3 {
                     return Err(VeyraError::runtime_error(
@@ -670,93 +2580,518 @@ impl Interpreter {
                     }
                 }
 
-                return Ok(Value::Array(result));
-            }
-            _ => {}
+                return Ok(Value::Array(result));
+            }
+            "send" => {
+                let [actor, message] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "send() takes an actor and a message",
+                    ));
+                };
+                let actor = self.actor_arg("send", actor)?;
+                self.send(actor, message.clone())?;
+                return Ok(Value::None);
+            }
+            "ask" => {
+                let [actor, message, timeout] = args else {
+                    return Err(VeyraError::runtime_error(
+                        "ask() takes an actor, a message and a timeout in milliseconds",
+                    ));
+                };
+                let actor = self.actor_arg("ask", actor)?;
+                let timeout = match timeout {
+                    Value::Integer(ms) if *ms >= 0 => Duration::from_millis(*ms as u64),
+                    Value::Float(ms) if *ms >= 0.0 => Duration::from_secs_f64(ms / 1000.0),
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "ask() timeout must be a non-negative number of milliseconds",
+                        ))
+                    }
+                };
+                return self.ask(actor, message.clone(), timeout);
+            }
+            "assert" => {
+                let message = match args {
+                    [condition] | [condition, _] if condition.is_truthy() => {
+                        return Ok(Value::None)
+                    }
+                    [_] => "Assertion failed".to_string(),
+                    [_, message] => {
+                        format!("Assertion failed: {}", Self::value_to_string(message))
+                    }
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "assert() takes a condition and an optional message",
+                        ))
+                    }
+                };
+                return Err(VeyraError::runtime_error(message));
+            }
+            "assert_eq" => {
+                if args.len() != 2 {
+                    return Err(VeyraError::runtime_error(
+                        "assert_eq() takes exactly two arguments",
+                    ));
+                }
+                if self.values_equal(&args[0], &args[1])? {
+                    return Ok(Value::None);
+                }
+                return Err(VeyraError::runtime_error(format!(
+                    "Assertion failed: {} != {}",
+                    args[0].repr(),
+                    args[1].repr()
+                )));
+            }
+            "help" => {
+                match args {
+                    [] => {
+                        let names: Vec<&str> =
+                            builtins::BUILTINS.iter().map(|doc| doc.name).collect();
+                        self.emit(&format!("Builtin functions: {}", names.join(", ")));
+                        self.emit("Use help(name) for details.");
+                    }
+                    [Value::String(name)] => {
+                        let doc = self.describe(name).ok_or_else(|| {
+                            VeyraError::runtime_error(format!(
+                                "help(): no documentation for '{}'",
+                                name
+                            ))
+                        })?;
+                        self.emit(&doc);
+                    }
+                    [_] => {
+                        return Err(VeyraError::runtime_error("help() requires a function name"))
+                    }
+                    _ => {
+                        return Err(VeyraError::runtime_error(
+                            "help() takes at most one argument",
+                        ))
+                    }
+                }
+                return Ok(Value::None);
+            }
+            _ => {}
+        }
+
+        let Some(function) = self.functions.get(name).cloned() else {
+            if let Some(chunk) = self.compiled_function(name) {
+                return vm::call(self, chunk, None, args);
+            }
+            return Err(self.undefined_function(name));
+        };
+        self.invoke(name, &function, None, args)
+    }
+
+    /// The chunk of a function of the bytecode the VM runs. Code of
+    /// imported modules, which the interpreter runs, does not see them.
+    pub(crate) fn compiled_function(&self, name: &str) -> Option<u32> {
+        let code = self.code.as_ref()?;
+        match self.running_modules.is_empty() {
+            true => code.function(name),
+            false => None,
+        }
+    }
+
+    /// Call a method from an `impl` block of the receiver's type
+    pub(crate) fn call_method(
+        &mut self,
+        receiver: Value,
+        method: &str,
+        args: &[Value],
+    ) -> Result<Value> {
+        // Methods are looked up on the receiver's type when called, so a
+        // trait method runs the implementation for whatever value it is given
+        let function = self
+            .methods
+            .get(receiver.impl_name())
+            .and_then(|methods| methods.get(method))
+            .cloned();
+        if function.is_none() {
+            if let Some(chunk) = self.compiled_method(receiver.impl_name(), method) {
+                return vm::call(self, chunk, Some(receiver), args);
+            }
+        }
+        let function = match (function, &receiver) {
+            (Some(function), _) => function,
+            (None, Value::Struct(instance)) => {
+                return Err(VeyraError::runtime_error(format!(
+                    "Struct '{}' has no method '{}'",
+                    instance.name, method
+                )))
+            }
+            (None, other) => {
+                return Err(VeyraError::runtime_error(format!(
+                    "Cannot call method '{}' on {}",
+                    method,
+                    other.type_name()
+                )))
+            }
+        };
+        self.invoke(method, &function, Some(receiver), args)
+    }
+
+    /// Whether the struct type has a method, like `__eq__`
+    fn has_method(&self, type_name: &str, method: &str) -> bool {
+        self.methods
+            .get(type_name)
+            .is_some_and(|methods| methods.contains_key(method))
+            || self.compiled_method(type_name, method).is_some()
+    }
+
+    /// The chunk of a method of the bytecode the VM runs
+    fn compiled_method(&self, type_name: &str, method: &str) -> Option<u32> {
+        self.code.as_ref()?.method(type_name, method)
+    }
+
+    /// Run a user-defined function with its parameters bound to `args`; a
+    /// method's receiver is bound to `self`, whether or not it is declared
+    fn invoke(
+        &mut self,
+        name: &str,
+        function: &Function,
+        receiver: Option<Value>,
+        args: &[Value],
+    ) -> Result<Value> {
+        let parameters = match (&receiver, function.parameters.split_first()) {
+            (Some(_), Some((first, rest))) if first.name == "self" => rest,
+            _ => &function.parameters[..],
+        };
+
+        if args.len() != parameters.len() {
+            return Err(VeyraError::runtime_error(format!(
+                "Function '{}' expects {} arguments, got {}",
+                name,
+                parameters.len(),
+                args.len()
+            )));
+        }
+
+        self.begin_call(name)?;
+        // Create new scope for function
+        self.environment.push_scope();
+
+        // Bind parameters
+        if let Some(receiver) = receiver {
+            self.environment.define("self".to_string(), receiver);
+        }
+        for (param, arg) in parameters.iter().zip(args) {
+            self.environment.define(param.name.clone(), arg.clone());
+        }
+
+        let result = self.run_body(&function.body);
+
+        // Clean up scope
+        self.environment.pop_scope();
+        self.end_call();
+
+        result
+    }
+
+    /// Execute a function body in the current scope and return its value
+    fn run_body(&mut self, body: &Block) -> Result<Value> {
+        match self.execute_block(body) {
+            Ok(_) => Ok(Value::None), // Function completed without return
+            Err(VeyraError::Unwind(Unwind::Return)) => {
+                Ok(self.return_value.take().unwrap_or(Value::None))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Count a call of the Veyra function `name` against the depth limit
+    /// and note it for the sites of tracked allocations
+    pub(crate) fn begin_call(&mut self, name: &str) -> Result<()> {
+        if let Some(max) = self.limits.max_call_depth {
+            if self.call_depth >= max {
+                let place = match name {
+                    "<lambda>" => "a lambda".to_string(),
+                    name => format!("'{}'", name),
+                };
+                return Err(VeyraError::halted(format!(
+                    "Call depth limit of {} exceeded in {}",
+                    max, place
+                )));
+            }
+        }
+        self.call_depth += 1;
+        if let Some(tracker) = &mut self.allocations {
+            tracker.enter(name);
+        }
+        Ok(())
+    }
+
+    /// End a call started with `begin_call`
+    pub(crate) fn end_call(&mut self) {
+        self.call_depth -= 1;
+        if let Some(tracker) = &mut self.allocations {
+            tracker.leave();
+        }
+    }
+
+    /// A lambda value holding copies of the variables its body uses
+    fn closure(&self, lambda: &LambdaExpression) -> Value {
+        let mut names = UsedNames::default();
+        match &lambda.body {
+            LambdaBody::Expression(body) => names.visit_expression(body),
+            LambdaBody::Block(body) => names.visit_block(body),
+        }
+        let captured = names
+            .0
+            .into_iter()
+            .filter(|name| !lambda.parameters.iter().any(|p| p.name == *name))
+            .filter_map(|name| {
+                let value = self.environment.get(&name)?.clone();
+                Some((name, value))
+            })
+            .collect();
+        Value::Function(FunctionValue::Lambda(Rc::new(Closure {
+            lambda: lambda.clone(),
+            captured,
+        })))
+    }
+
+    /// Call a function value: a named function as if called by its name, or
+    /// a lambda with its captured variables in scope
+    pub(crate) fn call_value(&mut self, function: &FunctionValue, args: &[Value]) -> Result<Value> {
+        let closure = match function {
+            FunctionValue::Named(name) => return self.call_function(name, args),
+            FunctionValue::Lambda(closure) => closure.clone(),
+            FunctionValue::Compiled(closure) => return vm::call_closure(self, closure, args),
+        };
+        let parameters = &closure.lambda.parameters;
+
+        if args.len() != parameters.len() {
+            return Err(VeyraError::runtime_error(format!(
+                "Lambda expects {} arguments, got {}",
+                parameters.len(),
+                args.len()
+            )));
+        }
+
+        self.begin_call("<lambda>")?;
+        self.environment.push_scope();
+        for (name, value) in &closure.captured {
+            self.environment.define(name.clone(), value.clone());
+        }
+        for (param, arg) in parameters.iter().zip(args) {
+            self.environment.define(param.name.clone(), arg.clone());
+        }
+
+        let result = match &closure.lambda.body {
+            LambdaBody::Expression(body) => match self.evaluate_expression(body) {
+                // `?` in the body returns from the lambda
+                Err(VeyraError::Unwind(Unwind::Return)) => {
+                    Ok(self.return_value.take().unwrap_or(Value::None))
+                }
+                result => result,
+            },
+            LambdaBody::Block(body) => self.run_body(body),
+        };
+
+        self.environment.pop_scope();
+        self.end_call();
+
+        result
+    }
+
+    /// Call `module::item`: a function of an imported module, or a builtin
+    /// of a standard module
+    pub(crate) fn call_module_item(
+        &mut self,
+        module: &str,
+        item: &str,
+        args: &[Value],
+    ) -> Result<Value> {
+        if self.imports.contains_key(module) {
+            let name = format!("{}::{}", module, item);
+            return self.call_module_function(&name, args);
         }
+        // For stdlib functions, they're loaded globally, so call by item name
+        self.call_function(item, args)
+    }
 
-        let function = self
-            .functions
+    /// Call `module::function` in the imported module's namespace
+    fn call_module_function(&mut self, name: &str, args: &[Value]) -> Result<Value> {
+        let (module, item) = name.rsplit_once("::").unwrap_or(("", name));
+        let file = self.imported_module(module)?;
+        self.in_module(&file, |this| {
+            let function = this.module_function(module, item)?;
+            this.invoke(name, &function, None, args)
+        })
+    }
+
+    /// The file of a module the running code imported as `name`
+    fn imported_module(&self, name: &str) -> Result<PathBuf> {
+        self.imports
             .get(name)
-            .ok_or_else(|| VeyraError::runtime_error(format!("Undefined function '{}'", name)))?
-            .clone();
+            .cloned()
+            .ok_or_else(|| VeyraError::runtime_error(format!("Module '{}' is not imported", name)))
+    }
+
+    /// A public function of the running module, which `module` names
+    fn module_function(&self, module: &str, item: &str) -> Result<Function> {
+        check_public(module, item)?;
+        self.functions.get(item).cloned().ok_or_else(|| {
+            VeyraError::runtime_error(format!("Module '{}' has no function '{}'", module, item))
+        })
+    }
 
-        if args.len() != function.parameters.len() {
+    /// Run code of the module loaded from `file` with its functions, globals
+    /// and imports in place of the caller's
+    fn in_module<T>(&mut self, file: &Path, run: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let Some(mut module) = self.modules.remove(file) else {
             return Err(VeyraError::runtime_error(format!(
-                "Function '{}' expects {} arguments, got {}",
-                name,
-                function.parameters.len(),
-                args.len()
+                "Module '{}' is already running",
+                file.display()
             )));
-        }
+        };
+        self.swap_module(&mut module);
+        self.running_modules.push((file.to_path_buf(), module));
 
-        // Create new scope for function
-        self.environment.push_scope();
+        // A line a runtime error gives is in the module's file
+        let result = run(self).map_err(|error| match error {
+            VeyraError::AtLine { .. } => VeyraError::InModule {
+                file: file.to_path_buf(),
+                error: Box::new(error),
+            },
+            error => error,
+        });
 
-        // Bind parameters
-        for (param, arg) in function.parameters.iter().zip(args) {
-            self.environment.define(param.name.clone(), arg.clone());
-        }
+        let (file, mut module) = self.running_modules.pop().expect("the module pushed above");
+        self.swap_module(&mut module);
+        self.modules.insert(file, module);
+        result
+    }
 
-        // Execute function body
-        let result = match self.execute_block(&function.body) {
-            Ok(_) => Ok(Value::None), // Function completed without return
-            Err(VeyraError::RuntimeError { message }) if message.starts_with("return:") => {
-                // Parse return value from error message
-                let value_part = &message[7..]; // Remove "return:" prefix
-                self.parse_return_value(value_part)
-            }
-            Err(e) => Err(e),
+    fn swap_module(&mut self, module: &mut Module) {
+        std::mem::swap(&mut self.functions, &mut module.functions);
+        std::mem::swap(&mut self.environment, &mut module.environment);
+        std::mem::swap(&mut self.imports, &mut module.imports);
+    }
+
+    /// `module::item` as a value: a global of the module, or one of its
+    /// functions
+    fn module_item(&mut self, module: &str, item: &str) -> Result<Value> {
+        let file = self.imported_module(module)?;
+        check_public(module, item)?;
+        let Some(loaded) = self.modules.get(&file) else {
+            return Err(VeyraError::runtime_error(format!(
+                "Module '{}' is already running",
+                module
+            )));
         };
+        if let Some(value) = loaded.environment.get(item) {
+            return Ok(value.clone());
+        }
+        if loaded.functions.contains_key(item) {
+            let name = format!("{}::{}", module, item);
+            return Ok(Value::Function(FunctionValue::Named(name)));
+        }
+        Err(VeyraError::runtime_error(format!(
+            "Module '{}' has no item '{}'",
+            loaded.name, item
+        )))
+    }
 
-        // Clean up scope
+    /// Execute a block in a scope of its own
+    fn execute_scoped(&mut self, block: &Block) -> Result<()> {
+        self.environment.push_scope();
+        let result = self.execute_block(block);
         self.environment.pop_scope();
-
         result
     }
 
-    fn call_module_function(&mut self, name: &str, _args: &[Value]) -> Result<Value> {
-        // Module functions are now implemented in Veyra stdlib files
-        // This function is kept for future extensibility but currently unused
-        Err(VeyraError::runtime_error(format!(
-            "Undefined function '{}'",
-            name
-        )))
+    /// The value a `catch` clause binds for `error`: the thrown value, or
+    /// the message of any other runtime error. None if `catch` cannot stop
+    /// the error.
+    pub(crate) fn caught(&mut self, error: &VeyraError) -> Option<Value> {
+        let message = error.catchable_message()?;
+        let thrown = match error {
+            VeyraError::InModule { error, .. } | VeyraError::AtLine { error, .. } => {
+                return self.caught(error)
+            }
+            VeyraError::Thrown { .. } => self.thrown.take(),
+            _ => None,
+        };
+        Some(thrown.unwrap_or_else(|| Value::String(message.to_string())))
     }
 
     fn execute_block(&mut self, block: &Block) -> Result<()> {
-        for statement in &block.statements {
-            self.execute_statement(statement)?;
+        self.tick()?;
+        for (index, statement) in block.statements.iter().enumerate() {
+            self.execute_statement(statement)
+                .map_err(|e| e.at_line(block.lines.get(index)))?;
         }
         Ok(())
     }
 
     fn execute_statement(&mut self, statement: &Statement) -> Result<()> {
+        self.tick()?;
         match statement {
             Statement::Expression(expr_stmt) => {
                 self.evaluate_expression(&expr_stmt.expression)?;
             }
             Statement::VariableDeclaration(var_decl) => {
                 let value = self.evaluate_expression(&var_decl.initializer)?;
-                self.environment.define(var_decl.name.clone(), value);
+                match &var_decl.pattern {
+                    Some(pattern) => {
+                        let mut bindings = Vec::new();
+                        if !self.match_pattern(pattern, &value, &mut bindings)? {
+                            return Err(VeyraError::runtime_error(format!(
+                                "Cannot destructure {} '{}' with this pattern",
+                                value.type_name(),
+                                Self::value_to_string(&value)
+                            )));
+                        }
+                        for (name, value) in bindings {
+                            self.environment.define(name, value);
+                        }
+                    }
+                    None => self.environment.define(var_decl.name.clone(), value),
+                }
             }
             Statement::Assignment(assignment) => {
+                // `s = s + a + b` appends like `s += a` and `s += b`. Only when
+                // the pieces are literals and variables: evaluating anything
+                // else could rebind `s` before it is read.
+                if let (Expression::Identifier(name), AssignmentOperator::Assign) =
+                    (&assignment.target, &assignment.operator)
+                {
+                    if let Some(pieces) = appended_pieces(name, &assignment.value) {
+                        if pieces.iter().all(|piece| {
+                            matches!(piece, Expression::Literal(_) | Expression::Identifier(_))
+                        }) && matches!(self.environment.get(name), Some(Value::String(_)))
+                        {
+                            let mut tail = Some(String::new());
+                            for piece in pieces {
+                                match (self.evaluate_expression(piece)?, &mut tail) {
+                                    (Value::String(text), Some(tail)) => tail.push_str(&text),
+                                    _ => tail = None,
+                                }
+                            }
+                            // Otherwise `+` means something else; evaluate it
+                            // in full below
+                            if let Some(tail) = tail {
+                                if self.append_in_place(name, &Value::String(tail)) {
+                                    return Ok(());
+                                }
+                            }
+                        }
+                    }
+                }
+
                 let value = self.evaluate_expression(&assignment.value)?;
                 match &assignment.target {
                     Expression::Identifier(name) => match assignment.operator {
                         AssignmentOperator::Assign => {
                             self.environment.set(name, value)?;
                         }
+                        AssignmentOperator::AddAssign if self.append_in_place(name, &value) => {}
                         AssignmentOperator::AddAssign => {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.add_values(&old_value, &value)?;
                             self.environment.set(name, new_value)?;
@@ -765,12 +3100,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::Subtract,
@@ -783,12 +3113,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::Multiply,
@@ -801,12 +3126,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::Divide,
@@ -815,16 +3135,24 @@ impl Interpreter {
                             )?;
                             self.environment.set(name, new_value)?;
                         }
+                        AssignmentOperator::FloorDivAssign => {
+                            let old_value = self
+                                .environment
+                                .get(name)
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
+                                .clone();
+                            let new_value = self.apply_binary_operator(
+                                &BinaryOperator::FloorDivide,
+                                &old_value,
+                                &value,
+                            )?;
+                            self.environment.set(name, new_value)?;
+                        }
                         AssignmentOperator::ModAssign => {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::Modulo,
@@ -837,12 +3165,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::BitwiseAnd,
@@ -855,12 +3178,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::BitwiseOr,
@@ -873,12 +3191,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::BitwiseXor,
@@ -891,12 +3204,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::LeftShift,
@@ -909,12 +3217,7 @@ impl Interpreter {
                             let old_value = self
                                 .environment
                                 .get(name)
-                                .ok_or_else(|| {
-                                    VeyraError::runtime_error(format!(
-                                        "Undefined variable '{}'",
-                                        name
-                                    ))
-                                })?
+                                .ok_or_else(|| self.environment.undefined_variable(name))?
                                 .clone();
                             let new_value = self.apply_binary_operator(
                                 &BinaryOperator::RightShift,
@@ -924,11 +3227,7 @@ impl Interpreter {
                             self.environment.set(name, new_value)?;
                         }
                     },
-                    _ => {
-                        return Err(VeyraError::runtime_error(
-                            "Complex assignment targets not implemented yet",
-                        ));
-                    }
+                    target => self.assign_element(target, &assignment.operator, value)?,
                 }
             }
             Statement::If(if_stmt) => {
@@ -954,11 +3253,8 @@ impl Interpreter {
             Statement::While(while_stmt) => {
                 while self.evaluate_expression(&while_stmt.condition)?.is_truthy() {
                     match self.execute_block(&while_stmt.body) {
-                        Ok(()) => {}
-                        Err(VeyraError::RuntimeError { message }) if message == "break" => break,
-                        Err(VeyraError::RuntimeError { message }) if message == "continue" => {
-                            continue
-                        }
+                        Ok(()) | Err(VeyraError::Unwind(Unwind::Continue)) => {}
+                        Err(VeyraError::Unwind(Unwind::Break)) => break,
                         Err(e) => return Err(e),
                     }
                 }
@@ -970,15 +3266,8 @@ impl Interpreter {
                         for item in arr {
                             self.environment.define(for_stmt.variable.clone(), item);
                             match self.execute_block(&for_stmt.body) {
-                                Ok(()) => {}
-                                Err(VeyraError::RuntimeError { message }) if message == "break" => {
-                                    break
-                                }
-                                Err(VeyraError::RuntimeError { message })
-                                    if message == "continue" =>
-                                {
-                                    continue
-                                }
+                                Ok(()) | Err(VeyraError::Unwind(Unwind::Continue)) => {}
+                                Err(VeyraError::Unwind(Unwind::Break)) => break,
                                 Err(e) => return Err(e),
                             }
                         }
@@ -996,46 +3285,189 @@ impl Interpreter {
                 } else {
                     Value::None
                 };
-                // Use error mechanism to bubble up return value (hack)
-                return Err(VeyraError::runtime_error(format!(
-                    "return:{}",
-                    Self::value_to_string(&value)
-                )));
+                // Unwind to the caller, which takes the value from
+                // `return_value`
+                self.return_value = Some(value);
+                return Err(VeyraError::Unwind(Unwind::Return));
+            }
+            Statement::Try(stmt) => {
+                let mut result = self.execute_scoped(&stmt.body);
+                if let Some(catch) = &stmt.catch {
+                    if let Some(error) = result.as_ref().err().and_then(|e| self.caught(e)) {
+                        self.environment.push_scope();
+                        if let Some(variable) = &catch.variable {
+                            self.environment.define(variable.clone(), error);
+                        }
+                        result = self.execute_block(&catch.body);
+                        self.environment.pop_scope();
+                    }
+                }
+                if let Some(finally) = &stmt.finally {
+                    // The value of a `return` or `throw` on its way out
+                    // survives the finally block, unless that ends early
+                    // itself
+                    let pending = (self.return_value.take(), self.thrown.take());
+                    self.execute_scoped(finally)?;
+                    (self.return_value, self.thrown) = pending;
+                }
+                result?;
+            }
+            Statement::Throw(throw) => {
+                let value = self.evaluate_expression(&throw.value)?;
+                let message = Self::value_to_string(&value);
+                self.thrown = Some(value);
+                return Err(VeyraError::Thrown { message });
             }
             Statement::Break => {
-                return Err(VeyraError::runtime_error("break"));
+                return Err(VeyraError::Unwind(Unwind::Break));
             }
             Statement::Continue => {
-                return Err(VeyraError::runtime_error("continue"));
+                return Err(VeyraError::Unwind(Unwind::Continue));
             }
-            Statement::Block(block) => {
+            Statement::Comment(_) => {}
+            Statement::Block(block) => self.execute_scoped(block)?,
+            Statement::TaskGroup(block) => {
+                self.task_groups.push(Vec::new());
                 self.environment.push_scope();
                 let result = self.execute_block(block);
                 self.environment.pop_scope();
+                let group = self.task_groups.len() - 1;
+                let joined = self.join_tasks(group);
+                self.task_groups.pop();
                 result?;
+                joined?;
             }
-            Statement::Match(_) => {
-                return Err(VeyraError::runtime_error(
-                    "match statements not implemented",
-                ));
+            Statement::Match(stmt) => {
+                let value = self.evaluate_expression(&stmt.expression)?;
+                for arm in &stmt.arms {
+                    if !self.enter_arm(&arm.pattern, arm.guard.as_ref(), &value)? {
+                        continue;
+                    }
+                    let result = self.execute_statement(&arm.body);
+                    self.environment.pop_scope();
+                    return result;
+                }
             }
         }
         Ok(())
     }
 
+    /// Whether `value` matches `pattern`, adding the variables it binds to
+    /// `bindings`. A struct pattern naming a field the struct lacks is an
+    /// error rather than a mismatch, as it can never match.
+    fn match_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: &Value,
+        bindings: &mut Vec<(String, Value)>,
+    ) -> Result<bool> {
+        match (pattern, value) {
+            (Pattern::Wildcard, _) => Ok(true),
+            (Pattern::Identifier(name), _) => {
+                bindings.push((name.clone(), value.clone()));
+                Ok(true)
+            }
+            (Pattern::Literal(literal), _) => {
+                let literal = self.literal_to_value(literal);
+                self.values_equal(&literal, value)
+            }
+            (Pattern::Tuple(patterns), Value::Tuple(values)) => {
+                if patterns.len() != values.len() {
+                    return Ok(false);
+                }
+                self.match_patterns(patterns, values, bindings)
+            }
+            (Pattern::Array { elements, rest }, Value::Array(values)) => {
+                let fits = match rest {
+                    Some(_) => values.len() >= elements.len(),
+                    None => values.len() == elements.len(),
+                };
+                if !fits || !self.match_patterns(elements, values, bindings)? {
+                    return Ok(false);
+                }
+                match rest {
+                    Some(rest) => {
+                        let rest_value = Value::Array(values[elements.len()..].to_vec());
+                        self.match_pattern(rest, &rest_value, bindings)
+                    }
+                    None => Ok(true),
+                }
+            }
+            (Pattern::Struct { name, fields }, Value::Struct(instance)) => {
+                if *name != instance.name {
+                    return Ok(false);
+                }
+                for (field, pattern) in fields {
+                    let Some((_, field_value)) = instance
+                        .fields
+                        .iter()
+                        .find(|(existing, _)| existing == field)
+                    else {
+                        return Err(VeyraError::runtime_error(format!(
+                            "Struct '{}' has no field '{}'",
+                            name, field
+                        )));
+                    };
+                    if !self.match_pattern(pattern, field_value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (Pattern::Variant { variant, value }, Value::Variant(actual, inner)) => {
+                Ok(variant == actual && self.match_pattern(value, inner, bindings)?)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Whether a match arm is taken for `value`. If so, a scope with the
+    /// pattern's variables is pushed for the arm's body, which the caller
+    /// pops; the guard is evaluated in that scope.
+    fn enter_arm(
+        &mut self,
+        pattern: &Pattern,
+        guard: Option<&Expression>,
+        value: &Value,
+    ) -> Result<bool> {
+        let mut bindings = Vec::new();
+        if !self.match_pattern(pattern, value, &mut bindings)? {
+            return Ok(false);
+        }
+        self.environment.push_scope();
+        for (name, value) in bindings {
+            self.environment.define(name, value);
+        }
+        let Some(guard) = guard else {
+            return Ok(true);
+        };
+        match self.evaluate_expression(guard) {
+            Ok(condition) if condition.is_truthy() => Ok(true),
+            result => {
+                self.environment.pop_scope();
+                result.map(|_| false)
+            }
+        }
+    }
+
+    fn match_patterns(
+        &mut self,
+        patterns: &[Pattern],
+        values: &[Value],
+        bindings: &mut Vec<(String, Value)>,
+    ) -> Result<bool> {
+        for (pattern, value) in patterns.iter().zip(values) {
+            if !self.match_pattern(pattern, value, bindings)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     fn evaluate_expression(&mut self, expression: &Expression) -> Result<Value> {
         match expression {
             Expression::Literal(literal) => Ok(self.literal_to_value(literal)),
-            Expression::Identifier(name) => {
-                if name == "self" {
-                    return Err(VeyraError::runtime_error(
-                        "'self' not supported in this context",
-                    ));
-                }
-                self.environment.get(name).cloned().ok_or_else(|| {
-                    VeyraError::runtime_error(format!("Undefined variable '{}'", name))
-                })
-            }
+            Expression::Identifier(name) => self.variable(name),
             Expression::Binary(binary) => {
                 let left = self.evaluate_expression(&binary.left)?;
                 let right = self.evaluate_expression(&binary.right)?;
@@ -1046,20 +3478,48 @@ impl Interpreter {
                 self.apply_unary_operator(&unary.operator, &operand)
             }
             Expression::Call(call) => {
+                // help(name) documents the function itself rather than evaluating it
+                if let (Expression::Identifier(func_name), [Expression::Identifier(target)]) =
+                    (call.callee.as_ref(), call.arguments.as_slice())
+                {
+                    if func_name == "help" && self.environment.get(target).is_none() {
+                        return self.call_function("help", &[Value::String(target.clone())]);
+                    }
+                }
+                // trace(expr) labels the value with the expression's source
+                if let (Expression::Identifier(func_name), [argument]) =
+                    (call.callee.as_ref(), call.arguments.as_slice())
+                {
+                    if func_name == "trace" && self.environment.get(func_name).is_none() {
+                        let value = self.evaluate_expression(argument)?;
+                        let label = Value::String(printer::expression_to_source(argument));
+                        return self.call_function("trace", &[value, label]);
+                    }
+                }
+
                 let mut args = Vec::new();
                 for arg_expr in &call.arguments {
                     args.push(self.evaluate_expression(arg_expr)?);
                 }
 
                 match call.callee.as_ref() {
-                    Expression::Identifier(func_name) => self.call_function(func_name, &args),
-                    Expression::ModuleAccess(module_access) => {
-                        // For stdlib functions, they're loaded globally, so call by item name
-                        self.call_function(&module_access.item, &args)
+                    Expression::Identifier(func_name) => match self.environment.get(func_name) {
+                        Some(Value::Function(function)) => {
+                            let function = function.clone();
+                            self.call_value(&function, &args)
+                        }
+                        _ => self.call_function(func_name, &args),
+                    },
+                    Expression::ModuleAccess(access) => {
+                        self.call_module_item(&access.module, &access.item, &args)
                     }
-                    _ => Err(VeyraError::runtime_error(
-                        "Complex function calls not implemented",
-                    )),
+                    callee => match self.evaluate_expression(callee)? {
+                        Value::Function(function) => self.call_value(&function, &args),
+                        other => Err(VeyraError::runtime_error(format!(
+                            "Cannot call {}",
+                            other.type_name()
+                        ))),
+                    },
                 }
             }
             Expression::Array(array) => {
@@ -1070,48 +3530,73 @@ impl Interpreter {
                 Ok(Value::Array(elements))
             }
             Expression::Dictionary(dict) => {
-                let mut map = HashMap::new();
+                let mut map = Dictionary::new();
                 for (key_expr, value_expr) in &dict.pairs {
                     let key = self.evaluate_expression(key_expr)?;
                     let value = self.evaluate_expression(value_expr)?;
-
-                    // Convert key to string for now
-                    let key_str = match key {
-                        Value::String(s) => s,
-                        Value::Integer(i) => i.to_string(),
-                        Value::Float(f) => f.to_string(),
-                        Value::Boolean(b) => b.to_string(),
-                        Value::Char(c) => c.to_string(),
-                        _ => return Err(VeyraError::runtime_error(
-                            "Dictionary keys must be hashable (string, int, float, bool, or char)",
-                        )),
-                    };
-
-                    map.insert(key_str, value);
+                    self.insert_key(&mut map, key, value, UNHASHABLE_KEY)?;
                 }
                 Ok(Value::Dictionary(map))
             }
             Expression::Set(set) => {
-                let mut hash_set = std::collections::HashSet::new();
+                let mut elements = Set::new();
                 for elem_expr in &set.elements {
                     let elem = self.evaluate_expression(elem_expr)?;
-
-                    // Convert element to string for now
-                    let elem_str =
-                        match elem {
-                            Value::String(s) => s,
-                            Value::Integer(i) => i.to_string(),
-                            Value::Float(f) => f.to_string(),
-                            Value::Boolean(b) => b.to_string(),
-                            Value::Char(c) => c.to_string(),
-                            _ => return Err(VeyraError::runtime_error(
-                                "Set elements must be hashable (string, int, float, bool, or char)",
-                            )),
-                        };
-
-                    hash_set.insert(elem_str);
+                    self.insert_key(&mut elements, elem, (), UNHASHABLE_ELEMENT)?;
+                }
+                Ok(Value::Set(elements))
+            }
+            Expression::StructInit(init) => {
+                let definition = self
+                    .structs
+                    .get(&init.struct_name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        VeyraError::runtime_error(format!(
+                            "Undefined struct '{}'",
+                            init.struct_name
+                        ))
+                    })?;
+                let mut values = HashMap::new();
+                for (field, value_expr) in &init.fields {
+                    if !definition.fields.iter().any(|f| &f.name == field) {
+                        return Err(VeyraError::runtime_error(format!(
+                            "Struct '{}' has no field '{}'",
+                            definition.name, field
+                        )));
+                    }
+                    let value = self.evaluate_expression(value_expr)?;
+                    values.insert(field.clone(), value);
+                }
+                let fields = definition
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let value = values.remove(&field.name).ok_or_else(|| {
+                            VeyraError::runtime_error(format!(
+                                "Missing field '{}' in '{}' initializer",
+                                field.name, definition.name
+                            ))
+                        })?;
+                        Ok((field.name.clone(), value))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Struct(StructValue {
+                    name: definition.name,
+                    fields,
+                }))
+            }
+            Expression::FieldAccess(access) => {
+                let object = self.evaluate_expression(&access.object)?;
+                field_value(object, &access.field)
+            }
+            Expression::MethodCall(call) => {
+                let receiver = self.evaluate_expression(&call.object)?;
+                let mut args = Vec::new();
+                for arg_expr in &call.arguments {
+                    args.push(self.evaluate_expression(arg_expr)?);
                 }
-                Ok(Value::Set(hash_set))
+                self.call_method(receiver, &call.method, &args)
             }
             Expression::Tuple(tuple) => {
                 let mut elements = Vec::new();
@@ -1123,38 +3608,7 @@ impl Interpreter {
             Expression::Index(index) => {
                 let object = self.evaluate_expression(&index.object)?;
                 let index_val = self.evaluate_expression(&index.index)?;
-
-                match (object, index_val) {
-                    (Value::Array(arr), Value::Integer(i)) => {
-                        if i < 0 || i as usize >= arr.len() {
-                            return Err(VeyraError::runtime_error("Array index out of bounds"));
-                        }
-                        Ok(arr[i as usize].clone())
-                    }
-                    (Value::Dictionary(map), Value::String(key)) => {
-                        map.get(&key).cloned().ok_or_else(|| {
-                            VeyraError::runtime_error(format!(
-                                "Key '{}' not found in dictionary",
-                                key
-                            ))
-                        })
-                    }
-                    (Value::Tuple(tuple), Value::Integer(i)) => {
-                        if i < 0 || i as usize >= tuple.len() {
-                            return Err(VeyraError::runtime_error("Tuple index out of bounds"));
-                        }
-                        Ok(tuple[i as usize].clone())
-                    }
-                    (Value::String(s), Value::Integer(i)) => {
-                        if i < 0 || i as usize >= s.len() {
-                            return Err(VeyraError::runtime_error("String index out of bounds"));
-                        }
-                        Ok(Value::String(
-                            s.chars().nth(i as usize).unwrap().to_string(),
-                        ))
-                    }
-                    _ => Err(VeyraError::runtime_error("Invalid indexing operation")),
-                }
+                self.index_value(object, index_val)
             }
             Expression::Range(range) => {
                 let start = self.evaluate_expression(&range.start)?;
@@ -1173,49 +3627,243 @@ impl Interpreter {
                     ))
                 }
             }
-            Expression::ModuleAccess(module_access) => {
-                // For now, we'll implement basic std library access
-                let full_name = format!("{}::{}", module_access.module, module_access.item);
-
-                // Check for constants first
-                match full_name.as_str() {
-                    "std::PI" | "math::PI" => Ok(Value::Float(std::f64::consts::PI)),
-                    "std::E" | "math::E" => Ok(Value::Float(std::f64::consts::E)),
-                    _ => {
-                        // Try to find the item in the global environment (stdlib items are loaded globally)
-                        self.environment
-                            .get(&module_access.item)
-                            .cloned()
-                            .ok_or_else(|| {
-                                VeyraError::runtime_error(format!(
-                                    "Undefined module item '{}::{}'",
-                                    module_access.module, module_access.item
-                                ))
-                            })
-                    }
-                }
-            }
+            Expression::ModuleAccess(access) => self.module_access(&access.module, &access.item),
             Expression::Cast(cast) => {
                 let value = self.evaluate_expression(&cast.expression)?;
                 self.cast_value(value, &cast.target_type)
             }
-            _ => Err(VeyraError::runtime_error("Expression type not implemented")),
+            Expression::InterpolatedString(string) => {
+                // Embedded values are converted as str() converts them
+                let mut text = String::new();
+                for part in &string.parts {
+                    match part {
+                        StringPart::Literal(literal) => text.push_str(literal),
+                        StringPart::Expression(e) => {
+                            let value = self.evaluate_expression(e)?;
+                            text.push_str(&Self::value_to_string(&value));
+                        }
+                    }
+                }
+                Ok(Value::String(text))
+            }
+            Expression::Lambda(lambda) => Ok(self.closure(lambda)),
+            Expression::Spawn(spawn) => self.spawn(&spawn.expression),
+            Expression::Propagate(propagate) => {
+                let value = self.evaluate_expression(&propagate.expression)?;
+                self.propagate(value)
+            }
+            Expression::Await(await_expr) => {
+                match self.evaluate_expression(&await_expr.expression)? {
+                    Value::Task(task) => self.await_task(task),
+                    // Async functions run to completion when called
+                    value => Ok(value),
+                }
+            }
+            Expression::If(if_expr) => {
+                if self.evaluate_expression(&if_expr.condition)?.is_truthy() {
+                    self.evaluate_expression(&if_expr.then_expr)
+                } else {
+                    self.evaluate_expression(&if_expr.else_expr)
+                }
+            }
+            Expression::Match(match_expr) => {
+                let value = self.evaluate_expression(&match_expr.expression)?;
+                for arm in &match_expr.arms {
+                    if !self.enter_arm(&arm.pattern, arm.guard.as_ref(), &value)? {
+                        continue;
+                    }
+                    let result = self.evaluate_expression(&arm.expression);
+                    self.environment.pop_scope();
+                    return result;
+                }
+                Err(VeyraError::runtime_error(format!(
+                    "No match arm matches {} '{}'",
+                    value.type_name(),
+                    Self::value_to_string(&value)
+                )))
+            }
+        }
+    }
+
+    /// The value of a variable, or the function or builtin of that name
+    pub(crate) fn variable(&self, name: &str) -> Result<Value> {
+        match self.environment.get(name) {
+            Some(value) => Ok(value.clone()),
+            // A function used as a value, e.g. passed to `map`
+            None if self.functions.contains_key(name)
+                || self.compiled_function(name).is_some()
+                || builtins::lookup(name).is_some() =>
+            {
+                Ok(Value::Function(FunctionValue::Named(name.to_string())))
+            }
+            None if name == "self" => Err(VeyraError::runtime_error(
+                "'self' is only available inside methods",
+            )),
+            None => Err(self.environment.undefined_variable(name)),
+        }
+    }
+
+    /// `object[index]`
+    pub(crate) fn index_value(&mut self, object: Value, index_val: Value) -> Result<Value> {
+        match (object, index_val) {
+            (Value::Array(arr), Value::Integer(i)) => {
+                if i < 0 || i as usize >= arr.len() {
+                    return Err(VeyraError::runtime_error("Array index out of bounds"));
+                }
+                Ok(arr[i as usize].clone())
+            }
+            (Value::Dictionary(map), key) => {
+                let hash = self.typed_key(&key)?.ok_or_else(|| {
+                    VeyraError::runtime_error(format!(
+                        "Cannot index a dictionary with {}",
+                        key.type_name()
+                    ))
+                })?;
+                match self.find_key(&map, &hash, &key)? {
+                    Some(position) => Ok(map.bucket(&hash)[position].1.clone()),
+                    None => Err(missing_key(&key)),
+                }
+            }
+            (Value::Tuple(tuple), Value::Integer(i)) => {
+                if i < 0 || i as usize >= tuple.len() {
+                    return Err(VeyraError::runtime_error("Tuple index out of bounds"));
+                }
+                Ok(tuple[i as usize].clone())
+            }
+            (Value::String(s), Value::Integer(i)) => {
+                if i < 0 || i as usize >= s.len() {
+                    return Err(VeyraError::runtime_error("String index out of bounds"));
+                }
+                Ok(Value::String(
+                    s.chars().nth(i as usize).unwrap().to_string(),
+                ))
+            }
+            _ => Err(VeyraError::runtime_error("Invalid indexing operation")),
+        }
+    }
+
+    /// Assign to an element or field, such as `a[i] = x`, `p.x += 1` or
+    /// `(*r)[k] = v`, changing the variable the target starts from, or the
+    /// target of the reference it goes through, in place. Indexes are
+    /// evaluated after the value, from left to right.
+    fn assign_element(
+        &mut self,
+        target: &Expression,
+        operator: &AssignmentOperator,
+        value: Value,
+    ) -> Result<()> {
+        let mut steps = Vec::new();
+        let variable = self.assignment_steps(target, &mut steps)?;
+        let value = match bytecode::compound_operator(operator) {
+            Some(operator) => {
+                let old =
+                    self.update_element(variable, &steps, false, |element| element.clone())?;
+                self.apply_binary_operator(&operator, &old, &value)?
+            }
+            None => value,
+        };
+        self.update_element(variable, &steps, true, |element| *element = value)
+    }
+
+    /// The variable an assignment target starts from, evaluating the indexes
+    /// on the way to the element into `steps`
+    fn assignment_steps<'e>(
+        &mut self,
+        target: &'e Expression,
+        steps: &mut Vec<Step<'e>>,
+    ) -> Result<&'e str> {
+        let variable = match target {
+            Expression::Identifier(name) => return Ok(name),
+            Expression::Index(index) => {
+                let variable = self.assignment_steps(&index.object, steps)?;
+                let index = self.evaluate_expression(&index.index)?;
+                let key = self.typed_key(&index)?;
+                steps.push(Step::Index(index, key));
+                variable
+            }
+            Expression::FieldAccess(access) => {
+                let variable = self.assignment_steps(&access.object, steps)?;
+                steps.push(Step::Field(&access.field));
+                variable
+            }
+            Expression::Unary(unary) if unary.operator == UnaryOperator::Dereference => {
+                let variable = self.assignment_steps(&unary.operand, steps)?;
+                steps.push(Step::Deref);
+                variable
+            }
+            _ => return Err(VeyraError::runtime_error("Invalid assignment target")),
+        };
+        Ok(variable)
+    }
+
+    /// Call `f` with the element `steps` lead to from `variable`. The
+    /// variable's value is taken out while it changes, as finding a key in a
+    /// dictionary on the way may call a struct's `__eq__`.
+    fn update_element<R>(
+        &mut self,
+        variable: &str,
+        steps: &[Step],
+        insert: bool,
+        f: impl FnOnce(&mut Value) -> R,
+    ) -> Result<R> {
+        let Some(slot) = self.environment.get_mut(variable) else {
+            return Err(self.environment.undefined_variable(variable));
+        };
+        let mut value = std::mem::replace(slot, Value::None);
+        let result = with_element(self, &mut value, steps, insert, f);
+        if let Some(slot) = self.environment.get_mut(variable) {
+            *slot = value;
+        }
+        result
+    }
+
+    /// `module::item` as a value
+    pub(crate) fn module_access(&mut self, module: &str, item: &str) -> Result<Value> {
+        if self.imports.contains_key(module) {
+            return self.module_item(module, item);
+        }
+        // For now, we'll implement basic std library access
+        let full_name = format!("{}::{}", module, item);
+
+        // Check for constants first
+        match full_name.as_str() {
+            "std::PI" | "math::PI" => Ok(Value::Float(std::f64::consts::PI)),
+            "std::E" | "math::E" => Ok(Value::Float(std::f64::consts::E)),
+            _ => {
+                // Try to find the item in the global environment (stdlib items are loaded globally)
+                self.environment.get(item).cloned().ok_or_else(|| {
+                    VeyraError::runtime_error(format!(
+                        "Undefined module item '{}::{}'",
+                        module, item
+                    ))
+                })
+            }
         }
     }
 
-    fn literal_to_value(&self, literal: &Literal) -> Value {
-        match literal {
-            Literal::Integer(n) => Value::Integer(*n),
-            Literal::Float(f) => Value::Float(*f),
-            Literal::String(s) => Value::String(s.clone()),
-            Literal::Char(c) => Value::Char(*c),
-            Literal::Boolean(b) => Value::Boolean(*b),
-            Literal::None => Value::None,
+    /// The value inside `Some` or `Ok` for `value?`, or an unwinding return
+    /// of a `None` or `Err`
+    pub(crate) fn propagate(&mut self, value: Value) -> Result<Value> {
+        match value {
+            Value::Variant(Variant::Some | Variant::Ok, value) => Ok(*value),
+            // The enclosing function returns the None or Err as it is
+            value @ (Value::None | Value::Variant(Variant::Err, _)) => {
+                self.return_value = Some(value);
+                Err(VeyraError::Unwind(Unwind::Return))
+            }
+            other => Err(VeyraError::runtime_error(format!(
+                "'?' requires an option or result, got {}",
+                other.type_name()
+            ))),
         }
     }
 
-    fn apply_binary_operator(
-        &self,
+    fn literal_to_value(&self, literal: &Literal) -> Value {
+        literal_value(literal)
+    }
+
+    pub(crate) fn apply_binary_operator(
+        &mut self,
         op: &BinaryOperator,
         left: &Value,
         right: &Value,
@@ -1258,110 +3906,136 @@ impl Interpreter {
                 Ok(Value::Float(a * *b as f64))
             }
 
-            (BinaryOperator::Divide, Value::Integer(a), Value::Integer(b)) => {
-                if *b == 0 {
-                    Err(VeyraError::runtime_error("Division by zero"))
-                } else {
-                    Ok(Value::Integer(a / b))
-                }
-            }
-            (BinaryOperator::Divide, Value::Float(a), Value::Float(b)) => {
-                if *b == 0.0 {
-                    Err(VeyraError::runtime_error("Division by zero"))
-                } else {
-                    Ok(Value::Float(a / b))
-                }
+            // Repetition: `"-" * 40`, `[0] * n`
+            (BinaryOperator::Multiply, Value::String(s), Value::Integer(n))
+            | (BinaryOperator::Multiply, Value::Integer(n), Value::String(s)) => {
+                Ok(Value::String(s.repeat(repeat_count(*n, s.len())?)))
             }
-            (BinaryOperator::Divide, Value::Integer(a), Value::Float(b)) => {
-                if *b == 0.0 {
-                    Err(VeyraError::runtime_error("Division by zero"))
-                } else {
-                    Ok(Value::Float(*a as f64 / b))
-                }
+            (BinaryOperator::Multiply, Value::Char(c), Value::Integer(n))
+            | (BinaryOperator::Multiply, Value::Integer(n), Value::Char(c)) => Ok(Value::String(
+                c.to_string().repeat(repeat_count(*n, c.len_utf8())?),
+            )),
+            (BinaryOperator::Multiply, Value::Array(elements), Value::Integer(n))
+            | (BinaryOperator::Multiply, Value::Integer(n), Value::Array(elements)) => {
+                let count = repeat_count(*n, elements.len())?;
+                Ok(Value::Array(
+                    elements
+                        .iter()
+                        .cloned()
+                        .cycle()
+                        .take(elements.len() * count)
+                        .collect(),
+                ))
             }
-            (BinaryOperator::Divide, Value::Float(a), Value::Integer(b)) => {
+
+            // `/` always gives a float; `//` rounds toward negative infinity
+            // and `%` takes the sign of the divisor, so that
+            // `a == (a // b) * b + a % b`
+            (BinaryOperator::FloorDivide, Value::Integer(a), Value::Integer(b)) => {
                 if *b == 0 {
                     Err(VeyraError::runtime_error("Division by zero"))
                 } else {
-                    Ok(Value::Float(a / *b as f64))
+                    a.checked_div(*b)
+                        .map(|q| {
+                            if a % b != 0 && (a < &0) != (b < &0) {
+                                q - 1
+                            } else {
+                                q
+                            }
+                        })
+                        .map(Value::Integer)
+                        .ok_or_else(|| VeyraError::runtime_error("Integer overflow in division"))
                 }
             }
-
             (BinaryOperator::Modulo, Value::Integer(a), Value::Integer(b)) => {
                 if *b == 0 {
                     Err(VeyraError::runtime_error("Modulo by zero"))
                 } else {
-                    Ok(Value::Integer(a % b))
+                    let r = a.wrapping_rem(*b);
+                    Ok(Value::Integer(if r != 0 && (r < 0) != (b < &0) {
+                        r + b
+                    } else {
+                        r
+                    }))
                 }
             }
+            (
+                BinaryOperator::Divide | BinaryOperator::FloorDivide | BinaryOperator::Modulo,
+                Value::Integer(_) | Value::Float(_),
+                Value::Integer(_) | Value::Float(_),
+            ) => {
+                let (a, b) = (as_f64(left), as_f64(right));
+                if b == 0.0 {
+                    let message = match op {
+                        BinaryOperator::Modulo => "Modulo by zero",
+                        _ => "Division by zero",
+                    };
+                    return Err(VeyraError::runtime_error(message));
+                }
+                Ok(Value::Float(match op {
+                    BinaryOperator::Divide => a / b,
+                    BinaryOperator::FloorDivide => (a / b).floor(),
+                    _ => {
+                        let r = a % b;
+                        if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                            r + b
+                        } else {
+                            r
+                        }
+                    }
+                }))
+            }
 
             // Comparison
-            (BinaryOperator::Equal, a, b) => Ok(Value::Boolean(self.values_equal(a, b))),
-            (BinaryOperator::NotEqual, a, b) => Ok(Value::Boolean(!self.values_equal(a, b))),
+            (BinaryOperator::Equal, a, b) => Ok(Value::Boolean(self.values_equal(a, b)?)),
+            (BinaryOperator::NotEqual, a, b) => Ok(Value::Boolean(!self.values_equal(a, b)?)),
 
-            (BinaryOperator::Less, Value::Integer(a), Value::Integer(b)) => {
-                Ok(Value::Boolean(a < b))
-            }
-            (BinaryOperator::Less, Value::Float(a), Value::Float(b)) => Ok(Value::Boolean(a < b)),
-            (BinaryOperator::Less, Value::String(a), Value::String(b)) => Ok(Value::Boolean(a < b)),
-            (BinaryOperator::Less, Value::Char(a), Value::Char(b)) => Ok(Value::Boolean(a < b)),
-            (BinaryOperator::Less, Value::String(a), Value::Char(b)) => {
-                Ok(Value::Boolean(*a < b.to_string()))
-            }
-            (BinaryOperator::Less, Value::Char(a), Value::String(b)) => {
-                Ok(Value::Boolean(a.to_string() < *b))
-            }
-            (BinaryOperator::LessEqual, Value::Integer(a), Value::Integer(b)) => {
-                Ok(Value::Boolean(a <= b))
-            }
-            (BinaryOperator::LessEqual, Value::Float(a), Value::Float(b)) => {
-                Ok(Value::Boolean(a <= b))
-            }
-            (BinaryOperator::LessEqual, Value::String(a), Value::String(b)) => {
-                Ok(Value::Boolean(a <= b))
-            }
-            (BinaryOperator::LessEqual, Value::Char(a), Value::Char(b)) => {
-                Ok(Value::Boolean(a <= b))
-            }
-            (BinaryOperator::LessEqual, Value::String(a), Value::Char(b)) => {
-                Ok(Value::Boolean(*a <= b.to_string()))
-            }
-            (BinaryOperator::LessEqual, Value::Char(a), Value::String(b)) => {
-                Ok(Value::Boolean(a.to_string() <= *b))
-            }
-            (BinaryOperator::Greater, Value::Integer(a), Value::Integer(b)) => {
-                Ok(Value::Boolean(a > b))
-            }
-            (BinaryOperator::Greater, Value::Float(a), Value::Float(b)) => {
-                Ok(Value::Boolean(a > b))
-            }
-            (BinaryOperator::Greater, Value::String(a), Value::String(b)) => {
-                Ok(Value::Boolean(a > b))
-            }
-            (BinaryOperator::Greater, Value::Char(a), Value::Char(b)) => Ok(Value::Boolean(a > b)),
-            (BinaryOperator::Greater, Value::String(a), Value::Char(b)) => {
-                Ok(Value::Boolean(*a > b.to_string()))
-            }
-            (BinaryOperator::Greater, Value::Char(a), Value::String(b)) => {
-                Ok(Value::Boolean(a.to_string() > *b))
-            }
-            (BinaryOperator::GreaterEqual, Value::Integer(a), Value::Integer(b)) => {
-                Ok(Value::Boolean(a >= b))
-            }
-            (BinaryOperator::GreaterEqual, Value::Float(a), Value::Float(b)) => {
-                Ok(Value::Boolean(a >= b))
-            }
-            (BinaryOperator::GreaterEqual, Value::String(a), Value::String(b)) => {
-                Ok(Value::Boolean(a >= b))
+            (
+                BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual,
+                Value::Integer(_) | Value::Float(_),
+                Value::Integer(_) | Value::Float(_),
+            ) => {
+                let (a, b) = (as_f64(left), as_f64(right));
+                Ok(Value::Boolean(match op {
+                    BinaryOperator::Less => a < b,
+                    BinaryOperator::LessEqual => a <= b,
+                    BinaryOperator::Greater => a > b,
+                    _ => a >= b,
+                }))
             }
-            (BinaryOperator::GreaterEqual, Value::Char(a), Value::Char(b)) => {
-                Ok(Value::Boolean(a >= b))
+            (
+                BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual,
+                a,
+                b,
+            ) => {
+                let ordering = compare_values(a, b).ok_or_else(|| {
+                    VeyraError::runtime_error(format!(
+                        "Unsupported operation: {} {:?} {}",
+                        a.type_name(),
+                        op,
+                        b.type_name()
+                    ))
+                })?;
+                Ok(Value::Boolean(match op {
+                    BinaryOperator::Less => ordering.is_lt(),
+                    BinaryOperator::LessEqual => ordering.is_le(),
+                    BinaryOperator::Greater => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }))
             }
-            (BinaryOperator::GreaterEqual, Value::String(a), Value::Char(b)) => {
-                Ok(Value::Boolean(*a >= b.to_string()))
+
+            // Membership
+            (BinaryOperator::In, item, container) => {
+                Ok(Value::Boolean(self.contains(container, item)?))
             }
-            (BinaryOperator::GreaterEqual, Value::Char(a), Value::String(b)) => {
-                Ok(Value::Boolean(a.to_string() >= *b))
+            (BinaryOperator::NotIn, item, container) => {
+                Ok(Value::Boolean(!self.contains(container, item)?))
             }
 
             // Logical (with short-circuiting)
@@ -1424,7 +4098,11 @@ impl Interpreter {
         }
     }
 
-    fn apply_unary_operator(&self, op: &UnaryOperator, operand: &Value) -> Result<Value> {
+    pub(crate) fn apply_unary_operator(
+        &mut self,
+        op: &UnaryOperator,
+        operand: &Value,
+    ) -> Result<Value> {
         match (op, operand) {
             (UnaryOperator::Minus, Value::Integer(n)) => Ok(Value::Integer(-n)),
             (UnaryOperator::Minus, Value::Float(f)) => Ok(Value::Float(-f)),
@@ -1433,14 +4111,14 @@ impl Interpreter {
             (UnaryOperator::Reference, val) => {
                 // Create an immutable reference
                 Ok(Value::Reference(Reference {
-                    value: Rc::new(RefCell::new(val.clone())),
+                    value: self.new_cell(val.clone()),
                     mutable: false,
                 }))
             }
             (UnaryOperator::MutableReference, val) => {
                 // Create a mutable reference
                 Ok(Value::Reference(Reference {
-                    value: Rc::new(RefCell::new(val.clone())),
+                    value: self.new_cell(val.clone()),
                     mutable: true,
                 }))
             }
@@ -1463,29 +4141,256 @@ impl Interpreter {
         }
     }
 
-    fn values_equal(&self, a: &Value, b: &Value) -> bool {
-        match (a, b) {
+    /// Append a string to a string variable without copying it, so building
+    /// a string piece by piece takes linear time. Returns false, changing
+    /// nothing, for other values.
+    fn append_in_place(&mut self, name: &str, piece: &Value) -> bool {
+        match (self.environment.get_mut(name), piece) {
+            (Some(Value::String(text)), Value::String(piece)) => {
+                text.push_str(piece);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether `item` is an element of an array, tuple or set, a key of a
+    /// dictionary, or a substring of a string
+    fn contains(&mut self, container: &Value, item: &Value) -> Result<bool> {
+        match (container, item) {
+            (Value::Array(elements) | Value::Tuple(elements), _) => {
+                for element in elements {
+                    if self.values_equal(element, item)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            (Value::Dictionary(map), _) => self.has_key(map, item),
+            (Value::Set(set), _) => self.has_key(set, item),
+            (Value::String(s), Value::String(sub)) => Ok(s.contains(sub.as_str())),
+            (Value::String(s), Value::Char(c)) => Ok(s.contains(*c)),
+            (Value::String(_), _) => Err(VeyraError::runtime_error(format!(
+                "'in <string>' requires a string or char on the left, got {}",
+                item.type_name()
+            ))),
+            _ => Err(VeyraError::runtime_error(format!(
+                "'in' requires an array, tuple, set, dictionary or string on the right, got {}",
+                container.type_name()
+            ))),
+        }
+    }
+
+    /// Structural equality: collections are equal when their elements are,
+    /// and an int equals a float with the same value. Structs compare field
+    /// by field unless their type defines `__eq__(other)`.
+    pub(crate) fn values_equal(&mut self, a: &Value, b: &Value) -> Result<bool> {
+        Ok(match (a, b) {
+            (Value::Struct(instance), other) | (other, Value::Struct(instance))
+                if self.has_method(&instance.name, "__eq__") =>
+            {
+                let receiver = Value::Struct(instance.clone());
+                self.call_method(receiver, "__eq__", std::slice::from_ref(other))?
+                    .is_truthy()
+            }
             (Value::Integer(a), Value::Integer(b)) => a == b,
             (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Integer(_), Value::Float(_)) | (Value::Float(_), Value::Integer(_)) => {
+                as_f64(a) == as_f64(b)
+            }
             (Value::String(a), Value::String(b)) => a == b,
             (Value::Char(a), Value::Char(b)) => a == b,
             (Value::String(a), Value::Char(b)) => *a == b.to_string(),
             (Value::Char(a), Value::String(b)) => a.to_string() == *b,
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::None, Value::None) => true,
+            (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+                self.all_equal(a.iter().zip(b), a.len() == b.len())?
+            }
+            (Value::Dictionary(a), Value::Dictionary(b)) => {
+                let mut pairs = Vec::new();
+                for (hash, entries) in a.buckets() {
+                    for (key, value) in entries {
+                        match self.find_key(b, hash, key)? {
+                            Some(position) => pairs.push((value, &b.bucket(hash)[position].1)),
+                            None => return Ok(false),
+                        }
+                    }
+                }
+                self.all_equal(pairs, a.len() == b.len())?
+            }
+            (Value::Set(a), Value::Set(b)) => {
+                for (hash, entries) in a.buckets() {
+                    for (key, _) in entries {
+                        if self.find_key(b, hash, key)?.is_none() {
+                            return Ok(false);
+                        }
+                    }
+                }
+                a.len() == b.len()
+            }
+            (Value::Variant(a, x), Value::Variant(b, y)) => a == b && self.values_equal(x, y)?,
+            (Value::Struct(a), Value::Struct(b)) => {
+                let fields = a
+                    .fields
+                    .iter()
+                    .zip(&b.fields)
+                    .map(|((_, a), (_, b))| (a, b));
+                self.all_equal(fields, a.name == b.name)?
+            }
             (Value::Reference(a), Value::Reference(b)) => {
                 // References are equal if they point to the same location
                 Rc::ptr_eq(&a.value, &b.value)
             }
+            (Value::Task(a), Value::Task(b)) => a == b,
+            (Value::Actor(a), Value::Actor(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
             _ => false,
+        })
+    }
+
+    /// Whether every pair is equal, given that the shapes match
+    fn all_equal<'v>(
+        &mut self,
+        pairs: impl IntoIterator<Item = (&'v Value, &'v Value)>,
+        same_shape: bool,
+    ) -> Result<bool> {
+        if !same_shape {
+            return Ok(false);
+        }
+        for (a, b) in pairs {
+            if !self.values_equal(a, b)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Where `key` is among the keys of `table` with `hash`, comparing them
+    /// with `values_equal`
+    pub(crate) fn find_key<V>(
+        &mut self,
+        table: &Table<V>,
+        hash: &str,
+        key: &Value,
+    ) -> Result<Option<usize>> {
+        for (position, (candidate, _)) in table.bucket(hash).iter().enumerate() {
+            if self.values_equal(candidate, key)? {
+                return Ok(Some(position));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether a dictionary has `key` or a set has it as an element
+    fn has_key<V>(&mut self, table: &Table<V>, key: &Value) -> Result<bool> {
+        Ok(match self.typed_key(key)? {
+            Some(hash) => self.find_key(table, &hash, key)?.is_some(),
+            None => false,
+        })
+    }
+
+    /// Add `key` and its value to `table`, replacing the value of an equal
+    /// key already there. `unhashable` is the error for a key that cannot be
+    /// hashed.
+    pub(crate) fn insert_key<V>(
+        &mut self,
+        table: &mut Table<V>,
+        key: Value,
+        value: V,
+        unhashable: &str,
+    ) -> Result<()> {
+        let Some(hash) = self.typed_key(&key)? else {
+            return Err(VeyraError::runtime_error(unhashable));
+        };
+        match self.find_key(table, &hash, &key)? {
+            Some(position) => *table.value_mut(&hash, position) = value,
+            None => {
+                table.push(hash, key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// The hash of a dictionary key or set element, which picks the bucket
+    /// it goes in, for the value types that can be hashed. It is like the
+    /// value's repr, so values of different types never share a bucket, with
+    /// strings and chars quoted the same way so that equal values share one.
+    /// A struct is hashed by its fields, or by what its `__hash__()` method
+    /// returns; one that only defines `__eq__` is hashed by its type alone,
+    /// so that every key its `__eq__` might accept is in the same bucket.
+    pub(crate) fn typed_key(&mut self, value: &Value) -> Result<Option<String>> {
+        let key = match value {
+            Value::String(s) => quote(s, '"'),
+            Value::Char(c) => quote(&c.to_string(), '"'),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Array(elements) | Value::Tuple(elements) => {
+                let Some(keys) = self.typed_keys(elements)? else {
+                    return Ok(None);
+                };
+                match (value, keys.as_slice()) {
+                    (Value::Array(_), _) => format!("[{}]", keys.join(", ")),
+                    (_, [single]) => format!("({},)", single),
+                    _ => format!("({})", keys.join(", ")),
+                }
+            }
+            Value::Variant(variant, value) => match self.typed_key(value)? {
+                Some(key) => format!("{}({})", variant.name(), key),
+                None => return Ok(None),
+            },
+            Value::Struct(instance) if self.has_method(&instance.name, "__hash__") => {
+                let hash = self.call_method(value.clone(), "__hash__", &[])?;
+                let key = self.typed_key(&hash)?.ok_or_else(|| {
+                    VeyraError::runtime_error(format!(
+                        "'{}.__hash__' must return a hashable value, got {}",
+                        instance.name,
+                        hash.type_name()
+                    ))
+                })?;
+                format!("{}#{}", instance.name, key)
+            }
+            Value::Struct(instance) if self.has_method(&instance.name, "__eq__") => {
+                format!("{}#", instance.name)
+            }
+            Value::Struct(instance) => {
+                let Some(keys) = self.typed_keys(instance.fields.iter().map(|(_, v)| v))? else {
+                    return Ok(None);
+                };
+                let fields: Vec<String> = instance
+                    .fields
+                    .iter()
+                    .zip(keys)
+                    .map(|((name, _), key)| format!("{}: {}", name, key))
+                    .collect();
+                format!("{} {{ {} }}", instance.name, fields.join(", "))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(key))
+    }
+
+    /// Keys of the elements of a collection, if they are all hashable
+    fn typed_keys<'v>(
+        &mut self,
+        elements: impl IntoIterator<Item = &'v Value>,
+    ) -> Result<Option<Vec<String>>> {
+        let mut keys = Vec::new();
+        for element in elements {
+            match self.typed_key(element)? {
+                Some(key) => keys.push(key),
+                None => return Ok(None),
+            }
         }
+        Ok(Some(keys))
     }
 
-    fn add_values(&self, a: &Value, b: &Value) -> Result<Value> {
+    fn add_values(&mut self, a: &Value, b: &Value) -> Result<Value> {
         self.apply_binary_operator(&BinaryOperator::Add, a, b)
     }
 
-    fn cast_value(&self, value: Value, target_type: &Type) -> Result<Value> {
+    pub(crate) fn cast_value(&self, value: Value, target_type: &Type) -> Result<Value> {
         match target_type {
             Type::Primitive(prim_type) => match prim_type {
                 PrimitiveType::Int | PrimitiveType::I32 | PrimitiveType::I64 => match value {
@@ -1561,7 +4466,7 @@ impl Interpreter {
         }
     }
 
-    fn value_to_string(value: &Value) -> String {
+    pub(crate) fn value_to_string(value: &Value) -> String {
         match value {
             Value::Integer(n) => n.to_string(),
             Value::Float(f) => f.to_string(),
@@ -1569,35 +4474,59 @@ impl Interpreter {
             Value::Char(c) => c.to_string(),
             Value::Boolean(b) => b.to_string(),
             Value::None => "None".to_string(),
+            // Elements are shown with `repr`, so `["1", 1]` is not `[1, 1]`
             Value::Array(arr) => {
-                let elements: Vec<String> = arr.iter().map(Self::value_to_string).collect();
+                let elements: Vec<String> = arr.iter().map(Value::repr).collect();
                 format!("[{}]", elements.join(", "))
             }
             Value::Dictionary(map) => {
                 let mut pairs: Vec<String> = map
                     .iter()
-                    .map(|(k, v)| format!("\"{}\": {}", k, Self::value_to_string(v)))
+                    .map(|(k, v)| format!("{}: {}", k.repr(), v.repr()))
                     .collect();
                 pairs.sort(); // For consistent output
                 format!("{{{}}}", pairs.join(", "))
             }
             Value::Set(set) => {
-                let mut elements: Vec<String> = set.iter().map(|s| format!("\"{}\"", s)).collect();
+                let mut elements: Vec<String> = set.keys().map(Value::repr).collect();
                 elements.sort(); // For consistent output
                 format!("{{{}}}", elements.join(", "))
             }
             Value::Tuple(tuple) => {
-                let elements: Vec<String> = tuple.iter().map(Self::value_to_string).collect();
-                format!("({})", elements.join(", "))
+                let elements: Vec<String> = tuple.iter().map(Value::repr).collect();
+                match elements.as_slice() {
+                    [single] => format!("({},)", single),
+                    _ => format!("({})", elements.join(", ")),
+                }
             }
             Value::Reference(r) => {
                 let prefix = if r.mutable { "&mut " } else { "&" };
                 format!("{}{}", prefix, Self::value_to_string(&r.value.borrow()))
             }
+            // The target is not shown, as weak references often close cycles
+            Value::Weak(w) => match w.value.upgrade() {
+                Some(target) => format!("<weak {}>", target.borrow().type_name()),
+                None => "<weak, dropped>".to_string(),
+            },
+            Value::Task(task) => format!("<task {}>", task),
+            Value::Actor(actor) => format!("<actor {}>", actor),
+            Value::Struct(instance) => {
+                let fields: Vec<String> = instance
+                    .fields
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value.repr()))
+                    .collect();
+                format!("{} {{ {} }}", instance.name, fields.join(", "))
+            }
+            Value::Function(FunctionValue::Named(name)) => format!("<fn {}>", name),
+            Value::Function(FunctionValue::Lambda(_) | FunctionValue::Compiled(_)) => {
+                "<lambda>".to_string()
+            }
+            Value::Variant(variant, value) => format!("{}({})", variant.name(), value.repr()),
         }
     }
 
-    fn handle_import(&mut self, import: &Import) -> Result<()> {
+    pub(crate) fn handle_import(&mut self, import: &Import) -> Result<()> {
         let module_path = import.path.join(".");
 
         // Built-in modules
@@ -1611,10 +4540,9 @@ impl Interpreter {
             "std.string" => {
                 self.load_string_module()?;
             }
-            _ => {
-                // Try to load from filesystem
-                self.load_external_module(&module_path)?;
-            }
+            // Other standard modules are built in
+            _ if import.path[0] == "std" => {}
+            _ => self.load_external_module(import)?,
         }
 
         Ok(())
@@ -1641,27 +4569,69 @@ impl Interpreter {
         Ok(())
     }
 
-    fn load_external_module(&mut self, _module_path: &str) -> Result<()> {
-        // TODO: Load external .vey files
-        // For now, just ignore unknown modules
-        Ok(())
-    }
+    /// Load a module from its file the first time it is imported, running
+    /// its top-level code in a namespace of its own, then bind it, or the
+    /// items selected from it, in the importing code
+    fn load_external_module(&mut self, import: &Import) -> Result<()> {
+        let name = import.path.join(".");
+        let importer = self.running_modules.last().map(|(file, _)| file.as_path());
+        let file = self.module_loader.resolve(&import.path, importer)?;
+        // Importing runs the module's code, which is reading the file
+        self.policy.check(Capability::Read(&file))?;
+
+        if self
+            .running_modules
+            .iter()
+            .any(|(running, _)| *running == file)
+        {
+            return Err(VeyraError::runtime_error(format!(
+                "Circular import of module '{}'",
+                name
+            )));
+        }
+        if !self.modules.contains_key(&file) {
+            let program = self.module_loader.load(&file)?;
+            if let Some(reload) = &mut self.hot_reload {
+                reload.watch(&file);
+            }
+            let module = Module {
+                name: name.clone(),
+                functions: HashMap::new(),
+                environment: Environment::new(),
+                imports: HashMap::new(),
+            };
+            self.modules.insert(file.clone(), module);
+            if let Err(e) = self.in_module(&file, |this| this.run_top_level(&program)) {
+                self.modules.remove(&file);
+                return Err(e);
+            }
+        }
+
+        let Some(items) = &import.items else {
+            let binding = import.alias.as_ref().or(import.path.last());
+            self.imports.insert(binding.cloned().unwrap_or(name), file);
+            return Ok(());
+        };
 
-    fn parse_return_value(&self, value_str: &str) -> Result<Value> {
-        // Simple return value parsing - in real implementation would be more sophisticated
-        if let Ok(n) = value_str.parse::<i64>() {
-            Ok(Value::Integer(n))
-        } else if let Ok(f) = value_str.parse::<f64>() {
-            Ok(Value::Float(f))
-        } else if value_str == "true" {
-            Ok(Value::Boolean(true))
-        } else if value_str == "false" {
-            Ok(Value::Boolean(false))
-        } else if value_str == "none" {
-            Ok(Value::None)
-        } else {
-            Ok(Value::String(value_str.to_string()))
+        // Selected functions are called through the module's dotted name,
+        // which no code can write
+        self.imports.insert(name.clone(), file.clone());
+        for item in items {
+            check_public(&name, item)?;
+            let module = &self.modules[&file];
+            let value = if module.functions.contains_key(item) {
+                Value::Function(FunctionValue::Named(format!("{}::{}", name, item)))
+            } else if let Some(value) = module.environment.get(item) {
+                value.clone()
+            } else {
+                return Err(VeyraError::runtime_error(format!(
+                    "Module '{}' has no item '{}'",
+                    module.name, item
+                )));
+            };
+            self.environment.define(item.clone(), value);
         }
+        Ok(())
     }
 }
 
@@ -1719,9 +4689,499 @@ impl Interpreter {
     }
 }
 
-pub fn interpret(program: &Program) -> Result<()> {
+/// The value of finished background work
+fn outcome_value(outcome: async_io::Outcome) -> Result<Value> {
+    match outcome {
+        Ok(Some(text)) => Ok(Value::String(text)),
+        Ok(None) => Ok(Value::None),
+        Err(message) => Err(VeyraError::runtime_error(message)),
+    }
+}
+
+/// The operands after `name` in `name + a + b + ...`, in order
+fn appended_pieces<'a>(name: &str, expr: &'a Expression) -> Option<Vec<&'a Expression>> {
+    match expr {
+        Expression::Binary(BinaryExpression {
+            left,
+            operator: BinaryOperator::Add,
+            right,
+        }) => {
+            let mut pieces = match left.as_ref() {
+                Expression::Identifier(l) if l == name => Vec::new(),
+                left => appended_pieces(name, left)?,
+            };
+            pieces.push(right);
+            Some(pieces)
+        }
+        _ => None,
+    }
+}
+
+/// Order of two values: numbers by value, strings and chars by code point,
+/// and arrays and tuples lexicographically. `None` if they do not compare.
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Integer(_) | Value::Float(_), Value::Integer(_) | Value::Float(_)) => {
+            as_f64(a).partial_cmp(&as_f64(b))
+        }
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::Char(b)) => Some(a.as_str().cmp(b.to_string().as_str())),
+        (Value::Char(a), Value::String(b)) => Some(a.to_string().as_str().cmp(b)),
+        (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+        (Value::Array(a), Value::Array(b)) | (Value::Tuple(a), Value::Tuple(b)) => {
+            for (a, b) in a.iter().zip(b) {
+                match compare_values(a, b)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(a.len().cmp(&b.len()))
+        }
+        (Value::Variant(a, x), Value::Variant(b, y)) if a == b => compare_values(x, y),
+        _ => None,
+    }
+}
+
+/// Longest string, in bytes, or array a repetition like `"-" * n` may build
+const MAX_REPEAT_LEN: usize = 1 << 24;
+
+/// Number of copies for a repetition like `"-" * n` of a value `len` long,
+/// if the result is no longer than `MAX_REPEAT_LEN`
+fn repeat_count(n: i64, len: usize) -> Result<usize> {
+    let count = usize::try_from(n)
+        .map_err(|_| VeyraError::runtime_error(format!("Cannot repeat a value {} times", n)))?;
+    match len.checked_mul(count) {
+        Some(total) if total <= MAX_REPEAT_LEN => Ok(count),
+        _ => Err(VeyraError::runtime_error(format!(
+            "Repeating a value of length {} {} times is too large (at most {} items)",
+            len, count, MAX_REPEAT_LEN
+        ))),
+    }
+}
+
+/// Variables a lambda body refers to, which it captures when they are defined
+#[derive(Default)]
+struct UsedNames(HashSet<String>);
+
+impl Visitor for UsedNames {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name) = expression {
+            self.0.insert(name.clone());
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// A copy of a value with storage of its own at the top level: a reference
+/// gets a new target holding the same value, while references nested inside
+/// the value are shared with the original
+fn shallow_copy(value: &Value) -> Value {
+    match value {
+        Value::Reference(r) => Value::Reference(Reference {
+            value: Rc::new(RefCell::new(r.value.borrow().clone())),
+            mutable: r.mutable,
+        }),
+        other => other.clone(),
+    }
+}
+
+/// A copy that shares no storage with the original. References that point to
+/// the same place in the original point to one new place in the copy, so
+/// aliasing and cycles are preserved; `copies` maps old targets to new ones.
+fn deep_copy(
+    value: &Value,
+    copies: &mut HashMap<*const RefCell<Value>, Rc<RefCell<Value>>>,
+) -> Value {
+    match value {
+        Value::Reference(r) => {
+            let original = Rc::as_ptr(&r.value);
+            let target = match copies.get(&original) {
+                Some(target) => target.clone(),
+                None => {
+                    let target = Rc::new(RefCell::new(Value::None));
+                    copies.insert(original, target.clone());
+                    let copied = deep_copy(&r.value.borrow(), copies);
+                    *target.borrow_mut() = copied;
+                    target
+                }
+            };
+            Value::Reference(Reference {
+                value: target,
+                mutable: r.mutable,
+            })
+        }
+        Value::Array(elements) => {
+            Value::Array(elements.iter().map(|v| deep_copy(v, copies)).collect())
+        }
+        Value::Tuple(elements) => {
+            Value::Tuple(elements.iter().map(|v| deep_copy(v, copies)).collect())
+        }
+        Value::Variant(variant, value) => {
+            Value::Variant(*variant, Box::new(deep_copy(value, copies)))
+        }
+        Value::Dictionary(map) => Value::Dictionary(map.map_values(|v| deep_copy(v, copies))),
+        Value::Struct(instance) => Value::Struct(StructValue {
+            name: instance.name.clone(),
+            fields: instance
+                .fields
+                .iter()
+                .map(|(name, v)| (name.clone(), deep_copy(v, copies)))
+                .collect(),
+        }),
+        // A weak reference follows its target into the copy when the target
+        // has been copied already
+        Value::Weak(w) => match copies.get(&w.value.as_ptr()) {
+            Some(target) => Value::Weak(WeakReference {
+                value: Rc::downgrade(target),
+                mutable: w.mutable,
+            }),
+            None => value.clone(),
+        },
+        other => other.clone(),
+    }
+}
+
+/// Targets of the strong references directly inside a value, without
+/// following the references themselves
+fn strong_targets(value: &Value, targets: &mut Vec<Rc<RefCell<Value>>>) {
+    match value {
+        Value::Reference(r) => targets.push(r.value.clone()),
+        Value::Array(elements) | Value::Tuple(elements) => {
+            for element in elements {
+                strong_targets(element, targets);
+            }
+        }
+        Value::Dictionary(map) => {
+            for element in map.values() {
+                strong_targets(element, targets);
+            }
+        }
+        Value::Struct(instance) => {
+            for (_, field) in &instance.fields {
+                strong_targets(field, targets);
+            }
+        }
+        Value::Function(FunctionValue::Lambda(closure)) => {
+            for value in closure.captured.values() {
+                strong_targets(value, targets);
+            }
+        }
+        Value::Function(FunctionValue::Compiled(closure)) => {
+            for value in &closure.captured {
+                strong_targets(value, targets);
+            }
+        }
+        Value::Variant(_, value) => strong_targets(value, targets),
+        _ => {}
+    }
+}
+
+/// A short description of a reference target, which does not follow the
+/// references inside it
+fn describe_cell(value: &Value) -> String {
+    match value {
+        Value::Array(elements) | Value::Tuple(elements) => {
+            format!("{} of {}", value.type_name(), elements.len())
+        }
+        Value::Dictionary(map) => format!("dictionary of {}", map.len()),
+        Value::Struct(instance) => instance.name.clone(),
+        Value::Reference(r) if r.mutable => "&mut reference".to_string(),
+        Value::Reference(_) => "& reference".to_string(),
+        other => other.repr(),
+    }
+}
+
+/// Strongly connected components of a graph given as adjacency lists,
+/// found with Kosaraju's algorithm without recursion
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    // Order the nodes by when a depth-first search finishes with them
+    let mut visited = vec![false; edges.len()];
+    let mut finished = Vec::with_capacity(edges.len());
+    for start in 0..edges.len() {
+        if std::mem::replace(&mut visited[start], true) {
+            continue;
+        }
+        let mut stack = vec![(start, 0)];
+        while let Some((node, next)) = stack.pop() {
+            match edges[node].get(next) {
+                Some(&successor) => {
+                    stack.push((node, next + 1));
+                    if !std::mem::replace(&mut visited[successor], true) {
+                        stack.push((successor, 0));
+                    }
+                }
+                None => finished.push(node),
+            }
+        }
+    }
+
+    // Each search of the reversed graph, latest finisher first, finds one
+    // component
+    let mut reversed = vec![Vec::new(); edges.len()];
+    for (node, successors) in edges.iter().enumerate() {
+        for &successor in successors {
+            reversed[successor].push(node);
+        }
+    }
+    let mut assigned = vec![false; edges.len()];
+    let mut components = Vec::new();
+    for &start in finished.iter().rev() {
+        if std::mem::replace(&mut assigned[start], true) {
+            continue;
+        }
+        let mut component = Vec::new();
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            component.push(node);
+            for &predecessor in &reversed[node] {
+                if !std::mem::replace(&mut assigned[predecessor], true) {
+                    stack.push(predecessor);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// An int or float as a float
+fn as_f64(value: &Value) -> f64 {
+    match value {
+        Value::Integer(n) => *n as f64,
+        Value::Float(f) => *f,
+        _ => f64::NAN,
+    }
+}
+
+/// Send `data` over a new TCP connection and read the response until the
+/// peer closes it
+fn tcp_request(address: &str, data: &str) -> std::io::Result<String> {
+    use std::io::{Read, Write};
+
+    let mut stream = std::net::TcpStream::connect(address)?;
+    stream.write_all(data.as_bytes())?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+pub(crate) fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Integer(n) => Value::Integer(*n),
+        Literal::Float(f) => Value::Float(*f),
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Char(c) => Value::Char(*c),
+        Literal::Boolean(b) => Value::Boolean(*b),
+        Literal::None => Value::None,
+    }
+}
+
+/// `object.field`
+/// One step from a variable to the element or field an assignment changes
+enum Step<'a> {
+    /// An index, and the dictionary key it stands for if it is hashable
+    Index(Value, Option<String>),
+    Field(&'a str),
+    Deref,
+}
+
+/// Call `f` with the element of `value` that `steps` lead to. With
+/// `insert`, a key missing from a dictionary at the last step is added.
+fn with_element<R>(
+    host: &mut Interpreter,
+    value: &mut Value,
+    steps: &[Step],
+    insert: bool,
+    f: impl FnOnce(&mut Value) -> R,
+) -> Result<R> {
+    match steps {
+        [] => Ok(f(value)),
+        [Step::Index(index, key), rest @ ..] => {
+            let insert = insert && rest.is_empty();
+            let element = element_mut(host, value, index, key.as_deref(), insert)?;
+            with_element(host, element, rest, insert, f)
+        }
+        [Step::Field(field), rest @ ..] => {
+            with_element(host, field_mut(value, field)?, rest, insert, f)
+        }
+        [Step::Deref, rest @ ..] => {
+            let target = assignable_target(value)?.clone();
+            let mut target = target.try_borrow_mut().map_err(|_| {
+                VeyraError::runtime_error("Cannot assign through a reference to itself")
+            })?;
+            with_element(host, &mut target, rest, insert, f)
+        }
+    }
+}
+
+/// The element at `index` of `container` that an assignment changes. `key`
+/// is the index as a dictionary key; with `insert`, a missing key is added.
+pub(crate) fn element_mut<'v>(
+    host: &mut Interpreter,
+    container: &'v mut Value,
+    index: &Value,
+    key: Option<&str>,
+    insert: bool,
+) -> Result<&'v mut Value> {
+    match (container, index) {
+        (Value::Array(elements), Value::Integer(i)) => {
+            if *i < 0 || *i as usize >= elements.len() {
+                return Err(VeyraError::runtime_error("Array index out of bounds"));
+            }
+            Ok(&mut elements[*i as usize])
+        }
+        (Value::Array(_), index) => Err(VeyraError::runtime_error(format!(
+            "Array index must be an integer, got {}",
+            index.type_name()
+        ))),
+        (Value::Dictionary(map), index) => {
+            let Some(key) = key else {
+                return Err(VeyraError::runtime_error(format!(
+                    "Cannot index a dictionary with {}",
+                    index.type_name()
+                )));
+            };
+            match host.find_key(map, key, index)? {
+                Some(position) => Ok(map.value_mut(key, position)),
+                None if insert => Ok(map.push(key.to_string(), index.clone(), Value::None)),
+                None => Err(missing_key(index)),
+            }
+        }
+        (other, _) => Err(VeyraError::runtime_error(format!(
+            "Cannot assign to an element of {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn missing_key(key: &Value) -> VeyraError {
+    VeyraError::runtime_error(format!(
+        "Key '{}' not found in dictionary",
+        Interpreter::value_to_string(key)
+    ))
+}
+
+/// The field of a struct that an assignment changes
+pub(crate) fn field_mut<'v>(object: &'v mut Value, field: &str) -> Result<&'v mut Value> {
+    match object {
+        Value::Struct(instance) => instance
+            .fields
+            .iter_mut()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                VeyraError::runtime_error(format!(
+                    "Struct '{}' has no field '{}'",
+                    instance.name, field
+                ))
+            }),
+        other => Err(VeyraError::runtime_error(format!(
+            "Cannot assign to field '{}' on {}",
+            field,
+            other.type_name()
+        ))),
+    }
+}
+
+/// What `*reference = value` changes, if `reference` is a mutable reference
+pub(crate) fn assignable_target(reference: &Value) -> Result<&Rc<RefCell<Value>>> {
+    match reference {
+        Value::Reference(r) if r.mutable => Ok(&r.value),
+        Value::Reference(_) => Err(VeyraError::runtime_error(
+            "Cannot assign through an immutable reference",
+        )),
+        other => Err(VeyraError::runtime_error(format!(
+            "Cannot dereference non-reference type: {}",
+            other.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn field_value(object: Value, field: &str) -> Result<Value> {
+    match object {
+        Value::Struct(instance) => instance.field(field).cloned().ok_or_else(|| {
+            VeyraError::runtime_error(format!(
+                "Struct '{}' has no field '{}'",
+                instance.name, field
+            ))
+        }),
+        other => Err(VeyraError::runtime_error(format!(
+            "Cannot access field '{}' on {}",
+            field,
+            other.type_name()
+        ))),
+    }
+}
+
+/// Initial value of an actor field of the given type
+fn zero_value(ty: &Type) -> Value {
+    match ty {
+        Type::Primitive(PrimitiveType::String) => Value::String(String::new()),
+        Type::Primitive(PrimitiveType::Bool) => Value::Boolean(false),
+        Type::Primitive(PrimitiveType::Char) => Value::Char('\0'),
+        Type::Primitive(PrimitiveType::F32 | PrimitiveType::F64) => Value::Float(0.0),
+        Type::Primitive(_) => Value::Integer(0),
+        Type::Array { .. } => Value::Array(Vec::new()),
+        _ => Value::None,
+    }
+}
+
+pub fn interpret(
+    program: &Program,
+    modules: ModuleLoader,
+    args: Vec<String>,
+    deterministic: bool,
+) -> Result<i32> {
+    let mut interpreter = Interpreter::new();
+    interpreter.module_loader = modules;
+    interpreter.args = args;
+    interpreter.deterministic = deterministic;
+    exit_code(run(interpreter, program))
+}
+
+/// Run a program, swapping in changed definitions whenever its source file
+/// is edited
+pub fn interpret_with_hot_reload(
+    program: &Program,
+    reload: HotReload,
+    modules: ModuleLoader,
+    args: Vec<String>,
+    deterministic: bool,
+) -> Result<i32> {
     let mut interpreter = Interpreter::new();
+    interpreter.hot_reload = Some(reload);
+    interpreter.module_loader = modules;
+    interpreter.args = args;
+    interpreter.deterministic = deterministic;
+    exit_code(run(interpreter, program))
+}
+
+/// The exit code of a program that ended with `result`: the code given to
+/// `exit`, or what `main` returned if that is an integer, or else 0
+pub fn exit_code(result: Result<Value>) -> Result<i32> {
+    match result {
+        Ok(Value::Integer(code)) => Ok(code as i32),
+        Ok(_) => Ok(0),
+        Err(VeyraError::Exit(code)) => Ok(code),
+        Err(e) => Err(e),
+    }
+}
+
+/// Names starting with an underscore are private to their module
+fn check_public(module: &str, item: &str) -> Result<()> {
+    if item.starts_with('_') {
+        return Err(VeyraError::runtime_error(format!(
+            "'{}' is private to module '{}'",
+            item, module
+        )));
+    }
+    Ok(())
+}
 
+fn run(mut interpreter: Interpreter, program: &Program) -> Result<Value> {
     // Load standard library modules
     interpreter.load_stdlib()?;
 
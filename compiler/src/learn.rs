@@ -0,0 +1,216 @@
+// Lessons for `veyc learn`
+//
+// A short course for newcomers. Each lesson explains one part of the
+// language and comes with an exercise: a program with a blank (`___`) or a
+// mistake in it. `veyc learn` writes the exercises to a directory, the
+// learner edits them in their own editor, and each one is run by the
+// interpreter and passes once it prints exactly the expected output.
+// Exercises run with no access to files, the network, processes or the
+// environment, and stop after a few seconds in case of an endless loop.
+
+use crate::interpreter::{Interpreter, Limits};
+use crate::lexer::tokenize;
+use crate::parser::parse;
+use crate::sandbox::Policy;
+use std::time::Duration;
+
+pub struct Lesson {
+    /// File name of the exercise, without the `.vey` extension
+    pub name: &'static str,
+    pub title: &'static str,
+    pub explanation: &'static str,
+    /// The exercise as the learner first sees it
+    pub starter: &'static str,
+    pub expected_output: &'static str,
+    pub hint: &'static str,
+    /// A program that passes
+    #[allow(dead_code)]
+    pub solution: &'static str,
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        name: "01_hello",
+        title: "Printing",
+        explanation: "print() writes a value followed by a new line. Text goes in double quotes.",
+        starter: "# Make this program print: Hello, Veyra!\n\nprint(___)\n",
+        expected_output: "Hello, Veyra!\n",
+        hint: "Replace ___ with the text in double quotes: \"Hello, Veyra!\"",
+        solution: "print(\"Hello, Veyra!\")\n",
+    },
+    Lesson {
+        name: "02_variables",
+        title: "Variables",
+        explanation: "let gives a value a name, which later code uses in its place. `{name}` \
+                      inside a string puts the value there.",
+        starter: "# Count the fruit, then print: 5 pieces of fruit\n\n\
+                  let apples = 3\n\
+                  let pears = 2\n\
+                  let total = ___\n\
+                  print(\"{total} pieces of fruit\")\n",
+        expected_output: "5 pieces of fruit\n",
+        hint: "The total is apples + pears.",
+        solution: "let apples = 3\nlet pears = 2\nlet total = apples + pears\n\
+                   print(\"{total} pieces of fruit\")\n",
+    },
+    Lesson {
+        name: "03_functions",
+        title: "Functions",
+        explanation: "fn declares a function. Its parameters go in parentheses and `return` gives \
+                      back its result.",
+        starter: "# Finish square() so that this prints 49\n\n\
+                  fn square(n) {\n    return ___\n}\n\n\
+                  print(square(7))\n",
+        expected_output: "49\n",
+        hint: "The square of n is n * n.",
+        solution: "fn square(n) {\n    return n * n\n}\n\nprint(square(7))\n",
+    },
+    Lesson {
+        name: "04_conditions",
+        title: "Conditions",
+        explanation: "if runs a block when its condition holds. elif tries another condition, and \
+                      else runs when none held.",
+        starter: "# Make describe() return \"cold\" below 10, \"mild\" up to 25 and \"hot\" above\n\n\
+                  fn describe(degrees) {\n\
+                  \x20   if degrees < 10 {\n        return \"cold\"\n\
+                  \x20   } elif ___ {\n        return \"mild\"\n\
+                  \x20   }\n    return \"hot\"\n}\n\n\
+                  print(describe(4))\nprint(describe(18))\nprint(describe(31))\n",
+        expected_output: "cold\nmild\nhot\n",
+        hint: "The second condition is degrees <= 25.",
+        solution: "fn describe(degrees) {\n\
+                   \x20   if degrees < 10 {\n        return \"cold\"\n\
+                   \x20   } elif degrees <= 25 {\n        return \"mild\"\n\
+                   \x20   }\n    return \"hot\"\n}\n\n\
+                   print(describe(4))\nprint(describe(18))\nprint(describe(31))\n",
+    },
+    Lesson {
+        name: "05_loops",
+        title: "Loops",
+        explanation: "for runs a block once for each item of an array. range(start, end) is \
+                      the numbers from start up to, but not including, end. while repeats as \
+                      long as its condition holds.",
+        starter: "# Add up the numbers from 1 to 10, including 10, and print 55\n\n\
+                  let mut total = 0\n\
+                  for n in range(1, 10) {\n    total += n\n}\n\
+                  print(total)\n",
+        expected_output: "55\n",
+        hint: "range(1, 10) stops at 9. Use range(1, 11) to include 10.",
+        solution: "let mut total = 0\nfor n in range(1, 11) {\n    total += n\n}\nprint(total)\n",
+    },
+    Lesson {
+        name: "06_arrays",
+        title: "Arrays",
+        explanation: "Square brackets make an array. push() returns a copy of an array with an \
+                      item added at the end, len() counts the items and `items[0]` is the first \
+                      one.",
+        starter: "# Add \"cherry\" to the fruit so that this prints 3, then cherry\n\n\
+                  let mut fruit = [\"apple\", \"banana\"]\n\
+                  ___\n\
+                  print(len(fruit))\n\
+                  print(fruit[2])\n",
+        expected_output: "3\ncherry\n",
+        hint: "fruit = push(fruit, \"cherry\")",
+        solution: "let mut fruit = [\"apple\", \"banana\"]\nfruit = push(fruit, \"cherry\")\n\
+                   print(len(fruit))\nprint(fruit[2])\n",
+    },
+    Lesson {
+        name: "07_structs",
+        title: "Structs and methods",
+        explanation: "A struct groups named fields. Methods go in an impl block and reach the \
+                      fields through `self`.",
+        starter: "# Finish area() so that this prints 12\n\n\
+                  struct Rectangle {\n    width: int\n    height: int\n}\n\n\
+                  impl Rectangle {\n    fn area(self) {\n        return ___\n    }\n}\n\n\
+                  let r = Rectangle { width: 3, height: 4 }\n\
+                  print(r.area())\n",
+        expected_output: "12\n",
+        hint: "self.width * self.height",
+        solution: "struct Rectangle {\n    width: int\n    height: int\n}\n\n\
+                   impl Rectangle {\n    fn area(self) {\n        return self.width * self.height\n    }\n}\n\n\
+                   let r = Rectangle { width: 3, height: 4 }\n\
+                   print(r.area())\n",
+    },
+    Lesson {
+        name: "08_match",
+        title: "Matching",
+        explanation: "match compares a value with patterns in turn and runs the first arm that \
+                      fits. `_` fits anything.",
+        starter: "# Add an arm so that 2 gives \"two\", then print: one two many\n\n\
+                  fn name(n) {\n    match n {\n        1 -> return \"one\"\n\
+                  \x20       _ -> return \"many\"\n    }\n}\n\n\
+                  print(name(1) + \" \" + name(2) + \" \" + name(7))\n",
+        expected_output: "one two many\n",
+        hint: "Add `2 -> return \"two\"` before the `_` arm, which would otherwise match first.",
+        solution: "fn name(n) {\n    match n {\n        1 -> return \"one\"\n\
+                   \x20       2 -> return \"two\"\n\
+                   \x20       _ -> return \"many\"\n    }\n}\n\n\
+                   print(name(1) + \" \" + name(2) + \" \" + name(7))\n",
+    },
+    Lesson {
+        name: "09_errors",
+        title: "Errors",
+        explanation: "throw raises an error. try runs a block, and if anything in it throws, the \
+                      catch block runs with the error instead.",
+        starter: "# Catch the error so that this prints: cannot divide by zero\n\n\
+                  fn divide(a, b) {\n    if b == 0 {\n        throw \"cannot divide by zero\"\n    }\n\
+                  \x20   return a / b\n}\n\n\
+                  print(divide(1, 0))\n",
+        expected_output: "cannot divide by zero\n",
+        hint: "try {\n    print(divide(1, 0))\n} catch error {\n    print(error)\n}",
+        solution: "fn divide(a, b) {\n    if b == 0 {\n        throw \"cannot divide by zero\"\n    }\n\
+                   \x20   return a / b\n}\n\n\
+                   try {\n    print(divide(1, 0))\n} catch error {\n    print(error)\n}\n",
+    },
+];
+
+/// What running an exercise showed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// The program did not parse, or stopped with an error
+    Failed(String),
+    /// The program ran but printed something else
+    WrongOutput {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Lesson {
+    /// Run an attempt at the exercise
+    pub fn check(&self, source: &str) -> Outcome {
+        let program = match tokenize(source).and_then(parse) {
+            Ok(program) => program,
+            Err(e) => return Outcome::Failed(e.to_string()),
+        };
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.set_echo_output(false);
+        interpreter.set_policy(Policy::deny_all());
+        interpreter.set_limits(Limits {
+            timeout: Some(Duration::from_secs(5)),
+            ..Limits::default()
+        });
+        let result = interpreter.interpret(&program);
+        let actual = interpreter.take_output();
+
+        if let Err(e) = result {
+            return Outcome::Failed(e.to_string());
+        }
+        // Trailing spaces and blank lines are not worth failing over
+        let tidy = |text: &str| {
+            let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+            lines.join("\n").trim_end().to_string()
+        };
+        if tidy(&actual) == tidy(self.expected_output) {
+            Outcome::Passed
+        } else {
+            Outcome::WrongOutput {
+                expected: self.expected_output.to_string(),
+                actual,
+            }
+        }
+    }
+}
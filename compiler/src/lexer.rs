@@ -1,4 +1,11 @@
 use crate::error::{Result, VeyraError};
+use std::ops::Range;
+
+/// A token together with its character range in the source
+pub type RangedToken = (Token, Range<usize>);
+
+/// A lexical error together with the character range it covers
+pub type RangedError = (VeyraError, Range<usize>);
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
@@ -17,6 +24,13 @@ pub enum TokenKind {
     String(String),
     Char(char),
     Boolean(bool),
+    /// The text of an interpolated string up to its first embedded
+    /// expression, then the text between expressions and the text after
+    /// the last one: `"a {x} b {y} c"` is `InterpolationStart("a ")`,
+    /// `x`, `InterpolationMiddle(" b ")`, `y`, `InterpolationEnd(" c")`
+    InterpolationStart(String),
+    InterpolationMiddle(String),
+    InterpolationEnd(String),
 
     // Identifiers and Keywords
     Identifier,
@@ -28,10 +42,12 @@ pub enum TokenKind {
     Await,
     Actor,
     Break,
+    Catch,
     Continue,
     Elif,
     Else,
     False,
+    Finally,
     Fn,
     For,
     If,
@@ -50,24 +66,30 @@ pub enum TokenKind {
     Some,
     Spawn,
     Struct,
+    TaskGroup,
+    Throw,
+    Trait,
     True,
+    Try,
     Unsafe,
     While,
 
     // Operators
-    Plus,     // +
-    Minus,    // -
-    Star,     // *
-    Slash,    // /
-    Percent,  // %
-    StarStar, // **
-
-    Equal,        // =
-    PlusEqual,    // +=
-    MinusEqual,   // -=
-    StarEqual,    // *=
-    SlashEqual,   // /=
-    PercentEqual, // %=
+    Plus,       // +
+    Minus,      // -
+    Star,       // *
+    Slash,      // /
+    SlashSlash, // //
+    Percent,    // %
+    StarStar,   // **
+
+    Equal,           // =
+    PlusEqual,       // +=
+    MinusEqual,      // -=
+    StarEqual,       // *=
+    SlashEqual,      // /=
+    SlashSlashEqual, // //=
+    PercentEqual,    // %=
 
     EqualEqual,   // ==
     BangEqual,    // !=
@@ -111,6 +133,8 @@ pub enum TokenKind {
     Dot,          // .
     Arrow,        // ->
     Dollar,       // $
+    Bang,         // ! (macro invocation, e.g. include!)
+    At,           // @ (attribute, e.g. @cfg(...))
 
     // Special
     Newline,
@@ -122,6 +146,161 @@ pub enum TokenKind {
     Comment,
 }
 
+/// Reserved words, and the token each one lexes to
+pub const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("and", TokenKind::And),
+    ("as", TokenKind::As),
+    ("async", TokenKind::Async),
+    ("await", TokenKind::Await),
+    ("actor", TokenKind::Actor),
+    ("break", TokenKind::Break),
+    ("catch", TokenKind::Catch),
+    ("continue", TokenKind::Continue),
+    ("elif", TokenKind::Elif),
+    ("else", TokenKind::Else),
+    ("false", TokenKind::False),
+    ("finally", TokenKind::Finally),
+    ("fn", TokenKind::Fn),
+    ("for", TokenKind::For),
+    ("if", TokenKind::If),
+    ("impl", TokenKind::Impl),
+    ("import", TokenKind::Import),
+    ("in", TokenKind::In),
+    ("let", TokenKind::Let),
+    ("loop", TokenKind::Loop),
+    ("match", TokenKind::Match),
+    ("mut", TokenKind::Mut),
+    ("None", TokenKind::None),
+    ("not", TokenKind::Not),
+    ("or", TokenKind::Or),
+    ("pub", TokenKind::Pub),
+    ("return", TokenKind::Return),
+    ("Some", TokenKind::Some),
+    ("spawn", TokenKind::Spawn),
+    ("struct", TokenKind::Struct),
+    ("task_group", TokenKind::TaskGroup),
+    ("throw", TokenKind::Throw),
+    ("trait", TokenKind::Trait),
+    ("true", TokenKind::True),
+    ("try", TokenKind::Try),
+    ("unsafe", TokenKind::Unsafe),
+    ("while", TokenKind::While),
+];
+
+/// How a token is highlighted: the kind of keyword it is, the kind of
+/// literal, or a comment, attribute or call. The REPL, the debugger and the
+/// editor grammars all color tokens by these classes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClass {
+    Conditional,
+    Loop,
+    /// Keywords that leave a block or hand over control, like `return`
+    Flow,
+    Exception,
+    /// Keywords that declare something, and any keyword not in another class
+    Declaration,
+    Modifier,
+    Import,
+    /// `and`, `or`, `not`, `in` and `as`
+    Operator,
+    Boolean,
+    /// `None` and `Some`
+    Constant,
+    Number,
+    /// String and character literals, and the text of interpolated strings
+    Text,
+    Comment,
+    /// The `@` starting an attribute
+    Attribute,
+    /// A name that is called
+    Call,
+}
+
+impl TokenClass {
+    /// The class of a token of `kind` followed by one of `next`, or None
+    /// for other names, operators and punctuation
+    pub fn of(kind: &TokenKind, next: Option<&TokenKind>) -> Option<Self> {
+        Some(match kind {
+            TokenKind::If | TokenKind::Elif | TokenKind::Else | TokenKind::Match => {
+                TokenClass::Conditional
+            }
+            TokenKind::For | TokenKind::While | TokenKind::Loop => TokenClass::Loop,
+            TokenKind::Try | TokenKind::Catch | TokenKind::Finally | TokenKind::Throw => {
+                TokenClass::Exception
+            }
+            TokenKind::Return
+            | TokenKind::Break
+            | TokenKind::Continue
+            | TokenKind::Await
+            | TokenKind::Spawn
+            | TokenKind::TaskGroup => TokenClass::Flow,
+            TokenKind::Mut | TokenKind::Pub | TokenKind::Async | TokenKind::Unsafe => {
+                TokenClass::Modifier
+            }
+            TokenKind::Import => TokenClass::Import,
+            TokenKind::And | TokenKind::Or | TokenKind::Not | TokenKind::In | TokenKind::As => {
+                TokenClass::Operator
+            }
+            TokenKind::True | TokenKind::False | TokenKind::Boolean(_) => TokenClass::Boolean,
+            TokenKind::None | TokenKind::Some => TokenClass::Constant,
+            TokenKind::Integer(_) | TokenKind::Float(_) => TokenClass::Number,
+            TokenKind::String(_)
+            | TokenKind::InterpolationStart(_)
+            | TokenKind::InterpolationMiddle(_)
+            | TokenKind::InterpolationEnd(_)
+            | TokenKind::Char(_) => TokenClass::Text,
+            TokenKind::Comment => TokenClass::Comment,
+            TokenKind::At => TokenClass::Attribute,
+            TokenKind::Identifier if next == Some(&TokenKind::LeftParen) => TokenClass::Call,
+            kind if KEYWORDS.iter().any(|(_, keyword)| keyword == kind) => TokenClass::Declaration,
+            _ => return None,
+        })
+    }
+}
+
+/// Operator symbols, and the token each one lexes to
+pub const OPERATORS: &[(&str, TokenKind)] = &[
+    ("+", TokenKind::Plus),
+    ("-", TokenKind::Minus),
+    ("*", TokenKind::Star),
+    ("/", TokenKind::Slash),
+    ("//", TokenKind::SlashSlash),
+    ("%", TokenKind::Percent),
+    ("**", TokenKind::StarStar),
+    ("=", TokenKind::Equal),
+    ("+=", TokenKind::PlusEqual),
+    ("-=", TokenKind::MinusEqual),
+    ("*=", TokenKind::StarEqual),
+    ("/=", TokenKind::SlashEqual),
+    ("//=", TokenKind::SlashSlashEqual),
+    ("%=", TokenKind::PercentEqual),
+    ("==", TokenKind::EqualEqual),
+    ("!=", TokenKind::BangEqual),
+    ("<", TokenKind::Less),
+    ("<=", TokenKind::LessEqual),
+    (">", TokenKind::Greater),
+    (">=", TokenKind::GreaterEqual),
+    ("?", TokenKind::Question),
+    ("?.", TokenKind::QuestionDot),
+    ("..", TokenKind::DotDot),
+    ("..=", TokenKind::DotDotEqual),
+    ("<-", TokenKind::LeftArrow),
+    ("->", TokenKind::Arrow),
+    ("&", TokenKind::Ampersand),
+    ("|", TokenKind::Pipe),
+    ("^", TokenKind::Caret),
+    ("~", TokenKind::Tilde),
+    ("&&", TokenKind::AmpersandAmpersand),
+    ("||", TokenKind::PipePipe),
+    ("<<", TokenKind::LeftShift),
+    (">>", TokenKind::RightShift),
+    ("&=", TokenKind::AmpersandEqual),
+    ("|=", TokenKind::PipeEqual),
+    ("^=", TokenKind::CaretEqual),
+    ("<<=", TokenKind::LeftShiftEqual),
+    (">>=", TokenKind::RightShiftEqual),
+];
+
 pub struct Lexer {
     input: Vec<char>,
     position: usize,
@@ -129,6 +308,16 @@ pub struct Lexer {
     column: usize,
     #[allow(dead_code)]
     indent_stack: Vec<usize>,
+    /// The string interpolations being lexed, innermost last
+    interpolations: Vec<Interpolation>,
+}
+
+/// An expression embedded in a string, being lexed
+struct Interpolation {
+    /// Braces opened inside it and not yet closed
+    braces: usize,
+    /// Line and column of the string's opening quote
+    quote: (usize, usize),
 }
 
 impl Lexer {
@@ -139,10 +328,30 @@ impl Lexer {
             line: 1,
             column: 1,
             indent_stack: vec![0], // Start with 0 indentation
+            interpolations: Vec::new(),
+        }
+    }
+
+    /// A lexer for `input` cut from a larger source at `line` and `column`,
+    /// so tokens and errors give their positions in that source
+    #[allow(dead_code)]
+    pub fn at(input: &str, line: usize, column: usize) -> Self {
+        Self {
+            line,
+            column,
+            ..Self::new(input)
         }
     }
 
     pub fn tokenize(&mut self) -> Result<Vec<Token>> {
+        let mut tokens = self.tokenize_with_comments()?;
+        tokens.retain(|token| token.kind != TokenKind::Comment);
+        Ok(tokens)
+    }
+
+    /// Tokenize keeping comments, which the parser attaches to the program
+    /// for tools that write it back out, such as the formatter
+    pub fn tokenize_with_comments(&mut self) -> Result<Vec<Token>> {
         let mut tokens = Vec::new();
 
         while !self.is_at_end() {
@@ -152,10 +361,7 @@ impl Lexer {
                 break;
             }
 
-            let token = self.next_token()?;
-            if token.kind != TokenKind::Comment {
-                tokens.push(token);
-            }
+            tokens.push(self.next_token()?);
         }
 
         tokens.push(Token {
@@ -168,6 +374,30 @@ impl Lexer {
         Ok(tokens)
     }
 
+    /// Tokenize for editor tooling such as syntax highlighting. Comments are
+    /// kept, each token carries its character range in the input, and a
+    /// lexical error ends tokenization without discarding earlier tokens.
+    #[allow(dead_code)]
+    pub fn tokenize_with_ranges(&mut self) -> (Vec<RangedToken>, Option<RangedError>) {
+        let mut tokens = Vec::new();
+
+        while !self.is_at_end() {
+            self.skip_whitespace();
+
+            if self.is_at_end() {
+                break;
+            }
+
+            let start = self.position;
+            match self.next_token() {
+                Ok(token) => tokens.push((token, start..self.position)),
+                Err(error) => return (tokens, Some((error, start..self.input.len()))),
+            }
+        }
+
+        (tokens, None)
+    }
+
     #[allow(dead_code)]
     fn handle_line_start(&mut self, tokens: &mut Vec<Token>) -> Result<()> {
         if self.is_at_end() {
@@ -251,6 +481,7 @@ impl Lexer {
     }
 
     fn next_token(&mut self) -> Result<Token> {
+        let start = self.position;
         let start_line = self.line;
         let start_column = self.column;
 
@@ -262,8 +493,25 @@ impl Lexer {
             ')' => TokenKind::RightParen,
             '[' => TokenKind::LeftBracket,
             ']' => TokenKind::RightBracket,
-            '{' => TokenKind::LeftBrace,
-            '}' => TokenKind::RightBrace,
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.braces += 1;
+                }
+                TokenKind::LeftBrace
+            }
+            '}' => match self.interpolations.last_mut() {
+                // The end of an embedded expression: the string goes on
+                Some(Interpolation { braces: 0, quote }) => {
+                    let quote = *quote;
+                    self.interpolations.pop();
+                    return self.string_literal(start_column, quote, true);
+                }
+                Some(interpolation) => {
+                    interpolation.braces -= 1;
+                    TokenKind::RightBrace
+                }
+                None => TokenKind::RightBrace,
+            },
             ',' => TokenKind::Comma,
             ';' => TokenKind::Semicolon,
             ':' => {
@@ -308,7 +556,13 @@ impl Lexer {
                 }
             }
             '/' => {
-                if self.match_char('=') {
+                if self.match_char('/') {
+                    if self.match_char('=') {
+                        TokenKind::SlashSlashEqual
+                    } else {
+                        TokenKind::SlashSlash
+                    }
+                } else if self.match_char('=') {
                     TokenKind::SlashEqual
                 } else {
                     TokenKind::Slash
@@ -325,7 +579,7 @@ impl Lexer {
                 if self.match_char('=') {
                     TokenKind::BangEqual
                 } else {
-                    return self.error("Unexpected character '!'");
+                    TokenKind::Bang
                 }
             }
             '<' => {
@@ -382,6 +636,7 @@ impl Lexer {
                 }
             }
             '~' => TokenKind::Tilde,
+            '@' => TokenKind::At,
             '?' => {
                 if self.match_char('.') {
                     TokenKind::QuestionDot
@@ -401,8 +656,9 @@ impl Lexer {
                 }
             }
 
-            // Comments
+            // Comments, whose lexeme is the whole comment
             '#' => {
+                let start = self.position - 1;
                 if self.match_char('[') && self.match_char('[') {
                     // Block comment
                     self.skip_block_comment()?;
@@ -410,10 +666,19 @@ impl Lexer {
                     // Line comment
                     self.skip_line_comment();
                 }
-                TokenKind::Comment
+                let text: String = self.input[start..self.position].iter().collect();
+                return Ok(Token {
+                    kind: TokenKind::Comment,
+                    lexeme: text.trim_end().to_string(),
+                    line: start_line,
+                    column: start_column,
+                });
             }
 
             // Newlines
+            '\n' if !self.interpolations.is_empty() => {
+                return self.error("Expected '}' to end the expression embedded in the string");
+            }
             '\n' => {
                 self.line += 1;
                 self.column = 1;
@@ -428,8 +693,17 @@ impl Lexer {
             }
 
             // String literals
+            '"' if self.peek() == '"' && self.input.get(self.position + 1) == Some(&'"') => {
+                self.position += 2;
+                self.column += 2;
+                return self.multiline_string_literal(start_column);
+            }
+            'r' if self.peek() == '"' => {
+                self.advance();
+                return self.raw_string_literal(start_column);
+            }
             '"' => {
-                return self.string_literal();
+                return self.string_literal(start_column, (start_line, start_column), false);
             }
 
             // Character literals
@@ -448,49 +722,19 @@ impl Lexer {
             }
 
             _ => {
-                return self.error(&format!("Unexpected character '{}'", c));
+                return Err(VeyraError::lex_error(
+                    start_line,
+                    start_column,
+                    format!("Unexpected character '{}'", c),
+                ));
             }
         };
 
-        let lexeme = match &kind {
-            TokenKind::LeftParen => "(".to_string(),
-            TokenKind::RightParen => ")".to_string(),
-            TokenKind::LeftBracket => "[".to_string(),
-            TokenKind::RightBracket => "]".to_string(),
-            TokenKind::LeftBrace => "{".to_string(),
-            TokenKind::RightBrace => "}".to_string(),
-            TokenKind::Comma => ",".to_string(),
-            TokenKind::Semicolon => ";".to_string(),
-            TokenKind::Colon => ":".to_string(),
-            TokenKind::Plus => "+".to_string(),
-            TokenKind::Minus => "-".to_string(),
-            TokenKind::Star => "*".to_string(),
-            TokenKind::Slash => "/".to_string(),
-            TokenKind::Percent => "%".to_string(),
-            TokenKind::StarStar => "**".to_string(),
-            TokenKind::Equal => "=".to_string(),
-            TokenKind::PlusEqual => "+=".to_string(),
-            TokenKind::MinusEqual => "-=".to_string(),
-            TokenKind::StarEqual => "*=".to_string(),
-            TokenKind::SlashEqual => "/=".to_string(),
-            TokenKind::PercentEqual => "%=".to_string(),
-            TokenKind::EqualEqual => "==".to_string(),
-            TokenKind::BangEqual => "!=".to_string(),
-            TokenKind::Less => "<".to_string(),
-            TokenKind::LessEqual => "<=".to_string(),
-            TokenKind::Greater => ">".to_string(),
-            TokenKind::GreaterEqual => ">=".to_string(),
-            TokenKind::Question => "?".to_string(),
-            TokenKind::QuestionDot => "?.".to_string(),
-            TokenKind::DotDot => "..".to_string(),
-            TokenKind::DotDotEqual => "..=".to_string(),
-            TokenKind::LeftArrow => "<-".to_string(),
-            TokenKind::Arrow => "->".to_string(),
-            TokenKind::Dollar => "$".to_string(),
-            TokenKind::Dot => ".".to_string(),
-            TokenKind::Newline => "\\n".to_string(),
-            TokenKind::Comment => "#".to_string(),
-            _ => String::new(),
+        // Newlines are spelled out so a listing of tokens stays one per line
+        let lexeme = if kind == TokenKind::Newline {
+            "\\n".to_string()
+        } else {
+            self.lexeme_from_range(start, self.position)
         };
 
         Ok(Token {
@@ -501,83 +745,39 @@ impl Lexer {
         })
     }
 
-    fn string_literal(&mut self) -> Result<Token> {
+    /// A string literal, after its opening quote, or the rest of an
+    /// interpolated string after the '}' ending an embedded expression
+    /// (`resumed`). The string ends at the closing quote or at the '{'
+    /// starting an embedded expression. `quote` is where its opening quote
+    /// is, which an unterminated string is reported at.
+    fn string_literal(
+        &mut self,
+        start_column: usize,
+        quote: (usize, usize),
+        resumed: bool,
+    ) -> Result<Token> {
         let start_line = self.line;
-        let start_column = self.column - 1; // Include opening quote
+        let start = self.position - 1; // Include the opening quote or '}'
         let mut value = String::new();
 
         while !self.is_at_end() && self.peek() != '"' {
             let c = self.advance();
+            if c == '{' && starts_interpolation(self.peek()) {
+                self.interpolations.push(Interpolation { braces: 0, quote });
+                let kind = if resumed {
+                    TokenKind::InterpolationMiddle(value)
+                } else {
+                    TokenKind::InterpolationStart(value)
+                };
+                return Ok(Token {
+                    kind,
+                    lexeme: self.lexeme_from_range(start, self.position),
+                    line: start_line,
+                    column: start_column,
+                });
+            }
             if c == '\\' {
-                // Handle escape sequences
-                if self.is_at_end() {
-                    return self.error("Unterminated string literal");
-                }
-                let escaped = self.advance();
-                match escaped {
-                    'n' => value.push('\n'),
-                    'r' => value.push('\r'),
-                    't' => value.push('\t'),
-                    '\\' => value.push('\\'),
-                    '"' => value.push('"'),
-                    '\'' => value.push('\''),
-                    '0' => value.push('\0'),
-                    'x' => {
-                        // Hex escape: \xHH
-                        if self.position + 1 >= self.input.len() {
-                            return self.error("Incomplete hex escape sequence");
-                        }
-                        let hex_chars: String = self.input[self.position..self.position + 2]
-                            .iter()
-                            .collect();
-                        if let Ok(byte) = u8::from_str_radix(&hex_chars, 16) {
-                            value.push(byte as char);
-                            self.position += 2;
-                            self.column += 2;
-                        } else {
-                            return self.error("Invalid hex escape sequence");
-                        }
-                    }
-                    'u' => {
-                        // Unicode escape: \u{HHHHHH}
-                        if self.peek() != '{' {
-                            return self.error("Expected '{' after \\u");
-                        }
-                        self.advance(); // consume '{'
-
-                        let mut hex_digits = String::new();
-                        while !self.is_at_end() && self.peek() != '}' {
-                            let digit = self.advance();
-                            if digit.is_ascii_hexdigit() {
-                                hex_digits.push(digit);
-                            } else {
-                                return self.error("Invalid character in unicode escape");
-                            }
-                        }
-
-                        if self.is_at_end() || self.peek() != '}' {
-                            return self.error("Unterminated unicode escape");
-                        }
-                        self.advance(); // consume '}'
-
-                        if hex_digits.is_empty() || hex_digits.len() > 6 {
-                            return self.error("Invalid unicode escape length");
-                        }
-
-                        if let Ok(code_point) = u32::from_str_radix(&hex_digits, 16) {
-                            if let Some(ch) = char::from_u32(code_point) {
-                                value.push(ch);
-                            } else {
-                                return self.error("Invalid unicode code point");
-                            }
-                        } else {
-                            return self.error("Invalid unicode escape sequence");
-                        }
-                    }
-                    _ => {
-                        return self.error(&format!("Unknown escape sequence '\\{}'", escaped));
-                    }
-                }
+                value.push(self.string_escape()?);
             } else {
                 if c == '\n' {
                     self.line += 1;
@@ -588,20 +788,214 @@ impl Lexer {
         }
 
         if self.is_at_end() {
-            return self.error("Unterminated string literal");
+            // Most likely the quote was meant to end an outer string whose
+            // embedded expression is missing its '}'
+            if !self.interpolations.is_empty() {
+                return self.error("Expected '}' to end the expression embedded in the string");
+            }
+            let (line, column) = quote;
+            return Err(VeyraError::lex_error(
+                line,
+                column,
+                "Unterminated string literal",
+            ));
         }
 
         // Consume closing quote
         self.advance();
 
+        if resumed {
+            return Ok(Token {
+                kind: TokenKind::InterpolationEnd(value),
+                lexeme: self.lexeme_from_range(start, self.position),
+                line: start_line,
+                column: start_column,
+            });
+        }
+        Ok(Token {
+            kind: TokenKind::String(value),
+            lexeme: self.lexeme_from_range(start, self.position),
+            line: start_line,
+            column: start_column,
+        })
+    }
+
+    /// A multi-line string, after its opening `"""`. Escapes work as in
+    /// other strings, but quotes need none and braces are literal text. A
+    /// line break right after the opening quotes is not part of the string,
+    /// and when the closing quotes are on a line of their own, that line's
+    /// indentation is removed from every line, so the text can be indented
+    /// along with the code around it.
+    fn multiline_string_literal(&mut self, start_column: usize) -> Result<Token> {
+        let start_line = self.line;
+        let start = self.position - 3;
+        // Each line's leading whitespace, then the rest of it with escapes
+        // already replaced
+        let mut lines = vec![(String::new(), String::new())];
+
+        loop {
+            if self.is_at_end() {
+                return Err(VeyraError::lex_error(
+                    start_line,
+                    start_column,
+                    "Unterminated multi-line string literal",
+                ));
+            }
+            if self.input[self.position..].starts_with(&['"', '"', '"']) {
+                break;
+            }
+            let c = self.advance();
+            let (indent, text) = lines.last_mut().unwrap();
+            match c {
+                '\\' => text.push(self.string_escape()?),
+                '\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                    lines.push((String::new(), String::new()));
+                }
+                ' ' | '\t' if text.is_empty() => indent.push(c),
+                _ => text.push(c),
+            }
+        }
+        self.position += 3;
+        self.column += 3;
+
+        if lines.len() > 1 && lines[0] == (String::new(), String::new()) {
+            lines.remove(0);
+        }
+        let margin = match lines.last() {
+            Some((indent, text)) if lines.len() > 1 && text.is_empty() => {
+                let margin = indent.clone();
+                lines.pop();
+                margin
+            }
+            _ => String::new(),
+        };
+
+        let mut value = Vec::new();
+        for (indent, text) in lines {
+            match indent.strip_prefix(margin.as_str()) {
+                Some(indent) => value.push(format!("{}{}", indent, text)),
+                None if text.is_empty() => value.push(String::new()),
+                None => {
+                    return Err(VeyraError::lex_error(
+                        start_line,
+                        start_column,
+                        "A line of this multi-line string is indented less than its closing quotes",
+                    ))
+                }
+            }
+        }
+
         Ok(Token {
-            kind: TokenKind::String(value.clone()),
-            lexeme: format!("\"{}\"", value),
+            kind: TokenKind::String(value.join("\n")),
+            lexeme: self.lexeme_from_range(start, self.position),
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
+    /// A raw string, after its opening `r"`: everything up to the next
+    /// quote, with backslashes and braces taken literally
+    fn raw_string_literal(&mut self, start_column: usize) -> Result<Token> {
+        let start_line = self.line;
+        let start = self.position - 2;
+        let mut value = String::new();
+
+        while !self.is_at_end() && self.peek() != '"' {
+            let c = self.advance();
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            }
+            value.push(c);
+        }
+        if self.is_at_end() {
+            return Err(VeyraError::lex_error(
+                start_line,
+                start_column,
+                "Unterminated raw string literal",
+            ));
+        }
+        self.advance(); // consume closing quote
+
+        Ok(Token {
+            kind: TokenKind::String(value),
+            lexeme: self.lexeme_from_range(start, self.position),
+            line: start_line,
+            column: start_column,
+        })
+    }
+
+    /// The character an escape sequence in a string stands for, after its
+    /// backslash
+    fn string_escape(&mut self) -> Result<char> {
+        if self.is_at_end() {
+            return self.error("Unterminated string literal");
+        }
+        let escaped = self.advance();
+        match escaped {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '\'' => Ok('\''),
+            '{' => Ok('{'),
+            '}' => Ok('}'),
+            '0' => Ok('\0'),
+            'x' => {
+                // Hex escape: \xHH
+                if self.position + 1 >= self.input.len() {
+                    return self.error("Incomplete hex escape sequence");
+                }
+                let hex_chars: String = self.input[self.position..self.position + 2]
+                    .iter()
+                    .collect();
+                if let Ok(byte) = u8::from_str_radix(&hex_chars, 16) {
+                    self.position += 2;
+                    self.column += 2;
+                    Ok(byte as char)
+                } else {
+                    self.error("Invalid hex escape sequence")
+                }
+            }
+            'u' => {
+                // Unicode escape: \u{HHHHHH}
+                if self.peek() != '{' {
+                    return self.error("Expected '{' after \\u");
+                }
+                self.advance(); // consume '{'
+
+                let mut hex_digits = String::new();
+                while !self.is_at_end() && self.peek() != '}' {
+                    let digit = self.advance();
+                    if digit.is_ascii_hexdigit() {
+                        hex_digits.push(digit);
+                    } else {
+                        return self.error("Invalid character in unicode escape");
+                    }
+                }
+
+                if self.is_at_end() || self.peek() != '}' {
+                    return self.error("Unterminated unicode escape");
+                }
+                self.advance(); // consume '}'
+
+                if hex_digits.is_empty() || hex_digits.len() > 6 {
+                    return self.error("Invalid unicode escape length");
+                }
+
+                match u32::from_str_radix(&hex_digits, 16).map(char::from_u32) {
+                    Ok(Some(ch)) => Ok(ch),
+                    Ok(None) => self.error("Invalid unicode code point"),
+                    Err(_) => self.error("Invalid unicode escape sequence"),
+                }
+            }
+            _ => self.error(&format!("Unknown escape sequence '\\{}'", escaped)),
+        }
+    }
+
     fn char_literal(&mut self) -> Result<Token> {
         let start_line = self.line;
         let start_column = self.column - 1; // Include opening quote
@@ -664,22 +1058,23 @@ impl Lexer {
                 format!("'{}'", value)
             },
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
     fn number_literal(&mut self, first_digit: char) -> Result<Token> {
         let start_line = self.line;
         let start_column = self.column - 1;
+        let start = self.position - 1;
         let mut value = String::new();
         value.push(first_digit);
 
         // Handle different number bases
         if first_digit == '0' && !self.is_at_end() {
             match self.peek() {
-                'b' => return self.binary_literal(start_line, start_column),
-                'o' => return self.octal_literal(start_line, start_column),
-                'x' => return self.hex_literal(start_line, start_column),
+                'b' => return self.binary_literal(start, start_line, start_column),
+                'o' => return self.octal_literal(start, start_line, start_column),
+                'x' => return self.hex_literal(start, start_line, start_column),
                 _ => {}
             }
         }
@@ -719,30 +1114,33 @@ impl Lexer {
             }
 
             let float_val = value.parse::<f64>().map_err(|_| {
-                VeyraError::lex_error(start_line, start_column + 1, "Invalid float literal")
+                VeyraError::lex_error(start_line, start_column, "Invalid float literal")
             })?;
 
             return Ok(Token {
                 kind: TokenKind::Float(float_val),
-                lexeme: self.lexeme_from_range(start_column, self.column - 1),
+                lexeme: self.lexeme_from_range(start, self.position),
                 line: start_line,
-                column: start_column + 1,
+                column: start_column,
             });
         }
 
-        let int_val = value.parse::<i64>().map_err(|_| {
-            VeyraError::lex_error(start_line, start_column + 1, "Invalid integer literal")
-        })?;
+        let int_val = integer(&value, 10, start_line, start_column)?;
 
         Ok(Token {
             kind: TokenKind::Integer(int_val),
-            lexeme: self.lexeme_from_range(start_column, self.column - 1),
+            lexeme: self.lexeme_from_range(start, self.position),
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
-    fn binary_literal(&mut self, start_line: usize, start_column: usize) -> Result<Token> {
+    fn binary_literal(
+        &mut self,
+        start: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Result<Token> {
         self.advance(); // consume 'b'
         let mut value = String::new();
 
@@ -754,23 +1152,27 @@ impl Lexer {
             }
         }
 
+        self.check_radix_end("binary")?;
         if value.is_empty() {
             return self.error("Invalid binary literal");
         }
 
-        let int_val = i64::from_str_radix(&value, 2).map_err(|_| {
-            VeyraError::lex_error(start_line, start_column + 1, "Invalid binary literal")
-        })?;
+        let int_val = integer(&value, 2, start_line, start_column)?;
 
         Ok(Token {
             kind: TokenKind::Integer(int_val),
-            lexeme: self.lexeme_from_range(start_column, self.column - 1),
+            lexeme: self.lexeme_from_range(start, self.position),
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
-    fn octal_literal(&mut self, start_line: usize, start_column: usize) -> Result<Token> {
+    fn octal_literal(
+        &mut self,
+        start: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Result<Token> {
         self.advance(); // consume 'o'
         let mut value = String::new();
 
@@ -783,23 +1185,27 @@ impl Lexer {
             }
         }
 
+        self.check_radix_end("octal")?;
         if value.is_empty() {
             return self.error("Invalid octal literal");
         }
 
-        let int_val = i64::from_str_radix(&value, 8).map_err(|_| {
-            VeyraError::lex_error(start_line, start_column + 1, "Invalid octal literal")
-        })?;
+        let int_val = integer(&value, 8, start_line, start_column)?;
 
         Ok(Token {
             kind: TokenKind::Integer(int_val),
-            lexeme: self.lexeme_from_range(start_column, self.column - 1),
+            lexeme: self.lexeme_from_range(start, self.position),
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
-    fn hex_literal(&mut self, start_line: usize, start_column: usize) -> Result<Token> {
+    fn hex_literal(
+        &mut self,
+        start: usize,
+        start_line: usize,
+        start_column: usize,
+    ) -> Result<Token> {
         self.advance(); // consume 'x'
         let mut value = String::new();
 
@@ -810,22 +1216,34 @@ impl Lexer {
             }
         }
 
+        self.check_radix_end("hexadecimal")?;
         if value.is_empty() {
             return self.error("Invalid hexadecimal literal");
         }
 
-        let int_val = i64::from_str_radix(&value, 16).map_err(|_| {
-            VeyraError::lex_error(start_line, start_column + 1, "Invalid hexadecimal literal")
-        })?;
+        let int_val = integer(&value, 16, start_line, start_column)?;
 
         Ok(Token {
             kind: TokenKind::Integer(int_val),
-            lexeme: self.lexeme_from_range(start_column, self.column - 1),
+            lexeme: self.lexeme_from_range(start, self.position),
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
+    /// A letter or digit right after a binary, octal or hexadecimal literal
+    /// is a digit its base does not allow, as in `0b102` or `0xFG`
+    fn check_radix_end(&self, base: &str) -> Result<()> {
+        if !self.is_at_end() && self.peek().is_alphanumeric() {
+            return self.error(&format!(
+                "Invalid digit '{}' in {} literal",
+                self.peek(),
+                base
+            ));
+        }
+        Ok(())
+    }
+
     fn identifier_or_keyword(&mut self, first_char: char) -> Result<Token> {
         let start_line = self.line;
         let start_column = self.column - 1;
@@ -836,46 +1254,16 @@ impl Lexer {
             value.push(self.advance());
         }
 
-        let kind = match value.as_str() {
-            "and" => TokenKind::And,
-            "as" => TokenKind::As,
-            "async" => TokenKind::Async,
-            "await" => TokenKind::Await,
-            "actor" => TokenKind::Actor,
-            "break" => TokenKind::Break,
-            "continue" => TokenKind::Continue,
-            "elif" => TokenKind::Elif,
-            "else" => TokenKind::Else,
-            "false" => TokenKind::False,
-            "fn" => TokenKind::Fn,
-            "for" => TokenKind::For,
-            "if" => TokenKind::If,
-            "impl" => TokenKind::Impl,
-            "import" => TokenKind::Import,
-            "in" => TokenKind::In,
-            "let" => TokenKind::Let,
-            "loop" => TokenKind::Loop,
-            "match" => TokenKind::Match,
-            "mut" => TokenKind::Mut,
-            "None" => TokenKind::None,
-            "not" => TokenKind::Not,
-            "or" => TokenKind::Or,
-            "pub" => TokenKind::Pub,
-            "return" => TokenKind::Return,
-            "Some" => TokenKind::Some,
-            "spawn" => TokenKind::Spawn,
-            "struct" => TokenKind::Struct,
-            "true" => TokenKind::True,
-            "unsafe" => TokenKind::Unsafe,
-            "while" => TokenKind::While,
-            _ => TokenKind::Identifier,
-        };
+        let kind = KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == value)
+            .map_or(TokenKind::Identifier, |(_, kind)| kind.clone());
 
         Ok(Token {
             kind,
             lexeme: value,
             line: start_line,
-            column: start_column + 1,
+            column: start_column,
         })
     }
 
@@ -1027,6 +1415,20 @@ impl Lexer {
     }
 }
 
+/// The value of an integer literal's digits in `radix`, which are all
+/// valid, so only a value too large for an int is an error
+fn integer(digits: &str, radix: u32, line: usize, column: usize) -> Result<i64> {
+    i64::from_str_radix(digits, radix)
+        .map_err(|_| VeyraError::lex_error(line, column, "Integer literal out of range"))
+}
+
+/// Whether a '{' followed by `next` in a string starts an embedded
+/// expression. Only names and parenthesized expressions are embedded, so
+/// `{}`, `{0}` and `{:.2}` stay literal text, as do JSON-like strings.
+pub fn starts_interpolation(next: char) -> bool {
+    next.is_alphabetic() || next == '_' || next == '('
+}
+
 pub fn tokenize(input: &str) -> Result<Vec<Token>> {
     let mut lexer = Lexer::new(input);
     lexer.tokenize()
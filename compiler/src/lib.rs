@@ -1,15 +1,53 @@
 // Library interface for the Veyra compiler
 // This exposes the internal modules for use by other tools like LSP
 
+pub mod allocations;
 pub mod ast;
+pub mod async_io;
+pub mod builtins;
+pub mod bytecode;
+pub mod cfg;
+pub mod convert;
+pub mod crash;
+pub mod deprecation;
+pub mod diagnostic;
 pub mod error;
+pub mod events;
+pub mod explain;
+pub mod fiber;
+pub mod flow;
+pub mod format;
+pub mod grammar;
+pub mod highlight;
+pub mod hot_reload;
+pub mod include;
+pub mod incremental;
 pub mod interpreter;
+pub mod learn;
 pub mod lexer;
+pub mod module_cache;
+pub mod modules;
+pub mod optimize;
 pub mod parser;
+pub mod playground;
+pub mod printer;
+pub mod resolve;
+pub mod sandbox;
+pub mod stats;
+pub mod suggest;
+pub mod table;
+pub mod typeck;
+pub mod vm;
+
+/// Version of the compiler, which tools built against it compare with
+/// `veyc --version`
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 // Re-export commonly used types
 pub use ast::*;
+pub use builtins::BuiltinDoc;
+pub use diagnostic::{Diagnostic, Severity};
 pub use error::VeyraError;
-pub use interpreter::Interpreter;
+pub use interpreter::{Interpreter, Limits};
 pub use lexer::{Lexer, Token, TokenKind};
 pub use parser::Parser;
@@ -1,13 +1,50 @@
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 
+mod allocations;
 mod ast;
+mod async_io;
+mod builtins;
+mod bytecode;
+mod cfg;
+mod crash;
+mod deprecation;
+mod diagnostic;
 mod error;
+mod events;
+mod explain;
+mod fiber;
+mod format;
+mod grammar;
+mod highlight;
+mod hot_reload;
+mod include;
 mod interpreter;
+mod learn;
 mod lexer;
+mod module_cache;
+mod modules;
+mod optimize;
 mod parser;
+mod playground;
+// veyc only prints single expressions, for trace()
+#[allow(dead_code)]
+mod printer;
+mod resolve;
+mod sandbox;
+mod stats;
+mod suggest;
+#[allow(dead_code)]
+mod table;
+mod typeck;
+mod vm;
 
+use diagnostic::{Diagnostic, Severity};
 use error::VeyraError;
+use events::Event;
 
 #[derive(Parser)]
 #[command(name = "veyc")]
@@ -20,34 +57,149 @@ struct Cli {
     /// Input file to compile
     input: Option<PathBuf>,
 
-    /// Output file (defaults to input name with .exe extension)
+    /// Output file (defaults to input name with .veyb extension)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Diagnostic output format
+    #[arg(long, value_enum, global = true, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Report diagnostics, and the command finishing, as newline-delimited
+    /// JSON events in the format shared with the other Veyra tools
+    #[arg(long, global = true)]
+    log_json: bool,
+
+    /// Features to enable for @cfg(feature = "...") items (comma-separated)
+    #[arg(long, global = true, value_delimiter = ',')]
+    features: Vec<String>,
+
+    /// Do not enable the project's default features
+    #[arg(long, global = true)]
+    no_default_features: bool,
+
+    /// Optimization level: 0 (none), 1 (constant folding and propagation,
+    /// dead branch elimination) or 2 (also inline tiny functions)
+    #[arg(
+        short = 'O',
+        long = "opt-level",
+        global = true,
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=optimize::MAX_LEVEL as i64)
+    )]
+    opt_level: u8,
+}
+
+/// Features requested on the command line
+struct FeatureArgs {
+    features: Vec<String>,
+    default_features: bool,
+}
+
+/// How `veyc run` runs a program
+#[derive(Default)]
+struct RunOptions {
+    hot_reload: bool,
+    vm: bool,
+    no_cache: bool,
+    deterministic: bool,
+    args: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum MessageFormat {
+    /// Human-readable messages
+    Human,
+    /// Newline-delimited JSON diagnostics on stdout
+    Json,
+    /// Newline-delimited JSON events on stdout, chosen with --log-json
+    #[value(skip)]
+    Events,
+}
+
+impl MessageFormat {
+    /// Print a diagnostic about the file being processed
+    fn report(self, diagnostic: &Diagnostic) {
+        match self {
+            MessageFormat::Human => {
+                let source = diagnostic
+                    .span
+                    .as_ref()
+                    .and_then(|span| std::fs::read_to_string(&span.file).ok());
+                eprint!("{}", diagnostic.render(source.as_deref()));
+            }
+            MessageFormat::Json => println!("{}", diagnostic.to_json()),
+            MessageFormat::Events => Event::from_diagnostic("veyc", diagnostic).emit(),
+        }
+    }
+}
+
+/// How `veyc parse` prints the AST
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum AstFormat {
+    /// Rust debug output, for reading
+    Debug,
+    /// The AST's serde form as JSON, for other tools to read
+    Json,
+}
+
+/// What `veyc grammar` prints
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum GrammarFormat {
+    /// The grammar as EBNF
+    Ebnf,
+    /// A TextMate grammar (JSON) for Sublime Text, VS Code and other editors
+    Tmlanguage,
+    /// A Vim syntax file
+    Vim,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Compile a Veyra source file
+    /// Compile a Veyra source file to bytecode
     Compile {
         /// Input file to compile
         input: PathBuf,
-        /// Output file
+        /// Output file (defaults to input name with .veyb extension)
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
     /// Run a Veyra source file directly (interpret)
     Run {
-        /// Input file to run
+        /// Input file to run: a source file, or a module compiled to
+        /// bytecode, which runs on the VM
         input: PathBuf,
+        /// Reload changed functions while the program runs
+        #[arg(long)]
+        hot_reload: bool,
+        /// Compile to bytecode and run it on the VM rather than the
+        /// tree-walking interpreter
+        #[arg(long, conflicts_with = "hot_reload")]
+        vm: bool,
+        /// Compile every module again instead of reusing the compiled
+        /// modules cached under target/
+        #[arg(long)]
+        no_cache: bool,
+        /// Run the same way every time, for snapshot tests of the output:
+        /// now() returns a fixed time and trace() records none
+        #[arg(long)]
+        deterministic: bool,
+        /// Arguments for the program, which it reads with args()
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
     },
     /// Check syntax without compiling
     Check {
-        /// Input file to check
-        input: PathBuf,
+        /// Input file to check (the entry point when used with --project)
+        input: Option<PathBuf>,
+        /// Check the whole project, following imports from the entry point
+        /// (or every file under src/ when there is no entry point)
+        #[arg(long)]
+        project: bool,
     },
     /// Show lexer tokens for debugging
     Lex {
@@ -58,24 +210,271 @@ enum Commands {
     Parse {
         /// Input file to parse
         input: PathBuf,
+        /// How to print the AST
+        #[arg(long, value_enum, default_value_t = AstFormat::Debug)]
+        format: AstFormat,
+    },
+    /// Summarize the code in a file or directory: lines, declarations,
+    /// comment ratio, function complexity and the longest functions
+    Stats {
+        /// File or directory to summarize
+        #[arg(default_value = ".")]
+        path: PathBuf,
+        /// How many of the longest functions to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Learn Veyra through short exercises, each checked when you have
+    /// edited it
+    Learn {
+        /// Directory to write the exercises to
+        #[arg(default_value = "veyra-learn")]
+        dir: PathBuf,
+    },
+    /// Print the grammar the parser accepts, as EBNF, or syntax
+    /// highlighting definitions for editors
+    Grammar {
+        #[arg(long, value_enum, default_value_t = GrammarFormat::Ebnf)]
+        format: GrammarFormat,
+    },
+    /// Explain an error code, such as E0002, with an example; without a
+    /// code, list them all
+    Explain {
+        /// The error code, or the diagnostic name such as parse-error
+        code: Option<String>,
+    },
+    /// Serve an HTTP API that runs programs in the sandbox, for hosting a
+    /// web playground
+    Serve {
+        /// Port to listen on; 0 picks a free one
+        #[arg(long, value_name = "PORT")]
+        playground: u16,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+        /// Seconds each program may run
+        #[arg(long, default_value_t = 5)]
+        timeout: u64,
+        /// Megabytes of memory each program may use
+        #[arg(long, default_value_t = 512)]
+        memory: u64,
+    },
+    /// Run one playground program read from stdin; `veyc serve` starts
+    /// this for each program it is sent
+    #[command(hide = true)]
+    PlaygroundWorker {
+        #[arg(long)]
+        max_steps: Option<u64>,
+        #[arg(long)]
+        max_call_depth: Option<usize>,
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        #[arg(long)]
+        max_output: Option<usize>,
+        #[arg(long)]
+        max_memory: u64,
     },
 }
 
+impl Commands {
+    fn input(&self) -> Option<&Path> {
+        match self {
+            Commands::Compile { input, .. }
+            | Commands::Run { input, .. }
+            | Commands::Lex { input }
+            | Commands::Parse { input, .. } => Some(input),
+            Commands::Check { input, .. } => input.as_deref(),
+            Commands::Stats { .. }
+            | Commands::Learn { .. }
+            | Commands::Grammar { .. }
+            | Commands::Explain { .. }
+            | Commands::Serve { .. }
+            | Commands::PlaygroundWorker { .. } => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Commands::Compile { .. } => "compile",
+            Commands::Run { .. } => "run",
+            Commands::Check { .. } => "check",
+            Commands::Lex { .. } => "lex",
+            Commands::Parse { .. } => "parse",
+            Commands::Stats { .. } => "stats",
+            Commands::Learn { .. } => "learn",
+            Commands::Grammar { .. } => "grammar",
+            Commands::Explain { .. } => "explain",
+            Commands::Serve { .. } => "serve",
+            Commands::PlaygroundWorker { .. } => "playground-worker",
+        }
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
+    let input = match &cli.command {
+        Some(command) => command.input(),
+        None => cli.input.as_deref(),
+    }
+    .map(Path::to_path_buf);
+
+    // A panic is a bug in veyc: report it, with a minimized input if the
+    // parser is what crashed. The playground runs programs sent by anyone,
+    // so its crashes are not written to disk.
+    let playground = matches!(
+        cli.command,
+        Some(Commands::Serve { .. } | Commands::PlaygroundWorker { .. })
+    );
+    if !playground {
+        crash::install(input.clone());
+        reset_sigpipe();
+    }
+    if std::panic::catch_unwind(|| run(cli)).is_err() {
+        if let Some(input) = &input {
+            crash::minimize_parser_crash(input);
+        }
+        std::process::exit(101);
+    }
+}
+
+/// Let a closed pipe end veyc quietly, as it does other Unix tools, rather
+/// than make the next print panic and report a crash: `veyc lex main.vey |
+/// head`. The playground keeps ignoring it, as it writes to its workers.
+#[cfg(unix)]
+fn reset_sigpipe() {
+    // SAFETY: no other thread has started yet
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+}
+
+#[cfg(not(unix))]
+fn reset_sigpipe() {}
+
+fn run(cli: Cli) {
+    let started = Instant::now();
+    let format = match cli.log_json {
+        true => MessageFormat::Events,
+        false => cli.message_format,
+    };
+    let command = match &cli.command {
+        Some(command) => command.name(),
+        None if cli.output.is_some() => "compile",
+        None => "run",
+    };
+    let opt_level = cli.opt_level;
+    let features = FeatureArgs {
+        features: cli.features,
+        default_features: !cli.no_default_features,
+    };
+
+    // Remember the file being processed so diagnostics can point at it
+    let mut current_file = None;
+    // Errors a command reported itself, which fail it without an error of
+    // its own
+    let mut reported = 0;
+    // What the program run by `veyc run` exited with
+    let mut exit_code = 0;
 
     let result = match cli.command {
-        Some(Commands::Compile { input, output }) => compile_file(&input, output.as_ref()),
-        Some(Commands::Run { input }) => run_file(&input),
-        Some(Commands::Check { input }) => check_file(&input),
+        Some(Commands::Compile { input, output }) => {
+            current_file = Some(input.clone());
+            compile_file(&input, output.as_ref(), &features, opt_level, format)
+        }
+        Some(Commands::Run {
+            input,
+            hot_reload,
+            vm,
+            no_cache,
+            deterministic,
+            args,
+        }) => {
+            current_file = Some(input.clone());
+            let options = RunOptions {
+                hot_reload,
+                vm,
+                no_cache,
+                deterministic,
+                args,
+            };
+            run_file(&input, &features, opt_level, format, options).map(|code| exit_code = code)
+        }
+        Some(Commands::Check {
+            input,
+            project: true,
+        }) => check_project(input.as_deref(), format).map(|errors| reported = errors),
+        Some(Commands::Check {
+            input: Some(input),
+            project: false,
+        }) => {
+            current_file = Some(input.clone());
+            check_file(&input, format).map(|errors| reported = errors)
+        }
+        Some(Commands::Check { input: None, .. }) => {
+            eprintln!("No input file specified. Use --project to check the whole project.");
+            std::process::exit(1);
+        }
         Some(Commands::Lex { input }) => lex_file(&input),
-        Some(Commands::Parse { input }) => parse_file(&input),
+        Some(Commands::Parse { input, format }) => parse_file(&input, opt_level, format),
+        Some(Commands::Stats { path, top }) => print_stats(&path, top, format),
+        Some(Commands::Learn { dir }) => run_lessons(&dir),
+        Some(Commands::Explain { code: None }) => {
+            for error in explain::ERROR_CODES {
+                println!("{}  {:<24} {}", error.code, error.name, error.title);
+            }
+            Ok(())
+        }
+        Some(Commands::Explain { code: Some(code) }) => match explain::lookup(&code) {
+            Some(error) => {
+                print!("{}", error.explanation());
+                Ok(())
+            }
+            None => {
+                eprintln!(
+                    "error: Unknown error code '{}'; `veyc explain` lists them all",
+                    code
+                );
+                reported += 1;
+                Ok(())
+            }
+        },
+        Some(Commands::Serve {
+            playground,
+            host,
+            timeout,
+            memory,
+        }) => serve_playground(&host, playground, timeout, memory),
+        Some(Commands::PlaygroundWorker {
+            max_steps,
+            max_call_depth,
+            timeout_ms,
+            max_output,
+            max_memory,
+        }) => {
+            let limits = interpreter::Limits {
+                max_steps,
+                max_call_depth,
+                timeout: timeout_ms.map(std::time::Duration::from_millis),
+                max_output,
+            };
+            playground::work(limits, max_memory)
+        }
+        Some(Commands::Grammar { format }) => {
+            match format {
+                GrammarFormat::Ebnf => print!("{}", grammar::ebnf()),
+                GrammarFormat::Tmlanguage => print!("{}", highlight::tmlanguage()),
+                GrammarFormat::Vim => print!("{}", highlight::vim()),
+            }
+            Ok(())
+        }
         None => {
             if let Some(input) = cli.input {
+                current_file = Some(input.clone());
                 if cli.output.is_some() {
-                    compile_file(&input, cli.output.as_ref())
+                    compile_file(&input, cli.output.as_ref(), &features, opt_level, format)
                 } else {
-                    run_file(&input)
+                    run_file(&input, &features, opt_level, format, RunOptions::default())
+                        .map(|code| exit_code = code)
                 }
             } else {
                 eprintln!("No input file specified. Use --help for usage information.");
@@ -84,73 +483,541 @@ fn main() {
         }
     };
 
-    if let Err(e) = result {
-        // Print error using Display format, not Debug
-        eprintln!("Error: {}", e);
+    if let Err(e) = &result {
+        let file = current_file.clone().unwrap_or_default();
+        format.report(&Diagnostic::from_error(e, &file));
+        if format == MessageFormat::Human {
+            eprintln!(
+                "\nFor more information about this error, try `veyc explain {}`.",
+                e.code()
+            );
+        }
+    }
+
+    if format == MessageFormat::Events {
+        let (severity, message) = match &result {
+            Err(_) => (Severity::Error, format!("{} failed", command)),
+            Ok(()) if reported > 0 => (
+                Severity::Error,
+                format!("{} found {} errors", command, reported),
+            ),
+            Ok(()) if exit_code != 0 => (
+                Severity::Error,
+                format!("{} exited with code {}", command, exit_code),
+            ),
+            Ok(()) => (Severity::Note, format!("{} finished", command)),
+        };
+        let mut event = Event::new("veyc", severity, message).took(started.elapsed());
+        if let Some(file) = &current_file {
+            event = event.in_file(file);
+        }
+        event.emit();
+    }
+
+    if result.is_err() || reported > 0 {
         std::process::exit(1);
     }
+    std::process::exit(exit_code);
 }
 
-fn compile_file(input: &PathBuf, output: Option<&PathBuf>) -> Result<(), VeyraError> {
-    println!("Compiling: {}", input.display());
-
-    // Read source file
-    let source = std::fs::read_to_string(input).map_err(|e| {
-        VeyraError::IoError(format!("Failed to read file '{}': {}", input.display(), e))
-    })?;
+fn compile_file(
+    input: &Path,
+    output: Option<&PathBuf>,
+    features: &FeatureArgs,
+    opt_level: u8,
+    format: MessageFormat,
+) -> Result<(), VeyraError> {
+    if format == MessageFormat::Human {
+        println!("Compiling: {}", input.display());
+    }
 
-    // Tokenize
-    let tokens = lexer::tokenize(&source)?;
+    // Read and tokenize the source file, expanding include!
+    let tokens = include::tokenize_file(input)?;
 
-    // Parse
+    // Parse, drop items disabled by @cfg, then optimize
     let ast = parser::parse(tokens)?;
+    let ast = cfg::configure(ast, &cfg_options(input, features)?);
+    let ast = optimize::optimize(ast, opt_level);
 
-    // For now, just print that we would compile
-    let output_name = output
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| input.with_extension("exe").to_string_lossy().to_string());
+    let mut module = bytecode::compile(&ast)?;
+    let source = std::path::absolute(input).unwrap_or_else(|_| input.to_path_buf());
+    module.source = Some(source.display().to_string());
+    let output = output
+        .cloned()
+        .unwrap_or_else(|| input.with_extension(bytecode::EXTENSION));
+    std::fs::write(&output, module.to_bytes()).map_err(|e| {
+        VeyraError::IoError(format!(
+            "Failed to write file '{}': {}",
+            output.display(),
+            e
+        ))
+    })?;
 
-    println!("Would compile to: {}", output_name);
-    println!("AST: {:#?}", ast);
+    if format == MessageFormat::Human {
+        println!("Compiled to: {}", output.display());
+    }
 
     Ok(())
 }
 
-fn run_file(input: &PathBuf) -> Result<(), VeyraError> {
-    println!("Running: {}", input.display());
+fn run_file(
+    input: &Path,
+    features: &FeatureArgs,
+    opt_level: u8,
+    format: MessageFormat,
+    run: RunOptions,
+) -> Result<i32, VeyraError> {
+    if format == MessageFormat::Human {
+        println!("Running: {}", input.display());
+    }
 
-    // Read source file
-    let source = std::fs::read_to_string(input).map_err(|e| {
-        VeyraError::IoError(format!("Failed to read file '{}': {}", input.display(), e))
-    })?;
+    let options = cfg_options(input, features)?;
+    // Inside a project, modules compiled by an earlier run are reused
+    let cache = (!run.no_cache)
+        .then(|| module_cache::ModuleCache::for_path(input))
+        .flatten();
+    let load = move |path: &Path| {
+        let compile = || {
+            // Read and tokenize the source file, expanding include!
+            let tokens = include::tokenize_file(path)?;
 
-    // Tokenize
-    let tokens = lexer::tokenize(&source)?;
+            // Parse, drop items disabled by @cfg, then optimize
+            let ast = parser::parse(tokens)?;
+            let ast = cfg::configure(ast, &options);
+            Ok(optimize::optimize(ast, opt_level))
+        };
+        let Some(cache) = &cache else {
+            return compile();
+        };
+        let source = std::fs::read_to_string(path).map_err(|e| {
+            VeyraError::IoError(format!("Failed to read file '{}': {}", path.display(), e))
+        })?;
+        let key = format!("{:?} -O{}", options, opt_level);
+        cache.load_or_compile(&source, &key, compile)
+    };
+    let load = Rc::new(load);
 
-    // Parse
-    let ast = parser::parse(tokens)?;
+    // Imported modules are compiled the same way as the entry file
+    let modules = {
+        let load = load.clone();
+        modules::ModuleLoader::new(input, Box::new(move |path: &Path| load(path)))
+    };
+
+    if input.extension().is_some_and(|e| e == bytecode::EXTENSION) {
+        let bytes = std::fs::read(input).map_err(|e| {
+            VeyraError::IoError(format!("Failed to read file '{}': {}", input.display(), e))
+        })?;
+        let module = bytecode::Module::from_bytes(&bytes)?;
+        // Errors are reported at the lines of the source, not the bytecode
+        let source = module.source.clone();
+        return vm::interpret(module, modules, run.args, run.deterministic).map_err(
+            |error| match (source, error) {
+                (Some(file), error @ VeyraError::AtLine { .. }) => VeyraError::InModule {
+                    file: file.into(),
+                    error: Box::new(error),
+                },
+                (_, error) => error,
+            },
+        );
+    }
+    let ast = load(input)?;
+    if run.vm {
+        return vm::interpret(
+            bytecode::compile(&ast)?,
+            modules,
+            run.args,
+            run.deterministic,
+        );
+    }
 
     // Interpret
-    interpreter::interpret(&ast)?;
+    if run.hot_reload {
+        let reload = hot_reload::HotReload::new(input, Box::new(move |path: &Path| load(path)));
+        interpreter::interpret_with_hot_reload(&ast, reload, modules, run.args, run.deterministic)
+    } else {
+        interpreter::interpret(&ast, modules, run.args, run.deterministic)
+    }
+}
 
-    Ok(())
+/// Check one file, returning how many errors were reported
+fn check_file(input: &Path, format: MessageFormat) -> Result<usize, VeyraError> {
+    if format == MessageFormat::Human {
+        println!("Checking: {}", input.display());
+    }
+
+    // Read and tokenize the source file, expanding include!
+    let tokens = include::tokenize_file(input)?;
+
+    // Parse, reporting every syntax error rather than just the first
+    let (ast, errors) = parser::parse_recovering(tokens);
+    if !errors.is_empty() {
+        for error in &errors {
+            format.report(&Diagnostic::from_error(error, input));
+        }
+        if format == MessageFormat::Human {
+            eprintln!("✗ {} syntax errors", errors.len());
+        }
+        return Ok(errors.len());
+    }
+
+    // Then look for names, calls and values that cannot work
+    let diagnostics = type_errors(&ast, input, input)?;
+    if diagnostics.is_empty() {
+        if format == MessageFormat::Human {
+            println!("✓ No errors found");
+        }
+        return Ok(0);
+    }
+
+    for diagnostic in &diagnostics {
+        format.report(diagnostic);
+    }
+    if format == MessageFormat::Human {
+        eprintln!("✗ {} errors", diagnostics.len());
+    }
+    Ok(diagnostics.len())
 }
 
-fn check_file(input: &PathBuf) -> Result<(), VeyraError> {
-    println!("Checking: {}", input.display());
+/// Type errors in the parsed program `ast` of `file`, reported as `display`
+fn type_errors(
+    ast: &ast::Program,
+    file: &Path,
+    display: &Path,
+) -> Result<Vec<Diagnostic>, VeyraError> {
+    let source = std::fs::read_to_string(file).map_err(|e| {
+        VeyraError::IoError(format!("Failed to read file '{}': {}", file.display(), e))
+    })?;
+    let (tokens, _) = lexer::Lexer::new(&source).tokenize_with_ranges();
+    Ok(typeck::check(ast, &tokens)
+        .into_iter()
+        .map(|error| {
+            Diagnostic::error(error.code, error.message).with_span(
+                display,
+                error.line,
+                error.column,
+            )
+        })
+        .collect())
+}
 
-    // Read source file
-    let source = std::fs::read_to_string(input).map_err(|e| {
-        VeyraError::IoError(format!("Failed to read file '{}': {}", input.display(), e))
+/// Check the project's files, returning how many errors were reported
+fn check_project(entry: Option<&Path>, format: MessageFormat) -> Result<usize, VeyraError> {
+    let current_dir = std::env::current_dir()
+        .map_err(|e| VeyraError::IoError(format!("Failed to get current directory: {}", e)))?;
+
+    // The project is the one containing the entry file, if one is given
+    let start = match entry {
+        Some(entry) => entry
+            .canonicalize()
+            .unwrap_or_else(|_| current_dir.join(entry)),
+        None => current_dir.clone(),
+    };
+    let root = modules::find_project_root(&start).unwrap_or(current_dir);
+    let src_dir = root.join("src");
+
+    if format == MessageFormat::Human {
+        println!("Checking project: {}", root.display());
+    }
+
+    let mut queue: Vec<PathBuf> = match entry {
+        Some(entry) => vec![entry.to_path_buf()],
+        None if src_dir.join("main.vey").is_file() => vec![src_dir.join("main.vey")],
+        None => {
+            let mut files = Vec::new();
+            collect_source_files(&src_dir, &mut files)?;
+            files.sort();
+            files.reverse();
+            files
+        }
+    };
+
+    // Imports resolve as they do when the program runs: next to the
+    // importing file, then next to the entry file, then among the
+    // project's installed packages
+    let entry_dir = match entry {
+        Some(_) => start.parent().unwrap_or(&root),
+        None => &src_dir,
+    };
+    let modules = modules::ModuleLoader::in_dir(entry_dir);
+
+    let mut visited = HashSet::new();
+    let mut diagnostics = Vec::new();
+
+    while let Some(file) = queue.pop() {
+        let key = file.canonicalize().unwrap_or_else(|_| file.clone());
+        if !visited.insert(key) {
+            continue;
+        }
+
+        let display = file.strip_prefix(&root).unwrap_or(&file).to_path_buf();
+
+        let ast = match include::tokenize_file(&file) {
+            Ok(tokens) => {
+                let (ast, errors) = parser::parse_recovering(tokens);
+                if errors.is_empty() {
+                    diagnostics.extend(type_errors(&ast, &file, &display)?);
+                }
+                diagnostics.extend(errors.iter().map(|e| Diagnostic::from_error(e, &display)));
+                ast
+            }
+            Err(e) => {
+                diagnostics.push(Diagnostic::from_error(&e, &display));
+                continue;
+            }
+        };
+
+        // Follow imports of project modules; std modules are built in
+        for item in &ast.items {
+            if let ast::Item::Import(import) = item {
+                if import.path.first().map(String::as_str) == Some("std") {
+                    continue;
+                }
+
+                match modules.resolve(&import.path, Some(&file)) {
+                    Ok(path) => queue.push(path),
+                    Err(_) => diagnostics.push(
+                        Diagnostic::error(
+                            "unresolved-import",
+                            format!("Unresolved import '{}'", import.path.join(".")),
+                        )
+                        .with_span(&display, None, None),
+                    ),
+                }
+            }
+        }
+    }
+
+    for diagnostic in &diagnostics {
+        format.report(diagnostic);
+    }
+
+    if format == MessageFormat::Human {
+        if diagnostics.is_empty() {
+            println!("✓ {} files checked, no errors", visited.len());
+        } else {
+            eprintln!(
+                "✗ {} errors in {} files checked",
+                diagnostics.len(),
+                visited.len()
+            );
+        }
+    }
+    Ok(diagnostics.len())
+}
+
+/// Configuration for `@cfg` items in `input`. Features are checked against
+/// the veyra.toml of the project containing the file, if there is one.
+fn cfg_options(input: &Path, args: &FeatureArgs) -> Result<cfg::CfgOptions, VeyraError> {
+    let input = std::path::absolute(input).map_err(|e| {
+        VeyraError::IoError(format!("Failed to resolve '{}': {}", input.display(), e))
     })?;
 
-    // Tokenize
-    let tokens = lexer::tokenize(&source)?;
+    let features = match input.parent().and_then(modules::find_project_root) {
+        Some(root) => cfg::resolve_features(
+            &cfg::declared_features(&root)?,
+            &args.features,
+            args.default_features,
+        )?,
+        None => args.features.iter().cloned().collect(),
+    };
+
+    Ok(cfg::CfgOptions::host().with_features(features))
+}
 
-    // Parse
-    let _ast = parser::parse(tokens)?;
+fn collect_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), VeyraError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| {
+        VeyraError::IoError(format!(
+            "Failed to read directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_source_files(&path, files)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("vey") {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Serve the playground API until the process is stopped, running each
+/// program in a child process with `memory` megabytes
+fn serve_playground(host: &str, port: u16, timeout: u64, memory: u64) -> Result<(), VeyraError> {
+    let listener = std::net::TcpListener::bind((host, port)).map_err(|e| {
+        VeyraError::IoError(format!("Failed to listen on {}:{}: {}", host, port, e))
+    })?;
+    let address = listener
+        .local_addr()
+        .map_err(|e| VeyraError::IoError(e.to_string()))?;
+    println!("Playground listening on http://{}", address);
+    let limits = interpreter::Limits {
+        timeout: Some(std::time::Duration::from_secs(timeout)),
+        ..playground::LIMITS
+    };
+    let program = std::env::current_exe()
+        .map_err(|e| VeyraError::IoError(format!("Failed to find veyc: {}", e)))?;
+    let isolation = playground::Isolation::Process {
+        program,
+        max_memory: memory * 1024 * 1024,
+    };
+    playground::serve(listener, limits, isolation)
+}
+
+/// Take the learner through the lessons, starting with the first exercise
+/// that does not pass yet and checking it each time they press Enter
+fn run_lessons(dir: &Path) -> Result<(), VeyraError> {
+    let io_error = |e: std::io::Error| VeyraError::IoError(format!("{}: {}", dir.display(), e));
+    std::fs::create_dir_all(dir).map_err(io_error)?;
+    for lesson in learn::LESSONS {
+        let path = dir.join(format!("{}.vey", lesson.name));
+        if !path.exists() {
+            std::fs::write(&path, lesson.starter).map_err(io_error)?;
+        }
+    }
+
+    let indent = |text: &str| {
+        text.lines()
+            .map(|line| format!("    {}\n", line))
+            .collect::<String>()
+    };
+    let total = learn::LESSONS.len();
+    for (number, lesson) in learn::LESSONS.iter().enumerate() {
+        let path = dir.join(format!("{}.vey", lesson.name));
+        let mut attempts = 0;
+        loop {
+            let source = std::fs::read_to_string(&path).map_err(io_error)?;
+            match lesson.check(&source) {
+                learn::Outcome::Passed if attempts > 0 => {
+                    println!("Passed!");
+                    break;
+                }
+                // Done in an earlier session
+                learn::Outcome::Passed => break,
+                _ if attempts == 0 => {
+                    println!("\n[{}/{}] {}\n", number + 1, total, lesson.title);
+                    println!("{}\n", lesson.explanation);
+                    print!(
+                        "Edit {} so that it prints:\n{}",
+                        path.display(),
+                        indent(lesson.expected_output)
+                    );
+                }
+                learn::Outcome::Failed(e) => println!("Not yet: {}", e),
+                learn::Outcome::WrongOutput { actual, .. } => {
+                    print!("Not yet. It printed:\n{}", indent(&actual))
+                }
+            }
+            attempts += 1;
+
+            loop {
+                print!("\nPress Enter to check it, h for a hint or q to quit: ");
+                std::io::Write::flush(&mut std::io::stdout()).map_err(io_error)?;
+                let mut answer = String::new();
+                if std::io::stdin().read_line(&mut answer).map_err(io_error)? == 0 {
+                    return Ok(());
+                }
+                match answer.trim() {
+                    "q" => return Ok(()),
+                    "h" => println!("Hint: {}", lesson.hint),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    println!(
+        "\nAll {} lessons are done. veyra-repl is a good place to try more.",
+        total
+    );
+    Ok(())
+}
+
+fn print_stats(path: &Path, top: usize, format: MessageFormat) -> Result<(), VeyraError> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        collect_source_files(path, &mut files)?;
+        files.sort();
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    let mut stats = stats::Stats::default();
+    let mut skipped = 0;
+    for file in files {
+        let source = std::fs::read_to_string(&file).map_err(|e| {
+            VeyraError::IoError(format!("Failed to read file '{}': {}", file.display(), e))
+        })?;
+        // Files under a directory are shown relative to it
+        let display = match file.strip_prefix(path) {
+            Ok(relative) if file != path => relative.to_path_buf(),
+            _ => file.clone(),
+        };
+        if let Err(e) = stats.add_file(display, &source) {
+            eprintln!("warning: skipping {}: {}", file.display(), e);
+            skipped += 1;
+        }
+    }
+
+    if format == MessageFormat::Json {
+        println!("{}", serde_json::to_string(&stats).unwrap_or_default());
+        return Ok(());
+    }
+
+    let percent = |part: usize, whole: usize| {
+        if whole == 0 {
+            0.0
+        } else {
+            part as f64 * 100.0 / whole as f64
+        }
+    };
+    println!("Files:      {}", stats.files);
+    if skipped > 0 {
+        println!("Skipped:    {} (syntax errors)", skipped);
+    }
+    println!(
+        "Lines:      {} ({} code, {} comments, {} blank)",
+        stats.lines, stats.code_lines, stats.comment_lines, stats.blank_lines
+    );
+    println!(
+        "Comments:   {:.1}% of non-blank lines",
+        stats.comment_ratio() * 100.0
+    );
+    println!("Functions:  {}", stats.functions.len());
+    println!("Structs:    {}", stats.structs);
+    println!("Actors:     {}", stats.actors);
+
+    if stats.functions.is_empty() {
+        return Ok(());
+    }
+    println!();
+    println!("Cyclomatic complexity:");
+    for (range, count) in stats.complexity_distribution() {
+        println!(
+            "  {:>6}  {:>5}  ({:.1}%)",
+            range,
+            count,
+            percent(count, stats.functions.len())
+        );
+    }
+
+    println!();
+    println!("Longest functions:");
+    for function in stats.longest_functions(top) {
+        println!(
+            "  {:>5} lines  {}  ({}:{}, complexity {})",
+            function.lines,
+            function.name,
+            function.file.display(),
+            function.line,
+            function.complexity
+        );
+    }
 
-    println!("✓ Syntax is valid");
     Ok(())
 }
 
@@ -173,22 +1040,27 @@ fn lex_file(input: &PathBuf) -> Result<(), VeyraError> {
     Ok(())
 }
 
-fn parse_file(input: &PathBuf) -> Result<(), VeyraError> {
-    println!("Parsing: {}", input.display());
-
-    // Read source file
-    let source = std::fs::read_to_string(input).map_err(|e| {
-        VeyraError::IoError(format!("Failed to read file '{}': {}", input.display(), e))
-    })?;
+fn parse_file(input: &Path, opt_level: u8, format: AstFormat) -> Result<(), VeyraError> {
+    if format == AstFormat::Debug {
+        println!("Parsing: {}", input.display());
+    }
 
-    // Tokenize
-    let tokens = lexer::tokenize(&source)?;
+    // Read and tokenize the source file, expanding include!
+    let tokens = include::tokenize_file(input)?;
 
-    // Parse
+    // Parse, showing the optimized AST when -O is given
     let ast = parser::parse(tokens)?;
+    let ast = optimize::optimize(ast, opt_level);
 
     // Print AST
-    println!("{:#?}", ast);
+    match format {
+        AstFormat::Debug => println!("{:#?}", ast),
+        AstFormat::Json => {
+            let json = serde_json::to_string_pretty(&ast)
+                .map_err(|e| VeyraError::InternalError(format!("Cannot serialize AST: {}", e)))?;
+            println!("{}", json);
+        }
+    }
 
     Ok(())
 }
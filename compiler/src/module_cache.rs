@@ -0,0 +1,109 @@
+// Cache of compiled modules
+//
+// Compiling a module (parsing it, dropping items disabled by @cfg and
+// optimizing) is repeated on every run even when its source has not
+// changed. `ModuleCache` keeps compiled modules in the project's target
+// directory, keyed by a hash of the source, the compiler build and the
+// compile options, so an unchanged module is loaded instead of compiled
+// again. The compiled form is the optimized AST, which keeps the line each
+// statement starts on, so bytecode compiled from a cached module has the
+// same line tables as bytecode compiled from the source.
+
+use crate::ast::Program;
+use crate::error::Result;
+use crate::modules;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where compiled modules are kept, relative to the project root
+pub const CACHE_DIR: &str = "target/veyra-cache";
+
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The cache of the project (the nearest directory with a veyra.toml)
+    /// containing a file or directory; none outside a project
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let path = std::path::absolute(path).ok()?;
+        let root = modules::find_project_root(&path)?;
+        Some(Self::new(root.join(CACHE_DIR)))
+    }
+
+    /// The module compiled from `source`, loaded from the cache if it was
+    /// compiled before with the same `options`, otherwise compiled with
+    /// `compile` and stored. Sources using include! are always compiled,
+    /// since the files they include are not part of the key. The cache is
+    /// only an optimization: failing to read or write it is not an error.
+    pub fn load_or_compile(
+        &self,
+        source: &str,
+        options: &str,
+        compile: impl FnOnce() -> Result<Program>,
+    ) -> Result<Program> {
+        if source.contains("include!") || source.contains("include_str!") {
+            return compile();
+        }
+
+        let path = self.dir.join(format!("{}.module", key(source, options)));
+        let cached = fs::read(&path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok());
+        if let Some(program) = cached {
+            return Ok(program);
+        }
+
+        let program = compile()?;
+        self.store(&path, &program);
+        Ok(program)
+    }
+
+    fn store(&self, path: &Path, program: &Program) {
+        let Ok(bytes) = bincode::serialize(program) else {
+            return;
+        };
+        // Write under a temporary name first, so a concurrent run never
+        // reads a partly written module
+        let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+        let stored = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&temporary, bytes))
+            .and_then(|_| fs::rename(&temporary, path));
+        if stored.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+}
+
+/// Hex SHA-256 of everything the compiled module depends on
+fn key(source: &str, options: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [env!("CARGO_PKG_VERSION"), &build_id(), options, source] {
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Identifies the running compiler build, so modules compiled by an older
+/// build of the same version are not reused: the executable's modification
+/// time
+fn build_id() -> String {
+    std::env::current_exe()
+        .and_then(fs::metadata)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos().to_string())
+        .unwrap_or_default()
+}
@@ -0,0 +1,142 @@
+// File-based modules for `import`
+//
+// `import geometry.shapes` loads `geometry/shapes.vey`, or
+// `geometry/shapes/main.vey`, looked up first next to the importing file,
+// then next to the entry script, then in the project's `veyra-modules/`
+// where veyra-pkg installs packages, so `import mypkg` loads an installed
+// package's main.vey. Imports starting with `std` name the built-in modules
+// and are not looked up on disk.
+
+use crate::ast::Program;
+use crate::error::{Result, VeyraError};
+use crate::hot_reload::Loader;
+use crate::{include, parser};
+use std::path::{Path, PathBuf};
+
+/// Where installed packages live, relative to the project root
+pub const PACKAGES_DIR: &str = "veyra-modules";
+
+/// Name of the project manifest, which marks the project root
+pub const PROJECT_FILE: &str = "veyra.toml";
+
+/// Finds and loads the files a program imports
+pub struct ModuleLoader {
+    /// Directories searched after the importing file's own
+    search: Vec<PathBuf>,
+    load: Loader,
+}
+
+impl ModuleLoader {
+    /// Modules of the program in `entry`, compiled by `load`
+    pub fn new(entry: &Path, load: Loader) -> Self {
+        let dir = entry
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        Self {
+            search: search_dirs(dir),
+            load,
+        }
+    }
+
+    /// Modules relative to `dir`, for code that has no file of its own such
+    /// as a REPL session, parsed without @cfg or optimization
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            search: search_dirs(dir),
+            load: Box::new(|path: &Path| parser::parse(include::tokenize_file(path)?)),
+        }
+    }
+
+    /// The file `import <path>` in `importer` refers to. `importer` is None
+    /// for code that has no file.
+    pub fn resolve(&self, path: &[String], importer: Option<&Path>) -> Result<PathBuf> {
+        if self.search.is_empty() {
+            return Err(VeyraError::runtime_error(format!(
+                "Cannot import '{}': importing modules is disabled",
+                path.join(".")
+            )));
+        }
+        let importer_dir = importer.and_then(Path::parent);
+        let found = importer_dir
+            .into_iter()
+            .chain(self.search.iter().map(PathBuf::as_path))
+            .find_map(|dir| find_module(dir, path));
+        match found {
+            Some(file) => Ok(file.canonicalize().unwrap_or(file)),
+            None => Err(VeyraError::runtime_error(format!(
+                "Module '{}' not found; looked for {} in {}",
+                path.join("."),
+                module_file(path).display(),
+                importer_dir
+                    .into_iter()
+                    .chain(self.search.iter().map(PathBuf::as_path))
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// A loader for code that may not import files, such as programs run
+    /// by the playground: every import fails without looking at the disk
+    pub fn disabled() -> Self {
+        Self {
+            search: Vec::new(),
+            load: Box::new(|path: &Path| {
+                Err(VeyraError::runtime_error(format!(
+                    "Cannot import '{}': importing modules is disabled",
+                    path.display()
+                )))
+            }),
+        }
+    }
+
+    /// Parse `file`, reporting syntax errors at the file rather than at the
+    /// importer
+    pub fn load(&self, file: &Path) -> Result<Program> {
+        (self.load)(file).map_err(|error| match error {
+            VeyraError::LexError { .. } | VeyraError::ParseError { .. } => VeyraError::InModule {
+                file: file.to_path_buf(),
+                error: Box::new(error),
+            },
+            error => error,
+        })
+    }
+}
+
+/// `dir`, then the `veyra-modules/` of the project containing it, or of
+/// `dir` itself outside a project
+fn search_dirs(dir: &Path) -> Vec<PathBuf> {
+    let absolute = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let root = find_project_root(&absolute).unwrap_or(absolute);
+    vec![dir.to_path_buf(), root.join(PACKAGES_DIR)]
+}
+
+/// The nearest directory at or above `start`, or above the file `start`,
+/// that contains veyra.toml
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let start = if start.is_file() {
+        start.parent()?
+    } else {
+        start
+    };
+    start
+        .ancestors()
+        .find(|dir| dir.join(PROJECT_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The module `path` under `dir`: `a/b.vey`, or `a/b/main.vey`
+pub fn find_module(dir: &Path, path: &[String]) -> Option<PathBuf> {
+    let file = dir.join(module_file(path));
+    if file.is_file() {
+        return Some(file);
+    }
+    let main = dir.join(path.iter().collect::<PathBuf>()).join("main.vey");
+    main.is_file().then_some(main)
+}
+
+fn module_file(path: &[String]) -> PathBuf {
+    path.iter().collect::<PathBuf>().with_extension("vey")
+}
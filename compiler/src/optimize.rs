@@ -0,0 +1,766 @@
+// Optimization passes run on the AST before it is interpreted
+//
+// `-O1` folds constant arithmetic, comparisons and string concatenation,
+// propagates immutable `let` bindings initialized to constants, and removes
+// dead branches (`if`/`elif` arms and `while` loops with constant conditions,
+// statements after `return`, `break` or `continue`). `-O2` also inlines calls
+// to tiny functions whose body is a single `return <expr>` when the call folds
+// to a constant.
+//
+// Every rewrite produces exactly what the interpreter would compute. An
+// operation that would fail at runtime (overflow, division by zero, mismatched
+// types) is left in place so the error is still reported when it runs.
+
+use crate::ast::*;
+use crate::builtins;
+use std::collections::{HashMap, HashSet};
+
+/// Highest supported optimization level
+pub const MAX_LEVEL: u8 = 2;
+
+/// Optimize `program` at `level` (0 leaves it unchanged)
+pub fn optimize(program: Program, level: u8) -> Program {
+    if level == 0 {
+        return program;
+    }
+
+    let inline = if level >= 2 {
+        inline_candidates(&program)
+    } else {
+        HashMap::new()
+    };
+
+    // Top-level statements run in order in the global scope, as one unit
+    let mut globals = Bindings::default();
+    for item in &program.items {
+        let functions: Vec<&Function> = match item {
+            Item::Statement(statement) => {
+                globals.visit_statement(statement);
+                continue;
+            }
+            Item::Function(func) => vec![func],
+            Item::Impl(imp) => imp.methods.iter().collect(),
+            Item::Trait(tr) => tr.methods.iter().map(|m| &m.function).collect(),
+            Item::Actor(actor) => actor.methods.iter().collect(),
+            Item::Struct(_) | Item::Import(_) => continue,
+        };
+        // Functions can assign to globals, which are then not constant
+        for func in functions {
+            let mut locals = Bindings::default();
+            locals.visit_block(&func.body);
+            globals.assigned.extend(locals.assigned);
+        }
+    }
+    let mut top_level = Folder::new(&inline, globals);
+
+    let mut items = Vec::with_capacity(program.items.len());
+    let mut lines = Lines::default();
+    for (index, item) in program.items.into_iter().enumerate() {
+        let line = program.lines.get(index);
+        if !matches!(item, Item::Statement(_)) {
+            lines.set(items.len(), line);
+        }
+        match item {
+            Item::Statement(statement) => {
+                let block = top_level.statement(statement, line);
+                for (index, statement) in block.statements.into_iter().enumerate() {
+                    lines.set(items.len(), block.lines.get(index));
+                    items.push(Item::Statement(statement));
+                }
+            }
+            Item::Function(func) => items.push(Item::Function(optimize_function(func, &inline))),
+            Item::Impl(mut imp) => {
+                imp.methods = imp
+                    .methods
+                    .into_iter()
+                    .map(|method| optimize_function(method, &inline))
+                    .collect();
+                items.push(Item::Impl(imp));
+            }
+            Item::Trait(mut tr) => {
+                tr.methods = tr
+                    .methods
+                    .into_iter()
+                    .map(|mut method| {
+                        method.function = optimize_function(method.function, &inline);
+                        method
+                    })
+                    .collect();
+                items.push(Item::Trait(tr));
+            }
+            Item::Actor(mut actor) => {
+                actor.methods = actor
+                    .methods
+                    .into_iter()
+                    .map(|method| optimize_function(method, &inline))
+                    .collect();
+                items.push(Item::Actor(actor));
+            }
+            item => items.push(item),
+        }
+    }
+
+    Program { items, lines }
+}
+
+fn optimize_function(mut func: Function, inline: &HashMap<String, Inlinable>) -> Function {
+    let mut bindings = Bindings::default();
+    bindings.visit_block(&func.body);
+    let mut folder = Folder::new(inline, bindings);
+    func.body = folder.block(func.body);
+    func
+}
+
+/// A function small enough to inline: its parameters and returned expression
+struct Inlinable {
+    parameters: Vec<String>,
+    body: Expression,
+}
+
+/// Functions defined once whose body is `return <expr>`, where the expression
+/// only uses the parameters, literals and operators
+fn inline_candidates(program: &Program) -> HashMap<String, Inlinable> {
+    let mut definitions: HashMap<&str, usize> = HashMap::new();
+    for item in &program.items {
+        if let Item::Function(func) = item {
+            *definitions.entry(func.name.as_str()).or_default() += 1;
+        }
+    }
+
+    program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Function(func) => Some(func),
+            _ => None,
+        })
+        .filter(|func| {
+            definitions[func.name.as_str()] == 1
+                && !func.is_async
+                && func.attributes.is_empty()
+                && func.parameters.iter().all(|p| p.default.is_none())
+                // Builtins are resolved before user functions
+                && builtins::lookup(&func.name).is_none()
+        })
+        .filter_map(|func| {
+            let [Statement::Return(ReturnStatement { value: Some(body) })] =
+                func.body.statements.as_slice()
+            else {
+                return None;
+            };
+            let parameters: Vec<String> = func.parameters.iter().map(|p| p.name.clone()).collect();
+            is_pure(body, &parameters).then(|| {
+                (
+                    func.name.clone(),
+                    Inlinable {
+                        parameters,
+                        body: body.clone(),
+                    },
+                )
+            })
+        })
+        .collect()
+}
+
+fn is_pure(expr: &Expression, parameters: &[String]) -> bool {
+    match expr {
+        Expression::Literal(_) => true,
+        Expression::Identifier(name) => parameters.contains(name),
+        Expression::Binary(bin) => {
+            is_pure(&bin.left, parameters) && is_pure(&bin.right, parameters)
+        }
+        Expression::Unary(unary) => {
+            matches!(
+                unary.operator,
+                UnaryOperator::Minus | UnaryOperator::Not | UnaryOperator::BitwiseNot
+            ) && is_pure(&unary.operand, parameters)
+        }
+        _ => false,
+    }
+}
+
+/// Which names in a function body (or the top level) are safe to propagate:
+/// those declared exactly once, by `let`, and never assigned
+#[derive(Default)]
+struct Bindings {
+    declarations: HashMap<String, usize>,
+    assigned: HashSet<String>,
+}
+
+impl Bindings {
+    fn is_constant(&self, name: &str) -> bool {
+        self.declarations.get(name) == Some(&1) && !self.assigned.contains(name)
+    }
+
+    fn declare(&mut self, name: &str) {
+        *self.declarations.entry(name.to_string()).or_default() += 1;
+    }
+}
+
+impl Visitor for Bindings {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VariableDeclaration(var) => {
+                // A destructuring pattern's names are declared by visit_pattern
+                if var.pattern.is_none() {
+                    self.declare(&var.name);
+                }
+                if var.mutable {
+                    // Mutable bindings are never propagated
+                    self.assigned
+                        .extend(var.names().into_iter().map(str::to_string));
+                }
+            }
+            Statement::Assignment(assign) => {
+                if let Some(name) = assigned_name(&assign.target) {
+                    self.assigned.insert(name.to_string());
+                }
+            }
+            Statement::For(stmt) => self.declare(&stmt.variable),
+            Statement::Try(stmt) => {
+                if let Some(variable) = stmt.catch.as_ref().and_then(|c| c.variable.as_ref()) {
+                    self.declare(variable);
+                }
+            }
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        for name in pattern.bindings() {
+            self.declare(name);
+        }
+    }
+
+    // Lambda parameters shadow outer bindings, and block bodies can declare
+    // and assign like any other block
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Lambda(lambda) = expression {
+            for parameter in &lambda.parameters {
+                self.declare(&parameter.name);
+            }
+        }
+        walk_expression(self, expression);
+    }
+}
+
+/// The variable an assignment target writes to, e.g. `a` in `a[0] = 1`
+fn assigned_name(target: &Expression) -> Option<&str> {
+    match target {
+        Expression::Identifier(name) => Some(name),
+        Expression::Index(index) => assigned_name(&index.object),
+        Expression::FieldAccess(field) => assigned_name(&field.object),
+        Expression::Unary(unary) => assigned_name(&unary.operand),
+        _ => None,
+    }
+}
+
+struct Folder<'a> {
+    inline: &'a HashMap<String, Inlinable>,
+    bindings: Bindings,
+    /// Known constant values, one map per nested body. A binding is only
+    /// visible to code after it in the same or an inner body.
+    scopes: Vec<HashMap<String, Literal>>,
+}
+
+impl<'a> Folder<'a> {
+    fn new(inline: &'a HashMap<String, Inlinable>, bindings: Bindings) -> Self {
+        Self {
+            inline,
+            bindings,
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    fn constant(&self, name: &str) -> Option<&Literal> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn block(&mut self, block: Block) -> Block {
+        self.scopes.push(HashMap::new());
+        let block = self.statements(block);
+        self.scopes.pop();
+        block
+    }
+
+    /// Optimize statements in the current scope, dropping any after a jump
+    fn statements(&mut self, block: Block) -> Block {
+        let mut result = Block::default();
+        for (index, statement) in block.statements.into_iter().enumerate() {
+            let jumps = matches!(
+                statement,
+                Statement::Return(_) | Statement::Throw(_) | Statement::Break | Statement::Continue
+            );
+            let optimized = self.statement(statement, block.lines.get(index));
+            for (index, statement) in optimized.statements.into_iter().enumerate() {
+                result.push(statement, optimized.lines.get(index));
+            }
+            if jumps {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Optimize one statement, which starts on `line`. Branches that always
+    /// run are spliced into the enclosing block (their bodies share its
+    /// scope in the interpreter), so this may return zero or several
+    /// statements.
+    fn statement(&mut self, statement: Statement, line: Option<u32>) -> Block {
+        let statement = match statement {
+            Statement::Expression(stmt) => Statement::Expression(ExpressionStatement {
+                expression: self.expression(stmt.expression),
+            }),
+            Statement::VariableDeclaration(mut var) => {
+                var.initializer = self.expression(var.initializer);
+                if let Expression::Literal(literal) = &var.initializer {
+                    if self.bindings.is_constant(&var.name) {
+                        if let Some(scope) = self.scopes.last_mut() {
+                            scope.insert(var.name.clone(), literal.clone());
+                        }
+                    }
+                }
+                Statement::VariableDeclaration(var)
+            }
+            Statement::Assignment(mut assign) => {
+                assign.value = self.expression(assign.value);
+                Statement::Assignment(assign)
+            }
+            Statement::If(stmt) => return self.if_statement(stmt, line),
+            Statement::While(mut stmt) => {
+                stmt.condition = self.expression(stmt.condition);
+                if constant_truth(&stmt.condition) == Some(false) {
+                    return Block::default();
+                }
+                stmt.body = self.block(stmt.body);
+                Statement::While(stmt)
+            }
+            Statement::For(mut stmt) => {
+                stmt.iterable = self.expression(stmt.iterable);
+                stmt.body = self.block(stmt.body);
+                Statement::For(stmt)
+            }
+            Statement::Match(mut stmt) => {
+                stmt.expression = self.expression(stmt.expression);
+                stmt.arms = stmt
+                    .arms
+                    .into_iter()
+                    .map(|arm| {
+                        self.scopes.push(HashMap::new());
+                        let guard = arm.guard.map(|guard| self.expression(guard));
+                        let mut body = self.statement(arm.body, None);
+                        self.scopes.pop();
+                        let body = match body.statements.len() {
+                            1 => body.statements.remove(0),
+                            _ => Statement::Block(body),
+                        };
+                        MatchArm {
+                            pattern: arm.pattern,
+                            guard,
+                            body,
+                        }
+                    })
+                    .collect();
+                Statement::Match(stmt)
+            }
+            Statement::Return(ret) => Statement::Return(ReturnStatement {
+                value: ret.value.map(|value| self.expression(value)),
+            }),
+            Statement::Try(mut stmt) => {
+                stmt.body = self.block(stmt.body);
+                stmt.catch = stmt.catch.map(|mut catch| {
+                    catch.body = self.block(catch.body);
+                    catch
+                });
+                stmt.finally = stmt.finally.map(|block| self.block(block));
+                Statement::Try(stmt)
+            }
+            Statement::Throw(throw) => Statement::Throw(ThrowStatement {
+                value: self.expression(throw.value),
+            }),
+            Statement::Block(block) => Statement::Block(self.block(block)),
+            Statement::TaskGroup(block) => Statement::TaskGroup(self.block(block)),
+            statement @ (Statement::Break | Statement::Continue | Statement::Comment(_)) => {
+                statement
+            }
+        };
+        let mut block = Block::default();
+        block.push(statement, line);
+        block
+    }
+
+    fn if_statement(&mut self, stmt: IfStatement, line: Option<u32>) -> Block {
+        let branches =
+            std::iter::once((stmt.condition, stmt.then_branch)).chain(stmt.elif_branches);
+
+        let mut kept: Vec<(Expression, Block)> = Vec::new();
+        let mut else_branch = stmt.else_branch;
+        for (condition, block) in branches {
+            let condition = self.expression(condition);
+            match constant_truth(&condition) {
+                Some(false) => {}
+                Some(true) => {
+                    // Always taken when reached; later branches are dead
+                    else_branch = Some(block);
+                    break;
+                }
+                None => {
+                    let block = self.block(block);
+                    kept.push((condition, block));
+                }
+            }
+        }
+
+        if kept.is_empty() {
+            return match else_branch {
+                Some(block) => self.statements(block),
+                None => Block::default(),
+            };
+        }
+
+        let mut kept = kept.into_iter();
+        let (condition, then_branch) = kept.next().unwrap_or_else(|| unreachable!());
+        let mut block = Block::default();
+        block.push(
+            Statement::If(IfStatement {
+                condition,
+                then_branch,
+                elif_branches: kept.collect(),
+                else_branch: else_branch.map(|block| self.block(block)),
+            }),
+            line,
+        );
+        block
+    }
+
+    /// Optimize a boxed expression, reusing its allocation
+    fn boxed(&mut self, mut expr: Box<Expression>) -> Box<Expression> {
+        let inner = std::mem::replace(&mut *expr, Expression::Literal(Literal::None));
+        *expr = self.expression(inner);
+        expr
+    }
+
+    fn all(&mut self, exprs: Vec<Expression>) -> Vec<Expression> {
+        exprs
+            .into_iter()
+            .map(|expr| self.expression(expr))
+            .collect()
+    }
+
+    fn expression(&mut self, expr: Expression) -> Expression {
+        match expr {
+            Expression::Identifier(name) => match self.constant(&name) {
+                Some(literal) => Expression::Literal(literal.clone()),
+                None => Expression::Identifier(name),
+            },
+            Expression::Binary(bin) => {
+                let left = self.boxed(bin.left);
+                let right = self.boxed(bin.right);
+                if let (Expression::Literal(a), Expression::Literal(b)) = (&*left, &*right) {
+                    if let Some(folded) = fold_binary(&bin.operator, a, b) {
+                        return Expression::Literal(folded);
+                    }
+                }
+                Expression::Binary(BinaryExpression {
+                    left,
+                    operator: bin.operator,
+                    right,
+                })
+            }
+            Expression::Unary(unary) => {
+                let operand = self.boxed(unary.operand);
+                if let Expression::Literal(literal) = &*operand {
+                    if let Some(folded) = fold_unary(&unary.operator, literal) {
+                        return Expression::Literal(folded);
+                    }
+                }
+                Expression::Unary(UnaryExpression {
+                    operator: unary.operator,
+                    operand,
+                })
+            }
+            Expression::Call(call) => {
+                let arguments = self.all(call.arguments);
+                if let Some(literal) = self.inline_call(&call.callee, &arguments) {
+                    return Expression::Literal(literal);
+                }
+                Expression::Call(CallExpression {
+                    callee: call.callee,
+                    arguments,
+                })
+            }
+            Expression::Index(index) => Expression::Index(IndexExpression {
+                object: self.boxed(index.object),
+                index: self.boxed(index.index),
+            }),
+            Expression::FieldAccess(field) => Expression::FieldAccess(FieldAccessExpression {
+                object: self.boxed(field.object),
+                field: field.field,
+            }),
+            Expression::MethodCall(call) => Expression::MethodCall(MethodCallExpression {
+                object: self.boxed(call.object),
+                method: call.method,
+                arguments: self.all(call.arguments),
+            }),
+            Expression::Array(array) => Expression::Array(ArrayExpression {
+                elements: self.all(array.elements),
+                trailing_comma: array.trailing_comma,
+            }),
+            Expression::Dictionary(dict) => Expression::Dictionary(DictionaryExpression {
+                pairs: dict
+                    .pairs
+                    .into_iter()
+                    .map(|(key, value)| (self.expression(key), self.expression(value)))
+                    .collect(),
+                trailing_comma: dict.trailing_comma,
+            }),
+            Expression::Set(set) => Expression::Set(SetExpression {
+                elements: self.all(set.elements),
+                trailing_comma: set.trailing_comma,
+            }),
+            Expression::Tuple(tuple) => Expression::Tuple(TupleExpression {
+                elements: self.all(tuple.elements),
+            }),
+            Expression::StructInit(mut init) => {
+                init.fields = init
+                    .fields
+                    .into_iter()
+                    .map(|(name, value)| (name, self.expression(value)))
+                    .collect();
+                Expression::StructInit(init)
+            }
+            Expression::If(if_expr) => Expression::If(IfExpression {
+                condition: self.boxed(if_expr.condition),
+                then_expr: self.boxed(if_expr.then_expr),
+                else_expr: self.boxed(if_expr.else_expr),
+            }),
+            Expression::Match(mut match_expr) => {
+                match_expr.expression = self.boxed(match_expr.expression);
+                match_expr.arms = match_expr
+                    .arms
+                    .into_iter()
+                    .map(|mut arm| {
+                        arm.guard = arm.guard.map(|guard| self.expression(guard));
+                        arm.expression = self.expression(arm.expression);
+                        arm
+                    })
+                    .collect();
+                Expression::Match(match_expr)
+            }
+            Expression::Range(range) => Expression::Range(RangeExpression {
+                start: self.boxed(range.start),
+                end: self.boxed(range.end),
+                ..range
+            }),
+            Expression::Await(inner) => Expression::Await(AwaitExpression {
+                expression: self.boxed(inner.expression),
+            }),
+            Expression::Propagate(inner) => Expression::Propagate(PropagateExpression {
+                expression: self.boxed(inner.expression),
+            }),
+            Expression::Spawn(inner) => Expression::Spawn(SpawnExpression {
+                expression: self.boxed(inner.expression),
+            }),
+            Expression::Cast(cast) => Expression::Cast(CastExpression {
+                expression: self.boxed(cast.expression),
+                ..cast
+            }),
+            Expression::InterpolatedString(string) => {
+                Expression::InterpolatedString(InterpolatedStringExpression {
+                    parts: string
+                        .parts
+                        .into_iter()
+                        .map(|part| match part {
+                            StringPart::Expression(e) => StringPart::Expression(self.expression(e)),
+                            literal => literal,
+                        })
+                        .collect(),
+                })
+            }
+            Expression::Lambda(mut lambda) => {
+                for parameter in &mut lambda.parameters {
+                    parameter.default = parameter.default.take().map(|d| self.expression(d));
+                }
+                lambda.body = match lambda.body {
+                    LambdaBody::Expression(body) => LambdaBody::Expression(self.boxed(body)),
+                    LambdaBody::Block(block) => LambdaBody::Block(self.block(block)),
+                };
+                Expression::Lambda(lambda)
+            }
+            expr @ (Expression::Literal(_) | Expression::ModuleAccess(_)) => expr,
+        }
+    }
+
+    /// The value of a call to an inlinable function with constant arguments
+    fn inline_call(&self, callee: &Expression, arguments: &[Expression]) -> Option<Literal> {
+        let Expression::Identifier(name) = callee else {
+            return None;
+        };
+        let function = self.inline.get(name)?;
+        if function.parameters.len() != arguments.len() {
+            return None;
+        }
+
+        let mut parameters = HashMap::new();
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            let Expression::Literal(literal) = argument else {
+                return None;
+            };
+            parameters.insert(parameter.clone(), literal.clone());
+        }
+
+        let mut body = Folder::new(self.inline, Bindings::default());
+        body.scopes = vec![parameters];
+        match body.expression(function.body.clone()) {
+            Expression::Literal(literal) => Some(literal),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a constant condition is truthy, as the interpreter decides it
+fn constant_truth(expr: &Expression) -> Option<bool> {
+    match expr {
+        Expression::Literal(literal) => Some(is_truthy(literal)),
+        _ => None,
+    }
+}
+
+fn is_truthy(literal: &Literal) -> bool {
+    match literal {
+        Literal::Boolean(b) => *b,
+        Literal::None => false,
+        Literal::Integer(n) => *n != 0,
+        Literal::Float(f) => *f != 0.0,
+        Literal::String(s) => !s.is_empty(),
+        Literal::Char(c) => *c != '\0',
+    }
+}
+
+fn fold_unary(op: &UnaryOperator, operand: &Literal) -> Option<Literal> {
+    match (op, operand) {
+        (UnaryOperator::Minus, Literal::Integer(n)) => n.checked_neg().map(Literal::Integer),
+        (UnaryOperator::Minus, Literal::Float(f)) => Some(Literal::Float(-f)),
+        (UnaryOperator::Not, literal) => Some(Literal::Boolean(!is_truthy(literal))),
+        (UnaryOperator::BitwiseNot, Literal::Integer(n)) => Some(Literal::Integer(!n)),
+        _ => None,
+    }
+}
+
+fn fold_binary(op: &BinaryOperator, left: &Literal, right: &Literal) -> Option<Literal> {
+    use BinaryOperator as Op;
+    use Literal::{Boolean, Char, Float, Integer, String};
+
+    let folded = match (op, left, right) {
+        (Op::Equal, a, b) => Boolean(literals_equal(a, b)),
+        (Op::NotEqual, a, b) => Boolean(!literals_equal(a, b)),
+        (Op::And, a, b) => Boolean(is_truthy(a) && is_truthy(b)),
+        (Op::Or, a, b) => Boolean(is_truthy(a) || is_truthy(b)),
+
+        (_, Integer(a), Integer(b)) => return fold_integers(op, *a, *b),
+        (_, Float(a), Float(b)) => return fold_floats(op, *a, *b),
+        (_, Integer(a), Float(b)) => return fold_floats(op, *a as f64, *b),
+        (_, Float(a), Integer(b)) => return fold_floats(op, *a, *b as f64),
+
+        (Op::Add, String(a), String(b)) => String(format!("{}{}", a, b)),
+        (_, String(a), String(b)) => return compare(op, a, b),
+        (_, Char(a), Char(b)) => return compare(op, a, b),
+        (_, String(a), Char(b)) => return compare(op, a, &b.to_string()),
+        (_, Char(a), String(b)) => return compare(op, &a.to_string(), b),
+        _ => return None,
+    };
+    Some(folded)
+}
+
+fn fold_integers(op: &BinaryOperator, a: i64, b: i64) -> Option<Literal> {
+    use BinaryOperator as Op;
+
+    let value = match op {
+        Op::Add => a.checked_add(b)?,
+        Op::Subtract => a.checked_sub(b)?,
+        Op::Multiply => a.checked_mul(b)?,
+        Op::Divide if b == 0 => return None,
+        Op::Divide => return Some(Literal::Float(a as f64 / b as f64)),
+        Op::FloorDivide => {
+            let q = a.checked_div(b)?;
+            if a % b != 0 && (a < 0) != (b < 0) {
+                q - 1
+            } else {
+                q
+            }
+        }
+        Op::Modulo => {
+            let r = a.checked_rem(b)?;
+            if r != 0 && (r < 0) != (b < 0) {
+                r + b
+            } else {
+                r
+            }
+        }
+        Op::Power if b < 0 => return Some(Literal::Float((a as f64).powf(b as f64))),
+        Op::Power => a.checked_pow(u32::try_from(b).ok()?)?,
+        Op::BitwiseAnd => a & b,
+        Op::BitwiseOr => a | b,
+        Op::BitwiseXor => a ^ b,
+        Op::LeftShift => a.checked_shl(u32::try_from(b).ok()?)?,
+        Op::RightShift => a.checked_shr(u32::try_from(b).ok()?)?,
+        _ => return compare(op, &a, &b),
+    };
+    Some(Literal::Integer(value))
+}
+
+/// Arithmetic and ordering comparisons on floats, or on an int and a float
+fn fold_floats(op: &BinaryOperator, a: f64, b: f64) -> Option<Literal> {
+    use BinaryOperator as Op;
+
+    let value = match op {
+        Op::Add => a + b,
+        Op::Subtract => a - b,
+        Op::Multiply => a * b,
+        Op::Divide | Op::FloorDivide | Op::Modulo if b == 0.0 => return None,
+        Op::Divide => a / b,
+        Op::FloorDivide => (a / b).floor(),
+        Op::Modulo => {
+            let r = a % b;
+            if r != 0.0 && (r < 0.0) != (b < 0.0) {
+                r + b
+            } else {
+                r
+            }
+        }
+        Op::Power => a.powf(b),
+        _ => return compare(op, &a, &b),
+    };
+    Some(Literal::Float(value))
+}
+
+fn compare<T: PartialOrd + ?Sized>(op: &BinaryOperator, a: &T, b: &T) -> Option<Literal> {
+    let result = match op {
+        BinaryOperator::Less => a < b,
+        BinaryOperator::LessEqual => a <= b,
+        BinaryOperator::Greater => a > b,
+        BinaryOperator::GreaterEqual => a >= b,
+        _ => return None,
+    };
+    Some(Literal::Boolean(result))
+}
+
+/// `==` on literals, matching the interpreter (an int equals a float with
+/// the same value)
+fn literals_equal(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+        (Literal::Integer(a), Literal::Integer(b)) => a == b,
+        (Literal::Float(a), Literal::Float(b)) => a == b,
+        (Literal::Integer(a), Literal::Float(b)) | (Literal::Float(b), Literal::Integer(a)) => {
+            *a as f64 == *b
+        }
+        (Literal::String(a), Literal::String(b)) => a == b,
+        (Literal::Char(a), Literal::Char(b)) => a == b,
+        (Literal::String(a), Literal::Char(b)) => *a == b.to_string(),
+        (Literal::Char(a), Literal::String(b)) => a.to_string() == *b,
+        (Literal::Boolean(a), Literal::Boolean(b)) => a == b,
+        (Literal::None, Literal::None) => true,
+        _ => false,
+    }
+}
@@ -1,19 +1,145 @@
 use crate::ast::*;
-use crate::error::{Result, VeyraError};
+use crate::error::{Found, Result, VeyraError};
 use crate::lexer::{Token, TokenKind};
+use crate::{cfg, deprecation};
+use std::collections::VecDeque;
+
+/// Deepest nesting of expressions, blocks and types the parser accepts.
+/// Every level costs a dozen stack frames here and in the passes that walk
+/// the tree, so without a limit input such as 10,000 open parentheses would
+/// overflow the stack and abort the process instead of failing to parse.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+/// Stack one level of nesting takes while parsing, with room to spare in
+/// unoptimized builds, which need about 40 KB
+const STACK_PER_LEVEL: usize = 48 * 1024;
+
+/// Stack of the thread the parser runs on: enough for `MAX_NESTING_DEPTH`,
+/// which the stack of a test or language server thread has no room for in
+/// unoptimized builds, and for the frames below the first level
+const STACK_SIZE: usize = MAX_NESTING_DEPTH * STACK_PER_LEVEL + 1024 * 1024;
+
+/// Names of the primitive types
+pub const PRIMITIVE_TYPES: &[(&str, PrimitiveType)] = &[
+    ("int", PrimitiveType::Int),
+    ("i32", PrimitiveType::I32),
+    ("i64", PrimitiveType::I64),
+    ("u32", PrimitiveType::U32),
+    ("u64", PrimitiveType::U64),
+    ("f32", PrimitiveType::F32),
+    ("f64", PrimitiveType::F64),
+    ("bool", PrimitiveType::Bool),
+    ("char", PrimitiveType::Char),
+    ("string", PrimitiveType::String),
+];
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    depth: usize,
+    /// Whether to skip past syntax errors and keep parsing
+    recovering: bool,
+    errors: Vec<VeyraError>,
+    /// Comments from the tokens, each with the index of the token after it,
+    /// waiting to be attached to the program
+    comments: VecDeque<(usize, Comment)>,
+    /// Where the program splits into pieces that parse on their own: the
+    /// index of each line break that ends a top-level item, with the number
+    /// of items before it
+    boundaries: Vec<(usize, usize)>,
 }
 
 impl Parser {
+    /// A parser for `tokens`. Comment tokens, as from
+    /// `Lexer::tokenize_with_comments`, end up in the parsed program.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        let mut kept: Vec<Token> = Vec::with_capacity(tokens.len());
+        let mut comments = VecDeque::new();
+        for token in tokens {
+            if token.kind == TokenKind::Comment {
+                let trailing = kept
+                    .last()
+                    .is_some_and(|previous| previous.kind != TokenKind::Newline);
+                let comment = Comment {
+                    text: token.lexeme,
+                    trailing,
+                };
+                comments.push_back((kept.len(), comment));
+            } else {
+                kept.push(token);
+            }
+        }
+
+        Self {
+            tokens: kept,
+            current: 0,
+            depth: 0,
+            recovering: false,
+            errors: Vec::new(),
+            comments,
+            boundaries: Vec::new(),
+        }
     }
 
+    /// Parse the program, failing at the first syntax error
     pub fn parse(&mut self) -> Result<Program> {
+        self.recovering = false;
+        self.parse_items()
+    }
+
+    /// Parse the program, continuing after syntax errors. A statement or
+    /// item with an error is skipped up to the end of its line (or its
+    /// closing brace), so the result has everything else in the file and
+    /// every error, in source order.
+    pub fn parse_recovering(&mut self) -> (Program, Vec<VeyraError>) {
+        self.recovering = true;
+        let program = match self.parse_items() {
+            Ok(program) => program,
+            Err(error) => {
+                self.errors.push(error);
+                Program {
+                    items: Vec::new(),
+                    lines: Lines::default(),
+                }
+            }
+        };
+        (program, std::mem::take(&mut self.errors))
+    }
+
+    /// Parse the program, also returning the top-level line breaks it can
+    /// be split at: each as the index of its `Newline` token among the
+    /// tokens other than comments, with the number of items before it.
+    /// Parsing the tokens between two of them on their own gives the same
+    /// items.
+    #[allow(dead_code)]
+    pub(crate) fn parse_with_boundaries(&mut self) -> Result<(Program, Vec<(usize, usize)>)> {
+        let program = self.parse()?;
+        Ok((program, std::mem::take(&mut self.boundaries)))
+    }
+
+    /// Parse the items on a thread of its own, whose stack is deep enough
+    /// for `MAX_NESTING_DEPTH` however small the caller's is
+    fn parse_items(&mut self) -> Result<Program> {
+        std::thread::scope(|scope| {
+            let parser = std::thread::Builder::new()
+                .name("parser".to_string())
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || self.parse_top_level())
+                .map_err(|e| {
+                    VeyraError::InternalError(format!("Failed to start the parser: {}", e))
+                })?;
+            parser
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+
+    fn parse_top_level(&mut self) -> Result<Program> {
         let mut items = Vec::new();
+        let mut lines = Lines::default();
+        // The line break after the last item, once comments ending the
+        // item's line have been attached to it
+        let mut line_end = None;
 
         while !self.is_at_end() {
             // Skip newlines at top level
@@ -26,7 +152,34 @@ impl Parser {
                 break;
             }
 
-            items.push(self.parse_item()?);
+            let leading = self.take_item_comments(&mut items);
+            if let Some(newline) = line_end.take() {
+                self.boundaries.push((newline, items.len()));
+            }
+            let line = self.peek().line as u32;
+            match self.parse_item() {
+                Ok(mut item) => {
+                    match item_comments(&mut item) {
+                        Some(comments) => *comments = leading,
+                        None => items.extend(leading.into_iter().map(comment_item)),
+                    }
+                    lines.set(items.len(), Some(line));
+                    items.push(item);
+                    if self.check(&TokenKind::Newline) {
+                        line_end = Some(self.current);
+                    }
+                }
+                Err(error) if self.recovering => {
+                    self.errors.push(error);
+                    let start = self.current;
+                    self.synchronize();
+                    // A stray '}' ends nothing at the top level
+                    if self.current == start {
+                        self.advance();
+                    }
+                }
+                Err(error) => return Err(error),
+            }
 
             // Skip trailing newlines after items
             while self.check(&TokenKind::Newline) {
@@ -34,12 +187,108 @@ impl Parser {
             }
         }
 
-        Ok(Program { items })
+        let rest = self.take_item_comments(&mut items);
+        if let Some(newline) = line_end {
+            self.boundaries.push((newline, items.len()));
+        }
+        items.extend(rest.into_iter().map(comment_item));
+        Ok(Program { items, lines })
+    }
+
+    /// Take the comments before the next item. One ending the line of a
+    /// declaration is attached to it; a trailing one after a statement
+    /// becomes an item of its own, and the rest are returned for the next
+    /// item.
+    fn take_item_comments(&mut self, items: &mut Vec<Item>) -> Vec<Comment> {
+        let mut leading = Vec::new();
+        for comment in self.take_comments() {
+            if !comment.trailing {
+                leading.push(comment);
+            } else if let Some(comments) = items.last_mut().and_then(item_comments) {
+                comments.push(comment);
+            } else {
+                items.push(comment_item(comment));
+            }
+        }
+        leading
+    }
+
+    /// Comments before the next token not yet attached to the program.
+    /// Comments inside an expression are only taken after it, at the next
+    /// statement or declaration; they keep their text and, when nothing
+    /// but trailing block comments is before them, their place at the end
+    /// of a line.
+    fn take_comments(&mut self) -> Vec<Comment> {
+        let mut taken = Vec::new();
+        let mut line_ended = false;
+        while self
+            .comments
+            .front()
+            .is_some_and(|(index, _)| *index <= self.current)
+        {
+            let Some((_, mut comment)) = self.comments.pop_front() else {
+                break;
+            };
+            comment.trailing &= !line_ended;
+            line_ended |= !comment.trailing || !comment.text.starts_with("#[[");
+            taken.push(comment);
+        }
+        taken
+    }
+
+    /// Take the comments before the next member of a struct, impl or
+    /// actor: one ending the line of the previous member is attached to it,
+    /// the rest are returned for the next member
+    fn take_member_comments(&mut self, previous: Option<&mut Vec<Comment>>) -> Vec<Comment> {
+        let mut comments = self.take_comments();
+        if let Some(previous) = previous {
+            let leading = comments.iter().position(|comment| !comment.trailing);
+            let leading = comments.split_off(leading.unwrap_or(comments.len()));
+            previous.append(&mut comments);
+            return leading;
+        }
+        for comment in &mut comments {
+            comment.trailing = false;
+        }
+        comments
     }
 
     fn parse_item(&mut self) -> Result<Item> {
+        let attributes = self.parse_attributes()?;
+        if !attributes.is_empty()
+            && !matches!(
+                self.peek().kind,
+                TokenKind::Fn
+                    | TokenKind::Async
+                    | TokenKind::Struct
+                    | TokenKind::Impl
+                    | TokenKind::Trait
+                    | TokenKind::Actor
+            )
+        {
+            return Err(self.error(
+                "Attributes can only be applied to functions, structs, impls, traits and actors",
+            ));
+        }
+
+        let mut item = self.parse_unattributed_item()?;
+        match &mut item {
+            Item::Function(func) => func.attributes = attributes,
+            Item::Struct(s) => s.attributes = attributes,
+            Item::Impl(imp) => imp.attributes = attributes,
+            Item::Trait(tr) => tr.attributes = attributes,
+            Item::Actor(actor) => actor.attributes = attributes,
+            Item::Import(_) | Item::Statement(_) => {}
+        }
+        Ok(item)
+    }
+
+    fn parse_unattributed_item(&mut self) -> Result<Item> {
         match self.peek().kind {
-            TokenKind::Fn => Ok(Item::Function(self.parse_function(false)?)),
+            // `fn(` starts a lambda, in a statement
+            TokenKind::Fn if !self.peek_next_is(&TokenKind::LeftParen) => {
+                Ok(Item::Function(self.parse_function(false)?))
+            }
             TokenKind::Async => {
                 self.advance(); // consume 'async'
                 if self.check(&TokenKind::Fn) {
@@ -50,6 +299,7 @@ impl Parser {
             }
             TokenKind::Struct => Ok(Item::Struct(self.parse_struct()?)),
             TokenKind::Impl => Ok(Item::Impl(self.parse_impl()?)),
+            TokenKind::Trait => Ok(Item::Trait(self.parse_trait()?)),
             TokenKind::Import => Ok(Item::Import(self.parse_import()?)),
             TokenKind::Actor => Ok(Item::Actor(self.parse_actor()?)),
             _ => {
@@ -69,15 +319,36 @@ impl Parser {
             .clone();
 
         self.consume(&TokenKind::LeftParen, "Expected '(' after function name")?;
+        let (parameters, trailing_comma, return_type) = self.parse_signature()?;
+        let body = self.parse_block()?;
+
+        Ok(Function {
+            name,
+            parameters,
+            return_type,
+            body,
+            is_async,
+            attributes: Vec::new(),
+            trailing_comma,
+            comments: Vec::new(),
+        })
+    }
 
+    /// The parameters and return type of a function or `fn` lambda, after
+    /// its '(', and whether the parameter list ends with a comma
+    fn parse_signature(&mut self) -> Result<(Vec<Parameter>, bool, Option<Type>)> {
+        // Long parameter lists may span lines and end with a comma
         let mut parameters = Vec::new();
-        if !self.check(&TokenKind::RightParen) {
-            loop {
-                parameters.push(self.parse_parameter()?);
-                if !self.match_token(&TokenKind::Comma) {
-                    break;
-                }
+        let mut trailing_comma = false;
+        self.skip_newlines();
+        while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+            parameters.push(self.parse_parameter()?);
+            self.skip_newlines();
+            if !self.match_token(&TokenKind::Comma) {
+                break;
             }
+            self.skip_newlines();
+            trailing_comma = self.check(&TokenKind::RightParen);
         }
 
         self.consume(&TokenKind::RightParen, "Expected ')' after parameters")?;
@@ -87,16 +358,85 @@ impl Parser {
         } else {
             None
         };
+        Ok((parameters, trailing_comma, return_type))
+    }
 
-        let body = self.parse_block()?;
+    /// Parse the `@name(args)` attributes before an item, one per line
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>> {
+        let mut attributes = Vec::new();
+        while self.match_token(&TokenKind::At) {
+            let start = self.peek().clone();
+            let attribute = self.parse_attribute()?;
+            let checked = match attribute.name.as_str() {
+                "cfg" => cfg::check_attribute(&attribute),
+                "deprecated" => deprecation::check_attribute(&attribute),
+                "mailbox" => MailboxConfig::from_attribute(&attribute).map(|_| ()),
+                _ => Ok(()),
+            };
+            checked
+                .map_err(|message| VeyraError::parse_error(start.line, start.column, message))?;
+            attributes.push(attribute);
+            self.skip_newlines();
+        }
+        Ok(attributes)
+    }
 
-        Ok(Function {
-            name,
-            parameters,
-            return_type,
-            body,
-            is_async,
-        })
+    fn parse_attribute(&mut self) -> Result<Attribute> {
+        if !self.check_attribute_word() {
+            return Err(self.error("Expected attribute name"));
+        }
+        let name = self.advance().lexeme.clone();
+
+        let mut args = Vec::new();
+        if self.match_token(&TokenKind::LeftParen) {
+            while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                args.push(self.parse_attribute_arg()?);
+                if !self.match_token(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.consume(
+                &TokenKind::RightParen,
+                "Expected ')' after attribute arguments",
+            )?;
+        }
+
+        Ok(Attribute { name, args })
+    }
+
+    /// Attribute names and keys may be identifiers or keywords, as in `not(...)`
+    fn check_attribute_word(&self) -> bool {
+        let token = self.peek();
+        !matches!(
+            token.kind,
+            TokenKind::String(_)
+                | TokenKind::InterpolationStart(_)
+                | TokenKind::Char(_)
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::None
+        ) && token
+            .lexeme
+            .starts_with(|c: char| c.is_alphabetic() || c == '_')
+    }
+
+    fn parse_attribute_arg(&mut self) -> Result<AttributeArg> {
+        if self.check_attribute_word() {
+            if self.peek_next_is(&TokenKind::Equal) {
+                let key = self.advance().lexeme.clone();
+                self.advance(); // consume '='
+                return Ok(AttributeArg::KeyValue(key, self.parse_attribute_literal()?));
+            }
+            return Ok(AttributeArg::Nested(self.parse_attribute()?));
+        }
+        Ok(AttributeArg::Literal(self.parse_attribute_literal()?))
+    }
+
+    fn parse_attribute_literal(&mut self) -> Result<Literal> {
+        match self.parse_primary()? {
+            Expression::Literal(literal) => Ok(literal),
+            _ => Err(self.error("Expected a literal in attribute arguments")),
+        }
     }
 
     fn parse_parameter(&mut self) -> Result<Parameter> {
@@ -135,15 +475,62 @@ impl Parser {
         self.consume(&TokenKind::LeftBrace, "Expected '{' after struct name")?;
         self.skip_newlines();
 
-        let mut fields = Vec::new();
+        let mut fields: Vec<Field> = Vec::new();
+        let mut trailing_comma = false;
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-            fields.push(self.parse_field()?);
+            let comments =
+                self.take_member_comments(fields.last_mut().map(|field| &mut field.comments));
+            let mut field = self.parse_field()?;
+            field.comments = comments;
+            fields.push(field);
+            trailing_comma = self.match_token(&TokenKind::Comma);
             self.skip_newlines();
         }
+        let end_comments =
+            self.take_member_comments(fields.last_mut().map(|field| &mut field.comments));
 
         self.consume(&TokenKind::RightBrace, "Expected '}' after struct fields")?;
 
-        Ok(Struct { name, fields })
+        Ok(Struct {
+            name,
+            fields,
+            attributes: Vec::new(),
+            trailing_comma,
+            comments: Vec::new(),
+            end_comments,
+        })
+    }
+
+    /// `Name { field: value, ... }`, after the struct name
+    fn parse_struct_init(&mut self, struct_name: String) -> Result<Expression> {
+        self.consume(&TokenKind::LeftBrace, "Expected '{' after struct name")?;
+        self.skip_newlines();
+
+        let mut fields = Vec::new();
+        let mut trailing_comma = false;
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let name = self
+                .consume_identifier("Expected field name")?
+                .lexeme
+                .clone();
+            self.consume(&TokenKind::Colon, "Expected ':' after field name")?;
+            self.skip_newlines();
+            fields.push((name, self.parse_expression()?));
+            self.skip_newlines();
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+            self.skip_newlines();
+            trailing_comma = self.check(&TokenKind::RightBrace);
+        }
+
+        self.skip_newlines();
+        self.consume(&TokenKind::RightBrace, "Expected '}' after struct fields")?;
+        Ok(Expression::StructInit(StructInitExpression {
+            struct_name,
+            fields,
+            trailing_comma,
+        }))
     }
 
     fn parse_field(&mut self) -> Result<Field> {
@@ -154,30 +541,128 @@ impl Parser {
         self.consume(&TokenKind::Colon, "Expected ':' after field name")?;
         let field_type = self.parse_type()?;
 
-        Ok(Field { name, field_type })
+        Ok(Field {
+            name,
+            field_type,
+            comments: Vec::new(),
+        })
     }
 
     fn parse_impl(&mut self) -> Result<Impl> {
         self.consume(&TokenKind::Impl, "Expected 'impl'")?;
 
-        let target = self
+        let mut target = self
             .consume_identifier("Expected type name")?
             .lexeme
             .clone();
+        // `impl Trait for Type`
+        let mut trait_name = None;
+        if self.match_token(&TokenKind::For) {
+            let type_name = self
+                .consume_identifier("Expected type name after 'for'")?
+                .lexeme
+                .clone();
+            trait_name = Some(std::mem::replace(&mut target, type_name));
+        }
 
         self.consume(&TokenKind::LeftBrace, "Expected '{' after impl target")?;
         self.skip_newlines();
 
-        let mut methods = Vec::new();
+        let mut methods: Vec<Function> = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let comments =
+                self.take_member_comments(methods.last_mut().map(|method| &mut method.comments));
+            let attributes = self.parse_attributes()?;
             let is_async = self.match_token(&TokenKind::Async);
-            methods.push(self.parse_function(is_async)?);
+            let mut method = self.parse_function(is_async)?;
+            method.attributes = attributes;
+            method.comments = comments;
+            methods.push(method);
             self.skip_newlines();
         }
+        let end_comments =
+            self.take_member_comments(methods.last_mut().map(|method| &mut method.comments));
 
         self.consume(&TokenKind::RightBrace, "Expected '}' after impl methods")?;
 
-        Ok(Impl { target, methods })
+        Ok(Impl {
+            target,
+            trait_name,
+            methods,
+            attributes: Vec::new(),
+            comments: Vec::new(),
+            end_comments,
+        })
+    }
+
+    fn parse_trait(&mut self) -> Result<Trait> {
+        self.consume(&TokenKind::Trait, "Expected 'trait'")?;
+
+        let name = self
+            .consume_identifier("Expected trait name")?
+            .lexeme
+            .clone();
+
+        self.consume(&TokenKind::LeftBrace, "Expected '{' after trait name")?;
+        self.skip_newlines();
+
+        let mut methods: Vec<TraitMethod> = Vec::new();
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let comments =
+                self.take_member_comments(methods.last_mut().map(|m| &mut m.function.comments));
+            let attributes = self.parse_attributes()?;
+            let is_async = self.match_token(&TokenKind::Async);
+            let mut method = self.parse_trait_method(is_async)?;
+            method.function.attributes = attributes;
+            method.function.comments = comments;
+            methods.push(method);
+            self.skip_newlines();
+        }
+        let end_comments =
+            self.take_member_comments(methods.last_mut().map(|m| &mut m.function.comments));
+
+        self.consume(&TokenKind::RightBrace, "Expected '}' after trait methods")?;
+
+        Ok(Trait {
+            name,
+            methods,
+            attributes: Vec::new(),
+            comments: Vec::new(),
+            end_comments,
+        })
+    }
+
+    /// A method signature in a trait, with a default body if a '{' follows
+    fn parse_trait_method(&mut self, is_async: bool) -> Result<TraitMethod> {
+        self.consume(&TokenKind::Fn, "Expected 'fn'")?;
+
+        let name = self
+            .consume_identifier("Expected method name")?
+            .lexeme
+            .clone();
+
+        self.consume(&TokenKind::LeftParen, "Expected '(' after method name")?;
+        let (parameters, trailing_comma, return_type) = self.parse_signature()?;
+        let has_default = self.check(&TokenKind::LeftBrace);
+        let body = if has_default {
+            self.parse_block()?
+        } else {
+            Block::default()
+        };
+
+        Ok(TraitMethod {
+            function: Function {
+                name,
+                parameters,
+                return_type,
+                body,
+                is_async,
+                attributes: Vec::new(),
+                trailing_comma,
+                comments: Vec::new(),
+            },
+            has_default,
+        })
     }
 
     fn parse_import(&mut self) -> Result<Import> {
@@ -218,7 +703,7 @@ impl Parser {
             }
         }
 
-        let alias = if self.match_token(&TokenKind::Identifier) && self.previous().lexeme == "as" {
+        let alias = if self.match_token(&TokenKind::As) {
             Some(
                 self.consume_identifier("Expected alias name")?
                     .lexeme
@@ -246,18 +731,43 @@ impl Parser {
         self.consume(&TokenKind::LeftBrace, "Expected '{' after actor name")?;
         self.skip_newlines();
 
-        let mut fields = Vec::new();
-        let mut methods = Vec::new();
+        let mut fields: Vec<Field> = Vec::new();
+        let mut methods: Vec<Function> = Vec::new();
+        // Whether the member parsed last is a method
+        let mut after_method = false;
 
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let previous = if after_method {
+                methods.last_mut().map(|method| &mut method.comments)
+            } else {
+                fields.last_mut().map(|field| &mut field.comments)
+            };
+            let comments = self.take_member_comments(previous);
+            let attributes = self.parse_attributes()?;
             if self.check(&TokenKind::Fn) || self.check(&TokenKind::Async) {
                 let is_async = self.match_token(&TokenKind::Async);
-                methods.push(self.parse_function(is_async)?);
+                let mut method = self.parse_function(is_async)?;
+                method.attributes = attributes;
+                method.comments = comments;
+                methods.push(method);
+                after_method = true;
+            } else if !attributes.is_empty() {
+                return Err(self.error("Attributes can only be applied to actor methods"));
             } else {
-                fields.push(self.parse_field()?);
+                let mut field = self.parse_field()?;
+                field.comments = comments;
+                fields.push(field);
+                self.match_token(&TokenKind::Comma);
+                after_method = false;
             }
             self.skip_newlines();
         }
+        let previous = if after_method {
+            methods.last_mut().map(|method| &mut method.comments)
+        } else {
+            fields.last_mut().map(|field| &mut field.comments)
+        };
+        let end_comments = self.take_member_comments(previous);
 
         self.consume(&TokenKind::RightBrace, "Expected '}' after actor body")?;
 
@@ -265,6 +775,9 @@ impl Parser {
             name,
             fields,
             methods,
+            attributes: Vec::new(),
+            comments: Vec::new(),
+            end_comments,
         })
     }
 
@@ -272,23 +785,17 @@ impl Parser {
         let base_type = match &self.peek().kind {
             TokenKind::Identifier => {
                 let name = self.advance().lexeme.clone();
-                match name.as_str() {
-                    "int" => Type::Primitive(PrimitiveType::Int),
-                    "i32" => Type::Primitive(PrimitiveType::I32),
-                    "i64" => Type::Primitive(PrimitiveType::I64),
-                    "u32" => Type::Primitive(PrimitiveType::U32),
-                    "u64" => Type::Primitive(PrimitiveType::U64),
-                    "f32" => Type::Primitive(PrimitiveType::F32),
-                    "f64" => Type::Primitive(PrimitiveType::F64),
-                    "bool" => Type::Primitive(PrimitiveType::Bool),
-                    "char" => Type::Primitive(PrimitiveType::Char),
-                    "string" => Type::Primitive(PrimitiveType::String),
-                    _ => Type::Custom(name),
+                match PRIMITIVE_TYPES
+                    .iter()
+                    .find(|(type_name, _)| *type_name == name)
+                {
+                    Some((_, primitive)) => Type::Primitive(primitive.clone()),
+                    None => Type::Custom(name),
                 }
             }
             TokenKind::LeftBracket => {
                 self.advance(); // consume '['
-                let element_type = Box::new(self.parse_type()?);
+                let element_type = Box::new(self.nested(Self::parse_type)?);
 
                 let size = if self.match_token(&TokenKind::Semicolon) {
                     if let TokenKind::Integer(n) = &self.peek().kind {
@@ -312,7 +819,7 @@ impl Parser {
                 let mut parameters = Vec::new();
                 if !self.check(&TokenKind::RightParen) {
                     loop {
-                        parameters.push(self.parse_type()?);
+                        parameters.push(self.nested(Self::parse_type)?);
                         if !self.match_token(&TokenKind::Comma) {
                             break;
                         }
@@ -325,7 +832,7 @@ impl Parser {
                 )?;
 
                 let return_type = if self.match_token(&TokenKind::Arrow) {
-                    Box::new(self.parse_type()?)
+                    Box::new(self.nested(Self::parse_type)?)
                 } else {
                     // Default to unit type (represented as empty tuple for now)
                     Box::new(Type::Custom("()".to_string()))
@@ -351,38 +858,71 @@ impl Parser {
         if self.match_token(&TokenKind::LeftBrace) {
             // Brace-delimited block
             self.skip_newlines();
-            let mut statements = Vec::new();
+            let mut block = Block::default();
 
             while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
-                statements.push(self.parse_statement()?);
+                self.parse_block_statement(&mut block)?;
                 self.skip_newlines();
             }
+            let comments = self.take_comments();
+            block
+                .statements
+                .extend(comments.into_iter().map(Statement::Comment));
 
             self.consume(&TokenKind::RightBrace, "Expected '}' after block")?;
-            Ok(Block { statements })
+            Ok(block)
         } else if self.match_token(&TokenKind::Indent) {
             // Indentation-delimited block
-            let mut statements = Vec::new();
+            let mut block = Block::default();
 
             while !self.check(&TokenKind::Dedent) && !self.is_at_end() {
-                statements.push(self.parse_statement()?);
+                self.parse_block_statement(&mut block)?;
                 self.skip_newlines();
             }
+            let comments = self.take_comments();
+            block
+                .statements
+                .extend(comments.into_iter().map(Statement::Comment));
 
             if self.check(&TokenKind::Dedent) {
                 self.advance();
             }
 
-            Ok(Block { statements })
+            Ok(block)
         } else {
             // Single statement block
-            let statement = self.parse_statement()?;
+            let mut lines = Lines::default();
+            lines.set(0, Some(self.peek().line as u32));
+            let statement = self.nested(Self::parse_statement)?;
             Ok(Block {
                 statements: vec![statement],
+                lines,
             })
         }
     }
 
+    /// Parse a statement of a block into `block`. When recovering, a
+    /// statement with an error is recorded and skipped instead.
+    fn parse_block_statement(&mut self, block: &mut Block) -> Result<()> {
+        let comments = self.take_comments();
+        block
+            .statements
+            .extend(comments.into_iter().map(Statement::Comment));
+        let line = self.peek().line as u32;
+        match self.nested(Self::parse_statement) {
+            Ok(statement) => {
+                block.lines.set(block.statements.len(), Some(line));
+                block.statements.push(statement);
+            }
+            Err(error) if self.recovering => {
+                self.errors.push(error);
+                self.synchronize();
+            }
+            Err(error) => return Err(error),
+        }
+        Ok(())
+    }
+
     fn parse_statement(&mut self) -> Result<Statement> {
         match &self.peek().kind {
             TokenKind::Let => self.parse_variable_declaration(),
@@ -391,6 +931,12 @@ impl Parser {
             TokenKind::For => self.parse_for_statement(),
             TokenKind::Match => self.parse_match_statement(),
             TokenKind::Return => self.parse_return_statement(),
+            TokenKind::Try => self.parse_try_statement(),
+            TokenKind::Throw => {
+                self.advance();
+                let value = self.parse_expression()?;
+                Ok(Statement::Throw(ThrowStatement { value }))
+            }
             TokenKind::Break => {
                 self.advance();
                 Ok(Statement::Break)
@@ -399,6 +945,10 @@ impl Parser {
                 self.advance();
                 Ok(Statement::Continue)
             }
+            TokenKind::TaskGroup => {
+                self.advance();
+                Ok(Statement::TaskGroup(self.parse_block()?))
+            }
             TokenKind::LeftBrace | TokenKind::Indent => Ok(Statement::Block(self.parse_block()?)),
             _ => {
                 // Try to parse as assignment or expression
@@ -425,10 +975,19 @@ impl Parser {
         self.consume(&TokenKind::Let, "Expected 'let'")?;
 
         let mutable = self.match_token(&TokenKind::Mut);
-        let name = self
-            .consume_identifier("Expected variable name")?
-            .lexeme
-            .clone();
+        // `let (a, b) = pair` destructures; a plain name stays in `name`
+        let destructures = self.check(&TokenKind::LeftParen)
+            || self.check(&TokenKind::LeftBracket)
+            || (self.check(&TokenKind::Identifier) && self.peek_next_is(&TokenKind::LeftBrace));
+        let (name, pattern) = if destructures {
+            (String::new(), Some(self.parse_pattern()?))
+        } else {
+            let name = self
+                .consume_identifier("Expected variable name")?
+                .lexeme
+                .clone();
+            (name, None)
+        };
 
         let var_type = if self.match_token(&TokenKind::Colon) {
             Some(self.parse_type()?)
@@ -444,6 +1003,7 @@ impl Parser {
             var_type,
             initializer,
             mutable,
+            pattern,
         }))
     }
 
@@ -511,9 +1071,15 @@ impl Parser {
         let mut arms = Vec::new();
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             let pattern = self.parse_pattern()?;
+            let guard = self.parse_match_guard()?;
             self.consume(&TokenKind::Arrow, "Expected '->' after match pattern")?;
             let body = self.parse_statement()?;
-            arms.push(MatchArm { pattern, body });
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+            self.match_token(&TokenKind::Comma);
             self.skip_newlines();
         }
 
@@ -538,6 +1104,42 @@ impl Parser {
         Ok(Statement::Return(ReturnStatement { value }))
     }
 
+    fn parse_try_statement(&mut self) -> Result<Statement> {
+        self.consume(&TokenKind::Try, "Expected 'try'")?;
+        let body = self.parse_block()?;
+
+        let catch = if self.match_token(&TokenKind::Catch) {
+            // `catch print(e)` has no variable: one is only named right
+            // before a braced or indented block
+            let names_variable = self.check(&TokenKind::Identifier)
+                && (self.peek_next_is(&TokenKind::LeftBrace)
+                    || self.peek_next_is(&TokenKind::Indent));
+            let variable = if names_variable {
+                Some(self.advance().lexeme.clone())
+            } else {
+                None
+            };
+            let body = self.parse_block()?;
+            Some(CatchClause { variable, body })
+        } else {
+            None
+        };
+        let finally = if self.match_token(&TokenKind::Finally) {
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error("Expected 'catch' or 'finally' after try block"));
+        }
+        Ok(Statement::Try(TryStatement {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
     fn parse_assignment_operator(&mut self) -> Option<AssignmentOperator> {
         match &self.peek().kind {
             TokenKind::Equal => {
@@ -560,6 +1162,10 @@ impl Parser {
                 self.advance();
                 Some(AssignmentOperator::DivAssign)
             }
+            TokenKind::SlashSlashEqual => {
+                self.advance();
+                Some(AssignmentOperator::FloorDivAssign)
+            }
             TokenKind::PercentEqual => {
                 self.advance();
                 Some(AssignmentOperator::ModAssign)
@@ -590,52 +1196,58 @@ impl Parser {
 
     // Expression parsing using precedence climbing
     fn parse_expression(&mut self) -> Result<Expression> {
-        self.parse_logical_or()
+        self.nested(Self::parse_logical_or)
     }
 
-    fn parse_logical_or(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_logical_and()?;
+    /// Parse operands joined by left-associative operators. Each operator
+    /// nests everything before it one level deeper, so a long chain counts
+    /// against `MAX_NESTING_DEPTH` like brackets do.
+    fn parse_chain(
+        &mut self,
+        operand: fn(&mut Self) -> Result<Expression>,
+        operator: fn(&mut Self) -> Option<BinaryOperator>,
+    ) -> Result<Expression> {
+        let depth = self.depth;
+        let mut chain = || {
+            let mut expr = operand(self)?;
+            while let Some(op) = operator(self) {
+                self.deepen()?;
+                let right = operand(self)?;
+                expr = Expression::Binary(BinaryExpression {
+                    left: Box::new(expr),
+                    operator: op,
+                    right: Box::new(right),
+                });
+            }
+            Ok(expr)
+        };
+        let result = chain();
+        self.depth = depth;
+        result
+    }
 
-        while self.match_token(&TokenKind::Or) {
-            let right = self.parse_logical_and()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: BinaryOperator::Or,
-                right: Box::new(right),
-            });
-        }
+    fn match_operator(
+        &mut self,
+        kind: &TokenKind,
+        operator: BinaryOperator,
+    ) -> Option<BinaryOperator> {
+        self.match_token(kind).then_some(operator)
+    }
 
-        Ok(expr)
+    fn parse_logical_or(&mut self) -> Result<Expression> {
+        self.parse_chain(Self::parse_logical_and, |p| {
+            p.match_operator(&TokenKind::Or, BinaryOperator::Or)
+        })
     }
 
     fn parse_logical_and(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_equality()?;
-
-        while self.match_token(&TokenKind::And) {
-            let right = self.parse_equality()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: BinaryOperator::And,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_equality, |p| {
+            p.match_operator(&TokenKind::And, BinaryOperator::And)
+        })
     }
 
     fn parse_equality(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_bitwise_or()?;
-
-        while let Some(op) = self.match_equality_operator() {
-            let right = self.parse_bitwise_or()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_bitwise_or, Self::match_equality_operator)
     }
 
     fn match_equality_operator(&mut self) -> Option<BinaryOperator> {
@@ -648,68 +1260,39 @@ impl Parser {
                 self.advance();
                 Some(BinaryOperator::NotEqual)
             }
+            TokenKind::In => {
+                self.advance();
+                Some(BinaryOperator::In)
+            }
+            TokenKind::Not if self.peek_next_is(&TokenKind::In) => {
+                self.advance();
+                self.advance();
+                Some(BinaryOperator::NotIn)
+            }
             _ => None,
         }
     }
 
     fn parse_bitwise_or(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_bitwise_xor()?;
-
-        while self.match_token(&TokenKind::Pipe) {
-            let right = self.parse_bitwise_xor()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: BinaryOperator::BitwiseOr,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_bitwise_xor, |p| {
+            p.match_operator(&TokenKind::Pipe, BinaryOperator::BitwiseOr)
+        })
     }
 
     fn parse_bitwise_xor(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_bitwise_and()?;
-
-        while self.match_token(&TokenKind::Caret) {
-            let right = self.parse_bitwise_and()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: BinaryOperator::BitwiseXor,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_bitwise_and, |p| {
+            p.match_operator(&TokenKind::Caret, BinaryOperator::BitwiseXor)
+        })
     }
 
     fn parse_bitwise_and(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_shift()?;
-
-        while self.match_token(&TokenKind::Ampersand) {
-            let right = self.parse_shift()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: BinaryOperator::BitwiseAnd,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_shift, |p| {
+            p.match_operator(&TokenKind::Ampersand, BinaryOperator::BitwiseAnd)
+        })
     }
 
     fn parse_shift(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_comparison()?;
-
-        while let Some(op) = self.match_shift_operator() {
-            let right = self.parse_comparison()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_comparison, Self::match_shift_operator)
     }
 
     fn match_shift_operator(&mut self) -> Option<BinaryOperator> {
@@ -727,18 +1310,7 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_addition()?;
-
-        while let Some(op) = self.match_comparison_operator() {
-            let right = self.parse_addition()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_addition, Self::match_comparison_operator)
     }
 
     fn match_comparison_operator(&mut self) -> Option<BinaryOperator> {
@@ -764,18 +1336,7 @@ impl Parser {
     }
 
     fn parse_addition(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_multiplication()?;
-
-        while let Some(op) = self.match_additive_operator() {
-            let right = self.parse_multiplication()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_multiplication, Self::match_additive_operator)
     }
 
     fn match_additive_operator(&mut self) -> Option<BinaryOperator> {
@@ -793,18 +1354,7 @@ impl Parser {
     }
 
     fn parse_multiplication(&mut self) -> Result<Expression> {
-        let mut expr = self.parse_power()?;
-
-        while let Some(op) = self.match_multiplicative_operator() {
-            let right = self.parse_power()?;
-            expr = Expression::Binary(BinaryExpression {
-                left: Box::new(expr),
-                operator: op,
-                right: Box::new(right),
-            });
-        }
-
-        Ok(expr)
+        self.parse_chain(Self::parse_power, Self::match_multiplicative_operator)
     }
 
     fn match_multiplicative_operator(&mut self) -> Option<BinaryOperator> {
@@ -817,6 +1367,10 @@ impl Parser {
                 self.advance();
                 Some(BinaryOperator::Divide)
             }
+            TokenKind::SlashSlash => {
+                self.advance();
+                Some(BinaryOperator::FloorDivide)
+            }
             TokenKind::Percent => {
                 self.advance();
                 Some(BinaryOperator::Modulo)
@@ -829,7 +1383,7 @@ impl Parser {
         let mut expr = self.parse_unary()?;
 
         if self.match_token(&TokenKind::StarStar) {
-            let right = self.parse_power()?; // Right associative
+            let right = self.nested(Self::parse_power)?; // Right associative
             expr = Expression::Binary(BinaryExpression {
                 left: Box::new(expr),
                 operator: BinaryOperator::Power,
@@ -844,7 +1398,7 @@ impl Parser {
         match &self.peek().kind {
             TokenKind::Minus => {
                 self.advance();
-                let operand = self.parse_unary()?;
+                let operand = self.nested(Self::parse_unary)?;
                 Ok(Expression::Unary(UnaryExpression {
                     operator: UnaryOperator::Minus,
                     operand: Box::new(operand),
@@ -852,7 +1406,7 @@ impl Parser {
             }
             TokenKind::Not => {
                 self.advance();
-                let operand = self.parse_unary()?;
+                let operand = self.nested(Self::parse_unary)?;
                 Ok(Expression::Unary(UnaryExpression {
                     operator: UnaryOperator::Not,
                     operand: Box::new(operand),
@@ -860,7 +1414,7 @@ impl Parser {
             }
             TokenKind::Tilde => {
                 self.advance();
-                let operand = self.parse_unary()?;
+                let operand = self.nested(Self::parse_unary)?;
                 Ok(Expression::Unary(UnaryExpression {
                     operator: UnaryOperator::BitwiseNot,
                     operand: Box::new(operand),
@@ -870,13 +1424,13 @@ impl Parser {
                 self.advance();
                 // Check for &mut
                 if self.match_token(&TokenKind::Mut) {
-                    let operand = self.parse_unary()?;
+                    let operand = self.nested(Self::parse_unary)?;
                     Ok(Expression::Unary(UnaryExpression {
                         operator: UnaryOperator::MutableReference,
                         operand: Box::new(operand),
                     }))
                 } else {
-                    let operand = self.parse_unary()?;
+                    let operand = self.nested(Self::parse_unary)?;
                     Ok(Expression::Unary(UnaryExpression {
                         operator: UnaryOperator::Reference,
                         operand: Box::new(operand),
@@ -885,7 +1439,7 @@ impl Parser {
             }
             TokenKind::Star => {
                 self.advance();
-                let operand = self.parse_unary()?;
+                let operand = self.nested(Self::parse_unary)?;
                 Ok(Expression::Unary(UnaryExpression {
                     operator: UnaryOperator::Dereference,
                     operand: Box::new(operand),
@@ -956,8 +1510,19 @@ impl Parser {
                         return Err(self.error("'::' can only be used after module names"));
                     }
                 }
-                TokenKind::Dot => {
+                TokenKind::Question => {
                     self.advance();
+                    expr = Expression::Propagate(PropagateExpression {
+                        expression: Box::new(expr),
+                    });
+                }
+                // `value?.field` is lexed as one token
+                TokenKind::QuestionDot | TokenKind::Dot => {
+                    if self.advance().kind == TokenKind::QuestionDot {
+                        expr = Expression::Propagate(PropagateExpression {
+                            expression: Box::new(expr),
+                        });
+                    }
                     let field = self.consume_identifier("Expected field name after '.'")?;
                     let field_name = field.lexeme.clone();
 
@@ -989,6 +1554,8 @@ impl Parser {
                         });
                     }
                 }
+                // A method chain may continue on the next line: `obj\n    .foo()`
+                TokenKind::Newline if self.at_chain_continuation() => self.skip_newlines(),
                 _ => break,
             }
         }
@@ -1013,6 +1580,7 @@ impl Parser {
                 self.advance();
                 Ok(Expression::Literal(Literal::String(value)))
             }
+            TokenKind::InterpolationStart(_) => self.parse_interpolated_string(),
             TokenKind::Char(c) => {
                 let value = *c;
                 self.advance();
@@ -1030,8 +1598,23 @@ impl Parser {
                 self.advance();
                 Ok(Expression::Literal(Literal::None))
             }
+            // `Some(x)` is a call of the builtin `Some`, like `Ok(x)`
+            TokenKind::Some => {
+                self.advance();
+                Ok(Expression::Identifier("Some".to_string()))
+            }
             TokenKind::Identifier => {
                 let name = self.advance().lexeme.clone();
+                if self.check(&TokenKind::Bang) {
+                    // Macros such as include! are expanded before parsing
+                    return Err(self.error(&format!(
+                        "Macro '{}!' is not available here (include! and include_str! need a source file)",
+                        name
+                    )));
+                }
+                if self.at_struct_fields() {
+                    return self.parse_struct_init(name);
+                }
                 Ok(Expression::Identifier(name))
             }
             TokenKind::LeftParen => {
@@ -1079,6 +1662,7 @@ impl Parser {
             TokenKind::LeftBracket => {
                 self.advance();
                 let mut elements = Vec::new();
+                let mut trailing_comma = false;
                 self.skip_newlines(); // Allow newlines after opening bracket
 
                 if !self.check(&TokenKind::RightBracket) {
@@ -1093,6 +1677,7 @@ impl Parser {
 
                         // Allow trailing comma
                         if self.check(&TokenKind::RightBracket) {
+                            trailing_comma = true;
                             break;
                         }
                     }
@@ -1102,7 +1687,10 @@ impl Parser {
                     &TokenKind::RightBracket,
                     "Expected ']' after array elements",
                 )?;
-                Ok(Expression::Array(ArrayExpression { elements }))
+                Ok(Expression::Array(ArrayExpression {
+                    elements,
+                    trailing_comma,
+                }))
             }
             TokenKind::LeftBrace => {
                 self.advance();
@@ -1114,6 +1702,7 @@ impl Parser {
                     // Empty dictionary by default
                     return Ok(Expression::Dictionary(DictionaryExpression {
                         pairs: vec![],
+                        trailing_comma: false,
                     }));
                 }
 
@@ -1127,11 +1716,13 @@ impl Parser {
                     let first_value = self.parse_expression()?;
                     self.skip_newlines(); // Allow newlines after value
                     let mut pairs = vec![(first_expr, first_value)];
+                    let mut trailing_comma = false;
 
                     while self.match_token(&TokenKind::Comma) {
                         self.skip_newlines(); // Allow newlines after comma
                         if self.check(&TokenKind::RightBrace) {
-                            break; // Trailing comma
+                            trailing_comma = true;
+                            break;
                         }
                         let key = self.parse_expression()?;
                         self.skip_newlines(); // Allow newlines after key
@@ -1147,15 +1738,20 @@ impl Parser {
                         &TokenKind::RightBrace,
                         "Expected '}' after dictionary pairs",
                     )?;
-                    Ok(Expression::Dictionary(DictionaryExpression { pairs }))
+                    Ok(Expression::Dictionary(DictionaryExpression {
+                        pairs,
+                        trailing_comma,
+                    }))
                 } else {
                     // It's a set: {1, 2, 3}
                     let mut elements = vec![first_expr];
+                    let mut trailing_comma = false;
 
                     while self.match_token(&TokenKind::Comma) {
                         self.skip_newlines(); // Allow newlines after comma
                         if self.check(&TokenKind::RightBrace) {
-                            break; // Trailing comma
+                            trailing_comma = true;
+                            break;
                         }
                         elements.push(self.parse_expression()?);
                         self.skip_newlines(); // Allow newlines after element
@@ -1163,21 +1759,26 @@ impl Parser {
 
                     self.skip_newlines(); // Allow newlines before closing brace
                     self.consume(&TokenKind::RightBrace, "Expected '}' after set elements")?;
-                    Ok(Expression::Set(SetExpression { elements }))
+                    Ok(Expression::Set(SetExpression {
+                        elements,
+                        trailing_comma,
+                    }))
                 }
             }
             TokenKind::If => self.parse_if_expression(),
             TokenKind::Match => self.parse_match_expression(),
+            TokenKind::Pipe | TokenKind::PipePipe => self.parse_pipe_lambda(),
+            TokenKind::Fn => self.parse_fn_lambda(),
             TokenKind::Await => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                let expr = self.nested(Self::parse_unary)?;
                 Ok(Expression::Await(AwaitExpression {
                     expression: Box::new(expr),
                 }))
             }
             TokenKind::Spawn => {
                 self.advance();
-                let expr = self.parse_unary()?;
+                let expr = self.nested(Self::parse_unary)?;
                 Ok(Expression::Spawn(SpawnExpression {
                     expression: Box::new(expr),
                 }))
@@ -1197,6 +1798,86 @@ impl Parser {
         }
     }
 
+    /// `|a, b| expression`, or `|| expression` without parameters
+    fn parse_pipe_lambda(&mut self) -> Result<Expression> {
+        let mut parameters = Vec::new();
+        if !self.match_token(&TokenKind::PipePipe) {
+            self.consume(&TokenKind::Pipe, "Expected '|'")?;
+            while !self.check(&TokenKind::Pipe) && !self.is_at_end() {
+                // No default values: in `|x = 1| ...` the '|' would be read
+                // as part of the default
+                let name = self
+                    .consume_identifier("Expected parameter name")?
+                    .lexeme
+                    .clone();
+                let param_type = if self.match_token(&TokenKind::Colon) {
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                parameters.push(Parameter {
+                    name,
+                    param_type,
+                    default: None,
+                });
+                if !self.match_token(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.consume(&TokenKind::Pipe, "Expected '|' after lambda parameters")?;
+        }
+
+        let body = self.parse_expression()?;
+        Ok(Expression::Lambda(LambdaExpression {
+            parameters,
+            return_type: None,
+            body: LambdaBody::Expression(Box::new(body)),
+        }))
+    }
+
+    /// `fn(a, b) -> type { ... }`
+    fn parse_fn_lambda(&mut self) -> Result<Expression> {
+        self.consume(&TokenKind::Fn, "Expected 'fn'")?;
+        self.consume(&TokenKind::LeftParen, "Expected '(' after 'fn' in a lambda")?;
+        let (parameters, _, return_type) = self.parse_signature()?;
+        let body = self.nested(Self::parse_block)?;
+        Ok(Expression::Lambda(LambdaExpression {
+            parameters,
+            return_type,
+            body: LambdaBody::Block(body),
+        }))
+    }
+
+    /// `"text {expr} text ..."`, from the lexer's interpolation tokens
+    fn parse_interpolated_string(&mut self) -> Result<Expression> {
+        let mut parts = Vec::new();
+        let mut text = match &self.advance().kind {
+            TokenKind::InterpolationStart(text) => text.clone(),
+            _ => unreachable!("called at an interpolated string"),
+        };
+        loop {
+            if !text.is_empty() {
+                parts.push(StringPart::Literal(text));
+            }
+            parts.push(StringPart::Expression(self.parse_expression()?));
+            match &self.peek().kind {
+                TokenKind::InterpolationMiddle(next) => text = next.clone(),
+                TokenKind::InterpolationEnd(last) => {
+                    let last = last.clone();
+                    self.advance();
+                    if !last.is_empty() {
+                        parts.push(StringPart::Literal(last));
+                    }
+                    return Ok(Expression::InterpolatedString(
+                        InterpolatedStringExpression { parts },
+                    ));
+                }
+                _ => return Err(self.error("Expected '}' after expression in string")),
+            }
+            self.advance();
+        }
+    }
+
     fn parse_if_expression(&mut self) -> Result<Expression> {
         self.consume(&TokenKind::If, "Expected 'if'")?;
         let condition = self.parse_expression()?;
@@ -1221,20 +1902,28 @@ impl Parser {
         self.consume(&TokenKind::Match, "Expected 'match'")?;
         let expression = self.parse_expression()?;
         self.consume(&TokenKind::LeftBrace, "Expected '{' after match expression")?;
+        self.skip_newlines();
 
+        // Arms are separated by commas and may be written one per line
         let mut arms = Vec::new();
+        let mut trailing_comma = false;
         while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
             let pattern = self.parse_pattern()?;
+            let guard = self.parse_match_guard()?;
             self.consume(&TokenKind::Arrow, "Expected '->' after pattern")?;
             let expression = self.parse_expression()?;
             arms.push(MatchExpressionArm {
                 pattern,
+                guard,
                 expression,
             });
+            self.skip_newlines();
 
             if !self.match_token(&TokenKind::Comma) {
                 break;
             }
+            self.skip_newlines();
+            trailing_comma = self.check(&TokenKind::RightBrace);
         }
 
         self.consume(&TokenKind::RightBrace, "Expected '}' after match arms")?;
@@ -1242,18 +1931,99 @@ impl Parser {
         Ok(Expression::Match(MatchExpression {
             expression: Box::new(expression),
             arms,
+            trailing_comma,
         }))
     }
 
+    /// `if condition` between a match arm's pattern and its '->'
+    fn parse_match_guard(&mut self) -> Result<Option<Expression>> {
+        if self.match_token(&TokenKind::If) {
+            Ok(Some(self.parse_expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The `(pattern)` after `Some`, `Ok` or `Err`
+    fn parse_variant_pattern(&mut self, variant: Variant) -> Result<Pattern> {
+        self.consume(
+            &TokenKind::LeftParen,
+            &format!("Expected '(' after '{}' in pattern", variant.name()),
+        )?;
+        let value = self.parse_pattern()?;
+        self.consume(&TokenKind::RightParen, "Expected ')' after pattern")?;
+        Ok(Pattern::Variant {
+            variant,
+            value: Box::new(value),
+        })
+    }
+
     fn parse_pattern(&mut self) -> Result<Pattern> {
         match &self.peek().kind {
             TokenKind::Identifier => {
                 let name = self.advance().lexeme.clone();
-                if name == "_" {
-                    Ok(Pattern::Wildcard)
+                match Variant::from_name(&name) {
+                    Some(variant) if self.check(&TokenKind::LeftParen) => {
+                        self.parse_variant_pattern(variant)
+                    }
+                    _ if name == "_" => Ok(Pattern::Wildcard),
+                    _ if self.check(&TokenKind::LeftBrace) => self.parse_struct_pattern(name),
+                    _ => Ok(Pattern::Identifier(name)),
+                }
+            }
+            TokenKind::Some => {
+                self.advance();
+                self.parse_variant_pattern(Variant::Some)
+            }
+            TokenKind::LeftParen => {
+                self.advance();
+                self.skip_newlines();
+                let mut elements = Vec::new();
+                let mut grouped = true;
+                while !self.check(&TokenKind::RightParen) && !self.is_at_end() {
+                    elements.push(self.parse_pattern()?);
+                    self.skip_newlines();
+                    if !self.match_token(&TokenKind::Comma) {
+                        break;
+                    }
+                    grouped = false;
+                    self.skip_newlines();
+                }
+                self.consume(&TokenKind::RightParen, "Expected ')' after tuple pattern")?;
+                // `(a)` is just `a`, as `(a,)` is a tuple of one
+                if grouped && elements.len() == 1 {
+                    Ok(elements.pop().unwrap())
                 } else {
-                    Ok(Pattern::Identifier(name))
+                    Ok(Pattern::Tuple(elements))
+                }
+            }
+            TokenKind::LeftBracket => {
+                self.advance();
+                self.skip_newlines();
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.check(&TokenKind::RightBracket) && !self.is_at_end() {
+                    if self.match_token(&TokenKind::DotDot) {
+                        rest = Some(Box::new(if self.check(&TokenKind::Identifier) {
+                            self.parse_pattern()?
+                        } else {
+                            Pattern::Wildcard
+                        }));
+                        self.skip_newlines();
+                        // `..` only comes last
+                        self.match_token(&TokenKind::Comma);
+                        self.skip_newlines();
+                        break;
+                    }
+                    elements.push(self.parse_pattern()?);
+                    self.skip_newlines();
+                    if !self.match_token(&TokenKind::Comma) {
+                        break;
+                    }
+                    self.skip_newlines();
                 }
+                self.consume(&TokenKind::RightBracket, "Expected ']' after array pattern")?;
+                Ok(Pattern::Array { elements, rest })
             }
             TokenKind::Integer(n) => {
                 let value = *n;
@@ -1286,6 +2056,35 @@ impl Parser {
         }
     }
 
+    /// `Name { field, field: pattern, ... }`, after the struct name
+    fn parse_struct_pattern(&mut self, name: String) -> Result<Pattern> {
+        self.consume(&TokenKind::LeftBrace, "Expected '{' after struct name")?;
+        self.skip_newlines();
+
+        let mut fields = Vec::new();
+        while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+            let field = self
+                .consume_identifier("Expected field name")?
+                .lexeme
+                .clone();
+            let pattern = if self.match_token(&TokenKind::Colon) {
+                self.skip_newlines();
+                self.parse_pattern()?
+            } else {
+                Pattern::Identifier(field.clone())
+            };
+            fields.push((field, pattern));
+            self.skip_newlines();
+            if !self.match_token(&TokenKind::Comma) {
+                break;
+            }
+            self.skip_newlines();
+        }
+
+        self.consume(&TokenKind::RightBrace, "Expected '}' after struct pattern")?;
+        Ok(Pattern::Struct { name, fields })
+    }
+
     // Utility methods
     fn match_token(&mut self, kind: &TokenKind) -> bool {
         if self.check(kind) {
@@ -1319,6 +2118,37 @@ impl Parser {
         &self.tokens[self.current]
     }
 
+    /// Whether the next tokens are `{ field:`, which starts the fields of a
+    /// struct literal rather than a block
+    fn at_struct_fields(&self) -> bool {
+        let mut tokens = self.tokens[self.current..]
+            .iter()
+            .map(|token| &token.kind)
+            .filter(|kind| !matches!(kind, TokenKind::Newline));
+        matches!(
+            (tokens.next(), tokens.next(), tokens.next()),
+            (
+                Some(TokenKind::LeftBrace),
+                Some(TokenKind::Identifier),
+                Some(TokenKind::Colon)
+            )
+        )
+    }
+
+    /// Whether the next line starts with `.`, continuing a method chain
+    fn at_chain_continuation(&self) -> bool {
+        self.tokens[self.current..]
+            .iter()
+            .find(|token| token.kind != TokenKind::Newline)
+            .is_some_and(|token| token.kind == TokenKind::Dot)
+    }
+
+    fn peek_next_is(&self, kind: &TokenKind) -> bool {
+        self.tokens.get(self.current + 1).is_some_and(|token| {
+            std::mem::discriminant(&token.kind) == std::mem::discriminant(kind)
+        })
+    }
+
     fn previous(&self) -> &Token {
         &self.tokens[self.current - 1]
     }
@@ -1345,14 +2175,92 @@ impl Parser {
         }
     }
 
+    /// Skip the rest of a statement after a syntax error: up to and
+    /// including the end of its line, or up to the '}' closing the block
+    /// it is in. Braces opened on the way are skipped as a whole, so a
+    /// broken `if` or function header takes its body with it.
+    fn synchronize(&mut self) {
+        let mut braces = 0usize;
+        while !self.is_at_end() {
+            match self.peek().kind {
+                TokenKind::LeftBrace => braces += 1,
+                TokenKind::RightBrace if braces == 0 => return,
+                TokenKind::RightBrace => braces -= 1,
+                TokenKind::Newline if braces == 0 => {
+                    self.advance();
+                    return;
+                }
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    /// Parse something that nests inside the current construct, failing
+    /// once the nesting gets deeper than `MAX_NESTING_DEPTH`
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.deepen()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Go one level deeper, failing past `MAX_NESTING_DEPTH`
+    fn deepen(&mut self) -> Result<()> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            let token = self.peek();
+            return Err(VeyraError::parse_error(
+                token.line,
+                token.column,
+                format!(
+                    "Code is nested too deeply (more than {} levels)",
+                    MAX_NESTING_DEPTH
+                ),
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
     fn error(&self, message: &str) -> VeyraError {
         let token = self.peek();
-        let detailed_message = format!("{}, found '{}'", message, token.lexeme);
-        VeyraError::parse_error(token.line, token.column, detailed_message)
+        VeyraError::ParseError {
+            line: token.line,
+            column: token.column,
+            message: message.to_string(),
+            found: Some(match token.kind {
+                TokenKind::Eof => Found::EndOfFile,
+                TokenKind::Newline => Found::EndOfLine,
+                _ => Found::Token(token.lexeme.clone()),
+            }),
+        }
     }
 }
 
+/// The comments attached to a declaration; imports and statements have none
+fn item_comments(item: &mut Item) -> Option<&mut Vec<Comment>> {
+    match item {
+        Item::Function(func) => Some(&mut func.comments),
+        Item::Struct(s) => Some(&mut s.comments),
+        Item::Impl(imp) => Some(&mut imp.comments),
+        Item::Trait(tr) => Some(&mut tr.comments),
+        Item::Actor(actor) => Some(&mut actor.comments),
+        Item::Import(_) | Item::Statement(_) => None,
+    }
+}
+
+fn comment_item(comment: Comment) -> Item {
+    Item::Statement(Statement::Comment(comment))
+}
+
 pub fn parse(tokens: Vec<Token>) -> Result<Program> {
     let mut parser = Parser::new(tokens);
     parser.parse()
 }
+
+/// Parse a program, collecting every syntax error instead of stopping at the
+/// first; see `Parser::parse_recovering`
+pub fn parse_recovering(tokens: Vec<Token>) -> (Program, Vec<VeyraError>) {
+    let mut parser = Parser::new(tokens);
+    parser.parse_recovering()
+}
@@ -0,0 +1,492 @@
+// Playground server: `veyc serve --playground <port>`
+//
+// A small HTTP API for hosting a web playground without a separate backend:
+//
+//   POST /run      the program as the request body, either as text or as
+//                  JSON `{"source": "..."}`; answers with a RunResult
+//   GET  /health   answers `ok`
+//
+// Programs run on the tree-walking interpreter with no access to files, the
+// network, processes or the environment, cannot import modules, and stop
+// when they run out of time, steps, call depth or output. `veyc serve` runs
+// each one in a child process limited in memory, so that a program that
+// exhausts memory or crashes veyc only ends its own run; `Isolation::Thread`
+// runs them on the server's threads instead. Each request is handled on its
+// own thread and must arrive in full within `REQUEST_DEADLINE`; programs
+// beyond what the machine can run at once are turned away with 503 rather
+// than queued. Responses allow any origin, so
+// the page can be served from elsewhere.
+
+use crate::diagnostic::Diagnostic;
+use crate::error::{Result, VeyraError};
+use crate::interpreter::{exit_code, Interpreter, Limits};
+use crate::lexer::tokenize;
+use crate::modules::ModuleLoader;
+use crate::parser::parse_recovering;
+use crate::sandbox::Policy;
+use serde::Serialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Limits a playground program runs under unless told otherwise
+pub const LIMITS: Limits = Limits {
+    max_steps: Some(50_000_000),
+    max_call_depth: Some(1_000),
+    timeout: Some(Duration::from_secs(5)),
+    max_output: Some(64 * 1024),
+};
+
+/// Largest program accepted, in bytes
+pub const MAX_SOURCE: usize = 64 * 1024;
+
+/// Largest request line and headers accepted together, in bytes
+const MAX_HEADERS: u64 = 8 * 1024;
+
+/// How long a client has to send its whole request
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Largest result read back from a child process, in bytes: the output
+/// limit, escaped, and room for the diagnostics
+const MAX_RESULT: u64 = 1024 * 1024;
+
+/// Memory a child process may use beyond the program's own: veyc itself and
+/// the parser's thread
+const WORKER_OVERHEAD: u64 = 64 * 1024 * 1024;
+
+/// How long past its own time limit a child process may take to answer
+/// before it is killed
+const GRACE: Duration = Duration::from_secs(2);
+
+/// Stack for each program's thread, deep enough for `LIMITS.max_call_depth`
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// File name diagnostics give for the program
+const FILE: &str = "main.vey";
+
+/// Where each program runs
+#[derive(Debug, Clone)]
+pub enum Isolation {
+    /// On the thread handling the request. A program that exhausts memory
+    /// takes the server down with it, so this is for tests and trusted use.
+    #[allow(dead_code)]
+    Thread,
+    /// In a child process, `program` run as `veyc playground-worker`, with
+    /// its data limited to `max_memory` bytes on top of the program's stack
+    /// and what veyc needs itself. Limiting memory needs a Unix system.
+    Process { program: PathBuf, max_memory: u64 },
+}
+
+/// What running a program produced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunResult {
+    /// Everything the program printed, up to the output limit
+    pub stdout: String,
+    /// 0 on success, what `exit` or `main` gave, or 1 after an error
+    pub exit_code: i32,
+    /// Syntax errors, or the error the program stopped with
+    pub diagnostics: Vec<Diagnostic>,
+    pub duration_ms: f64,
+}
+
+/// Parse and run `source` under `limits`
+pub fn run(source: &str, limits: Limits) -> RunResult {
+    let started = Instant::now();
+    let finish = |stdout: String, exit_code: i32, diagnostics: Vec<Diagnostic>| RunResult {
+        stdout,
+        exit_code,
+        diagnostics,
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    };
+    let diagnose = |error: &VeyraError| Diagnostic::from_error(error, Path::new(FILE));
+
+    let tokens = match tokenize(source) {
+        Ok(tokens) => tokens,
+        Err(error) => return finish(String::new(), 1, vec![diagnose(&error)]),
+    };
+    let (program, errors) = parse_recovering(tokens);
+    if !errors.is_empty() {
+        return finish(String::new(), 1, errors.iter().map(diagnose).collect());
+    }
+
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_echo_output(false);
+    interpreter.set_policy(Policy::deny_all());
+    interpreter.set_module_loader(ModuleLoader::disabled());
+    interpreter.set_limits(limits);
+    let result = exit_code(interpreter.interpret(&program));
+    let stdout = interpreter.take_output();
+    match result {
+        Ok(code) => finish(stdout, code, Vec::new()),
+        Err(error) => finish(stdout, 1, vec![diagnose(&error)]),
+    }
+}
+
+/// Arguments for `veyc playground-worker` that give it `limits` and
+/// `max_memory`
+pub fn worker_args(limits: Limits, max_memory: u64) -> Vec<String> {
+    let mut args = vec!["playground-worker".to_string()];
+    let mut add = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            args.extend([name.to_string(), value]);
+        }
+    };
+    add("--max-steps", limits.max_steps.map(|n| n.to_string()));
+    add(
+        "--max-call-depth",
+        limits.max_call_depth.map(|n| n.to_string()),
+    );
+    add(
+        "--timeout-ms",
+        limits.timeout.map(|t| t.as_millis().to_string()),
+    );
+    add("--max-output", limits.max_output.map(|n| n.to_string()));
+    add("--max-memory", Some(max_memory.to_string()));
+    args
+}
+
+/// Run the program read from stdin under `limits`, with at most
+/// `max_memory` bytes of memory besides its stack and veyc's, and print its
+/// RunResult as JSON. This is what `veyc playground-worker` does for each
+/// program run with `Isolation::Process`.
+pub fn work(limits: Limits, max_memory: u64) -> Result<()> {
+    limit_memory(max_memory + STACK_SIZE as u64 + WORKER_OVERHEAD)
+        .map_err(|e| VeyraError::IoError(format!("Failed to limit memory: {}", e)))?;
+    let mut source = String::new();
+    std::io::stdin()
+        .take(MAX_SOURCE as u64)
+        .read_to_string(&mut source)
+        .map_err(|e| VeyraError::IoError(format!("Failed to read the program: {}", e)))?;
+    let result = thread::Builder::new()
+        .name("playground".to_string())
+        .stack_size(STACK_SIZE)
+        .spawn(move || run(&source, limits))
+        .map_err(|e| VeyraError::IoError(format!("Failed to start the program: {}", e)))?
+        .join()
+        .map_err(|_| VeyraError::runtime_error("The program crashed veyc"))?;
+    println!("{}", serde_json::to_string(&result).unwrap_or_default());
+    Ok(())
+}
+
+#[cfg(unix)]
+fn limit_memory(bytes: u64) -> std::io::Result<()> {
+    rlimit::setrlimit(rlimit::Resource::DATA, bytes, bytes)
+}
+
+#[cfg(not(unix))]
+fn limit_memory(_bytes: u64) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Run `source` as `isolation` says and give its RunResult as JSON
+fn run_json(source: &str, limits: Limits, isolation: &Isolation) -> String {
+    let result = match isolation {
+        Isolation::Thread => run(source, limits),
+        Isolation::Process {
+            program,
+            max_memory,
+        } => {
+            let started = Instant::now();
+            match run_in_process(program, *max_memory, source, limits) {
+                Ok(Some(json)) => return json,
+                outcome => {
+                    let message = match outcome {
+                        Err(e) => format!("The program could not be started: {}", e),
+                        _ => "The program was stopped: it ran out of memory or crashed veyc"
+                            .to_string(),
+                    };
+                    let error = VeyraError::runtime_error(message);
+                    RunResult {
+                        stdout: String::new(),
+                        exit_code: 1,
+                        diagnostics: vec![Diagnostic::from_error(&error, Path::new(FILE))],
+                        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+                    }
+                }
+            }
+        }
+    };
+    serde_json::to_string(&result).unwrap_or_default()
+}
+
+/// Run `source` in a `veyc playground-worker` process and give the JSON it
+/// printed, or None when it did not finish cleanly
+fn run_in_process(
+    program: &Path,
+    max_memory: u64,
+    source: &str,
+    limits: Limits,
+) -> std::io::Result<Option<String>> {
+    let mut child = Command::new(program)
+        .args(worker_args(limits, max_memory))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child.stdout.take();
+    let reader = thread::spawn(move || {
+        let mut json = String::new();
+        if let Some(stdout) = stdout {
+            let _ = stdout.take(MAX_RESULT).read_to_string(&mut json);
+        }
+        json
+    });
+    if let Some(mut stdin) = child.stdin.take() {
+        // A child that has already stopped reading is reported by its status
+        let _ = stdin.write_all(source.as_bytes());
+    }
+
+    let deadline = Instant::now() + limits.timeout.unwrap_or(LIMITS.timeout.unwrap()) + GRACE;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let json = reader.join().unwrap_or_default();
+    Ok(match status {
+        Some(status) if status.success() && !json.trim().is_empty() => Some(json),
+        _ => None,
+    })
+}
+
+/// The places for programs running at once
+struct Slots {
+    running: AtomicUsize,
+    capacity: usize,
+}
+
+impl Slots {
+    /// A place for one more program, or None when all are taken
+    fn claim(self: &Arc<Self>) -> Option<Slot> {
+        if self.running.fetch_add(1, Ordering::SeqCst) >= self.capacity {
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(Slot(self.clone()))
+    }
+}
+
+/// A place among the programs running at once, given back when dropped,
+/// even by a panicking thread
+struct Slot(Arc<Slots>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Answer requests on `listener` until the process is stopped
+pub fn serve(listener: TcpListener, limits: Limits, isolation: Isolation) -> Result<()> {
+    let slots = Arc::new(Slots {
+        running: AtomicUsize::new(0),
+        capacity: thread::available_parallelism().map_or(1, |n| n.get()),
+    });
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let slots = slots.clone();
+        let isolation = isolation.clone();
+        let spawned = thread::Builder::new()
+            .name("playground".to_string())
+            .stack_size(STACK_SIZE)
+            .spawn(move || {
+                let _ = handle(&stream, limits, &isolation, &slots);
+            });
+        if let Err(e) = spawned {
+            return Err(VeyraError::IoError(format!(
+                "Failed to start a playground thread: {}",
+                e
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// What a client asked for, read in full
+enum Request {
+    Health,
+    Preflight,
+    Run(String),
+    /// A request answered with an error: its status and message
+    Rejected(&'static str, String),
+}
+
+/// A client's stream, read until `deadline` however slowly it sends
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Read one request from `stream` and answer it. A program is only given a
+/// place to run once its request has arrived in full.
+fn handle(
+    stream: &TcpStream,
+    limits: Limits,
+    isolation: &Isolation,
+    slots: &Arc<Slots>,
+) -> std::io::Result<()> {
+    let error = |status: &str, message: &str| {
+        respond(
+            stream,
+            status,
+            "application/json",
+            &json!({ "error": message }).to_string(),
+        )
+    };
+
+    let request = match read_request(stream) {
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+            ) =>
+        {
+            Request::Rejected(
+                "408 Request Timeout",
+                format!(
+                    "Requests must arrive within {} seconds",
+                    REQUEST_DEADLINE.as_secs()
+                ),
+            )
+        }
+        request => request?,
+    };
+    match request {
+        Request::Health => respond(stream, "200 OK", "text/plain", "ok"),
+        Request::Preflight => respond(stream, "204 No Content", "text/plain", ""),
+        Request::Rejected(status, message) => error(status, &message),
+        Request::Run(source) => {
+            let Some(_slot) = slots.claim() else {
+                return error(
+                    "503 Service Unavailable",
+                    "Too many programs running, try again shortly",
+                );
+            };
+            let result = run_json(&source, limits, isolation);
+            // The slot is given back on return, before the connection closes
+            respond(stream, "200 OK", "application/json", &result)
+        }
+    }
+}
+
+/// Read one request from `stream`, all of it before `REQUEST_DEADLINE`
+fn read_request(stream: &TcpStream) -> std::io::Result<Request> {
+    let mut reader = BufReader::new(Deadline {
+        stream,
+        deadline: Instant::now() + REQUEST_DEADLINE,
+    });
+    let rejected = |status, message: &str| Ok(Request::Rejected(status, message.to_string()));
+
+    // The request line and headers are read through one budget, so a
+    // client cannot make the server buffer an endless line
+    let mut head = (&mut reader).take(MAX_HEADERS);
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        if head.limit() == 0 && !line.ends_with('\n') {
+            return rejected(
+                "431 Request Header Fields Too Large",
+                &format!("Request headers are limited to {} bytes", MAX_HEADERS),
+            );
+        }
+        if line.is_empty() || (line.trim().is_empty() && !lines.is_empty()) {
+            break;
+        }
+        lines.push(line);
+    }
+
+    let request_line = lines.first().map_or("", String::as_str);
+    let mut content_length = 0;
+    let mut json_body = false;
+    for header in lines.iter().skip(1) {
+        if let Some((name, value)) = header.split_once(':') {
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => match value.parse() {
+                    Ok(length) => content_length = length,
+                    Err(_) => return rejected("400 Bad Request", "Invalid Content-Length"),
+                },
+                "content-type" => json_body = value.starts_with("application/json"),
+                _ => {}
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    match (method, path) {
+        ("GET", "/health") => Ok(Request::Health),
+        ("OPTIONS", "/run") => Ok(Request::Preflight),
+        ("POST", "/run") if content_length > MAX_SOURCE => rejected(
+            "413 Payload Too Large",
+            &format!("Programs are limited to {} bytes", MAX_SOURCE),
+        ),
+        ("POST", "/run") => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            let Ok(body) = String::from_utf8(body) else {
+                return rejected("400 Bad Request", "The program is not valid UTF-8");
+            };
+            if !json_body {
+                return Ok(Request::Run(body));
+            }
+            let value: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+            match value["source"].as_str() {
+                Some(source) => Ok(Request::Run(source.to_string())),
+                None => rejected("400 Bad Request", "Expected {\"source\": \"...\"}"),
+            }
+        }
+        (_, "/run") | (_, "/health") => rejected("405 Method Not Allowed", "Method not allowed"),
+        _ => rejected("404 Not Found", "Not found"),
+    }
+}
+
+fn respond(
+    mut stream: &TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,1306 @@
+// Printing syntax trees as Veyra source
+//
+// `Formatter` writes a program in the canonical layout veyra-fmt uses. It
+// works from the tree alone, so `ast_to_source` also prints programs built
+// in code rather than parsed: codemods, project templates and tests can
+// generate Veyra without assembling strings. Whatever the parser produces
+// prints as source that parses back to the same program, apart from layout
+// such as trailing commas.
+
+use crate::ast::*;
+use crate::lexer::{starts_interpolation, Lexer, TokenKind};
+use clap::ValueEnum;
+use std::collections::HashMap;
+
+/// Source code for `program`, laid out as `config` says
+pub fn ast_to_source(program: &Program, config: &Config) -> String {
+    Formatter::new(config.clone()).format_program(program)
+}
+
+/// Source code for one expression, with lists kept on a single line
+pub fn expression_to_source(expression: &Expression) -> String {
+    let mut formatter = Formatter::new(Config::default());
+    formatter.inline = true;
+    formatter.format_expression(expression);
+    formatter.output
+}
+
+/// Whether a list written one item per line ends with a comma
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TrailingCommas {
+    Always,
+    Never,
+    /// Keep a trailing comma only where the source had one
+    Preserve,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub indent_size: usize,
+    pub max_line_length: usize,
+    pub use_spaces: bool, // vs tabs
+    pub trailing_commas: TrailingCommas,
+    pub align_fields: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            indent_size: 4,
+            max_line_length: 100,
+            use_spaces: true,
+            trailing_commas: TrailingCommas::Always,
+            align_fields: false,
+        }
+    }
+}
+
+pub struct Formatter {
+    config: Config,
+    current_indent: usize,
+    output: String,
+    /// Set while a list is tried on one line; lists nested in it then stay
+    /// on that line too instead of breaking on their own
+    inline: bool,
+    /// How the source wrote each integer and string literal, in source
+    /// order for each value: `0xFF` or `1_000` for integers, raw and
+    /// multi-line strings as they were. `None` is the canonical form.
+    spellings: HashMap<Spelled, Vec<Option<String>>>,
+    /// Literals written so far, so a layout that is tried and discarded
+    /// gives back the spellings it used
+    literals_written: Vec<Spelled>,
+    spellings_used: HashMap<Spelled, usize>,
+}
+
+/// The value of a literal whose spelling the formatter keeps
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Spelled {
+    Integer(i64),
+    String(String),
+}
+
+impl Formatter {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            current_indent: 0,
+            output: String::new(),
+            inline: false,
+            spellings: HashMap::new(),
+            literals_written: Vec::new(),
+            spellings_used: HashMap::new(),
+        }
+    }
+
+    /// Write literals the way `source` wrote them: integers in their base
+    /// and with their digit separators, raw and multi-line strings as they
+    /// were instead of as one escaped line
+    pub fn with_spellings_from(mut self, source: &str) -> Self {
+        self.spellings.clear();
+        for token in Lexer::new(source).tokenize().unwrap_or_default() {
+            let (literal, spelling) = match token.kind {
+                TokenKind::Integer(n) => (Spelled::Integer(n), Some(token.lexeme)),
+                TokenKind::String(s) => {
+                    let kept =
+                        token.lexeme.starts_with("r\"") || token.lexeme.starts_with("\"\"\"");
+                    (Spelled::String(s), kept.then_some(token.lexeme))
+                }
+                _ => continue,
+            };
+            self.spellings.entry(literal).or_default().push(spelling);
+        }
+        self
+    }
+
+    pub fn format_program(&mut self, program: &Program) -> String {
+        self.output.clear();
+        self.current_indent = 0;
+        self.literals_written.clear();
+        self.spellings_used.clear();
+
+        for (i, item) in program.items.iter().enumerate() {
+            if let Item::Statement(Statement::Comment(comment)) = item {
+                if comment.trailing {
+                    self.write_trailing_comments(std::slice::from_ref(comment));
+                    continue;
+                }
+            }
+            if i > 0 {
+                self.output.push('\n');
+            }
+            self.format_item(item);
+        }
+
+        // Ensure file ends with newline
+        if !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+
+        self.output.clone()
+    }
+
+    fn format_item(&mut self, item: &Item) {
+        match item {
+            Item::Function(func) => self.format_function(func),
+            Item::Struct(s) => self.format_struct(s),
+            Item::Impl(i) => self.format_impl(i),
+            Item::Trait(t) => self.format_trait(t),
+            Item::Import(import) => self.format_import(import),
+            Item::Actor(actor) => self.format_actor(actor),
+            Item::Statement(stmt) => {
+                self.write_indent();
+                self.format_statement(stmt);
+            }
+        }
+    }
+
+    fn format_function(&mut self, func: &Function) {
+        self.format_signature(func);
+        self.output.push_str(" {");
+        self.format_block_content(&func.body);
+        self.write_trailing_comments(&func.comments);
+    }
+
+    /// A function up to its body: comments, attributes, name, parameters and
+    /// return type
+    fn format_signature(&mut self, func: &Function) {
+        self.write_leading_comments(&func.comments);
+        self.format_attributes(&func.attributes);
+        self.write_indent();
+        if func.is_async {
+            self.output.push_str("async ");
+        }
+        self.output.push_str("fn ");
+        self.output.push_str(&func.name);
+        self.format_list(
+            ("(", ")"),
+            false,
+            &func.parameters,
+            func.trailing_comma,
+            |f, param, _| f.format_parameter(param),
+        );
+        if let Some(ret_type) = &func.return_type {
+            self.output.push_str(" -> ");
+            self.format_type(ret_type);
+        }
+    }
+
+    fn format_parameter(&mut self, param: &Parameter) {
+        self.output.push_str(&param.name);
+        if let Some(t) = &param.param_type {
+            self.output.push_str(": ");
+            self.format_type(t);
+        }
+        if let Some(default) = &param.default {
+            self.output.push_str(" = ");
+            self.format_expression(default);
+        }
+    }
+
+    /// Write the comments on the lines before a declaration, each on its
+    /// own line at the current indentation
+    fn write_leading_comments(&mut self, comments: &[Comment]) {
+        for comment in comments.iter().filter(|comment| !comment.trailing) {
+            self.write_indent();
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+        }
+    }
+
+    /// Write the comments that end the line just written
+    fn write_trailing_comments(&mut self, comments: &[Comment]) {
+        for comment in comments.iter().filter(|comment| comment.trailing) {
+            self.output.push(' ');
+            self.output.push_str(&comment.text);
+        }
+    }
+
+    /// Write the comments after the last member of a struct, impl or actor,
+    /// before its closing brace
+    fn write_end_comments(&mut self, comments: &[Comment]) {
+        self.write_trailing_comments(comments);
+        self.output.push('\n');
+        self.current_indent += 1;
+        self.write_leading_comments(comments);
+        self.current_indent -= 1;
+    }
+
+    fn format_attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.write_indent();
+            self.output.push('@');
+            self.output.push_str(&attribute.to_string());
+            self.output.push('\n');
+        }
+    }
+
+    fn format_struct(&mut self, s: &Struct) {
+        self.write_leading_comments(&s.comments);
+        self.format_attributes(&s.attributes);
+        self.write_indent();
+        self.output.push_str("struct ");
+        self.output.push_str(&s.name);
+        self.output.push_str(" {");
+        if s.fields.is_empty() {
+            self.write_end_comments(&s.end_comments);
+            self.write_indent();
+            self.output.push('}');
+        } else {
+            self.output.push('\n');
+            self.current_indent += 1;
+            let width = self.field_name_width(s.fields.iter().map(|field| &field.name));
+            for (i, field) in s.fields.iter().enumerate() {
+                self.write_leading_comments(&field.comments);
+                self.write_indent();
+                self.write_field_name(&field.name, width);
+                self.format_type(&field.field_type);
+                if i + 1 < s.fields.len() || self.trailing_comma(s.trailing_comma) {
+                    self.output.push(',');
+                }
+                self.write_trailing_comments(&field.comments);
+                self.output.push('\n');
+            }
+            self.write_leading_comments(&s.end_comments);
+            self.current_indent -= 1;
+            self.write_indent();
+            self.output.push('}');
+        }
+        self.write_trailing_comments(&s.comments);
+    }
+
+    fn format_impl(&mut self, i: &Impl) {
+        self.write_leading_comments(&i.comments);
+        self.format_attributes(&i.attributes);
+        self.write_indent();
+        self.output.push_str("impl ");
+        if let Some(trait_name) = &i.trait_name {
+            self.output.push_str(trait_name);
+            self.output.push_str(" for ");
+        }
+        self.output.push_str(&i.target);
+        self.output.push_str(" {");
+        if i.methods.is_empty() {
+            self.write_end_comments(&i.end_comments);
+            self.write_indent();
+            self.output.push('}');
+        } else {
+            self.output.push('\n');
+            self.current_indent += 1;
+            for (idx, method) in i.methods.iter().enumerate() {
+                if idx > 0 {
+                    self.output.push('\n');
+                }
+                self.format_function(method);
+                self.output.push('\n');
+            }
+            self.write_leading_comments(&i.end_comments);
+            self.current_indent -= 1;
+            self.write_indent();
+            self.output.push('}');
+        }
+        self.write_trailing_comments(&i.comments);
+    }
+
+    fn format_trait(&mut self, t: &Trait) {
+        self.write_leading_comments(&t.comments);
+        self.format_attributes(&t.attributes);
+        self.write_indent();
+        self.output.push_str("trait ");
+        self.output.push_str(&t.name);
+        self.output.push_str(" {");
+        if t.methods.is_empty() {
+            self.write_end_comments(&t.end_comments);
+            self.write_indent();
+            self.output.push('}');
+        } else {
+            self.output.push('\n');
+            self.current_indent += 1;
+            for (idx, method) in t.methods.iter().enumerate() {
+                // Required methods are kept together; default methods are
+                // set apart like the methods of an impl
+                if idx > 0 && (method.has_default || t.methods[idx - 1].has_default) {
+                    self.output.push('\n');
+                }
+                if method.has_default {
+                    self.format_function(&method.function);
+                } else {
+                    self.format_signature(&method.function);
+                    self.write_trailing_comments(&method.function.comments);
+                }
+                self.output.push('\n');
+            }
+            self.write_leading_comments(&t.end_comments);
+            self.current_indent -= 1;
+            self.write_indent();
+            self.output.push('}');
+        }
+        self.write_trailing_comments(&t.comments);
+    }
+
+    fn format_import(&mut self, import: &Import) {
+        self.write_indent();
+        self.output.push_str("import ");
+        self.output.push_str(&import.path.join("."));
+        if let Some(items) = &import.items {
+            self.output.push_str(".{");
+            self.output.push_str(&items.join(", "));
+            self.output.push('}');
+        }
+        if let Some(alias) = &import.alias {
+            self.output.push_str(" as ");
+            self.output.push_str(alias);
+        }
+    }
+
+    fn format_actor(&mut self, actor: &Actor) {
+        self.write_leading_comments(&actor.comments);
+        self.format_attributes(&actor.attributes);
+        self.write_indent();
+        self.output.push_str("actor ");
+        self.output.push_str(&actor.name);
+        self.output.push_str(" {");
+        if actor.fields.is_empty() && actor.methods.is_empty() {
+            self.write_end_comments(&actor.end_comments);
+            self.write_indent();
+            self.output.push('}');
+            self.write_trailing_comments(&actor.comments);
+            return;
+        }
+        self.output.push('\n');
+        self.current_indent += 1;
+        let width = self.field_name_width(actor.fields.iter().map(|field| &field.name));
+        for field in &actor.fields {
+            self.write_leading_comments(&field.comments);
+            self.write_indent();
+            self.write_field_name(&field.name, width);
+            self.format_type(&field.field_type);
+            self.output.push(',');
+            self.write_trailing_comments(&field.comments);
+            self.output.push('\n');
+        }
+        for (idx, method) in actor.methods.iter().enumerate() {
+            if idx > 0 || !actor.fields.is_empty() {
+                self.output.push('\n');
+            }
+            self.format_function(method);
+            self.output.push('\n');
+        }
+        self.write_leading_comments(&actor.end_comments);
+        self.current_indent -= 1;
+        self.write_indent();
+        self.output.push('}');
+        self.write_trailing_comments(&actor.comments);
+    }
+
+    fn format_type(&mut self, t: &Type) {
+        match t {
+            Type::Primitive(p) => {
+                let name = match p {
+                    PrimitiveType::Int => "int",
+                    PrimitiveType::I32 => "i32",
+                    PrimitiveType::I64 => "i64",
+                    PrimitiveType::U32 => "u32",
+                    PrimitiveType::U64 => "u64",
+                    PrimitiveType::F32 => "f32",
+                    PrimitiveType::F64 => "f64",
+                    PrimitiveType::Bool => "bool",
+                    PrimitiveType::Char => "char",
+                    PrimitiveType::String => "string",
+                };
+                self.output.push_str(name);
+            }
+            Type::Array { element_type, size } => {
+                self.output.push('[');
+                self.format_type(element_type);
+                if let Some(s) = size {
+                    self.output.push_str("; ");
+                    self.output.push_str(&s.to_string());
+                }
+                self.output.push(']');
+            }
+            Type::Optional(inner) => {
+                self.format_type(inner);
+                self.output.push('?');
+            }
+            Type::Reference { target, mutable } => {
+                self.output.push('&');
+                if *mutable {
+                    self.output.push_str("mut ");
+                }
+                self.format_type(target);
+            }
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                self.output.push_str("fn(");
+                for (i, param) in parameters.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.format_type(param);
+                }
+                self.output.push_str(") -> ");
+                self.format_type(return_type);
+            }
+            Type::Custom(name) => self.output.push_str(name),
+        }
+    }
+
+    /// Write a statement from the current position; callers write the
+    /// indentation before it
+    fn format_statement(&mut self, stmt: &Statement) {
+        match stmt {
+            Statement::VariableDeclaration(var_decl) => {
+                self.output.push_str("let ");
+                if var_decl.mutable {
+                    self.output.push_str("mut ");
+                }
+                match &var_decl.pattern {
+                    Some(pattern) => self.format_pattern(pattern),
+                    None => self.output.push_str(&var_decl.name),
+                }
+                if let Some(t) = &var_decl.var_type {
+                    self.output.push_str(": ");
+                    self.format_type(t);
+                }
+                self.output.push_str(" = ");
+                self.format_expression(&var_decl.initializer);
+            }
+            Statement::Assignment(assign) => {
+                self.format_expression(&assign.target);
+                self.output.push(' ');
+                match assign.operator {
+                    AssignmentOperator::Assign => self.output.push('='),
+                    AssignmentOperator::AddAssign => self.output.push_str("+="),
+                    AssignmentOperator::SubAssign => self.output.push_str("-="),
+                    AssignmentOperator::MulAssign => self.output.push_str("*="),
+                    AssignmentOperator::DivAssign => self.output.push_str("/="),
+                    AssignmentOperator::FloorDivAssign => self.output.push_str("//="),
+                    AssignmentOperator::ModAssign => self.output.push_str("%="),
+                    AssignmentOperator::BitwiseAndAssign => self.output.push_str("&="),
+                    AssignmentOperator::BitwiseOrAssign => self.output.push_str("|="),
+                    AssignmentOperator::BitwiseXorAssign => self.output.push_str("^="),
+                    AssignmentOperator::LeftShiftAssign => self.output.push_str("<<="),
+                    AssignmentOperator::RightShiftAssign => self.output.push_str(">>="),
+                }
+                self.output.push(' ');
+                self.format_expression(&assign.value);
+            }
+            Statement::Expression(expr_stmt) => {
+                self.format_expression(&expr_stmt.expression);
+            }
+            Statement::If(if_stmt) => {
+                self.output.push_str("if ");
+                self.format_expression(&if_stmt.condition);
+                self.output.push_str(" {");
+                self.format_block_content(&if_stmt.then_branch);
+
+                for (elif_cond, elif_body) in &if_stmt.elif_branches {
+                    self.output.push_str(" elif ");
+                    self.format_expression(elif_cond);
+                    self.output.push_str(" {");
+                    self.format_block_content(elif_body);
+                }
+
+                if let Some(else_body) = &if_stmt.else_branch {
+                    self.output.push_str(" else {");
+                    self.format_block_content(else_body);
+                }
+            }
+            Statement::While(while_stmt) => {
+                self.output.push_str("while ");
+                self.format_expression(&while_stmt.condition);
+                self.output.push_str(" {");
+                self.format_block_content(&while_stmt.body);
+            }
+            Statement::For(for_stmt) => {
+                self.output.push_str("for ");
+                self.output.push_str(&for_stmt.variable);
+                self.output.push_str(" in ");
+                self.format_expression(&for_stmt.iterable);
+                self.output.push_str(" {");
+                self.format_block_content(&for_stmt.body);
+            }
+            Statement::Match(match_stmt) => {
+                self.output.push_str("match ");
+                self.format_expression(&match_stmt.expression);
+                self.output.push_str(" {");
+                self.output.push('\n');
+                self.current_indent += 1;
+                // One arm per line; a block or if body opens on the arm's
+                // line and closes at its indentation
+                for arm in &match_stmt.arms {
+                    self.write_indent();
+                    self.format_arm_head(&arm.pattern, arm.guard.as_ref());
+                    self.format_statement(&arm.body);
+                    self.output.push('\n');
+                }
+                self.current_indent -= 1;
+                self.write_indent();
+                self.output.push('}');
+            }
+            Statement::Return(ret_stmt) => {
+                self.output.push_str("return");
+                if let Some(e) = &ret_stmt.value {
+                    self.output.push(' ');
+                    self.format_expression(e);
+                }
+            }
+            Statement::Try(try_stmt) => {
+                self.output.push_str("try {");
+                self.format_block_content(&try_stmt.body);
+                if let Some(catch) = &try_stmt.catch {
+                    self.output.push_str(" catch ");
+                    if let Some(variable) = &catch.variable {
+                        self.output.push_str(variable);
+                        self.output.push(' ');
+                    }
+                    self.output.push('{');
+                    self.format_block_content(&catch.body);
+                }
+                if let Some(finally) = &try_stmt.finally {
+                    self.output.push_str(" finally {");
+                    self.format_block_content(finally);
+                }
+            }
+            Statement::Throw(throw) => {
+                self.output.push_str("throw ");
+                self.format_expression(&throw.value);
+            }
+            Statement::Break => {
+                self.output.push_str("break");
+            }
+            Statement::Continue => {
+                self.output.push_str("continue");
+            }
+            Statement::Block(block) => {
+                self.output.push('{');
+                self.format_block_content(block);
+            }
+            Statement::TaskGroup(block) => {
+                self.output.push_str("task_group {");
+                self.format_block_content(block);
+            }
+            Statement::Comment(comment) => {
+                self.output.push_str(&comment.text);
+            }
+        }
+    }
+
+    /// `pattern if guard -> `, the start of a match arm
+    fn format_arm_head(&mut self, pattern: &Pattern, guard: Option<&Expression>) {
+        self.format_pattern(pattern);
+        if let Some(guard) = guard {
+            self.output.push_str(" if ");
+            self.format_expression(guard);
+        }
+        self.output.push_str(" -> ");
+    }
+
+    fn format_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier(name) => self.output.push_str(name),
+            Pattern::Literal(lit) => self.format_literal(lit),
+            Pattern::Wildcard => self.output.push('_'),
+            Pattern::Tuple(elements) => {
+                self.output.push('(');
+                self.format_patterns(elements);
+                // `(a,)` is a tuple, `(a)` just `a`
+                if elements.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
+            }
+            Pattern::Array { elements, rest } => {
+                self.output.push('[');
+                self.format_patterns(elements);
+                if let Some(rest) = rest {
+                    if !elements.is_empty() {
+                        self.output.push_str(", ");
+                    }
+                    self.output.push_str("..");
+                    if let Pattern::Identifier(name) = rest.as_ref() {
+                        self.output.push_str(name);
+                    }
+                }
+                self.output.push(']');
+            }
+            Pattern::Struct { name, fields } => {
+                self.output.push_str(name);
+                if fields.is_empty() {
+                    self.output.push_str(" {}");
+                    return;
+                }
+                self.output.push_str(" { ");
+                for (i, (field, pattern)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.output.push_str(field);
+                    // `Point { x }` for `Point { x: x }`
+                    if !matches!(pattern, Pattern::Identifier(binding) if binding == field) {
+                        self.output.push_str(": ");
+                        self.format_pattern(pattern);
+                    }
+                }
+                self.output.push_str(" }");
+            }
+            Pattern::Variant { variant, value } => {
+                self.output.push_str(variant.name());
+                self.output.push('(');
+                self.format_pattern(value);
+                self.output.push(')');
+            }
+        }
+    }
+
+    fn format_patterns(&mut self, patterns: &[Pattern]) {
+        for (i, pattern) in patterns.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.format_pattern(pattern);
+        }
+    }
+
+    /// Write the statements of a block after its '{', one per line, and
+    /// the closing '}'. A comment that ended a line in the source ends the
+    /// line written before it.
+    fn format_block_content(&mut self, block: &Block) {
+        self.current_indent += 1;
+        for stmt in &block.statements {
+            if let Statement::Comment(comment) = stmt {
+                if comment.trailing {
+                    self.write_trailing_comments(std::slice::from_ref(comment));
+                    continue;
+                }
+            }
+            self.output.push('\n');
+            self.write_indent();
+            self.format_statement(stmt);
+        }
+        self.current_indent -= 1;
+
+        self.output.push('\n');
+        self.write_indent();
+        self.output.push('}');
+    }
+
+    fn format_literal(&mut self, lit: &Literal) {
+        match lit {
+            Literal::Integer(n) => {
+                if !self.write_spelling(Spelled::Integer(*n)) {
+                    self.output.push_str(&n.to_string());
+                }
+            }
+            // Keep the decimal point so whole floats stay floats
+            Literal::Float(f) if f.fract() == 0.0 && f.is_finite() => {
+                self.output.push_str(&format!("{:.1}", f))
+            }
+            Literal::Float(f) => self.output.push_str(&f.to_string()),
+            Literal::String(s) => {
+                if !self.write_spelling(Spelled::String(s.clone())) {
+                    self.output.push('"');
+                    self.write_string_text(s);
+                    self.output.push('"');
+                }
+            }
+            Literal::Char(c) => {
+                self.output.push('\'');
+                self.write_escaped(*c, '\'');
+                self.output.push('\'');
+            }
+            Literal::Boolean(b) => self.output.push_str(if *b { "true" } else { "false" }),
+            Literal::None => self.output.push_str("None"),
+        }
+    }
+
+    /// Write a literal the way the source wrote it; false, writing
+    /// nothing, if it is to be written in its canonical form
+    fn write_spelling(&mut self, literal: Spelled) -> bool {
+        let used = self.spellings_used.entry(literal.clone()).or_default();
+        let spelling = self
+            .spellings
+            .get(&literal)
+            .and_then(|spellings| spellings.get(*used))
+            .cloned()
+            .flatten();
+        *used += 1;
+        self.literals_written.push(literal);
+        match spelling {
+            Some(spelling) => {
+                self.output.push_str(&spelling);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget the literals written after the first `count`, when the text
+    /// they were written in is discarded
+    fn unwrite_literals(&mut self, count: usize) {
+        for literal in self.literals_written.drain(count..) {
+            if let Some(used) = self.spellings_used.get_mut(&literal) {
+                *used -= 1;
+            }
+        }
+    }
+
+    /// Text inside a string literal, escaping the braces that would
+    /// otherwise start an embedded expression
+    fn write_string_text(&mut self, text: &str) {
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '{' && chars.peek().is_some_and(|&next| starts_interpolation(next)) {
+                self.output.push_str("\\{");
+            } else {
+                self.write_escaped(c, '"');
+            }
+        }
+    }
+
+    /// `"text {expr} text"`. Embedded expressions stay on one line, and
+    /// those not starting with a name are parenthesized, as the lexer
+    /// requires.
+    fn format_interpolated_string(&mut self, string: &InterpolatedStringExpression) {
+        self.output.push('"');
+        for part in &string.parts {
+            match part {
+                StringPart::Literal(text) => self.write_string_text(text),
+                StringPart::Expression(expr) => {
+                    self.output.push('{');
+                    let start = self.output.len();
+                    let nested = std::mem::replace(&mut self.inline, true);
+                    self.format_expression(expr);
+                    self.inline = nested;
+                    if !self.output[start..].starts_with(starts_interpolation) {
+                        self.output.insert(start, '(');
+                        self.output.push(')');
+                    }
+                    self.output.push('}');
+                }
+            }
+        }
+        self.output.push('"');
+    }
+
+    /// A character of a string or char literal, escaped where the lexer
+    /// would not read it back as itself
+    fn write_escaped(&mut self, c: char, quote: char) {
+        match c {
+            '\n' => self.output.push_str("\\n"),
+            '\r' => self.output.push_str("\\r"),
+            '\t' => self.output.push_str("\\t"),
+            '\0' => self.output.push_str("\\0"),
+            '\\' => self.output.push_str("\\\\"),
+            c if c == quote => {
+                self.output.push('\\');
+                self.output.push(c);
+            }
+            // Control characters are all below U+0100, which \x covers
+            c if c.is_control() => self.output.push_str(&format!("\\x{:02x}", c as u32)),
+            c => self.output.push(c),
+        }
+    }
+
+    /// Write an operand, in parentheses if it binds more loosely than its
+    /// position needs
+    fn format_operand(&mut self, expr: &Expression, min_precedence: u8) {
+        if precedence(expr) < min_precedence {
+            self.output.push('(');
+            self.format_expression(expr);
+            self.output.push(')');
+        } else {
+            self.format_expression(expr);
+        }
+    }
+
+    fn format_expression(&mut self, expr: &Expression) {
+        if let Some((receiver, links)) = method_chain(expr) {
+            self.format_chain(receiver, &links);
+            return;
+        }
+
+        match expr {
+            Expression::Literal(lit) => self.format_literal(lit),
+            Expression::Identifier(name) => {
+                self.output.push_str(name);
+            }
+            Expression::Binary(bin_expr) => {
+                // `**` groups to the right, every other operator to the left
+                let level = binary_precedence(&bin_expr.operator);
+                let (left_min, right_min) = match bin_expr.operator {
+                    BinaryOperator::Power => (level + 1, level),
+                    _ => (level, level + 1),
+                };
+                self.format_operand(&bin_expr.left, left_min);
+                self.output.push(' ');
+                let op_str = match bin_expr.operator {
+                    BinaryOperator::Add => "+",
+                    BinaryOperator::Subtract => "-",
+                    BinaryOperator::Multiply => "*",
+                    BinaryOperator::Divide => "/",
+                    BinaryOperator::FloorDivide => "//",
+                    BinaryOperator::Modulo => "%",
+                    BinaryOperator::Power => "**",
+                    BinaryOperator::Equal => "==",
+                    BinaryOperator::NotEqual => "!=",
+                    BinaryOperator::Less => "<",
+                    BinaryOperator::LessEqual => "<=",
+                    BinaryOperator::Greater => ">",
+                    BinaryOperator::GreaterEqual => ">=",
+                    BinaryOperator::In => "in",
+                    BinaryOperator::NotIn => "not in",
+                    BinaryOperator::And => "and",
+                    BinaryOperator::Or => "or",
+                    BinaryOperator::BitwiseAnd => "&",
+                    BinaryOperator::BitwiseOr => "|",
+                    BinaryOperator::BitwiseXor => "^",
+                    BinaryOperator::LeftShift => "<<",
+                    BinaryOperator::RightShift => ">>",
+                };
+                self.output.push_str(op_str);
+                self.output.push(' ');
+                self.format_operand(&bin_expr.right, right_min);
+            }
+            Expression::Unary(unary_expr) => {
+                let op_str = match unary_expr.operator {
+                    UnaryOperator::Minus => "-",
+                    UnaryOperator::Not => "not ",
+                    UnaryOperator::BitwiseNot => "~",
+                    UnaryOperator::Reference => "&",
+                    UnaryOperator::MutableReference => "&mut ",
+                    UnaryOperator::Dereference => "*",
+                };
+                self.output.push_str(op_str);
+                // `- -x` and `* *p` must not run together into `--x`, `**p`
+                if op_str.ends_with(' ') || !starts_with_operator(&unary_expr.operand) {
+                    self.format_operand(&unary_expr.operand, UNARY);
+                } else {
+                    self.format_operand(&unary_expr.operand, PRIMARY);
+                }
+            }
+            Expression::Call(call_expr) => {
+                // `(obj.field)(x)` calls a stored function; without the
+                // parentheses it would be a method call
+                let callee_min = match *call_expr.callee {
+                    Expression::FieldAccess(_) => PRIMARY,
+                    _ => POSTFIX,
+                };
+                self.format_operand(&call_expr.callee, callee_min);
+                self.format_arguments(&call_expr.arguments);
+            }
+            Expression::Index(index_expr) => {
+                self.format_operand(&index_expr.object, POSTFIX);
+                self.output.push('[');
+                self.format_expression(&index_expr.index);
+                self.output.push(']');
+            }
+            Expression::Propagate(propagate) => {
+                self.format_operand(&propagate.expression, POSTFIX);
+                self.output.push('?');
+            }
+            Expression::FieldAccess(field_expr) => {
+                self.format_operand(&field_expr.object, POSTFIX);
+                self.format_link(expr);
+            }
+            Expression::MethodCall(method_expr) => {
+                self.format_operand(&method_expr.object, POSTFIX);
+                self.format_link(expr);
+            }
+            Expression::ModuleAccess(mod_expr) => {
+                self.output.push_str(&mod_expr.module);
+                self.output.push_str("::");
+                self.output.push_str(&mod_expr.item);
+            }
+            Expression::Array(array_expr) => self.format_list(
+                ("[", "]"),
+                false,
+                &array_expr.elements,
+                array_expr.trailing_comma,
+                |f, elem, _| f.format_expression(elem),
+            ),
+            Expression::Dictionary(dict_expr) => self.format_list(
+                ("{", "}"),
+                false,
+                &dict_expr.pairs,
+                dict_expr.trailing_comma,
+                |f, (key, value), _| {
+                    f.format_expression(key);
+                    f.output.push_str(": ");
+                    f.format_expression(value);
+                },
+            ),
+            Expression::Set(set_expr) => self.format_list(
+                ("{", "}"),
+                false,
+                &set_expr.elements,
+                set_expr.trailing_comma,
+                |f, elem, _| f.format_expression(elem),
+            ),
+            Expression::Tuple(tuple_expr) => {
+                self.output.push('(');
+                for (i, elem) in tuple_expr.elements.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.format_expression(elem);
+                }
+                if tuple_expr.elements.len() == 1 {
+                    self.output.push(',');
+                }
+                self.output.push(')');
+            }
+            Expression::StructInit(struct_expr) => {
+                self.output.push_str(&struct_expr.struct_name);
+                self.output.push(' ');
+                let width = self.field_name_width(struct_expr.fields.iter().map(|(name, _)| name));
+                self.format_list(
+                    ("{", "}"),
+                    true,
+                    &struct_expr.fields,
+                    struct_expr.trailing_comma,
+                    |f, (name, value), one_per_line| {
+                        if one_per_line {
+                            f.write_field_name(name, width);
+                        } else {
+                            f.output.push_str(name);
+                            f.output.push_str(": ");
+                        }
+                        f.format_expression(value);
+                    },
+                );
+            }
+            Expression::If(if_expr) => {
+                self.output.push_str("if ");
+                self.format_expression(&if_expr.condition);
+                self.output.push_str(" then ");
+                self.format_expression(&if_expr.then_expr);
+                self.output.push_str(" else ");
+                self.format_expression(&if_expr.else_expr);
+            }
+            Expression::Match(match_expr) => {
+                self.output.push_str("match ");
+                self.format_expression(&match_expr.expression);
+                self.output.push(' ');
+                self.format_list(
+                    ("{", "}"),
+                    true,
+                    &match_expr.arms,
+                    match_expr.trailing_comma,
+                    |f, arm, _| {
+                        f.format_arm_head(&arm.pattern, arm.guard.as_ref());
+                        f.format_expression(&arm.expression);
+                    },
+                );
+            }
+            Expression::Range(range_expr) => {
+                self.format_expression(&range_expr.start);
+                if range_expr.inclusive {
+                    self.output.push_str("..=");
+                } else {
+                    self.output.push_str("..");
+                }
+                self.format_expression(&range_expr.end);
+            }
+            Expression::Await(await_expr) => {
+                self.output.push_str("await ");
+                self.format_operand(&await_expr.expression, UNARY);
+            }
+            Expression::Spawn(spawn_expr) => {
+                self.output.push_str("spawn ");
+                self.format_operand(&spawn_expr.expression, UNARY);
+            }
+            Expression::Cast(cast_expr) => {
+                self.format_operand(&cast_expr.expression, CAST);
+                self.output.push_str(" as ");
+                self.format_type(&cast_expr.target_type);
+            }
+            Expression::InterpolatedString(string) => self.format_interpolated_string(string),
+            Expression::Lambda(lambda) => match &lambda.body {
+                LambdaBody::Expression(body) => {
+                    self.output.push('|');
+                    for (i, param) in lambda.parameters.iter().enumerate() {
+                        if i > 0 {
+                            self.output.push_str(", ");
+                        }
+                        self.format_parameter(param);
+                    }
+                    self.output.push_str("| ");
+                    self.format_expression(body);
+                }
+                LambdaBody::Block(body) => {
+                    self.output.push_str("fn");
+                    self.format_list(
+                        ("(", ")"),
+                        false,
+                        &lambda.parameters,
+                        false,
+                        |f, param, _| f.format_parameter(param),
+                    );
+                    if let Some(ret_type) = &lambda.return_type {
+                        self.output.push_str(" -> ");
+                        self.format_type(ret_type);
+                    }
+                    self.output.push_str(" {");
+                    self.format_block_content(body);
+                }
+            },
+        }
+    }
+
+    fn format_arguments(&mut self, arguments: &[Expression]) {
+        self.output.push('(');
+        for (i, arg) in arguments.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.format_expression(arg);
+        }
+        self.output.push(')');
+    }
+
+    /// Write a method chain on one line if it fits within the maximum line
+    /// length, otherwise one link per line indented under the receiver
+    fn format_chain(&mut self, receiver: &Expression, links: &[&Expression]) {
+        let start = self.output.len();
+        let written = self.literals_written.len();
+        let nested = std::mem::replace(&mut self.inline, true);
+        self.format_operand(receiver, POSTFIX);
+        for link in links {
+            self.format_link(link);
+        }
+        self.inline = nested;
+
+        if nested || self.fits(start) {
+            return;
+        }
+
+        self.output.truncate(start);
+        self.unwrite_literals(written);
+        self.format_operand(receiver, POSTFIX);
+        self.current_indent += 1;
+        for link in links {
+            self.output.push('\n');
+            self.write_indent();
+            self.format_link(link);
+        }
+        self.current_indent -= 1;
+    }
+
+    /// `.field` or `.method(args)`, without the object
+    fn format_link(&mut self, link: &Expression) {
+        match link {
+            Expression::FieldAccess(field_expr) => {
+                self.output.push('.');
+                self.output.push_str(&field_expr.field);
+            }
+            Expression::MethodCall(method_expr) => {
+                self.output.push('.');
+                self.output.push_str(&method_expr.method);
+                self.format_arguments(&method_expr.arguments);
+            }
+            _ => self.format_expression(link),
+        }
+    }
+
+    /// Write a comma-separated list on one line if it fits within the
+    /// maximum line length, otherwise one item per line. `padded` puts spaces
+    /// inside the brackets of the one-line form; `format_item` is told
+    /// whether its item has a line to itself.
+    fn format_list<T>(
+        &mut self,
+        (open, close): (&str, &str),
+        padded: bool,
+        items: &[T],
+        source_trailing_comma: bool,
+        format_item: impl Fn(&mut Self, &T, bool),
+    ) {
+        let start = self.output.len();
+        let written = self.literals_written.len();
+        let nested = std::mem::replace(&mut self.inline, true);
+        self.output.push_str(open);
+        for (i, item) in items.iter().enumerate() {
+            self.output.push_str(match (i, padded) {
+                (0, true) => " ",
+                (0, false) => "",
+                _ => ", ",
+            });
+            format_item(self, item, false);
+        }
+        if padded && !items.is_empty() {
+            self.output.push(' ');
+        }
+        self.output.push_str(close);
+        self.inline = nested;
+
+        // An enclosing list decides for the whole line
+        if nested || items.is_empty() || self.fits(start) {
+            return;
+        }
+
+        self.output.truncate(start);
+        self.unwrite_literals(written);
+        self.output.push_str(open);
+        self.output.push('\n');
+        self.current_indent += 1;
+        for (i, item) in items.iter().enumerate() {
+            self.write_indent();
+            format_item(self, item, true);
+            if i + 1 < items.len() || self.trailing_comma(source_trailing_comma) {
+                self.output.push(',');
+            }
+            self.output.push('\n');
+        }
+        self.current_indent -= 1;
+        self.write_indent();
+        self.output.push_str(close);
+    }
+
+    /// Whether the text written since `start` stayed on one line within the
+    /// maximum line length
+    fn fits(&self, start: usize) -> bool {
+        if self.output[start..].contains('\n') {
+            return false;
+        }
+        let line_start = self.output.rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.output[line_start..];
+        // Tabs are as wide as the indent size
+        let tabs = line.chars().take_while(|c| *c == '\t').count();
+        let width = line.chars().count() + tabs * self.config.indent_size.saturating_sub(1);
+        width <= self.config.max_line_length
+    }
+
+    /// Whether the last item of a list written one item per line gets a comma
+    fn trailing_comma(&self, in_source: bool) -> bool {
+        match self.config.trailing_commas {
+            TrailingCommas::Always => true,
+            TrailingCommas::Never => false,
+            TrailingCommas::Preserve => in_source,
+        }
+    }
+
+    /// Column width that `name: ` is padded to when aligning fields
+    fn field_name_width<'a>(&self, names: impl Iterator<Item = &'a String>) -> usize {
+        if !self.config.align_fields {
+            return 0;
+        }
+        names.map(|name| name.chars().count()).max().unwrap_or(0)
+    }
+
+    fn write_field_name(&mut self, name: &str, width: usize) {
+        self.output.push_str(name);
+        self.output.push(':');
+        let padding = width.saturating_sub(name.chars().count()) + 1;
+        self.output.extend(std::iter::repeat_n(' ', padding));
+    }
+
+    fn write_indent(&mut self) {
+        if self.config.use_spaces {
+            for _ in 0..(self.current_indent * self.config.indent_size) {
+                self.output.push(' ');
+            }
+        } else {
+            for _ in 0..self.current_indent {
+                self.output.push('\t');
+            }
+        }
+    }
+}
+
+// Precedence levels of the parser, from loosest to tightest binding. Binary
+// operators take the levels below UNARY; `if` expressions bind loosest of all.
+const UNARY: u8 = 12;
+const CAST: u8 = 13;
+const POSTFIX: u8 = 14;
+const PRIMARY: u8 = 15;
+
+fn binary_precedence(operator: &BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => 1,
+        BinaryOperator::And => 2,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::In
+        | BinaryOperator::NotIn => 3,
+        BinaryOperator::BitwiseOr => 4,
+        BinaryOperator::BitwiseXor => 5,
+        BinaryOperator::BitwiseAnd => 6,
+        BinaryOperator::LeftShift | BinaryOperator::RightShift => 7,
+        BinaryOperator::Less
+        | BinaryOperator::LessEqual
+        | BinaryOperator::Greater
+        | BinaryOperator::GreaterEqual => 8,
+        BinaryOperator::Add | BinaryOperator::Subtract => 9,
+        BinaryOperator::Multiply
+        | BinaryOperator::Divide
+        | BinaryOperator::FloorDivide
+        | BinaryOperator::Modulo => 10,
+        BinaryOperator::Power => 11,
+    }
+}
+
+fn precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary(binary) => binary_precedence(&binary.operator),
+        Expression::Unary(_) | Expression::Await(_) | Expression::Spawn(_) => UNARY,
+        Expression::Literal(Literal::Integer(n)) if *n < 0 => UNARY,
+        Expression::Literal(Literal::Float(f)) if f.is_sign_negative() => UNARY,
+        Expression::Cast(_) => CAST,
+        Expression::Call(_)
+        | Expression::Index(_)
+        | Expression::FieldAccess(_)
+        | Expression::MethodCall(_)
+        | Expression::Propagate(_) => POSTFIX,
+        // The body of `|x| body` extends as far as it can
+        Expression::If(_) | Expression::Range(_) => 0,
+        Expression::Lambda(lambda) if matches!(lambda.body, LambdaBody::Expression(_)) => 0,
+        _ => PRIMARY,
+    }
+}
+
+/// Whether an expression is written starting with an operator symbol
+fn starts_with_operator(expr: &Expression) -> bool {
+    match expr {
+        Expression::Unary(unary) => unary.operator != UnaryOperator::Not,
+        Expression::Literal(_) => precedence(expr) == UNARY,
+        _ => false,
+    }
+}
+
+/// Split a chain of two or more method calls into the object of its first
+/// call and the calls and field accesses after it, in source order
+fn method_chain(expr: &Expression) -> Option<(&Expression, Vec<&Expression>)> {
+    let mut links = Vec::new();
+    let mut receiver = expr;
+    let mut calls = 0;
+    let mut chain_len = 0;
+    loop {
+        match receiver {
+            Expression::MethodCall(call) => {
+                links.push(receiver);
+                receiver = &call.object;
+                calls += 1;
+                chain_len = links.len();
+            }
+            Expression::FieldAccess(access) => {
+                links.push(receiver);
+                receiver = &access.object;
+            }
+            _ => break,
+        }
+    }
+    if calls < 2 {
+        return None;
+    }
+
+    // Field accesses before the first call stay with the receiver
+    links.truncate(chain_len);
+    let receiver = match links.last()? {
+        Expression::MethodCall(call) => &*call.object,
+        _ => return None,
+    };
+    links.reverse();
+    Some((receiver, links))
+}
@@ -0,0 +1,749 @@
+// Name resolution
+//
+// `resolve` builds the scoped symbol table of a program: every definition
+// (function, struct, actor, import, variable, parameter) with its kind and
+// span, and every use of a name with the definition it refers to. The
+// linter and the language server share it rather than each tracking scopes.
+//
+// Scoping is lexical. Top-level definitions are visible everywhere,
+// regardless of order; functions, blocks, loop bodies and match arms open
+// nested scopes whose names are visible after their declaration.
+//
+// The AST does not record positions, so spans are recovered by matching
+// names against the ranged token stream of the source, in source order.
+
+use crate::ast::*;
+use crate::builtins;
+use crate::lexer::{RangedToken, TokenKind};
+use std::ops::Range;
+
+pub type SymbolId = usize;
+pub type ScopeId = usize;
+
+/// Names defined by builtin modules when imported as a whole
+const MODULE_EXPORTS: &[(&str, &[&str])] = &[("std.math", &["PI", "E"]), ("std.collections", &[])];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Struct,
+    Trait,
+    Actor,
+    Import,
+    Variable,
+    Parameter,
+    /// Actor state, visible as a variable in the actor's methods
+    Field,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Global,
+    Function,
+    Block,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub scope: ScopeId,
+    /// Character range of the defining name, when it appears in the source
+    pub span: Option<Range<usize>>,
+    pub mutable: bool,
+    /// Position in resolution order; names are visible to later uses
+    order: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub symbols: Vec<SymbolId>,
+}
+
+/// Where a name is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// In an expression or assignment target
+    Value,
+    /// In a type annotation
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Symbol(SymbolId),
+    Builtin,
+    Unresolved,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub name: String,
+    pub kind: ReferenceKind,
+    pub scope: ScopeId,
+    pub span: Option<Range<usize>>,
+    pub resolution: Resolution,
+    order: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolTable {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol>,
+    pub references: Vec<Reference>,
+    /// The program imports a module from a file, whose struct, trait and
+    /// actor types are shared with every module, so an unresolved type name
+    /// may come from it
+    pub imports_types: bool,
+}
+
+#[allow(dead_code)]
+impl SymbolTable {
+    pub const GLOBAL: ScopeId = 0;
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id]
+    }
+
+    /// Uses of a symbol, in source order
+    pub fn references_to(&self, id: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.resolution == Resolution::Symbol(id))
+    }
+
+    /// Uses of names that are neither defined nor builtin
+    pub fn unresolved(&self) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(|reference| reference.resolution == Resolution::Unresolved)
+    }
+
+    /// Uses of values that are neither defined, builtin nor possibly a type
+    /// of an imported module, which are errors
+    pub fn undefined(&self) -> impl Iterator<Item = &Reference> {
+        self.unresolved().filter(|reference| {
+            reference.kind == ReferenceKind::Value
+                && !(self.imports_types && reference.name.starts_with(char::is_uppercase))
+        })
+    }
+
+    /// The symbol defined or used at a character offset
+    pub fn symbol_at(&self, offset: usize) -> Option<SymbolId> {
+        let contains = |span: &Option<Range<usize>>| {
+            span.as_ref()
+                .is_some_and(|span| span.start <= offset && offset <= span.end)
+        };
+        self.symbols
+            .iter()
+            .position(|symbol| contains(&symbol.span))
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|reference| contains(&reference.span))
+                    .and_then(|reference| match reference.resolution {
+                        Resolution::Symbol(id) => Some(id),
+                        _ => None,
+                    })
+            })
+    }
+
+    /// Names defined in `scope` and the scopes enclosing it
+    pub fn visible_names(&self, scope: ScopeId) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            names.extend(
+                self.scopes[id]
+                    .symbols
+                    .iter()
+                    .map(|symbol| self.symbols[*symbol].name.as_str()),
+            );
+            current = self.scopes[id].parent;
+        }
+        names
+    }
+
+    /// The definition `name` refers to when used at the end of `scope`
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId> {
+        self.lookup_at(scope, name, usize::MAX)
+    }
+
+    fn lookup_at(&self, scope: ScopeId, name: &str, order: usize) -> Option<SymbolId> {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            if let Some(found) = self.find_in(id, name, order) {
+                return Some(found);
+            }
+            current = self.scopes[id].parent;
+        }
+        None
+    }
+
+    /// The definition of `name` in `scope` visible to a use at `order`
+    fn find_in(&self, scope: ScopeId, name: &str, order: usize) -> Option<SymbolId> {
+        let scope = &self.scopes[scope];
+        let mut candidates = scope
+            .symbols
+            .iter()
+            .copied()
+            .filter(|symbol| self.symbols[*symbol].name == name);
+
+        let before = candidates
+            .clone()
+            .rfind(|symbol| self.symbols[*symbol].order < order);
+        match scope.kind {
+            // Globals are visible before their definition
+            ScopeKind::Global => before.or_else(|| candidates.next()),
+            _ => before,
+        }
+    }
+
+    /// The definition `symbol` hides: a same-named one visible from an
+    /// enclosing scope of the same function, or of top-level code
+    pub fn shadowed(&self, symbol: SymbolId) -> Option<SymbolId> {
+        let symbol = &self.symbols[symbol];
+        let mut scope = &self.scopes[symbol.scope];
+        while scope.kind != ScopeKind::Function {
+            let id = scope.parent?;
+            if let Some(found) = self.find_in(id, &symbol.name, symbol.order) {
+                return Some(found);
+            }
+            scope = &self.scopes[id];
+        }
+        None
+    }
+}
+
+/// Resolve every name in `program`. `tokens` is the ranged token stream of
+/// the source the program was parsed from and supplies the spans.
+pub fn resolve(program: &Program, tokens: &[RangedToken]) -> SymbolTable {
+    let mut resolver = Resolver {
+        table: SymbolTable {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                parent: None,
+                symbols: Vec::new(),
+            }],
+            symbols: Vec::new(),
+            references: Vec::new(),
+            imports_types: false,
+        },
+        scope: SymbolTable::GLOBAL,
+        order: 0,
+        spans: Spans::new(tokens),
+    };
+    for item in &program.items {
+        resolver.item(item);
+    }
+
+    let mut table = resolver.table;
+    for i in 0..table.references.len() {
+        let reference = &table.references[i];
+        let resolution = match table.lookup_at(reference.scope, &reference.name, reference.order) {
+            Some(symbol) => Resolution::Symbol(symbol),
+            None if builtins::lookup(&reference.name).is_some() => Resolution::Builtin,
+            None => Resolution::Unresolved,
+        };
+        table.references[i].resolution = resolution;
+    }
+    table
+}
+
+/// Finds where the names of an AST are written, by matching them in
+/// source order against the ranged token stream
+pub(crate) struct Spans<'a> {
+    tokens: &'a [RangedToken],
+    /// Index of the next token to match names against
+    cursor: usize,
+}
+
+impl<'a> Spans<'a> {
+    pub(crate) fn new(tokens: &'a [RangedToken]) -> Self {
+        Self { tokens, cursor: 0 }
+    }
+
+    /// The token of the next occurrence of `name`
+    pub(crate) fn next(&mut self, name: &str) -> Option<&'a RangedToken> {
+        let tokens = self.tokens;
+        let offset = tokens[self.cursor.min(tokens.len())..]
+            .iter()
+            .position(|(token, _)| is_word(&token.kind) && token.lexeme == name)?;
+        self.cursor += offset + 1;
+        Some(&tokens[self.cursor - 1])
+    }
+}
+
+struct Resolver<'a> {
+    table: SymbolTable,
+    scope: ScopeId,
+    order: usize,
+    spans: Spans<'a>,
+}
+
+impl Resolver<'_> {
+    /// Span of the next occurrence of `name` in the source
+    fn span(&mut self, name: &str) -> Option<Range<usize>> {
+        self.spans.next(name).map(|(_, range)| range.clone())
+    }
+
+    fn next_order(&mut self) -> usize {
+        self.order += 1;
+        self.order
+    }
+
+    fn define(&mut self, name: &str, kind: SymbolKind, span: Option<Range<usize>>, mutable: bool) {
+        let order = self.next_order();
+        let id = self.table.symbols.len();
+        self.table.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            scope: self.scope,
+            span,
+            mutable,
+            order,
+        });
+        self.table.scopes[self.scope].symbols.push(id);
+    }
+
+    fn reference(&mut self, name: &str, kind: ReferenceKind) {
+        let span = self.span(name);
+        let order = self.next_order();
+        self.table.references.push(Reference {
+            name: name.to_string(),
+            kind,
+            scope: self.scope,
+            span,
+            resolution: Resolution::Unresolved,
+            order,
+        });
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind) {
+        self.table.scopes.push(Scope {
+            kind,
+            parent: Some(self.scope),
+            symbols: Vec::new(),
+        });
+        self.scope = self.table.scopes.len() - 1;
+    }
+
+    fn pop_scope(&mut self) {
+        self.scope = self.table.scopes[self.scope]
+            .parent
+            .unwrap_or(SymbolTable::GLOBAL);
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Function(func) => self.function(func, false),
+            Item::Struct(s) => {
+                self.attributes(&s.attributes);
+                let span = self.span(&s.name);
+                self.define(&s.name, SymbolKind::Struct, span, false);
+                self.fields(&s.fields);
+            }
+            Item::Impl(imp) => {
+                self.attributes(&imp.attributes);
+                if let Some(trait_name) = &imp.trait_name {
+                    self.reference(trait_name, ReferenceKind::Type);
+                }
+                self.reference(&imp.target, ReferenceKind::Type);
+                for method in &imp.methods {
+                    self.function(method, true);
+                }
+            }
+            Item::Trait(tr) => {
+                self.attributes(&tr.attributes);
+                let span = self.span(&tr.name);
+                self.define(&tr.name, SymbolKind::Trait, span, false);
+                for method in &tr.methods {
+                    self.function(&method.function, true);
+                }
+            }
+            Item::Actor(actor) => {
+                self.attributes(&actor.attributes);
+                let span = self.span(&actor.name);
+                self.define(&actor.name, SymbolKind::Actor, span, false);
+                self.push_scope(ScopeKind::Block);
+                for field in &actor.fields {
+                    let span = self.span(&field.name);
+                    self.type_annotation(&field.field_type);
+                    self.define(&field.name, SymbolKind::Field, span, true);
+                }
+                for method in &actor.methods {
+                    self.function(method, true);
+                }
+                self.pop_scope();
+            }
+            Item::Import(import) => self.import(import),
+            Item::Statement(statement) => self.statement(statement),
+        }
+    }
+
+    fn import(&mut self, import: &Import) {
+        let mut path_spans: Vec<Option<Range<usize>>> = import
+            .path
+            .iter()
+            .map(|segment| self.span(segment))
+            .collect();
+        let path = import.path.join(".");
+        if import.path[0] != "std" {
+            self.table.imports_types = true;
+        }
+
+        match &import.items {
+            Some(items) => {
+                for item in items {
+                    let span = self.span(item);
+                    self.define(item, SymbolKind::Import, span, false);
+                }
+            }
+            None => {
+                // A module's functions and variables stay in its namespace;
+                // only the standard modules listed bind names directly
+                if let Some((_, exports)) =
+                    MODULE_EXPORTS.iter().find(|(module, _)| *module == path)
+                {
+                    for export in *exports {
+                        self.define(export, SymbolKind::Import, None, false);
+                    }
+                }
+                match &import.alias {
+                    Some(alias) => {
+                        let span = self.span(alias);
+                        self.define(alias, SymbolKind::Import, span, false);
+                    }
+                    None => {
+                        if let Some(last) = import.path.last() {
+                            let span = path_spans.pop().flatten();
+                            self.define(last, SymbolKind::Import, span, false);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.span(&attribute.name);
+            for arg in &attribute.args {
+                match arg {
+                    AttributeArg::KeyValue(key, _) => {
+                        self.span(key);
+                    }
+                    AttributeArg::Nested(nested) => self.attributes(std::slice::from_ref(nested)),
+                    AttributeArg::Literal(_) => {}
+                }
+            }
+        }
+    }
+
+    fn fields(&mut self, fields: &[Field]) {
+        for field in fields {
+            self.span(&field.name);
+            self.type_annotation(&field.field_type);
+        }
+    }
+
+    fn type_annotation(&mut self, ty: &Type) {
+        match ty {
+            Type::Primitive(primitive) => {
+                self.span(primitive_name(primitive));
+            }
+            Type::Array { element_type, .. } => self.type_annotation(element_type),
+            Type::Optional(inner) => self.type_annotation(inner),
+            Type::Reference { target, .. } => self.type_annotation(target),
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                for parameter in parameters {
+                    self.type_annotation(parameter);
+                }
+                self.type_annotation(return_type);
+            }
+            Type::Custom(name) if is_identifier(name) => self.reference(name, ReferenceKind::Type),
+            Type::Custom(_) => {}
+        }
+    }
+
+    fn function(&mut self, func: &Function, is_method: bool) {
+        self.attributes(&func.attributes);
+        let span = self.span(&func.name);
+        // Methods are reached through their receiver, not by name
+        if !is_method {
+            self.define(&func.name, SymbolKind::Function, span, false);
+        }
+
+        self.push_scope(ScopeKind::Function);
+        if is_method && !func.parameters.iter().any(|p| p.name == "self") {
+            self.define("self", SymbolKind::Parameter, None, false);
+        }
+        self.signature(&func.parameters, func.return_type.as_ref());
+        self.block(&func.body);
+        self.pop_scope();
+    }
+
+    /// Define the parameters of a function or lambda in the current scope
+    fn signature(&mut self, parameters: &[Parameter], return_type: Option<&Type>) {
+        for param in parameters {
+            let span = self.span(&param.name);
+            if let Some(ty) = &param.param_type {
+                self.type_annotation(ty);
+            }
+            if let Some(default) = &param.default {
+                self.expression(default);
+            }
+            self.define(&param.name, SymbolKind::Parameter, span, false);
+        }
+        if let Some(ty) = return_type {
+            self.type_annotation(ty);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.push_scope(ScopeKind::Block);
+        for statement in &block.statements {
+            self.statement(statement);
+        }
+        self.pop_scope();
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Expression(stmt) => self.expression(&stmt.expression),
+            Statement::VariableDeclaration(var) => {
+                // The names are written first but defined after the initializer
+                let bindings = match &var.pattern {
+                    Some(pattern) => self.pattern_bindings(pattern),
+                    None => vec![(var.name.as_str(), self.span(&var.name))],
+                };
+                if let Some(ty) = &var.var_type {
+                    self.type_annotation(ty);
+                }
+                self.expression(&var.initializer);
+                for (name, span) in bindings {
+                    self.define(name, SymbolKind::Variable, span, var.mutable);
+                }
+            }
+            Statement::Assignment(assign) => {
+                self.expression(&assign.target);
+                self.expression(&assign.value);
+            }
+            Statement::If(stmt) => {
+                self.expression(&stmt.condition);
+                self.block(&stmt.then_branch);
+                for (condition, block) in &stmt.elif_branches {
+                    self.expression(condition);
+                    self.block(block);
+                }
+                if let Some(block) = &stmt.else_branch {
+                    self.block(block);
+                }
+            }
+            Statement::While(stmt) => {
+                self.expression(&stmt.condition);
+                self.block(&stmt.body);
+            }
+            Statement::For(stmt) => {
+                let span = self.span(&stmt.variable);
+                self.expression(&stmt.iterable);
+                self.push_scope(ScopeKind::Block);
+                self.define(&stmt.variable, SymbolKind::Variable, span, false);
+                self.block(&stmt.body);
+                self.pop_scope();
+            }
+            Statement::Match(stmt) => {
+                self.expression(&stmt.expression);
+                for arm in &stmt.arms {
+                    self.push_scope(ScopeKind::Block);
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.statement(&arm.body);
+                    self.pop_scope();
+                }
+            }
+            Statement::Return(ret) => {
+                if let Some(value) = &ret.value {
+                    self.expression(value);
+                }
+            }
+            Statement::Try(stmt) => {
+                self.block(&stmt.body);
+                if let Some(catch) = &stmt.catch {
+                    self.push_scope(ScopeKind::Block);
+                    if let Some(variable) = &catch.variable {
+                        let span = self.span(variable);
+                        self.define(variable, SymbolKind::Variable, span, false);
+                    }
+                    self.block(&catch.body);
+                    self.pop_scope();
+                }
+                if let Some(block) = &stmt.finally {
+                    self.block(block);
+                }
+            }
+            Statement::Throw(throw) => self.expression(&throw.value),
+            Statement::Block(block) | Statement::TaskGroup(block) => self.block(block),
+            Statement::Break | Statement::Continue | Statement::Comment(_) => {}
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        for (name, span) in self.pattern_bindings(pattern) {
+            self.define(name, SymbolKind::Variable, span, false);
+        }
+    }
+
+    /// The variables a pattern binds with where they are written, after
+    /// referencing the structs it names
+    fn pattern_bindings<'a>(
+        &mut self,
+        pattern: &'a Pattern,
+    ) -> Vec<(&'a str, Option<Range<usize>>)> {
+        let mut bindings = Vec::new();
+        self.collect_pattern_bindings(pattern, &mut bindings);
+        bindings
+    }
+
+    fn collect_pattern_bindings<'a>(
+        &mut self,
+        pattern: &'a Pattern,
+        bindings: &mut Vec<(&'a str, Option<Range<usize>>)>,
+    ) {
+        match pattern {
+            Pattern::Identifier(name) => bindings.push((name, self.span(name))),
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+            Pattern::Tuple(elements) => {
+                for element in elements {
+                    self.collect_pattern_bindings(element, bindings);
+                }
+            }
+            Pattern::Array { elements, rest } => {
+                for element in elements {
+                    self.collect_pattern_bindings(element, bindings);
+                }
+                if let Some(rest) = rest {
+                    self.collect_pattern_bindings(rest, bindings);
+                }
+            }
+            Pattern::Struct { name, fields } => {
+                self.reference(name, ReferenceKind::Type);
+                for (field, pattern) in fields {
+                    // `Point { x }` binds `x` where the field is named
+                    if !matches!(pattern, Pattern::Identifier(binding) if binding == field) {
+                        self.span(field);
+                    }
+                    self.collect_pattern_bindings(pattern, bindings);
+                }
+            }
+            Pattern::Variant { value, .. } => self.collect_pattern_bindings(value, bindings),
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(name) => self.reference(name, ReferenceKind::Value),
+            Expression::FieldAccess(field) => {
+                self.expression(&field.object);
+                self.span(&field.field);
+            }
+            Expression::MethodCall(call) => {
+                self.expression(&call.object);
+                self.span(&call.method);
+                for arg in &call.arguments {
+                    self.expression(arg);
+                }
+            }
+            Expression::ModuleAccess(access) => {
+                self.span(&access.module);
+                self.span(&access.item);
+            }
+            Expression::StructInit(init) => {
+                self.reference(&init.struct_name, ReferenceKind::Value);
+                for (name, value) in &init.fields {
+                    self.span(name);
+                    self.expression(value);
+                }
+            }
+            Expression::Match(match_expr) => {
+                self.expression(&match_expr.expression);
+                for arm in &match_expr.arms {
+                    self.push_scope(ScopeKind::Block);
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.expression(&arm.expression);
+                    self.pop_scope();
+                }
+            }
+            Expression::Cast(cast) => {
+                self.expression(&cast.expression);
+                self.type_annotation(&cast.target_type);
+            }
+            Expression::Lambda(lambda) => {
+                self.push_scope(ScopeKind::Function);
+                self.signature(&lambda.parameters, lambda.return_type.as_ref());
+                match &lambda.body {
+                    LambdaBody::Expression(body) => self.expression(body),
+                    LambdaBody::Block(body) => self.block(body),
+                }
+                self.pop_scope();
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+impl Visitor for Resolver<'_> {
+    fn visit_expression(&mut self, expression: &Expression) {
+        self.expression(expression);
+    }
+}
+
+/// Tokens a name can be matched against: identifiers and keywords (which
+/// attribute names like `not` may be)
+fn is_word(kind: &TokenKind) -> bool {
+    !matches!(
+        kind,
+        TokenKind::String(_)
+            | TokenKind::InterpolationStart(_)
+            | TokenKind::InterpolationMiddle(_)
+            | TokenKind::InterpolationEnd(_)
+            | TokenKind::Char(_)
+            | TokenKind::Integer(_)
+            | TokenKind::Float(_)
+            | TokenKind::Comment
+            | TokenKind::Newline
+            | TokenKind::Indent
+            | TokenKind::Dedent
+            | TokenKind::Eof
+    )
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+pub(crate) fn primitive_name(primitive: &PrimitiveType) -> &'static str {
+    match primitive {
+        PrimitiveType::Int => "int",
+        PrimitiveType::I32 => "i32",
+        PrimitiveType::I64 => "i64",
+        PrimitiveType::U32 => "u32",
+        PrimitiveType::U64 => "u64",
+        PrimitiveType::F32 => "f32",
+        PrimitiveType::F64 => "f64",
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::Char => "char",
+        PrimitiveType::String => "string",
+    }
+}
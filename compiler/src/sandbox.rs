@@ -0,0 +1,203 @@
+// Capability policy for builtins that reach outside the interpreter
+//
+// A `Policy` decides which files a program may read or write, which hosts it
+// may connect to, which programs it may run and which environment variables
+// it may see. Builtins ask the policy before acting and fail with a
+// "Permission denied" runtime error otherwise, so untrusted code can be run
+// with only the access it needs. Importing a module file counts as reading
+// it. The default policy allows everything.
+
+use crate::error::{Result, VeyraError};
+use std::path::{Component, Path, PathBuf};
+
+/// An access a builtin is about to make
+#[derive(Debug, Clone, Copy)]
+pub enum Capability<'a> {
+    Read(&'a Path),
+    Write(&'a Path),
+    /// A host name or address, without the port
+    Network(&'a str),
+    Process(&'a str),
+    Env(&'a str),
+}
+
+/// Allowed and denied entries for one kind of access. A denied entry wins
+/// over an allowed one; anything unlisted gets the default.
+#[derive(Debug, Clone)]
+struct Rules<T> {
+    default: bool,
+    allow: Vec<T>,
+    deny: Vec<T>,
+}
+
+impl<T> Rules<T> {
+    fn new(default: bool) -> Self {
+        Self {
+            default,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+
+    fn permits(&self, matches: impl Fn(&T) -> bool) -> bool {
+        if self.deny.iter().any(&matches) {
+            return false;
+        }
+        self.default || self.allow.iter().any(matches)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Policy {
+    read: Rules<PathBuf>,
+    write: Rules<PathBuf>,
+    network: Rules<String>,
+    process: Rules<String>,
+    env: Rules<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::allow_all()
+    }
+}
+
+// The allow and deny builders are for the REPL, embedders and tools; veyc
+// runs with the default policy or with `deny_all`
+impl Policy {
+    /// Everything is allowed unless denied
+    pub fn allow_all() -> Self {
+        Self::with_default(true)
+    }
+
+    /// Nothing is allowed unless allowed explicitly
+    pub fn deny_all() -> Self {
+        Self::with_default(false)
+    }
+
+    fn with_default(default: bool) -> Self {
+        Self {
+            read: Rules::new(default),
+            write: Rules::new(default),
+            network: Rules::new(default),
+            process: Rules::new(default),
+            env: Rules::new(default),
+        }
+    }
+
+    /// Allow reading files under a directory (or the file itself)
+    #[allow(dead_code)]
+    pub fn allow_read(mut self, path: impl AsRef<Path>) -> Self {
+        self.read.allow.push(normalize(path.as_ref()));
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn deny_read(mut self, path: impl AsRef<Path>) -> Self {
+        self.read.deny.push(normalize(path.as_ref()));
+        self
+    }
+
+    /// Allow creating and writing files under a directory (or the file itself)
+    #[allow(dead_code)]
+    pub fn allow_write(mut self, path: impl AsRef<Path>) -> Self {
+        self.write.allow.push(normalize(path.as_ref()));
+        self
+    }
+
+    /// Allow connecting to a host; `*.example.com` also matches its
+    /// subdomains
+    #[allow(dead_code)]
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        self.network.allow.push(host.into().to_lowercase());
+        self
+    }
+
+    /// Allow running a program, given exactly as code names it: allowing
+    /// `git` does not allow `/tmp/evil/git`, which is another program
+    #[allow(dead_code)]
+    pub fn allow_process(mut self, program: impl Into<String>) -> Self {
+        self.process.allow.push(program.into());
+        self
+    }
+
+    /// Allow reading an environment variable; `*` matches every variable
+    #[allow(dead_code)]
+    pub fn allow_env(mut self, name: impl Into<String>) -> Self {
+        self.env.allow.push(name.into());
+        self
+    }
+
+    pub fn permits(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Read(path) => {
+                let path = normalize(path);
+                self.read.permits(|dir| path.starts_with(dir))
+            }
+            Capability::Write(path) => {
+                let path = normalize(path);
+                self.write.permits(|dir| path.starts_with(dir))
+            }
+            Capability::Network(host) => {
+                let host = host.to_lowercase();
+                self.network.permits(|pattern| host_matches(pattern, &host))
+            }
+            Capability::Process(program) => self.process.permits(|allowed| allowed == program),
+            Capability::Env(name) => self
+                .env
+                .permits(|allowed| allowed == "*" || allowed == name),
+        }
+    }
+
+    /// Fail with a runtime error unless the capability is permitted
+    pub fn check(&self, capability: Capability) -> Result<()> {
+        if self.permits(capability) {
+            return Ok(());
+        }
+        let action = match capability {
+            Capability::Read(path) => format!("reading '{}'", path.display()),
+            Capability::Write(path) => format!("writing '{}'", path.display()),
+            Capability::Network(host) => format!("connecting to '{}'", host),
+            Capability::Process(program) => format!("running '{}'", program),
+            Capability::Env(name) => format!("reading environment variable '{}'", name),
+        };
+        Err(VeyraError::runtime_error(format!(
+            "Permission denied: {} is not allowed by the sandbox policy",
+            action
+        )))
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+        None => pattern == host,
+    }
+}
+
+/// Absolute form of a path with `.` and `..` removed and symlinks resolved
+/// where it exists, so `allowed/../secret` does not pass as `allowed`
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    // A new file in an existing directory: resolve the directory's symlinks
+    match (normalized.parent(), normalized.file_name()) {
+        (Some(parent), Some(name)) => match parent.canonicalize() {
+            Ok(parent) => parent.join(name),
+            Err(_) => normalized,
+        },
+        _ => normalized,
+    }
+}
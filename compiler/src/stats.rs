@@ -0,0 +1,255 @@
+// Code statistics
+//
+// `veyc stats` summarizes a codebase for keeping an eye on its health: how
+// many lines are code, comments and blank, how many functions, structs and
+// actors it defines, how complex its functions are and which are longest.
+// The numbers are only printed; nothing is recorded or sent anywhere.
+//
+// Declarations and complexity come from walking the AST with a `Visitor`.
+// The AST has no positions, so line counts come from the tokens.
+
+use crate::ast::*;
+use crate::error::Result;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parser::Parser;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// The ranges of cyclomatic complexity in the distribution, by their
+/// largest value
+pub const COMPLEXITY_RANGES: [(usize, &str); 4] =
+    [(5, "1-5"), (10, "6-10"), (20, "11-20"), (usize::MAX, "21+")];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stats {
+    pub files: usize,
+    pub lines: usize,
+    pub code_lines: usize,
+    /// Lines with comments and no code
+    pub comment_lines: usize,
+    pub blank_lines: usize,
+    /// Functions, including impl and actor methods
+    pub functions: Vec<FunctionStats>,
+    pub structs: usize,
+    pub actors: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionStats {
+    /// The function's name, or `Type.name` for a method
+    pub name: String,
+    pub file: PathBuf,
+    /// The line of its `fn`, from 1
+    pub line: usize,
+    /// Lines from its `fn` to its closing brace
+    pub lines: usize,
+    /// One more than the number of decisions in its body: conditions,
+    /// loops, extra match arms and `and`/`or` operators
+    pub complexity: usize,
+}
+
+impl Stats {
+    /// Add the statistics of a source file. Nothing is added for a file
+    /// with a syntax error.
+    pub fn add_file(&mut self, file: PathBuf, source: &str) -> Result<()> {
+        let tokens = Lexer::new(source).tokenize_with_comments()?;
+        let program = Parser::new(tokens.clone()).parse()?;
+
+        let mut code = HashSet::new();
+        let mut comments = HashSet::new();
+        for token in &tokens {
+            let lines = match token.kind {
+                TokenKind::Newline | TokenKind::Eof => continue,
+                TokenKind::Comment => &mut comments,
+                _ => &mut code,
+            };
+            lines.extend(token.line..=token.line + token.lexeme.matches('\n').count());
+        }
+        let total = source.lines().count();
+        let comment_only = comments.difference(&code).count();
+        self.files += 1;
+        self.lines += total;
+        self.code_lines += code.len();
+        self.comment_lines += comment_only;
+        self.blank_lines += total.saturating_sub(code.len() + comment_only);
+
+        let mut declarations = Declarations::default();
+        declarations.visit_program(&program);
+        self.structs += declarations.structs;
+        self.actors += declarations.actors;
+        let spans = function_spans(&tokens);
+        for (function, (start, end)) in declarations.functions.into_iter().zip(spans) {
+            let Some((name, complexity)) = function else {
+                continue;
+            };
+            self.functions.push(FunctionStats {
+                name,
+                file: file.clone(),
+                line: start,
+                lines: end - start + 1,
+                complexity,
+            });
+        }
+        Ok(())
+    }
+
+    /// The share of non-blank lines that are comments, from 0 to 1
+    pub fn comment_ratio(&self) -> f64 {
+        let non_blank = self.code_lines + self.comment_lines;
+        if non_blank == 0 {
+            return 0.0;
+        }
+        self.comment_lines as f64 / non_blank as f64
+    }
+
+    /// How many functions fall into each of `COMPLEXITY_RANGES`
+    pub fn complexity_distribution(&self) -> Vec<(&'static str, usize)> {
+        let mut counts = COMPLEXITY_RANGES.map(|(_, label)| (label, 0));
+        for function in &self.functions {
+            let range = COMPLEXITY_RANGES
+                .iter()
+                .position(|(largest, _)| function.complexity <= *largest)
+                .unwrap_or(COMPLEXITY_RANGES.len() - 1);
+            counts[range].1 += 1;
+        }
+        counts.to_vec()
+    }
+
+    /// The `count` longest functions, longest first
+    pub fn longest_functions(&self, count: usize) -> Vec<&FunctionStats> {
+        let mut functions: Vec<&FunctionStats> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.lines.cmp(&a.lines).then(a.line.cmp(&b.line)));
+        functions.truncate(count);
+        functions
+    }
+}
+
+/// The structs and actors of a program, and its functions in source order
+/// with their complexity
+#[derive(Default)]
+struct Declarations {
+    /// The impl, trait or actor whose methods are being visited
+    owner: Option<String>,
+    /// `None` for a trait method without a body, which is not counted but
+    /// still has a `fn` in the source
+    functions: Vec<Option<(String, usize)>>,
+    structs: usize,
+    actors: usize,
+}
+
+impl Visitor for Declarations {
+    fn visit_item(&mut self, item: &Item) {
+        self.owner = match item {
+            Item::Impl(imp) => Some(imp.target.clone()),
+            Item::Trait(tr) => Some(tr.name.clone()),
+            Item::Actor(actor) => Some(actor.name.clone()),
+            _ => None,
+        };
+        match item {
+            Item::Struct(_) => self.structs += 1,
+            Item::Actor(_) => self.actors += 1,
+            Item::Trait(tr) => {
+                for method in &tr.methods {
+                    if method.has_default {
+                        self.visit_function(&method.function);
+                    } else {
+                        self.functions.push(None);
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        walk_item(self, item);
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        let name = match &self.owner {
+            Some(owner) => format!("{}.{}", owner, function.name),
+            None => function.name.clone(),
+        };
+        let mut complexity = Complexity(1);
+        complexity.visit_function(function);
+        self.functions.push(Some((name, complexity.0)));
+    }
+
+    // Top-level statements are not in any function
+    fn visit_statement(&mut self, _statement: &Statement) {}
+}
+
+/// Counts the decisions in a function
+struct Complexity(usize);
+
+impl Visitor for Complexity {
+    fn visit_statement(&mut self, statement: &Statement) {
+        self.0 += match statement {
+            Statement::If(stmt) => 1 + stmt.elif_branches.len(),
+            Statement::While(_) | Statement::For(_) => 1,
+            Statement::Try(stmt) => usize::from(stmt.catch.is_some()),
+            Statement::Match(stmt) => {
+                stmt.arms.len().saturating_sub(1)
+                    + stmt.arms.iter().filter(|arm| arm.guard.is_some()).count()
+            }
+            _ => 0,
+        };
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        self.0 += match expression {
+            Expression::Binary(binary) => {
+                matches!(binary.operator, BinaryOperator::And | BinaryOperator::Or) as usize
+            }
+            Expression::If(_) => 1,
+            Expression::Match(expr) => {
+                expr.arms.len().saturating_sub(1)
+                    + expr.arms.iter().filter(|arm| arm.guard.is_some()).count()
+            }
+            _ => 0,
+        };
+        walk_expression(self, expression);
+    }
+}
+
+/// The first and last line of every function, in source order: from its
+/// `fn` to the brace closing its body
+fn function_spans(tokens: &[Token]) -> Vec<(usize, usize)> {
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+    let mut spans = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Fn
+            || tokens.get(i + 1).map(|next| &next.kind) != Some(&TokenKind::Identifier)
+        {
+            continue;
+        }
+        // The body is the first brace outside the parameter list, which
+        // may hold dictionary defaults
+        let mut parens = 0usize;
+        let mut braces = 0usize;
+        let mut end = token.line;
+        for token in &tokens[i + 1..] {
+            match token.kind {
+                // A body without braces ends before the next function
+                TokenKind::Fn if braces == 0 => break,
+                TokenKind::LeftParen => parens += 1,
+                TokenKind::RightParen => parens = parens.saturating_sub(1),
+                TokenKind::LeftBrace if parens == 0 => braces += 1,
+                TokenKind::RightBrace if parens == 0 => {
+                    braces = braces.saturating_sub(1);
+                    if braces == 0 {
+                        end = token.line;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            end = token.line;
+        }
+        spans.push((token.line, end));
+    }
+    spans
+}
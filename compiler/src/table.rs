@@ -0,0 +1,163 @@
+// Dictionaries and sets, keyed by Veyra values
+//
+// A key's hash, from `Interpreter::typed_key`, only picks the bucket the key
+// goes in. Keys in one bucket are told apart by the interpreter with
+// `values_equal`, which calls a struct's `__eq__`, so structs whose
+// `__hash__` values collide stay separate keys. Each entry keeps the key
+// value itself, which is what printing and iteration show.
+
+use crate::interpreter::{quote, Value};
+use std::collections::HashMap;
+
+/// The entries of a dictionary, `Table<Value>`, or of a set, `Table<()>`,
+/// by the hash of their key
+#[derive(Debug, Clone)]
+pub struct Table<V> {
+    buckets: HashMap<String, Vec<(Value, V)>>,
+    len: usize,
+}
+
+pub type Dictionary = Table<Value>;
+pub type Set = Table<()>;
+
+impl<V> Default for Table<V> {
+    fn default() -> Self {
+        Self {
+            buckets: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<V> Table<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Every key and its value, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &V)> {
+        self.buckets
+            .values()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Each hash and the entries whose keys have it
+    pub(crate) fn buckets(&self) -> impl Iterator<Item = (&str, &[(Value, V)])> {
+        self.buckets
+            .iter()
+            .map(|(hash, entries)| (hash.as_str(), entries.as_slice()))
+    }
+
+    /// The entries whose keys have `hash`, in the order they were added
+    pub(crate) fn bucket(&self, hash: &str) -> &[(Value, V)] {
+        self.buckets.get(hash).map_or(&[], Vec::as_slice)
+    }
+
+    /// The value of the entry at `position` in the bucket of `hash`
+    pub(crate) fn value_mut(&mut self, hash: &str, position: usize) -> &mut V {
+        &mut self.buckets.get_mut(hash).unwrap()[position].1
+    }
+
+    /// Add a key that is not in the table yet
+    pub(crate) fn push(&mut self, hash: String, key: Value, value: V) -> &mut V {
+        let bucket = self.buckets.entry(hash).or_default();
+        bucket.push((key, value));
+        self.len += 1;
+        &mut bucket.last_mut().unwrap().1
+    }
+
+    /// The same keys, each with `f` applied to its value
+    pub(crate) fn map_values<W>(&self, mut f: impl FnMut(&V) -> W) -> Table<W> {
+        Table {
+            buckets: self
+                .buckets
+                .iter()
+                .map(|(hash, entries)| {
+                    let entries = entries.iter().map(|(k, v)| (k.clone(), f(v))).collect();
+                    (hash.clone(), entries)
+                })
+                .collect(),
+            len: self.len,
+        }
+    }
+
+    /// Add or replace the entry of a string key, which needs no interpreter
+    /// to compare with the keys it shares a bucket with
+    pub fn insert_str(&mut self, key: String, value: V) {
+        let hash = quote(&key, '"');
+        let bucket = self.buckets.entry(hash).or_default();
+        match bucket.iter_mut().find(|(k, _)| is_text(k, &key)) {
+            Some(entry) => entry.1 = value,
+            None => {
+                bucket.push((Value::String(key), value));
+                self.len += 1;
+            }
+        }
+    }
+
+    /// The value of a string key
+    pub fn get_str(&self, key: &str) -> Option<&V> {
+        self.bucket(&quote(key, '"'))
+            .iter()
+            .find(|(k, _)| is_text(k, key))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Whether a key is the string, or the char, `text`
+fn is_text(key: &Value, text: &str) -> bool {
+    match key {
+        Value::String(s) => s == text,
+        Value::Char(c) => text.chars().eq(std::iter::once(*c)),
+        _ => false,
+    }
+}
+
+impl<V: PartialEq> PartialEq for Table<V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self.buckets().all(|(hash, entries)| {
+                entries
+                    .iter()
+                    .all(|entry| other.bucket(hash).contains(entry))
+            })
+    }
+}
+
+impl<V> IntoIterator for Table<V> {
+    type Item = (Value, V);
+    type IntoIter =
+        std::iter::Flatten<std::collections::hash_map::IntoValues<String, Vec<(Value, V)>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.buckets.into_values().flatten()
+    }
+}
+
+impl<V> FromIterator<(String, V)> for Table<V> {
+    /// A table of string keys
+    fn from_iter<I: IntoIterator<Item = (String, V)>>(entries: I) -> Self {
+        let mut table = Self::new();
+        for (key, value) in entries {
+            table.insert_str(key, value);
+        }
+        table
+    }
+}
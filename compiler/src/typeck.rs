@@ -0,0 +1,1183 @@
+// Type checking
+//
+// `check` finds mistakes that would otherwise only surface when the program
+// runs: names that are never defined, calls with the wrong number of
+// arguments, and values that do not match a type annotation. It runs after
+// parsing, as part of `veyc check`.
+//
+// Annotations are optional: the types of unannotated variables and the
+// return types of unannotated functions are inferred from their
+// initializers and `return` values. A value is only checked when its type
+// is known; anything else has the unknown type, which matches every
+// annotation. Calls are checked against the program's own functions and
+// struct methods; the arity of builtins is left to the interpreter.
+//
+// `analyze` also keeps the type of each variable and the signature of each
+// function it saw, for editors to show on hover.
+//
+// Undefined names come from `resolve`. The AST has no positions, so like
+// `resolve` the checker finds where each name is written by matching names
+// against the token stream in source order.
+
+use crate::ast::*;
+use crate::builtins::BUILTINS;
+use crate::lexer::RangedToken;
+use crate::resolve::{self, Spans};
+use crate::suggest;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Rounds of inference over the whole program, so that functions can use
+/// the inferred return types of functions defined after them
+const INFERENCE_ROUNDS: usize = 3;
+
+/// A mistake found without running the program
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    /// `undefined-name`, `wrong-arguments`, `unknown-field` or
+    /// `type-mismatch`
+    pub code: &'static str,
+    pub message: String,
+    /// Where the name or keyword the mistake was found at is written
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// What the checker knows about the type of a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    Bool,
+    Char,
+    String,
+    None,
+    Array(Box<Ty>),
+    /// `T?`: a `T` or None
+    Optional(Box<Ty>),
+    Function,
+    Struct(String),
+    /// Could be anything; matches every type
+    Unknown,
+}
+
+impl Ty {
+    /// The type an annotation names. Types the checker does not model,
+    /// such as those of libraries, are unknown.
+    pub fn from_annotation(ty: &Type, structs: &HashSet<String>) -> Ty {
+        match ty {
+            Type::Primitive(primitive) => match primitive {
+                PrimitiveType::Int
+                | PrimitiveType::I32
+                | PrimitiveType::I64
+                | PrimitiveType::U32
+                | PrimitiveType::U64 => Ty::Int,
+                PrimitiveType::F32 | PrimitiveType::F64 => Ty::Float,
+                PrimitiveType::Bool => Ty::Bool,
+                PrimitiveType::Char => Ty::Char,
+                PrimitiveType::String => Ty::String,
+            },
+            Type::Array { element_type, .. } => {
+                Ty::Array(Box::new(Ty::from_annotation(element_type, structs)))
+            }
+            Type::Optional(inner) => Ty::Optional(Box::new(Ty::from_annotation(inner, structs))),
+            Type::Function { .. } => Ty::Function,
+            Type::Custom(name) if name == "float" => Ty::Float,
+            Type::Custom(name) if name == "array" => Ty::Array(Box::new(Ty::Unknown)),
+            Type::Custom(name) if structs.contains(name) => Ty::Struct(name.clone()),
+            Type::Reference { .. } | Type::Custom(_) => Ty::Unknown,
+        }
+    }
+
+    /// Whether a value of type `actual` may be used where `self` is
+    /// expected. An int may be used as a float.
+    pub fn accepts(&self, actual: &Ty) -> bool {
+        match (self, actual) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Float, Ty::Int) => true,
+            (Ty::Optional(_), Ty::None) => true,
+            (Ty::Optional(expected), Ty::Optional(actual)) => expected.accepts(actual),
+            (Ty::Optional(expected), actual) => expected.accepts(actual),
+            (Ty::Array(expected), Ty::Array(actual)) => expected.accepts(actual),
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    /// The type of a value that is either of two types: an int or a float
+    /// is a float, a `T` or None is a `T?`, and anything else is unknown
+    pub fn join(self, other: Ty) -> Ty {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Ty::Unknown, _) | (_, Ty::Unknown) => Ty::Unknown,
+            (Ty::Int, Ty::Float) | (Ty::Float, Ty::Int) => Ty::Float,
+            (Ty::None, Ty::Optional(inner)) | (Ty::Optional(inner), Ty::None) => {
+                Ty::Optional(inner)
+            }
+            (Ty::None, ty) | (ty, Ty::None) => Ty::Optional(Box::new(ty)),
+            (Ty::Optional(a), Ty::Optional(b)) => Ty::Optional(Box::new(a.join(*b))),
+            (Ty::Optional(a), b) | (b, Ty::Optional(a)) => Ty::Optional(Box::new(a.join(b))),
+            (Ty::Array(a), Ty::Array(b)) => Ty::Array(Box::new(a.join(*b))),
+            _ => Ty::Unknown,
+        }
+    }
+
+    /// The type of a value that is any of a group of types
+    fn join_all(types: impl IntoIterator<Item = Ty>) -> Ty {
+        types.into_iter().reduce(Ty::join).unwrap_or(Ty::Unknown)
+    }
+
+    /// The type a builtin's documented signature says it returns
+    fn of_builtin(name: &str) -> Ty {
+        if name == "range" {
+            return Ty::Array(Box::new(Ty::Int));
+        }
+        let returns = BUILTINS
+            .iter()
+            .find(|doc| doc.name == name)
+            .and_then(|doc| doc.signature.split_once(" -> "))
+            .map(|(_, returns)| returns);
+        match returns {
+            Some("int") => Ty::Int,
+            Some("float") => Ty::Float,
+            Some("bool") => Ty::Bool,
+            Some("char") => Ty::Char,
+            Some("string") => Ty::String,
+            Some("array") => Ty::Array(Box::new(Ty::Unknown)),
+            _ => Ty::Unknown,
+        }
+    }
+}
+
+/// Written the way annotations are
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => f.write_str("int"),
+            Ty::Float => f.write_str("float"),
+            Ty::Bool => f.write_str("bool"),
+            Ty::Char => f.write_str("char"),
+            Ty::String => f.write_str("string"),
+            Ty::None => f.write_str("None"),
+            Ty::Array(element) if **element == Ty::Unknown => f.write_str("array"),
+            Ty::Array(element) => write!(f, "[{}]", element),
+            Ty::Optional(inner) => write!(f, "{}?", inner),
+            Ty::Function => f.write_str("function"),
+            Ty::Struct(name) => f.write_str(name),
+            Ty::Unknown => f.write_str("any"),
+        }
+    }
+}
+
+/// What checking a program found
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Mistakes, in source order
+    pub errors: Vec<TypeError>,
+    /// The declared or inferred type of each variable and parameter whose
+    /// type is known, as `name: type`, and the signature of each function,
+    /// by the character offset of the name where it is defined
+    #[allow(dead_code)]
+    pub types: HashMap<usize, String>,
+}
+
+/// Check a program. `tokens` is the ranged token stream of the source it
+/// was parsed from and supplies the positions.
+pub fn check(program: &Program, tokens: &[RangedToken]) -> Vec<TypeError> {
+    analyze(program, tokens).errors
+}
+
+/// Check a program, keeping the types it infers
+pub fn analyze(program: &Program, tokens: &[RangedToken]) -> Analysis {
+    // Earlier rounds only infer return types
+    let mut returns = HashMap::new();
+    for _ in 1..INFERENCE_ROUNDS {
+        let mut checker = Checker::new(program, tokens, returns.clone());
+        for item in &program.items {
+            checker.item(item);
+        }
+        if checker.returns == returns {
+            break;
+        }
+        returns = checker.returns;
+    }
+
+    let mut checker = Checker::new(program, tokens, returns);
+    for item in &program.items {
+        checker.item(item);
+    }
+    let mut errors = checker.errors;
+    errors.extend(undefined_names(program, tokens));
+    errors.sort_by_key(|error| (error.line, error.column));
+    Analysis {
+        errors,
+        types: checker.types,
+    }
+}
+
+/// Uses of names that are neither defined nor builtin
+fn undefined_names(program: &Program, tokens: &[RangedToken]) -> Vec<TypeError> {
+    let names = resolve::resolve(program, tokens);
+    names
+        .undefined()
+        .map(|reference| {
+            let token = reference
+                .span
+                .as_ref()
+                .and_then(|span| tokens.iter().find(|(_, range)| range.start == span.start));
+            let similar =
+                suggest::similar_names(&reference.name, names.visible_names(reference.scope));
+            error(
+                "undefined-name",
+                format!(
+                    "'{}' is not defined{}",
+                    reference.name,
+                    suggest::did_you_mean(&similar)
+                ),
+                token,
+            )
+        })
+        .collect()
+}
+
+fn error(code: &'static str, message: String, at: Option<&RangedToken>) -> TypeError {
+    TypeError {
+        code,
+        message,
+        line: at.map(|(token, _)| token.line),
+        column: at.map(|(token, _)| token.column),
+    }
+}
+
+/// The function whose body is being checked
+struct Body {
+    name: String,
+    return_type: Option<Ty>,
+    /// Types of the values returned so far
+    returns: Vec<Ty>,
+}
+
+/// A variable in scope
+#[derive(Clone)]
+struct Variable {
+    ty: Ty,
+    /// Whether the type comes from an annotation rather than inference;
+    /// only annotated variables have assignments checked
+    declared: bool,
+    /// Offset of the name where an inferred variable is defined, to keep
+    /// its type up to date as it widens
+    defined: Option<usize>,
+}
+
+/// Identifies a function by where it is in the program
+type FunctionId = *const Function;
+
+struct Checker<'a> {
+    spans: Spans<'a>,
+    /// Top-level functions, for checking calls
+    functions: HashMap<&'a str, &'a Function>,
+    /// Fields of each struct, with their types
+    structs: HashMap<&'a str, Vec<(&'a str, Ty)>>,
+    /// Methods of each struct from its `impl` blocks
+    methods: HashMap<(&'a str, &'a str), &'a Function>,
+    struct_names: HashSet<String>,
+    /// Variables of each enclosing scope
+    scopes: Vec<HashMap<String, Variable>>,
+    bodies: Vec<Body>,
+    /// Return types inferred for functions without a declared one
+    returns: HashMap<FunctionId, Ty>,
+    types: HashMap<usize, String>,
+    errors: Vec<TypeError>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(
+        program: &'a Program,
+        tokens: &'a [RangedToken],
+        returns: HashMap<FunctionId, Ty>,
+    ) -> Self {
+        let struct_names: HashSet<String> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Struct(s) => Some(s.name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let mut functions = HashMap::new();
+        let mut structs = HashMap::new();
+        let mut methods = HashMap::new();
+        for item in &program.items {
+            match item {
+                Item::Function(func) => {
+                    functions.insert(func.name.as_str(), func);
+                }
+                Item::Struct(s) => {
+                    let fields = s
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            let ty = Ty::from_annotation(&field.field_type, &struct_names);
+                            (field.name.as_str(), ty)
+                        })
+                        .collect();
+                    structs.insert(s.name.as_str(), fields);
+                }
+                Item::Impl(imp) => {
+                    for method in &imp.methods {
+                        methods.insert((imp.target.as_str(), method.name.as_str()), method);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            spans: Spans::new(tokens),
+            functions,
+            structs,
+            methods,
+            struct_names,
+            scopes: vec![HashMap::new()],
+            bodies: Vec::new(),
+            returns,
+            types: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The token of the next occurrence of `name`
+    fn at(&mut self, name: &str) -> Option<&'a RangedToken> {
+        self.spans.next(name)
+    }
+
+    fn report(&mut self, code: &'static str, message: String, at: Option<&RangedToken>) {
+        self.errors.push(error(code, message, at));
+    }
+
+    /// Report a value of type `actual` given where `expected` is required
+    fn expect(
+        &mut self,
+        expected: &Ty,
+        actual: &Ty,
+        what: impl fmt::Display,
+        at: Option<&RangedToken>,
+    ) {
+        if !expected.accepts(actual) {
+            self.report(
+                "type-mismatch",
+                format!("{} should be {}, found {}", what, expected, actual),
+                at,
+            );
+        }
+    }
+
+    fn annotation(&mut self, ty: &Type) -> Ty {
+        self.type_names(ty);
+        Ty::from_annotation(ty, &self.struct_names)
+    }
+
+    /// Step over the names an annotation is written with
+    fn type_names(&mut self, ty: &Type) {
+        match ty {
+            Type::Primitive(primitive) => {
+                self.at(resolve::primitive_name(primitive));
+            }
+            Type::Array { element_type, .. } => self.type_names(element_type),
+            Type::Optional(inner) => self.type_names(inner),
+            Type::Reference { target, .. } => self.type_names(target),
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                for parameter in parameters {
+                    self.type_names(parameter);
+                }
+                self.type_names(return_type);
+            }
+            Type::Custom(name) if resolve::is_identifier(name) => {
+                self.at(name);
+            }
+            Type::Custom(_) => {}
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            self.at(&attribute.name);
+            for arg in &attribute.args {
+                match arg {
+                    AttributeArg::KeyValue(key, _) => {
+                        self.at(key);
+                    }
+                    AttributeArg::Nested(nested) => self.attributes(std::slice::from_ref(nested)),
+                    AttributeArg::Literal(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Define a variable of a declared type
+    fn define(&mut self, name: &str, ty: Ty) {
+        let variable = Variable {
+            ty,
+            declared: true,
+            defined: None,
+        };
+        self.bind(name, variable);
+    }
+
+    /// Define a variable of an inferred type, named at `at`
+    fn infer(&mut self, name: &str, ty: Ty, at: Option<&RangedToken>) {
+        self.record(at, name, &ty);
+        let variable = Variable {
+            ty,
+            declared: false,
+            defined: at.map(|(_, range)| range.start),
+        };
+        self.bind(name, variable);
+    }
+
+    fn bind(&mut self, name: &str, variable: Variable) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), variable);
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Widen the inferred type of a variable to cover `ty` too
+    fn widen(&mut self, name: &str, ty: Ty) {
+        let scope = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name));
+        let Some(variable) = scope else {
+            return;
+        };
+        variable.ty = variable.ty.clone().join(ty);
+        if let Some(offset) = variable.defined {
+            match &variable.ty {
+                Ty::Unknown => self.types.remove(&offset),
+                ty => self.types.insert(offset, format!("{}: {}", name, ty)),
+            };
+        }
+    }
+
+    /// The type of the value of a variable, if it is in scope
+    fn type_of(&self, name: &str) -> Option<Ty> {
+        self.variable(name).map(|variable| variable.ty.clone())
+    }
+
+    /// Keep the type of the variable defined at `at`, for hovering
+    fn record(&mut self, at: Option<&RangedToken>, name: &str, ty: &Ty) {
+        if let (Some((_, range)), false) = (at, *ty == Ty::Unknown) {
+            self.types.insert(range.start, format!("{}: {}", name, ty));
+        }
+    }
+
+    fn item(&mut self, item: &'a Item) {
+        match item {
+            Item::Function(func) => self.function(func, None),
+            Item::Struct(s) => {
+                self.attributes(&s.attributes);
+                self.at(&s.name);
+                for field in &s.fields {
+                    self.at(&field.name);
+                    self.type_names(&field.field_type);
+                }
+            }
+            Item::Impl(imp) => {
+                self.attributes(&imp.attributes);
+                if let Some(trait_name) = &imp.trait_name {
+                    self.at(trait_name);
+                }
+                self.at(&imp.target);
+                let target = match self.struct_names.contains(&imp.target) {
+                    true => Ty::Struct(imp.target.clone()),
+                    false => Ty::Unknown,
+                };
+                for method in &imp.methods {
+                    self.function(method, Some(target.clone()));
+                }
+            }
+            Item::Trait(tr) => {
+                self.attributes(&tr.attributes);
+                self.at(&tr.name);
+                for method in &tr.methods {
+                    self.function(&method.function, Some(Ty::Unknown));
+                }
+            }
+            Item::Actor(actor) => {
+                self.attributes(&actor.attributes);
+                self.at(&actor.name);
+                self.scopes.push(HashMap::new());
+                for field in &actor.fields {
+                    self.at(&field.name);
+                    let ty = self.annotation(&field.field_type);
+                    self.define(&field.name, ty);
+                }
+                for method in &actor.methods {
+                    self.function(method, Some(Ty::Unknown));
+                }
+                self.scopes.pop();
+            }
+            Item::Import(import) => {
+                for segment in &import.path {
+                    self.at(segment);
+                }
+                for name in import.items.iter().flatten().chain(&import.alias) {
+                    self.at(name);
+                }
+            }
+            Item::Statement(statement) => self.statement(statement),
+        }
+    }
+
+    /// Check a function; methods have the type of their receiver
+    fn function(&mut self, func: &'a Function, receiver: Option<Ty>) {
+        self.attributes(&func.attributes);
+        let at = self.at(&func.name);
+        self.scopes.push(HashMap::new());
+        if let Some(receiver) = receiver {
+            self.define("self", receiver);
+        }
+        let return_type = self.signature(&func.parameters, func.return_type.as_ref());
+        self.bodies.push(Body {
+            name: func.name.clone(),
+            return_type,
+            returns: Vec::new(),
+        });
+        self.block(&func.body);
+        let body = self.bodies.pop().expect("function body");
+        self.scopes.pop();
+
+        if func.return_type.is_none() {
+            // Falling off the end returns None
+            let mut returns = body.returns;
+            if !always_returns(&func.body) {
+                returns.push(Ty::None);
+            }
+            self.returns.insert(func, Ty::join_all(returns));
+        }
+        if let Some((_, range)) = at {
+            let signature = self.describe(func);
+            self.types.insert(range.start, signature);
+        }
+    }
+
+    /// The signature of a function, with the types the checker knows
+    fn describe(&self, func: &Function) -> String {
+        let parameters: Vec<String> = func
+            .parameters
+            .iter()
+            .map(|param| match &param.param_type {
+                Some(ty) => format!(
+                    "{}: {}",
+                    param.name,
+                    Ty::from_annotation(ty, &self.struct_names)
+                ),
+                None => param.name.clone(),
+            })
+            .collect();
+        let signature = format!("fn {}({})", func.name, parameters.join(", "));
+        match self.return_type(func) {
+            Ty::Unknown => signature,
+            ty => format!("{} -> {}", signature, ty),
+        }
+    }
+
+    /// Define the parameters of a function or lambda in the current scope,
+    /// returning its declared return type
+    fn signature(&mut self, parameters: &[Parameter], return_type: Option<&Type>) -> Option<Ty> {
+        for param in parameters {
+            let at = self.at(&param.name);
+            let ty = match &param.param_type {
+                Some(ty) => self.annotation(ty),
+                None => Ty::Unknown,
+            };
+            self.record(at, &param.name, &ty);
+            if let Some(default) = &param.default {
+                let actual = self.expression(default);
+                self.expect(
+                    &ty,
+                    &actual,
+                    format_args!("Default of '{}'", param.name),
+                    at,
+                );
+            }
+            self.define(&param.name, ty);
+        }
+        return_type.map(|ty| self.annotation(ty))
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        for statement in &block.statements {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Expression(stmt) => {
+                self.expression(&stmt.expression);
+            }
+            Statement::VariableDeclaration(var) => {
+                let at = match &var.pattern {
+                    Some(pattern) => {
+                        self.pattern_names(pattern);
+                        None
+                    }
+                    None => self.at(&var.name),
+                };
+                let declared = var.var_type.as_ref().map(|ty| self.annotation(ty));
+                let actual = self.expression(&var.initializer);
+                if let Some(declared) = &declared {
+                    self.expect(declared, &actual, format_args!("'{}'", var.name), at);
+                }
+                match (&var.pattern, declared) {
+                    (Some(pattern), _) => self.define_pattern(pattern),
+                    (None, Some(declared)) => {
+                        self.record(at, &var.name, &declared);
+                        self.define(&var.name, declared);
+                    }
+                    // A variable that starts out as None is usually given
+                    // its real value later
+                    (None, None) if var.mutable && actual == Ty::None => {
+                        self.infer(&var.name, Ty::Unknown, None)
+                    }
+                    (None, None) => self.infer(&var.name, actual, at),
+                }
+            }
+            Statement::Assignment(assign) => {
+                let (target, at) = match &assign.target {
+                    Expression::Identifier(name) => {
+                        let at = self.at(name);
+                        let Some(variable) = self.variable(name).cloned() else {
+                            self.expression(&assign.value);
+                            return;
+                        };
+                        if !variable.declared {
+                            // An inferred type grows to cover every value
+                            // assigned to the variable
+                            let value = self.expression(&assign.value);
+                            let value = match &assign.operator {
+                                AssignmentOperator::Assign => value,
+                                operator => compound_type(operator, &variable.ty, &value),
+                            };
+                            self.widen(name, value);
+                            return;
+                        }
+                        (format!("'{}'", name), (variable.ty, at))
+                    }
+                    Expression::FieldAccess(access) => {
+                        self.expression(&access.object);
+                        let object = self.expression_type_of(&access.object);
+                        let at = self.at(&access.field);
+                        let ty = self.field(&object, &access.field, at);
+                        (format!("Field '{}'", access.field), (ty, at))
+                    }
+                    other => {
+                        self.expression(other);
+                        (String::new(), (Ty::Unknown, None))
+                    }
+                };
+                let (declared, at) = at;
+                let actual = self.expression(&assign.value);
+                if assign.operator == AssignmentOperator::Assign {
+                    self.expect(&declared, &actual, target, at);
+                }
+            }
+            Statement::If(stmt) => {
+                self.expression(&stmt.condition);
+                self.block(&stmt.then_branch);
+                for (condition, block) in &stmt.elif_branches {
+                    self.expression(condition);
+                    self.block(block);
+                }
+                if let Some(block) = &stmt.else_branch {
+                    self.block(block);
+                }
+            }
+            Statement::While(stmt) => {
+                self.expression(&stmt.condition);
+                self.block(&stmt.body);
+            }
+            Statement::For(stmt) => {
+                let at = self.at(&stmt.variable);
+                let element = match self.expression(&stmt.iterable) {
+                    Ty::Array(element) => *element,
+                    Ty::String => Ty::Char,
+                    _ => Ty::Unknown,
+                };
+                self.scopes.push(HashMap::new());
+                self.infer(&stmt.variable, element, at);
+                self.block(&stmt.body);
+                self.scopes.pop();
+            }
+            Statement::Match(stmt) => {
+                self.expression(&stmt.expression);
+                for arm in &stmt.arms {
+                    self.scopes.push(HashMap::new());
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    self.statement(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Statement::Return(ret) => {
+                let Some(Body {
+                    name, return_type, ..
+                }) = self.bodies.last()
+                else {
+                    if let Some(value) = &ret.value {
+                        self.expression(value);
+                    }
+                    return;
+                };
+                let (name, return_type) = (name.clone(), return_type.clone());
+                let at = match return_type {
+                    Some(_) => self.at("return"),
+                    None => None,
+                };
+                let actual = match &ret.value {
+                    Some(value) => self.expression(value),
+                    None => Ty::None,
+                };
+                if let Some(body) = self.bodies.last_mut() {
+                    body.returns.push(actual.clone());
+                }
+                if let Some(expected) = return_type {
+                    self.expect(
+                        &expected,
+                        &actual,
+                        format_args!("Return value of '{}'", name),
+                        at,
+                    );
+                }
+            }
+            Statement::Try(stmt) => {
+                self.block(&stmt.body);
+                if let Some(catch) = &stmt.catch {
+                    self.scopes.push(HashMap::new());
+                    if let Some(variable) = &catch.variable {
+                        let at = self.at(variable);
+                        self.infer(variable, Ty::Unknown, at);
+                    }
+                    self.block(&catch.body);
+                    self.scopes.pop();
+                }
+                if let Some(block) = &stmt.finally {
+                    self.block(block);
+                }
+            }
+            Statement::Throw(throw) => {
+                self.expression(&throw.value);
+            }
+            Statement::Block(block) | Statement::TaskGroup(block) => self.block(block),
+            Statement::Break | Statement::Continue | Statement::Comment(_) => {}
+        }
+    }
+
+    /// Step over the names a pattern binds and the structs it names
+    fn pattern_names(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier(name) => {
+                self.at(name);
+            }
+            Pattern::Literal(_) | Pattern::Wildcard => {}
+            Pattern::Tuple(elements) => elements.iter().for_each(|p| self.pattern_names(p)),
+            Pattern::Array { elements, rest } => {
+                elements.iter().for_each(|p| self.pattern_names(p));
+                if let Some(rest) = rest {
+                    self.pattern_names(rest);
+                }
+            }
+            Pattern::Struct { name, fields } => {
+                self.at(name);
+                for (field, pattern) in fields {
+                    if !matches!(pattern, Pattern::Identifier(binding) if binding == field) {
+                        self.at(field);
+                    }
+                    self.pattern_names(pattern);
+                }
+            }
+            Pattern::Variant { value, .. } => self.pattern_names(value),
+        }
+    }
+
+    fn define_pattern(&mut self, pattern: &Pattern) {
+        for name in pattern.bindings() {
+            self.infer(name, Ty::Unknown, None);
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        self.pattern_names(pattern);
+        self.define_pattern(pattern);
+    }
+
+    /// The type of an expression already checked, without stepping over
+    /// its names again
+    fn expression_type_of(&self, expression: &Expression) -> Ty {
+        match expression {
+            Expression::Identifier(name) => self.type_of(name).unwrap_or(Ty::Unknown),
+            _ => Ty::Unknown,
+        }
+    }
+
+    /// The type of a field of a value, reporting fields its struct lacks
+    fn field(&mut self, object: &Ty, field: &str, at: Option<&RangedToken>) -> Ty {
+        let Ty::Struct(name) = object else {
+            return Ty::Unknown;
+        };
+        let Some(fields) = self.structs.get(name.as_str()) else {
+            return Ty::Unknown;
+        };
+        match fields.iter().find(|(candidate, _)| *candidate == field) {
+            Some((_, ty)) => ty.clone(),
+            None => {
+                let similar = suggest::similar_names(field, fields.iter().map(|(name, _)| *name));
+                let message = format!(
+                    "Struct '{}' has no field '{}'{}",
+                    name,
+                    field,
+                    suggest::did_you_mean(&similar)
+                );
+                self.report("unknown-field", message, at);
+                Ty::Unknown
+            }
+        }
+    }
+
+    /// Check the arguments of a call to `function` against its parameters,
+    /// leaving out a method's `self`
+    fn arguments(&mut self, function: &Function, arguments: &[Ty], at: Option<&RangedToken>) {
+        let parameters: Vec<&Parameter> = function
+            .parameters
+            .iter()
+            .filter(|param| param.name != "self")
+            .collect();
+        let required = parameters.iter().filter(|p| p.default.is_none()).count();
+        if arguments.len() < required || arguments.len() > parameters.len() {
+            let expected = if required == parameters.len() {
+                format!("{} argument{}", required, plural(required))
+            } else {
+                format!("{} to {} arguments", required, parameters.len())
+            };
+            self.report(
+                "wrong-arguments",
+                format!(
+                    "'{}' takes {} but {} {} given",
+                    function.name,
+                    expected,
+                    arguments.len(),
+                    if arguments.len() == 1 { "was" } else { "were" }
+                ),
+                at,
+            );
+            return;
+        }
+        for (i, (param, actual)) in parameters.iter().zip(arguments).enumerate() {
+            if let Some(ty) = &param.param_type {
+                let expected = Ty::from_annotation(ty, &self.struct_names);
+                self.expect(
+                    &expected,
+                    actual,
+                    format_args!("Argument {} of '{}'", i + 1, function.name),
+                    at,
+                );
+            }
+        }
+    }
+
+    /// The declared return type of a function, or the one inferred from
+    /// its return statements by an earlier round
+    fn return_type(&self, function: &Function) -> Ty {
+        match &function.return_type {
+            Some(ty) => Ty::from_annotation(ty, &self.struct_names),
+            None => self
+                .returns
+                .get(&(function as FunctionId))
+                .cloned()
+                .unwrap_or(Ty::Unknown),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Ty {
+        match expression {
+            Expression::Literal(literal) => literal_type(literal),
+            Expression::Identifier(name) => {
+                self.at(name);
+                match self.type_of(name) {
+                    Some(ty) => ty,
+                    None if self.functions.contains_key(name.as_str()) => Ty::Function,
+                    None => Ty::Unknown,
+                }
+            }
+            Expression::Binary(binary) => {
+                let left = self.expression(&binary.left);
+                let right = self.expression(&binary.right);
+                binary_type(&binary.operator, &left, &right)
+            }
+            Expression::Unary(unary) => {
+                let operand = self.expression(&unary.operand);
+                match unary.operator {
+                    UnaryOperator::Minus if matches!(operand, Ty::Int | Ty::Float) => operand,
+                    UnaryOperator::Not => Ty::Bool,
+                    _ => Ty::Unknown,
+                }
+            }
+            Expression::Call(call) => {
+                let Expression::Identifier(name) = call.callee.as_ref() else {
+                    self.expression(&call.callee);
+                    for arg in &call.arguments {
+                        self.expression(arg);
+                    }
+                    return Ty::Unknown;
+                };
+                let at = self.at(name);
+                let arguments: Vec<Ty> =
+                    call.arguments.iter().map(|a| self.expression(a)).collect();
+                // A variable of the same name hides the function
+                if self.variable(name).is_some() {
+                    return Ty::Unknown;
+                }
+                match self.functions.get(name.as_str()).copied() {
+                    Some(function) => {
+                        self.arguments(function, &arguments, at);
+                        self.return_type(function)
+                    }
+                    None => Ty::of_builtin(name),
+                }
+            }
+            Expression::Index(index) => {
+                let object = self.expression(&index.object);
+                self.expression(&index.index);
+                match object {
+                    Ty::Array(element) => *element,
+                    _ => Ty::Unknown,
+                }
+            }
+            Expression::FieldAccess(access) => {
+                let object = self.expression(&access.object);
+                let at = self.at(&access.field);
+                self.field(&object, &access.field, at)
+            }
+            Expression::MethodCall(call) => {
+                let object = self.expression(&call.object);
+                let at = self.at(&call.method);
+                let arguments: Vec<Ty> =
+                    call.arguments.iter().map(|a| self.expression(a)).collect();
+                let Ty::Struct(name) = &object else {
+                    return Ty::Unknown;
+                };
+                match self
+                    .methods
+                    .get(&(name.as_str(), call.method.as_str()))
+                    .copied()
+                {
+                    Some(method) => {
+                        self.arguments(method, &arguments, at);
+                        self.return_type(method)
+                    }
+                    None => Ty::Unknown,
+                }
+            }
+            Expression::ModuleAccess(access) => {
+                self.at(&access.module);
+                self.at(&access.item);
+                Ty::Unknown
+            }
+            Expression::Array(array) => {
+                let elements: Vec<Ty> = array.elements.iter().map(|e| self.expression(e)).collect();
+                Ty::Array(Box::new(Ty::join_all(elements)))
+            }
+            Expression::Dictionary(dict) => {
+                for (key, value) in &dict.pairs {
+                    self.expression(key);
+                    self.expression(value);
+                }
+                Ty::Unknown
+            }
+            Expression::Set(set) => {
+                for element in &set.elements {
+                    self.expression(element);
+                }
+                Ty::Unknown
+            }
+            Expression::Tuple(tuple) => {
+                for element in &tuple.elements {
+                    self.expression(element);
+                }
+                Ty::Unknown
+            }
+            Expression::StructInit(init) => {
+                self.at(&init.struct_name);
+                let ty = match self.struct_names.contains(&init.struct_name) {
+                    true => Ty::Struct(init.struct_name.clone()),
+                    false => Ty::Unknown,
+                };
+                for (field, value) in &init.fields {
+                    let at = self.at(field);
+                    let expected = self.field(&ty, field, at);
+                    let actual = self.expression(value);
+                    self.expect(&expected, &actual, format_args!("Field '{}'", field), at);
+                }
+                ty
+            }
+            Expression::If(if_expr) => {
+                self.expression(&if_expr.condition);
+                let then = self.expression(&if_expr.then_expr);
+                let otherwise = self.expression(&if_expr.else_expr);
+                then.join(otherwise)
+            }
+            Expression::Match(match_expr) => {
+                self.expression(&match_expr.expression);
+                let mut arms = Vec::new();
+                for arm in &match_expr.arms {
+                    self.scopes.push(HashMap::new());
+                    self.pattern(&arm.pattern);
+                    if let Some(guard) = &arm.guard {
+                        self.expression(guard);
+                    }
+                    arms.push(self.expression(&arm.expression));
+                    self.scopes.pop();
+                }
+                Ty::join_all(arms)
+            }
+            Expression::Range(range) => {
+                self.expression(&range.start);
+                self.expression(&range.end);
+                Ty::Unknown
+            }
+            Expression::Await(AwaitExpression { expression })
+            | Expression::Spawn(SpawnExpression { expression })
+            | Expression::Propagate(PropagateExpression { expression }) => {
+                self.expression(expression);
+                Ty::Unknown
+            }
+            Expression::Cast(cast) => {
+                self.expression(&cast.expression);
+                self.annotation(&cast.target_type)
+            }
+            Expression::InterpolatedString(string) => {
+                for part in &string.parts {
+                    if let StringPart::Expression(expression) = part {
+                        self.expression(expression);
+                    }
+                }
+                Ty::String
+            }
+            Expression::Lambda(lambda) => {
+                self.scopes.push(HashMap::new());
+                let return_type = self.signature(&lambda.parameters, lambda.return_type.as_ref());
+                self.bodies.push(Body {
+                    name: "lambda".to_string(),
+                    return_type,
+                    returns: Vec::new(),
+                });
+                match &lambda.body {
+                    LambdaBody::Expression(body) => {
+                        self.expression(body);
+                    }
+                    LambdaBody::Block(body) => self.block(body),
+                }
+                self.bodies.pop();
+                self.scopes.pop();
+                Ty::Function
+            }
+        }
+    }
+}
+
+fn literal_type(literal: &Literal) -> Ty {
+    match literal {
+        Literal::Integer(_) => Ty::Int,
+        Literal::Float(_) => Ty::Float,
+        Literal::String(_) => Ty::String,
+        Literal::Char(_) => Ty::Char,
+        Literal::Boolean(_) => Ty::Bool,
+        Literal::None => Ty::None,
+    }
+}
+
+/// The type of `left op right`, following the interpreter: `/` always
+/// gives a float, and mixing ints and floats gives a float
+fn binary_type(operator: &BinaryOperator, left: &Ty, right: &Ty) -> Ty {
+    use BinaryOperator::*;
+    match (operator, left, right) {
+        (
+            Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual | In | NotIn | And | Or,
+            _,
+            _,
+        ) => Ty::Bool,
+        (Add, Ty::String, Ty::String) => Ty::String,
+        (Divide, Ty::Int | Ty::Float, Ty::Int | Ty::Float) => Ty::Float,
+        (Add | Subtract | Multiply | FloorDivide | Modulo, Ty::Int, Ty::Int) => Ty::Int,
+        (
+            Add | Subtract | Multiply | FloorDivide | Modulo | Power,
+            Ty::Int | Ty::Float,
+            Ty::Int | Ty::Float,
+        ) if *left == Ty::Float || *right == Ty::Float => Ty::Float,
+        (BitwiseAnd | BitwiseOr | BitwiseXor | LeftShift | RightShift, Ty::Int, Ty::Int) => Ty::Int,
+        _ => Ty::Unknown,
+    }
+}
+
+/// The type of `target op= value`
+fn compound_type(operator: &AssignmentOperator, target: &Ty, value: &Ty) -> Ty {
+    use AssignmentOperator::*;
+    let operator = match operator {
+        Assign => return value.clone(),
+        AddAssign => BinaryOperator::Add,
+        SubAssign => BinaryOperator::Subtract,
+        MulAssign => BinaryOperator::Multiply,
+        DivAssign => BinaryOperator::Divide,
+        FloorDivAssign => BinaryOperator::FloorDivide,
+        ModAssign => BinaryOperator::Modulo,
+        BitwiseAndAssign => BinaryOperator::BitwiseAnd,
+        BitwiseOrAssign => BinaryOperator::BitwiseOr,
+        BitwiseXorAssign => BinaryOperator::BitwiseXor,
+        LeftShiftAssign => BinaryOperator::LeftShift,
+        RightShiftAssign => BinaryOperator::RightShift,
+    };
+    binary_type(&operator, target, value)
+}
+
+/// Whether running `block` always ends in a return or a throw, so that it
+/// never falls off the end and returns None
+fn always_returns(block: &Block) -> bool {
+    let last = block
+        .statements
+        .iter()
+        .rfind(|statement| !matches!(statement, Statement::Comment(_)));
+    match last {
+        Some(Statement::Return(_) | Statement::Throw(_)) => true,
+        Some(Statement::If(stmt)) => {
+            always_returns(&stmt.then_branch)
+                && stmt
+                    .elif_branches
+                    .iter()
+                    .all(|(_, block)| always_returns(block))
+                && stmt.else_branch.as_ref().is_some_and(always_returns)
+        }
+        Some(Statement::Block(block)) => always_returns(block),
+        _ => false,
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
@@ -0,0 +1,925 @@
+// Bytecode virtual machine
+//
+// `run` executes a compiled `Module` on a stack machine, as a faster
+// alternative to walking the syntax tree: variables of functions live in
+// numbered slots rather than scopes of names, and control flow is jumps
+// rather than recursion through statements. `veyc run --vm` runs programs
+// this way, as does `veyc run` on a `.veyb` file.
+//
+// The VM runs on an `Interpreter`, which provides what compiled code shares
+// with code of the syntax tree: builtins, operators, globals, imported
+// modules, tasks and actors. Code the interpreter runs reaches the compiled
+// functions, methods and lambdas through it, so builtins like `map` call
+// compiled lambdas and imported modules run as they would otherwise.
+//
+// Each call runs in a frame of its own, with its slots and a stack of
+// values. An error leaves instructions through the innermost handler of the
+// frame: a `catch` handler continues with the caught value, and a `finally`
+// handler keeps the error to raise again once its block has run. Limits
+// count a step for every call and every jump back to the start of a loop.
+
+use crate::ast::{Actor, Field, MailboxConfig};
+use crate::builtins;
+use crate::bytecode::{CaptureFrom, Chunk, ChunkKind, Instruction, Module, Pattern, StructDef};
+use crate::error::{Result, Unwind, VeyraError};
+use crate::interpreter::{self, FunctionValue, Interpreter, StructValue, Value};
+use crate::modules::ModuleLoader;
+use crate::suggest;
+use crate::table::{Dictionary, Set};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// A module ready to run, with its declarations indexed by name
+pub struct Code {
+    module: Module,
+    constants: Vec<Value>,
+    functions: HashMap<String, u32>,
+    /// The function a `Call` of each name runs, unless a builtin or a
+    /// global function value has the name
+    calls: Vec<Option<u32>>,
+    /// Methods by type name and method name, including the default methods
+    /// of traits
+    methods: HashMap<String, HashMap<String, u32>>,
+    structs: HashMap<String, StructDef>,
+    traits: HashSet<String>,
+}
+
+/// A lambda of compiled code with copies of the variables it captures, in
+/// the order of its chunk's captures
+#[derive(Debug)]
+pub struct Closure {
+    pub chunk: u32,
+    pub captured: Vec<Value>,
+}
+
+/// Run a compiled program, as `interpreter::interpret` runs a syntax tree
+pub fn interpret(
+    module: Module,
+    modules: ModuleLoader,
+    args: Vec<String>,
+    deterministic: bool,
+) -> Result<i32> {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_module_loader(modules);
+    interpreter.set_args(args);
+    interpreter.set_deterministic(deterministic);
+    interpreter::exit_code(run(&mut interpreter, module))
+}
+
+/// Run a compiled program on `interpreter`: its top-level code, then `main`
+/// if it takes no arguments, then the messages left for its actors. Returns
+/// what `main` returned, or None.
+pub fn run(interpreter: &mut Interpreter, module: Module) -> Result<Value> {
+    let result = run_module(interpreter, module);
+    if result.is_err() {
+        interpreter.cancel_tasks();
+    }
+    result
+}
+
+fn run_module(interpreter: &mut Interpreter, module: Module) -> Result<Value> {
+    let code = Rc::new(Code::new(module)?);
+    for block in &code.module.impls {
+        if let Some(trait_name) = &block.trait_name {
+            interpreter
+                .implementations
+                .entry(block.target.clone())
+                .or_default()
+                .insert(trait_name.clone());
+        }
+    }
+    interpreter.code = Some(code.clone());
+
+    let entry = code.module.chunk(code.module.entry);
+    let slots = vec![Value::None; entry.slots as usize];
+    execute(interpreter, &code, code.module.entry, slots)?;
+    let mut returned = Value::None;
+    if let Some(main) = code.function("main") {
+        if code.module.chunk(main).parameters.is_empty() {
+            returned = invoke(interpreter, &code, main, None, Vec::new())?;
+        }
+    }
+    interpreter.finish_program()?;
+    Ok(returned)
+}
+
+impl Code {
+    /// Index a module's declarations, checking each `impl` of a trait
+    /// against the trait as `impl` blocks of a syntax tree are checked
+    pub fn new(module: Module) -> Result<Self> {
+        let constants = module
+            .constants
+            .iter()
+            .map(interpreter::literal_value)
+            .collect();
+        let functions: HashMap<String, u32> = module
+            .functions
+            .iter()
+            .map(|&chunk| (module.chunk(chunk).name.clone(), chunk))
+            .collect();
+        let calls = module
+            .names
+            .iter()
+            .map(|name| {
+                let function = functions.get(name).copied();
+                function.filter(|_| builtins::lookup(name).is_none())
+            })
+            .collect();
+        let structs = module
+            .structs
+            .iter()
+            .map(|definition| (definition.name.clone(), definition.clone()))
+            .collect();
+        let traits = module.traits.iter().map(|t| t.name.clone()).collect();
+        let mut code = Self {
+            constants,
+            functions,
+            calls,
+            methods: HashMap::new(),
+            structs,
+            traits,
+            module,
+        };
+        for index in 0..code.module.impls.len() {
+            code.define_impl(index)?;
+        }
+        Ok(code)
+    }
+
+    fn define_impl(&mut self, index: usize) -> Result<()> {
+        let module = &self.module;
+        let block = &module.impls[index];
+        let mut methods: Vec<u32> = block.methods.clone();
+        if let Some(trait_name) = &block.trait_name {
+            let definition = module
+                .traits
+                .iter()
+                .find(|t| t.name == *trait_name)
+                .ok_or_else(|| {
+                    let names = module.traits.iter().map(|t| t.name.as_str());
+                    VeyraError::runtime_error(format!(
+                        "Undefined trait '{}'{}",
+                        trait_name,
+                        suggest::did_you_mean(&suggest::similar_names(trait_name, names))
+                    ))
+                })?;
+            for &method in &block.methods {
+                let method = module.chunk(method);
+                let Some(required) = definition.methods.iter().find(|m| m.name == method.name)
+                else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Method '{}' is not a member of trait '{}'",
+                        method.name, trait_name
+                    )));
+                };
+                if required.arity as usize != method.parameters.len() {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Method '{}' of trait '{}' takes {} arguments, but the impl for '{}' takes {}",
+                        method.name,
+                        trait_name,
+                        required.arity,
+                        block.target,
+                        method.parameters.len()
+                    )));
+                }
+            }
+            for method in &definition.methods {
+                let defined = block
+                    .methods
+                    .iter()
+                    .any(|&chunk| module.chunk(chunk).name == method.name);
+                if defined {
+                    continue;
+                }
+                let Some(default) = method.default else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Missing method '{}' in impl of trait '{}' for '{}'",
+                        method.name, trait_name, block.target
+                    )));
+                };
+                methods.push(default);
+            }
+        }
+        let defined = self.methods.entry(block.target.clone()).or_default();
+        for method in methods {
+            defined.insert(module.chunk(method).name.clone(), method);
+        }
+        Ok(())
+    }
+
+    /// The chunk of a top-level function
+    pub(crate) fn function(&self, name: &str) -> Option<u32> {
+        self.functions.get(name).copied()
+    }
+
+    pub(crate) fn method(&self, type_name: &str, method: &str) -> Option<u32> {
+        self.methods.get(type_name)?.get(method).copied()
+    }
+
+    pub(crate) fn has_trait(&self, name: &str) -> bool {
+        self.traits.contains(name)
+    }
+}
+
+/// Call a compiled function or method for the interpreter
+pub(crate) fn call(
+    host: &mut Interpreter,
+    chunk: u32,
+    receiver: Option<Value>,
+    args: &[Value],
+) -> Result<Value> {
+    let code = host.code.clone().expect("compiled code to call");
+    invoke(host, &code, chunk, receiver, args.to_vec())
+}
+
+/// Call a compiled lambda for the interpreter
+pub(crate) fn call_closure(
+    host: &mut Interpreter,
+    closure: &Closure,
+    args: &[Value],
+) -> Result<Value> {
+    let code = host.code.clone().expect("compiled code to call");
+    let chunk = code.module.chunk(closure.chunk);
+    if args.len() != chunk.parameters.len() {
+        return Err(VeyraError::runtime_error(format!(
+            "Lambda expects {} arguments, got {}",
+            chunk.parameters.len(),
+            args.len()
+        )));
+    }
+    let mut slots = args.to_vec();
+    slots.resize(chunk.slots as usize, Value::None);
+    for (capture, value) in chunk.captures.iter().zip(&closure.captured) {
+        slots[capture.to as usize] = value.clone();
+    }
+    host.begin_call("<lambda>")?;
+    let result = execute(host, &code, closure.chunk, slots);
+    host.end_call();
+    result
+}
+
+/// Handle a message with the `receive` method of a compiled actor, given
+/// the actor's state, which is returned with the final values of its fields
+pub(crate) fn receive(
+    host: &mut Interpreter,
+    actor: usize,
+    mut state: HashMap<String, Value>,
+    message: Value,
+) -> (Result<Value>, HashMap<String, Value>) {
+    let code = host.code.clone().expect("compiled code to call");
+    let definition = &code.module.actors[actor];
+    let receive = definition.methods.iter().copied().find(|&method| {
+        let method = code.module.chunk(method);
+        method.name == "receive" && method.parameters.len() == 1
+    });
+    let Some(receive) = receive else {
+        let error = VeyraError::runtime_error(format!(
+            "Actor '{}' has no receive(message) method",
+            definition.name
+        ));
+        return (Err(error), state);
+    };
+    if let Err(error) = host.begin_call(&format!("{}.receive", definition.name)) {
+        return (Err(error), state);
+    }
+
+    // The message, then the fields
+    let chunk = code.module.chunk(receive);
+    let mut slots = vec![message];
+    for (field, _) in &definition.fields {
+        slots.push(state.remove(field).unwrap_or(Value::None));
+    }
+    slots.resize(chunk.slots as usize, Value::None);
+    let mut frame = Frame::new(&code, chunk, slots);
+    let result = frame.run(host);
+    for ((field, _), value) in definition.fields.iter().zip(frame.slots.drain(1..)) {
+        state.insert(field.clone(), value);
+    }
+    host.end_call();
+    (result, state)
+}
+
+/// Call a function or method from compiled code
+fn invoke(
+    host: &mut Interpreter,
+    code: &Rc<Code>,
+    index: u32,
+    receiver: Option<Value>,
+    args: Vec<Value>,
+) -> Result<Value> {
+    let chunk = code.module.chunk(index);
+    if args.len() != chunk.parameters.len() {
+        return Err(VeyraError::runtime_error(format!(
+            "Function '{}' expects {} arguments, got {}",
+            chunk.name,
+            chunk.parameters.len(),
+            args.len()
+        )));
+    }
+    let mut slots = Vec::with_capacity(chunk.slots as usize);
+    slots.extend(receiver);
+    slots.extend(args);
+    slots.resize(chunk.slots as usize, Value::None);
+    host.begin_call(&chunk.name)?;
+    let result = execute(host, code, index, slots);
+    host.end_call();
+    result
+}
+
+/// Run a chunk with its slots filled in
+fn execute(
+    host: &mut Interpreter,
+    code: &Rc<Code>,
+    chunk: u32,
+    slots: Vec<Value>,
+) -> Result<Value> {
+    Frame::new(code, code.module.chunk(chunk), slots).run(host)
+}
+
+/// Where control goes when an error leaves the instructions of a region
+struct Handler {
+    /// Whether it is a `catch` handler, or else a `finally` handler
+    catch: bool,
+    target: u32,
+    /// Values on the stack when the region started
+    depth: usize,
+}
+
+/// An error on hold while a `finally` block runs, with the thrown or
+/// returned value it carries
+struct Pending {
+    error: VeyraError,
+    thrown: Option<Value>,
+    return_value: Option<Value>,
+}
+
+/// The state of a running chunk
+struct Frame<'c> {
+    code: &'c Rc<Code>,
+    chunk: &'c Chunk,
+    slots: Vec<Value>,
+    stack: Vec<Value>,
+    handlers: Vec<Handler>,
+    pending: Vec<Pending>,
+    ip: usize,
+}
+
+impl<'c> Frame<'c> {
+    fn new(code: &'c Rc<Code>, chunk: &'c Chunk, slots: Vec<Value>) -> Self {
+        Self {
+            code,
+            chunk,
+            slots,
+            stack: Vec::new(),
+            handlers: Vec::new(),
+            pending: Vec::new(),
+            ip: 0,
+        }
+    }
+
+    /// Run to the end of the chunk and return its value
+    fn run(&mut self, host: &mut Interpreter) -> Result<Value> {
+        host.tick()?;
+        loop {
+            let error = match self.step(host) {
+                Ok(value) => return Ok(value),
+                Err(error) => self.locate(error),
+            };
+            match self.unwind(host, error) {
+                Ok(()) => {}
+                // `?` returns from functions and lambdas
+                Err(VeyraError::Unwind(Unwind::Return))
+                    if !matches!(self.chunk.kind, ChunkKind::Entry | ChunkKind::Task) =>
+                {
+                    return Ok(host.return_value.take().unwrap_or(Value::None));
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Attach the source line of the instruction that just ran to a runtime
+    /// error raised by it, unless it already has the line of a call it
+    /// came out of
+    fn locate(&self, error: VeyraError) -> VeyraError {
+        error.at_line(self.chunk.line(self.ip.saturating_sub(1)))
+    }
+
+    /// Continue at the innermost handler that takes the error, or return
+    /// the error if none does
+    fn unwind(&mut self, host: &mut Interpreter, error: VeyraError) -> Result<()> {
+        while let Some(handler) = self.handlers.pop() {
+            if handler.catch {
+                let Some(caught) = host.caught(&error) else {
+                    continue;
+                };
+                self.stack.truncate(handler.depth);
+                self.stack.push(caught);
+            } else {
+                self.stack.truncate(handler.depth);
+                self.pending.push(Pending {
+                    error,
+                    thrown: host.thrown.take(),
+                    return_value: host.return_value.take(),
+                });
+            }
+            self.ip = handler.target as usize;
+            return Ok(());
+        }
+        Err(error)
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("a value on the stack")
+    }
+
+    /// The top `count` values of the stack, in the order they were pushed
+    fn pop_many(&mut self, count: u32) -> Vec<Value> {
+        self.stack.split_off(self.stack.len() - count as usize)
+    }
+
+    fn push(&mut self, value: Value) {
+        self.stack.push(value);
+    }
+
+    /// Run instructions until the chunk returns or an error occurs
+    fn step(&mut self, host: &mut Interpreter) -> Result<Value> {
+        let code = self.code;
+        let module = &code.module;
+        let instructions = &self.chunk.code;
+        loop {
+            let instruction = &instructions[self.ip];
+            self.ip += 1;
+            match instruction {
+                Instruction::Constant(index) => {
+                    self.push(code.constants[*index as usize].clone());
+                }
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::LoadLocal(slot) => {
+                    self.push(self.slots[*slot as usize].clone());
+                }
+                Instruction::StoreLocal(slot) => {
+                    self.slots[*slot as usize] = self.pop();
+                }
+                Instruction::LoadName(name) => {
+                    let value = host.variable(module.name(*name))?;
+                    self.push(value);
+                }
+                Instruction::DefineGlobal(name) => {
+                    let value = self.pop();
+                    host.environment
+                        .define(module.name(*name).to_string(), value);
+                }
+                Instruction::StoreGlobal(name) => {
+                    let value = self.pop();
+                    host.environment.set(module.name(*name), value)?;
+                }
+                Instruction::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = host.apply_binary_operator(operator, &left, &right)?;
+                    self.push(value);
+                }
+                Instruction::Unary(operator) => {
+                    let operand = self.pop();
+                    let value = host.apply_unary_operator(operator, &operand)?;
+                    self.push(value);
+                }
+                Instruction::Jump(target) => {
+                    let target = *target as usize;
+                    if target < self.ip {
+                        host.tick()?;
+                    }
+                    self.ip = target;
+                }
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop().is_truthy() {
+                        self.ip = *target as usize;
+                    }
+                }
+                Instruction::Call(name, count) => {
+                    let args = self.pop_many(*count);
+                    let value = self.call_named(host, *name, args)?;
+                    self.push(value);
+                }
+                Instruction::CallValue(count) => {
+                    let args = self.pop_many(*count);
+                    let value = match self.pop() {
+                        Value::Function(function) => host.call_value(&function, &args)?,
+                        other => {
+                            return Err(VeyraError::runtime_error(format!(
+                                "Cannot call {}",
+                                other.type_name()
+                            )))
+                        }
+                    };
+                    self.push(value);
+                }
+                Instruction::CallMethod(name, count) => {
+                    let args = self.pop_many(*count);
+                    let receiver = self.pop();
+                    let value = host.call_method(receiver, module.name(*name), &args)?;
+                    self.push(value);
+                }
+                Instruction::CallModule {
+                    module: name,
+                    item,
+                    arguments,
+                } => {
+                    let args = self.pop_many(*arguments);
+                    let value =
+                        host.call_module_item(module.name(*name), module.name(*item), &args)?;
+                    self.push(value);
+                }
+                Instruction::ModuleItem { module: name, item } => {
+                    let value = host.module_access(module.name(*name), module.name(*item))?;
+                    self.push(value);
+                }
+                Instruction::Index => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = host.index_value(object, index)?;
+                    self.push(value);
+                }
+                Instruction::Field(name) => {
+                    let object = self.pop();
+                    self.push(interpreter::field_value(object, module.name(*name))?);
+                }
+                Instruction::SetIndex => {
+                    let index = self.pop();
+                    let mut object = self.pop();
+                    let value = self.pop();
+                    let key = host.typed_key(&index)?;
+                    *interpreter::element_mut(host, &mut object, &index, key.as_deref(), true)? =
+                        value;
+                    self.push(object);
+                }
+                Instruction::SetField(name) => {
+                    let mut object = self.pop();
+                    let value = self.pop();
+                    *interpreter::field_mut(&mut object, module.name(*name))? = value;
+                    self.push(object);
+                }
+                Instruction::StoreDeref => {
+                    let reference = self.pop();
+                    let value = self.pop();
+                    *interpreter::assignable_target(&reference)?.borrow_mut() = value;
+                }
+                Instruction::Array(count) => {
+                    let elements = self.pop_many(*count);
+                    self.push(Value::Array(elements));
+                }
+                Instruction::Tuple(count) => {
+                    let elements = self.pop_many(*count);
+                    self.push(Value::Tuple(elements));
+                }
+                Instruction::Dictionary(count) => {
+                    let mut pairs = self.pop_many(count * 2).into_iter();
+                    let mut map = Dictionary::new();
+                    while let (Some(key), Some(value)) = (pairs.next(), pairs.next()) {
+                        host.insert_key(&mut map, key, value, interpreter::UNHASHABLE_KEY)?;
+                    }
+                    self.push(Value::Dictionary(map));
+                }
+                Instruction::Set(count) => {
+                    let mut set = Set::new();
+                    for element in self.pop_many(*count) {
+                        host.insert_key(&mut set, element, (), interpreter::UNHASHABLE_ELEMENT)?;
+                    }
+                    self.push(Value::Set(set));
+                }
+                Instruction::Struct(shape) => {
+                    let shape = &module.shapes[*shape as usize];
+                    let values = self.pop_many(shape.fields.len() as u32);
+                    let value = self.construct(&shape.name, &shape.fields, values)?;
+                    self.push(value);
+                }
+                Instruction::Range { inclusive } => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let (Value::Integer(start), Value::Integer(end)) = (start, end) else {
+                        return Err(VeyraError::runtime_error(
+                            "Range expressions require integer bounds",
+                        ));
+                    };
+                    let end = if *inclusive { end + 1 } else { end };
+                    self.push(Value::Array((start..end).map(Value::Integer).collect()));
+                }
+                Instruction::Cast(ty) => {
+                    let value = self.pop();
+                    let value = host.cast_value(value, &module.types[*ty as usize])?;
+                    self.push(value);
+                }
+                Instruction::Interpolate(count) => {
+                    let text: String = self
+                        .pop_many(*count)
+                        .iter()
+                        .map(Interpreter::value_to_string)
+                        .collect();
+                    self.push(Value::String(text));
+                }
+                Instruction::Closure(chunk) => {
+                    let captured = self.captures(host, *chunk)?;
+                    let closure = Closure {
+                        chunk: *chunk,
+                        captured,
+                    };
+                    self.push(Value::Function(FunctionValue::Compiled(Rc::new(closure))));
+                }
+                Instruction::Spawn(chunk) => {
+                    let target = module.chunk(*chunk);
+                    let mut slots = vec![Value::None; target.slots as usize];
+                    for (capture, value) in target.captures.iter().zip(self.captures(host, *chunk)?)
+                    {
+                        slots[capture.to as usize] = value;
+                    }
+                    let (code, chunk) = (code.clone(), *chunk);
+                    let task = host.run_task(HashMap::new(), move |host| {
+                        execute(host, &code, chunk, slots)
+                    });
+                    self.push(task);
+                }
+                Instruction::SpawnActor(index) => {
+                    let definition = &module.actors[*index as usize];
+                    let actor = Actor {
+                        name: definition.name.clone(),
+                        fields: definition
+                            .fields
+                            .iter()
+                            .map(|(name, field_type)| Field {
+                                name: name.clone(),
+                                field_type: field_type.clone(),
+                                comments: Vec::new(),
+                            })
+                            .collect(),
+                        methods: Vec::new(),
+                        attributes: Vec::new(),
+                        comments: Vec::new(),
+                        end_comments: Vec::new(),
+                    };
+                    let mailbox = MailboxConfig {
+                        capacity: definition.mailbox_capacity,
+                        overflow: definition.overflow,
+                    };
+                    let value = host.add_actor(actor, Some(*index as usize), mailbox);
+                    self.push(value);
+                }
+                Instruction::Await => {
+                    let value = match self.pop() {
+                        Value::Task(task) => host.await_task(task)?,
+                        // Async functions run to completion when called
+                        value => value,
+                    };
+                    self.push(value);
+                }
+                Instruction::Propagate => {
+                    let value = self.pop();
+                    let value = host.propagate(value)?;
+                    self.push(value);
+                }
+                Instruction::Return => return Ok(self.pop()),
+                Instruction::Throw => {
+                    let value = self.pop();
+                    let message = Interpreter::value_to_string(&value);
+                    host.thrown = Some(value);
+                    return Err(VeyraError::Thrown { message });
+                }
+                Instruction::SetupCatch(target) | Instruction::SetupFinally(target) => {
+                    self.handlers.push(Handler {
+                        catch: matches!(instruction, Instruction::SetupCatch(_)),
+                        target: *target,
+                        depth: self.stack.len(),
+                    });
+                }
+                Instruction::PopHandler => {
+                    self.handlers.pop();
+                }
+                Instruction::Reraise => {
+                    let pending = self.pending.pop().expect("an error to raise again");
+                    host.thrown = pending.thrown;
+                    host.return_value = pending.return_value;
+                    return Err(pending.error);
+                }
+                Instruction::IterStart(slot) => {
+                    let Value::Array(mut elements) = self.pop() else {
+                        return Err(VeyraError::runtime_error(
+                            "Cannot iterate over non-array value",
+                        ));
+                    };
+                    // Reversed, so each element is taken from the end
+                    elements.reverse();
+                    self.slots[*slot as usize] = Value::Array(elements);
+                }
+                Instruction::IterNext { slot, exit } => {
+                    let Value::Array(elements) = &mut self.slots[*slot as usize] else {
+                        unreachable!("a loop over an array");
+                    };
+                    match elements.pop() {
+                        Some(element) => self.push(element),
+                        None => self.ip = *exit as usize,
+                    }
+                }
+                Instruction::Match { pattern, otherwise } => {
+                    let value = self.pop();
+                    let pattern = &module.patterns[*pattern as usize];
+                    let mut bindings = Vec::new();
+                    if matches(host, code, pattern, &value, &mut bindings)? {
+                        self.bind(host, bindings);
+                    } else {
+                        self.ip = *otherwise as usize;
+                    }
+                }
+                Instruction::Destructure(pattern) => {
+                    let value = self.pop();
+                    let pattern = &module.patterns[*pattern as usize];
+                    let mut bindings = Vec::new();
+                    if !matches(host, code, pattern, &value, &mut bindings)? {
+                        return Err(VeyraError::runtime_error(format!(
+                            "Cannot destructure {} '{}' with this pattern",
+                            value.type_name(),
+                            Interpreter::value_to_string(&value)
+                        )));
+                    }
+                    self.bind(host, bindings);
+                }
+                Instruction::NoMatch => {
+                    let value = self.pop();
+                    return Err(VeyraError::runtime_error(format!(
+                        "No match arm matches {} '{}'",
+                        value.type_name(),
+                        Interpreter::value_to_string(&value)
+                    )));
+                }
+                Instruction::TaskGroup => host.task_groups.push(Vec::new()),
+                Instruction::EndTaskGroup | Instruction::LeaveTaskGroup => {
+                    let group = host.task_groups.len() - 1;
+                    let joined = host.join_tasks(group);
+                    host.task_groups.pop();
+                    if let Instruction::EndTaskGroup = instruction {
+                        joined?;
+                    }
+                }
+                Instruction::Import(import) => {
+                    host.handle_import(&module.imports[*import as usize])?;
+                }
+            }
+        }
+    }
+
+    /// Call a function by name: a function value in a global of that name,
+    /// a builtin, or a function of the program
+    fn call_named(&mut self, host: &mut Interpreter, name: u32, args: Vec<Value>) -> Result<Value> {
+        let code = self.code;
+        let text = code.module.name(name);
+        if let Some(Value::Function(function)) = host.environment.get(text) {
+            let function = function.clone();
+            return host.call_value(&function, &args);
+        }
+        match code.calls[name as usize] {
+            Some(chunk) => invoke(host, code, chunk, None, args),
+            None => host.call_function(text, &args),
+        }
+    }
+
+    /// Copies of the values a lambda or spawned expression captures
+    fn captures(&self, host: &Interpreter, chunk: u32) -> Result<Vec<Value>> {
+        let module = &self.code.module;
+        module
+            .chunk(chunk)
+            .captures
+            .iter()
+            .map(|capture| match capture.from {
+                CaptureFrom::Slot(slot) => Ok(self.slots[slot as usize].clone()),
+                CaptureFrom::Global(name) => host.variable(module.name(name)),
+            })
+            .collect()
+    }
+
+    /// A struct from the values given for some of its fields
+    fn construct(&self, name: &str, given: &[String], values: Vec<Value>) -> Result<Value> {
+        let definition = self
+            .code
+            .structs
+            .get(name)
+            .ok_or_else(|| VeyraError::runtime_error(format!("Undefined struct '{}'", name)))?;
+        let mut values: HashMap<&str, Value> =
+            given.iter().map(String::as_str).zip(values).collect();
+        if let Some(field) = given.iter().find(|f| !definition.fields.contains(f)) {
+            return Err(VeyraError::runtime_error(format!(
+                "Struct '{}' has no field '{}'",
+                name, field
+            )));
+        }
+        let fields = definition
+            .fields
+            .iter()
+            .map(|field| {
+                let value = values.remove(field.as_str()).ok_or_else(|| {
+                    VeyraError::runtime_error(format!(
+                        "Missing field '{}' in '{}' initializer",
+                        field, name
+                    ))
+                })?;
+                Ok((field.clone(), value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Value::Struct(StructValue {
+            name: name.to_string(),
+            fields,
+        }))
+    }
+
+    /// Store the values a matched pattern binds
+    fn bind(&mut self, host: &mut Interpreter, bindings: Vec<(&Pattern, Value)>) {
+        for (pattern, value) in bindings {
+            match pattern {
+                Pattern::Bind(slot) => self.slots[*slot as usize] = value,
+                Pattern::BindGlobal(name) => {
+                    let name = self.code.module.name(*name).to_string();
+                    host.environment.define(name, value);
+                }
+                _ => unreachable!("a binding pattern"),
+            }
+        }
+    }
+}
+
+/// Whether `value` matches `pattern`, adding the patterns that bind a
+/// variable to `bindings` with their values. A struct pattern naming a
+/// field the struct lacks is an error, as it can never match.
+fn matches<'p>(
+    host: &mut Interpreter,
+    code: &Code,
+    pattern: &'p Pattern,
+    value: &Value,
+    bindings: &mut Vec<(&'p Pattern, Value)>,
+) -> Result<bool> {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => Ok(true),
+        (Pattern::Bind(_) | Pattern::BindGlobal(_), _) => {
+            bindings.push((pattern, value.clone()));
+            Ok(true)
+        }
+        (Pattern::Constant(index), _) => host.values_equal(&code.constants[*index as usize], value),
+        (Pattern::Tuple(patterns), Value::Tuple(values)) => {
+            if patterns.len() != values.len() {
+                return Ok(false);
+            }
+            all_match(host, code, patterns, values, bindings)
+        }
+        (Pattern::Array { elements, rest }, Value::Array(values)) => {
+            let fits = match rest {
+                Some(_) => values.len() >= elements.len(),
+                None => values.len() == elements.len(),
+            };
+            if !fits || !all_match(host, code, elements, values, bindings)? {
+                return Ok(false);
+            }
+            match rest {
+                Some(rest) => {
+                    let rest_value = Value::Array(values[elements.len()..].to_vec());
+                    matches(host, code, rest, &rest_value, bindings)
+                }
+                None => Ok(true),
+            }
+        }
+        (Pattern::Struct { name, fields }, Value::Struct(instance)) => {
+            if *name != instance.name {
+                return Ok(false);
+            }
+            for (field, pattern) in fields {
+                let Some(field_value) = instance.field(field) else {
+                    return Err(VeyraError::runtime_error(format!(
+                        "Struct '{}' has no field '{}'",
+                        name, field
+                    )));
+                };
+                if !matches(host, code, pattern, field_value, bindings)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+        (Pattern::Variant { variant, value }, Value::Variant(actual, inner)) => {
+            Ok(variant == actual && matches(host, code, value, inner, bindings)?)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn all_match<'p>(
+    host: &mut Interpreter,
+    code: &Code,
+    patterns: &'p [Pattern],
+    values: &[Value],
+    bindings: &mut Vec<(&'p Pattern, Value)>,
+) -> Result<bool> {
+    for (pattern, value) in patterns.iter().zip(values) {
+        if !matches(host, code, pattern, value, bindings)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
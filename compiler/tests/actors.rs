@@ -0,0 +1,68 @@
+// Tests for actor mailboxes
+
+mod common;
+
+use common::run;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+
+/// The error parsing `source` stops with
+fn parse_error(source: &str) -> String {
+    tokenize(source).and_then(parse).unwrap_err().to_string()
+}
+
+/// An actor that prints each message it handles, with the given `@mailbox`
+fn logger(mailbox: &str) -> String {
+    format!(
+        "{}\nactor Log {{\n    fn receive(msg) {{\n        print(\"got \" + str(msg))\n    }}\n}}\n\
+         let log = spawn Log\n",
+        mailbox
+    )
+}
+
+#[test]
+fn full_mailboxes_apply_their_overflow_strategy() {
+    let sends = "for i in [1, 2, 3] {\n    send(log, i)\n    print(\"sent \" + str(i))\n}\n";
+
+    // Unbounded: everything waits until the program ends
+    let output = run(&format!("{}{}", logger(""), sends));
+    assert_eq!(output, "sent 1\nsent 2\nsent 3\ngot 1\ngot 2\ngot 3\n");
+
+    // Blocking: the third send waits for the actor to handle the first
+    let output = run(&format!("{}{}", logger("@mailbox(capacity = 2)"), sends));
+    assert_eq!(output, "sent 1\nsent 2\ngot 1\nsent 3\ngot 2\ngot 3\n");
+
+    let output = run(&format!(
+        "{}{}",
+        logger("@mailbox(capacity = 2, overflow = \"drop_oldest\")"),
+        sends
+    ));
+    assert_eq!(output, "sent 1\nsent 2\nsent 3\ngot 2\ngot 3\n");
+
+    let error = run(&format!(
+        "{}{}",
+        logger("@mailbox(capacity = 2, overflow = \"error\")"),
+        sends
+    ));
+    assert!(
+        error.contains("Mailbox of actor 'Log' is full (capacity 2)"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn invalid_mailboxes_are_parse_errors() {
+    let error = parse_error(&logger("@mailbox(capacity = 0)"));
+    assert!(
+        error.contains("@mailbox takes a positive capacity"),
+        "{}",
+        error
+    );
+    let error = parse_error(&logger("@mailbox(overflow = \"wait\")"));
+    assert!(
+        error.contains("Unknown overflow strategy 'wait'"),
+        "{}",
+        error
+    );
+}
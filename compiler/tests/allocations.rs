@@ -0,0 +1,55 @@
+// Tests for allocation tracking
+
+mod common;
+
+use common::{program, quiet};
+use veyra_compiler::allocations::AllocationSite;
+use veyra_compiler::interpreter::Value;
+
+fn site(functions: &[&str]) -> AllocationSite {
+    AllocationSite {
+        functions: functions.iter().map(|f| f.to_string()).collect(),
+    }
+}
+
+#[test]
+fn counts_live_allocations_by_call_site() {
+    let source = "fn node(value) {\n    return &mut [value]\n}\n\
+         fn build(n) {\n    let mut nodes = []\n    for i in range(n) {\n        nodes = push(nodes, node(i))\n    }\n    return nodes\n}\n\
+         fn scratch() {\n    let tmp = &[1, 2]\n    return len(*tmp)\n}\n\
+         let untracked = &0\n";
+    let mut interpreter = quiet();
+    interpreter.load_definitions(&program(source)).unwrap();
+    assert_eq!(interpreter.allocation_counts(), []);
+
+    interpreter.track_allocations();
+    let kept = interpreter.call("build", &[Value::Integer(3)]).unwrap();
+    interpreter.call("scratch", &[]).unwrap();
+
+    let counts: Vec<_> = interpreter
+        .allocation_counts()
+        .into_iter()
+        .map(|count| (count.site, count.allocated, count.live))
+        .collect();
+    assert_eq!(
+        counts,
+        [(site(&["build", "node"]), 3, 3), (site(&["scratch"]), 1, 0)]
+    );
+    assert_eq!(
+        site(&["build", "node"]).to_string(),
+        "node (called from build)"
+    );
+    assert_eq!(site(&[]).to_string(), "top-level code");
+
+    // Nothing the program can reach holds the returned nodes, so they count
+    // as leaked until the caller drops them
+    let leaks = interpreter.leaks();
+    assert_eq!(leaks.count(), 3);
+    assert_eq!(
+        leaks.to_string(),
+        "3 values leaked\n  3 created in node (called from build): array of 1, array of 1, array of 1"
+    );
+    drop(kept);
+    assert_eq!(interpreter.allocation_counts()[0].live, 0);
+    assert!(interpreter.leaks().is_empty());
+}
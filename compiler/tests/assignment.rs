@@ -0,0 +1,74 @@
+// Tests for assigning to elements, fields and through references
+
+mod common;
+
+use common::run;
+
+#[test]
+fn assigns_to_elements_and_fields() {
+    let source = "struct Point {\n    x: int\n    y: int\n}\n\
+                  let mut nums = [1, 2, 3]\nnums[0] = 10\nnums[2] += 5\nprint(nums)\n\
+                  let mut grid = [[0, 0], [0, 0]]\ngrid[1][0] = 7\ngrid[0][1] -= 1\nprint(grid)\n\
+                  let mut p = Point { x: 1, y: 2 }\nlet before = p\np.x = 5\np.y *= 3\nprint(p)\nprint(before)\n\
+                  let mut shapes = [p]\nshapes[0].x += 4\nprint(shapes[0].x)\n";
+    assert_eq!(
+        run(source),
+        "[10, 2, 8]\n[[0, -1], [7, 0]]\nPoint { x: 5, y: 6 }\nPoint { x: 1, y: 2 }\n9\n"
+    );
+}
+
+#[test]
+fn dictionaries_gain_missing_keys() {
+    let source = "fn count(words) {\n    let counts = {}\n    for w in words {\n        \
+                  if w in counts {\n            counts[w] += 1\n        } else {\n            \
+                  counts[w] = 1\n        }\n    }\n    return counts\n}\n\
+                  print(count([\"a\", \"b\", \"a\"]))\n\
+                  let d = {}\nd[(1, 2)] = \"pair\"\nprint(d[(1, 2)])\n";
+    assert_eq!(run(source), "{\"a\": 2, \"b\": 1}\npair\n");
+
+    assert!(run("let d = {}\nd[\"x\"] += 1\n").contains("Key 'x' not found in dictionary"));
+    assert!(run("let d = {\"a\": {}}\nd[\"b\"][\"c\"] = 1\n").contains("Key 'b' not found"));
+}
+
+#[test]
+fn indexes_are_evaluated_once_after_the_value() {
+    let source = "let mut order = []\n\
+                  fn at(i) {\n    order = push(order, i)\n    return i\n}\n\
+                  let grid = [[0, 0], [0, 0]]\n\
+                  grid[at(1)][at(0)] += at(5)\nprint(grid)\nprint(order)\n";
+    assert_eq!(run(source), "[[0, 0], [5, 0]]\n[5, 1, 0]\n");
+}
+
+#[test]
+fn writes_through_mutable_references() {
+    let source = "let r = &mut [1, 2]\nlet alias = r\n(*r)[1] = 9\nprint(*alias)\n\
+                  *r = [0]\nprint(*alias)\n\
+                  let items = [&mut 1, &mut 2]\n*items[0] += 10\nprint(*items[0])\n";
+    assert_eq!(run(source), "[1, 9]\n[0]\n11\n");
+
+    assert!(
+        run("let r = &[1]\n*r = [2]\n").contains("Cannot assign through an immutable reference")
+    );
+}
+
+#[test]
+fn reports_targets_that_cannot_change() {
+    for (source, error) in [
+        ("let a = [1]\na[1] = 2\n", "Array index out of bounds"),
+        (
+            "let a = [1]\na[\"x\"] = 2\n",
+            "Array index must be an integer, got string",
+        ),
+        (
+            "let t = (1, 2)\nt[0] = 3\n",
+            "Cannot assign to an element of tuple",
+        ),
+        ("let n = 1\nn.x = 2\n", "Cannot assign to field 'x' on int"),
+        (
+            "let d = {}\nd[[fn() { return 1 }]] = 1\n",
+            "Cannot index a dictionary with array",
+        ),
+    ] {
+        assert!(run(source).contains(error), "{}", source);
+    }
+}
@@ -0,0 +1,60 @@
+// Tests for the AST's JSON form, printed by `veyc parse --format json`
+
+use std::fs;
+use std::path::Path;
+use veyra_compiler::ast::Program;
+use veyra_compiler::lexer::Lexer;
+use veyra_compiler::parser::parse;
+
+#[test]
+fn examples_round_trip_through_json() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    for dir in ["examples", "tests"] {
+        for entry in fs::read_dir(root.join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "vey") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            let Ok(tokens) = Lexer::new(&source).tokenize_with_comments() else {
+                continue;
+            };
+            let Ok(program) = parse(tokens) else {
+                continue;
+            };
+
+            let json = serde_json::to_string(&program).unwrap();
+            let back: Program = serde_json::from_str(&json).unwrap();
+            assert!(back == program, "{} changed through JSON", path.display());
+        }
+    }
+}
+
+#[test]
+fn nodes_are_tagged_with_their_kind() {
+    let tokens = Lexer::new("let x = 1 + 2\n").tokenize().unwrap();
+    let json = serde_json::to_value(parse(tokens).unwrap()).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "items": [{
+                "Statement": {
+                    "VariableDeclaration": {
+                        "name": "x",
+                        "var_type": null,
+                        "initializer": {
+                            "Binary": {
+                                "left": {"Literal": {"Integer": 1}},
+                                "operator": "Add",
+                                "right": {"Literal": {"Integer": 2}}
+                            }
+                        },
+                        "mutable": false,
+                        "pattern": null
+                    }
+                }
+            }],
+            "lines": [1]
+        })
+    );
+}
@@ -0,0 +1,73 @@
+// Tests for the async I/O builtins
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+fn run(source: &str) -> Result<String, String> {
+    let mut interpreter = common::quiet();
+    interpreter
+        .interpret(&common::program(source))
+        .map_err(|e| e.to_string())?;
+    Ok(interpreter.take_output())
+}
+
+#[test]
+fn reads_files_in_the_background() {
+    let dir = common::files(&[("hello.txt", "hello")]);
+    let path = dir.path().join("hello.txt");
+    let output = run(&format!(
+        "let task = read_file_async({:?})\nprint(await task)\n",
+        path.display().to_string()
+    ));
+    assert_eq!(output.unwrap(), "hello\n");
+
+    // A missing file is an error of the task, raised by await
+    let output = run("let task = read_file_async(\"/no/such/file.txt\")\n\
+         try {\n    await task\n} catch e {\n    print(\"caught\")\n}\n");
+    assert_eq!(output.unwrap(), "caught\n");
+}
+
+#[test]
+fn sleeps_overlap() {
+    let start = Instant::now();
+    let output = run(
+        "let timers = [sleep_async(150), sleep_async(150), sleep_async(150)]\n\
+         for timer in timers {\n    await timer\n}\n\
+         print(\"done\")\n",
+    );
+    assert_eq!(output.unwrap(), "done\n");
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(150));
+    assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+}
+
+#[test]
+fn fetches_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        for status in ["200 OK", "404 Not Found"] {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request: Vec<String> = BufReader::new(&stream)
+                .lines()
+                .map(Result::unwrap)
+                .take_while(|line| !line.is_empty())
+                .collect();
+            assert_eq!(request[0], "GET /status HTTP/1.0");
+            write!(stream, "HTTP/1.0 {}\r\nContent-Length: 2\r\n\r\nup", status).unwrap();
+        }
+    });
+
+    let url = format!("http://127.0.0.1:{}/status", port);
+    let output = run(&format!("print(await http_get_async({:?}))\n", url));
+    assert_eq!(output.unwrap(), "up\n");
+    let error = run(&format!("await http_get_async({:?})\n", url)).unwrap_err();
+    assert!(error.contains("404 Not Found"), "{}", error);
+    server.join().unwrap();
+
+    let error = run("http_get_async(\"https://example.com\")\n").unwrap_err();
+    assert!(error.contains("https is not supported"), "{}", error);
+}
@@ -0,0 +1,140 @@
+// Tests for lowering programs to bytecode and the `.veyb` format
+
+use veyra_compiler::ast::BinaryOperator;
+use veyra_compiler::bytecode::{
+    compile, CaptureFrom, Chunk, ChunkKind, Instruction, Module, MAGIC,
+};
+use veyra_compiler::parser::parse;
+use veyra_compiler::Lexer;
+
+fn compiled(source: &str) -> Module {
+    let tokens = Lexer::new(source).tokenize().unwrap();
+    compile(&parse(tokens).unwrap()).unwrap()
+}
+
+fn chunk<'a>(module: &'a Module, name: &str) -> &'a Chunk {
+    module.chunks.iter().find(|c| c.name == name).unwrap()
+}
+
+/// The names a chunk loads by name rather than from a slot
+fn names_loaded(module: &Module, chunk: &Chunk) -> Vec<String> {
+    chunk
+        .code
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::LoadName(name) => Some(module.name(*name).to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn round_trips_through_bytes() {
+    let module = compiled(
+        "struct Point {\n    x: int\n    y: int\n}\n\
+         fn norm(p: Point) -> int {\n    return p.x * p.x + p.y * p.y\n}\n\
+         let points = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]\n\
+         for p in points {\n    match norm(p) {\n        5 -> print(\"five\")\n        n if n > 20 -> print(\"far {n}\")\n        _ -> print(\"near\")\n    }\n}\n\
+         let double = |x| x * 2.5\n\
+         try {\n    throw double(1)\n} catch e {\n    print(e)\n} finally {\n    print(\"done\")\n}\n",
+    );
+    let bytes = module.to_bytes();
+    assert_eq!(bytes[..4], MAGIC);
+    assert_eq!(Module::from_bytes(&bytes).unwrap(), module);
+
+    assert!(Module::from_bytes(b"not bytecode").is_err());
+    let mut newer = bytes.clone();
+    newer[4] += 1;
+    let error = Module::from_bytes(&newer).unwrap_err().to_string();
+    assert!(error.contains("format version"), "{}", error);
+}
+
+#[test]
+fn keeps_function_variables_in_slots() {
+    let module = compiled(
+        "let scale = 3\n\
+         fn total(values) {\n    let mut sum = 0\n    for v in values {\n        sum += v * scale\n    }\n    return sum\n}\n\
+         print(total([1, 2]))\n",
+    );
+    let total = chunk(&module, "total");
+    assert_eq!(total.kind, ChunkKind::Function);
+    assert_eq!(total.parameters, ["values"]);
+    // Only the top-level variable is looked up by name
+    assert_eq!(names_loaded(&module, total), ["scale"]);
+
+    let entry = module.chunk(module.entry);
+    assert_eq!(entry.kind, ChunkKind::Entry);
+    assert!(entry.code.contains(&Instruction::DefineGlobal(
+        module.names.iter().position(|n| n == "scale").unwrap() as u32
+    )));
+    assert_eq!(module.functions.len(), 1);
+}
+
+#[test]
+fn copies_captured_variables_into_lambdas() {
+    let module = compiled(
+        "fn adder(n) {\n    let offset = n + 1\n    return |x| x + offset\n}\n\
+         print(adder(1)(2))\n",
+    );
+    let lambda = chunk(&module, "<lambda>");
+    assert_eq!(lambda.kind, ChunkKind::Lambda);
+    assert_eq!(lambda.captures.len(), 1);
+    assert_eq!(lambda.captures[0].from, CaptureFrom::Slot(1));
+    assert_eq!(names_loaded(&module, lambda), Vec::<String>::new());
+}
+
+#[test]
+fn runs_finally_blocks_before_returning() {
+    let module = compiled(
+        "fn read() {\n    try {\n        return 1\n    } finally {\n        print(\"closed\")\n    }\n}\n",
+    );
+    let code = &chunk(&module, "read").code;
+    let print = module.names.iter().position(|n| n == "print").unwrap() as u32;
+    let returned = code.iter().position(|i| *i == Instruction::Return).unwrap();
+    // The return leaves the handler and prints before it returns, and the
+    // handler prints again on an error
+    assert_eq!(code[2], Instruction::PopHandler);
+    assert!(code[..returned].contains(&Instruction::Call(print, 1)));
+    let calls = code
+        .iter()
+        .filter(|i| **i == Instruction::Call(print, 1))
+        .count();
+    assert_eq!(calls, 3);
+    assert!(code.contains(&Instruction::Reraise));
+}
+
+#[test]
+fn rejects_break_outside_of_a_loop() {
+    let tokens = Lexer::new("fn f() {\n    break\n}\n").tokenize().unwrap();
+    let error = compile(&parse(tokens).unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "'break' outside of a loop");
+}
+
+#[test]
+fn records_the_line_of_each_statement() {
+    let module = compiled(
+        "fn half(n) {\n    let m = n\n    return m / 2\n}\n\n\
+         let double = |x| x * 2\n\
+         print(half(4))\n",
+    );
+    let half = chunk(&module, "half");
+    let divide = half
+        .code
+        .iter()
+        .position(|i| *i == Instruction::Binary(BinaryOperator::Divide))
+        .unwrap();
+    assert_eq!(half.line(0), Some(2));
+    assert_eq!(half.line(divide), Some(3));
+    let lambda = module
+        .chunks
+        .iter()
+        .find(|c| c.kind == ChunkKind::Lambda)
+        .unwrap();
+    assert!((0..lambda.code.len()).all(|offset| lambda.line(offset) == Some(6)));
+    let entry = &module.chunks[module.entry as usize];
+    assert_eq!(entry.line(entry.code.len() - 1), Some(7));
+
+    // The tables are part of the `.veyb` file
+    let loaded = Module::from_bytes(&module.to_bytes()).unwrap();
+    assert_eq!(chunk(&loaded, "half").lines, half.lines);
+}
@@ -0,0 +1,117 @@
+// Tests for `veyc check --message-format json`
+
+mod common;
+
+use std::process::Command;
+
+/// Check `source` as a file of its own and return the exit code and the JSON
+/// diagnostics printed, one per line
+fn check(name: &str, source: &str) -> (Option<i32>, Vec<serde_json::Value>) {
+    let dir = common::files(&[(name, source)]);
+    let file = dir.path().join(name);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_veyc"))
+        .args(["check", "--message-format", "json"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    let diagnostics = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    (output.status.code(), diagnostics)
+}
+
+#[test]
+fn prints_one_object_per_diagnostic() {
+    let (code, diagnostics) = check(
+        "types.vey",
+        "let total = 3\nprint(totl)\nlet count: int = \"three\"\n",
+    );
+    assert_eq!(code, Some(1));
+    assert_eq!(diagnostics.len(), 2);
+    let first = &diagnostics[0];
+    assert_eq!(first["severity"], "error");
+    assert_eq!(first["code"], "undefined-name");
+    assert_eq!(first["error_code"], "E0012");
+    assert_eq!(first["message"], "'totl' is not defined");
+    assert_eq!(first["suggestion"], "did you mean 'total'?");
+    assert!(first["span"]["file"]
+        .as_str()
+        .unwrap()
+        .ends_with("types.vey"));
+    assert_eq!(first["span"]["line"], 2);
+    assert_eq!(first["span"]["column"], 7);
+    assert_eq!(diagnostics[1]["code"], "type-mismatch");
+    assert_eq!(diagnostics[1]["span"]["line"], 3);
+    assert!(diagnostics[1]["suggestion"].is_null());
+
+    // Every syntax error is reported, and nothing else is printed
+    let (code, diagnostics) = check("syntax.vey", "fn f( {\n}\nlet = 3\n");
+    assert_eq!(code, Some(1));
+    let lines: Vec<_> = diagnostics.iter().map(|d| &d["span"]["line"]).collect();
+    assert_eq!(lines, [1, 3]);
+    assert!(diagnostics.iter().all(|d| d["code"] == "parse-error"));
+
+    let (code, diagnostics) = check("clean.vey", "let total = 3\nprint(total)\n");
+    assert_eq!((code, diagnostics.len()), (Some(0), 0));
+}
+
+#[test]
+fn imports_resolve_as_they_do_when_run() {
+    // lib/helper.vey imports a module next to the entry file, and the entry
+    // file imports an installed package by its main.vey
+    let project = common::files(&[
+        ("veyra.toml", "name = \"app\"\n"),
+        (
+            "app/main.vey",
+            "import lib.helper\nimport greet\nprint(helper::twice(greet::NAME))\n",
+        ),
+        (
+            "app/lib/helper.vey",
+            "import shared\nfn twice(s) { return s + shared::SEP + s }\n",
+        ),
+        ("app/shared.vey", "let SEP = \"-\"\n"),
+        ("veyra-modules/greet/main.vey", "let NAME = \"hi\"\n"),
+        ("app/broken.vey", "import missing\n"),
+    ]);
+    // Run from outside the project, which must be found from the entry file
+    let elsewhere = tempfile::tempdir().unwrap();
+    let veyc = |args: &[&str], file: &str| {
+        Command::new(env!("CARGO_BIN_EXE_veyc"))
+            .args(args)
+            .arg(project.path().join(file))
+            .current_dir(elsewhere.path())
+            .output()
+            .unwrap()
+    };
+
+    let run = veyc(&["run"], "app/main.vey");
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    assert!(String::from_utf8_lossy(&run.stdout).ends_with("\nhi-hi\n"));
+    let check = veyc(
+        &["check", "--project", "--message-format", "json"],
+        "app/main.vey",
+    );
+    assert_eq!(
+        check.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&check.stdout)
+    );
+
+    let check = veyc(
+        &["check", "--project", "--message-format", "json"],
+        "app/broken.vey",
+    );
+    assert_eq!(check.status.code(), Some(1));
+    let diagnostic: serde_json::Value =
+        serde_json::from_str(String::from_utf8_lossy(&check.stdout).trim()).unwrap();
+    assert_eq!(diagnostic["code"], "unresolved-import");
+    assert_eq!(diagnostic["message"], "Unresolved import 'missing'");
+    assert_eq!(diagnostic["span"]["file"], "app/broken.vey");
+}
@@ -0,0 +1,88 @@
+// Helpers shared by the integration tests
+//
+// Each test file is its own crate and uses only some of these.
+#![allow(dead_code)]
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use veyra_compiler::bytecode::compile;
+use veyra_compiler::error::Result;
+use veyra_compiler::interpreter::Value;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::{vm, Interpreter, Program};
+
+pub fn program(source: &str) -> Program {
+    parse(tokenize(source).unwrap()).unwrap()
+}
+
+/// An interpreter that keeps what programs print instead of echoing it
+pub fn quiet() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_echo_output(false);
+    interpreter
+}
+
+/// A temporary directory holding `files`, each a path relative to it and
+/// the file's contents
+pub fn files(files: &[(&str, &str)]) -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (name, source) in files {
+        write(dir.path(), name, source);
+    }
+    dir
+}
+
+/// Write `source` to `name` in `dir`, making its directories, and return
+/// the file's canonical path
+pub fn write(dir: &Path, name: &str, source: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, source).unwrap();
+    path.canonicalize().unwrap()
+}
+
+/// What a program prints, or the error it stops with, on both engines,
+/// which must agree
+pub fn run(source: &str) -> String {
+    run_with(source, |_| {})
+}
+
+/// `run` with `setup` applied to each engine's interpreter first
+pub fn run_with(source: &str, setup: impl Fn(&mut Interpreter)) -> String {
+    run_program_with(&program(source), setup)
+}
+
+/// `run_with` for a program already parsed
+pub fn run_program_with(program: &Program, setup: impl Fn(&mut Interpreter)) -> String {
+    on_both(program, setup, |result, interpreter| {
+        let mut output = interpreter.take_output();
+        if let Err(error) = result {
+            output.push_str(&error.to_string());
+        }
+        output
+    })
+}
+
+/// What `finish` makes of the result of running `program` on each engine,
+/// with `setup` applied to its interpreter first, which must agree
+pub fn on_both<T: PartialEq + Debug>(
+    program: &Program,
+    setup: impl Fn(&mut Interpreter),
+    finish: impl Fn(Result<Value>, &mut Interpreter) -> T,
+) -> T {
+    let [tree, bytecode] = [false, true].map(|use_vm| {
+        let mut interpreter = quiet();
+        setup(&mut interpreter);
+        let result = match use_vm {
+            false => interpreter.interpret(program),
+            true => vm::run(&mut interpreter, compile(program).unwrap()),
+        };
+        finish(result, &mut interpreter)
+    });
+    assert_eq!(tree, bytecode);
+    tree
+}
@@ -0,0 +1,26 @@
+// Tests for building strings with `s = s + piece`, which appends in place
+
+mod common;
+
+use common::run;
+
+#[test]
+fn appends_literals_and_variables() {
+    let source = "let mut s = \"\"\nlet piece = \"ab\"\n\
+                  for i in range(3) {\n    s = s + piece + \"-\"\n}\nprint(s)\n\
+                  let t = \"x\"\nlet u = t\nt = t + \"y\"\nprint(t, u)\n";
+    assert_eq!(run(source), "ab-ab-ab-\nxy x\n");
+}
+
+#[test]
+fn reads_the_string_before_calls_change_it() {
+    let source = "let s = \"a\"\n\
+                  fn f() {\n    s = \"ZZZ\"\n    return \"b\"\n}\n\
+                  s = s + f()\nprint(s)\n";
+    assert_eq!(run(source), "ab\n");
+
+    let source = "let t = \"x\"\n\
+                  fn g() {\n    t = 5\n    return \"y\"\n}\n\
+                  t = t + g()\nprint(t)\n";
+    assert_eq!(run(source), "xy\n");
+}
@@ -0,0 +1,94 @@
+// Tests for converting between Rust values and interpreter values
+
+mod common;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use veyra_compiler::convert::{from_value, to_value};
+use veyra_compiler::interpreter::Value;
+use veyra_compiler::Interpreter;
+
+/// A quiet interpreter with the functions and types of `source` defined
+fn interpreter(source: &str) -> Interpreter {
+    let mut interpreter = common::quiet();
+    interpreter
+        .load_definitions(&common::program(source))
+        .unwrap();
+    interpreter
+}
+
+#[test]
+fn converts_arguments_and_results() {
+    let mut interpreter = interpreter(
+        "fn totals(scores) {\n    return {\"ada\": array_sum(scores[\"ada\"]), \"bob\": array_sum(scores[\"bob\"])}\n}\n\
+         fn find(names, wanted) {\n    for name in names {\n        if name == wanted {\n            return Some((name, len(name)))\n        }\n    }\n    return None\n}\n",
+    );
+
+    let scores = HashMap::from([
+        ("ada".to_string(), vec![3, 4]),
+        ("bob".to_string(), vec![10]),
+    ]);
+    let totals = interpreter.call("totals", &[scores.into()]).unwrap();
+    let totals: HashMap<String, i64> = totals.try_into().unwrap();
+    assert_eq!(
+        totals,
+        HashMap::from([("ada".to_string(), 7), ("bob".to_string(), 10)])
+    );
+
+    let names: Value = vec!["ada", "bob"].into();
+    let found = interpreter
+        .call("find", &[names.clone(), "bob".into()])
+        .unwrap();
+    assert_eq!(
+        Option::<(String, u32)>::try_from(found).unwrap(),
+        Some(("bob".to_string(), 3))
+    );
+    let missing = interpreter.call("find", &[names, "eve".into()]).unwrap();
+    assert_eq!(Option::<(String, u32)>::try_from(missing).unwrap(), None);
+
+    let error = i32::try_from(Value::String("3".to_string())).unwrap_err();
+    assert_eq!(error.to_string(), "Type Error: Expected int, got string");
+    let error = u8::try_from(Value::Integer(300)).unwrap_err();
+    assert_eq!(error.to_string(), "Type Error: 300 does not fit in u8");
+    assert_eq!(f64::try_from(Value::Integer(2)).unwrap(), 2.0);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Point {
+    x: i64,
+    y: f64,
+    label: Option<String>,
+}
+
+#[test]
+fn converts_serde_types() {
+    let mut interpreter = interpreter(
+        "struct Point {\n    x: int\n    y: float\n    label: string\n}\n\
+         fn moved(p) {\n    return Point { x: p[\"x\"] + 1, y: p[\"y\"] * 2.0, label: \"moved\" }\n}\n",
+    );
+
+    let point = Point {
+        x: 1,
+        y: 1.5,
+        label: None,
+    };
+    let value = to_value(&point).unwrap();
+    let moved: Point = from_value(interpreter.call("moved", &[value]).unwrap()).unwrap();
+    assert_eq!(
+        moved,
+        Point {
+            x: 2,
+            y: 3.0,
+            label: Some("moved".to_string()),
+        }
+    );
+
+    let error = from_value::<Point>(Value::Integer(1)).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Type Error: Cannot convert value"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,34 @@
+// Tests for the `copy` and `deep_copy` builtins
+
+mod common;
+
+use common::run;
+
+#[test]
+fn a_copy_shares_the_references_it_holds() {
+    let source = "let inner = &mut [1, 2]\nlet outer = [inner, 3]\n\
+                  let copied = copy(outer)\n(*inner)[0] = 10\n\
+                  print(*outer[0], *copied[0])\n\
+                  let r = copy(inner)\n(*inner)[1] = 20\nprint(*inner, *r)\n";
+    assert_eq!(run(source), "[10, 2] [10, 2]\n[10, 20] [10, 2]\n");
+}
+
+#[test]
+fn a_deep_copy_shares_nothing_with_the_original() {
+    let source = "struct Holder {\n    rows: any\n}\n\
+                  let inner = &mut [1, 2]\nlet outer = Holder { rows: [inner, inner] }\n\
+                  let copied = deep_copy(outer)\n(*inner)[0] = 10\n\
+                  print(*outer.rows[0], *copied.rows[0])\n\
+                  (*copied.rows[0])[1] = 5\nprint(*copied.rows[1], *inner)\n";
+    // The copy's two rows still alias each other, but not the original
+    assert_eq!(run(source), "[10, 2] [1, 2]\n[1, 5] [10, 2]\n");
+}
+
+#[test]
+fn a_value_that_contains_itself_is_deep_copied() {
+    let source = "let r = &mut [0, None]\n(*r)[1] = r\n\
+                  let copied = deep_copy(r)\n\
+                  print((*copied)[1] == copied, (*copied)[1] == r)\n\
+                  (*copied)[0] = 5\nprint((*r)[0], (*(*copied)[1])[0])\n";
+    assert_eq!(run(source), "true false\n0 5\n");
+}
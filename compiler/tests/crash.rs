@@ -0,0 +1,83 @@
+// Tests for crash reports and for shrinking the input of a crash, which
+// both rely on catching panics rather than aborting
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::panic;
+use veyra_compiler::crash;
+
+// One test, as the panic hook and the report it writes are per process
+#[test]
+fn reports_crashes_and_minimizes_their_input() {
+    let cache = tempfile::tempdir().unwrap();
+    std::env::set_var("XDG_CACHE_HOME", cache.path());
+    let input = cache.path().join("input.vey");
+    let source = "let a = 1\nprint(a)\nlet b = [1, 2\nprint(b)\n";
+    fs::write(&input, source).unwrap();
+
+    crash::install(Some(input.clone()));
+    assert!(panic::catch_unwind(|| panic!("veyc bug")).is_err());
+    let report_path = crash::report_path().expect("no crash report written");
+    let report = fs::read_to_string(&report_path).unwrap();
+    assert!(report.starts_with(&format!("veyc {} crashed\n", veyra_compiler::VERSION)));
+    assert!(
+        report.contains("panic: veyc bug at tests/crash.rs:"),
+        "{}",
+        report
+    );
+    let hash: String = Sha256::digest(source)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert!(report.contains(&format!("input: {} (sha256 {})", input.display(), hash)));
+    assert!(report.contains("\nbacktrace:\n"));
+
+    // Input that does not crash on its own is not minimized
+    assert_eq!(crash::minimize_crash(&input, |_| {}), None);
+
+    let minimized = crash::minimize_crash(&input, |source| {
+        if source.contains("[1") {
+            panic!("unclosed array");
+        }
+    })
+    .expect("the input was not minimized");
+    assert_eq!(fs::read_to_string(&minimized).unwrap(), "[1");
+    let report = fs::read_to_string(&report_path).unwrap();
+    assert!(report.ends_with(&format!("\nminimized input: {}\n", minimized.display())));
+
+    // Panics elsewhere do not count as the same crash
+    let other = crash::minimize_crash(&input, |source| {
+        if source.contains("[1") {
+            panic!("unclosed array");
+        }
+        if source.contains("print") {
+            panic!("print");
+        }
+    });
+    assert_eq!(fs::read_to_string(other.unwrap()).unwrap(), "[1");
+
+    let _ = panic::take_hook();
+}
+
+#[cfg(unix)]
+#[test]
+fn a_closed_pipe_ends_veyc_without_a_crash_report() {
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("main.vey");
+    fs::write(&input, "print(1)\n".repeat(20_000)).unwrap();
+    let mut veyc = Command::new(env!("CARGO_BIN_EXE_veyc"))
+        .arg("lex")
+        .arg(&input)
+        .env("XDG_CACHE_HOME", dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(veyc.stdout.take());
+    let output = veyc.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("crashed"), "{}", stderr);
+    assert!(!dir.path().join("veyra").exists());
+}
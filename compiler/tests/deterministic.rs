@@ -0,0 +1,38 @@
+// Tests for deterministic mode, used by `veyc run --deterministic`
+
+mod common;
+
+use veyra_compiler::interpreter::DETERMINISTIC_NOW;
+
+/// What a program prints in deterministic mode, on both engines, which must
+/// agree
+fn output(source: &str) -> String {
+    common::run_with(source, |interpreter| interpreter.set_deterministic(true))
+}
+
+#[test]
+fn the_clock_is_fixed() {
+    let source = "let start = now()\nprint(start)\nprint(now() - start)\n";
+    assert_eq!(output(source), format!("{}\n0\n", DETERMINISTIC_NOW));
+
+    let source = "let x = trace(6 * 7)\ntrace(x + 1, \"next\")\ntrace_dump()\n";
+    assert_eq!(
+        output(source),
+        "[    0.000ms] 6 * 7 = 42\n[    0.000ms] next = 43\n"
+    );
+}
+
+#[test]
+fn runs_print_the_same_output() {
+    let source = "let scores = {\"dave\": 3, \"amy\": 9, \"carl\": 1, \"bea\": 4}\n\
+                  let tags = {\"z\", \"m\", \"a\", \"q\"}\n\
+                  print(scores)\nprint(tags)\nprint([scores, tags, now()])\n";
+    let first = output(source);
+    assert_eq!(
+        first.lines().next(),
+        Some("{\"amy\": 9, \"bea\": 4, \"carl\": 1, \"dave\": 3}")
+    );
+    for _ in 0..10 {
+        assert_eq!(output(source), first);
+    }
+}
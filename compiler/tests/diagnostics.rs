@@ -0,0 +1,149 @@
+// Tests for diagnostics rendered for people, under the offending source line
+
+mod common;
+
+use common::{program, quiet, run};
+use std::path::Path;
+use veyra_compiler::bytecode::compile;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::{vm, Diagnostic};
+
+/// Render the error `source` stops with, as `veyc run` prints it
+fn render(source: &str) -> String {
+    let error = match tokenize(source).and_then(parse) {
+        Ok(program) => quiet().interpret(&program).unwrap_err(),
+        Err(error) => error,
+    };
+    Diagnostic::from_error(&error, Path::new("main.vey")).render(Some(source))
+}
+
+#[test]
+fn parse_errors_point_at_the_token_they_found() {
+    assert_eq!(
+        render("let total = 0\nfn add(a, {\n}\n"),
+        "error[E0002]: Expected parameter name\n \
+         --> main.vey:2:11\n  \
+         |\n\
+         2 | fn add(a, {\n  \
+         |           ^ found '{'\n"
+    );
+    assert_eq!(
+        render("let name = \"Ada\"\nprint(name 42)\n"),
+        "error[E0002]: Expected ')' after arguments\n \
+         --> main.vey:2:12\n  \
+         |\n\
+         2 | print(name 42)\n  \
+         |            ^^ found '42'\n"
+    );
+    assert!(render("fn main() {\n    print(1)\n").ends_with("^ found end of file\n"));
+    assert!(render("let x = 1 +\n").ends_with("^ found end of line\n"));
+    assert!(render("fn add(&a) {\n}\n").ends_with("^ found '&'\n"));
+    assert!(render("fn add(a <<= 2) {\n}\n").ends_with("^^^ found '<<='\n"));
+}
+
+#[test]
+fn lex_errors_show_the_line_and_tabs_keep_the_caret_aligned() {
+    assert_eq!(
+        render("if true {\n\tlet price = 5 ` 2\n}\n"),
+        "error[E0001]: Unexpected character '`'\n \
+         --> main.vey:2:16\n  \
+         |\n\
+         2 |     let price = 5 ` 2\n  \
+         |                   ^\n"
+    );
+}
+
+#[test]
+fn runtime_errors_point_at_their_statement_and_keep_their_notes() {
+    assert_eq!(
+        render("let count = 1\nprint(cout)\n"),
+        "error[E0004]: Undefined variable 'cout'\n \
+         --> main.vey:2\n  \
+         |\n\
+         2 | print(cout)\n  \
+         | ^^^^^^^^^^^\n  \
+         = help: did you mean 'count'?\n"
+    );
+    assert_eq!(
+        render("throw \"disk full\"\n"),
+        "error[E0006]: disk full\n \
+         --> main.vey:1\n  \
+         |\n\
+         1 | throw \"disk full\"\n  \
+         | ^^^^^^^^^^^^^^^^^\n  \
+         = note: the value was thrown and no try block caught it\n"
+    );
+    // Inside a call, the line is the one of the statement that failed
+    assert!(
+        render("fn f() {\n    let a = 1\n    return a / 0\n}\nprint(f())\n")
+            .contains("--> main.vey:3\n")
+    );
+}
+
+#[test]
+fn unterminated_strings_point_at_their_opening_quote() {
+    assert_eq!(
+        render("let s = \"abc\nprint(1)\n"),
+        "error[E0001]: Unterminated string literal\n \
+         --> main.vey:1:9\n  \
+         |\n\
+         1 | let s = \"abc\n  \
+         |         ^\n"
+    );
+}
+
+#[test]
+fn long_lines_are_cut_around_the_caret() {
+    let items: Vec<String> = (0..60).map(|i| i.to_string()).collect();
+    let rendered = render(&format!("print([{}] z)\n", items.join(", ")));
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert!(lines[3].starts_with("1 | ..., 36, 37,"));
+    assert!(lines[3].ends_with("59] z)"));
+    assert_eq!(lines[3].find('z'), lines[4].find('^'));
+    assert!(lines[4].ends_with("^ found 'z'"));
+}
+
+#[test]
+fn vm_errors_point_at_the_line_that_failed() {
+    let source = "fn half(n) {\n    let m = n\n    return m / 0\n}\nprint(half(4))\n";
+    let error = vm::run(&mut quiet(), compile(&program(source)).unwrap()).unwrap_err();
+    assert_eq!(error.to_string(), "Runtime Error: Division by zero");
+    assert_eq!(
+        Diagnostic::from_error(&error, Path::new("main.vey")).render(Some(source)),
+        "error[E0004]: Division by zero\n \
+         --> main.vey:3\n  \
+         |\n\
+         3 |     return m / 0\n  \
+         |     ^^^^^^^^^^^^\n"
+    );
+
+    // Located errors are still caught as they were thrown
+    assert_eq!(
+        run("fn f() {\n    throw 5\n}\ntry {\n    f()\n} catch e {\n    print(e + 1)\n}\n"),
+        "6\n"
+    );
+}
+
+#[test]
+fn suggestions_become_help_lines() {
+    let diagnostic = Diagnostic::error("undefined-name", "'y' is not defined")
+        .with_span(Path::new("src/main.vey"), Some(1), Some(7))
+        .with_suggestion("did you mean 'x'?");
+    assert_eq!(
+        diagnostic.render(Some("print(y)\n")),
+        "error[E0012]: 'y' is not defined\n \
+         --> src/main.vey:1:7\n  \
+         |\n\
+         1 | print(y)\n  \
+         |       ^\n  \
+         = help: did you mean 'x'?\n"
+    );
+    // Without the source, only the message and where it is are shown
+    assert_eq!(
+        diagnostic.render(None),
+        "error[E0012]: 'y' is not defined\n \
+         --> src/main.vey:1:7\n  \
+         = help: did you mean 'x'?\n"
+    );
+}
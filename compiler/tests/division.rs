@@ -0,0 +1,70 @@
+// Tests for `/`, floor division with `//`, and `%` on ints and floats
+
+mod common;
+
+use common::run;
+
+#[test]
+fn dividing_ints_gives_a_float() {
+    let source = "print(7 / 2, 8 / 2, -7 / 2)\n\
+                  print(repr(6 / 3), type_of(6 / 3))\n\
+                  let mut y = 7\n\
+                  y /= 2\n\
+                  print(y)\n";
+    assert_eq!(run(source), "3.5 4 -3.5\n2.0 float\n3.5\n");
+}
+
+#[test]
+fn floor_division_rounds_down() {
+    let source = "print(7 // 2, -7 // 2, 7 // -2, -7 // -2)\n\
+                  print(repr(7 // 2), repr(7.0 // 2), repr(-7.5 // 2))\n\
+                  let mut x = 9\n\
+                  x //= 2\n\
+                  print(x)\n";
+    assert_eq!(run(source), "3 -4 -4 3\n3 3.0 -4.0\n4\n");
+}
+
+#[test]
+fn modulo_takes_the_sign_of_the_divisor() {
+    let source = "print(7 % 3, -7 % 3, 7 % -3, -7 % -3)\n\
+                  print(7.5 % 2, -7.5 % 2, 7.5 % -2)\n\
+                  let mut z = 7.5\n\
+                  z %= 2\n\
+                  print(z)\n";
+    assert_eq!(run(source), "1 2 -2 -1\n1.5 0.5 -0.5\n1.5\n");
+}
+
+#[test]
+fn dividing_by_zero_is_an_error() {
+    for (source, error) in [
+        ("print(1 / 0)\n", "Division by zero"),
+        ("print(1.0 / 0)\n", "Division by zero"),
+        ("print(1 // 0)\n", "Division by zero"),
+        ("print(1 // 0.0)\n", "Division by zero"),
+        ("print(5 % 0)\n", "Modulo by zero"),
+        ("print(5.5 % 0)\n", "Modulo by zero"),
+    ] {
+        assert_eq!(
+            run(source),
+            format!("Runtime Error: {}", error),
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        run("try {\n    print(1 // 0)\n} catch error {\n    print(\"caught: \" + error)\n}\n"),
+        "caught: Division by zero\n"
+    );
+}
+
+#[test]
+fn floor_dividing_the_smallest_int_by_minus_one_overflows() {
+    assert_eq!(
+        run("let min = -9223372036854775807 - 1\nprint(min // -1)\n"),
+        "Runtime Error: Integer overflow in division"
+    );
+    assert_eq!(
+        run("let min = -9223372036854775807 - 1\nprint(min % -1)\n"),
+        "0\n"
+    );
+}
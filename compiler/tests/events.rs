@@ -0,0 +1,31 @@
+// Tests for the event log shared by the tools
+
+use std::path::Path;
+use std::time::Duration;
+use veyra_compiler::events::Event;
+use veyra_compiler::{Diagnostic, Severity};
+
+#[test]
+fn events_have_the_same_fields_whatever_they_report() {
+    let diagnostic = Diagnostic::error("type-error", "'y' is not defined")
+        .with_span(Path::new("src/main.vey"), Some(2), Some(8))
+        .with_suggestion("did you mean 'x'?");
+    assert_eq!(
+        Event::from_diagnostic("veyc", &diagnostic).to_json(),
+        "{\"tool\":\"veyc\",\"file\":\"src/main.vey\",\"span\":{\"line\":2,\"column\":8},\
+         \"severity\":\"error\",\"message\":\"'y' is not defined (help: did you mean 'x'?)\",\
+         \"duration_ms\":null}"
+    );
+
+    let finished = Event::new("veyra-fmt", Severity::Note, "0 of 3 files need formatting")
+        .took(Duration::from_micros(1500));
+    assert_eq!(
+        finished.to_json(),
+        "{\"tool\":\"veyra-fmt\",\"file\":null,\"span\":null,\"severity\":\"note\",\
+         \"message\":\"0 of 3 files need formatting\",\"duration_ms\":1.5}"
+    );
+    assert_eq!(
+        serde_json::from_str::<Event>(&finished.to_json()).unwrap(),
+        finished
+    );
+}
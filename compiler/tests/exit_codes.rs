@@ -0,0 +1,50 @@
+// Tests for program arguments and exit codes
+
+mod common;
+
+use veyra_compiler::interpreter::exit_code;
+
+/// Run a program with arguments on both engines, returning its exit code,
+/// or the message of the error it stopped with, and its output
+fn run(source: &str, args: &[&str]) -> (Result<i32, String>, String) {
+    common::on_both(
+        &common::program(source),
+        |interpreter| interpreter.set_args(args.iter().map(|arg| arg.to_string()).collect()),
+        |result, interpreter| {
+            let code = exit_code(result).map_err(|error| error.to_string());
+            (code, interpreter.take_output())
+        },
+    )
+}
+
+#[test]
+fn main_returns_the_exit_code() {
+    let source = "fn main() {\n    let a = args()\n    print(a)\n    return len(a)\n}\n";
+    let (code, output) = run(source, &["in.txt", "--fast"]);
+    assert_eq!(code.unwrap(), 2);
+    assert_eq!(output, "[\"in.txt\", \"--fast\"]\n");
+
+    // Other return values, and programs without main, exit with 0
+    assert_eq!(
+        run("fn main() {\n    return \"done\"\n}\n", &[]).0.unwrap(),
+        0
+    );
+    assert_eq!(run("print(1)\n", &[]).0.unwrap(), 0);
+}
+
+#[test]
+fn exit_ends_the_program_past_catch() {
+    let source = "fn check(n) {\n    if n > 2 {\n        exit(n)\n    }\n}\n\
+                  try {\n    check(1)\n    check(5)\n    print(\"unreachable\")\n} catch e {\n    print(\"caught\")\n}\n\
+                  print(\"unreachable\")\n";
+    let (code, output) = run(source, &[]);
+    assert_eq!(code.unwrap(), 5);
+    assert_eq!(output, "");
+
+    assert_eq!(run("exit()\nexit(1)\n", &[]).0.unwrap(), 0);
+    let (code, _) = run("exit(\"no\")\n", &[]);
+    assert_eq!(
+        code.unwrap_err(),
+        "Runtime Error: exit() takes an optional integer exit code"
+    );
+}
@@ -0,0 +1,105 @@
+// Tests for the error codes explained by `veyc explain`
+
+mod common;
+
+use veyra_compiler::error::Unwind;
+use veyra_compiler::explain::{self, ERROR_CODES};
+use veyra_compiler::lexer::{tokenize, Lexer};
+use veyra_compiler::parser::parse_recovering;
+use veyra_compiler::{typeck, Diagnostic, VeyraError};
+
+/// Names of the errors `veyc check` and then `veyc run` report for `source`
+fn errors(source: &str) -> Vec<&'static str> {
+    let tokens = match tokenize(source) {
+        Ok(tokens) => tokens,
+        Err(error) => return vec![error.name()],
+    };
+    let (program, errors) = parse_recovering(tokens);
+    if !errors.is_empty() {
+        return errors.iter().map(VeyraError::name).collect();
+    }
+    let (tokens, _) = Lexer::new(source).tokenize_with_ranges();
+    let found: Vec<&str> = typeck::check(&program, &tokens)
+        .iter()
+        .map(|error| error.code)
+        .collect();
+    if !found.is_empty() {
+        return found;
+    }
+
+    match common::quiet().interpret(&program) {
+        Ok(_) => Vec::new(),
+        Err(error) => vec![error.name()],
+    }
+}
+
+#[test]
+fn examples_show_their_error_and_the_fixes_remove_it() {
+    for error in ERROR_CODES {
+        if let Some(example) = error.example {
+            assert!(
+                errors(example).contains(&error.name),
+                "the example for {} gives {:?}",
+                error.code,
+                errors(example)
+            );
+        }
+        if let Some(fixed) = error.fixed {
+            assert_eq!(errors(fixed), Vec::<&str>::new(), "{} fixed", error.code);
+        }
+    }
+}
+
+#[test]
+fn codes_are_numbered_in_order_and_cover_every_error() {
+    for (i, error) in ERROR_CODES.iter().enumerate() {
+        assert_eq!(error.code, format!("E{:04}", i + 1));
+        assert!(error
+            .explanation()
+            .starts_with(&format!("{}: ", error.code)));
+    }
+
+    let every_kind = [
+        VeyraError::lex_error(1, 1, ""),
+        VeyraError::parse_error(1, 1, ""),
+        VeyraError::type_error(""),
+        VeyraError::runtime_error(""),
+        VeyraError::halted(""),
+        VeyraError::Thrown {
+            message: String::new(),
+        },
+        VeyraError::Exit(0),
+        VeyraError::Unwind(Unwind::Break),
+        VeyraError::IoError(String::new()),
+        VeyraError::ConfigError(String::new()),
+        VeyraError::InternalError(String::new()),
+    ];
+    for error in &every_kind {
+        assert_eq!(explain::code_for(error.name()), Some(error.code()));
+    }
+    for name in [
+        "undefined-name",
+        "type-mismatch",
+        "unknown-field",
+        "wrong-arguments",
+        "unresolved-import",
+    ] {
+        assert!(explain::code_for(name).is_some(), "{}", name);
+    }
+}
+
+#[test]
+fn codes_are_looked_up_however_they_are_written() {
+    for code in ["E0007", "e0007", "E7", "0007", "misplaced-control-flow"] {
+        assert_eq!(explain::lookup(code).map(|error| error.code), Some("E0007"));
+    }
+    assert!(explain::lookup("E9999").is_none());
+    assert!(explain::lookup("E").is_none());
+
+    let diagnostic = Diagnostic::error("wrong-arguments", "'add' takes 2 arguments");
+    assert_eq!(diagnostic.error_code, Some("E0015"));
+    assert!(diagnostic.to_json().contains("\"error_code\":\"E0015\""));
+    assert!(!Diagnostic::error("deprecated", "")
+        .to_json()
+        .contains("error_code"));
+}
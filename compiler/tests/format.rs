@@ -0,0 +1,80 @@
+// Tests for the `format` builtin and `print` with a format string
+
+mod common;
+
+use common::run;
+
+#[test]
+fn fills_in_placeholders() {
+    let source = "let x = 3\nlet y = 2.5\n\
+                  print(format(\"x={}, y={}\", x, y))\n\
+                  print(\"{1} before {0}\", \"a\", \"b\")\n\
+                  print(format(\"{} and {}\", [1, \"two\"], None))\n\
+                  print(\"{{}} {}\", \"braces\")\n\
+                  print(\"{}\")\n";
+    assert_eq!(
+        run(source),
+        "x=3, y=2.5\nb before a\n[1, \"two\"] and None\n{} braces\n{}\n"
+    );
+}
+
+#[test]
+fn pads_and_rounds() {
+    let source = "print(\"[{:>8.2}] [{:<6}] [{:^7}] [{:*^7}] [{:6}]\", 3.14159, \"ab\", \"mid\", 42, 5)\n\
+                  print(\"[{:05}] [{:08.3}] [{:.1}] [{:.3}] [{:.0}]\", -42, -3.14159, 7, \"abcdef\", 2.5)\n";
+    assert_eq!(
+        run(source),
+        "[    3.14] [ab    ] [  mid  ] [**42***] [     5]\n\
+         [-0042] [-003.142] [7.0] [abc] [2]\n"
+    );
+}
+
+#[test]
+fn prints_values_side_by_side_without_a_format_string() {
+    let source = "print(1, 2)\n\
+                  print([1, 2], \"three\", None)\n\
+                  print(\"total:\", 2.5, true)\n\
+                  print(\"{} left\", 3)\n";
+    assert_eq!(
+        run(source),
+        "1 2\n[1, 2] three None\ntotal: 2.5 true\n3 left\n"
+    );
+}
+
+#[test]
+fn reports_mismatched_arguments() {
+    for (source, error) in [
+        (
+            "print(\"{} {}\", 1)\n",
+            "Format string uses 2 arguments but 1 was given",
+        ),
+        (
+            "format(\"{}\")\n",
+            "Format string uses 1 argument but 0 were given",
+        ),
+        (
+            "format(\"{0} {3}\", 1, 2)\n",
+            "Format string uses 4 arguments but 2 were given",
+        ),
+        (
+            "format(\"{}\", 1, 2)\n",
+            "Argument 1 has no placeholder in the format string",
+        ),
+        (
+            "format(\"{:x}\", 1)\n",
+            "Invalid placeholder '{:x}' in format string",
+        ),
+        ("format(\"{\", 1)\n", "Unclosed '{' in format string"),
+        ("format(\"a}\")\n", "Unmatched '}' in format string"),
+        (
+            "format(\"{:05}\", \"a\")\n",
+            "Zero padding needs a number, got string",
+        ),
+        (
+            "format(1, 2)\n",
+            "format() requires a format string, got int",
+        ),
+    ] {
+        assert!(run(source).contains(error), "{}", source);
+    }
+}
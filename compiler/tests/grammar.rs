@@ -0,0 +1,112 @@
+// Tests keeping the published grammar in step with the parser
+//
+// Every rule's examples must parse, every rule a definition mentions must
+// exist and spec/GRAMMAR.ebnf must be what `veyc grammar` prints.
+
+use std::collections::HashSet;
+use veyra_compiler::grammar::{ebnf, rules};
+use veyra_compiler::lexer::Lexer;
+use veyra_compiler::parser::Parser;
+
+/// Rules nothing else refers to: the start symbol, and comments, which may
+/// appear anywhere
+const ROOTS: [&str; 2] = ["program", "comment"];
+
+/// The rule names an alternative refers to, skipping terminals in quotes
+/// and `? ... ?` descriptions
+fn references(alternative: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut chars = alternative.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' | '?' => {
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_ascii_alphanumeric() || next == '_') {
+                        break;
+                    }
+                    name.push(next);
+                    chars.next();
+                }
+                names.push(name);
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+#[test]
+fn examples_parse() {
+    for rule in rules() {
+        assert!(
+            !rule.examples.is_empty(),
+            "rule `{}` has no examples",
+            rule.name
+        );
+        for example in rule.examples {
+            let parsed = Lexer::new(example)
+                .tokenize()
+                .and_then(|tokens| Parser::new(tokens).parse());
+            if let Err(e) = parsed {
+                panic!(
+                    "example of rule `{}` does not parse: {}\n{}",
+                    rule.name, e, example
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rules_are_defined_once_and_used() {
+    let mut defined = HashSet::new();
+    for rule in rules() {
+        assert!(
+            defined.insert(rule.name),
+            "rule `{}` is defined twice",
+            rule.name
+        );
+    }
+
+    let mut used = HashSet::new();
+    for rule in rules() {
+        for alternative in rule.alternatives {
+            for name in references(alternative) {
+                assert!(
+                    defined.contains(name.as_str()),
+                    "rule `{}` refers to undefined rule `{}`",
+                    rule.name,
+                    name
+                );
+                if name != rule.name {
+                    used.insert(name);
+                }
+            }
+        }
+    }
+    for rule in rules() {
+        assert!(
+            ROOTS.contains(&rule.name) || used.contains(rule.name),
+            "rule `{}` is not used by any other rule",
+            rule.name
+        );
+    }
+}
+
+#[test]
+fn spec_is_up_to_date() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../spec/GRAMMAR.ebnf");
+    let spec = std::fs::read_to_string(path).unwrap();
+    assert!(
+        spec == ebnf(),
+        "spec/GRAMMAR.ebnf is out of date; run `veyc grammar > spec/GRAMMAR.ebnf`"
+    );
+}
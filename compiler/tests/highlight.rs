@@ -0,0 +1,111 @@
+// Tests for the editor syntax definitions printed by `veyc grammar --format`
+
+use veyra_compiler::highlight;
+use veyra_compiler::lexer::{tokenize, Lexer, TokenClass, TokenKind, KEYWORDS, OPERATORS};
+
+#[test]
+fn operator_table_matches_the_lexer() {
+    for (symbol, kind) in OPERATORS {
+        let tokens = tokenize(symbol).unwrap();
+        assert_eq!(&tokens[0].kind, kind, "{:?} lexes differently", symbol);
+        assert!(
+            matches!(tokens[1].kind, TokenKind::Newline | TokenKind::Eof),
+            "{:?} is more than one token",
+            symbol
+        );
+    }
+    for (keyword, kind) in KEYWORDS {
+        assert_eq!(&tokenize(keyword).unwrap()[0].kind, kind);
+    }
+}
+
+#[test]
+fn tmlanguage_highlights_every_keyword() {
+    let grammar: serde_json::Value = serde_json::from_str(&highlight::tmlanguage()).unwrap();
+    assert_eq!(grammar["scopeName"], "source.veyra");
+
+    let patterns = grammar["repository"]["keywords"]["patterns"]
+        .as_array()
+        .unwrap();
+    let highlighted: Vec<&str> = patterns
+        .iter()
+        .flat_map(|pattern| {
+            let alternatives = pattern["match"].as_str().unwrap();
+            alternatives
+                .trim_start_matches("\\b(")
+                .trim_end_matches(")\\b")
+                .split('|')
+        })
+        .collect();
+    for (keyword, _) in KEYWORDS {
+        assert!(
+            highlighted.contains(keyword),
+            "{} is not highlighted",
+            keyword
+        );
+    }
+
+    let operators = grammar["repository"]["operators"]["patterns"][0]["match"]
+        .as_str()
+        .unwrap();
+    assert!(operators.starts_with("//=|"));
+    assert!(operators.contains("|\\?\\.|"));
+}
+
+#[test]
+fn vim_syntax_lists_keywords_types_and_builtins() {
+    let vim = highlight::vim();
+    let keywords: Vec<&str> = vim
+        .lines()
+        .filter_map(|line| line.strip_prefix("syn keyword "))
+        .flat_map(|line| line.split_whitespace().skip(1))
+        .collect();
+    for (keyword, _) in KEYWORDS {
+        assert_eq!(
+            keywords.iter().filter(|word| *word == keyword).count(),
+            1,
+            "{}",
+            keyword
+        );
+    }
+
+    assert!(vim.contains("syn keyword veyraConditional elif else if match\n"));
+    assert!(vim.contains("syn keyword veyraType int i32 i64 u32 u64 f32 f64 bool char string\n"));
+    assert!(vim.contains(" exec "));
+    assert!(vim.contains("hi def link veyraBuiltin Function\n"));
+    assert!(vim.ends_with("let b:current_syntax = \"veyra\"\n"));
+}
+
+#[test]
+fn tokens_are_classed_for_highlighting() {
+    let tokens = Lexer::new("@test\nfn check() {\n    task_group {\n        spawn work(\"a\", 1.5) # later\n    }\n    return Some(true)\n}\n")
+        .tokenize_with_comments()
+        .unwrap();
+    let classes: Vec<TokenClass> = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| TokenClass::of(&token.kind, tokens.get(i + 1).map(|t| &t.kind)))
+        .collect();
+    assert_eq!(
+        classes,
+        [
+            TokenClass::Attribute,   // @
+            TokenClass::Declaration, // fn
+            TokenClass::Call,        // check
+            TokenClass::Flow,        // task_group
+            TokenClass::Flow,        // spawn
+            TokenClass::Call,        // work
+            TokenClass::Text,        // "a"
+            TokenClass::Number,      // 1.5
+            TokenClass::Comment,     // # later
+            TokenClass::Flow,        // return
+            TokenClass::Constant,    // Some
+            TokenClass::Boolean,     // true
+        ]
+    );
+
+    // Every keyword has a class, so a new one is never left uncolored
+    for (keyword, kind) in KEYWORDS {
+        assert!(TokenClass::of(kind, None).is_some(), "{}", keyword);
+    }
+}
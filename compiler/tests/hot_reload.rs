@@ -0,0 +1,122 @@
+// Tests for swapping in edited definitions while a program runs
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use veyra_compiler::hot_reload::{HotReload, Reloaded};
+use veyra_compiler::include::tokenize_file;
+use veyra_compiler::modules::ModuleLoader;
+use veyra_compiler::parser::parse;
+use veyra_compiler::Limits;
+
+#[test]
+fn changed_bodies_are_swapped_in_and_changed_signatures_kept() {
+    let mut interpreter = common::quiet();
+    interpreter
+        .interpret(&common::program(
+            "fn greet(name) {\n    return \"hi \" + name\n}\n\
+             fn add(a, b) {\n    return a + b\n}\n\
+             let mut count = 41\n\
+             count += 1\n",
+        ))
+        .unwrap();
+
+    let reloaded = interpreter.reload(
+        Path::new("main.vey"),
+        &common::program(
+            "fn greet(name) {\n    return \"hello \" + name\n}\n\
+             fn add(a, b, c) {\n    return a + b + c\n}\n\
+             let mut count = 0\n",
+        ),
+    );
+    assert_eq!(
+        reloaded,
+        Reloaded {
+            updated: 1,
+            warnings: vec!["signature of 'add' changed; restart to apply it".to_string()],
+        }
+    );
+
+    // Globals keep their values, and top-level code is not run again
+    interpreter
+        .interpret(&common::program(
+            "print(greet(\"Ada\"))\nprint(add(1, 2))\nprint(count)\n",
+        ))
+        .unwrap();
+    assert_eq!(interpreter.take_output(), "hello Ada\n3\n42\n");
+}
+
+#[test]
+fn reloading_a_module_changes_its_functions_only() {
+    let project = tempfile::tempdir().unwrap();
+    let module = common::write(
+        project.path(),
+        "shapes.vey",
+        "fn area(w, h) {\n    return w * h\n}\n",
+    );
+    let mut interpreter = common::quiet();
+    interpreter.set_module_loader(ModuleLoader::in_dir(project.path()));
+    interpreter
+        .interpret(&common::program(
+            "import shapes\nfn area(w, h) {\n    return 0\n}\n",
+        ))
+        .unwrap();
+
+    let reloaded = interpreter.reload(
+        &module,
+        &common::program("fn area(w, h) {\n    return w * h / 2\n}\n"),
+    );
+    assert_eq!(reloaded.updated, 1);
+    interpreter
+        .interpret(&common::program(
+            "print(shapes::area(4, 3))\nprint(area(4, 3))\n",
+        ))
+        .unwrap();
+    assert_eq!(interpreter.take_output(), "6\n0\n");
+}
+
+#[test]
+fn imported_modules_are_watched() {
+    let project = tempfile::tempdir().unwrap();
+    let entry = common::write(
+        project.path(),
+        "main.vey",
+        "import version\nwhile version::current() == 1 {\n}\nprint(version::current())\n",
+    );
+    let module = common::write(
+        project.path(),
+        "version.vey",
+        "fn current() {\n    return 1\n}\n",
+    );
+
+    let mut interpreter = common::quiet();
+    interpreter.set_module_loader(ModuleLoader::in_dir(project.path()));
+    interpreter.set_limits(Limits {
+        timeout: Some(Duration::from_secs(20)),
+        ..Limits::default()
+    });
+    interpreter.set_hot_reload(HotReload::new(
+        &entry,
+        Box::new(|path: &Path| parse(tokenize_file(path)?)),
+    ));
+
+    // The loop runs until the module's edit is picked up. The new file is
+    // dated ahead so its modification time differs however coarse it is.
+    let editor = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        fs::write(&module, "fn current() {\n    return 2\n}\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&module)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+    });
+    let source = fs::read_to_string(&entry).unwrap();
+    let result = interpreter.interpret(&common::program(&source));
+    editor.join().unwrap();
+    result.unwrap();
+    assert_eq!(interpreter.take_output(), "2\n");
+}
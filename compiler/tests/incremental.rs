@@ -0,0 +1,155 @@
+// Tests for reparsing documents edit by edit
+
+use std::path::Path;
+use veyra_compiler::incremental::Document;
+use veyra_compiler::parser::parse;
+use veyra_compiler::{Lexer, Program};
+
+/// The program with its comments, as editor tooling parses it
+fn full_parse(text: &str) -> Option<Program> {
+    parse(Lexer::new(text).tokenize_with_comments().ok()?).ok()
+}
+
+/// The lines the program's items and statements start on, which program
+/// equality leaves out
+fn lines(program: &Program) -> Vec<serde_json::Value> {
+    fn collect(value: &serde_json::Value, lines: &mut Vec<serde_json::Value>) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                lines.extend(fields.get("lines").cloned());
+                fields.values().for_each(|field| collect(field, lines));
+            }
+            serde_json::Value::Array(values) => values.iter().for_each(|v| collect(v, lines)),
+            _ => {}
+        }
+    }
+    let mut lines = Vec::new();
+    collect(&serde_json::to_value(program).unwrap(), &mut lines);
+    lines
+}
+
+/// Applies an edit and checks the document agrees with parsing its new
+/// text from scratch
+fn check_edit(document: &mut Document, range: std::ops::Range<usize>, text: &str) {
+    let result = document.edit(range.clone(), text);
+    let expected = full_parse(document.text());
+    match (&result, expected) {
+        (Ok(_), Some(program)) => assert!(
+            document.program() == &program && lines(document.program()) == lines(&program),
+            "programs differ after replacing {:?} with {:?} in\n{}",
+            range,
+            text,
+            document.text()
+        ),
+        (Err(_), None) => {}
+        (result, expected) => panic!(
+            "edit gave {:?} but a full parse {} after replacing {:?} with {:?} in\n{}",
+            result,
+            if expected.is_some() {
+                "succeeds"
+            } else {
+                "fails"
+            },
+            range,
+            text,
+            document.text()
+        ),
+    }
+}
+
+#[test]
+fn edits_agree_with_full_parses_of_the_examples() {
+    let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let mut paths: Vec<_> = std::fs::read_dir(examples)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "vey"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = std::fs::read_to_string(&path).unwrap();
+        let Ok(mut document) = Document::parse(&source) else {
+            continue;
+        };
+        assert!(Some(document.program()) == full_parse(&source).as_ref());
+
+        let length = source.chars().count();
+        for position in (0..length).step_by(length / 25 + 1) {
+            // Typing a character and taking it back
+            check_edit(&mut document, position..position, "x");
+            check_edit(&mut document, position..position + 1, "");
+            // Splitting a line, and joining it again
+            check_edit(&mut document, position..position, "\n");
+            check_edit(&mut document, position..position + 1, "");
+            // Removing a few characters, then putting them back
+            let end = (position + 7).min(length);
+            let removed: String = document
+                .text()
+                .chars()
+                .skip(position)
+                .take(end - position)
+                .collect();
+            check_edit(&mut document, position..end, "");
+            check_edit(&mut document, position..position, &removed);
+        }
+        assert_eq!(document.text(), source);
+    }
+}
+
+#[test]
+fn reparses_only_the_items_around_an_edit() {
+    let source = "let a = 1\nlet b = 2\nlet c = 3\nlet d = 4\nlet e = 5\nlet f = 6\n";
+    let mut document = Document::parse(source).unwrap();
+    let offset = source.find("4").unwrap();
+    let reparsed = document.edit(offset..offset + 1, "40").unwrap();
+    assert_eq!(reparsed, 2..5);
+    assert!(Some(document.program()) == full_parse(document.text()).as_ref());
+}
+
+#[test]
+fn edits_can_join_and_split_items() {
+    let mut document = Document::parse("let x = 1\nprint(x)\nfn f() {\n    return 2\n}\n").unwrap();
+    // The next line now continues the expression
+    let call = document.text().find("print(x)").unwrap();
+    check_edit(&mut document, call..call + 8, "- 2");
+    // Opening a block that swallows the function, then closing it again
+    check_edit(&mut document, 0..0, "if true {\n");
+    let end = document.text().chars().count();
+    check_edit(&mut document, end..end, "}\n");
+    check_edit(&mut document, 0..10, "");
+    assert!(document.text().ends_with("}\n}\n"));
+}
+
+#[test]
+fn recovers_after_a_syntax_error() {
+    let source = "let w = 0\nlet x = 1\nlet y = 2\nlet z = 3\nlet v = 4\n";
+    let mut document = Document::parse(source).unwrap();
+    let before = document.program().clone();
+    let error = document.edit(28..28, "(").unwrap_err();
+    let full = parse(
+        Lexer::new(document.text())
+            .tokenize_with_comments()
+            .unwrap(),
+    );
+    assert_eq!(error.to_string(), full.unwrap_err().to_string());
+    assert_eq!(document.program(), &before);
+    assert_eq!(
+        document.text(),
+        "let w = 0\nlet x = 1\nlet y = (2\nlet z = 3\nlet v = 4\n"
+    );
+    // The text parses again, all of it anew
+    assert_eq!(document.edit(30..30, ")").unwrap(), 0..5);
+    assert!(Some(document.program()) == full_parse(document.text()).as_ref());
+}
+
+#[test]
+fn parses_everything_when_an_edit_reaches_past_the_items_around_it() {
+    let source = "let a = 1\nlet b = 2\nlet c = 3\nlet d = 4\nlet e = 5\n# \"\"\"\n";
+    let mut document = Document::parse(source).unwrap();
+    // Opening a string that a comment far below closes
+    let reparsed = document.edit(8..9, "\"\"\"").unwrap();
+    assert_eq!(reparsed, 0..1);
+    assert!(!document.has_error());
+    assert!(Some(document.program()) == full_parse(document.text()).as_ref());
+}
@@ -0,0 +1,56 @@
+// Tests for expressions embedded in string literals, `"Hello, {name}!"`
+
+mod common;
+
+use common::run;
+use veyra_compiler::lexer::tokenize;
+
+#[test]
+fn embeds_names_and_expressions() {
+    let source = "let name = \"Ada\"\nlet n = 3\n\
+                  print(\"Hello, {name}!\")\n\
+                  print(\"{n} + 1 = {n + 1}\")\n\
+                  print(\"paren: {(1 + 2) * 3}\")\n\
+                  print(\"nested: {name + \"!\"}\")\n\
+                  fn f(x) { return x * 2 }\n\
+                  print(\"f: {f(n)}, len: {len(name)}\")\n\
+                  print(\"{name}{name}\")\n";
+    assert_eq!(
+        run(source),
+        "Hello, Ada!\n3 + 1 = 4\nparen: 9\nnested: Ada!\nf: 6, len: 3\nAdaAda\n"
+    );
+}
+
+#[test]
+fn embedded_values_are_shown_as_str_does() {
+    let source = "let name = \"Ada\"\n\
+                  print(\"value {None} {true} {(2.0)} {([\"a\", 1])}\")\n\
+                  let s = \"hi {name}\"\n\
+                  print(s + \"?\", len(s))\n";
+    assert_eq!(run(source), "value None true 2 [\"a\", 1]\nhi Ada? 6\n");
+}
+
+#[test]
+fn other_braces_are_text() {
+    let source = "let name = \"Ada\"\n\
+                  print(\"escaped: \\{name}\")\n\
+                  print(\"json: {\\\"k\\\": 1} {} {0} {:.2} {[1]}\")\n";
+    assert_eq!(
+        run(source),
+        "escaped: {name}\njson: {\"k\": 1} {} {0} {:.2} {[1]}\n"
+    );
+}
+
+#[test]
+fn errors_in_embedded_expressions() {
+    assert_eq!(
+        run("print(\"oops {missing}\")\n"),
+        "Runtime Error: Undefined variable 'missing'"
+    );
+    let error = tokenize("print(\"open {name\")\n").unwrap_err().to_string();
+    assert!(
+        error.contains("Expected '}' to end the expression embedded in the string"),
+        "{}",
+        error
+    );
+}
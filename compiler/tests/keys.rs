@@ -0,0 +1,84 @@
+// Tests for the values dictionaries and sets can be keyed by
+
+mod common;
+
+use common::run;
+
+#[test]
+fn keys_of_different_types_do_not_collide() {
+    let source = "let mut d = {\"[1, 2]\": \"string\"}\n\
+                  d[[1, 2]] = \"array\"\n\
+                  d[(1, 2)] = \"tuple\"\n\
+                  d[1] = \"int\"\n\
+                  d[\"1\"] = \"one\"\n\
+                  d[Some(\"1\")] = \"text in Some\"\n\
+                  print(len(d))\n\
+                  print([d[\"[1, 2]\"], d[[1, 2]], d[1], d[\"1\"], d[Some(\"1\")]])\n\
+                  print((Some(1) in d, true in {\"true\"}))\n\
+                  print(d)\n";
+    assert_eq!(
+        run(source),
+        "6\n[\"string\", \"array\", \"int\", \"one\", \"text in Some\"]\n(false, false)\n\
+         {\"1\": \"one\", \"[1, 2]\": \"string\", (1, 2): \"tuple\", 1: \"int\", Some(\"1\"): \"text in Some\", [1, 2]: \"array\"}\n"
+    );
+}
+
+#[test]
+fn equal_values_share_a_key() {
+    let source = "let d = {1: \"a\", [\"x\", 'y']: \"b\"}\n\
+                  print([d[1.0], d[['x', \"y\"]], 'c' in {\"c\"}, len({1, 1.0, 2})])\n\
+                  print({\"\\0tag\": 1})\n\
+                  print(d[2])\n";
+    assert_eq!(
+        run(source),
+        "[\"a\", \"b\", true, 2]\n{\"\\0tag\": 1}\nRuntime Error: Key '2' not found in dictionary"
+    );
+}
+
+#[test]
+fn structs_do_not_share_a_key_with_their_text() {
+    let source = "struct P { x: int }\n\
+                  struct Tag { name: string }\n\
+                  impl Tag {\n    fn __hash__() {\n        return self.name\n    }\n}\n\
+                  print([P { x: 1 } in {\"P { x: 1 }\": 0}, P { x: 1 } in {P { x: 1 }: 0}])\n\
+                  let d = {Tag { name: \"a\" }: 1, \"a\": 2, \"Tag#\\\"a\\\"\": 3}\n\
+                  print([len(d), d[Tag { name: \"a\" }], d[\"a\"]])\n";
+    assert_eq!(run(source), "[false, true]\n[3, 1, 2]\n");
+}
+
+#[test]
+fn keys_with_colliding_hashes_stay_separate() {
+    let source = "struct Name { first: string, last: string }\n\
+                  impl Name {\n    fn __hash__() {\n        return len(self.last)\n    }\n}\n\
+                  let a = Name { first: \"a\", last: \"xy\" }\n\
+                  let b = Name { first: \"b\", last: \"zw\" }\n\
+                  let mut d = {a: 1}\n\
+                  print([a == b, b in d, len({a, b})])\n\
+                  d[b] = 2\n\
+                  d[a] = 3\n\
+                  print([len(d), d[a], d[b]])\n";
+    assert_eq!(run(source), "[false, false, 2]\n[2, 3, 2]\n");
+}
+
+#[test]
+fn keys_are_found_with_eq_alone() {
+    let source = "struct Loose { x: int, tag: string }\n\
+                  impl Loose {\n    fn __eq__(other) {\n        return self.x == other.x\n    }\n}\n\
+                  let d = {Loose { x: 1, tag: \"p\" }: \"one\", Loose { x: 2, tag: \"p\" }: \"two\"}\n\
+                  print([d[Loose { x: 1, tag: \"q\" }], d[Loose { x: 2, tag: \"r\" }]])\n\
+                  print([Loose { x: 1, tag: \"q\" } in {Loose { x: 1, tag: \"p\" }}, len({Loose { x: 3, tag: \"a\" }, Loose { x: 3, tag: \"b\" }})])\n\
+                  print(d[Loose { x: 3, tag: \"p\" }])\n";
+    assert_eq!(
+        run(source),
+        "[\"one\", \"two\"]\n[true, 1]\nRuntime Error: Key 'Loose { x: 3, tag: \"p\" }' not found in dictionary"
+    );
+}
+
+#[test]
+fn struct_keys_print_as_values() {
+    let source = "struct P { x: int }\n\
+                  print({P { x: 1 }})\n\
+                  print({P { x: 2 }: [1]})\n\
+                  print({P { x: 1 }} == {P { x: 1 }})\n";
+    assert_eq!(run(source), "{P { x: 1 }}\n{P { x: 2 }: [1]}\ntrue\n");
+}
@@ -0,0 +1,50 @@
+// Tests for the lessons of `veyc learn`
+
+use std::collections::HashSet;
+use veyra_compiler::learn::{Outcome, LESSONS};
+
+#[test]
+fn solutions_pass_and_starters_do_not() {
+    for lesson in LESSONS {
+        assert_eq!(
+            lesson.check(lesson.solution),
+            Outcome::Passed,
+            "solution to {}",
+            lesson.name
+        );
+        assert_ne!(
+            lesson.check(lesson.starter),
+            Outcome::Passed,
+            "starter of {}",
+            lesson.name
+        );
+    }
+}
+
+#[test]
+fn lessons_have_distinct_names_in_order() {
+    let names: Vec<&str> = LESSONS.iter().map(|lesson| lesson.name).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+}
+
+#[test]
+fn wrong_output_and_errors_are_reported() {
+    let hello = &LESSONS[0];
+    assert_eq!(
+        hello.check("print(\"Hello\")\n"),
+        Outcome::WrongOutput {
+            expected: "Hello, Veyra!\n".to_string(),
+            actual: "Hello\n".to_string(),
+        }
+    );
+    // Trailing whitespace does not matter
+    assert_eq!(hello.check("print(\"Hello, Veyra!  \")\n"), Outcome::Passed);
+    assert!(matches!(hello.check("print(\n"), Outcome::Failed(_)));
+    assert!(matches!(
+        hello.check("print(read_file(\"/etc/hostname\"))\n"),
+        Outcome::Failed(_)
+    ));
+}
@@ -0,0 +1,56 @@
+// Tests for hexadecimal, binary and octal integer literals, and digit
+// separators
+
+mod common;
+
+use common::run;
+use veyra_compiler::lexer::tokenize;
+
+#[test]
+fn reads_each_base() {
+    let source = "print(0xFF, 0xff, 0b1010, 0o777, 1_000_000)\n\
+                  print(0x7FFF_FFFF_FFFF_FFFF, 0b1111_0000, -0x10)\n\
+                  print(0xff + 1, 0b1 << 4, 0o10 * 2)\n";
+    assert_eq!(
+        run(source),
+        "255 255 10 511 1000000\n9223372036854775807 240 -16\n256 16 16\n"
+    );
+}
+
+#[test]
+fn separators_are_ignored_in_floats_too() {
+    assert_eq!(run("print(1_000.5, 1_0.2_5)\n"), "1000.5 10.25\n");
+}
+
+#[test]
+fn rejects_bad_digits_and_overflow() {
+    for (source, error) in [
+        ("print(0x)\n", "Invalid hexadecimal literal"),
+        ("print(0xG)\n", "Invalid digit 'G' in hexadecimal literal"),
+        ("print(0b102)\n", "Invalid digit '2' in binary literal"),
+        ("print(0o8)\n", "Invalid digit '8' in octal literal"),
+        (
+            "print(0x8000_0000_0000_0000)\n",
+            "Integer literal out of range",
+        ),
+        (
+            "print(0xFFFFFFFFFFFFFFFFFFFF)\n",
+            "Integer literal out of range",
+        ),
+        (
+            "print(0b1_0000000000000000000000000000000000000000000000000000000000000000)\n",
+            "Integer literal out of range",
+        ),
+        (
+            "print(0o1_000_000_000_000_000_000_000)\n",
+            "Integer literal out of range",
+        ),
+        (
+            "print(9223372036854775808)\n",
+            "Integer literal out of range",
+        ),
+    ] {
+        let message = tokenize(source).unwrap_err().to_string();
+        assert!(message.contains(error), "{}: {}", source, message);
+    }
+}
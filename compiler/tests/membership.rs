@@ -0,0 +1,51 @@
+// Tests for the `in` and `not in` operators
+
+mod common;
+
+use common::run;
+
+#[test]
+fn finds_elements_keys_and_substrings() {
+    let source = "print(2 in [1, 2, 3], 5 in [1, 2], 5 not in [1, 2])\n\
+                  print(\"a\" in {\"a\": 1}, 1 in {\"a\": 1}, \"b\" not in {\"a\": 1})\n\
+                  print(\"ell\" in \"hello\", 'h' in \"hello\", \"\" in \"x\", \"z\" not in \"abc\")\n\
+                  print(3 in {1, 2, 3}, (1, 2) in [(1, 2)], 1 in (1, 2))\n";
+    assert_eq!(
+        run(source),
+        "true false true\ntrue false true\ntrue true true true\ntrue true true\n"
+    );
+}
+
+#[test]
+fn binds_tighter_than_not_and_and() {
+    let source = "print(not 1 in [1])\n\
+                  print(1 in [1] and 2 in [2])\n\
+                  let words = [\"apple\", \"pear\"]\n\
+                  for w in [\"pear\", \"fig\"] {\n    if w not in words {\n        print(\"missing \" + w)\n    }\n}\n";
+    assert_eq!(run(source), "false\ntrue\nmissing fig\n");
+}
+
+#[test]
+fn compares_elements_with_eq() {
+    let source = "struct Loose { x: int, tag: string }\n\
+                  impl Loose {\n    fn __eq__(other) {\n        return self.x == other.x\n    }\n}\n\
+                  let items = [Loose { x: 1, tag: \"b\" }]\n\
+                  print(Loose { x: 1, tag: \"a\" } in items, Loose { x: 2, tag: \"a\" } not in items)\n";
+    assert_eq!(run(source), "true true\n");
+}
+
+#[test]
+fn rejects_values_that_have_no_members() {
+    assert_eq!(
+        run("print(1 in 5)\n"),
+        "Runtime Error: 'in' requires an array, tuple, set, dictionary or string on the right, got int"
+    );
+    assert_eq!(
+        run("print(1 not in None)\n"),
+        "Runtime Error: 'in' requires an array, tuple, set, dictionary or string on the right, got none"
+    );
+    assert_eq!(
+        run("print(1 in \"abc\")\n"),
+        "Runtime Error: 'in <string>' requires a string or char on the left, got int"
+    );
+}
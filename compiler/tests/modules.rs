@@ -0,0 +1,133 @@
+// Tests for importing modules from files
+
+mod common;
+
+use std::path::Path;
+use veyra_compiler::diagnostic::Diagnostic;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::modules::ModuleLoader;
+use veyra_compiler::parser::parse;
+use veyra_compiler::Interpreter;
+
+fn run_in(dir: &Path, source: &str) -> Result<String, String> {
+    let program = parse(tokenize(source).unwrap()).unwrap();
+    let mut interpreter = common::quiet();
+    interpreter.set_module_loader(ModuleLoader::in_dir(dir));
+    interpreter.interpret(&program).map_err(|e| e.to_string())?;
+    Ok(interpreter.take_output())
+}
+
+#[test]
+fn modules_have_their_own_namespace() {
+    let files = common::files(&[
+        (
+            "geometry/shapes.vey",
+            "import geometry.units\n\
+                 let SIDES = 4\n\
+                 let mut made = 0\n\
+                 fn scale(x) { return x * units::FACTOR }\n\
+                 fn square(n) {\n    made = made + 1\n    return scale(n * n)\n}\n\
+                 fn made_count() { return made }\n",
+        ),
+        ("geometry/units.vey", "let FACTOR = 2\n"),
+    ]);
+
+    let output = run_in(
+        files.path(),
+        "import geometry.shapes\n\
+         import geometry.shapes as sh\n\
+         fn scale(x) { return x }\n\
+         print(shapes::square(3))\n\
+         print(sh::square(1))\n\
+         print(scale(5))\n\
+         print(shapes::made_count())\n\
+         print(shapes::SIDES)\n\
+         let f = shapes::square\n\
+         print(f(1))\n",
+    );
+    assert_eq!(output.unwrap(), "18\n2\n5\n2\n4\n2\n");
+
+    // Nothing a module defines leaks into the importer
+    let error = run_in(files.path(), "import geometry.shapes\nprint(SIDES)\n").unwrap_err();
+    assert!(error.contains("Undefined variable 'SIDES'"), "{}", error);
+    let error = run_in(files.path(), "import geometry.shapes\nsquare(1)\n").unwrap_err();
+    assert!(error.contains("Undefined function 'square'"), "{}", error);
+}
+
+#[test]
+fn selective_imports_and_private_names() {
+    let files = common::files(&[(
+        "text.vey",
+        "let GREETING = \"hello\"\n\
+             fn _shout(s) { return string_to_upper(s) }\n\
+             fn greet(name) { return _shout(GREETING + \" \" + name) }\n",
+    )]);
+
+    let output = run_in(
+        files.path(),
+        "import text.{greet, GREETING}\nprint(greet(\"ann\"))\nprint(GREETING)\n",
+    );
+    assert_eq!(output.unwrap(), "HELLO ANN\nhello\n");
+
+    let error = run_in(files.path(), "import text.{_shout}\n").unwrap_err();
+    assert!(
+        error.contains("'_shout' is private to module 'text'"),
+        "{}",
+        error
+    );
+    let error = run_in(files.path(), "import text\ntext::_shout(\"a\")\n").unwrap_err();
+    assert!(error.contains("'_shout' is private"), "{}", error);
+    let error = run_in(files.path(), "import text.{wave}\n").unwrap_err();
+    assert!(
+        error.contains("Module 'text' has no item 'wave'"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn finds_installed_packages_and_reports_problems() {
+    let files = common::files(&[
+        ("veyra.toml", "name = \"app\"\n"),
+        ("src/a.vey", "import b\nfn f() { return 1 }\n"),
+        ("src/b.vey", "import a\n"),
+        (
+            "veyra-modules/colors/main.vey",
+            "fn red() { return \"#f00\" }\n",
+        ),
+        (
+            "veyra-modules/colors/dark.vey",
+            "fn red() { return \"#800\" }\n",
+        ),
+    ]);
+    let src = files.path().join("src");
+
+    let output = run_in(
+        &src,
+        "import colors\nimport colors.dark\nprint(colors::red())\nprint(dark::red())\n",
+    );
+    assert_eq!(output.unwrap(), "#f00\n#800\n");
+
+    let error = run_in(&src, "import a\n").unwrap_err();
+    assert!(error.contains("Circular import of module 'a'"), "{}", error);
+    let error = run_in(&src, "import shapes\n").unwrap_err();
+    assert!(error.contains("Module 'shapes' not found"), "{}", error);
+
+    // The standard modules are built in
+    assert!(run_in(&src, "import std.io\n").is_ok());
+}
+
+#[test]
+fn syntax_errors_point_at_the_module() {
+    let files = common::files(&[("broken.vey", "let a = 1\nfn f( {\n")]);
+    let program = parse(tokenize("import broken\n").unwrap()).unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.set_module_loader(ModuleLoader::in_dir(files.path()));
+    let error = interpreter.interpret(&program).unwrap_err();
+
+    let diagnostic = Diagnostic::from_error(&error, Path::new("main.vey"));
+    assert_eq!(diagnostic.code, "parse-error");
+    let span = diagnostic.span.unwrap();
+    assert!(span.file.ends_with("broken.vey"), "{}", span.file);
+    assert_eq!((span.line, span.column), (Some(2), Some(7)));
+}
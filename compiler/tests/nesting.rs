@@ -0,0 +1,73 @@
+// Tests for the parser's limit on nesting, which must fail cleanly rather
+// than overflow the stack, in unoptimized builds and on small test threads
+// too
+
+mod common;
+
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::{parse, parse_recovering, MAX_NESTING_DEPTH};
+use veyra_compiler::{resolve, typeck, Lexer};
+
+#[test]
+fn deep_nesting_is_a_syntax_error() {
+    let source = format!("print({}1{})\n", "(".repeat(10_000), ")".repeat(10_000));
+    let error = parse(tokenize(&source).unwrap()).unwrap_err();
+    assert!(
+        error.to_string().contains(&format!(
+            "Code is nested too deeply (more than {} levels)",
+            MAX_NESTING_DEPTH
+        )),
+        "{}",
+        error
+    );
+    let (_, errors) = parse_recovering(tokenize(&source).unwrap());
+    assert!(errors[0].to_string().contains("nested too deeply"));
+
+    let source = format!("let x = {}\nprint(x)\n", vec!["1"; 3000].join(" + "));
+    let error = parse(tokenize(&source).unwrap()).unwrap_err();
+    assert!(error.to_string().contains("nested too deeply"), "{}", error);
+
+    let source = format!(
+        "{}print(1)\n{}",
+        "if true {\n".repeat(10_000),
+        "}\n".repeat(10_000)
+    );
+    assert!(parse(tokenize(&source).unwrap()).is_err());
+}
+
+#[test]
+fn nesting_up_to_the_limit_parses() {
+    for source in [
+        format!("print({}x)\n", "-".repeat(250)),
+        format!("print({}1{})\n", "[".repeat(250), "]".repeat(250)),
+        format!(
+            "{}print(1)\n{}",
+            "if true {\n".repeat(250),
+            "}\n".repeat(250)
+        ),
+        format!("print({})\n", vec!["1"; 250].join(" + ")),
+        format!("print({})\n", vec!["x"; 250].join(" and ")),
+    ] {
+        assert!(parse(tokenize(&source).unwrap()).is_ok());
+    }
+}
+
+/// A chain up to the limit gets through the passes after parsing, on a
+/// stack the size of a program's main thread rather than a test thread's
+#[test]
+fn long_operator_chains_check_and_run() {
+    let source = format!("let x = {}\nprint(x)\n", vec!["1"; 250].join(" + "));
+    let output = std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || {
+            let (tokens, _) = Lexer::new(&source).tokenize_with_ranges();
+            let program = common::program(&source);
+            resolve::resolve(&program, &tokens);
+            typeck::analyze(&program, &tokens);
+            common::run(&source)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(output, "250\n");
+}
@@ -0,0 +1,126 @@
+// Tests for the optimization passes: the source they turn a program into,
+// and that the program still prints the same
+
+mod common;
+
+use common::{program, run_program_with};
+use std::path::Path;
+use veyra_compiler::interpreter::Limits;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::optimize::{optimize, MAX_LEVEL};
+use veyra_compiler::parser::parse;
+use veyra_compiler::printer::{ast_to_source, Config};
+
+/// `source` optimized at `level`, printed back as source
+fn optimized(source: &str, level: u8) -> String {
+    ast_to_source(&optimize(program(source), level), &Config::default())
+}
+
+/// What `source` prints when optimized at `level`, or the error it stops
+/// with, on both engines
+fn output(source: &str, level: u8) -> String {
+    run_program_with(&optimize(program(source), level), |interpreter| {
+        interpreter.set_deterministic(true);
+        interpreter.set_limits(Limits {
+            max_steps: Some(1_000_000),
+            ..Limits::default()
+        });
+    })
+}
+
+#[test]
+fn folds_constant_expressions() {
+    assert_eq!(
+        optimized(
+            "print(2 + 3 * 4)\nprint(7 // 2, -7 % 3, 1 / 4)\nprint(\"a\" + \"b\")\n\
+             print(3 < 5 and not false)\nprint(-(2 ** 3))\n",
+            1
+        ),
+        "print(14)\nprint(3, 2, 0.25)\nprint(\"ab\")\nprint(true)\nprint(-8)\n"
+    );
+}
+
+#[test]
+fn leaves_operations_that_fail_to_run_time() {
+    let source = "print(10 / 0)\nprint(9223372036854775807 + 1)\nprint(1 + \"a\")\n";
+    assert_eq!(optimized(source, MAX_LEVEL), source);
+}
+
+#[test]
+fn propagates_immutable_constants() {
+    assert_eq!(
+        optimized(
+            "let width = 4\nlet height = width * 2 + 1\nlet mut count = 0\n\
+             print(height)\ncount += 1\nprint(count)\n",
+            1
+        ),
+        "let width = 4\nlet height = 9\nlet mut count = 0\nprint(9)\ncount += 1\nprint(count)\n"
+    );
+}
+
+#[test]
+fn removes_dead_branches_and_unreachable_statements() {
+    let source = "if 1 > 2 {\n    print(\"never\")\n} elif true {\n    print(\"taken\")\n} else {\n    print(\"else\")\n}\n\
+                  while false {\n    print(\"loop\")\n}\n\
+                  fn f(x) {\n    return x\n    print(\"after return\")\n}\n\
+                  for i in range(3) {\n    if i == 1 {\n        continue\n        print(\"after continue\")\n    }\n    print(i)\n}\n";
+    assert_eq!(
+        optimized(source, 1),
+        "print(\"taken\")\n\
+         fn f(x) {\n    return x\n}\n\
+         for i in range(3) {\n    if i == 1 {\n        continue\n    }\n    print(i)\n}\n"
+    );
+}
+
+#[test]
+fn inlines_tiny_functions_only_at_level_two() {
+    let source =
+        "fn square(n) {\n    return n * n\n}\nlet mut m = 3\nprint(square(4))\nprint(square(m))\n";
+    assert_eq!(optimized(source, 1), source);
+    assert_eq!(
+        optimized(source, 2),
+        "fn square(n) {\n    return n * n\n}\nlet mut m = 3\nprint(16)\nprint(square(m))\n"
+    );
+}
+
+#[test]
+fn optimizing_does_not_change_what_programs_print() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut compared = 0;
+    for file in ["examples", "tests"]
+        .iter()
+        .flat_map(|sub| std::fs::read_dir(dir.join(sub)).unwrap())
+    {
+        let path = file.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "vey") {
+            continue;
+        }
+        let source = std::fs::read_to_string(&path).unwrap();
+        if tokenize(&source).and_then(parse).is_err() {
+            continue;
+        }
+        assert_eq!(
+            output(&source, 0),
+            output(&source, MAX_LEVEL),
+            "{}",
+            path.display()
+        );
+        compared += 1;
+    }
+    assert!(compared > 10);
+}
+
+#[test]
+fn folds_equality_of_ints_and_floats_by_value() {
+    assert_eq!(
+        optimized("print(1 == 1.0)\nprint(2.5 != 2)\nprint(1 == \"1\")\n", 1),
+        "print(true)\nprint(true)\nprint(false)\n"
+    );
+}
+
+#[test]
+fn inlines_returned_values_unchanged() {
+    let source = "fn five() {\n    return \"5\"\n}\nfn two() {\n    return 2.0\n}\n\
+                  print(five())\nprint(two())\n";
+    assert!(optimized(source, 2).ends_with("print(\"5\")\nprint(2.0)\n"));
+}
@@ -0,0 +1,200 @@
+// Tests for the playground API served by `veyc serve --playground`
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use veyra_compiler::playground::{self, Isolation, LIMITS};
+use veyra_compiler::Limits;
+
+#[test]
+fn runs_programs_and_reports_what_they_printed() {
+    let result = playground::run("print(\"hello\")\nexit(3)\n", LIMITS);
+    assert_eq!(result.stdout, "hello\n");
+    assert_eq!(result.exit_code, 3);
+    assert!(result.diagnostics.is_empty());
+
+    // Every syntax error is reported, with its position
+    let result = playground::run("fn f( {\n}\nlet = 3\n", LIMITS);
+    assert_eq!(result.exit_code, 1);
+    let lines: Vec<Option<usize>> = result
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.as_ref().unwrap().line)
+        .collect();
+    assert_eq!(lines, [Some(1), Some(3)]);
+    assert_eq!(result.diagnostics[0].code, "parse-error");
+}
+
+#[test]
+fn programs_stay_in_the_sandbox_and_within_limits() {
+    let result = playground::run("print(\"before\")\nread_file(\"/etc/passwd\")\n", LIMITS);
+    assert_eq!(result.stdout, "before\n");
+    assert!(result.diagnostics[0].message.contains("not allowed"));
+
+    // Modules would be read from the server's disk, so none are looked for
+    let result = playground::run("import leak\nprint(\"after\")\n", LIMITS);
+    assert_eq!(result.stdout, "");
+    assert_eq!(
+        result.diagnostics[0].message,
+        "Cannot import 'leak': importing modules is disabled"
+    );
+
+    let limits = Limits {
+        max_output: Some(100),
+        ..LIMITS
+    };
+    let result = playground::run("while true {\n    print(\"spam\")\n}\n", limits);
+    assert!(result.stdout.len() <= 100);
+    assert_eq!(
+        result.diagnostics[0].message,
+        "Output limit of 100 bytes exceeded"
+    );
+
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(100)),
+        ..LIMITS
+    };
+    let result = playground::run("while true {}\n", limits);
+    assert_eq!(
+        result.diagnostics[0].message,
+        "Time limit of 100ms exceeded"
+    );
+}
+
+/// Send a raw HTTP request and return the response's status line and body
+fn request(address: &str, request: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+#[test]
+fn serves_runs_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || playground::serve(listener, LIMITS, Isolation::Thread));
+
+    let source = "print(1 + 2)\n";
+    let (status, body) = request(
+        &address,
+        &format!(
+            "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            source.len(),
+            source
+        ),
+    );
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(result["stdout"], "3\n");
+    assert_eq!(result["exit_code"], 0);
+    assert!(result["duration_ms"].is_number());
+
+    let json = r#"{"source": "print(nme)"}"#;
+    let (_, body) = request(
+        &address,
+        &format!(
+            "POST /run HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            json.len(),
+            json
+        ),
+    );
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(result["diagnostics"][0]["code"], "runtime-error");
+
+    let (status, _) = request(
+        &address,
+        &format!(
+            "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            playground::MAX_SOURCE + 1
+        ),
+    );
+    assert_eq!(status, "HTTP/1.1 413 Payload Too Large");
+    let (status, body) = request(&address, "GET /health HTTP/1.1\r\n\r\n");
+    assert_eq!((status.as_str(), body.as_str()), ("HTTP/1.1 200 OK", "ok"));
+    let (status, _) = request(&address, "GET /run HTTP/1.1\r\n\r\n");
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+
+    // Headers are read up to a limit rather than buffered without end
+    let mut long = "GET /health HTTP/1.1\r\nX-Padding: ".to_string();
+    long.push_str(&"a".repeat(8 * 1024 - long.len()));
+    let (status, _) = request(&address, &long);
+    assert_eq!(status, "HTTP/1.1 431 Request Header Fields Too Large");
+    let (status, _) = request(&address, "GET /health HTTP/1.1\r\n\r\n");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let (status, _) = request(
+        &address,
+        "POST /run HTTP/1.1\r\nContent-Length: lots\r\n\r\nprint(1)\n",
+    );
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+}
+
+#[test]
+fn clients_still_sending_do_not_hold_places_to_run() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || playground::serve(listener, LIMITS, Isolation::Thread));
+
+    let idle: Vec<TcpStream> = (0..thread::available_parallelism().map_or(1, |n| n.get()) + 1)
+        .map(|_| {
+            let mut stream = TcpStream::connect(&address).unwrap();
+            stream.write_all(b"POST /run HTTP/1.1\r\n").unwrap();
+            stream
+        })
+        .collect();
+    let source = "print(\"still served\")\n";
+    let (status, body) = request(
+        &address,
+        &format!(
+            "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            source.len(),
+            source
+        ),
+    );
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(result["stdout"], "still served\n");
+    drop(idle);
+}
+
+#[test]
+fn child_processes_contain_memory_and_crashes() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let isolation = Isolation::Process {
+        program: env!("CARGO_BIN_EXE_veyc").into(),
+        max_memory: 64 * 1024 * 1024,
+    };
+    thread::spawn(move || playground::serve(listener, LIMITS, isolation));
+    let run = |source: &str| {
+        let (status, body) = request(
+            &address,
+            &format!(
+                "POST /run HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                source.len(),
+                source
+            ),
+        );
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        serde_json::from_str::<serde_json::Value>(&body).unwrap()
+    };
+
+    let result = run("print(\"hi\")\nexit(2)\n");
+    assert_eq!(result["stdout"], "hi\n");
+    assert_eq!(result["exit_code"], 2);
+
+    // Running out of memory ends the program's process, not the server
+    let source = "let mut s = \"x\"\nwhile true {\n    s = s + s\n}\n";
+    for _ in 0..thread::available_parallelism().map_or(1, |n| n.get()) + 1 {
+        let result = run(source);
+        assert_eq!(result["exit_code"], 1);
+        assert_eq!(
+            result["diagnostics"][0]["message"],
+            "The program was stopped: it ran out of memory or crashed veyc"
+        );
+    }
+    assert_eq!(run("print(1)\n")["stdout"], "1\n");
+}
@@ -0,0 +1,101 @@
+// Tests for printing syntax trees as source
+
+use veyra_compiler::ast::*;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::printer::{ast_to_source, Config};
+
+fn identifier(name: &str) -> Box<Expression> {
+    Box::new(Expression::Identifier(name.to_string()))
+}
+
+fn binary(
+    left: Box<Expression>,
+    operator: BinaryOperator,
+    right: Box<Expression>,
+) -> Box<Expression> {
+    Box::new(Expression::Binary(BinaryExpression {
+        left,
+        operator,
+        right,
+    }))
+}
+
+#[test]
+fn prints_programs_built_in_code() {
+    // fn area(w, h) { return w * (h + 1) }, where the tree alone says the
+    // sum is an operand of the product
+    let area = Function {
+        name: "area".to_string(),
+        parameters: ["w", "h"]
+            .iter()
+            .map(|name| Parameter {
+                name: name.to_string(),
+                param_type: None,
+                default: None,
+            })
+            .collect(),
+        return_type: None,
+        body: Block {
+            statements: vec![Statement::Return(ReturnStatement {
+                value: Some(*binary(
+                    identifier("w"),
+                    BinaryOperator::Multiply,
+                    binary(
+                        identifier("h"),
+                        BinaryOperator::Add,
+                        Box::new(Expression::Literal(Literal::Integer(1))),
+                    ),
+                )),
+            })],
+            lines: Lines::default(),
+        },
+        is_async: false,
+        attributes: Vec::new(),
+        trailing_comma: false,
+        comments: Vec::new(),
+    };
+    let call = Expression::Call(CallExpression {
+        callee: identifier("area"),
+        arguments: vec![
+            Expression::Literal(Literal::Integer(2)),
+            Expression::Literal(Literal::Integer(3)),
+        ],
+    });
+    let program = Program {
+        items: vec![
+            Item::Function(area),
+            Item::Statement(Statement::VariableDeclaration(VariableDeclaration {
+                name: "total".to_string(),
+                var_type: None,
+                initializer: call,
+                mutable: true,
+                pattern: None,
+            })),
+            Item::Statement(Statement::Expression(ExpressionStatement {
+                expression: Expression::Call(CallExpression {
+                    callee: identifier("print"),
+                    arguments: vec![Expression::Literal(Literal::String(
+                        "say \"hi\"\n".to_string(),
+                    ))],
+                }),
+            })),
+        ],
+        lines: Lines::default(),
+    };
+
+    let source = ast_to_source(&program, &Config::default());
+    assert_eq!(
+        source,
+        "fn area(w, h) {\n    return w * (h + 1)\n}\n\
+         let mut total = area(2, 3)\n\
+         print(\"say \\\"hi\\\"\\n\")\n"
+    );
+    assert_eq!(parse(tokenize(&source).unwrap()).unwrap(), program);
+
+    let tabs = Config {
+        use_spaces: false,
+        ..Config::default()
+    };
+    assert!(ast_to_source(&program, &tabs).contains("\n\treturn w * (h + 1)\n"));
+}
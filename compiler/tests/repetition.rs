@@ -0,0 +1,66 @@
+// Tests for repeating strings and arrays with `*`
+
+mod common;
+
+use common::run;
+
+#[test]
+fn repeats_strings_chars_and_arrays() {
+    let source = "print(\"ab\" * 3)\n\
+                  print(2 * \"-\")\n\
+                  print('x' * 4)\n\
+                  print([0] * 3)\n\
+                  print(2 * [1, \"a\"])\n\
+                  let mut line = \"=\"\n\
+                  line *= 5\n\
+                  print(line)\n";
+    assert_eq!(
+        run(source),
+        "ababab\n--\nxxxx\n[0, 0, 0]\n[1, \"a\", 1, \"a\"]\n=====\n"
+    );
+}
+
+#[test]
+fn zero_copies_are_empty() {
+    let source = "print([\"abc\" * 0, 'x' * 0])\n\
+                  print([1, 2] * 0)\n\
+                  print(len(\"\" * 9223372036854775807))\n\
+                  print([] * 9223372036854775807)\n";
+    assert_eq!(run(source), "[\"\", \"\"]\n[]\n0\n[]\n");
+}
+
+#[test]
+fn rejects_negative_counts() {
+    assert_eq!(
+        run("print(\"ab\" * -1)\n"),
+        "Runtime Error: Cannot repeat a value -1 times"
+    );
+    assert_eq!(
+        run("print([1] * -3)\n"),
+        "Runtime Error: Cannot repeat a value -3 times"
+    );
+}
+
+#[test]
+fn rejects_huge_counts() {
+    assert_eq!(
+        run("print(\"abc\" * 9223372036854775807)\n"),
+        "Runtime Error: Repeating a value of length 3 9223372036854775807 times is too large (at most 16777216 items)"
+    );
+    assert_eq!(
+        run("print([1, 2, 3] * 9223372036854775807)\n"),
+        "Runtime Error: Repeating a value of length 3 9223372036854775807 times is too large (at most 16777216 items)"
+    );
+}
+
+#[test]
+fn repetition_errors_can_be_caught() {
+    let source = "try {\n    let s = \"x\" * 9223372036854775807\n} catch error {\n    print(\"caught: \" + error)\n}\n\
+                  let mut items = [1]\n\
+                  try {\n    items *= 9223372036854775807\n} catch error {\n    print(\"caught\")\n}\n\
+                  print(items)\n";
+    assert_eq!(
+        run(source),
+        "caught: Repeating a value of length 1 9223372036854775807 times is too large (at most 16777216 items)\ncaught\n[1]\n"
+    );
+}
@@ -0,0 +1,46 @@
+// Tests for `repr`, which shows values unambiguously, against `str` and
+// `print`, which show them as text
+
+mod common;
+
+use common::run;
+
+#[test]
+fn repr_quotes_and_escapes() {
+    let source = "print(repr(\"a\\\"b\\n\"), repr('x'), repr('\\n'))\n\
+                  print(repr(1), repr(2.0), repr(None), repr(true))\n\
+                  print(repr(repr(\"q\")))\n";
+    assert_eq!(
+        run(source),
+        "\"a\\\"b\\n\" 'x' '\\n'\n1 2.0 None true\n\"\\\"q\\\"\"\n"
+    );
+}
+
+#[test]
+fn str_and_print_show_text_as_it_is() {
+    let source = "print(str(\"a\\\"b\"), str('x'), str(2.0))\n\
+                  print(\"s\", 'c', 2.0)\n";
+    assert_eq!(run(source), "a\"b x 2\ns c 2\n");
+}
+
+#[test]
+fn collections_show_their_elements_with_repr() {
+    let source = "print(repr([1, \"a\", 'c']), repr({\"k\": \"v\"}), repr((1, \"t\")))\n\
+                  print(str([1, \"a\"]))\n\
+                  struct P { name: string }\n\
+                  print(repr(P { name: \"n\" }))\n";
+    assert_eq!(
+        run(source),
+        "[1, \"a\", 'c'] {\"k\": \"v\"} (1, \"t\")\n[1, \"a\"]\nP { name: \"n\" }\n"
+    );
+}
+
+#[test]
+fn repr_takes_one_argument() {
+    for source in ["print(repr())\n", "print(repr(1, 2))\n"] {
+        assert_eq!(
+            run(source),
+            "Runtime Error: repr() takes exactly one argument"
+        );
+    }
+}
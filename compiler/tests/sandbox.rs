@@ -0,0 +1,77 @@
+// Tests for running code under a sandbox policy
+
+mod common;
+
+use tempfile::TempDir;
+use veyra_compiler::modules::ModuleLoader;
+use veyra_compiler::sandbox::{Capability, Policy};
+
+/// A temporary directory holding `leak.vey`
+struct Module {
+    dir: TempDir,
+}
+
+impl Module {
+    fn new() -> Self {
+        Self {
+            dir: common::files(&[(
+                "leak.vey",
+                "let token = \"t0ken\"\nfn secret() { return \"s3cret\" }\n",
+            )]),
+        }
+    }
+
+    /// What `source` prints, or the error it stops with, on both engines,
+    /// which must agree
+    fn run(&self, policy: &Policy, source: &str) -> String {
+        common::run_with(source, |interpreter| {
+            interpreter.set_module_loader(ModuleLoader::in_dir(self.dir.path()));
+            interpreter.set_policy(policy.clone());
+        })
+    }
+}
+
+#[test]
+fn imports_need_permission_to_read_the_module() {
+    let module = Module::new();
+    let source = "import leak\nprint(leak::secret() + \" \" + leak::token)\n";
+
+    let denied = module.run(&Policy::deny_all(), source);
+    assert!(
+        denied.contains("Permission denied: reading '") && !denied.contains("s3cret"),
+        "{}",
+        denied
+    );
+    let selective = module.run(
+        &Policy::deny_all(),
+        "import leak.{secret}\nprint(secret())\n",
+    );
+    assert!(selective.contains("Permission denied"), "{}", selective);
+
+    let allowed = Policy::deny_all().allow_read(module.dir.path());
+    assert_eq!(module.run(&allowed, source), "s3cret t0ken\n");
+    let denied_file = Policy::allow_all().deny_read(module.dir.path().join("leak.vey"));
+    assert!(module
+        .run(&denied_file, source)
+        .contains("Permission denied"));
+}
+
+#[test]
+fn programs_are_allowed_only_by_the_name_given() {
+    let policy = Policy::deny_all().allow_process("echo");
+    let run = |source: &str| {
+        common::run_with(source, |interpreter| interpreter.set_policy(policy.clone()))
+    };
+
+    assert!(run("print(exec(\"echo\", [\"hi\"]))\n").contains("hi"));
+    // Another program with the same file name is not the one allowed
+    for program in ["/tmp/evil/echo", "/bin/echo", "./echo"] {
+        let denied = run(&format!("print(exec(\"{}\", [\"hi\"]))\n", program));
+        assert!(
+            denied.contains(&format!("Permission denied: running '{}'", program)),
+            "{}",
+            denied
+        );
+        assert!(!policy.permits(Capability::Process(program)));
+    }
+}
@@ -0,0 +1,106 @@
+// Tests for when tasks run, their names, metadata and task-local variables
+
+mod common;
+
+use common::run;
+
+#[test]
+fn tasks_start_when_awaited_and_take_turns_at_awaits() {
+    let output = run("fn note(name) {\n    print(name, \"waits\")\n}\n\
+         fn step(name) {\n    print(name, \"starts\")\n    await spawn note(name)\n    print(name, \"ends\")\n    return name\n}\n\
+         let a = spawn step(\"a\")\n\
+         let b = spawn step(\"b\")\n\
+         print(\"spawned both\")\n\
+         print(await b, await a)\n");
+    assert_eq!(
+        output,
+        "spawned both\na starts\nb starts\na waits\na ends\nb waits\nb ends\nb a\n"
+    );
+}
+
+#[test]
+fn tasks_copy_the_variables_they_use_when_spawned() {
+    let output = run("fn double(n) {\n    return n * 2\n}\n\
+         let mut handles = []\n\
+         for i in range(3) {\n    handles = push(handles, spawn double(i))\n}\n\
+         let mut n = 10\nlet later = spawn double(n)\nn = 20\n\
+         print([await handles[0], await handles[1], await handles[2], await later])\n");
+    assert_eq!(output, "[0, 2, 4, 20]\n");
+}
+
+#[test]
+fn tasks_waiting_on_each_other_are_a_deadlock() {
+    let output = run("fn first() {\n    return await b\n}\n\
+         fn second() {\n    return await a\n}\n\
+         let a = spawn first()\n\
+         let b = spawn second()\n\
+         await a\n");
+    assert_eq!(
+        output,
+        "Runtime Error: Deadlock: the program waits on tasks that are all waiting"
+    );
+}
+
+#[test]
+fn tasks_spawned_after_an_await_run_in_the_same_round() {
+    let output = run("fn leaf() {\n    return 5\n}\n\
+         fn outer() {\n    await sleep_async(1)\n    let inner = spawn leaf()\n    return await inner\n}\n\
+         print(await spawn outer())\n");
+    assert_eq!(output, "5\n");
+}
+
+#[test]
+fn tasks_have_ids_names_and_parents() {
+    let output = run("fn work() {\n    set_task_name(\"worker\")\n    let child = spawn task_info()\n    return [task_id(), await child]\n}\n\
+         let handle = spawn work()\n\
+         print(task_info(handle)[\"status\"])\n\
+         let result = await handle\n\
+         print(result[1][\"parent\"] == result[0])\n\
+         print(result[1][\"name\"])\n\
+         print(result[1][\"status\"])\n\
+         print(task_name(handle))\n\
+         print(task_info(handle)[\"status\"])\n\
+         print(task_name())\n\
+         print(task_id())\n");
+    assert_eq!(
+        output,
+        "pending\ntrue\ntask-1\nrunning\nworker\nawaited\nmain\nNone\n"
+    );
+
+    let error = run("set_task_name(\"main\")\n");
+    assert!(
+        error.contains("only be called in a spawned task"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn task_locals_are_copied_into_spawned_tasks() {
+    let output = run("fn child() {\n    let seen = task_local_get(\"request\")\n    task_local_set(\"request\", 99)\n    return seen\n}\n\
+         print(task_local_get(\"request\"))\n\
+         print(task_local_get(\"request\", 0))\n\
+         task_local_set(\"request\", 7)\n\
+         print(await spawn child())\n\
+         print(task_local_get(\"request\"))\n");
+    assert_eq!(output, "None\n0\n7\n7\n");
+
+    let error = run("task_local_set([1, |x| x], 1)\n");
+    assert!(error.contains("cannot be a task-local key"), "{}", error);
+}
+
+#[test]
+fn tasks_see_changes_made_between_their_turns() {
+    let output = run("let mut turns = []\n\
+         fn tick() {\n    return 0\n}\n\
+         fn worker(name) {\n    for i in range(3) {\n        turns = push(turns, name + str(i))\n        await spawn tick()\n    }\n    return len(turns)\n}\n\
+         let a = spawn worker(\"a\")\n\
+         let b = spawn worker(\"b\")\n\
+         print(await a, await b)\n\
+         print(turns)\n");
+    // Each task is resumed three times, after the other changed `turns`
+    assert_eq!(
+        output,
+        "6 6\n[\"a0\", \"b0\", \"a1\", \"b1\", \"a2\", \"b2\"]\n"
+    );
+}
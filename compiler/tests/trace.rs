@@ -0,0 +1,83 @@
+// Tests for recording values with trace()
+
+mod common;
+
+use common::quiet;
+use veyra_compiler::bytecode::compile;
+use veyra_compiler::interpreter::TRACE_CAPACITY;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::{vm, Interpreter, Program};
+
+fn parsed(source: &str) -> Program {
+    parse(tokenize(source).unwrap()).unwrap()
+}
+
+/// The expression and value of each trace, from both engines
+fn traces(source: &str) -> Vec<(String, String)> {
+    let program = parsed(source);
+    let mut tree = quiet();
+    tree.interpret(&program).unwrap();
+    let mut on_vm = quiet();
+    vm::run(&mut on_vm, compile(&program).unwrap()).unwrap();
+
+    let entries = |interpreter: &Interpreter| -> Vec<(String, String)> {
+        interpreter
+            .traces()
+            .map(|t| (t.expression.clone(), t.value.repr()))
+            .collect()
+    };
+    assert_eq!(entries(&tree), entries(&on_vm));
+    entries(&tree)
+}
+
+#[test]
+fn records_expression_source_and_value() {
+    let traced = traces(
+        "fn square(n) {\n    return n * n\n}\n\
+         let mut total = 0\n\
+         for x in [1, 2] {\n    total += trace(square(x) + 1)\n}\n\
+         trace(total, \"total\")\n\
+         let name = trace(\"veyra\")\n",
+    );
+    let expected = [
+        ("square(x) + 1", "2"),
+        ("square(x) + 1", "5"),
+        ("total", "7"),
+        ("\"veyra\"", "\"veyra\""),
+    ];
+    assert_eq!(
+        traced,
+        expected.map(|(e, v)| (e.to_string(), v.to_string()))
+    );
+}
+
+#[test]
+fn returns_the_value_and_keeps_time_order() {
+    let mut interpreter = quiet();
+    interpreter
+        .interpret(&parsed("let x = trace(1) + trace(2)\nprint(x)\n"))
+        .unwrap();
+    assert_eq!(interpreter.take_output(), "3\n");
+    let entries: Vec<_> = interpreter.traces().collect();
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].elapsed <= entries[1].elapsed);
+}
+
+#[test]
+fn keeps_only_the_latest_entries() {
+    let traced = traces("for i in range(1005) {\n    trace(i)\n}\n");
+    assert_eq!(traced.len(), TRACE_CAPACITY);
+    assert_eq!(traced[0], ("i".to_string(), "5".to_string()));
+    assert_eq!(traced.last().unwrap().1, "1004");
+}
+
+#[test]
+fn trace_dump_prints_the_log() {
+    let mut interpreter = quiet();
+    interpreter
+        .interpret(&parsed("let a = [1, 2]\ntrace(len(a))\ntrace_dump()\n"))
+        .unwrap();
+    let output = interpreter.take_output();
+    assert!(output.ends_with("ms] len(a) = 2\n"), "{}", output);
+}
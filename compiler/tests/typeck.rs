@@ -0,0 +1,212 @@
+// Tests for the type checking pass of `veyc check`
+
+use veyra_compiler::parser::parse;
+use veyra_compiler::typeck::{analyze, check, TypeError};
+use veyra_compiler::Lexer;
+
+fn type_errors(source: &str) -> Vec<TypeError> {
+    let (tokens, error) = Lexer::new(source).tokenize_with_ranges();
+    assert!(error.is_none());
+    let program = parse(tokens.iter().map(|(token, _)| token.clone()).collect()).unwrap();
+    check(&program, &tokens)
+}
+
+/// The code and message of each error
+fn messages(source: &str) -> Vec<(&'static str, String)> {
+    type_errors(source)
+        .into_iter()
+        .map(|error| (error.code, error.message))
+        .collect()
+}
+
+#[test]
+fn accepts_well_typed_programs() {
+    let source = "struct Point {\n    x: float\n    y: float\n}\n\
+         impl Point {\n    fn scaled(self, by: float) -> Point {\n        return Point { x: self.x * by, y: self.y * by }\n    }\n}\n\
+         fn mean(values: [int]) -> float {\n    let mut total = 0\n    for v in values {\n        total = total + v\n    }\n    return total / len(values)\n}\n\
+         fn greet(name: string, punctuation: string = \"!\") -> string {\n    return \"Hello, \" + name + punctuation\n}\n\
+         let p: Point = Point { x: 1, y: 2.5 }\n\
+         let q = p.scaled(2)\n\
+         let m: float = mean([1, 2, 3])\n\
+         let maybe: int? = None\n\
+         let n: int = \"42\" as int\n\
+         print(greet(\"ann\"), greet(\"bob\", \"?\"), q.x, m, maybe, n)\n";
+    assert_eq!(type_errors(source), []);
+}
+
+#[test]
+fn reports_undefined_names_and_wrong_arity() {
+    let source = "fn area(w: int, h: int = 1) -> int {\n    return w * h\n}\n\
+         let total = area(2)\n\
+         print(totl)\n\
+         area()\n\
+         area(1, 2, 3)\n";
+    assert_eq!(
+        messages(source),
+        [
+            (
+                "undefined-name",
+                "'totl' is not defined (did you mean 'total'?)".to_string()
+            ),
+            (
+                "wrong-arguments",
+                "'area' takes 1 to 2 arguments but 0 were given".to_string()
+            ),
+            (
+                "wrong-arguments",
+                "'area' takes 1 to 2 arguments but 3 were given".to_string()
+            ),
+        ]
+    );
+
+    // Errors are located at the name they concern
+    let errors = type_errors(source);
+    assert_eq!(errors[0].line, Some(5));
+    assert_eq!(errors[1].line, Some(6));
+
+    // A local variable hides the function of the same name
+    assert_eq!(
+        type_errors("fn f(x) { return x }\nfn g(f) { return f(1, 2) }\nprint(g(f))\n"),
+        []
+    );
+}
+
+#[test]
+fn reports_values_that_do_not_match_annotations() {
+    let source = "struct User {\n    name: string\n    age: int\n}\n\
+         fn label(user: User) -> string {\n    return user.age\n}\n\
+         let count: int = \"three\"\n\
+         let mut ratio: float = 1\n\
+         ratio = true\n\
+         let user = User { name: \"ann\", age: 3.5 }\n\
+         let u: User = User { name: \"bob\", email: \"b@x\" }\n\
+         u.age = \"old\"\n\
+         print(label(\"ann\"), u.nam)\n";
+    assert_eq!(
+        messages(source),
+        [
+            (
+                "type-mismatch",
+                "Return value of 'label' should be string, found int".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'count' should be int, found string".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'ratio' should be float, found bool".to_string()
+            ),
+            (
+                "type-mismatch",
+                "Field 'age' should be int, found float".to_string()
+            ),
+            (
+                "unknown-field",
+                "Struct 'User' has no field 'email'".to_string()
+            ),
+            (
+                "type-mismatch",
+                "Field 'age' should be int, found string".to_string()
+            ),
+            (
+                "type-mismatch",
+                "Argument 1 of 'label' should be User, found string".to_string()
+            ),
+            (
+                "unknown-field",
+                "Struct 'User' has no field 'nam' (did you mean 'name'?)".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn infers_types_without_annotations() {
+    let source = "fn half(n: int) {\n    return n / 2\n}\n\
+         fn find(names: [string], name: string) {\n    for n in names {\n        if n == name {\n            return n\n        }\n    }\n}\n\
+         let greeting = \"hi\"\n\
+         let mut total = 0\n\
+         total += 1.5\n\
+         let x: int = greeting\n\
+         let h: int = half(3)\n\
+         let found: string = find([\"ann\"], \"ann\")\n\
+         let t: int = total\n\
+         let size: string = len(greeting)\n";
+    assert_eq!(
+        messages(source),
+        [
+            (
+                "type-mismatch",
+                "'x' should be int, found string".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'h' should be int, found float".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'found' should be string, found string?".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'t' should be int, found float".to_string()
+            ),
+            (
+                "type-mismatch",
+                "'size' should be string, found int".to_string()
+            ),
+        ]
+    );
+
+    // Types are kept by the offset of each definition
+    let (tokens, _) = Lexer::new(source).tokenize_with_ranges();
+    let program = parse(tokens.iter().map(|(token, _)| token.clone()).collect()).unwrap();
+    let analysis = analyze(&program, &tokens);
+    // Annotated variables included, and inferred ones with the type they
+    // widen to
+    let mut types: Vec<&str> = analysis.types.values().map(String::as_str).collect();
+    types.sort_unstable();
+    assert_eq!(
+        types,
+        [
+            "fn find(names: [string], name: string) -> string?",
+            "fn half(n: int) -> float",
+            "found: string",
+            "greeting: string",
+            "h: int",
+            "n: int",
+            "n: string",
+            "name: string",
+            "names: [string]",
+            "size: string",
+            "t: int",
+            "total: float",
+            "x: int",
+        ]
+    );
+}
+
+#[test]
+fn imports_define_only_what_they_bring_in() {
+    // A module's functions and variables stay in its namespace, so a typo
+    // next to an import is still reported
+    assert_eq!(
+        messages("import util\nlet total = util::sum([1, 2])\nprint(totl)\n"),
+        [(
+            "undefined-name",
+            "'totl' is not defined (did you mean 'total'?)".to_string()
+        )]
+    );
+    assert_eq!(
+        messages("import std.net as network\nprint(netwrk)\n").len(),
+        1
+    );
+
+    // Struct and actor types are shared by every module
+    assert_eq!(
+        messages("import shapes\nlet p = Point { x: 1 }\nlet log = spawn Log\n"),
+        []
+    );
+    assert_eq!(messages("let p = Point { x: 1 }\n").len(), 1);
+}
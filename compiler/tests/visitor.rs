@@ -0,0 +1,87 @@
+// Tests for walking programs with Visitor and VisitorMut
+
+use veyra_compiler::ast::*;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::printer::{ast_to_source, Config};
+
+fn parsed(source: &str) -> Program {
+    parse(tokenize(source).unwrap()).unwrap()
+}
+
+/// Names of the functions called, the types written and the variables
+/// bound by patterns
+#[derive(Default)]
+struct Collect {
+    calls: Vec<String>,
+    types: usize,
+    bindings: Vec<String>,
+}
+
+impl Visitor for Collect {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Call(call) = expression {
+            if let Expression::Identifier(name) = call.callee.as_ref() {
+                self.calls.push(name.clone());
+            }
+        }
+        walk_expression(self, expression);
+    }
+
+    fn visit_type(&mut self, ty: &Type) {
+        self.types += 1;
+        walk_type(self, ty);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let Pattern::Identifier(name) = pattern {
+            self.bindings.push(name.clone());
+        }
+        walk_pattern(self, pattern);
+    }
+}
+
+#[test]
+fn visits_nested_expressions_types_and_patterns() {
+    let program = parsed(
+        "struct Point {\n    x: float\n    y: float\n}\n\
+         fn total(xs: [int]) -> int {\n    return reduce(xs, |a, b| a + b, 0)\n}\n\
+         let (first, [second, ..rest]) = (1, [2, 3, 4])\n\
+         match Some(first) {\n    Some(n) if check(n) -> print(str(n))\n    _ -> print(\"none\")\n}\n",
+    );
+    let mut collect = Collect::default();
+    collect.visit_program(&program);
+    assert_eq!(
+        collect.calls,
+        ["reduce", "Some", "check", "print", "str", "print"]
+    );
+    // float, float, [int], int inside it, and the return type
+    assert_eq!(collect.types, 5);
+    assert_eq!(collect.bindings, ["first", "second", "rest", "n"]);
+}
+
+/// Renames every use of one variable
+struct Rename<'a>(&'a str, &'a str);
+
+impl VisitorMut for Rename<'_> {
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if let Expression::Identifier(name) = expression {
+            if name == self.0 {
+                *name = self.1.to_string();
+            }
+        }
+        walk_expression_mut(self, expression);
+    }
+}
+
+#[test]
+fn rewrites_the_tree_in_place() {
+    let mut program = parsed(
+        "let count = 1\nfn bump() {\n    print(\"{count + 1}\")\n    return [count, count * 2]\n}\n",
+    );
+    Rename("count", "n").visit_program_mut(&mut program);
+    assert_eq!(
+        ast_to_source(&program, &Config::default()),
+        "let count = 1\nfn bump() {\n    print(\"{n + 1}\")\n    return [n, n * 2]\n}\n"
+    );
+}
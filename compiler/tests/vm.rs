@@ -0,0 +1,76 @@
+// Tests for running compiled programs on the bytecode VM
+
+mod common;
+
+use common::run;
+
+#[test]
+fn runs_loops_calls_and_closures_like_the_interpreter() {
+    let output = run(
+        "fn fib(n) {\n    if n < 2 {\n        return n\n    }\n    return fib(n - 1) + fib(n - 2)\n}\n\
+         let mut total = 0\n\
+         for i in range(10) {\n    if i == 7 {\n        break\n    }\n    if i % 2 == 0 {\n        continue\n    }\n    total += i\n}\n\
+         let scale = 3\n\
+         let scaled = map([1, 2, 3], |x| x * scale)\n\
+         let [first, ..rest] = scaled\n\
+         print([fib(12), total, first, rest])\n",
+    );
+    assert_eq!(output, "[144, 9, 3, [6, 9]]\n");
+}
+
+#[test]
+fn unwinds_through_catch_finally_and_question_marks() {
+    let output = run(
+        "fn half(n) {\n    if n % 2 == 1 {\n        return Err(\"odd\")\n    }\n    return Ok(n / 2)\n}\n\
+         fn quarter(n) {\n    let h = half(n)?\n    return half(h)\n}\n\
+         fn risky() {\n    try {\n        throw \"boom\"\n    } finally {\n        print(\"cleanup\")\n    }\n}\n\
+         print([quarter(8), quarter(6)])\n\
+         try {\n    risky()\n} catch e {\n    print(\"caught \" + e)\n}\n\
+         for i in range(3) {\n    try {\n        if i == 1 {\n            continue\n        }\n        print(i)\n    } finally {\n        print(\"after \" + str(i))\n    }\n}\n",
+    );
+    assert_eq!(
+        output,
+        "[Ok(2.0), Err(\"odd\")]\ncleanup\ncaught boom\n0\nafter 0\nafter 1\n2\nafter 2\n"
+    );
+}
+
+#[test]
+fn runs_traits_structs_and_actors() {
+    let output = run(
+        "trait Shape {\n    fn area(self)\n    fn describe(self) {\n        return \"area \" + str(self.area())\n    }\n}\n\
+         struct Square {\n    side: int\n}\n\
+         impl Shape for Square {\n    fn area(self) {\n        return self.side * self.side\n    }\n}\n\
+         actor Counter {\n    count: int\n    fn receive(n) {\n        count += n\n        print(\"count \" + str(count))\n    }\n}\n\
+         print([Square { side: 3 }.describe(), implements(Square { side: 1 }, \"Shape\")])\n\
+         let counter = spawn Counter\n\
+         send(counter, 2)\n\
+         send(counter, 5)\n",
+    );
+    assert_eq!(output, "[\"area 9\", true]\ncount 2\ncount 7\n");
+}
+
+#[test]
+fn reports_errors_like_the_interpreter() {
+    let source = "trait Shape {\n    fn area(self)\n}\n\
+                  struct Dot {}\n\
+                  impl Shape for Dot {}\n";
+    let error = run(source);
+    assert!(error.contains("area"), "{}", error);
+
+    assert!(run("fn f(a, b) {\n    return a + b\n}\nprint(f(1))\n").contains("arguments"));
+}
+
+#[test]
+fn lambdas_copy_top_level_variables() {
+    let source = "let c = 0\nlet f = |n| n + c\nc = 10\nprint(f(1))\n\
+                  fn make() {\n    return || c\n}\nlet g = make()\nc = 20\nprint(g())\n";
+    assert_eq!(run(source), "1\n10\n");
+}
+
+#[test]
+fn if_expressions_evaluate_one_branch() {
+    let source = "fn loud(x) {\n    print(\"evaluated\", x)\n    return x\n}\n\
+                  let c = true\nlet v = if c then 1 else 2\nprint(v)\n\
+                  print(if v > 1 then loud(\"big\") else loud(\"small\"))\n";
+    assert_eq!(run(source), "1\nevaluated small\nsmall\n");
+}
@@ -0,0 +1,129 @@
+// Thread pool benchmarks on fan-out workloads: jobs that queue more jobs,
+// with and without work stealing, scripts that fan out with `spawn`, and
+// how long a latency-sensitive job waits behind a full queue.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use crossbeam::channel;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use veyra_runtime::{JobPriority, ScriptPool, ThreadPool, ThreadPoolConfig};
+
+fn started(work_stealing: bool) -> Arc<ThreadPool> {
+    let pool = ThreadPool::with_config(ThreadPoolConfig {
+        threads: num_cpus::get(),
+        work_stealing,
+        ..Default::default()
+    });
+    futures::executor::block_on(pool.start()).unwrap();
+    Arc::new(pool)
+}
+
+/// A little arithmetic, so jobs are not free
+fn work(seed: u64) -> u64 {
+    (0..200).fold(seed, |acc, i| acc.wrapping_mul(31).wrapping_add(i))
+}
+
+/// One job queues `width` jobs that each queue `width` more, the way a task
+/// spawning subtasks does. Without stealing, the jobs a worker queues stay
+/// on that worker.
+fn nested_fan_out(c: &mut Criterion) {
+    let width = 64;
+    let mut group = c.benchmark_group("fan_out/jobs");
+    for (name, work_stealing) in [("work_stealing", true), ("no_stealing", false)] {
+        let pool = started(work_stealing);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let (done, finished) = channel::unbounded();
+                let spawner = Arc::clone(&pool);
+                pool.execute(move || {
+                    for i in 0..width {
+                        let (pool, done) = (Arc::clone(&spawner), done.clone());
+                        spawner
+                            .execute(move || {
+                                for j in 0..width {
+                                    let done = done.clone();
+                                    pool.execute(move || {
+                                        let _ = done.send(work(i * width + j));
+                                    })
+                                    .unwrap();
+                                }
+                            })
+                            .unwrap();
+                    }
+                })
+                .unwrap();
+                for _ in 0..width * width {
+                    black_box(finished.recv().unwrap());
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Scripts that each spawn tasks and await them all
+fn spawn_fan_out(c: &mut Criterion) {
+    let scripts = ScriptPool::new(started(true));
+    let source = "fn square(n) {\n    return n * n\n}\n\
+                  let mut tasks = []\n\
+                  for i in range(50) {\n    tasks = push(tasks, spawn square(i))\n}\n\
+                  let mut total = 0\n\
+                  for t in tasks {\n    total = total + await t\n}\n";
+    let mut group = c.benchmark_group("fan_out/spawn");
+    for count in [8, 32] {
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &n| {
+            b.iter(|| {
+                let results =
+                    futures::executor::block_on(scripts.run_all(vec![source; n])).unwrap();
+                assert!(results.iter().all(Result::is_ok));
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Time from queueing a job until it starts, while every worker has a
+/// backlog of slow jobs
+fn queue_latency(c: &mut Criterion) {
+    let pool = started(true);
+    let backlog = pool.worker_count() * 4;
+    let mut group = c.benchmark_group("latency");
+    for (name, priority) in [("high", JobPriority::High), ("normal", JobPriority::Normal)] {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter_custom(|iterations| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iterations {
+                    let (done, finished) = channel::unbounded();
+                    for _ in 0..backlog {
+                        let done = done.clone();
+                        pool.execute(move || {
+                            std::thread::sleep(Duration::from_micros(200));
+                            let _ = done.send(None);
+                        })
+                        .unwrap();
+                    }
+                    let queued = Instant::now();
+                    let started = done.clone();
+                    pool.execute_with_priority(
+                        move || {
+                            let _ = started.send(Some(queued.elapsed()));
+                        },
+                        priority,
+                    )
+                    .unwrap();
+                    for _ in 0..=backlog {
+                        if let Some(latency) = finished.recv().unwrap() {
+                            total += latency;
+                        }
+                    }
+                }
+                total
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, nested_fan_out, spawn_fan_out, queue_latency);
+criterion_main!(benches);
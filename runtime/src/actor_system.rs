@@ -1,12 +1,18 @@
 use anyhow::Result;
 use dashmap::DashMap;
 use futures::future::BoxFuture;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
 use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
 use uuid::Uuid;
+use veyra_compiler::{MailboxConfig, Overflow};
 
 /// Actor system implementation for Veyra
 pub struct ActorSystem {
@@ -33,7 +39,7 @@ pub struct ActorSystemStats {
 pub struct ActorRef {
     pub id: ActorId,
     pub name: String,
-    sender: mpsc::UnboundedSender<ActorMessage>,
+    mailbox: Arc<Mailbox>,
     #[allow(dead_code)]
     system: Arc<ActorSystem>,
 }
@@ -80,6 +86,20 @@ pub struct ActorContext {
     pub system: Arc<ActorSystem>,
 }
 
+/// The queue of messages sent to an actor, bounded or not. A bounded
+/// mailbox applies its overflow strategy when a message arrives while it is
+/// full, so a slow actor cannot make memory grow without limit.
+struct Mailbox {
+    queue: Mutex<VecDeque<ActorMessage>>,
+    config: MailboxConfig,
+    /// Signalled when a message is queued or the mailbox closes
+    message_ready: Notify,
+    /// Signalled when the actor takes a message, making room
+    space_ready: Notify,
+    closed: AtomicBool,
+    dropped: AtomicU64,
+}
+
 /// Internal message structure
 struct ActorMessage {
     #[allow(dead_code)]
@@ -106,8 +126,34 @@ pub enum ActorError {
     ActorPanicked(String),
     SupervisionFailed(String),
     SystemError(String),
+    /// No reply to an `ask` arrived within the timeout
+    Timeout(Duration),
+    /// A message was sent to a full mailbox whose overflow strategy is
+    /// `error`; holds the capacity
+    MailboxFull(usize),
+}
+
+impl fmt::Display for ActorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActorError::MessageProcessingFailed(msg) => {
+                write!(f, "Message processing failed: {}", msg)
+            }
+            ActorError::ActorPanicked(msg) => write!(f, "Actor panicked: {}", msg),
+            ActorError::SupervisionFailed(msg) => write!(f, "Supervision failed: {}", msg),
+            ActorError::SystemError(msg) => write!(f, "Actor system error: {}", msg),
+            ActorError::Timeout(timeout) => {
+                write!(f, "Actor did not reply within {}ms", timeout.as_millis())
+            }
+            ActorError::MailboxFull(capacity) => {
+                write!(f, "Actor mailbox is full (capacity {})", capacity)
+            }
+        }
+    }
 }
 
+impl std::error::Error for ActorError {}
+
 /// Supervision strategy for handling actor failures
 #[derive(Debug, Clone)]
 pub enum SupervisionStrategy {
@@ -167,21 +213,38 @@ impl ActorSystem {
     }
 
     pub async fn spawn_actor<A>(
+        &self,
+        name: String,
+        actor: A,
+        initial_state: A::State,
+    ) -> Result<ActorRef>
+    where
+        A: Actor + 'static,
+    {
+        self.spawn_actor_with_mailbox(name, actor, initial_state, MailboxConfig::default())
+            .await
+    }
+
+    /// Spawn an actor whose mailbox holds at most `mailbox.capacity`
+    /// messages, as declared by `@mailbox` on a Veyra actor
+    pub async fn spawn_actor_with_mailbox<A>(
         &self,
         name: String,
         mut actor: A,
         initial_state: A::State,
+        mailbox: MailboxConfig,
     ) -> Result<ActorRef>
     where
         A: Actor + 'static,
     {
         let actor_id = Uuid::new_v4();
-        let (sender, mut receiver) = mpsc::unbounded_channel::<ActorMessage>();
+        let mailbox = Arc::new(Mailbox::new(mailbox));
+        let receiver = Arc::clone(&mailbox);
 
         let actor_ref = ActorRef {
             id: actor_id,
             name: name.clone(),
-            sender: sender.clone(),
+            mailbox,
             system: Arc::new(self.clone()), // This is problematic - we need a different approach
         };
 
@@ -230,12 +293,10 @@ impl ActorSystem {
                         }
                     }
                     ActorResult::Error(error) => {
-                        system_clone.handle_actor_error(actor_id, error).await;
                         if let Some(reply_channel) = message.reply_to {
-                            let _ = reply_channel.send(ActorResult::Error(
-                                ActorError::MessageProcessingFailed("Actor error".to_string()),
-                            ));
+                            let _ = reply_channel.send(ActorResult::Error(error.clone()));
                         }
+                        system_clone.handle_actor_error(actor_id, error).await;
                     }
                     ActorResult::Stop => {
                         let _ = actor.post_stop(&context).await;
@@ -283,7 +344,8 @@ impl ActorSystem {
                 reply_to: None,
             };
 
-            let _ = actor_ref.sender.send(stop_message);
+            actor_ref.mailbox.push(stop_message);
+            actor_ref.mailbox.close();
             self.stats.write().active_actors -= 1;
         }
 
@@ -353,6 +415,82 @@ impl Clone for ActorSystem {
     }
 }
 
+impl Mailbox {
+    fn new(config: MailboxConfig) -> Self {
+        Self {
+            queue: Mutex::new(VecDeque::new()),
+            config,
+            message_ready: Notify::new(),
+            space_ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Queue a message, applying the overflow strategy if the mailbox is
+    /// full
+    async fn send(&self, message: ActorMessage) -> Result<()> {
+        let Some(capacity) = self.config.capacity else {
+            self.push(message);
+            return Ok(());
+        };
+        loop {
+            if self.closed.load(Ordering::Acquire) {
+                return Err(anyhow::anyhow!("Failed to send message to actor"));
+            }
+            let space = self.space_ready.notified();
+            {
+                let mut queue = self.queue.lock();
+                if queue.len() < capacity {
+                    queue.push_back(message);
+                    break;
+                }
+                match self.config.overflow {
+                    Overflow::Block => {}
+                    Overflow::DropOldest => {
+                        queue.pop_front();
+                        queue.push_back(message);
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    Overflow::Error => return Err(ActorError::MailboxFull(capacity).into()),
+                }
+            }
+            space.await;
+        }
+        self.message_ready.notify_one();
+        Ok(())
+    }
+
+    /// Queue a message regardless of the capacity
+    fn push(&self, message: ActorMessage) {
+        self.queue.lock().push_back(message);
+        self.message_ready.notify_one();
+    }
+
+    /// The next message, or None once the mailbox is closed and empty
+    async fn recv(&self) -> Option<ActorMessage> {
+        loop {
+            let ready = self.message_ready.notified();
+            if let Some(message) = self.queue.lock().pop_front() {
+                self.space_ready.notify_one();
+                return Some(message);
+            }
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            ready.await;
+        }
+    }
+
+    /// Stop accepting messages; those already queued are still handled
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.message_ready.notify_one();
+        self.space_ready.notify_waiters();
+    }
+}
+
 impl ActorRef {
     pub async fn send_message<T>(&self, message: T) -> Result<()>
     where
@@ -365,17 +503,25 @@ impl ActorRef {
             reply_to: None,
         };
 
-        self.sender
-            .send(actor_message)
-            .map_err(|_| anyhow::anyhow!("Failed to send message to actor"))?;
+        self.mailbox.send(actor_message).await
+    }
 
-        Ok(())
+    /// Messages waiting to be handled
+    pub fn mailbox_len(&self) -> usize {
+        self.mailbox.queue.lock().len()
+    }
+
+    /// Messages dropped from a full `drop_oldest` mailbox so far
+    pub fn dropped_messages(&self) -> u64 {
+        self.mailbox.dropped.load(Ordering::Relaxed)
     }
 
-    pub async fn ask<T, R>(&self, message: T) -> Result<ActorResult>
+    /// Send a message and wait up to `timeout` for the actor's reply,
+    /// deserialized into `R`
+    pub async fn ask<T, R>(&self, message: T, timeout: Duration) -> Result<R>
     where
         T: Send + 'static,
-        R: Send + 'static,
+        R: DeserializeOwned,
     {
         let (reply_sender, reply_receiver) = oneshot::channel();
 
@@ -386,13 +532,20 @@ impl ActorRef {
             reply_to: Some(reply_sender),
         };
 
-        self.sender
-            .send(actor_message)
-            .map_err(|_| anyhow::anyhow!("Failed to send message to actor"))?;
+        self.mailbox.send(actor_message).await?;
 
-        reply_receiver
+        let reply = tokio::time::timeout(timeout, reply_receiver)
             .await
-            .map_err(|_| anyhow::anyhow!("Failed to receive reply from actor"))
+            .map_err(|_| ActorError::Timeout(timeout))?
+            .map_err(|_| anyhow::anyhow!("Failed to receive reply from actor"))?;
+
+        match reply {
+            ActorResult::Ok(value) => Ok(serde_json::from_value(value.unwrap_or(Value::Null))?),
+            ActorResult::Error(error) => Err(error.into()),
+            ActorResult::Stop | ActorResult::Restart => {
+                Err(anyhow::anyhow!("Actor stopped before replying"))
+            }
+        }
     }
 }
 
@@ -1,6 +1,8 @@
 use anyhow::Result;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
 use std::collections::HashSet;
+use std::fmt::Write;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::task::JoinHandle;
@@ -8,25 +10,36 @@ use tokio::time::interval;
 
 use crate::memory_manager::{MemoryManager, ObjectRef};
 
-/// Garbage collector with mark-and-sweep and generational collection
+/// Generational garbage collector over the objects of a `MemoryManager`.
+///
+/// New objects start in generation 0. Once the young generations hold
+/// `young_generation_size` bytes, a minor collection traces them from the
+/// roots and from the old objects that reference them, frees the dead ones
+/// and moves every survivor up a generation. The oldest generation is
+/// collected by a major collection, which marks a bounded number of objects
+/// per step so that no single pause has to trace the whole heap, then
+/// finishes with a short remark of the roots and of the objects given new
+/// references while it was marking, and sweeps.
 pub struct GarbageCollector {
-    memory_manager: Arc<MemoryManager>,
-    roots: RwLock<HashSet<ObjectRef>>,
+    collector: Arc<Collector>,
     collection_thread: RwLock<Option<JoinHandle<()>>>,
-    stats: RwLock<GcStats>,
-    config: GcConfig,
 }
 
 #[derive(Debug, Clone)]
 pub struct GcConfig {
     /// Interval between garbage collection cycles
     pub collection_interval: Duration,
-    /// Memory pressure threshold to trigger collection
+    /// Bytes in the oldest generation above which a major collection
+    /// starts, or in the whole heap without generational collection
     pub memory_threshold: usize,
     /// Enable generational collection
     pub generational: bool,
-    /// Number of generations for generational GC
+    /// Number of generations for generational GC, at least 2
     pub generations: usize,
+    /// Bytes allocated in generation 0 that trigger a minor collection
+    pub young_generation_size: usize,
+    /// Objects marked per incremental step of a major collection
+    pub mark_step: usize,
 }
 
 impl Default for GcConfig {
@@ -36,69 +49,212 @@ impl Default for GcConfig {
             memory_threshold: 64 * 1024 * 1024, // 64MB
             generational: true,
             generations: 3,
+            young_generation_size: 8 * 1024 * 1024, // 8MB
+            mark_step: 10_000,
         }
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct GcStats {
     pub collections: u64,
     pub objects_collected: u64,
     pub bytes_collected: u64,
     pub collection_time: Duration,
+    #[serde(skip)]
     pub last_collection: Option<Instant>,
+    pub minor_collections: u64,
+    pub major_collections: u64,
+    pub objects_promoted: u64,
+    /// Pauses of minor collections
+    pub minor_pauses: PauseHistogram,
+    /// Pauses of major collections: each marking step, and the remark and
+    /// sweep that end a collection
+    pub major_pauses: PauseHistogram,
+}
+
+/// Upper bounds of the buckets of a `PauseHistogram`
+pub const PAUSE_BUCKETS: [Duration; 8] = [
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+];
+
+/// Distribution of pause times
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PauseHistogram {
+    /// Pauses no longer than each bound of `PAUSE_BUCKETS` and longer than
+    /// the one before, then pauses longer than all of them
+    pub buckets: [u64; PAUSE_BUCKETS.len() + 1],
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl PauseHistogram {
+    pub fn record(&mut self, pause: Duration) {
+        let bucket = PAUSE_BUCKETS
+            .iter()
+            .position(|bound| pause <= *bound)
+            .unwrap_or(PAUSE_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total += pause;
+        self.max = self.max.max(pause);
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.total
+            .checked_div(self.count.try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default()
+    }
+
+    /// The bucket bound at or below which fraction `q` of the pauses fall;
+    /// the longest pause for pauses beyond the last bucket
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = (q.clamp(0.0, 1.0) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, bound) in self.buckets.iter().zip(PAUSE_BUCKETS) {
+            seen += bucket;
+            if seen >= rank.max(1) {
+                return bound.min(self.max);
+            }
+        }
+        self.max
+    }
+
+    /// Prometheus exposition lines of a histogram called `name`
+    fn write_metrics(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bucket, bound) in self.buckets.iter().zip(PAUSE_BUCKETS) {
+            cumulative += bucket;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name,
+                labels,
+                bound.as_secs_f64(),
+                cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(
+            out,
+            "{}_sum{{{}}} {}",
+            name,
+            labels,
+            self.total.as_secs_f64()
+        );
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+impl GcStats {
+    /// The statistics in the Prometheus text exposition format
+    pub fn export_metrics(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP veyra_gc_pause_seconds Garbage collection pause times\n");
+        out.push_str("# TYPE veyra_gc_pause_seconds histogram\n");
+        self.minor_pauses
+            .write_metrics(&mut out, "veyra_gc_pause_seconds", "kind=\"minor\"");
+        self.major_pauses
+            .write_metrics(&mut out, "veyra_gc_pause_seconds", "kind=\"major\"");
+
+        out.push_str("# HELP veyra_gc_collections_total Completed garbage collections\n");
+        out.push_str("# TYPE veyra_gc_collections_total counter\n");
+        let _ = writeln!(
+            out,
+            "veyra_gc_collections_total{{kind=\"minor\"}} {}",
+            self.minor_collections
+        );
+        let _ = writeln!(
+            out,
+            "veyra_gc_collections_total{{kind=\"major\"}} {}",
+            self.major_collections
+        );
+        for (name, help, value) in [
+            (
+                "veyra_gc_collected_objects_total",
+                "Objects freed by the garbage collector",
+                self.objects_collected,
+            ),
+            (
+                "veyra_gc_collected_bytes_total",
+                "Bytes freed by the garbage collector",
+                self.bytes_collected,
+            ),
+            (
+                "veyra_gc_promoted_objects_total",
+                "Objects moved to an older generation",
+                self.objects_promoted,
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CollectionResult {
+    pub objects: usize,
+    pub bytes: usize,
+}
+
+/// State shared with the collection task
+struct Collector {
+    memory_manager: Arc<MemoryManager>,
+    roots: RwLock<HashSet<ObjectRef>>,
+    /// The major collection in progress
+    marking: Mutex<Option<Marking>>,
+    stats: RwLock<GcStats>,
+    config: GcConfig,
+}
+
+struct Marking {
+    marked: HashSet<ObjectRef>,
+    /// Marked objects whose references are still to be marked
+    gray: Vec<ObjectRef>,
 }
 
 impl GarbageCollector {
     pub fn new(memory_manager: Arc<MemoryManager>) -> Self {
-        Self {
-            memory_manager,
-            roots: RwLock::new(HashSet::new()),
-            collection_thread: RwLock::new(None),
-            stats: RwLock::new(GcStats::default()),
-            config: GcConfig::default(),
-        }
+        Self::with_config(memory_manager, GcConfig::default())
     }
 
     pub fn with_config(memory_manager: Arc<MemoryManager>, config: GcConfig) -> Self {
         Self {
-            memory_manager,
-            roots: RwLock::new(HashSet::new()),
+            collector: Arc::new(Collector {
+                memory_manager,
+                roots: RwLock::new(HashSet::new()),
+                marking: Mutex::new(None),
+                stats: RwLock::new(GcStats::default()),
+                config,
+            }),
             collection_thread: RwLock::new(None),
-            stats: RwLock::new(GcStats::default()),
-            config,
         }
     }
 
     pub async fn start(&self) -> Result<()> {
-        let memory_manager = Arc::clone(&self.memory_manager);
-        let config = self.config.clone();
-        let stats = Arc::new(RwLock::new(GcStats::default()));
+        let collector = Arc::clone(&self.collector);
 
         let handle = tokio::spawn(async move {
-            let mut interval = interval(config.collection_interval);
+            let mut interval = interval(collector.config.collection_interval);
 
             loop {
                 interval.tick().await;
-
-                // Check if collection is needed
-                let memory_usage = memory_manager.get_memory_usage().await;
-                if memory_usage.total_allocated > config.memory_threshold {
-                    let start_time = Instant::now();
-
-                    // Perform collection
-                    if let Ok(collected) = Self::collect_garbage(&memory_manager, &config).await {
-                        let collection_time = start_time.elapsed();
-
-                        // Update stats
-                        let mut stats_guard = stats.write();
-                        stats_guard.collections += 1;
-                        stats_guard.objects_collected += collected.objects as u64;
-                        stats_guard.bytes_collected += collected.bytes as u64;
-                        stats_guard.collection_time += collection_time;
-                        stats_guard.last_collection = Some(start_time);
-                    }
-                }
+                collector.tick();
             }
         });
 
@@ -116,145 +272,250 @@ impl GarbageCollector {
     }
 
     pub fn add_root(&self, object: ObjectRef) {
-        self.roots.write().insert(object);
+        self.collector.roots.write().insert(object);
     }
 
     pub fn remove_root(&self, object: &ObjectRef) {
-        self.roots.write().remove(object);
+        self.collector.roots.write().remove(object);
     }
 
+    /// Collect every generation now, finishing any major collection in
+    /// progress
     pub async fn force_collection(&self) -> Result<CollectionResult> {
-        Self::collect_garbage(&self.memory_manager, &self.config).await
+        Ok(self.collector.collect_all())
     }
 
-    async fn collect_garbage(
-        memory_manager: &MemoryManager,
-        config: &GcConfig,
-    ) -> Result<CollectionResult> {
-        if config.generational {
-            Self::generational_collect(memory_manager, config).await
-        } else {
-            Self::mark_and_sweep(memory_manager).await
-        }
+    /// Collect the young generations now
+    pub fn collect_minor(&self) -> CollectionResult {
+        self.collector.collect_minor()
     }
 
-    async fn mark_and_sweep(memory_manager: &MemoryManager) -> Result<CollectionResult> {
-        // Mark phase
-        let mut marked = HashSet::new();
-        let roots = memory_manager.get_roots().await;
+    /// Take one step of a major collection, starting one if none is in
+    /// progress. Returns what was freed once the collection finishes.
+    pub fn major_step(&self) -> Option<CollectionResult> {
+        self.collector.major_step()
+    }
 
-        for root in roots {
-            Self::mark_object(&mut marked, &root, memory_manager).await;
-        }
+    /// Whether a major collection is in progress
+    pub fn is_marking(&self) -> bool {
+        self.collector.marking.lock().is_some()
+    }
 
-        // Sweep phase
-        let all_objects = memory_manager.get_all_objects().await;
-        let mut collected_objects = 0;
-        let mut collected_bytes = 0;
-
-        for object_ref in all_objects {
-            if !marked.contains(&object_ref) {
-                let size = memory_manager.get_object_size(&object_ref).await;
-                memory_manager.deallocate_object(object_ref).await?;
-                collected_objects += 1;
-                collected_bytes += size;
-            }
-        }
+    pub fn get_stats(&self) -> GcStats {
+        (*self.collector.stats.read()).clone()
+    }
 
-        Ok(CollectionResult {
-            objects: collected_objects,
-            bytes: collected_bytes,
-        })
+    /// Pause times and collection counts for a metrics endpoint, in the
+    /// Prometheus text format
+    pub fn export_metrics(&self) -> String {
+        self.collector.stats.read().export_metrics()
     }
+}
 
-    async fn generational_collect(
-        memory_manager: &MemoryManager,
-        config: &GcConfig,
-    ) -> Result<CollectionResult> {
-        let mut total_result = CollectionResult {
-            objects: 0,
-            bytes: 0,
-        };
+impl Collector {
+    fn oldest(&self) -> usize {
+        self.config.generations.max(2) - 1
+    }
 
-        // Collect younger generations more frequently
-        for generation in 0..config.generations {
-            let result = Self::collect_generation(memory_manager, generation).await?;
-            total_result.objects += result.objects;
-            total_result.bytes += result.bytes;
+    fn roots(&self) -> Vec<ObjectRef> {
+        let mut roots = self.memory_manager.roots();
+        roots.extend(self.roots.read().iter().copied());
+        roots
+    }
 
-            // Only collect older generations occasionally
-            if generation > 0 && total_result.objects < 100 {
-                break;
+    /// Collect what the configuration and memory use call for
+    fn tick(&self) {
+        let memory = &self.memory_manager;
+        if !self.config.generational {
+            let stats = memory.get_stats();
+            if stats.total_allocated - stats.total_freed > self.config.memory_threshold {
+                self.mark_and_sweep();
             }
+            return;
         }
 
-        Ok(total_result)
+        if memory.generation_bytes(0) >= self.config.young_generation_size {
+            self.collect_minor();
+        }
+        let marking = self.marking.lock().is_some();
+        if marking || memory.generation_bytes(self.oldest()) > self.config.memory_threshold {
+            self.major_step();
+        }
     }
 
-    async fn collect_generation(
-        memory_manager: &MemoryManager,
-        generation: usize,
-    ) -> Result<CollectionResult> {
-        // Simplified generational collection
-        // In a real implementation, this would track object ages
-        // and collect objects based on their generation
+    fn collect_all(&self) -> CollectionResult {
+        if !self.config.generational {
+            return self.mark_and_sweep();
+        }
+        let mut result = self.collect_minor();
+        let major = loop {
+            if let Some(major) = self.major_step() {
+                break major;
+            }
+        };
+        result.objects += major.objects;
+        result.bytes += major.bytes;
+        result
+    }
 
-        let objects_in_generation = memory_manager.get_objects_in_generation(generation).await;
-        let mut marked = HashSet::new();
+    fn collect_minor(&self) -> CollectionResult {
+        let start = Instant::now();
+        let memory = &self.memory_manager;
+        let oldest = self.oldest();
+        let young = |object: &ObjectRef| memory.generation_of(object).is_some_and(|g| g < oldest);
+
+        // Old objects that reference young ones act as roots
+        let mut gray: Vec<ObjectRef> = self.roots().into_iter().filter(young).collect();
+        for holder in memory.remembered() {
+            if memory.generation_of(&holder) == Some(oldest) {
+                gray.extend(memory.references_of(&holder).into_iter().filter(young));
+            }
+        }
+        let mut live = HashSet::new();
+        while let Some(object) = gray.pop() {
+            if live.insert(object) {
+                gray.extend(memory.references_of(&object).into_iter().filter(young));
+            }
+        }
 
-        // Mark objects reachable from older generations and roots
-        let roots = memory_manager.get_roots().await;
-        for root in roots {
-            Self::mark_object(&mut marked, &root, memory_manager).await;
+        let mut result = CollectionResult::default();
+        let mut promoted = 0;
+        let mut marking = self.marking.lock();
+        for generation in (0..oldest).rev() {
+            for object in memory.generation_objects(generation) {
+                if !live.contains(&object) {
+                    result.bytes += memory.free(object);
+                    result.objects += 1;
+                    continue;
+                }
+                memory.promote(object, generation + 1);
+                promoted += 1;
+                // Objects reaching the oldest generation during a major
+                // collection are live, so they count as marked
+                if let Some(marking) = marking.as_mut().filter(|_| generation + 1 == oldest) {
+                    if marking.marked.insert(object) {
+                        marking.gray.extend(memory.references_of(&object));
+                    }
+                }
+            }
         }
+        drop(marking);
+        memory.prune_remembered();
+
+        let pause = start.elapsed();
+        let mut stats = self.stats.write();
+        stats.minor_collections += 1;
+        stats.objects_promoted += promoted;
+        stats.minor_pauses.record(pause);
+        self.record(&mut stats, &result, start, pause);
+        result
+    }
+
+    fn major_step(&self) -> Option<CollectionResult> {
+        let start = Instant::now();
+        let memory = &self.memory_manager;
+        let mut guard = self.marking.lock();
+        let marking = guard.get_or_insert_with(|| {
+            memory.track_modified(true);
+            Marking {
+                marked: HashSet::new(),
+                gray: self.roots(),
+            }
+        });
 
-        // Sweep unmarked objects in this generation
-        let mut collected_objects = 0;
-        let mut collected_bytes = 0;
+        let mut budget = self.config.mark_step.max(1);
+        while budget > 0 {
+            let Some(object) = marking.gray.pop() else {
+                break;
+            };
+            if marking.marked.insert(object) {
+                marking.gray.extend(memory.references_of(&object));
+                budget -= 1;
+            }
+        }
+        if !marking.gray.is_empty() {
+            self.stats.write().major_pauses.record(start.elapsed());
+            return None;
+        }
 
-        for object_ref in objects_in_generation {
-            if !marked.contains(&object_ref) {
-                let size = memory_manager.get_object_size(&object_ref).await;
-                memory_manager.deallocate_object(object_ref).await?;
-                collected_objects += 1;
-                collected_bytes += size;
+        // Remark: the roots and the objects given new references while
+        // marking may reach objects that are not marked yet
+        let mut marking = guard.take().expect("major collection in progress");
+        marking.gray.extend(self.roots());
+        for object in memory.take_modified() {
+            if marking.marked.contains(&object) {
+                marking.gray.extend(memory.references_of(&object));
             }
         }
+        memory.track_modified(false);
+        while let Some(object) = marking.gray.pop() {
+            if marking.marked.insert(object) {
+                marking.gray.extend(memory.references_of(&object));
+            }
+        }
+        drop(guard);
 
-        Ok(CollectionResult {
-            objects: collected_objects,
-            bytes: collected_bytes,
-        })
+        let mut result = CollectionResult::default();
+        for object in memory.generation_objects(self.oldest()) {
+            if !marking.marked.contains(&object) {
+                result.bytes += memory.free(object);
+                result.objects += 1;
+            }
+        }
+        memory.prune_remembered();
+
+        let pause = start.elapsed();
+        let mut stats = self.stats.write();
+        stats.major_collections += 1;
+        stats.major_pauses.record(pause);
+        self.record(&mut stats, &result, start, pause);
+        Some(result)
     }
 
-    fn mark_object<'a>(
-        marked: &'a mut HashSet<ObjectRef>,
-        object_ref: &'a ObjectRef,
-        memory_manager: &'a MemoryManager,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>> {
-        Box::pin(async move {
-            if marked.contains(object_ref) {
-                return;
-            }
+    /// Collect the whole heap at once, without generations
+    fn mark_and_sweep(&self) -> CollectionResult {
+        let start = Instant::now();
+        let memory = &self.memory_manager;
 
-            marked.insert(*object_ref);
+        // Mark phase
+        let mut marked = HashSet::new();
+        let mut gray = self.roots();
+        while let Some(object) = gray.pop() {
+            if marked.insert(object) {
+                gray.extend(memory.references_of(&object));
+            }
+        }
 
-            // Mark all objects referenced by this object
-            if let Ok(references) = memory_manager.get_object_references(object_ref).await {
-                for referenced in references {
-                    Self::mark_object(marked, &referenced, memory_manager).await;
+        // Sweep phase
+        let mut result = CollectionResult::default();
+        for generation in 0..self.config.generations.max(1) {
+            for object in memory.generation_objects(generation) {
+                if !marked.contains(&object) {
+                    result.bytes += memory.free(object);
+                    result.objects += 1;
                 }
             }
-        })
-    }
+        }
 
-    pub fn get_stats(&self) -> GcStats {
-        (*self.stats.read()).clone()
+        let pause = start.elapsed();
+        let mut stats = self.stats.write();
+        stats.major_collections += 1;
+        stats.major_pauses.record(pause);
+        self.record(&mut stats, &result, start, pause);
+        result
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct CollectionResult {
-    pub objects: usize,
-    pub bytes: usize,
+    fn record(
+        &self,
+        stats: &mut GcStats,
+        result: &CollectionResult,
+        start: Instant,
+        pause: Duration,
+    ) {
+        stats.collections += 1;
+        stats.objects_collected += result.objects as u64;
+        stats.bytes_collected += result.bytes as u64;
+        stats.collection_time += pause;
+        stats.last_collection = Some(start);
+    }
 }
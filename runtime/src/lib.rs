@@ -1,4 +1,5 @@
 use anyhow::Result;
+use std::fmt::Write;
 use std::sync::Arc;
 
 pub mod actor_system;
@@ -6,6 +7,7 @@ pub mod async_runtime;
 pub mod garbage_collector;
 pub mod jit_compiler;
 pub mod memory_manager;
+pub mod script_pool;
 pub mod thread_pool;
 
 // Re-export important types
@@ -14,6 +16,7 @@ pub use async_runtime::*;
 pub use garbage_collector::*;
 pub use jit_compiler::*;
 pub use memory_manager::*;
+pub use script_pool::*;
 pub use thread_pool::*;
 
 /// Main runtime system for Veyra
@@ -61,14 +64,38 @@ impl VeyraRuntime {
         // Shutdown all subsystems in reverse order
         self.jit_compiler.shutdown().await?;
         self.actor_system.shutdown().await?;
-        // Note: ThreadPool doesn't support mutable shutdown through Arc
-        // In a real implementation, we'd use a different approach
+        self.thread_pool.shutdown().await?;
         self.async_runtime.shutdown().await?;
         self.garbage_collector.stop().await?;
         self.memory_manager.shutdown().await?;
 
         Ok(())
     }
+
+    /// The runtime's metrics for a scrape endpoint, in the Prometheus text
+    /// format: garbage collection pauses and counts, and the size of the heap
+    pub fn export_metrics(&self) -> String {
+        let mut out = self.garbage_collector.export_metrics();
+        let heap = self.memory_manager.get_stats();
+        for (name, help, value) in [
+            (
+                "veyra_heap_bytes",
+                "Bytes of live objects",
+                heap.total_allocated - heap.total_freed,
+            ),
+            ("veyra_heap_objects", "Live objects", heap.active_objects),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+
+    /// Evaluate independent scripts on this runtime's thread pool
+    pub fn script_pool(&self, limits: veyra_compiler::Limits) -> ScriptPool {
+        ScriptPool::new(Arc::clone(&self.thread_pool)).with_limits(limits)
+    }
 }
 
 impl Default for VeyraRuntime {
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
 
 /// Advanced memory manager with region-based allocation and tracking
@@ -8,6 +9,12 @@ pub struct MemoryManager {
     regions: RwLock<HashMap<RegionId, Region>>,
     objects: RwLock<HashMap<ObjectRef, ObjectInfo>>,
     roots: RwLock<HashSet<ObjectRef>>,
+    /// Objects holding a reference to an object of a younger generation,
+    /// which a minor collection treats as roots
+    remembered: RwLock<HashSet<ObjectRef>>,
+    /// Objects given new references while `track_modified` is on
+    modified: RwLock<HashSet<ObjectRef>>,
+    tracking_modified: AtomicBool,
     stats: RwLock<MemoryStats>,
     config: MemoryConfig,
 }
@@ -83,6 +90,9 @@ impl MemoryManager {
             regions: RwLock::new(HashMap::new()),
             objects: RwLock::new(HashMap::new()),
             roots: RwLock::new(HashSet::new()),
+            remembered: RwLock::new(HashSet::new()),
+            modified: RwLock::new(HashSet::new()),
+            tracking_modified: AtomicBool::new(false),
             stats: RwLock::new(MemoryStats::default()),
             config,
         }
@@ -90,7 +100,7 @@ impl MemoryManager {
 
     pub async fn initialize(&self) -> Result<()> {
         // Create initial region
-        self.create_region(0).await?;
+        self.create_region(0);
         Ok(())
     }
 
@@ -99,6 +109,8 @@ impl MemoryManager {
         self.regions.write().clear();
         self.objects.write().clear();
         self.roots.write().clear();
+        self.remembered.write().clear();
+        self.modified.write().clear();
         Ok(())
     }
 
@@ -111,7 +123,7 @@ impl MemoryManager {
         let object_ref = Uuid::new_v4();
 
         // Find or create a suitable region
-        let region_id = self.find_or_create_region(size, generation).await?;
+        let region_id = self.find_or_create_region(size, generation);
 
         // Create object info
         let object_info = ObjectInfo {
@@ -151,31 +163,41 @@ impl MemoryManager {
     }
 
     pub async fn deallocate_object(&self, object_ref: ObjectRef) -> Result<()> {
+        self.free(object_ref);
+        Ok(())
+    }
+
+    /// Deallocate an object, returning its size
+    pub fn free(&self, object_ref: ObjectRef) -> usize {
         let object_info = {
             let mut objects = self.objects.write();
             objects.remove(&object_ref)
         };
 
-        if let Some(info) = object_info {
-            // Update region
-            {
-                let mut regions = self.regions.write();
-                if let Some(region) = regions.get_mut(&info.region_id) {
-                    region.allocated -= info.size;
-                    region.objects.remove(&object_ref);
-                }
-            }
+        let Some(info) = object_info else {
+            return 0;
+        };
 
-            // Update stats
-            {
-                let mut stats = self.stats.write();
-                stats.total_freed += info.size;
-                stats.active_objects -= 1;
-                stats.deallocations += 1;
+        // Update region
+        {
+            let mut regions = self.regions.write();
+            if let Some(region) = regions.get_mut(&info.region_id) {
+                region.allocated -= info.size;
+                region.objects.remove(&object_ref);
             }
         }
+        self.remembered.write().remove(&object_ref);
+        self.modified.write().remove(&object_ref);
 
-        Ok(())
+        // Update stats
+        {
+            let mut stats = self.stats.write();
+            stats.total_freed += info.size;
+            stats.active_objects -= 1;
+            stats.deallocations += 1;
+        }
+
+        info.size
     }
 
     pub async fn add_reference(&self, from_object: ObjectRef, to_object: ObjectRef) -> Result<()> {
@@ -193,6 +215,18 @@ impl MemoryManager {
             to_info.reference_count += 1;
         }
 
+        // Write barrier: remember old-to-young references for minor
+        // collections, and new references for incremental marking
+        let generation = |object| objects.get(object).map(|info| info.generation);
+        if let (Some(from), Some(to)) = (generation(&from_object), generation(&to_object)) {
+            if from > to {
+                self.remembered.write().insert(from_object);
+            }
+        }
+        if self.tracking_modified.load(Ordering::SeqCst) {
+            self.modified.write().insert(from_object);
+        }
+
         Ok(())
     }
 
@@ -217,12 +251,22 @@ impl MemoryManager {
     }
 
     pub async fn get_object_references(&self, object_ref: &ObjectRef) -> Result<Vec<ObjectRef>> {
+        Ok(self.references_of(object_ref))
+    }
+
+    pub fn references_of(&self, object_ref: &ObjectRef) -> Vec<ObjectRef> {
         let objects = self.objects.read();
-        if let Some(info) = objects.get(object_ref) {
-            Ok(info.references.clone())
-        } else {
-            Ok(Vec::new())
-        }
+        objects
+            .get(object_ref)
+            .map(|info| info.references.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn generation_of(&self, object_ref: &ObjectRef) -> Option<usize> {
+        self.objects
+            .read()
+            .get(object_ref)
+            .map(|info| info.generation)
     }
 
     pub async fn get_object_size(&self, object_ref: &ObjectRef) -> usize {
@@ -236,15 +280,113 @@ impl MemoryManager {
     }
 
     pub async fn get_objects_in_generation(&self, generation: usize) -> Vec<ObjectRef> {
-        let objects = self.objects.read();
-        objects
+        self.generation_objects(generation)
+    }
+
+    /// Objects of one generation, found through its regions
+    pub fn generation_objects(&self, generation: usize) -> Vec<ObjectRef> {
+        let regions = self.regions.read();
+        regions
             .values()
-            .filter(|info| info.generation == generation)
-            .map(|info| info.object_ref)
+            .filter(|region| region.generation == generation)
+            .flat_map(|region| region.objects.iter().copied())
             .collect()
     }
 
+    /// Bytes allocated to objects of one generation
+    pub fn generation_bytes(&self, generation: usize) -> usize {
+        let regions = self.regions.read();
+        regions
+            .values()
+            .filter(|region| region.generation == generation)
+            .map(|region| region.allocated)
+            .sum()
+    }
+
+    /// Move an object to an older generation
+    pub fn promote(&self, object_ref: ObjectRef, generation: usize) {
+        let Some((size, old_region)) = self
+            .objects
+            .read()
+            .get(&object_ref)
+            .map(|info| (info.size, info.region_id))
+        else {
+            return;
+        };
+        let new_region = self.find_or_create_region(size, generation);
+
+        {
+            let mut regions = self.regions.write();
+            if let Some(region) = regions.get_mut(&old_region) {
+                region.allocated -= size;
+                region.objects.remove(&object_ref);
+            }
+            if let Some(region) = regions.get_mut(&new_region) {
+                region.allocated += size;
+                region.objects.insert(object_ref);
+            }
+        }
+
+        let younger_references = {
+            let mut objects = self.objects.write();
+            if let Some(info) = objects.get_mut(&object_ref) {
+                info.generation = generation;
+                info.region_id = new_region;
+            }
+            let references = objects
+                .get(&object_ref)
+                .map(|info| info.references.clone())
+                .unwrap_or_default();
+            references.iter().any(|reference| {
+                objects
+                    .get(reference)
+                    .is_some_and(|info| info.generation < generation)
+            })
+        };
+        if younger_references {
+            self.remembered.write().insert(object_ref);
+        }
+    }
+
+    /// Objects holding references to younger objects
+    pub fn remembered(&self) -> Vec<ObjectRef> {
+        self.remembered.read().iter().copied().collect()
+    }
+
+    /// Forget remembered objects that no longer reference younger objects
+    pub fn prune_remembered(&self) {
+        let objects = self.objects.read();
+        let younger = |object: &ObjectRef| {
+            let Some(info) = objects.get(object) else {
+                return false;
+            };
+            info.references.iter().any(|reference| {
+                objects
+                    .get(reference)
+                    .is_some_and(|referenced| referenced.generation < info.generation)
+            })
+        };
+        self.remembered.write().retain(younger);
+    }
+
+    /// Start or stop recording which objects are given new references
+    pub fn track_modified(&self, on: bool) {
+        self.tracking_modified.store(on, Ordering::SeqCst);
+        if !on {
+            self.modified.write().clear();
+        }
+    }
+
+    /// The objects given new references since the last call
+    pub fn take_modified(&self) -> HashSet<ObjectRef> {
+        std::mem::take(&mut *self.modified.write())
+    }
+
     pub async fn get_roots(&self) -> Vec<ObjectRef> {
+        self.roots()
+    }
+
+    pub fn roots(&self) -> Vec<ObjectRef> {
         let roots = self.roots.read();
         roots.iter().cloned().collect()
     }
@@ -280,11 +422,7 @@ impl MemoryManager {
         self.stats.read().clone()
     }
 
-    async fn find_or_create_region(
-        &self,
-        required_size: usize,
-        generation: usize,
-    ) -> Result<RegionId> {
+    fn find_or_create_region(&self, required_size: usize, generation: usize) -> RegionId {
         // First, try to find an existing region with enough space
         {
             let regions = self.regions.read();
@@ -292,16 +430,16 @@ impl MemoryManager {
                 if region.generation == generation
                     && (region.size - region.allocated) >= required_size
                 {
-                    return Ok(region.id);
+                    return region.id;
                 }
             }
         }
 
         // Create a new region
-        self.create_region(generation).await
+        self.create_region(generation)
     }
 
-    async fn create_region(&self, generation: usize) -> Result<RegionId> {
+    fn create_region(&self, generation: usize) -> RegionId {
         let region_id = Uuid::new_v4();
         let region = Region {
             id: region_id,
@@ -316,7 +454,7 @@ impl MemoryManager {
         // Update stats
         self.stats.write().active_regions += 1;
 
-        Ok(region_id)
+        region_id
     }
 
     pub async fn defragment(&self) -> Result<()> {
@@ -0,0 +1,150 @@
+use crate::ThreadPool;
+use anyhow::Result;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use veyra_compiler::cfg::{self, CfgOptions};
+use veyra_compiler::modules::ModuleLoader;
+use veyra_compiler::playground;
+use veyra_compiler::sandbox::Policy;
+use veyra_compiler::{Interpreter, Limits, VeyraError};
+
+/// Batch evaluation of independent scripts on the runtime thread pool.
+/// Each script gets its own interpreter, so scripts share no state, and runs
+/// under the pool's resource limits and capability policy. Scripts cannot
+/// import modules. By default they run as playground programs do: with no
+/// access to files, the network, processes or the environment, and under
+/// the playground's limits.
+pub struct ScriptPool {
+    pool: Arc<ThreadPool>,
+    limits: Limits,
+    policy: Policy,
+}
+
+/// Result of a script that ran to completion
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptOutput {
+    /// Everything the script printed
+    pub output: String,
+    pub elapsed: Duration,
+}
+
+pub type ScriptResult = std::result::Result<ScriptOutput, VeyraError>;
+
+/// A script queued on the pool; resolves once it has run
+pub struct ScriptHandle {
+    receiver: oneshot::Receiver<ScriptResult>,
+}
+
+impl ScriptPool {
+    /// Run scripts on `pool`, which must already be started
+    pub fn new(pool: Arc<ThreadPool>) -> Self {
+        Self {
+            pool,
+            limits: playground::LIMITS,
+            policy: Policy::deny_all(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Let scripts make the accesses `policy` allows
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+
+    /// Queue one script
+    pub fn evaluate(&self, source: impl Into<String>) -> Result<ScriptHandle> {
+        let source = source.into();
+        let limits = self.limits;
+        let policy = self.policy.clone();
+        let (sender, receiver) = oneshot::channel();
+
+        self.pool.execute(move || {
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| run_script(&source, limits, policy)))
+                    .unwrap_or_else(|_| {
+                        Err(VeyraError::InternalError(
+                            "Interpreter panicked while running the script".to_string(),
+                        ))
+                    });
+            // The caller may have dropped the handle; the result is then unused
+            let _ = sender.send(result);
+        })?;
+
+        Ok(ScriptHandle { receiver })
+    }
+
+    /// Queue every script, returning handles in the same order
+    pub fn evaluate_all<I, S>(&self, sources: I) -> Result<Vec<ScriptHandle>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        sources
+            .into_iter()
+            .map(|source| self.evaluate(source))
+            .collect()
+    }
+
+    /// Run every script and wait for all of them, in order
+    pub async fn run_all<I, S>(&self, sources: I) -> Result<Vec<ScriptResult>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let handles = self.evaluate_all(sources)?;
+        Ok(futures::future::join_all(handles).await)
+    }
+}
+
+impl Future for ScriptHandle {
+    type Output = ScriptResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.receiver).poll(cx).map(|result| {
+            result.unwrap_or_else(|_| {
+                Err(VeyraError::InternalError(
+                    "Script was dropped before it ran".to_string(),
+                ))
+            })
+        })
+    }
+}
+
+fn run_script(source: &str, limits: Limits, policy: Policy) -> ScriptResult {
+    let start = Instant::now();
+
+    let tokens = veyra_compiler::lexer::tokenize(source)?;
+    let program = veyra_compiler::parser::parse(tokens)?;
+    let program = cfg::configure(program, &CfgOptions::host());
+
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_echo_output(false);
+    interpreter.set_policy(policy);
+    interpreter.set_module_loader(ModuleLoader::disabled());
+    interpreter.set_limits(limits);
+    interpreter.interpret(&program)?;
+
+    Ok(ScriptOutput {
+        output: interpreter.take_output(),
+        elapsed: start.elapsed(),
+    })
+}
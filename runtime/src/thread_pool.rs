@@ -1,29 +1,37 @@
 use anyhow::Result;
-use crossbeam::queue::SegQueue;
-use parking_lot::{Mutex, RwLock};
-use std::collections::VecDeque;
-use std::sync::atomic::{AtomicBool, Ordering};
+use crossbeam::deque::{Injector, Steal, Stealer, Worker as Deque};
+use parking_lot::{Condvar, Mutex};
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
-use uuid::Uuid;
 
-/// Advanced thread pool with work stealing and load balancing
+/// Thread pool with a work-stealing scheduler.
+///
+/// Every worker has its own deque. A job queued from inside a running job,
+/// as a fan-out does, goes to the deque of the worker running it, which
+/// takes its newest job first while idle workers steal the oldest from the
+/// other end. Jobs queued from outside the pool go to a shared queue.
+/// `JobPriority::High` jobs have a lane of their own that every worker
+/// checks before anything else, and `JobPriority::Low` jobs run when there
+/// is nothing else to do. So that a steady stream of higher priority jobs
+/// cannot starve the others, every `FAIRNESS_INTERVAL`th job a worker takes
+/// comes from the lower lanes first. The pool has a fixed number of workers.
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    global_queue: Arc<SegQueue<Job>>,
-    shutdown: Arc<AtomicBool>,
-    stats: Arc<RwLock<ThreadPoolStats>>,
-    config: ThreadPoolConfig,
+    shared: Arc<Shared>,
+    /// Deques of the workers, handed to their threads by `start`
+    deques: Mutex<Vec<Deque<Job>>>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
 #[derive(Debug, Clone)]
 pub struct ThreadPoolConfig {
-    pub min_threads: usize,
-    pub max_threads: usize,
-    pub keep_alive: Duration,
+    /// Worker threads, started by `start` and kept until `shutdown`
+    pub threads: usize,
     pub queue_size: usize,
     pub work_stealing: bool,
 }
@@ -31,9 +39,7 @@ pub struct ThreadPoolConfig {
 impl Default for ThreadPoolConfig {
     fn default() -> Self {
         Self {
-            min_threads: 2,
-            max_threads: num_cpus::get() * 2,
-            keep_alive: Duration::from_secs(60),
+            threads: 2,
             queue_size: 1024,
             work_stealing: true,
         }
@@ -47,68 +53,136 @@ pub struct ThreadPoolStats {
     pub jobs_executed: u64,
     pub jobs_queued: u64,
     pub jobs_stolen: u64,
+    /// Jobs run from the high priority lane
+    pub jobs_prioritized: u64,
     pub average_execution_time: Duration,
     pub peak_queue_size: usize,
 }
 
-struct Worker {
-    id: Uuid,
-    thread: Option<JoinHandle<()>>,
-    local_queue: Arc<Mutex<VecDeque<Job>>>,
-    is_active: Arc<AtomicBool>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobPriority {
+    High,
+    Normal,
+    Low,
+}
+
+/// How long an idle worker sleeps before looking for work again, in case
+/// it missed a wakeup for a job pushed to another worker's deque
+const IDLE_WAIT: Duration = Duration::from_millis(10);
+
+/// How often a worker takes a job from the lower lanes first: every
+/// `FAIRNESS_INTERVAL`th job it takes, alternately starting with normal and
+/// with low priority jobs. Between those, high priority jobs come first.
+const FAIRNESS_INTERVAL: u64 = 8;
+
+/// A queue of jobs of one priority
+#[derive(Clone, Copy)]
+enum Lane {
+    High,
+    Normal,
+    Low,
+}
+
+/// Tells pools apart in `LOCAL`
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The deque of the worker running on this thread
+    static LOCAL: RefCell<Option<Local>> = const { RefCell::new(None) };
+}
+
+struct Local {
+    pool: usize,
+    deque: Deque<Job>,
+}
+
+struct Shared {
+    id: usize,
+    priority: Injector<Job>,
+    injector: Injector<Job>,
+    low: Injector<Job>,
+    stealers: Vec<Stealer<Job>>,
+    /// Deque length above which jobs queued by a worker go to the shared
+    /// queue instead
+    local_limit: usize,
+    work_stealing: bool,
+    shutdown: AtomicBool,
+    idle: Mutex<()>,
+    wake: Condvar,
+    counters: Counters,
+}
+
+#[derive(Default)]
+struct Counters {
+    active: AtomicUsize,
+    executed: AtomicU64,
+    queued: AtomicU64,
+    stolen: AtomicU64,
+    prioritized: AtomicU64,
+    busy_nanos: AtomicU64,
+    peak_queue: AtomicUsize,
 }
 
 impl ThreadPool {
     pub fn new(thread_count: usize) -> Self {
         Self::with_config(ThreadPoolConfig {
-            min_threads: thread_count,
-            max_threads: thread_count * 2,
+            threads: thread_count,
             ..Default::default()
         })
     }
 
     pub fn with_config(config: ThreadPoolConfig) -> Self {
-        let global_queue = Arc::new(SegQueue::new());
-        let shutdown = Arc::new(AtomicBool::new(false));
-        let stats = Arc::new(RwLock::new(ThreadPoolStats::default()));
-
-        let mut workers = Vec::with_capacity(config.max_threads);
-
-        // Create initial workers
-        for _ in 0..config.min_threads {
-            let worker = Worker::new();
-            workers.push(worker);
-        }
+        let workers = config.threads.max(1);
+        let deques: Vec<Deque<Job>> = (0..workers).map(|_| Deque::new_lifo()).collect();
+        let shared = Shared {
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            priority: Injector::new(),
+            injector: Injector::new(),
+            low: Injector::new(),
+            stealers: deques.iter().map(Deque::stealer).collect(),
+            local_limit: (config.queue_size / workers).max(1),
+            work_stealing: config.work_stealing,
+            shutdown: AtomicBool::new(false),
+            idle: Mutex::new(()),
+            wake: Condvar::new(),
+            counters: Counters::default(),
+        };
 
         Self {
-            workers,
-            global_queue,
-            shutdown,
-            stats,
-            config,
+            shared: Arc::new(shared),
+            deques: Mutex::new(deques),
+            threads: Mutex::new(Vec::new()),
         }
     }
 
+    /// Start the workers. Jobs queued before the pool starts wait for it.
     pub async fn start(&self) -> Result<()> {
-        for worker in &self.workers {
-            worker.start(
-                Arc::clone(&self.global_queue),
-                Arc::clone(&self.shutdown),
-                Arc::clone(&self.stats),
-            )?;
+        let deques = std::mem::take(&mut *self.deques.lock());
+        let mut threads = self.threads.lock();
+        for (index, deque) in deques.into_iter().enumerate() {
+            let shared = Arc::clone(&self.shared);
+            let thread = thread::Builder::new()
+                .name(format!("veyra-worker-{}", index))
+                .spawn(move || worker_loop(shared, index, deque))?;
+            threads.push(thread);
         }
         Ok(())
     }
 
-    pub async fn shutdown(&mut self) -> Result<()> {
-        // Signal shutdown
-        self.shutdown.store(true, Ordering::SeqCst);
-
-        // Wait for all workers to finish
-        for worker in &mut self.workers {
-            worker.shutdown().await?;
+    /// Stop the workers once they finish the jobs they are running. Jobs
+    /// still queued are dropped.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.wake_all();
+
+        let threads = std::mem::take(&mut *self.threads.lock());
+        for thread in threads {
+            // Only the current thread can be a worker, when a job shuts
+            // down its own pool
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
         }
-
         Ok(())
     }
 
@@ -116,208 +190,213 @@ impl ThreadPool {
     where
         F: FnOnce() + Send + 'static,
     {
-        if self.shutdown.load(Ordering::SeqCst) {
+        self.execute_with_priority(job, JobPriority::Normal)
+    }
+
+    pub fn execute_with_priority<F>(&self, job: F, priority: JobPriority) -> Result<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let shared = &self.shared;
+        if shared.shutdown.load(Ordering::SeqCst) {
             return Err(anyhow::anyhow!("Thread pool is shutting down"));
         }
 
-        let job = Box::new(job);
-
-        // Try to assign to least loaded worker first
-        if let Some(worker) = self.find_least_loaded_worker() {
-            if let Some(mut queue) = worker.local_queue.try_lock() {
-                if queue.len() < self.config.queue_size / self.workers.len() {
-                    queue.push_back(job);
-                    self.stats.write().jobs_queued += 1;
-                    return Ok(());
+        let job: Job = Box::new(job);
+        match priority {
+            JobPriority::High => shared.priority.push(job),
+            JobPriority::Low => shared.low.push(job),
+            JobPriority::Normal => {
+                if let Err(job) = shared.push_local(job) {
+                    shared.injector.push(job);
                 }
             }
         }
 
-        // Fall back to global queue
-        self.global_queue.push(job);
-        self.stats.write().jobs_queued += 1;
-
-        // Scale up if needed
-        self.scale_up_if_needed()?;
-
+        let counters = &shared.counters;
+        counters.queued.fetch_add(1, Ordering::Relaxed);
+        counters
+            .peak_queue
+            .fetch_max(shared.queued(), Ordering::Relaxed);
+        shared.wake_one();
         Ok(())
     }
 
-    pub fn execute_with_priority<F>(&self, job: F, _priority: JobPriority) -> Result<()>
-    where
-        F: FnOnce() + Send + 'static,
-    {
-        // For now, just execute normally
-        // In a full implementation, we'd have priority queues
-        self.execute(job)
-    }
-
     pub fn get_stats(&self) -> ThreadPoolStats {
-        let mut stats = self.stats.read().clone();
+        let counters = &self.shared.counters;
+        let workers = self.shared.stealers.len();
+        let active_threads = counters.active.load(Ordering::Relaxed).min(workers);
+        let jobs_executed = counters.executed.load(Ordering::Relaxed);
+        let busy = Duration::from_nanos(counters.busy_nanos.load(Ordering::Relaxed));
+
+        ThreadPoolStats {
+            active_threads,
+            idle_threads: workers - active_threads,
+            jobs_executed,
+            jobs_queued: counters.queued.load(Ordering::Relaxed),
+            jobs_stolen: counters.stolen.load(Ordering::Relaxed),
+            jobs_prioritized: counters.prioritized.load(Ordering::Relaxed),
+            average_execution_time: busy
+                .checked_div(jobs_executed.try_into().unwrap_or(u32::MAX))
+                .unwrap_or_default(),
+            peak_queue_size: counters.peak_queue.load(Ordering::Relaxed),
+        }
+    }
 
-        // Update current thread counts
-        stats.active_threads = self.count_active_threads();
-        stats.idle_threads = self.workers.len() - stats.active_threads;
+    /// Number of worker threads
+    pub fn worker_count(&self) -> usize {
+        self.shared.stealers.len()
+    }
+}
 
-        stats
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::SeqCst);
+        self.shared.wake_all();
     }
+}
 
-    fn find_least_loaded_worker(&self) -> Option<&Worker> {
-        self.workers.iter().min_by_key(|worker| {
-            worker
-                .local_queue
-                .try_lock()
-                .map(|queue| queue.len())
-                .unwrap_or(usize::MAX)
+impl Shared {
+    /// Queue a job on the deque of the current thread, if it is one of
+    /// this pool's workers with room to spare
+    fn push_local(&self, job: Job) -> std::result::Result<(), Job> {
+        LOCAL.with(|local| match &*local.borrow() {
+            Some(local) if local.pool == self.id && local.deque.len() < self.local_limit => {
+                local.deque.push(job);
+                Ok(())
+            }
+            _ => Err(job),
         })
     }
 
-    fn count_active_threads(&self) -> usize {
-        self.workers
-            .iter()
-            .filter(|worker| worker.is_active.load(Ordering::SeqCst))
-            .count()
+    /// Jobs waiting in the shared queues and the deques
+    fn queued(&self) -> usize {
+        self.priority.len()
+            + self.injector.len()
+            + self.low.len()
+            + self.stealers.iter().map(Stealer::len).sum::<usize>()
     }
 
-    fn scale_up_if_needed(&self) -> Result<()> {
-        let active_threads = self.count_active_threads();
-        let queue_size = self.global_queue.len();
-
-        // Scale up if queue is getting full and we haven't reached max threads
-        if queue_size > self.config.queue_size / 2 && self.workers.len() < self.config.max_threads {
-            // This is simplified - in reality we'd need to handle adding workers at runtime
-            // For now, we just log that we would scale up
-            eprintln!(
-                "Would scale up thread pool: queue_size={}, active_threads={}",
-                queue_size, active_threads
-            );
-        }
-
-        Ok(())
+    fn has_shared_work(&self) -> bool {
+        !self.priority.is_empty() || !self.injector.is_empty() || !self.low.is_empty()
     }
-}
 
-impl Worker {
-    fn new() -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            thread: None,
-            local_queue: Arc::new(Mutex::new(VecDeque::new())),
-            is_active: Arc::new(AtomicBool::new(false)),
-        }
+    fn wake_one(&self) {
+        // Taking the lock orders the wakeup after a worker's last look at
+        // the queues, so it cannot be missed
+        let _idle = self.idle.lock();
+        self.wake.notify_one();
     }
 
-    fn start(
-        &self,
-        global_queue: Arc<SegQueue<Job>>,
-        shutdown: Arc<AtomicBool>,
-        stats: Arc<RwLock<ThreadPoolStats>>,
-    ) -> Result<()> {
-        let local_queue = Arc::clone(&self.local_queue);
-        let is_active = Arc::clone(&self.is_active);
-        let worker_id = self.id;
-
-        let _handle = thread::Builder::new()
-            .name(format!("veyra-worker-{}", worker_id))
-            .spawn(move || {
-                Self::worker_loop(
-                    worker_id,
-                    global_queue,
-                    local_queue,
-                    shutdown,
-                    stats,
-                    is_active,
-                )
-            })?;
-
-        // Store handle (this is unsafe in the current design, but for demo purposes)
-        // In a real implementation, we'd use a different approach
-
-        Ok(())
+    fn wake_all(&self) {
+        let _idle = self.idle.lock();
+        self.wake.notify_all();
     }
 
-    async fn shutdown(&mut self) -> Result<()> {
-        if let Some(handle) = self.thread.take() {
-            let _ = handle.join();
-        }
-        Ok(())
+    /// The next job for worker `index`, whose `taken`th job it is: from
+    /// the high priority lane, then the normal lane, then the low one,
+    /// except every `FAIRNESS_INTERVAL`th job
+    fn find_job(&self, index: usize, deque: &Deque<Job>, taken: u64) -> Option<Job> {
+        let lanes = match taken % (2 * FAIRNESS_INTERVAL) {
+            n if n == FAIRNESS_INTERVAL - 1 => [Lane::Normal, Lane::Low, Lane::High],
+            n if n == 2 * FAIRNESS_INTERVAL - 1 => [Lane::Low, Lane::Normal, Lane::High],
+            _ => [Lane::High, Lane::Normal, Lane::Low],
+        };
+        lanes
+            .into_iter()
+            .find_map(|lane| self.take_from(lane, index, deque))
     }
 
-    fn worker_loop(
-        __worker_id: Uuid,
-        global_queue: Arc<SegQueue<Job>>,
-        local_queue: Arc<Mutex<VecDeque<Job>>>,
-        shutdown: Arc<AtomicBool>,
-        stats: Arc<RwLock<ThreadPoolStats>>,
-        is_active: Arc<AtomicBool>,
-    ) {
-        let mut idle_start = None;
-
-        while !shutdown.load(Ordering::SeqCst) {
-            // Try to get work from local queue first
-            let job = {
-                let mut queue = local_queue.lock();
-                queue.pop_front()
-            };
-
-            let job = job.or_else(|| {
-                // Try global queue
-                global_queue.pop()
-            });
-
-            if let Some(job) = job {
-                // Mark as active
-                is_active.store(true, Ordering::SeqCst);
-                idle_start = None;
-
-                // Execute job
-                let start_time = Instant::now();
-                job();
-                let execution_time = start_time.elapsed();
-
-                // Update stats
-                {
-                    let mut stats_guard = stats.write();
-                    stats_guard.jobs_executed += 1;
-
-                    // Update average execution time
-                    if stats_guard.jobs_executed > 0 {
-                        let total_time = stats_guard.average_execution_time
-                            * (stats_guard.jobs_executed - 1) as u32
-                            + execution_time;
-                        stats_guard.average_execution_time =
-                            total_time / stats_guard.jobs_executed as u32;
+    /// A job of the priority `lane` holds. Normal jobs are the worker's own
+    /// newest, a batch from the shared queue, or the oldest job of another
+    /// worker, in that order.
+    fn take_from(&self, lane: Lane, index: usize, deque: &Deque<Job>) -> Option<Job> {
+        match lane {
+            Lane::High => {
+                let job = take(|| self.priority.steal())?;
+                self.counters.prioritized.fetch_add(1, Ordering::Relaxed);
+                Some(job)
+            }
+            Lane::Low => take(|| self.low.steal()),
+            Lane::Normal => {
+                if let Some(job) = deque.pop() {
+                    return Some(job);
+                }
+                if let Some(job) = take(|| self.injector.steal_batch_and_pop(deque)) {
+                    return Some(job);
+                }
+                if !self.work_stealing {
+                    return None;
+                }
+                // Start with the next worker so thieves spread out
+                let workers = self.stealers.len();
+                let victims = (1..workers).map(|offset| &self.stealers[(index + offset) % workers]);
+                for victim in victims {
+                    if let Some(job) = take(|| victim.steal_batch_and_pop(deque)) {
+                        self.counters.stolen.fetch_add(1, Ordering::Relaxed);
+                        return Some(job);
                     }
                 }
+                None
+            }
+        }
+    }
 
-                // Mark as potentially idle
-                is_active.store(false, Ordering::SeqCst);
-            } else {
-                // No work available, enter idle state
-                if idle_start.is_none() {
-                    idle_start = Some(Instant::now());
-                }
+    fn run(&self, job: Job) {
+        let counters = &self.counters;
+        counters.active.fetch_add(1, Ordering::Relaxed);
+        let start = Instant::now();
+        // A panicking job must not take its worker down with it
+        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+        let elapsed = start.elapsed().as_nanos().try_into().unwrap_or(u64::MAX);
+        counters.busy_nanos.fetch_add(elapsed, Ordering::Relaxed);
+        counters.executed.fetch_add(1, Ordering::Relaxed);
+        counters.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
 
-                // Sleep briefly to avoid busy waiting
-                thread::sleep(Duration::from_millis(1));
+/// Retry a steal until it succeeds or finds nothing
+fn take(mut steal: impl FnMut() -> Steal<Job>) -> Option<Job> {
+    loop {
+        match steal() {
+            Steal::Success(job) => return Some(job),
+            Steal::Empty => return None,
+            Steal::Retry => {}
+        }
+    }
+}
 
-                // Check if we should exit due to prolonged idleness
-                if let Some(start) = idle_start {
-                    if start.elapsed() > Duration::from_secs(60) {
-                        // In a real implementation, we might reduce the thread pool size
-                        // For now, just continue
-                    }
+fn worker_loop(shared: Arc<Shared>, index: usize, deque: Deque<Job>) {
+    LOCAL.with(|local| {
+        *local.borrow_mut() = Some(Local {
+            pool: shared.id,
+            deque,
+        })
+    });
+
+    let mut taken = 0;
+    while !shared.shutdown.load(Ordering::SeqCst) {
+        let job = LOCAL.with(|local| {
+            let local = local.borrow();
+            let deque = &local.as_ref().expect("worker deque").deque;
+            shared.find_job(index, deque, taken)
+        });
+
+        match job {
+            Some(job) => {
+                taken += 1;
+                shared.run(job);
+            }
+            None => {
+                let mut idle = shared.idle.lock();
+                if !shared.has_shared_work() && !shared.shutdown.load(Ordering::SeqCst) {
+                    shared.wake.wait_for(&mut idle, IDLE_WAIT);
                 }
             }
         }
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum JobPriority {
-    High,
-    Normal,
-    Low,
+    LOCAL.with(|local| local.borrow_mut().take());
 }
 
 impl Default for ThreadPool {
@@ -0,0 +1,149 @@
+// Tests for generational collection, incremental marking and the pause
+// statistics of the garbage collector
+
+use std::sync::Arc;
+use veyra_runtime::{GarbageCollector, GcConfig, MemoryManager, ObjectRef, VeyraRuntime};
+
+/// A collector with three generations over a fresh heap, taking `mark_step`
+/// objects per step of a major collection
+fn collector(mark_step: usize) -> (Arc<MemoryManager>, GarbageCollector) {
+    let memory = Arc::new(MemoryManager::new());
+    let config = GcConfig {
+        generations: 3,
+        mark_step,
+        ..GcConfig::default()
+    };
+    let gc = GarbageCollector::with_config(Arc::clone(&memory), config);
+    (memory, gc)
+}
+
+async fn allocate(memory: &MemoryManager, generation: usize) -> ObjectRef {
+    memory.allocate_object(64, Some(generation)).await.unwrap()
+}
+
+/// Run a major collection to the end
+fn finish_major(gc: &GarbageCollector) -> usize {
+    loop {
+        if let Some(result) = gc.major_step() {
+            return result.objects;
+        }
+    }
+}
+
+#[tokio::test]
+async fn a_young_object_held_only_by_an_old_one_survives_a_minor_collection() {
+    let (memory, gc) = collector(10);
+    let old = allocate(&memory, 2).await;
+    let held = allocate(&memory, 0).await;
+    let garbage = allocate(&memory, 0).await;
+    memory.add_reference(old, held).await.unwrap();
+
+    let result = gc.collect_minor();
+    assert_eq!(result.objects, 1);
+    assert_eq!(memory.generation_of(&held), Some(1));
+    assert_eq!(memory.generation_of(&garbage), None);
+    // The old object is left to major collections
+    assert_eq!(memory.generation_of(&old), Some(2));
+}
+
+#[tokio::test]
+async fn a_reference_added_while_marking_is_kept() {
+    let (memory, gc) = collector(1);
+    let root = allocate(&memory, 2).await;
+    let middle = allocate(&memory, 2).await;
+    let moved = allocate(&memory, 2).await;
+    let garbage = allocate(&memory, 2).await;
+    memory.add_root(root);
+    memory.add_reference(root, middle).await.unwrap();
+    memory.add_reference(middle, moved).await.unwrap();
+
+    // The first step marks the root only, so moving the reference into it
+    // hides `moved` from the rest of the marking
+    assert!(gc.major_step().is_none());
+    assert!(gc.is_marking());
+    memory.add_reference(root, moved).await.unwrap();
+    memory.remove_reference(middle, moved).await.unwrap();
+
+    assert_eq!(finish_major(&gc), 1);
+    assert!(!gc.is_marking());
+    assert_eq!(memory.generation_of(&moved), Some(2));
+    assert_eq!(memory.generation_of(&garbage), None);
+}
+
+#[tokio::test]
+async fn survivors_move_up_one_generation_per_minor_collection() {
+    let (memory, gc) = collector(1);
+    let object = allocate(&memory, 0).await;
+    gc.add_root(object);
+
+    for generation in [1, 2, 2] {
+        gc.collect_minor();
+        assert_eq!(memory.generation_of(&object), Some(generation));
+    }
+    assert_eq!(memory.generation_bytes(0), 0);
+    assert_eq!(memory.generation_bytes(1), 0);
+    assert_eq!(memory.generation_bytes(2), 64);
+    assert_eq!(gc.get_stats().objects_promoted, 2);
+
+    // An object promoted into the oldest generation while a major
+    // collection is marking counts as marked, though the collection never
+    // saw it referenced
+    let filler = allocate(&memory, 2).await;
+    memory.add_reference(object, filler).await.unwrap();
+    assert!(gc.major_step().is_none());
+    let young = allocate(&memory, 1).await;
+    gc.add_root(young);
+    gc.collect_minor();
+    gc.remove_root(&young);
+    assert_eq!(memory.generation_of(&young), Some(2));
+    assert_eq!(finish_major(&gc), 0);
+    assert_eq!(memory.generation_of(&young), Some(2));
+}
+
+#[tokio::test]
+async fn pauses_are_recorded_in_histograms() {
+    let (memory, gc) = collector(1);
+    for _ in 0..3 {
+        let object = allocate(&memory, 2).await;
+        gc.add_root(object);
+    }
+    gc.collect_minor();
+    finish_major(&gc);
+
+    let stats = gc.get_stats();
+    assert_eq!((stats.minor_collections, stats.major_collections), (1, 1));
+    assert_eq!(stats.minor_pauses.count, 1);
+    // Two steps that leave objects to mark, then the one that finishes
+    assert_eq!(stats.major_pauses.count, 3);
+    for histogram in [&stats.minor_pauses, &stats.major_pauses] {
+        assert_eq!(histogram.buckets.iter().sum::<u64>(), histogram.count);
+        assert!(histogram.max <= histogram.total);
+        assert!(histogram.quantile(1.0) <= histogram.max);
+    }
+    let metrics = gc.export_metrics();
+    assert!(metrics.contains("veyra_gc_pause_seconds_count{kind=\"minor\"} 1\n"));
+    assert!(metrics.contains("veyra_gc_pause_seconds_count{kind=\"major\"} 3\n"));
+}
+
+#[tokio::test]
+async fn the_runtime_exports_collector_and_heap_metrics() {
+    let runtime = VeyraRuntime::new();
+    let object = runtime
+        .memory_manager
+        .allocate_object(128, None)
+        .await
+        .unwrap();
+    runtime
+        .memory_manager
+        .allocate_object(32, None)
+        .await
+        .unwrap();
+    runtime.garbage_collector.add_root(object);
+    runtime.garbage_collector.collect_minor();
+
+    let metrics = runtime.export_metrics();
+    assert!(metrics.contains("veyra_gc_collections_total{kind=\"minor\"} 1\n"));
+    assert!(metrics.contains("veyra_gc_collected_bytes_total 32\n"));
+    assert!(metrics.contains("veyra_heap_bytes 128\n"));
+    assert!(metrics.contains("veyra_heap_objects 1\n"));
+}
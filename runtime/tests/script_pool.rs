@@ -0,0 +1,102 @@
+// Tests for evaluating scripts on the thread pool: their results and output,
+// errors, limits, dropped handles and the sandbox scripts run in
+
+use std::sync::Arc;
+use std::time::Duration;
+use veyra_compiler::sandbox::Policy;
+use veyra_compiler::{Limits, VeyraError};
+use veyra_runtime::{ScriptPool, ThreadPool, ThreadPoolConfig};
+
+async fn scripts() -> ScriptPool {
+    let pool = ThreadPool::with_config(ThreadPoolConfig {
+        threads: 2,
+        ..Default::default()
+    });
+    pool.start().await.unwrap();
+    ScriptPool::new(Arc::new(pool))
+}
+
+#[tokio::test]
+async fn results_come_back_in_order_with_their_output() {
+    let scripts = scripts().await;
+    let sources: Vec<String> = (0..8)
+        .map(|i| format!("let n = {}\nprint(n * n)\n", i))
+        .collect();
+    let results = scripts.run_all(sources).await.unwrap();
+    let outputs: Vec<String> = results
+        .into_iter()
+        .map(|result| result.unwrap().output)
+        .collect();
+    let expected: Vec<String> = (0..8).map(|i| format!("{}\n", i * i)).collect();
+    assert_eq!(outputs, expected);
+}
+
+#[tokio::test]
+async fn parse_and_runtime_errors_are_results() {
+    let scripts = scripts().await;
+    let results = scripts
+        .run_all(["print(1 +\n", "print(missing)\n", "print(\"fine\")\n"])
+        .await
+        .unwrap();
+    assert!(matches!(results[0], Err(VeyraError::ParseError { .. })));
+    let error = results[1].as_ref().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Runtime Error: Undefined variable 'missing'"
+    );
+    assert_eq!(results[2].as_ref().unwrap().output, "fine\n");
+}
+
+#[tokio::test]
+async fn limits_stop_endless_loops() {
+    let forever = "let mut n = 0\nwhile true {\n    n += 1\n}\n";
+
+    let steps = scripts().await.with_limits(Limits {
+        max_steps: Some(10_000),
+        ..Limits::default()
+    });
+    let error = steps.evaluate(forever).unwrap().await.unwrap_err();
+    assert!(error.to_string().contains("Step limit"), "{}", error);
+
+    let time = scripts().await.with_limits(Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    });
+    let error = time.evaluate(forever).unwrap().await.unwrap_err();
+    assert!(error.to_string().contains("Time limit"), "{}", error);
+}
+
+#[tokio::test]
+async fn dropped_handles_leave_other_scripts_running() {
+    let scripts = scripts().await;
+    for _ in 0..4 {
+        drop(scripts.evaluate("print(1)\n").unwrap());
+    }
+    let result = scripts.evaluate("print(2)\n").unwrap().await;
+    assert_eq!(result.unwrap().output, "2\n");
+}
+
+#[tokio::test]
+async fn scripts_run_in_the_sandbox_unless_given_a_policy() {
+    let source = "print(exec(\"echo\", [\"hello\"]))\n";
+    let error = scripts().await.evaluate(source).unwrap().await.unwrap_err();
+    assert!(error.to_string().contains("Permission denied"), "{}", error);
+
+    let error = scripts()
+        .await
+        .evaluate("import helpers\n")
+        .unwrap()
+        .await
+        .unwrap_err();
+    assert!(
+        error.to_string().contains("importing modules is disabled"),
+        "{}",
+        error
+    );
+
+    let allowed = scripts()
+        .await
+        .with_policy(Policy::deny_all().allow_process("echo"));
+    let output = allowed.evaluate(source).unwrap().await;
+    assert!(output.unwrap().output.contains("hello"));
+}
@@ -0,0 +1,119 @@
+// Tests for the order the thread pool runs jobs in, its shutdown, jobs that
+// panic, and lower priority jobs under a stream of higher priority ones
+
+use crossbeam::channel::{self, Receiver};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use veyra_runtime::{JobPriority, ThreadPool, ThreadPoolConfig};
+
+fn pool(threads: usize) -> ThreadPool {
+    ThreadPool::with_config(ThreadPoolConfig {
+        threads,
+        ..Default::default()
+    })
+}
+
+/// Queue a job that reports `label` when it runs
+fn queue(pool: &ThreadPool, label: &'static str, priority: JobPriority) -> Receiver<&'static str> {
+    let (sender, receiver) = channel::unbounded();
+    pool.execute_with_priority(move || sender.send(label).unwrap(), priority)
+        .unwrap();
+    receiver
+}
+
+#[tokio::test]
+async fn runs_higher_priority_jobs_first() {
+    let pool = pool(1);
+    let (ran, order) = channel::unbounded();
+    for (label, priority) in [
+        ("low", JobPriority::Low),
+        ("normal", JobPriority::Normal),
+        ("high", JobPriority::High),
+        ("normal", JobPriority::Normal),
+        ("high", JobPriority::High),
+        ("low", JobPriority::Low),
+    ] {
+        let ran = ran.clone();
+        pool.execute_with_priority(move || ran.send(label).unwrap(), priority)
+            .unwrap();
+    }
+
+    // Jobs queued before the pool starts wait for it
+    thread::sleep(Duration::from_millis(20));
+    assert!(order.is_empty());
+    pool.start().await.unwrap();
+    let order: Vec<_> = (0..6)
+        .map(|_| order.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect();
+    assert_eq!(order, ["high", "high", "normal", "normal", "low", "low"]);
+    assert_eq!(pool.get_stats().jobs_prioritized, 2);
+    pool.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_running_jobs_and_refuses_new_ones() {
+    let pool = pool(2);
+    pool.start().await.unwrap();
+    let finished = Arc::new(AtomicBool::new(false));
+    let (started, running) = channel::bounded(1);
+    let done = Arc::clone(&finished);
+    pool.execute(move || {
+        started.send(()).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        done.store(true, Ordering::SeqCst);
+    })
+    .unwrap();
+    running.recv_timeout(Duration::from_secs(5)).unwrap();
+
+    pool.shutdown().await.unwrap();
+    assert!(finished.load(Ordering::SeqCst));
+    assert!(pool.execute(|| {}).is_err());
+    assert_eq!(pool.get_stats().active_threads, 0);
+}
+
+#[tokio::test]
+async fn a_panicking_job_does_not_stop_its_worker() {
+    let pool = pool(1);
+    pool.start().await.unwrap();
+    pool.execute(|| panic!("job failed")).unwrap();
+    let after = queue(&pool, "after", JobPriority::Normal);
+    assert_eq!(after.recv_timeout(Duration::from_secs(5)), Ok("after"));
+    pool.shutdown().await.unwrap();
+    assert_eq!(pool.get_stats().jobs_executed, 2);
+}
+
+/// A high priority job that queues another like it until `stop` is set
+fn requeue(pool: Arc<ThreadPool>, stop: Arc<AtomicBool>, count: Arc<AtomicUsize>) {
+    if stop.load(Ordering::SeqCst) {
+        return;
+    }
+    count.fetch_add(1, Ordering::SeqCst);
+    let next = Arc::clone(&pool);
+    let _ = pool.execute_with_priority(move || requeue(next, stop, count), JobPriority::High);
+}
+
+#[tokio::test]
+async fn lower_priority_jobs_run_under_a_stream_of_high_priority_ones() {
+    let pool = Arc::new(pool(1));
+    pool.start().await.unwrap();
+
+    // Each high priority job queues the next, so the high lane never empties
+    let stop = Arc::new(AtomicBool::new(false));
+    let high_jobs = Arc::new(AtomicUsize::new(0));
+    for _ in 0..4 {
+        let (pool, stop, count) = (Arc::clone(&pool), Arc::clone(&stop), Arc::clone(&high_jobs));
+        pool.clone()
+            .execute_with_priority(move || requeue(pool, stop, count), JobPriority::High)
+            .unwrap();
+    }
+
+    let normal = queue(&pool, "normal", JobPriority::Normal);
+    let low = queue(&pool, "low", JobPriority::Low);
+    assert_eq!(normal.recv_timeout(Duration::from_secs(5)), Ok("normal"));
+    assert_eq!(low.recv_timeout(Duration::from_secs(5)), Ok("low"));
+    stop.store(true, Ordering::SeqCst);
+    assert!(high_jobs.load(Ordering::SeqCst) > 0);
+    pool.shutdown().await.unwrap();
+}
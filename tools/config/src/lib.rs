@@ -0,0 +1,148 @@
+// Project configuration shared by the Veyra tools
+// Locates the project root (the nearest veyra.toml) and parses the
+// [fmt], [lint], [test] and [repl] sections so every tool agrees on them.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub use veyra_compiler::modules::{find_project_root, PROJECT_FILE};
+
+/// Tool sections of veyra.toml. Unset options are `None` so each tool can
+/// fall back to its own defaults and let command-line flags win.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub fmt: FmtConfig,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub lint: LintConfig,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub test: TestConfig,
+
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub repl: ReplConfig,
+}
+
+/// `[fmt]` section used by veyra-fmt and the language server
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FmtConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indent_size: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_line_length: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub use_tabs: Option<bool>,
+
+    /// "always" | "never" | "preserve": whether lists broken over several
+    /// lines end with a comma
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trailing_commas: Option<String>,
+
+    /// Line up the types and values of struct fields written one per line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align_fields: Option<bool>,
+}
+
+/// `[lint]` section used by veyra-lint
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LintConfig {
+    /// rule_name -> "error" | "warning" | "info" | "off"
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rules: HashMap<String, String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings_as_errors: Option<bool>,
+
+    /// Functions the taint lints trust to make program input safe
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sanitizers: Vec<String>,
+}
+
+/// `[test]` section used by `veyra-pkg test`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestConfig {
+    /// Directory containing test files, relative to the project root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+
+    /// Default test filter when none is given on the command line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+}
+
+/// `[repl]` section, applied on top of the user's REPL configuration
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReplConfig {
+    /// Startup script, relative to the project root
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup_script: Option<PathBuf>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syntax_highlighting: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_tips: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_timing: Option<bool>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// A discovered project: its root directory and tool configuration
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub root: PathBuf,
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    /// Find the project containing `start` and load its configuration.
+    /// Returns `Ok(None)` when there is no veyra.toml above `start`.
+    pub fn discover(start: &Path) -> Result<Option<Self>> {
+        match find_project_root(start) {
+            Some(root) => {
+                let config = ProjectConfig::load(&root)?;
+                Ok(Some(Self { root, config }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Discover the project containing the current directory
+    pub fn discover_from_cwd() -> Result<Option<Self>> {
+        Self::discover(&std::env::current_dir()?)
+    }
+
+    /// Resolve a path from the configuration against the project root
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl ProjectConfig {
+    /// Parse the tool sections of `<root>/veyra.toml`
+    pub fn load(root: &Path) -> Result<Self> {
+        let project_file = root.join(PROJECT_FILE);
+        let content = fs::read_to_string(&project_file)
+            .map_err(|e| anyhow!("Failed to read {}: {}", project_file.display(), e))?;
+        Self::from_toml(&content).map_err(|e| anyhow!("Invalid {}: {}", project_file.display(), e))
+    }
+
+    /// Parse the tool sections from veyra.toml contents
+    pub fn from_toml(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+}
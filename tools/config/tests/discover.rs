@@ -0,0 +1,70 @@
+// Tests for finding veyra.toml and loading its sections
+
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+use veyra_config::{find_project_root, Project, PROJECT_FILE};
+
+/// A temporary directory, by its canonical path so it compares equal to
+/// discovered roots
+fn scratch() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().canonicalize().unwrap();
+    (dir, path)
+}
+
+#[test]
+fn the_nearest_project_file_above_a_nested_directory_is_used() {
+    let (_scratch, root) = scratch();
+    fs::write(
+        root.join(PROJECT_FILE),
+        "[fmt]\nindent-size = 2\n\n[lint.rules]\nunused-variable = \"off\"\n",
+    )
+    .unwrap();
+    let nested = root.join("src/shapes");
+    fs::create_dir_all(&nested).unwrap();
+    let file = nested.join("circle.vey");
+    fs::write(&file, "let r = 1\n").unwrap();
+
+    for start in [&nested, &file] {
+        let project = Project::discover(start).unwrap().unwrap();
+        assert_eq!(project.root, root);
+        assert_eq!(project.config.fmt.indent_size, Some(2));
+        assert_eq!(project.config.fmt.use_tabs, None);
+        assert_eq!(project.config.lint.rules["unused-variable"], "off");
+    }
+
+    // A package with its own veyra.toml is a project of its own
+    fs::write(nested.join(PROJECT_FILE), "").unwrap();
+    let project = Project::discover(&file).unwrap().unwrap();
+    assert_eq!(project.root, nested);
+    assert_eq!(project.config.fmt.indent_size, None);
+}
+
+#[test]
+fn a_directory_without_a_project_file_has_no_project() {
+    let (_scratch, root) = scratch();
+    let nested = root.join("a/b");
+    fs::create_dir_all(&nested).unwrap();
+    assert_eq!(find_project_root(&nested), None);
+    assert!(Project::discover(&nested).unwrap().is_none());
+
+    // A directory named veyra.toml is not a project file
+    fs::create_dir(root.join(PROJECT_FILE)).unwrap();
+    assert!(Project::discover(&nested).unwrap().is_none());
+}
+
+#[test]
+fn a_malformed_project_file_is_an_error_naming_it() {
+    let (_scratch, root) = scratch();
+    let project_file = root.join(PROJECT_FILE);
+    for content in ["[fmt\nindent-size = 2\n", "[fmt]\nindent-size = \"two\"\n"] {
+        fs::write(&project_file, content).unwrap();
+        let error = Project::discover(&root).unwrap_err().to_string();
+        assert!(
+            error.starts_with(&format!("Invalid {}", project_file.display())),
+            "{}",
+            error
+        );
+    }
+}
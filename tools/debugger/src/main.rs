@@ -4,15 +4,22 @@ use colored::*;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // Import from the main compiler
 use veyra_compiler::{
-    ast::*, interpreter::Interpreter, lexer::Lexer, parser::Parser as VeyraParser,
+    ast::*,
+    error::VeyraError,
+    interpreter::Interpreter,
+    lexer::{Lexer, TokenClass},
+    parser::Parser as VeyraParser,
 };
 
+/// Breakpoint file kept in the project root
+const BREAKPOINTS_FILE: &str = ".veyra-dbg.json";
+
 #[derive(Parser)]
 #[command(name = "veyra-dbg")]
 #[command(about = "Debugger for the Veyra programming language")]
@@ -38,6 +45,111 @@ struct Breakpoint {
     enabled: bool,
 }
 
+/// Breakpoints saved per source file, keyed by path relative to the project root
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BreakpointStore {
+    files: BTreeMap<String, Vec<Breakpoint>>,
+}
+
+impl BreakpointStore {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Invalid breakpoint file {}: {}", path.display(), e))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n")?;
+        Ok(())
+    }
+}
+
+/// Where breakpoints for a debugged file are persisted
+struct BreakpointLocation {
+    store_path: PathBuf,
+    key: String,
+}
+
+impl BreakpointLocation {
+    fn for_file(file: &Path) -> Self {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let dir = file.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let root = veyra_config::find_project_root(&dir).unwrap_or(dir);
+        let key = file
+            .strip_prefix(&root)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        Self {
+            store_path: root.join(BREAKPOINTS_FILE),
+            key,
+        }
+    }
+}
+
+fn paint(class: TokenClass, text: &str) -> ColoredString {
+    match class {
+        TokenClass::Boolean | TokenClass::Constant => text.yellow(),
+        TokenClass::Number => text.magenta(),
+        TokenClass::Text => text.green(),
+        TokenClass::Comment => text.bright_black().italic(),
+        TokenClass::Attribute => text.purple(),
+        TokenClass::Call => text.blue(),
+        // Every other class is a keyword
+        _ => text.cyan().bold(),
+    }
+}
+
+/// Colorize source code with the compiler lexer, returning one string per line.
+/// Text after a lexical error is left plain.
+fn highlight_source(source: &str) -> Vec<String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut classes: Vec<Option<TokenClass>> = vec![None; chars.len()];
+
+    let (tokens, _) = Lexer::new(source).tokenize_with_ranges();
+    for (i, (token, range)) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1).map(|(t, _)| &t.kind);
+        if let Some(class) = TokenClass::of(&token.kind, next) {
+            for slot in &mut classes[range.start.min(chars.len())..range.end.min(chars.len())] {
+                *slot = Some(class);
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\n' {
+            lines.push(std::mem::take(&mut line));
+            i += 1;
+            continue;
+        }
+
+        // Paint runs of characters that share a class
+        let class = classes[i];
+        let start = i;
+        while i < chars.len() && chars[i] != '\n' && classes[i] == class {
+            i += 1;
+        }
+        let text: String = chars[start..i].iter().filter(|&&c| c != '\r').collect();
+        match class {
+            Some(class) => line.push_str(&paint(class, &text).to_string()),
+            None => line.push_str(&text),
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
 #[derive(Debug, Clone)]
 enum DebugCommand {
     Run,
@@ -51,6 +163,7 @@ enum DebugCommand {
     Print(String), // Print variable
     Backtrace,     // Show call stack
     Variables,     // Show all variables
+    Trace,         // Show values recorded by trace()
     Help,
     Quit,
 }
@@ -58,8 +171,9 @@ enum DebugCommand {
 struct DebuggerState {
     _source_code: String,
     source_lines: Vec<String>,
-    _ast: Program,
-    _interpreter: Interpreter,
+    highlighted_lines: Vec<String>,
+    ast: Program,
+    interpreter: Interpreter,
     breakpoints: HashMap<usize, Breakpoint>,
     next_breakpoint_id: usize,
     current_line: usize,
@@ -80,12 +194,14 @@ enum StepMode {
 impl DebuggerState {
     fn new(source_code: String, ast: Program) -> Self {
         let source_lines: Vec<String> = source_code.lines().map(|s| s.to_string()).collect();
+        let highlighted_lines = highlight_source(&source_code);
 
         Self {
             _source_code: source_code,
             source_lines,
-            _ast: ast,
-            _interpreter: Interpreter::new(),
+            highlighted_lines,
+            ast,
+            interpreter: Interpreter::new(),
             breakpoints: HashMap::new(),
             next_breakpoint_id: 1,
             current_line: 1,
@@ -115,6 +231,27 @@ impl DebuggerState {
         self.breakpoints.remove(&id).is_some()
     }
 
+    /// Restore breakpoints saved for this file, skipping lines that no longer exist
+    fn restore_breakpoints(&mut self, saved: Vec<Breakpoint>) -> usize {
+        let mut restored = 0;
+        for breakpoint in saved {
+            if breakpoint.line == 0 || breakpoint.line > self.source_lines.len() {
+                continue;
+            }
+            self.next_breakpoint_id = self.next_breakpoint_id.max(breakpoint.id + 1);
+            self.breakpoints.insert(breakpoint.id, breakpoint);
+            restored += 1;
+        }
+        restored
+    }
+
+    /// Breakpoints ordered by ID, as saved to the breakpoint file
+    fn sorted_breakpoints(&self) -> Vec<Breakpoint> {
+        let mut breakpoints: Vec<Breakpoint> = self.breakpoints.values().cloned().collect();
+        breakpoints.sort_by_key(|bp| bp.id);
+        breakpoints
+    }
+
     fn _should_break_at_line(&self, line: usize) -> bool {
         for breakpoint in self.breakpoints.values() {
             if breakpoint.enabled && breakpoint.line == line {
@@ -137,18 +274,20 @@ impl DebuggerState {
 
             if line_num >= start && line_num <= end {
                 let marker = if line_num == self.current_line {
-                    " -> "
+                    " -> ".green().bold()
                 } else if self
                     .breakpoints
                     .values()
                     .any(|bp| bp.line == line_num && bp.enabled)
                 {
-                    " *  "
+                    " *  ".red().bold()
                 } else {
-                    "    "
+                    "    ".normal()
                 };
 
-                result.push(format!("{}{:3}: {}", marker, line_num, line));
+                let text = self.highlighted_lines.get(i).unwrap_or(line);
+                let number = format!("{:3}:", line_num);
+                result.push(format!("{}{} {}", marker, number.dimmed(), text));
             }
         }
 
@@ -169,19 +308,63 @@ impl DebuggerState {
 
 struct Debugger {
     state: DebuggerState,
+    breakpoint_location: BreakpointLocation,
     _verbose: bool,
 }
 
 impl Debugger {
-    fn new(source_code: String, ast: Program, verbose: bool) -> Self {
+    fn new(source_code: String, ast: Program, file: &Path, verbose: bool) -> Self {
         let state = DebuggerState::new(source_code, ast);
 
         Self {
             state,
+            breakpoint_location: BreakpointLocation::for_file(file),
             _verbose: verbose,
         }
     }
 
+    /// Load breakpoints saved by an earlier session on the same file
+    fn load_breakpoints(&mut self) -> Result<()> {
+        let mut store = BreakpointStore::load(&self.breakpoint_location.store_path)?;
+        if let Some(saved) = store.files.remove(&self.breakpoint_location.key) {
+            let restored = self.state.restore_breakpoints(saved);
+            if restored > 0 {
+                println!(
+                    "{} Restored {} breakpoint(s) from {}",
+                    "✓".green().bold(),
+                    restored,
+                    BREAKPOINTS_FILE
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Write this file's breakpoints back, keeping entries for other files
+    fn save_breakpoints(&self) -> Result<()> {
+        let path = &self.breakpoint_location.store_path;
+        let mut store = BreakpointStore::load(path)?;
+        let breakpoints = self.state.sorted_breakpoints();
+
+        if breakpoints.is_empty() {
+            if store.files.remove(&self.breakpoint_location.key).is_none() {
+                return Ok(());
+            }
+        } else {
+            store
+                .files
+                .insert(self.breakpoint_location.key.clone(), breakpoints);
+        }
+
+        if store.files.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        store.save(path)
+    }
+
     fn parse_command(&self, input: &str) -> Result<DebugCommand> {
         let parts: Vec<&str> = input.split_whitespace().collect();
 
@@ -222,6 +405,7 @@ impl Debugger {
             }
             "bt" | "backtrace" => Ok(DebugCommand::Backtrace),
             "vars" | "variables" => Ok(DebugCommand::Variables),
+            "tr" | "trace" => Ok(DebugCommand::Trace),
             "h" | "help" => Ok(DebugCommand::Help),
             "q" | "quit" => Ok(DebugCommand::Quit),
             _ => Err(anyhow!("Unknown command: {}", parts[0])),
@@ -276,10 +460,12 @@ impl Debugger {
                     id,
                     line
                 );
+                self.save_breakpoints()?;
             }
             DebugCommand::Delete(id) => {
                 if self.state.delete_breakpoint(id) {
                     println!("{} Breakpoint {} deleted", "✓".green().bold(), id);
+                    self.save_breakpoints()?;
                 } else {
                     println!("{} Breakpoint {} not found", "!".yellow().bold(), id);
                 }
@@ -318,6 +504,22 @@ impl Debugger {
                     }
                 }
             }
+            DebugCommand::Trace => {
+                let entries: Vec<_> = self.state.interpreter.traces().collect();
+                if entries.is_empty() {
+                    println!("No values traced yet");
+                } else {
+                    println!("{}", "Trace:".bold());
+                    for entry in entries {
+                        println!(
+                            "  {} {} = {}",
+                            format!("[{:>9.3}ms]", entry.elapsed.as_secs_f64() * 1000.0).dimmed(),
+                            entry.expression.cyan().bold(),
+                            entry.value.repr()
+                        );
+                    }
+                }
+            }
             DebugCommand::Help => {
                 self.print_help();
             }
@@ -354,7 +556,15 @@ impl Debugger {
                 println!("{}", line);
             }
         } else {
-            println!("{} Program finished", "✓".green().bold());
+            // Nothing to stop at, so run the program through
+            self.state.interpreter = Interpreter::new();
+            match self.state.interpreter.interpret(&self.state.ast) {
+                Ok(_) => println!("{} Program finished", "✓".green().bold()),
+                Err(VeyraError::Exit(code)) => {
+                    println!("{} Program exited with code {}", "✓".green().bold(), code)
+                }
+                Err(e) => println!("{} Program failed: {}", "✗".red().bold(), e),
+            }
             self.state.is_running = false;
         }
 
@@ -441,6 +651,11 @@ impl Debugger {
             "vars, variables".green(),
             "".dimmed()
         );
+        println!(
+            "  {} {} - Show values recorded by trace()",
+            "tr, trace".green(),
+            "".dimmed()
+        );
         println!();
         println!("{}", "Other:".yellow().bold());
         println!("  {} {} - Show this help", "h, help".green(), "".dimmed());
@@ -560,7 +775,10 @@ fn main() -> Result<()> {
     );
 
     // Create debugger
-    let mut debugger = Debugger::new(source_code, ast, cli.verbose);
+    let mut debugger = Debugger::new(source_code, ast, &cli.file, cli.verbose);
+    if let Err(e) = debugger.load_breakpoints() {
+        eprintln!("{} {}", "!".yellow().bold(), e);
+    }
 
     if cli.run {
         // Start debugging immediately
@@ -0,0 +1,24 @@
+// Fuzz the formatter with arbitrary source text
+//
+// Any input the parser accepts must format without error, to code that
+// parses to the same program (`format_source` checks this itself), and
+// formatting that output again must not change it.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use veyra_fmt::{format_source, parse, FormatterConfig};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    if parse(source).is_err() {
+        return;
+    }
+
+    let config = FormatterConfig::default();
+    let formatted = format_source(source, &config).unwrap();
+    let again = format_source(&formatted, &config).unwrap();
+    assert_eq!(formatted, again, "formatting is not idempotent");
+});
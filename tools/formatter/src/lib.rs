@@ -0,0 +1,84 @@
+// Formatting of Veyra source code
+//
+// `Formatter`, from the compiler's printer, writes a parsed program back out
+// in the canonical layout, keeping how the source spelled its literals.
+// Formatting only changes layout: `format_source` refuses output that parses
+// to a different program than its input, so a formatter bug cannot silently
+// change what the code does.
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use veyra_compiler::{ast::*, lexer::Lexer, parser::Parser as VeyraParser};
+
+pub use veyra_compiler::printer::{Config as FormatterConfig, Formatter, TrailingCommas};
+
+/// Parse source code into a program
+pub fn parse(source: &str) -> Result<Program> {
+    let tokens = Lexer::new(source)
+        .tokenize_with_comments()
+        .map_err(|e| anyhow!("Syntax error: {}", e))?;
+    VeyraParser::new(tokens)
+        .parse()
+        .map_err(|e| anyhow!("Parse error: {}", e))
+}
+
+/// Formatter settings from the `[fmt]` section of veyra.toml, with the
+/// defaults for the options it leaves unset
+pub fn project_config(fmt: &veyra_config::FmtConfig) -> Result<FormatterConfig> {
+    let defaults = FormatterConfig::default();
+    Ok(FormatterConfig {
+        indent_size: fmt.indent_size.unwrap_or(defaults.indent_size),
+        max_line_length: fmt.max_line_length.unwrap_or(defaults.max_line_length),
+        use_spaces: !fmt.use_tabs.unwrap_or(!defaults.use_spaces),
+        trailing_commas: match &fmt.trailing_commas {
+            Some(policy) => TrailingCommas::from_str(policy, true)
+                .map_err(|e| anyhow!("Invalid [fmt] trailing-commas in veyra.toml: {}", e))?,
+            None => defaults.trailing_commas,
+        },
+        align_fields: fmt.align_fields.unwrap_or(defaults.align_fields),
+    })
+}
+
+/// Format source code, checking that the result means the same program
+pub fn format_source(source: &str, config: &FormatterConfig) -> Result<String> {
+    let program = parse(source)?;
+    let formatted = Formatter::new(config.clone())
+        .with_spellings_from(source)
+        .format_program(&program);
+
+    let reparsed = parse(&formatted).map_err(|e| {
+        anyhow!(
+            "Formatted code does not parse ({}); this is a formatter bug",
+            e
+        )
+    })?;
+    if !same_program(&program, &reparsed) {
+        return Err(anyhow!(
+            "Formatting would change the meaning of the program; this is a formatter bug"
+        ));
+    }
+    Ok(formatted)
+}
+
+/// Whether two programs are the same apart from layout, such as where the
+/// source had trailing commas or the lines statements start on
+pub fn same_program(a: &Program, b: &Program) -> bool {
+    fn without_layout(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(fields) => {
+                fields.remove("trailing_comma");
+                fields.remove("lines");
+                fields.values_mut().for_each(without_layout);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(without_layout),
+            _ => {}
+        }
+    }
+
+    let (Ok(mut a), Ok(mut b)) = (serde_json::to_value(a), serde_json::to_value(b)) else {
+        return false;
+    };
+    without_layout(&mut a);
+    without_layout(&mut b);
+    a == b
+}
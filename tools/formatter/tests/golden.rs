@@ -0,0 +1,84 @@
+// Golden-file tests for veyra-fmt
+//
+// Each directory under tests/golden holds a case: `input.vey`, the
+// `expected.vey` it formats to, and a veyra.toml with the [fmt] options to
+// use. Run with UPDATE_GOLDEN=1 to rewrite the expected files after an
+// intended change in output.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn format(case: &Path, source: &str) -> String {
+    let scratch = tempfile::tempdir().unwrap();
+    let file = scratch.path().join("input.vey");
+    fs::write(&file, source).unwrap();
+
+    // The case directory is the working directory, so its veyra.toml applies
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-fmt"))
+        .arg("--write")
+        .arg(&file)
+        .current_dir(case)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success() && output.stderr.is_empty(),
+        "veyra-fmt failed on {}: {}",
+        case.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    fs::read_to_string(&file).unwrap()
+}
+
+fn cases() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut cases: Vec<PathBuf> = fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    cases
+}
+
+#[test]
+fn golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for case in cases() {
+        let input = fs::read_to_string(case.join("input.vey")).unwrap();
+        let formatted = format(&case, &input);
+        let expected_path = case.join("expected.vey");
+
+        if update {
+            fs::write(&expected_path, &formatted).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if formatted != expected {
+            failures.push(format!(
+                "{}:\n--- expected\n{}\n--- formatted\n{}",
+                case.display(),
+                expected,
+                formatted
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    for case in cases() {
+        let expected = fs::read_to_string(case.join("expected.vey")).unwrap();
+        assert_eq!(
+            format(&case, &expected),
+            expected,
+            "formatting {} again changed it",
+            case.display()
+        );
+    }
+}
@@ -0,0 +1,26 @@
+// Tests for formatting with the [fmt] section of veyra.toml
+
+use veyra_config::ProjectConfig;
+use veyra_fmt::{format_source, project_config, TrailingCommas};
+
+#[test]
+fn the_fmt_section_overrides_the_defaults() {
+    let config =
+        ProjectConfig::from_toml("[fmt]\nuse-tabs = true\ntrailing-commas = \"never\"\n").unwrap();
+    let formatter = project_config(&config.fmt).unwrap();
+    assert!(!formatter.use_spaces);
+    assert_eq!(formatter.trailing_commas, TrailingCommas::Never);
+    assert_eq!(formatter.indent_size, 4);
+    assert_eq!(
+        format_source("fn f() {\n  return 1\n}\n", &formatter).unwrap(),
+        "fn f() {\n\treturn 1\n}\n"
+    );
+
+    let config = ProjectConfig::from_toml("[fmt]\ntrailing-commas = \"sometimes\"\n").unwrap();
+    let error = project_config(&config.fmt).unwrap_err().to_string();
+    assert!(
+        error.starts_with("Invalid [fmt] trailing-commas in veyra.toml"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,344 @@
+// Round-trip tests for veyra-fmt
+//
+// Formatting must only change layout: the formatted code parses to the same
+// program as the original and formatting it again changes nothing. This is
+// checked over every .vey file in the repository under several formatter
+// configurations, and over randomly generated expressions, which exercise
+// operator precedence, escaping and line breaking more thoroughly than
+// hand-written code does.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use veyra_compiler::ast::*;
+use veyra_fmt::{format_source, parse, same_program, Formatter, FormatterConfig, TrailingCommas};
+
+fn configs() -> Vec<FormatterConfig> {
+    let default = FormatterConfig::default();
+    vec![
+        default.clone(),
+        FormatterConfig {
+            max_line_length: 30,
+            trailing_commas: TrailingCommas::Never,
+            align_fields: true,
+            ..default.clone()
+        },
+        FormatterConfig {
+            max_line_length: 50,
+            use_spaces: false,
+            trailing_commas: TrailingCommas::Preserve,
+            ..default
+        },
+    ]
+}
+
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            source_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "vey") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn repository_sources_round_trip() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut files = Vec::new();
+    source_files(&manifest.join("tests/golden"), &mut files);
+    for dir in ["examples", "stdlib", "tests"] {
+        source_files(&manifest.join("../..").join(dir), &mut files);
+    }
+    assert!(!files.is_empty());
+
+    let mut checked = 0;
+    for file in files {
+        let source = fs::read_to_string(&file).unwrap();
+        // Files the parser does not accept yet have nothing to format
+        if parse(&source).is_err() {
+            continue;
+        }
+        for config in configs() {
+            let formatted = format_source(&source, &config)
+                .unwrap_or_else(|e| panic!("{}: {}", file.display(), e));
+            let again = format_source(&formatted, &config).unwrap();
+            assert_eq!(
+                again,
+                formatted,
+                "formatting {} is not idempotent",
+                file.display()
+            );
+        }
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+/// A small deterministic random number generator (xorshift), so failures
+/// reproduce from the printed seed
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const NAMES: &[&str] = &["a", "b", "total", "items", "user_name", "x"];
+const TEXT: &[char] = &[
+    'a', 'Z', ' ', '"', '\'', '\\', '\n', '\t', 'é', '✓', '\u{1}', '{', '}',
+];
+
+fn text(rng: &mut Rng) -> String {
+    (0..rng.below(6)).map(|_| *rng.pick(TEXT)).collect()
+}
+
+fn literal(rng: &mut Rng) -> Literal {
+    match rng.below(7) {
+        0 => Literal::Integer(rng.below(1_000_000) as i64),
+        1 => Literal::Float(rng.below(400) as f64 / 8.0),
+        2 => Literal::String(text(rng)),
+        3 => Literal::Char(*rng.pick(TEXT)),
+        4 => Literal::Boolean(rng.below(2) == 0),
+        5 => Literal::None,
+        _ => Literal::Integer(rng.below(10) as i64),
+    }
+}
+
+fn pattern(rng: &mut Rng) -> Pattern {
+    match rng.below(4) {
+        0 => Pattern::Wildcard,
+        1 => Pattern::Identifier(rng.pick(NAMES).to_string()),
+        2 => Pattern::Tuple(vec![pattern(rng), pattern(rng)]),
+        _ => Pattern::Literal(Literal::Integer(rng.below(100) as i64)),
+    }
+}
+
+fn expressions(rng: &mut Rng, depth: usize, min: usize) -> Vec<Expression> {
+    (0..min + rng.below(4))
+        .map(|_| expression(rng, depth))
+        .collect()
+}
+
+fn expression(rng: &mut Rng, depth: usize) -> Expression {
+    if depth == 0 {
+        return match rng.below(2) {
+            0 => Expression::Literal(literal(rng)),
+            _ => Expression::Identifier(rng.pick(NAMES).to_string()),
+        };
+    }
+    let depth = depth - 1;
+    let boxed = |rng: &mut Rng| Box::new(expression(rng, depth));
+
+    match rng.below(20) {
+        0 | 1 => {
+            let operators = [
+                BinaryOperator::Add,
+                BinaryOperator::Subtract,
+                BinaryOperator::Multiply,
+                BinaryOperator::Divide,
+                BinaryOperator::FloorDivide,
+                BinaryOperator::Modulo,
+                BinaryOperator::Power,
+                BinaryOperator::Equal,
+                BinaryOperator::NotEqual,
+                BinaryOperator::Less,
+                BinaryOperator::LessEqual,
+                BinaryOperator::Greater,
+                BinaryOperator::GreaterEqual,
+                BinaryOperator::In,
+                BinaryOperator::NotIn,
+                BinaryOperator::And,
+                BinaryOperator::Or,
+                BinaryOperator::BitwiseAnd,
+                BinaryOperator::BitwiseOr,
+                BinaryOperator::BitwiseXor,
+                BinaryOperator::LeftShift,
+                BinaryOperator::RightShift,
+            ];
+            Expression::Binary(BinaryExpression {
+                left: boxed(rng),
+                operator: rng.pick(&operators).clone(),
+                right: boxed(rng),
+            })
+        }
+        2 => {
+            let operators = [
+                UnaryOperator::Minus,
+                UnaryOperator::Not,
+                UnaryOperator::BitwiseNot,
+                UnaryOperator::Reference,
+                UnaryOperator::MutableReference,
+                UnaryOperator::Dereference,
+            ];
+            Expression::Unary(UnaryExpression {
+                operator: rng.pick(&operators).clone(),
+                operand: boxed(rng),
+            })
+        }
+        3 => Expression::Call(CallExpression {
+            callee: boxed(rng),
+            arguments: expressions(rng, depth, 0),
+        }),
+        4 => Expression::Index(IndexExpression {
+            object: boxed(rng),
+            index: boxed(rng),
+        }),
+        5 => Expression::FieldAccess(FieldAccessExpression {
+            object: boxed(rng),
+            field: rng.pick(NAMES).to_string(),
+        }),
+        6 | 7 => Expression::MethodCall(MethodCallExpression {
+            object: boxed(rng),
+            method: rng.pick(NAMES).to_string(),
+            arguments: expressions(rng, depth, 0),
+        }),
+        8 => Expression::Array(ArrayExpression {
+            elements: expressions(rng, depth, 0),
+            trailing_comma: false,
+        }),
+        9 => Expression::Dictionary(DictionaryExpression {
+            pairs: (0..rng.below(4))
+                .map(|_| (expression(rng, depth), expression(rng, depth)))
+                .collect(),
+            trailing_comma: false,
+        }),
+        10 => Expression::Set(SetExpression {
+            elements: expressions(rng, depth, 1),
+            trailing_comma: false,
+        }),
+        11 => Expression::Tuple(TupleExpression {
+            elements: expressions(rng, depth, 1),
+        }),
+        12 => Expression::StructInit(StructInitExpression {
+            struct_name: "Point".to_string(),
+            // `Point {}` would be an identifier followed by a block
+            fields: (0..1 + rng.below(3))
+                .map(|i| (format!("f{}", i), expression(rng, depth)))
+                .collect(),
+            trailing_comma: false,
+        }),
+        13 => Expression::If(IfExpression {
+            condition: boxed(rng),
+            then_expr: boxed(rng),
+            else_expr: boxed(rng),
+        }),
+        14 => Expression::Match(MatchExpression {
+            expression: boxed(rng),
+            arms: (0..1 + rng.below(3))
+                .map(|_| MatchExpressionArm {
+                    pattern: pattern(rng),
+                    guard: (rng.below(3) == 0).then(|| expression(rng, depth)),
+                    expression: expression(rng, depth),
+                })
+                .collect(),
+            trailing_comma: false,
+        }),
+        15 => Expression::Cast(CastExpression {
+            expression: boxed(rng),
+            target_type: Type::Primitive(
+                rng.pick(&[
+                    PrimitiveType::Int,
+                    PrimitiveType::F64,
+                    PrimitiveType::U32,
+                    PrimitiveType::String,
+                ])
+                .clone(),
+            ),
+        }),
+        16 => match rng.below(2) {
+            0 => Expression::Await(AwaitExpression {
+                expression: boxed(rng),
+            }),
+            _ => Expression::Spawn(SpawnExpression {
+                expression: boxed(rng),
+            }),
+        },
+        17 => {
+            // Text between the expressions, never empty, as the parser
+            // produces it
+            let mut parts = Vec::new();
+            for _ in 0..1 + rng.below(3) {
+                let text = text(rng);
+                if !text.is_empty() {
+                    parts.push(StringPart::Literal(text));
+                }
+                parts.push(StringPart::Expression(expression(rng, depth)));
+            }
+            let text = text(rng);
+            if !text.is_empty() {
+                parts.push(StringPart::Literal(text));
+            }
+            Expression::InterpolatedString(InterpolatedStringExpression { parts })
+        }
+        18 => {
+            let parameters = NAMES[..rng.below(3)]
+                .iter()
+                .map(|name| Parameter {
+                    name: name.to_string(),
+                    param_type: (rng.below(2) == 0).then_some(Type::Primitive(PrimitiveType::Int)),
+                    default: None,
+                })
+                .collect();
+            // `fn` lambdas span lines, which string interpolation cannot;
+            // the golden tests cover them
+            Expression::Lambda(LambdaExpression {
+                parameters,
+                return_type: None,
+                body: LambdaBody::Expression(boxed(rng)),
+            })
+        }
+        _ => Expression::ModuleAccess(ModuleAccessExpression {
+            module: "math".to_string(),
+            item: rng.pick(NAMES).to_string(),
+        }),
+    }
+}
+
+#[test]
+fn generated_expressions_round_trip() {
+    for seed in 1..=500u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        let program = Program {
+            items: (0..3)
+                .map(|i| {
+                    Item::Statement(Statement::VariableDeclaration(VariableDeclaration {
+                        name: format!("v{}", i),
+                        mutable: false,
+                        var_type: None,
+                        initializer: expression(&mut rng, 4),
+                        pattern: None,
+                    }))
+                })
+                .collect(),
+            lines: Lines::default(),
+        };
+
+        for config in configs() {
+            let formatted = Formatter::new(config).format_program(&program);
+            let reparsed = parse(&formatted).unwrap_or_else(|e| {
+                panic!(
+                    "seed {}: formatted code does not parse: {}\n{}",
+                    seed, e, formatted
+                )
+            });
+            assert!(
+                same_program(&program, &reparsed),
+                "seed {}: formatting changed the program\n{}",
+                seed,
+                formatted
+            );
+        }
+    }
+}
@@ -0,0 +1,222 @@
+// Cross-checking tree-sitter grammars against veyc
+//
+// Editors highlight Veyra with tree-sitter grammars that are written
+// separately from the parser and drift from it as the language changes.
+// `check_file` parses a file with both and compares the results: whether
+// each accepts the file, and for files both accept, the kinds of the
+// top-level nodes. Tree-sitter grammars name their nodes after the rules
+// printed by `veyc grammar`, so a function is a `function_item` and a `let`
+// a `variable_declaration` in both.
+//
+// The tree-sitter side runs the `tree-sitter parse` command in the grammar's
+// directory and reads the tree it prints, so the grammar is built the same
+// way editors build it and nothing here depends on a tree-sitter version.
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use veyra_compiler::{
+    ast::{Item, Statement},
+    lexer::Lexer,
+    parser::Parser,
+};
+
+/// What tree-sitter made of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree {
+    /// Whether the tree has `ERROR` or `MISSING` nodes
+    pub has_error: bool,
+    /// The kinds of the root's children, without comments and attributes
+    pub top_level: Vec<String>,
+}
+
+/// How the two parsers disagree on a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Mismatch {
+    /// veyc reports a syntax error that tree-sitter does not
+    OnlyVeycRejects { error: String },
+    /// tree-sitter finds an error in a file veyc accepts
+    OnlyTreeSitterRejects,
+    /// Both accept the file but see different top-level nodes
+    TopLevel {
+        veyc: Vec<String>,
+        tree_sitter: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Mismatch::OnlyVeycRejects { error } => {
+                write!(f, "tree-sitter accepts it but veyc does not: {}", error)
+            }
+            Mismatch::OnlyTreeSitterRejects => {
+                write!(f, "veyc accepts it but tree-sitter finds a syntax error")
+            }
+            Mismatch::TopLevel { veyc, tree_sitter } => write!(
+                f,
+                "top-level nodes differ\n    veyc:        {}\n    tree-sitter: {}",
+                veyc.join(" "),
+                tree_sitter.join(" ")
+            ),
+        }
+    }
+}
+
+/// The node kind a tree-sitter grammar uses for a top-level item
+pub fn node_kind(item: &Item) -> &'static str {
+    match item {
+        Item::Function(_) => "function_item",
+        Item::Struct(_) => "struct_item",
+        Item::Impl(_) => "impl_item",
+        Item::Trait(_) => "trait_item",
+        Item::Import(_) => "import_item",
+        Item::Actor(_) => "actor_item",
+        Item::Statement(statement) => match statement {
+            Statement::Expression(_) => "expression_statement",
+            Statement::VariableDeclaration(_) => "variable_declaration",
+            Statement::Assignment(_) => "assignment",
+            Statement::If(_) => "if_statement",
+            Statement::While(_) => "while_statement",
+            Statement::For(_) => "for_statement",
+            Statement::Match(_) => "match_statement",
+            Statement::Return(_) => "return_statement",
+            Statement::Try(_) => "try_statement",
+            Statement::Throw(_) => "throw_statement",
+            Statement::Break => "break_statement",
+            Statement::Continue => "continue_statement",
+            Statement::Block(_) => "braced_block",
+            Statement::TaskGroup(_) => "task_group",
+            Statement::Comment(_) => "comment",
+        },
+    }
+}
+
+/// The top-level node kinds veyc parses `source` into, or its syntax error
+pub fn veyc_top_level(source: &str) -> std::result::Result<Vec<String>, String> {
+    let tokens = Lexer::new(source).tokenize().map_err(|e| e.to_string())?;
+    let program = Parser::new(tokens).parse().map_err(|e| e.to_string())?;
+    Ok(program
+        .items
+        .iter()
+        .map(node_kind)
+        .filter(|kind| !skipped(kind))
+        .map(String::from)
+        .collect())
+}
+
+/// Comments and attributes are left out on both sides: tree-sitter grammars
+/// usually make them siblings of what they belong to
+fn skipped(kind: &str) -> bool {
+    kind.contains("comment") || kind == "attribute"
+}
+
+/// Read the tree `tree-sitter parse` prints, such as
+///
+/// ```text
+/// (source_file [0, 0] - [1, 0]
+///   (function_item [0, 0] - [0, 12]
+///     name: (identifier [0, 3] - [0, 4])))
+/// ```
+///
+/// Anything after the tree, like the timing line printed for files with
+/// errors, is ignored. `None` if there is no tree.
+pub fn parse_tree_output(output: &str) -> Option<Tree> {
+    let mut chars = output.chars().peekable();
+    let mut depth = 0usize;
+    let mut seen_root = false;
+    let mut tree = Tree {
+        has_error: false,
+        top_level: Vec::new(),
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            // Quoted text, as in (MISSING ")"), may hold parentheses
+            '"' if seen_root => {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '(' => {
+                seen_root = true;
+                depth += 1;
+                let mut kind = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    kind.push(c);
+                    chars.next();
+                }
+                if kind == "ERROR" || kind == "MISSING" {
+                    tree.has_error = true;
+                }
+                if depth == 2 && !skipped(&kind) {
+                    tree.top_level.push(kind);
+                }
+            }
+            ')' if seen_root => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return Some(tree);
+                }
+            }
+            _ => {}
+        }
+    }
+    // A tree cut short is still worth comparing
+    seen_root.then_some(tree)
+}
+
+/// Parse `file` with the tree-sitter grammar in `grammar`, using the
+/// tree-sitter command line tool `tree_sitter`
+pub fn tree_sitter_parse(tree_sitter: &str, grammar: &Path, file: &Path) -> Result<Tree> {
+    let file = fs::canonicalize(file)?;
+    let output = Command::new(tree_sitter)
+        .arg("parse")
+        .arg(&file)
+        .current_dir(grammar)
+        .output()
+        .map_err(|e| anyhow!("could not run `{}`: {}", tree_sitter, e))?;
+    parse_tree_output(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+        anyhow!(
+            "`{} parse` printed no tree for {}: {}",
+            tree_sitter,
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    })
+}
+
+/// How veyc's result for a file differs from tree-sitter's, if it does.
+/// Files both reject agree, whatever the errors.
+pub fn compare(veyc: &std::result::Result<Vec<String>, String>, tree: &Tree) -> Option<Mismatch> {
+    match veyc {
+        Err(_) if tree.has_error => None,
+        Err(error) => Some(Mismatch::OnlyVeycRejects {
+            error: error.clone(),
+        }),
+        Ok(_) if tree.has_error => Some(Mismatch::OnlyTreeSitterRejects),
+        Ok(top_level) if *top_level != tree.top_level => Some(Mismatch::TopLevel {
+            veyc: top_level.clone(),
+            tree_sitter: tree.top_level.clone(),
+        }),
+        Ok(_) => None,
+    }
+}
+
+/// Parse a file with both parsers and compare the results
+pub fn check_file(tree_sitter: &str, grammar: &Path, file: &Path) -> Result<Option<Mismatch>> {
+    let source = fs::read_to_string(file)?;
+    let tree = tree_sitter_parse(tree_sitter, grammar, file)?;
+    Ok(compare(&veyc_top_level(&source), &tree))
+}
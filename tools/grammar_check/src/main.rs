@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::path::PathBuf;
+use veyra_grammar_check::{check_file, Mismatch};
+use walkdir::WalkDir;
+
+#[derive(Parser)]
+#[command(name = "veyra-grammar-check")]
+#[command(about = "Check that a tree-sitter grammar for Veyra parses like veyc")]
+#[command(version = "0.1.0")]
+struct Cli {
+    /// Files or directories of Veyra code to parse with both
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Directory of the tree-sitter grammar (the one with grammar.js)
+    #[arg(short, long)]
+    grammar: PathBuf,
+
+    /// The tree-sitter command line tool
+    #[arg(long, default_value = "tree-sitter")]
+    tree_sitter: String,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct FileMismatch {
+    file: PathBuf,
+    #[serde(flatten)]
+    mismatch: Mismatch,
+}
+
+#[derive(Serialize)]
+struct Report {
+    files: usize,
+    mismatches: Vec<FileMismatch>,
+}
+
+/// The .vey files under `paths`, in a stable order
+fn corpus(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_file() {
+            files.push(path.clone());
+            continue;
+        }
+        if !path.is_dir() {
+            return Err(anyhow!("{} does not exist", path.display()));
+        }
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_file()
+                && entry.path().extension().and_then(|s| s.to_str()) == Some("vey")
+            {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let files = corpus(&cli.paths)?;
+    let mut report = Report {
+        files: files.len(),
+        mismatches: Vec::new(),
+    };
+    for file in files {
+        if let Some(mismatch) = check_file(&cli.tree_sitter, &cli.grammar, &file)? {
+            report.mismatches.push(FileMismatch { file, mismatch });
+        }
+    }
+
+    match cli.format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Text => {
+            for FileMismatch { file, mismatch } in &report.mismatches {
+                println!("{}: {}", file.display(), mismatch);
+            }
+            println!(
+                "{} files checked, {} parsed differently",
+                report.files,
+                report.mismatches.len()
+            );
+        }
+    }
+    if !report.mismatches.is_empty() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
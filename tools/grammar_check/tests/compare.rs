@@ -0,0 +1,173 @@
+// Tests for comparing tree-sitter's trees with veyc's
+//
+// tree-sitter itself is not needed: the trees are what `tree-sitter parse`
+// prints, and a stand-in script plays the tool where one has to be run.
+
+use veyra_compiler::grammar;
+use veyra_grammar_check::{check_file, compare, parse_tree_output, veyc_top_level, Mismatch};
+
+const PROGRAM: &str = r#"import std.math
+# a comment
+@inline
+fn main() {
+    print(1)
+}
+struct Point {
+    x: int
+}
+impl Point {
+    fn get(self) {
+        return self.x
+    }
+}
+actor Counter {
+    count: int
+}
+let x = 1
+x = 2
+print(x)
+if x {
+    print(x)
+}
+while false {
+    break
+}
+for i in [1] {
+    continue
+}
+match x {
+    _ -> print(x)
+}
+{
+    print(x)
+}
+task_group {
+    spawn main()
+}
+"#;
+
+const TREE: &str = r#"(source_file [0, 0] - [48, 0]
+  (import_item [0, 0] - [0, 15]
+    path: (identifier [0, 7] - [0, 10])
+    path: (identifier [0, 11] - [0, 15]))
+  (comment [1, 0] - [1, 11])
+  (attribute [2, 0] - [2, 7]
+    name: (identifier [2, 1] - [2, 7]))
+  (function_item [3, 0] - [5, 1]
+    name: (identifier [3, 3] - [3, 7])
+    body: (braced_block [3, 10] - [5, 1]
+      (expression_statement [4, 4] - [4, 12]
+        (call [4, 4] - [4, 12]
+          function: (identifier [4, 4] - [4, 9])
+          arguments: (arguments [4, 9] - [4, 12]
+            (integer_literal [4, 10] - [4, 11]))))))
+  (struct_item [6, 0] - [8, 1])
+  (impl_item [9, 0] - [13, 1])
+  (actor_item [14, 0] - [16, 1])
+  (variable_declaration [17, 0] - [17, 9])
+  (assignment [18, 0] - [18, 5])
+  (expression_statement [19, 0] - [19, 8])
+  (if_statement [20, 0] - [22, 1])
+  (while_statement [23, 0] - [25, 1])
+  (for_statement [26, 0] - [28, 1])
+  (match_statement [29, 0] - [31, 1])
+  (braced_block [32, 0] - [34, 1])
+  (task_group [35, 0] - [37, 1]))
+"#;
+
+#[test]
+fn reads_top_level_nodes() {
+    let tree = parse_tree_output(TREE).unwrap();
+    assert!(!tree.has_error);
+    assert_eq!(tree.top_level.len(), 14);
+    assert_eq!(tree.top_level[0], "import_item");
+    assert_eq!(tree.top_level[1], "function_item");
+}
+
+#[test]
+fn agrees_with_matching_tree() {
+    let tree = parse_tree_output(TREE).unwrap();
+    assert_eq!(compare(&veyc_top_level(PROGRAM), &tree), None);
+}
+
+#[test]
+fn node_kinds_are_grammar_rules() {
+    for kind in veyc_top_level(PROGRAM).unwrap() {
+        assert!(
+            grammar::rules().any(|rule| rule.name == kind),
+            "`{}` is not a rule of `veyc grammar`",
+            kind
+        );
+    }
+}
+
+#[test]
+fn finds_errors_and_ignores_trailing_output() {
+    // Quoted text may hold parentheses, and the line after the tree is
+    // what tree-sitter prints for a file with errors
+    let output = "(source_file [0, 0] - [1, 0]\n  (expression_statement [0, 0] - [0, 6]\n    (call [0, 0] - [0, 6]\n      (MISSING \")\" [0, 6] - [0, 6]))))\nbad.vey\t0.05 ms\t(MISSING \")\" [0, 6] - [0, 6])\n";
+    let tree = parse_tree_output(output).unwrap();
+    assert!(tree.has_error);
+    assert_eq!(tree.top_level, ["expression_statement"]);
+
+    let tree =
+        parse_tree_output("(source_file [0, 0] - [0, 3]\n  (ERROR [0, 0] - [0, 3]))").unwrap();
+    assert!(tree.has_error);
+    assert_eq!(parse_tree_output("Failed to load language"), None);
+}
+
+#[test]
+fn reports_each_kind_of_mismatch() {
+    let accepted = parse_tree_output(
+        "(source_file [0, 0] - [0, 9]\n  (variable_declaration [0, 0] - [0, 9]))",
+    )
+    .unwrap();
+    let rejected =
+        parse_tree_output("(source_file [0, 0] - [0, 9]\n  (ERROR [0, 0] - [0, 9]))").unwrap();
+
+    assert_eq!(compare(&veyc_top_level("let x = 1"), &accepted), None);
+    assert_eq!(compare(&veyc_top_level("let x = "), &rejected), None);
+    assert!(matches!(
+        compare(&veyc_top_level("let x = "), &accepted),
+        Some(Mismatch::OnlyVeycRejects { .. })
+    ));
+    assert_eq!(
+        compare(&veyc_top_level("let x = 1"), &rejected),
+        Some(Mismatch::OnlyTreeSitterRejects)
+    );
+    assert_eq!(
+        compare(&veyc_top_level("x = 1"), &accepted),
+        Some(Mismatch::TopLevel {
+            veyc: vec!["assignment".to_string()],
+            tree_sitter: vec!["variable_declaration".to_string()],
+        })
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_tree_sitter_in_the_grammar_directory() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let grammar = dir.join("tree-sitter-veyra");
+    fs::create_dir_all(&grammar).unwrap();
+    fs::write(grammar.join("tree.txt"), TREE).unwrap();
+    // Prints the saved tree, which is only found from the grammar directory
+    let script = dir.join("tree-sitter");
+    fs::write(&script, "#!/bin/sh\ntest \"$1\" = parse && cat tree.txt\n").unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    let file = dir.join("program.vey");
+
+    fs::write(&file, PROGRAM).unwrap();
+    let tree_sitter = script.to_str().unwrap();
+    assert_eq!(check_file(tree_sitter, &grammar, &file).unwrap(), None);
+
+    fs::write(&file, "print(1)").unwrap();
+    assert!(matches!(
+        check_file(tree_sitter, &grammar, &file).unwrap(),
+        Some(Mismatch::TopLevel { .. })
+    ));
+}
@@ -0,0 +1,344 @@
+// Detection of loop-invariant expressions for the `loop-invariant` lint
+//
+// An expression is invariant in a loop when nothing it reads can change
+// while the loop runs: its variables are never assigned, redeclared or
+// passed to a mutating call in the loop body. Invariant expressions that
+// call a builtin (like `len(arr)` in `while i < len(arr)`) are recomputed on
+// every iteration for nothing and can be hoisted out of the loop.
+
+use std::collections::HashSet;
+use veyra_compiler::{ast::*, builtins};
+
+/// Builtins without side effects, whose result depends only on the arguments
+const PURE_BUILTINS: &[&str] = &[
+    "str",
+    "repr",
+    "format",
+    "len",
+    "type_of",
+    "implements",
+    "int",
+    "float",
+    "bool",
+    "char",
+    "is_int",
+    "is_float",
+    "is_string",
+    "is_bool",
+    "is_char",
+    "is_array",
+    "is_dict",
+    "is_none",
+    "sqrt",
+    "pow",
+    "abs",
+    "min",
+    "max",
+    "floor",
+    "ceil",
+    "round",
+    "string_to_upper",
+    "string_to_lower",
+    "string_trim",
+    "clamp",
+    "array_sum",
+    "array_avg",
+    "range",
+];
+
+/// Builtins that are meant to update the array passed as their first argument
+const MUTATING_BUILTINS: &[&str] = &["push", "pop"];
+
+/// An invariant expression, rendered as source, in the `loop_index`-th loop
+/// of the program (counting `while` and `for` loops in source order)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopInvariant {
+    pub loop_index: usize,
+    pub expression: String,
+}
+
+pub fn find_loop_invariants(program: &Program) -> Vec<LoopInvariant> {
+    // A call to a user function may assign any variable assigned in a function
+    let mut assigned_in_functions = Mutations::default();
+    for item in &program.items {
+        if !matches!(item, Item::Statement(_)) {
+            assigned_in_functions.visit_item(item);
+        }
+    }
+
+    let mut finder = Finder {
+        assigned_in_functions: assigned_in_functions.names,
+        loops: 0,
+        found: Vec::new(),
+    };
+    finder.visit_program(program);
+    finder.found
+}
+
+/// Checks each loop, counting them in source order
+struct Finder {
+    assigned_in_functions: HashSet<String>,
+    loops: usize,
+    found: Vec<LoopInvariant>,
+}
+
+impl Visitor for Finder {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::While(stmt) => self.check_loop(Some(&stmt.condition), &stmt.body, None),
+            Statement::For(stmt) => self.check_loop(None, &stmt.body, Some(&stmt.variable)),
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    // Loops only appear in statements
+    fn visit_expression(&mut self, _expression: &Expression) {}
+}
+
+impl Finder {
+    fn check_loop(
+        &mut self,
+        condition: Option<&Expression>,
+        body: &Block,
+        variable: Option<&String>,
+    ) {
+        let loop_index = self.loops;
+        self.loops += 1;
+
+        let mut mutations = Mutations::default();
+        mutations.visit_block(body);
+        if let Some(condition) = condition {
+            mutations.visit_expression(condition);
+        }
+        mutations.names.extend(variable.cloned());
+        if mutations.calls_user_function {
+            mutations
+                .names
+                .extend(self.assigned_in_functions.iter().cloned());
+        }
+
+        let mut check = InvariantCheck {
+            mutated: &mutations.names,
+            found: Vec::new(),
+        };
+        if let Some(condition) = condition {
+            check.visit_expression(condition);
+        }
+        check.visit_block(body);
+
+        let mut seen = HashSet::new();
+        for expression in check.found {
+            if seen.insert(expression.clone()) {
+                self.found.push(LoopInvariant {
+                    loop_index,
+                    expression,
+                });
+            }
+        }
+    }
+}
+
+/// Variables a loop may change, and whether it calls user code that could
+/// change others
+#[derive(Default)]
+struct Mutations {
+    names: HashSet<String>,
+    calls_user_function: bool,
+}
+
+impl Visitor for Mutations {
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            // Redeclared on every iteration
+            Statement::VariableDeclaration(var) => {
+                self.names
+                    .extend(var.names().into_iter().map(str::to_string));
+            }
+            Statement::Assignment(assign) => self.names.extend(root_variable(&assign.target)),
+            Statement::For(stmt) => {
+                self.names.insert(stmt.variable.clone());
+            }
+            Statement::Try(stmt) => {
+                let variable = stmt.catch.as_ref().and_then(|c| c.variable.clone());
+                self.names.extend(variable);
+            }
+            _ => {}
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        self.names
+            .extend(pattern.bindings().into_iter().map(str::to_string));
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Call(call) => match call.callee.as_ref() {
+                Expression::Identifier(name) if MUTATING_BUILTINS.contains(&name.as_str()) => {
+                    if let Some(target) = call.arguments.first() {
+                        self.names.extend(root_variable(target));
+                    }
+                }
+                Expression::Identifier(name) if builtins::lookup(name).is_some() => {}
+                _ => self.calls_user_function = true,
+            },
+            Expression::MethodCall(call) => {
+                // Methods may update their receiver
+                self.names.extend(root_variable(&call.object));
+                self.calls_user_function = true;
+            }
+            Expression::Unary(unary) if unary.operator == UnaryOperator::MutableReference => {
+                self.names.extend(root_variable(&unary.operand));
+            }
+            Expression::Spawn(_) => self.calls_user_function = true,
+            _ => {}
+        }
+        walk_expression(self, expr);
+    }
+}
+
+/// The variable an expression reads from or writes to, e.g. `a` in `a.b[0]`
+fn root_variable(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Identifier(name) => Some(name.clone()),
+        Expression::Index(index) => root_variable(&index.object),
+        Expression::FieldAccess(field) => root_variable(&field.object),
+        Expression::Unary(unary) => root_variable(&unary.operand),
+        _ => None,
+    }
+}
+
+/// Collects the largest invariant subexpressions that call a builtin
+struct InvariantCheck<'a> {
+    mutated: &'a HashSet<String>,
+    found: Vec<String>,
+}
+
+impl Visitor for InvariantCheck<'_> {
+    /// Statements of the loop body. Nested loops are checked on their own,
+    /// except for a `for` iterable, which is evaluated once per outer iteration.
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::While(_) => {}
+            Statement::For(stmt) => self.visit_expression(&stmt.iterable),
+            Statement::Assignment(assign) => self.visit_expression(&assign.value),
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        if self.is_invariant(expr) && calls_builtin(expr) {
+            self.found.push(render(expr));
+        } else {
+            walk_expression(self, expr);
+        }
+    }
+}
+
+impl InvariantCheck<'_> {
+    fn is_invariant(&self, expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal(_) => true,
+            Expression::Identifier(name) => !self.mutated.contains(name),
+            Expression::Binary(bin) => {
+                self.is_invariant(&bin.left) && self.is_invariant(&bin.right)
+            }
+            Expression::Unary(unary) => {
+                matches!(
+                    unary.operator,
+                    UnaryOperator::Minus | UnaryOperator::Not | UnaryOperator::BitwiseNot
+                ) && self.is_invariant(&unary.operand)
+            }
+            Expression::Call(call) => {
+                matches!(call.callee.as_ref(), Expression::Identifier(name) if PURE_BUILTINS.contains(&name.as_str()))
+                    && call.arguments.iter().all(|arg| self.is_invariant(arg))
+            }
+            Expression::Index(index) => {
+                self.is_invariant(&index.object) && self.is_invariant(&index.index)
+            }
+            Expression::FieldAccess(field) => self.is_invariant(&field.object),
+            _ => false,
+        }
+    }
+}
+
+fn calls_builtin(expr: &Expression) -> bool {
+    match expr {
+        Expression::Call(_) => true,
+        Expression::Binary(bin) => calls_builtin(&bin.left) || calls_builtin(&bin.right),
+        Expression::Unary(unary) => calls_builtin(&unary.operand),
+        Expression::Index(index) => calls_builtin(&index.object) || calls_builtin(&index.index),
+        Expression::FieldAccess(field) => calls_builtin(&field.object),
+        _ => false,
+    }
+}
+
+/// Source text of an invariant expression, for the lint message
+fn render(expr: &Expression) -> String {
+    match expr {
+        Expression::Literal(Literal::String(s)) => format!("{:?}", s),
+        Expression::Literal(Literal::Char(c)) => format!("{:?}", c),
+        Expression::Literal(Literal::Integer(n)) => n.to_string(),
+        Expression::Literal(Literal::Float(f)) => format!("{:?}", f),
+        Expression::Literal(Literal::Boolean(b)) => b.to_string(),
+        Expression::Literal(Literal::None) => "None".to_string(),
+        Expression::Identifier(name) => name.clone(),
+        Expression::Binary(bin) => format!(
+            "{} {} {}",
+            render_operand(&bin.left),
+            binary_symbol(&bin.operator),
+            render_operand(&bin.right)
+        ),
+        Expression::Unary(unary) => {
+            let symbol = match unary.operator {
+                UnaryOperator::Not => "not ",
+                UnaryOperator::BitwiseNot => "~",
+                _ => "-",
+            };
+            format!("{}{}", symbol, render_operand(&unary.operand))
+        }
+        Expression::Call(call) => {
+            let args: Vec<String> = call.arguments.iter().map(render).collect();
+            format!("{}({})", render(&call.callee), args.join(", "))
+        }
+        Expression::Index(index) => format!("{}[{}]", render(&index.object), render(&index.index)),
+        Expression::FieldAccess(field) => format!("{}.{}", render(&field.object), field.field),
+        _ => "...".to_string(),
+    }
+}
+
+fn render_operand(expr: &Expression) -> String {
+    match expr {
+        Expression::Binary(_) => format!("({})", render(expr)),
+        _ => render(expr),
+    }
+}
+
+fn binary_symbol(op: &BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::FloorDivide => "//",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Power => "**",
+        BinaryOperator::Equal => "==",
+        BinaryOperator::NotEqual => "!=",
+        BinaryOperator::Less => "<",
+        BinaryOperator::LessEqual => "<=",
+        BinaryOperator::Greater => ">",
+        BinaryOperator::GreaterEqual => ">=",
+        BinaryOperator::In => "in",
+        BinaryOperator::NotIn => "not in",
+        BinaryOperator::And => "and",
+        BinaryOperator::Or => "or",
+        BinaryOperator::BitwiseAnd => "&",
+        BinaryOperator::BitwiseOr => "|",
+        BinaryOperator::BitwiseXor => "^",
+        BinaryOperator::LeftShift => "<<",
+        BinaryOperator::RightShift => ">>",
+    }
+}
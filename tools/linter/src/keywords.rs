@@ -0,0 +1,142 @@
+// Source positions for keyword-introduced AST nodes
+//
+// The AST does not record positions, but functions, loops, `if`, `match`,
+// `return`, `break` and `continue` each start with their keyword. Walking the
+// program in source order and pairing the n-th such node with the n-th token
+// of its keyword locates it.
+
+use std::collections::HashMap;
+use std::mem::{discriminant, Discriminant};
+use veyra_compiler::{
+    ast::*,
+    lexer::{Lexer, TokenKind},
+};
+
+pub struct KeywordPositions {
+    /// Character offset of each node's keyword, keyed by node address
+    offsets: HashMap<usize, usize>,
+}
+
+impl KeywordPositions {
+    pub fn new(program: &Program, content: &str) -> Self {
+        let mut nodes = Nodes(Vec::new());
+        nodes.visit_program(program);
+
+        // Offsets of each keyword's tokens, in order
+        let (tokens, _) = Lexer::new(content).tokenize_with_ranges();
+        let mut keywords: HashMap<Discriminant<TokenKind>, Vec<usize>> = HashMap::new();
+        for (token, range) in &tokens {
+            keywords
+                .entry(discriminant(&token.kind))
+                .or_default()
+                .push(range.start);
+        }
+
+        let mut seen: HashMap<Discriminant<TokenKind>, usize> = HashMap::new();
+        let mut offsets = HashMap::new();
+        for (address, keyword) in nodes.0 {
+            let keyword = discriminant(&keyword);
+            let count = seen.entry(keyword).or_default();
+            if let Some(offset) = keywords.get(&keyword).and_then(|all| all.get(*count)) {
+                offsets.insert(address, *offset);
+            }
+            *count += 1;
+        }
+
+        Self { offsets }
+    }
+
+    pub fn function(&self, function: &Function) -> Option<usize> {
+        self.offsets.get(&address(function)).copied()
+    }
+
+    pub fn statement(&self, statement: &Statement) -> Option<usize> {
+        self.offsets.get(&address(statement)).copied()
+    }
+}
+
+/// The keyword a statement starts with, and its spelling
+pub fn statement_keyword(statement: &Statement) -> Option<(TokenKind, &'static str)> {
+    match statement {
+        Statement::If(_) => Some((TokenKind::If, "if")),
+        Statement::While(_) => Some((TokenKind::While, "while")),
+        Statement::For(_) => Some((TokenKind::For, "for")),
+        Statement::Match(_) => Some((TokenKind::Match, "match")),
+        Statement::Return(_) => Some((TokenKind::Return, "return")),
+        Statement::Try(_) => Some((TokenKind::Try, "try")),
+        Statement::Throw(_) => Some((TokenKind::Throw, "throw")),
+        Statement::Break => Some((TokenKind::Break, "break")),
+        Statement::Continue => Some((TokenKind::Continue, "continue")),
+        _ => None,
+    }
+}
+
+fn address<T>(node: &T) -> usize {
+    node as *const T as usize
+}
+
+/// Keyword-introduced nodes in source order
+struct Nodes(Vec<(usize, TokenKind)>);
+
+impl Visitor for Nodes {
+    fn visit_item(&mut self, item: &Item) {
+        // Trait methods without a body are not walked, but start with `fn`
+        let Item::Trait(tr) = item else {
+            return walk_item(self, item);
+        };
+        for method in &tr.methods {
+            if method.has_default {
+                self.visit_function(&method.function);
+            } else {
+                self.0.push((address(&method.function), TokenKind::Fn));
+            }
+        }
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        self.0.push((address(function), TokenKind::Fn));
+        walk_function(self, function);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Some((keyword, _)) = statement_keyword(statement) {
+            self.0.push((address(statement), keyword));
+        }
+        // Arm guards start with `if` between the pattern and the body
+        let Statement::Match(stmt) = statement else {
+            return walk_statement(self, statement);
+        };
+        self.visit_expression(&stmt.expression);
+        for arm in &stmt.arms {
+            self.guard(arm.guard.as_ref());
+            self.visit_statement(&arm.body);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        // Not located, but their keywords must be counted
+        match expression {
+            Expression::If(_) => self.0.push((address(expression), TokenKind::If)),
+            Expression::Match(match_expr) => {
+                self.0.push((address(expression), TokenKind::Match));
+                self.visit_expression(&match_expr.expression);
+                for arm in &match_expr.arms {
+                    self.guard(arm.guard.as_ref());
+                    self.visit_expression(&arm.expression);
+                }
+                return;
+            }
+            _ => {}
+        }
+        walk_expression(self, expression);
+    }
+}
+
+impl Nodes {
+    fn guard(&mut self, guard: Option<&Expression>) {
+        if let Some(guard) = guard {
+            self.0.push((address(guard), TokenKind::If));
+            self.visit_expression(guard);
+        }
+    }
+}
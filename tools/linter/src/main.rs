@@ -5,10 +5,24 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use walkdir::WalkDir;
 
 // Import from the main compiler
-use veyra_compiler::{ast::*, lexer::Lexer, parser::Parser as VeyraParser};
+use veyra_compiler::{
+    ast::*,
+    deprecation,
+    events::Event,
+    flow::ControlFlowGraph,
+    lexer::{Lexer, TokenKind},
+    parser::Parser as VeyraParser,
+    resolve::{self, SymbolKind, SymbolTable},
+    suggest, Severity,
+};
+
+mod invariants;
+mod keywords;
+mod taint;
 
 #[derive(Parser)]
 #[command(name = "veyra-lint")]
@@ -50,6 +64,11 @@ struct Cli {
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Report issues, and the run finishing, as newline-delimited JSON
+    /// events in the format shared with the other Veyra tools
+    #[arg(long)]
+    log_json: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -85,6 +104,11 @@ struct LintConfig {
 
     #[serde(default)]
     warnings_as_errors: bool,
+
+    /// Functions that make input safe to pass to `exec`, `write_file` and
+    /// network requests
+    #[serde(default)]
+    sanitizers: Vec<String>,
 }
 
 struct Linter {
@@ -179,6 +203,26 @@ impl Linter {
             },
         );
 
+        rules.insert(
+            "deprecated",
+            LintRule {
+                _name: "deprecated",
+                level: LintLevel::Warning,
+                enabled: true,
+                _description: "Use of a function or struct marked @deprecated",
+            },
+        );
+
+        rules.insert(
+            "loop-invariant",
+            LintRule {
+                _name: "loop-invariant",
+                level: LintLevel::Warning,
+                enabled: true,
+                _description: "Expression is recomputed in a loop but never changes",
+            },
+        );
+
         rules.insert(
             "complex-expression",
             LintRule {
@@ -189,6 +233,36 @@ impl Linter {
             },
         );
 
+        rules.insert(
+            "tainted-exec",
+            LintRule {
+                _name: "tainted-exec",
+                level: LintLevel::Warning,
+                enabled: true,
+                _description: "Program input reaches exec() without being sanitized",
+            },
+        );
+
+        rules.insert(
+            "tainted-write",
+            LintRule {
+                _name: "tainted-write",
+                level: LintLevel::Warning,
+                enabled: true,
+                _description: "Program input chooses the file write_file() writes",
+            },
+        );
+
+        rules.insert(
+            "tainted-request",
+            LintRule {
+                _name: "tainted-request",
+                level: LintLevel::Warning,
+                enabled: true,
+                _description: "Program input chooses where a network request goes",
+            },
+        );
+
         // Apply config overrides
         for (rule_name, level_str) in &config.rules {
             if let Some(rule) = rules.get_mut(rule_name.as_str()) {
@@ -237,14 +311,21 @@ impl Linter {
             .parse()
             .map_err(|e| anyhow!("Parse error in {}: {}", path.display(), e))?;
 
+        let (ranged, _) = Lexer::new(&content).tokenize_with_ranges();
+        let names = resolve::resolve(&ast, &ranged);
+
         // Run lint checks
-        self.check_unused_variables(&ast);
-        self.check_unused_functions(&ast);
-        self.check_undefined_variables(&ast);
-        self.check_unreachable_code(&ast);
-        self.check_missing_returns(&ast);
+        self.check_unused_variables(&names, &content);
+        self.check_unused_functions(&ast, &names, &content);
+        self.check_undefined_variables(&names, &content);
+        let keywords = keywords::KeywordPositions::new(&ast, &content);
+        self.check_unreachable_code(&ast, &keywords, &content);
+        self.check_missing_returns(&ast, &keywords, &content);
         self.check_empty_blocks(&ast);
-        self.check_variable_shadowing(&ast);
+        self.check_variable_shadowing(&names, &content);
+        self.check_deprecated_uses(&ast, &content);
+        self.check_loop_invariants(&ast, &content);
+        self.check_tainted_sinks(&ast, &content);
 
         Ok(())
     }
@@ -279,76 +360,127 @@ impl Linter {
         }
     }
 
-    fn check_unused_variables(&mut self, program: &Program) {
-        let mut analyzer = VariableAnalyzer::new();
-        analyzer.analyze_program(program);
-
-        for var in analyzer.unused_variables() {
+    fn check_unused_variables(&mut self, names: &SymbolTable, content: &str) {
+        for (id, symbol) in names.symbols.iter().enumerate() {
+            if symbol.kind != SymbolKind::Variable
+                || symbol.name.starts_with('_')
+                || names.references_to(id).next().is_some()
+            {
+                continue;
+            }
+            let (line, column) = position(content, &symbol.span);
             self.add_issue(
                 "unused-variable",
-                format!("Variable '{}' is declared but never used", var),
-                1, // TODO: Track actual line numbers in AST
-                1,
+                format!("Variable '{}' is declared but never used", symbol.name),
+                line,
+                column,
                 Some("Consider removing the variable or prefixing with '_'".to_string()),
             );
         }
     }
 
-    fn check_unused_functions(&mut self, program: &Program) {
-        let mut analyzer = FunctionAnalyzer::new();
-        analyzer.analyze_program(program);
-
-        for func in analyzer.unused_functions() {
+    fn check_unused_functions(&mut self, program: &Program, names: &SymbolTable, content: &str) {
+        // Entry points and functions with attributes (like @test) are called
+        // from outside the program
+        let called_externally: HashSet<&str> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(func) if !func.attributes.is_empty() => Some(func.name.as_str()),
+                _ => None,
+            })
+            .chain(["main"])
+            .collect();
+
+        for (id, symbol) in names.symbols.iter().enumerate() {
+            if symbol.kind != SymbolKind::Function
+                || symbol.name.starts_with('_')
+                || called_externally.contains(symbol.name.as_str())
+                || names.references_to(id).next().is_some()
+            {
+                continue;
+            }
+            let (line, column) = position(content, &symbol.span);
             self.add_issue(
                 "unused-function",
-                format!("Function '{}' is defined but never called", func),
-                1,
-                1,
+                format!("Function '{}' is defined but never called", symbol.name),
+                line,
+                column,
                 Some("Consider removing the function or making it public".to_string()),
             );
         }
     }
 
-    fn check_undefined_variables(&mut self, program: &Program) {
-        let mut analyzer = VariableAnalyzer::new();
-        analyzer.analyze_program(program);
-
-        for var in analyzer.undefined_variables() {
+    fn check_undefined_variables(&mut self, names: &SymbolTable, content: &str) {
+        for reference in names.undefined() {
+            let similar =
+                suggest::similar_names(&reference.name, names.visible_names(reference.scope));
+            let (line, column) = position(content, &reference.span);
             self.add_issue(
                 "undefined-variable",
-                format!("Variable '{}' is used but never defined", var),
-                1,
-                1,
-                None,
+                format!("Variable '{}' is used but never defined", reference.name),
+                line,
+                column,
+                similar
+                    .first()
+                    .map(|name| format!("Did you mean '{}'?", name)),
             );
         }
     }
 
-    fn check_unreachable_code(&mut self, program: &Program) {
-        let mut analyzer = ReachabilityAnalyzer::new();
-        analyzer.analyze_program(program);
-
-        if analyzer.has_unreachable_code() {
-            self.add_issue(
-                "unreachable-code",
-                "Code after return statement is unreachable".to_string(),
-                1,
-                1,
-                Some("Remove unreachable code".to_string()),
-            );
+    fn check_unreachable_code(
+        &mut self,
+        program: &Program,
+        keywords: &keywords::KeywordPositions,
+        content: &str,
+    ) {
+        let top_level = program.items.iter().filter_map(|item| match item {
+            Item::Statement(statement) => Some(statement),
+            _ => None,
+        });
+        let graphs = std::iter::once(ControlFlowGraph::new(top_level))
+            .chain(functions(program).map(ControlFlowGraph::function));
+
+        for graph in graphs {
+            for unreachable in &graph.unreachable {
+                let keyword = keywords::statement_keyword(unreachable.after)
+                    .map_or("this statement".to_string(), |(_, name)| {
+                        format!("'{}'", name)
+                    });
+                let (line, column) = position_at(content, keywords.statement(unreachable.after));
+                self.add_issue(
+                    "unreachable-code",
+                    format!("Code after {} is unreachable", keyword),
+                    line,
+                    column,
+                    Some("Remove unreachable code".to_string()),
+                );
+            }
         }
     }
 
-    fn check_missing_returns(&mut self, program: &Program) {
-        let mut analyzer = ReturnAnalyzer::new();
-        analyzer.analyze_program(program);
-
-        for func in analyzer.functions_missing_returns() {
+    fn check_missing_returns(
+        &mut self,
+        program: &Program,
+        keywords: &keywords::KeywordPositions,
+        content: &str,
+    ) {
+        for func in functions(program) {
+            let graph = ControlFlowGraph::function(func);
+            let returns_value =
+                func.return_type.is_some() || graph.returns().iter().any(Option::is_some);
+            if !returns_value || !graph.falls_through() {
+                continue;
+            }
+            let (line, column) = position_at(content, keywords.function(func));
             self.add_issue(
                 "missing-return",
-                format!("Function '{}' may not return a value on all paths", func),
-                1,
-                1,
+                format!(
+                    "Function '{}' may not return a value on all paths",
+                    func.name
+                ),
+                line,
+                column,
                 Some("Add explicit return statements".to_string()),
             );
         }
@@ -369,120 +501,136 @@ impl Linter {
         }
     }
 
-    fn check_variable_shadowing(&mut self, program: &Program) {
-        let mut analyzer = ShadowAnalyzer::new();
-        analyzer.analyze_program(program);
+    fn check_variable_shadowing(&mut self, names: &SymbolTable, content: &str) {
+        let is_variable = |kind| matches!(kind, SymbolKind::Variable | SymbolKind::Parameter);
 
-        for var in analyzer.shadowed_variables() {
+        for (id, symbol) in names.symbols.iter().enumerate() {
+            if !is_variable(symbol.kind) || symbol.name.starts_with('_') {
+                continue;
+            }
+            let Some(outer) = names.shadowed(id) else {
+                continue;
+            };
+            if !is_variable(names.symbol(outer).kind) {
+                continue;
+            }
+            let (line, column) = position(content, &symbol.span);
             self.add_issue(
                 "shadow-variable",
-                format!("Variable '{}' shadows another variable in outer scope", var),
-                1,
-                1,
+                format!(
+                    "Variable '{}' shadows another variable in outer scope",
+                    symbol.name
+                ),
+                line,
+                column,
                 Some("Use a different variable name".to_string()),
             );
         }
     }
 
-    fn get_issues(&self) -> &[LintIssue] {
-        &self.issues
-    }
-
-    fn clear_issues(&mut self) {
-        self.issues.clear();
-    }
-}
-
-// Simplified analyzers (these would need full implementation)
-struct VariableAnalyzer {
-    declared: HashSet<String>,
-    used: HashSet<String>,
-}
-
-impl VariableAnalyzer {
-    fn new() -> Self {
-        Self {
-            declared: HashSet::new(),
-            used: HashSet::new(),
+    fn check_deprecated_uses(&mut self, program: &Program, content: &str) {
+        let deprecations = deprecation::deprecations(program);
+        if deprecations.is_empty() {
+            return;
         }
-    }
 
-    fn analyze_program(&mut self, _program: &Program) {
-        // TODO: Implement proper variable tracking
-        // For now, return empty sets
-    }
-
-    fn unused_variables(&self) -> Vec<String> {
-        self.declared.difference(&self.used).cloned().collect()
-    }
-
-    fn undefined_variables(&self) -> Vec<String> {
-        self.used.difference(&self.declared).cloned().collect()
-    }
-}
-
-struct FunctionAnalyzer {
-    defined: HashSet<String>,
-    called: HashSet<String>,
-}
-
-impl FunctionAnalyzer {
-    fn new() -> Self {
-        Self {
-            defined: HashSet::new(),
-            called: HashSet::new(),
+        let (tokens, _) = Lexer::new(content).tokenize_with_ranges();
+        for (index, range) in deprecation::uses(&tokens, &deprecations) {
+            let (line, column) = line_column(content, range.start);
+            let deprecated = &deprecations[index];
+            self.add_issue(
+                "deprecated",
+                deprecated.message(),
+                line,
+                column,
+                deprecated.note.clone(),
+            );
         }
     }
 
-    fn analyze_program(&mut self, _program: &Program) {
-        // TODO: Implement proper function tracking
-    }
-
-    fn unused_functions(&self) -> Vec<String> {
-        self.defined.difference(&self.called).cloned().collect()
-    }
-}
-
-struct ReachabilityAnalyzer {
-    has_unreachable: bool,
-}
-
-impl ReachabilityAnalyzer {
-    fn new() -> Self {
-        Self {
-            has_unreachable: false,
+    fn check_loop_invariants(&mut self, program: &Program, content: &str) {
+        let invariants = invariants::find_loop_invariants(program);
+        if invariants.is_empty() {
+            return;
         }
-    }
-
-    fn analyze_program(&mut self, _program: &Program) {
-        // TODO: Implement proper reachability analysis
-    }
 
-    fn has_unreachable_code(&self) -> bool {
-        self.has_unreachable
+        // Loops are numbered in source order, so the n-th loop keyword locates the n-th loop
+        let (tokens, _) = Lexer::new(content).tokenize_with_ranges();
+        let loops: Vec<usize> = tokens
+            .iter()
+            .filter(|(token, _)| matches!(token.kind, TokenKind::While | TokenKind::For))
+            .map(|(_, range)| range.start)
+            .collect();
+
+        for invariant in invariants {
+            let (line, column) = loops
+                .get(invariant.loop_index)
+                .map_or((1, 1), |start| line_column(content, *start));
+            self.add_issue(
+                "loop-invariant",
+                format!(
+                    "'{}' is recomputed on every iteration but does not change in the loop",
+                    invariant.expression
+                ),
+                line,
+                column,
+                Some("Compute it once before the loop and store it in a variable".to_string()),
+            );
+        }
     }
-}
 
-struct ReturnAnalyzer {
-    missing_returns: Vec<String>,
-}
+    fn check_tainted_sinks(&mut self, program: &Program, content: &str) {
+        let tainted = taint::find_tainted_sinks(program, &self.config.sanitizers);
+        if tainted.is_empty() {
+            return;
+        }
 
-impl ReturnAnalyzer {
-    fn new() -> Self {
-        Self {
-            missing_returns: Vec::new(),
+        // Sink calls are numbered in source order, so the n-th `exec(` locates
+        // the n-th call to exec
+        let (tokens, _) = Lexer::new(content).tokenize_with_ranges();
+        let calls_to = |name: &str| -> Vec<usize> {
+            tokens
+                .windows(2)
+                .filter(|pair| {
+                    pair[0].0.kind == TokenKind::Identifier
+                        && pair[0].0.lexeme == name
+                        && pair[1].0.kind == TokenKind::LeftParen
+                })
+                .map(|pair| pair[0].1.start)
+                .collect()
+        };
+
+        for found in tainted {
+            let (line, column) = calls_to(found.sink)
+                .get(found.call_index)
+                .map_or((1, 1), |start| line_column(content, *start));
+            self.add_issue(
+                found.rule,
+                format!(
+                    "'{}' passed to {}() comes from {}() and is not sanitized",
+                    found.argument, found.sink, found.source
+                ),
+                line,
+                column,
+                Some(
+                    "Validate it first, or pass it through a function listed under \
+                     `sanitizers` in the lint config"
+                        .to_string(),
+                ),
+            );
         }
     }
 
-    fn analyze_program(&mut self, _program: &Program) {
-        // TODO: Implement proper return analysis
+    fn get_issues(&self) -> &[LintIssue] {
+        &self.issues
     }
 
-    fn functions_missing_returns(&self) -> &[String] {
-        &self.missing_returns
+    fn clear_issues(&mut self) {
+        self.issues.clear();
     }
 }
 
+// Simplified analyzers (these would need full implementation)
 struct BlockAnalyzer {
     has_empty: bool,
 }
@@ -501,24 +649,32 @@ impl BlockAnalyzer {
     }
 }
 
-struct ShadowAnalyzer {
-    shadowed: Vec<String>,
+/// 1-based line and column of a source span, or the start of the file when
+/// the span is unknown
+fn position(content: &str, span: &Option<std::ops::Range<usize>>) -> (usize, usize) {
+    position_at(content, span.as_ref().map(|span| span.start))
 }
 
-impl ShadowAnalyzer {
-    fn new() -> Self {
-        Self {
-            shadowed: Vec::new(),
-        }
-    }
+fn position_at(content: &str, offset: Option<usize>) -> (usize, usize) {
+    offset.map_or((1, 1), |offset| line_column(content, offset))
+}
 
-    fn analyze_program(&mut self, _program: &Program) {
-        // TODO: Implement proper shadow analysis
-    }
+/// Every function in the program, including impl and actor methods
+fn functions(program: &Program) -> impl Iterator<Item = &Function> {
+    program.items.iter().flat_map(|item| match item {
+        Item::Function(func) => std::slice::from_ref(func),
+        Item::Impl(imp) => imp.methods.as_slice(),
+        Item::Actor(actor) => actor.methods.as_slice(),
+        _ => &[],
+    })
+}
 
-    fn shadowed_variables(&self) -> &[String] {
-        &self.shadowed
-    }
+/// 1-based line and column of a character offset
+fn line_column(content: &str, char_idx: usize) -> (usize, usize) {
+    let before: Vec<char> = content.chars().take(char_idx).collect();
+    let line = before.iter().filter(|c| **c == '\n').count() + 1;
+    let column = before.iter().rev().take_while(|c| **c != '\n').count() + 1;
+    (line, column)
 }
 
 fn collect_veyra_files(paths: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>> {
@@ -559,21 +715,33 @@ fn collect_veyra_files(paths: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf
 }
 
 fn load_config(config_path: Option<&Path>) -> Result<LintConfig> {
-    if let Some(path) = config_path {
-        let content = fs::read_to_string(path)?;
-        let config: LintConfig = serde_json::from_str(&content)?;
-        Ok(config)
-    } else {
+    // The project's [lint] section is the base; a lint config file overrides it
+    let mut config = match veyra_config::Project::discover_from_cwd()? {
+        Some(project) => LintConfig {
+            rules: project.config.lint.rules,
+            warnings_as_errors: project.config.lint.warnings_as_errors.unwrap_or(false),
+            sanitizers: project.config.lint.sanitizers,
+        },
+        None => LintConfig::default(),
+    };
+
+    let file_path = config_path.map(Path::to_path_buf).or_else(|| {
         // Look for default config files
-        for default_path in &[".veyra-lint.json", "veyra-lint.json"] {
-            if Path::new(default_path).exists() {
-                let content = fs::read_to_string(default_path)?;
-                let config: LintConfig = serde_json::from_str(&content)?;
-                return Ok(config);
-            }
-        }
-        Ok(LintConfig::default())
+        [".veyra-lint.json", "veyra-lint.json"]
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+    });
+
+    if let Some(path) = file_path {
+        let content = fs::read_to_string(path)?;
+        let file_config: LintConfig = serde_json::from_str(&content)?;
+        config.rules.extend(file_config.rules);
+        config.warnings_as_errors |= file_config.warnings_as_errors;
+        config.sanitizers.extend(file_config.sanitizers);
     }
+
+    Ok(config)
 }
 
 fn print_issues_text(issues: &[LintIssue], show_warnings: bool) {
@@ -619,7 +787,7 @@ fn print_issues_text(issues: &[LintIssue], show_warnings: bool) {
                     issue.rule
                 );
             }
-            _ => {}
+            _ => continue,
         }
 
         if let Some(suggestion) = &issue.suggestion {
@@ -684,8 +852,27 @@ fn print_issues_json(issues: &[LintIssue]) -> Result<()> {
     Ok(())
 }
 
+fn log_issues(issues: &[LintIssue]) {
+    for issue in issues {
+        let severity = match issue.level {
+            LintLevel::Error => Severity::Error,
+            LintLevel::Warning => Severity::Warning,
+            LintLevel::Info => Severity::Note,
+        };
+        let mut message = format!("{} [{}]", issue.message, issue.rule);
+        if let Some(suggestion) = &issue.suggestion {
+            message = format!("{} (help: {})", message, suggestion);
+        }
+        Event::new("veyra-lint", severity, message)
+            .in_file(&issue.file)
+            .at(issue.line, issue.column)
+            .emit();
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let started = Instant::now();
 
     let config = load_config(cli.config.as_deref())?;
     let mut linter = Linter::new(config);
@@ -699,26 +886,34 @@ fn main() -> Result<()> {
 
     let files = collect_veyra_files(&paths, cli.recursive)?;
 
-    if files.is_empty() {
+    if files.is_empty() && !cli.log_json {
         println!("No .vey files found");
         return Ok(());
     }
 
     let mut has_errors = false;
+    let mut issue_count = 0;
 
-    for file in files {
-        if cli.verbose {
+    for file in &files {
+        if cli.verbose && !cli.log_json {
             println!("Linting: {}", file.display());
         }
 
         linter.clear_issues();
 
-        if let Err(e) = linter.lint_file(&file) {
-            eprintln!("Error linting {}: {}", file.display(), e);
+        if let Err(e) = linter.lint_file(file) {
+            if cli.log_json {
+                Event::new("veyra-lint", Severity::Error, e.to_string())
+                    .in_file(file)
+                    .emit();
+            } else {
+                eprintln!("Error linting {}: {}", file.display(), e);
+            }
             continue;
         }
 
         let issues = linter.get_issues();
+        issue_count += issues.len();
 
         // Check for errors
         if issues.iter().any(|issue| issue.level == LintLevel::Error) {
@@ -726,11 +921,23 @@ fn main() -> Result<()> {
         }
 
         match cli.format.as_str() {
+            _ if cli.log_json => log_issues(issues),
             "json" => print_issues_json(issues)?,
             _ => print_issues_text(issues, cli.warnings),
         }
     }
 
+    if cli.log_json {
+        let severity = match has_errors {
+            true => Severity::Error,
+            false => Severity::Note,
+        };
+        let message = format!("{} issues in {} files", issue_count, files.len());
+        Event::new("veyra-lint", severity, message)
+            .took(started.elapsed())
+            .emit();
+    }
+
     if has_errors {
         std::process::exit(1);
     }
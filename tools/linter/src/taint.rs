@@ -0,0 +1,410 @@
+// Taint analysis for the `tainted-*` security lints
+//
+// Values that come from outside the program (command-line arguments,
+// environment variables, data read from the network) are tainted, and so
+// is anything computed from them. A tainted value that reaches a dangerous
+// sink, such as the program `exec` runs, the path `write_file` writes to or
+// the address of a network request, lets whoever controls the input choose
+// what the program does.
+//
+// Taint flows forward through each function's control-flow graph (and the
+// top-level code's) until nothing changes: a variable is tainted where it
+// may hold a tainted value on some path. Passing a value through a
+// sanitizer, such as `int()` or a function the project lists under
+// `sanitizers`, makes it clean. Calls to user functions are not followed:
+// their result is tainted when any argument is, and their parameters start
+// clean. A function may run at any point of the top-level code, so it starts
+// with every top-level variable that is ever tainted.
+
+use std::collections::HashMap;
+use veyra_compiler::{
+    ast::*,
+    flow::{ControlFlowGraph, Terminator},
+    printer,
+};
+
+/// Builtins whose result carries data from outside the program
+const SOURCES: &[&str] = &["args", "env_var", "tcp_request", "http_get_async"];
+
+/// Builtins whose result cannot carry text through from their arguments:
+/// numbers, booleans and type names
+const SANITIZERS: &[&str] = &["int", "float", "bool", "len", "type_of", "implements"];
+
+/// Where a dangerous sink takes its arguments, and the rule that flags it
+struct Sink {
+    name: &'static str,
+    rule: &'static str,
+    /// Arguments checked; `None` for all of them
+    argument: Option<usize>,
+}
+
+const SINKS: &[Sink] = &[
+    Sink {
+        name: "exec",
+        rule: "tainted-exec",
+        argument: None,
+    },
+    Sink {
+        name: "write_file",
+        rule: "tainted-write",
+        argument: Some(0),
+    },
+    Sink {
+        name: "tcp_request",
+        rule: "tainted-request",
+        argument: Some(0),
+    },
+    Sink {
+        name: "http_get_async",
+        rule: "tainted-request",
+        argument: Some(0),
+    },
+];
+
+/// A tainted value passed to the `call_index`-th call of `sink` in the
+/// program (counting that sink's calls in source order)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaintedSink {
+    pub rule: &'static str,
+    pub sink: &'static str,
+    pub call_index: usize,
+    /// The argument, rendered as source
+    pub argument: String,
+    /// The builtin the tainted data came from
+    pub source: &'static str,
+}
+
+/// Tainted variables, each with the source its data came from
+type Taint = HashMap<String, &'static str>;
+
+pub fn find_tainted_sinks(program: &Program, sanitizers: &[String]) -> Vec<TaintedSink> {
+    let mut index = ProgramIndex::default();
+    index.visit_program(program);
+
+    let mut analysis = Analysis {
+        sanitizers,
+        index: &index,
+        found: Vec::new(),
+    };
+
+    let top_level = program.items.iter().filter_map(|item| match item {
+        Item::Statement(statement) => Some(statement),
+        _ => None,
+    });
+    let globals = analysis.check(&ControlFlowGraph::new(top_level), Taint::new());
+
+    for item in &program.items {
+        let functions = match item {
+            Item::Function(func) => std::slice::from_ref(func),
+            Item::Impl(imp) => imp.methods.as_slice(),
+            Item::Actor(actor) => actor.methods.as_slice(),
+            _ => &[],
+        };
+        for func in functions {
+            let mut entry = globals.clone();
+            for param in &func.parameters {
+                entry.remove(&param.name);
+            }
+            analysis.check(&ControlFlowGraph::function(func), entry);
+        }
+    }
+
+    analysis
+        .found
+        .sort_by(|a, b| (a.sink, a.call_index).cmp(&(b.sink, b.call_index)));
+    analysis.found.dedup();
+    analysis.found
+}
+
+struct Analysis<'a> {
+    sanitizers: &'a [String],
+    index: &'a ProgramIndex,
+    found: Vec<TaintedSink>,
+}
+
+impl Analysis<'_> {
+    /// Report the sinks in a graph that tainted data reaches from `entry`.
+    /// Returns every variable tainted anywhere in the graph.
+    fn check(&mut self, graph: &ControlFlowGraph, entry: Taint) -> Taint {
+        let blocks = &graph.blocks;
+        let mut inputs: Vec<Option<Taint>> = vec![None; blocks.len()];
+        inputs[graph.entry] = Some(entry);
+
+        let mut worklist = vec![graph.entry];
+        while let Some(id) = worklist.pop() {
+            let mut taint = inputs[id].clone().unwrap_or_default();
+            for statement in &blocks[id].statements {
+                self.transfer(statement, &mut taint);
+            }
+            for (position, &successor) in blocks[id].successors.iter().enumerate() {
+                let mut flowing = taint.clone();
+                self.bind(&blocks[id].terminator, position, &mut flowing);
+                // Taint only ever grows, so a block needs another look
+                // whenever more of it arrives
+                let changed = match &mut inputs[successor] {
+                    Some(input) => {
+                        let before = input.len();
+                        for (name, source) in flowing {
+                            input.entry(name).or_insert(source);
+                        }
+                        input.len() != before
+                    }
+                    empty => {
+                        *empty = Some(flowing);
+                        true
+                    }
+                };
+                if changed && !worklist.contains(&successor) {
+                    worklist.push(successor);
+                }
+            }
+        }
+
+        // Taint is settled; look for sinks with what holds at each statement
+        let mut everywhere = Taint::new();
+        for (id, block) in blocks.iter().enumerate() {
+            let Some(mut taint) = inputs[id].clone() else {
+                continue;
+            };
+            everywhere.extend(taint.clone());
+            for statement in &block.statements {
+                match statement {
+                    Statement::VariableDeclaration(var) => self.sinks(&var.initializer, &taint),
+                    Statement::Assignment(assign) => {
+                        self.sinks(&assign.target, &taint);
+                        self.sinks(&assign.value, &taint);
+                    }
+                    Statement::Expression(stmt) => self.sinks(&stmt.expression, &taint),
+                    _ => {}
+                }
+                self.transfer(statement, &mut taint);
+                everywhere.extend(taint.clone());
+            }
+            match block.terminator {
+                Terminator::Branch(expr)
+                | Terminator::Match(expr)
+                | Terminator::Throw(expr)
+                | Terminator::Return(Some(expr)) => self.sinks(expr, &taint),
+                Terminator::Iterate(stmt) => self.sinks(&stmt.iterable, &taint),
+                _ => {}
+            }
+        }
+        everywhere
+    }
+
+    /// Update the tainted variables for a statement that has run
+    fn transfer(&self, statement: &Statement, taint: &mut Taint) {
+        match statement {
+            Statement::VariableDeclaration(var) => {
+                let source = self.source(&var.initializer, taint);
+                for name in var.names() {
+                    set(taint, name, source);
+                }
+            }
+            Statement::Assignment(assign) => {
+                let source = self.source(&assign.value, taint);
+                match (&assign.target, &assign.operator, source) {
+                    (Expression::Identifier(name), AssignmentOperator::Assign, None) => {
+                        taint.remove(name);
+                    }
+                    // Writing into part of a value, or combining with it, keeps
+                    // whatever taint the rest of it had
+                    (target, _, Some(source)) => {
+                        if let Some(name) = root_variable(target) {
+                            taint.entry(name).or_insert(source);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // `push(items, value)` puts the value in the array
+            Statement::Expression(stmt) => {
+                let Expression::Call(call) = &stmt.expression else {
+                    return;
+                };
+                let is_push =
+                    matches!(call.callee.as_ref(), Expression::Identifier(name) if name == "push");
+                if let (true, [target, value]) = (is_push, call.arguments.as_slice()) {
+                    if let (Some(name), Some(source)) =
+                        (root_variable(target), self.source(value, taint))
+                    {
+                        taint.entry(name).or_insert(source);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Bind the variables a terminator declares on the way to its
+    /// `position`-th successor: a `for` loop's variable, or a match arm's
+    fn bind(&self, terminator: &Terminator, position: usize, taint: &mut Taint) {
+        match terminator {
+            Terminator::Iterate(stmt) if position == 0 => {
+                let source = self.source(&stmt.iterable, taint);
+                set(taint, &stmt.variable, source);
+            }
+            Terminator::Match(expr) => {
+                let arm = self
+                    .index
+                    .arms
+                    .get(&(*expr as *const Expression))
+                    .and_then(|arms| arms.get(position));
+                if let Some(bindings) = arm {
+                    let source = self.source(expr, taint);
+                    for name in bindings {
+                        set(taint, name, source);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Where tainted data in an expression comes from, if it has any
+    fn source(&self, expr: &Expression, taint: &Taint) -> Option<&'static str> {
+        let mut search = Search {
+            taint,
+            sanitizers: self.sanitizers,
+            found: None,
+        };
+        search.visit_expression(expr);
+        search.found
+    }
+
+    /// Report the sinks in an expression that receive tainted data
+    fn sinks(&mut self, expr: &Expression, taint: &Taint) {
+        let mut check = SinkCheck {
+            analysis: self,
+            taint,
+        };
+        check.visit_expression(expr);
+    }
+}
+
+fn set(taint: &mut Taint, name: &str, source: Option<&'static str>) {
+    match source {
+        Some(source) => taint.insert(name.to_string(), source),
+        None => taint.remove(name),
+    };
+}
+
+/// Sink calls numbered in source order, and the variables each match arm
+/// binds, found by address since the control-flow graph refers into the AST
+#[derive(Default)]
+struct ProgramIndex {
+    calls: HashMap<*const CallExpression, usize>,
+    counts: HashMap<&'static str, usize>,
+    arms: HashMap<*const Expression, Vec<Vec<String>>>,
+}
+
+impl Visitor for ProgramIndex {
+    fn visit_statement(&mut self, statement: &Statement) {
+        if let Statement::Match(stmt) = statement {
+            let arms = stmt
+                .arms
+                .iter()
+                .map(|arm| {
+                    arm.pattern
+                        .bindings()
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect()
+                })
+                .collect();
+            self.arms.insert(&stmt.expression, arms);
+        }
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        if let Expression::Call(call) = expr {
+            if let Some(sink) = sink(call) {
+                let count = self.counts.entry(sink.name).or_default();
+                self.calls.insert(call, *count);
+                *count += 1;
+            }
+        }
+        walk_expression(self, expr);
+    }
+}
+
+fn sink(call: &CallExpression) -> Option<&'static Sink> {
+    match call.callee.as_ref() {
+        Expression::Identifier(name) => SINKS.iter().find(|sink| sink.name == name),
+        _ => None,
+    }
+}
+
+/// Looks for a tainted variable or a source call, without looking inside
+/// sanitizers or functions that have not been called
+struct Search<'a> {
+    taint: &'a Taint,
+    sanitizers: &'a [String],
+    found: Option<&'static str>,
+}
+
+impl Visitor for Search<'_> {
+    fn visit_expression(&mut self, expr: &Expression) {
+        if self.found.is_some() {
+            return;
+        }
+        match expr {
+            Expression::Identifier(name) => self.found = self.taint.get(name).copied(),
+            Expression::Call(call) => match call.callee.as_ref() {
+                Expression::Identifier(name) if SOURCES.contains(&name.as_str()) => {
+                    self.found = SOURCES.iter().find(|source| **source == name).copied();
+                }
+                Expression::Identifier(name)
+                    if SANITIZERS.contains(&name.as_str())
+                        || name.starts_with("is_")
+                        || self.sanitizers.contains(name) => {}
+                _ => walk_expression(self, expr),
+            },
+            Expression::Lambda(_) => {}
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+/// Checks the sink calls in an expression
+struct SinkCheck<'a, 'b> {
+    analysis: &'a mut Analysis<'b>,
+    taint: &'a Taint,
+}
+
+impl Visitor for SinkCheck<'_, '_> {
+    fn visit_expression(&mut self, expr: &Expression) {
+        if let Expression::Call(call) = expr {
+            if let Some(sink) = sink(call) {
+                let checked = match sink.argument {
+                    Some(index) => call.arguments.get(index..=index).unwrap_or_default(),
+                    None => call.arguments.as_slice(),
+                };
+                for argument in checked {
+                    if let Some(source) = self.analysis.source(argument, self.taint) {
+                        self.analysis.found.push(TaintedSink {
+                            rule: sink.rule,
+                            sink: sink.name,
+                            call_index: self.analysis.index.calls[&(call as *const _)],
+                            argument: printer::expression_to_source(argument),
+                            source,
+                        });
+                    }
+                }
+            }
+        }
+        walk_expression(self, expr);
+    }
+}
+
+/// The variable an expression writes to, e.g. `a` in `a.b[0]`
+fn root_variable(expr: &Expression) -> Option<String> {
+    match expr {
+        Expression::Identifier(name) => Some(name.clone()),
+        Expression::Index(index) => root_variable(&index.object),
+        Expression::FieldAccess(field) => root_variable(&field.object),
+        Expression::Unary(unary) => root_variable(&unary.operand),
+        _ => None,
+    }
+}
@@ -0,0 +1,140 @@
+// Tests for the tainted-exec, tainted-write and tainted-request lints
+
+use std::fs;
+use std::process::Command;
+
+/// `(rule, line, message)` of each taint issue veyra-lint reports for
+/// `source`, with `config` as the lint config file when given
+fn taint_issues(source: &str, config: Option<&str>) -> Vec<(String, u64, String)> {
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let file = dir.join("main.vey");
+    fs::write(&file, source).unwrap();
+    if let Some(config) = config {
+        fs::write(dir.join(".veyra-lint.json"), config).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-lint"))
+        .args(["--format", "json", "main.vey"])
+        .current_dir(dir)
+        .output()
+        .unwrap();
+
+    let issues: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "veyra-lint did not print JSON ({}): {}",
+            e,
+            String::from_utf8_lossy(&output.stderr)
+        )
+    });
+    issues
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|issue| issue["rule"].as_str().unwrap().starts_with("tainted-"))
+        .map(|issue| {
+            (
+                issue["rule"].as_str().unwrap().to_string(),
+                issue["line"].as_u64().unwrap(),
+                issue["message"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+fn rules_and_lines(issues: &[(String, u64, String)]) -> Vec<(&str, u64)> {
+    issues
+        .iter()
+        .map(|(rule, line, _)| (rule.as_str(), *line))
+        .collect()
+}
+
+#[test]
+fn flags_input_reaching_each_kind_of_sink() {
+    let issues = taint_issues(
+        "let argv = args()\n\
+         let name = argv[0]\n\
+         exec(\"ls\", [name])\n\
+         write_file(\"out-\" + name, \"data\")\n\
+         write_file(\"out.txt\", name)\n\
+         let host = env_var(\"HOST\")\n\
+         let reply = tcp_request(host, \"PING\")\n\
+         let _page = await http_get_async(\"http://\" + reply)\n",
+        None,
+    );
+    let mut found = rules_and_lines(&issues);
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            ("tainted-exec", 3),
+            ("tainted-request", 7),
+            ("tainted-request", 8),
+            ("tainted-write", 4),
+        ]
+    );
+    assert!(issues.iter().any(|(_, line, message)| *line == 3
+        && message == "'[name]' passed to exec() comes from args() and is not sanitized"));
+}
+
+#[test]
+fn follows_taint_along_control_flow() {
+    let issues = taint_issues(
+        "let argv = args()\n\
+         let command = \"date\"\n\
+         exec(command)\n\
+         if len(argv) > 0 {\n\
+         \x20   command = argv[0]\n\
+         }\n\
+         exec(command)\n\
+         command = \"date\"\n\
+         exec(command)\n\
+         let parts = []\n\
+         for arg in argv {\n\
+         \x20   push(parts, arg)\n\
+         }\n\
+         exec(\"echo\", parts)\n\
+         match argv[0] {\n\
+         \x20   first -> exec(first)\n\
+         }\n",
+        None,
+    );
+    assert_eq!(
+        rules_and_lines(&issues),
+        vec![
+            ("tainted-exec", 7),
+            ("tainted-exec", 14),
+            ("tainted-exec", 16)
+        ]
+    );
+}
+
+#[test]
+fn reaches_into_functions_through_globals_but_not_parameters() {
+    let issues = taint_issues(
+        "let target = args()[0]\n\
+         fn deploy(host) {\n\
+         \x20   exec(\"ssh\", [host])\n\
+         \x20   exec(\"ssh\", [target])\n\
+         }\n\
+         deploy(\"localhost\")\n",
+        None,
+    );
+    assert_eq!(rules_and_lines(&issues), vec![("tainted-exec", 4)]);
+}
+
+#[test]
+fn sanitizers_make_input_clean() {
+    let source = "let argv = args()\n\
+                  let count = int(argv[0])\n\
+                  exec(\"seq\", [str(count)])\n\
+                  exec(\"echo\", [shell_quote(argv[1])])\n\
+                  fn shell_quote(s) {\n\
+                  \x20   return \"'\" + s + \"'\"\n\
+                  }\n";
+    assert_eq!(
+        rules_and_lines(&taint_issues(source, None)),
+        vec![("tainted-exec", 4)]
+    );
+    assert!(taint_issues(source, Some(r#"{"sanitizers": ["shell_quote"]}"#)).is_empty());
+}
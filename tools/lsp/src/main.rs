@@ -2,39 +2,70 @@ use anyhow::Result;
 use dashmap::DashMap;
 use ropey::Rope;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use tower_lsp::jsonrpc::Result as LspResult;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
+use workspace::Workspace;
+
+mod workspace;
 
 // Import from the main compiler
 use veyra_compiler::{
     ast::*,
+    deprecation,
     error::VeyraError,
+    include,
+    incremental::Document,
     lexer::{Lexer, Token, TokenKind},
     parser::Parser as VeyraParser,
+    resolve::{self, SymbolId, SymbolTable},
+    typeck,
 };
 
+/// Diagnostic code for uses of names that are not defined
+const UNDEFINED_NAME: &str = "undefined-name";
+
+/// Diagnostic code for uses of functions and structs marked @deprecated
+const DEPRECATED: &str = "deprecated";
+
 #[derive(Debug)]
 struct DocumentInfo {
+    uri: Url,
     rope: Rope,
     version: i32,
     diagnostics: Vec<Diagnostic>,
     symbols: Vec<DocumentSymbol>,
     tokens: Vec<Token>,
+    /// The program when the document is not kept parsed edit by edit, or
+    /// has syntax errors
     ast: Option<Program>,
+    /// The program kept up to date edit by edit, reparsing only the items
+    /// an edit touches, once the document has parsed without errors
+    parsed: Option<Document>,
+    /// Names defined and used in the document, when it parses
+    names: Option<SymbolTable>,
+    /// Declared and inferred types of variables and signatures of
+    /// functions, by the offset of their defining name, when it parses
+    types: HashMap<usize, String>,
 }
 
 impl DocumentInfo {
-    fn new(text: String, version: i32) -> Self {
+    fn new(uri: Url, text: String, version: i32) -> Self {
         let rope = Rope::from_str(&text);
         let mut info = Self {
+            uri,
             rope,
             version,
             diagnostics: Vec::new(),
             symbols: Vec::new(),
             tokens: Vec::new(),
             ast: None,
+            parsed: None,
+            names: None,
+            types: HashMap::new(),
         };
         info.analyze();
         info
@@ -45,15 +76,19 @@ impl DocumentInfo {
 
         for change in changes {
             if let Some(range) = change.range {
-                let start_idx = self.rope.line_to_char(range.start.line as usize)
-                    + range.start.character as usize;
-                let end_idx =
-                    self.rope.line_to_char(range.end.line as usize) + range.end.character as usize;
+                let start_idx = self.char_index(range.start);
+                let end_idx = self.char_index(range.end);
                 self.rope.remove(start_idx..end_idx);
                 self.rope.insert(start_idx, &change.text);
+                if let Some(parsed) = &mut self.parsed {
+                    // A syntax error is reported by analyze, which then
+                    // parses the whole document
+                    let _ = parsed.edit(start_idx..end_idx, &change.text);
+                }
             } else {
                 // Full document update
                 self.rope = Rope::from_str(&change.text);
+                self.parsed = None;
             }
         }
 
@@ -66,46 +101,123 @@ impl DocumentInfo {
         self.symbols.clear();
         self.tokens.clear();
         self.ast = None;
+        self.names = None;
+        self.types.clear();
+
+        // Tokenize once, keeping comments for the documentation of
+        // declarations and each token's range for the passes over names
+        let (ranged, error) = Lexer::new(&text).tokenize_with_ranges();
+        self.tokens = ranged
+            .iter()
+            .map(|(token, _)| token)
+            .filter(|token| token.kind != TokenKind::Comment)
+            .cloned()
+            .collect();
+        if let Some((error, _)) = error {
+            self.add_diagnostic_from_error(&error);
+            return;
+        }
 
-        // Tokenize
-        let mut lexer = Lexer::new(&text);
-        match lexer.tokenize() {
-            Ok(tokens) => {
-                self.tokens = tokens.clone();
-
-                // Parse
-                let mut parser = VeyraParser::new(tokens);
-                match parser.parse() {
-                    Ok(ast) => {
-                        self.ast = Some(ast.clone());
-                        self.extract_symbols(&ast);
-                    }
+        // Included files are not part of the text, so documents with
+        // includes are parsed whole
+        let incremental = !include::has_includes(&self.tokens);
+
+        // Parse, keeping whatever parsed around syntax errors so the
+        // outline and navigation still work while editing. A document kept
+        // up to date edit by edit has nothing left to parse.
+        let mut errors = Vec::new();
+        let up_to_date = self
+            .parsed
+            .as_ref()
+            .is_some_and(|parsed| !parsed.has_error());
+        if !(incremental && up_to_date) {
+            let mut tokens: Vec<Token> = ranged.iter().map(|(token, _)| token.clone()).collect();
+            tokens.push(self.end_of_text());
+
+            // Expand include! relative to the document before parsing
+            if let (false, Ok(path)) = (incremental, self.uri.to_file_path()) {
+                let dir = path.parent().unwrap_or(std::path::Path::new("."));
+                tokens = match include::expand_includes(tokens, dir) {
+                    Ok(tokens) => tokens,
                     Err(e) => {
                         self.add_diagnostic_from_error(&e);
+                        return;
                     }
-                }
+                };
             }
-            Err(e) => {
-                self.add_diagnostic_from_error(&e);
+
+            let (ast, parse_errors) = VeyraParser::new(tokens).parse_recovering();
+            errors = parse_errors;
+            self.parsed = (incremental && errors.is_empty())
+                .then(|| Document::parse(&text).ok())
+                .flatten();
+            if self.parsed.is_none() {
+                self.ast = Some(ast);
+            }
+        }
+        for error in &errors {
+            self.add_diagnostic_from_error(error);
+        }
+
+        // The program is borrowed from the document while the passes over
+        // it fill in the rest
+        let (ast, parsed) = (self.ast.take(), self.parsed.take());
+        if let Some(program) = ast.as_ref().or(parsed.as_ref().map(Document::program)) {
+            self.extract_symbols(program);
+            let names = resolve::resolve(program, &ranged);
+            // Skipped code may define the names that look undefined
+            if errors.is_empty() {
+                self.add_undefined_name_diagnostics(&names);
+                self.types = typeck::analyze(program, &ranged).types;
             }
+            self.add_deprecation_diagnostics(&ranged, program);
+            self.names = Some(names);
+        }
+        (self.ast, self.parsed) = (ast, parsed);
+    }
+
+    /// The program parsed from the document, as far as it parses
+    fn program(&self) -> Option<&Program> {
+        self.ast
+            .as_ref()
+            .or(self.parsed.as_ref().map(Document::program))
+    }
+
+    /// The token ending the document's tokens, which the parser expects
+    fn end_of_text(&self) -> Token {
+        let last = self.rope.len_lines() - 1;
+        Token {
+            kind: TokenKind::Eof,
+            lexeme: String::new(),
+            line: last + 1,
+            column: self.rope.line(last).len_chars() + 1,
         }
     }
 
     fn add_diagnostic_from_error(&mut self, error: &VeyraError) {
-        // Convert VeyraError to LSP Diagnostic
+        // Convert VeyraError to LSP Diagnostic, at the reported line and
+        // column (both counted from 1) when there is one
+        let start = match error {
+            VeyraError::LexError { line, column, .. }
+            | VeyraError::ParseError { line, column, .. } => Position {
+                line: line.saturating_sub(1) as u32,
+                character: column.saturating_sub(1) as u32,
+            },
+            _ => Position {
+                line: 0,
+                character: 0,
+            },
+        };
         let diagnostic = Diagnostic {
             range: Range {
-                start: Position {
-                    line: 0, // TODO: Extract line info from error
-                    character: 0,
-                },
+                start,
                 end: Position {
-                    line: 0,
-                    character: 0,
+                    line: start.line,
+                    character: start.character + 1,
                 },
             },
             severity: Some(DiagnosticSeverity::ERROR),
-            code: None,
+            code: Some(NumberOrString::String(error.code().to_string())),
             code_description: None,
             source: Some("veyra".to_string()),
             message: error.to_string(),
@@ -117,6 +229,136 @@ impl DocumentInfo {
         self.diagnostics.push(diagnostic);
     }
 
+    /// Report undefined identifiers with "did you mean" suggestions. The
+    /// suggestions are kept in the diagnostic data for the quick fix.
+    fn add_undefined_name_diagnostics(&mut self, names: &SymbolTable) {
+        for reference in names.undefined() {
+            let Some(span) = &reference.span else {
+                continue;
+            };
+
+            let visible = names.visible_names(reference.scope).into_iter().chain(
+                veyra_compiler::builtins::BUILTINS
+                    .iter()
+                    .map(|doc| doc.name),
+            );
+            let suggestions = veyra_compiler::suggest::similar_names(&reference.name, visible);
+
+            let related: Vec<DiagnosticRelatedInformation> = suggestions
+                .iter()
+                .filter_map(|suggestion| {
+                    let symbol = names.symbol(names.lookup(reference.scope, suggestion)?);
+                    Some(DiagnosticRelatedInformation {
+                        location: Location::new(
+                            self.uri.clone(),
+                            self.char_range(symbol.span.as_ref()?),
+                        ),
+                        message: format!("'{}' is defined here", suggestion),
+                    })
+                })
+                .collect();
+
+            let message = format!(
+                "Undefined name '{}'{}",
+                reference.name,
+                veyra_compiler::suggest::did_you_mean(&suggestions)
+            );
+
+            self.diagnostics.push(Diagnostic {
+                range: self.char_range(span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(UNDEFINED_NAME.to_string())),
+                code_description: None,
+                source: Some("veyra".to_string()),
+                message,
+                related_information: (!related.is_empty()).then_some(related),
+                tags: None,
+                data: Some(serde_json::json!({ "suggestions": suggestions })),
+            });
+        }
+    }
+
+    /// Report uses of deprecated items, tagged so editors strike them through
+    fn add_deprecation_diagnostics(
+        &mut self,
+        tokens: &[(Token, std::ops::Range<usize>)],
+        program: &Program,
+    ) {
+        let deprecations = deprecation::deprecations(program);
+        if deprecations.is_empty() {
+            return;
+        }
+
+        for (index, range) in deprecation::uses(tokens, &deprecations) {
+            self.diagnostics.push(Diagnostic {
+                range: self.char_range(&range),
+                severity: Some(DiagnosticSeverity::WARNING),
+                code: Some(NumberOrString::String(DEPRECATED.to_string())),
+                code_description: None,
+                source: Some("veyra".to_string()),
+                message: deprecations[index].message(),
+                related_information: None,
+                tags: Some(vec![DiagnosticTag::DEPRECATED]),
+                data: None,
+            });
+        }
+    }
+
+    /// The symbol defined or used at `position`
+    fn symbol_at(&self, position: Position) -> Option<(&SymbolTable, SymbolId)> {
+        let names = self.names.as_ref()?;
+        let line = position.line as usize;
+        if line >= self.rope.len_lines() {
+            return None;
+        }
+        Some((names, names.symbol_at(self.char_index(position))?))
+    }
+
+    /// The type of the variable or the signature of the function defined
+    /// or used at `position`, when the type checker knows it
+    fn type_at(&self, position: Position) -> Option<&str> {
+        let (names, id) = self.symbol_at(position)?;
+        let span = names.symbol(id).span.as_ref()?;
+        self.types.get(&span.start).map(String::as_str)
+    }
+
+    /// Ranges of a symbol's definition and its uses in this document. The
+    /// definition comes first; it is missing for implicit names like `self`.
+    fn occurrences(&self, names: &SymbolTable, id: SymbolId) -> (Option<Range>, Vec<Range>) {
+        let definition = names
+            .symbol(id)
+            .span
+            .as_ref()
+            .map(|span| self.char_range(span));
+        let uses = names
+            .references_to(id)
+            .filter_map(|reference| reference.span.as_ref())
+            .map(|span| self.char_range(span))
+            .collect();
+        (definition, uses)
+    }
+
+    fn char_range(&self, range: &std::ops::Range<usize>) -> Range {
+        Range {
+            start: self.char_position(range.start),
+            end: self.char_position(range.end),
+        }
+    }
+
+    /// The LSP position of a char index
+    fn char_position(&self, char_idx: usize) -> Position {
+        workspace::position(&self.rope, char_idx)
+    }
+
+    /// The char index of an LSP position, the reverse of `char_position`.
+    /// Positions past the end of the document are clamped to it.
+    fn char_index(&self, position: Position) -> usize {
+        let line = (position.line as usize).min(self.rope.len_lines());
+        let line_start = self.rope.char_to_utf16_cu(self.rope.line_to_char(line));
+        let utf16 = (line_start + position.character as usize).min(self.rope.len_utf16_cu());
+        self.rope.utf16_cu_to_char(utf16)
+    }
+
     fn extract_symbols(&mut self, program: &Program) {
         for item in &program.items {
             self.extract_symbol_from_item(item);
@@ -130,7 +372,7 @@ impl DocumentInfo {
                     name: func.name.clone(),
                     detail: Some(format!("function({} parameters)", func.parameters.len())),
                     kind: SymbolKind::FUNCTION,
-                    tags: None,
+                    tags: deprecated_tag(func),
                     #[allow(deprecated)]
                     deprecated: None,
                     range: Range {
@@ -157,49 +399,81 @@ impl DocumentInfo {
                 };
                 self.symbols.push(symbol);
             }
-            Item::Statement(Statement::VariableDeclaration(var_decl)) => {
+            Item::Struct(s) => {
                 let symbol = DocumentSymbol {
-                    name: var_decl.name.clone(),
-                    detail: Some("variable".to_string()),
-                    kind: SymbolKind::VARIABLE,
-                    tags: None,
+                    name: s.name.clone(),
+                    detail: Some(format!("struct({} fields)", s.fields.len())),
+                    kind: SymbolKind::STRUCT,
+                    tags: deprecated_tag(s),
                     #[allow(deprecated)]
                     deprecated: None,
-                    range: Range {
-                        start: Position {
-                            line: 0,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: 0,
-                            character: 0,
-                        },
-                    },
-                    selection_range: Range {
-                        start: Position {
-                            line: 0,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: 0,
-                            character: 0,
-                        },
-                    },
+                    range: Range::default(),
+                    selection_range: Range::default(),
                     children: None,
                 };
                 self.symbols.push(symbol);
             }
+            Item::Statement(Statement::VariableDeclaration(var_decl)) => {
+                for name in var_decl.names() {
+                    let symbol = DocumentSymbol {
+                        name: name.to_string(),
+                        detail: Some("variable".to_string()),
+                        kind: SymbolKind::VARIABLE,
+                        tags: None,
+                        #[allow(deprecated)]
+                        deprecated: None,
+                        range: Range {
+                            start: Position {
+                                line: 0,
+                                character: 0,
+                            },
+                            end: Position {
+                                line: 0,
+                                character: 0,
+                            },
+                        },
+                        selection_range: Range {
+                            start: Position {
+                                line: 0,
+                                character: 0,
+                            },
+                            end: Position {
+                                line: 0,
+                                character: 0,
+                            },
+                        },
+                        children: None,
+                    };
+                    self.symbols.push(symbol);
+                }
+            }
             _ => {}
         }
     }
 
+    /// The comments on the lines before the top-level declaration of
+    /// `name`, which document it
+    fn documentation(&self, name: &str) -> Option<String> {
+        let comments = self.program()?.items.iter().find_map(|item| match item {
+            Item::Function(func) if func.name == name => Some(&func.comments),
+            Item::Struct(s) if s.name == name => Some(&s.comments),
+            Item::Actor(actor) if actor.name == name => Some(&actor.comments),
+            _ => None,
+        })?;
+        let lines: Vec<&str> = comments
+            .iter()
+            .filter(|comment| !comment.trailing)
+            .map(Comment::content)
+            .collect();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
     fn get_text_at_position(&self, position: Position) -> Option<String> {
         let line_idx = position.line as usize;
-        let char_idx = position.character as usize;
-
         if line_idx >= self.rope.len_lines() {
             return None;
         }
+        let char_idx = self.char_index(position) - self.rope.line_to_char(line_idx);
 
         let line = self.rope.line(line_idx);
         let line_str = line.to_string();
@@ -232,9 +506,56 @@ impl DocumentInfo {
     }
 }
 
+/// Symbol tags for an item: DEPRECATED when it is marked @deprecated
+fn deprecated_tag(item: &impl Attributed) -> Option<Vec<SymbolTag>> {
+    item.has_attribute("deprecated")
+        .then(|| vec![SymbolTag::DEPRECATED])
+}
+
+/// Quick fixes replacing an undefined name with one of its suggestions
+fn undefined_name_fixes(uri: &Url, diagnostic: &Diagnostic) -> Vec<CodeActionOrCommand> {
+    if diagnostic.code != Some(NumberOrString::String(UNDEFINED_NAME.to_string())) {
+        return Vec::new();
+    }
+
+    let suggestions = diagnostic
+        .data
+        .as_ref()
+        .and_then(|data| data.get("suggestions"))
+        .and_then(|list| list.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    suggestions
+        .iter()
+        .filter_map(|suggestion| suggestion.as_str())
+        .enumerate()
+        .map(|(i, suggestion)| {
+            let edit = TextEdit {
+                range: diagnostic.range,
+                new_text: suggestion.to_string(),
+            };
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Change to '{}'", suggestion),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic.clone()]),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                    ..Default::default()
+                }),
+                is_preferred: Some(i == 0),
+                ..Default::default()
+            })
+        })
+        .collect()
+}
+
 struct VeyraLanguageServer {
     client: Client,
     documents: Arc<DashMap<Url, DocumentInfo>>,
+    workspace: Arc<RwLock<Workspace>>,
+    /// Whether the client lets us register file watchers
+    watch_files: Arc<AtomicBool>,
 }
 
 impl VeyraLanguageServer {
@@ -242,15 +563,106 @@ impl VeyraLanguageServer {
         Self {
             client,
             documents: Arc::new(DashMap::new()),
+            workspace: Arc::new(RwLock::new(Workspace::default())),
+            watch_files: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Publish a document's diagnostics with the settings of its workspace root
     async fn publish_diagnostics(&self, uri: Url, diagnostics: Vec<Diagnostic>) {
+        let diagnostics = match self.workspace.read().unwrap().root_for(&uri) {
+            Some(root) => root.configure(diagnostics),
+            None => diagnostics,
+        };
         self.client
             .publish_diagnostics(uri, diagnostics, None)
             .await;
     }
 
+    /// Publish the diagnostics of every open document again, after the
+    /// workspace folders or their configuration changed
+    async fn republish_diagnostics(&self) {
+        let documents: Vec<(Url, Vec<Diagnostic>)> = self
+            .documents
+            .iter()
+            .map(|document| (document.uri.clone(), document.diagnostics.clone()))
+            .collect();
+        for (uri, diagnostics) in documents {
+            self.publish_diagnostics(uri, diagnostics).await;
+        }
+    }
+
+    /// Analyze every open document again, since a file it includes may have
+    /// changed on disk
+    fn reanalyze_documents(&self) {
+        for mut document in self.documents.iter_mut() {
+            document.analyze();
+        }
+    }
+
+    /// Ask the client to tell us about source and configuration files that
+    /// change outside the editor, e.g. on a git checkout
+    async fn register_file_watchers(&self) {
+        let watcher = |pattern: &str| FileSystemWatcher {
+            glob_pattern: GlobPattern::String(pattern.to_string()),
+            kind: None,
+        };
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![watcher("**/*.vey"), watcher("**/veyra.toml")],
+        };
+        let registration = Registration {
+            id: "veyra-watched-files".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Could not watch workspace files: {}", e),
+                )
+                .await;
+        }
+    }
+
+    /// Keep the workspace index in step with an open document
+    fn index_document(&self, uri: &Url, text: &str) {
+        if let Some(root) = self.workspace.write().unwrap().root_for_mut(uri) {
+            root.index_file(uri.clone(), text);
+        }
+    }
+
+    /// Definitions of a name in other files of the document's workspace root
+    fn workspace_definitions(&self, uri: &Url, name: &str) -> Vec<Location> {
+        let workspace = self.workspace.read().unwrap();
+        let Some(root) = workspace.root_for(uri) else {
+            return Vec::new();
+        };
+        root.definitions(name)
+            .filter(|symbol| symbol.location.uri != *uri)
+            .map(|symbol| symbol.location.clone())
+            .collect()
+    }
+
+    /// Formatter settings from the `[fmt]` section of the project the
+    /// document belongs to: its workspace root's, or for a file outside the
+    /// workspace the one found above it
+    fn format_config(&self, uri: &Url) -> Result<veyra_fmt::FormatterConfig> {
+        let fmt = match self.workspace.read().unwrap().root_for(uri) {
+            Some(root) => root
+                .project
+                .as_ref()
+                .map(|project| project.config.fmt.clone()),
+            None => match uri.to_file_path() {
+                Ok(path) => {
+                    veyra_config::Project::discover(&path)?.map(|project| project.config.fmt)
+                }
+                Err(()) => None,
+            },
+        };
+        veyra_fmt::project_config(&fmt.unwrap_or_default())
+    }
+
     fn get_completions_for_context(&self, _uri: &Url, _position: Position) -> Vec<CompletionItem> {
         // Built-in keywords
         let mut completions = vec![
@@ -303,6 +715,7 @@ impl VeyraLanguageServer {
             ("print", "Print a value to stdout"),
             ("len", "Get the length of an array or string"),
             ("str", "Convert a value to string"),
+            ("format", "Fill in the {} placeholders of a format string"),
             ("push", "Add an element to an array"),
             ("pop", "Remove and return the last element of an array"),
         ];
@@ -327,7 +740,32 @@ impl VeyraLanguageServer {
 
 #[tower_lsp::async_trait]
 impl LanguageServer for VeyraLanguageServer {
-    async fn initialize(&self, _: InitializeParams) -> LspResult<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
+        let watch_files = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.did_change_watched_files.as_ref())
+            .and_then(|watched| watched.dynamic_registration)
+            .unwrap_or(false);
+        self.watch_files.store(watch_files, Ordering::Relaxed);
+
+        // Index each workspace folder and pick up its veyra.toml; clients
+        // without workspace folders send a single root
+        #[allow(deprecated)]
+        let folders: Vec<Url> = match params.workspace_folders {
+            Some(folders) if !folders.is_empty() => {
+                folders.into_iter().map(|folder| folder.uri).collect()
+            }
+            _ => params.root_uri.into_iter().collect(),
+        };
+        {
+            let mut workspace = self.workspace.write().unwrap();
+            for uri in folders {
+                workspace.add(uri);
+            }
+        }
+
         Ok(InitializeResult {
             server_info: Some(ServerInfo {
                 name: "Veyra Language Server".to_string(),
@@ -352,6 +790,13 @@ impl LanguageServer for VeyraLanguageServer {
                 }),
                 document_symbol_provider: Some(OneOf::Left(true)),
                 workspace_symbol_provider: Some(OneOf::Left(true)),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
                 definition_provider: Some(OneOf::Left(true)),
                 references_provider: Some(OneOf::Left(true)),
                 document_highlight_provider: Some(OneOf::Left(true)),
@@ -371,6 +816,7 @@ impl LanguageServer for VeyraLanguageServer {
                                     SemanticTokenType::FUNCTION,
                                     SemanticTokenType::VARIABLE,
                                     SemanticTokenType::COMMENT,
+                                    SemanticTokenType::DECORATOR,
                                 ],
                                 token_modifiers: vec![],
                             },
@@ -388,6 +834,61 @@ impl LanguageServer for VeyraLanguageServer {
         self.client
             .log_message(MessageType::INFO, "Veyra Language Server initialized!")
             .await;
+
+        let messages: Vec<String> = self
+            .workspace
+            .read()
+            .unwrap()
+            .roots()
+            .iter()
+            .map(|root| match &root.project {
+                Some(project) => format!(
+                    "Workspace folder {} uses project configuration from {}",
+                    root.path.display(),
+                    project.root.display()
+                ),
+                None => format!("Workspace folder {}", root.path.display()),
+            })
+            .collect();
+        for message in messages {
+            self.client.log_message(MessageType::INFO, message).await;
+        }
+
+        if self.watch_files.load(Ordering::Relaxed) {
+            self.register_file_watchers().await;
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        {
+            let mut workspace = self.workspace.write().unwrap();
+            for folder in &params.event.removed {
+                workspace.remove(&folder.uri);
+            }
+            for folder in params.event.added {
+                workspace.add(folder.uri);
+            }
+        }
+        self.republish_diagnostics().await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        {
+            let mut workspace = self.workspace.write().unwrap();
+            for change in &params.changes {
+                let Some(root) = workspace.root_for_mut(&change.uri) else {
+                    continue;
+                };
+                if change.uri.path().ends_with("/veyra.toml") {
+                    root.reload_project();
+                } else if !self.documents.contains_key(&change.uri) {
+                    // Open documents are indexed from the editor's text
+                    root.reindex_file(&change.uri);
+                }
+            }
+        }
+        self.reanalyze_documents();
+        self.republish_diagnostics().await;
     }
 
     async fn shutdown(&self) -> LspResult<()> {
@@ -399,7 +900,8 @@ impl LanguageServer for VeyraLanguageServer {
         let version = params.text_document.version;
         let text = params.text_document.text;
 
-        let document_info = DocumentInfo::new(text, version);
+        self.index_document(&uri, &text);
+        let document_info = DocumentInfo::new(uri.clone(), text, version);
         let diagnostics = document_info.diagnostics.clone();
 
         self.documents.insert(uri.clone(), document_info);
@@ -413,8 +915,10 @@ impl LanguageServer for VeyraLanguageServer {
         if let Some(mut document) = self.documents.get_mut(&uri) {
             document.update(params.content_changes, version);
             let diagnostics = document.diagnostics.clone();
+            let text = document.rope.to_string();
             drop(document); // Release the lock
 
+            self.index_document(&uri, &text);
             self.publish_diagnostics(uri, diagnostics).await;
         }
     }
@@ -426,7 +930,13 @@ impl LanguageServer for VeyraLanguageServer {
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        self.documents.remove(&params.text_document.uri);
+        let uri = params.text_document.uri;
+        self.documents.remove(&uri);
+
+        // Unsaved edits are gone, so index the file as it is on disk
+        if let Some(root) = self.workspace.write().unwrap().root_for_mut(&uri) {
+            root.reindex_file(&uri);
+        }
     }
 
     async fn completion(&self, params: CompletionParams) -> LspResult<Option<CompletionResponse>> {
@@ -443,9 +953,27 @@ impl LanguageServer for VeyraLanguageServer {
         let position = params.text_document_position_params.position;
 
         if let Some(document) = self.documents.get(&uri) {
+            if let Some(ty) = document.type_at(position) {
+                let mut contents = vec![MarkedString::LanguageString(LanguageString {
+                    language: "veyra".to_string(),
+                    value: ty.to_string(),
+                })];
+                // Functions also show their documentation comments
+                let word = document
+                    .get_text_at_position(position)
+                    .filter(|_| ty.starts_with("fn "));
+                if let Some(text) = word.and_then(|word| document.documentation(&word)) {
+                    contents.push(MarkedString::String(text));
+                }
+                return Ok(Some(Hover {
+                    contents: HoverContents::Array(contents),
+                    range: None,
+                }));
+            }
             if let Some(word) = document.get_text_at_position(position) {
+                let documentation;
                 let hover_content = match word.as_str() {
-                    "print" => "Built-in function: print(value) - Print a value to stdout",
+                    "print" => "Built-in function: print(value, args) - Print values separated by spaces, or a format string filled in with args, to stdout",
                     "len" => {
                         "Built-in function: len(collection) - Get the length of an array or string"
                     }
@@ -455,7 +983,13 @@ impl LanguageServer for VeyraLanguageServer {
                     "if" => "Keyword: if - Conditional statement",
                     "while" => "Keyword: while - While loop",
                     "for" => "Keyword: for - For loop",
-                    _ => return Ok(None),
+                    _ => match document.documentation(&word) {
+                        Some(text) => {
+                            documentation = text;
+                            &documentation
+                        }
+                        None => return Ok(None),
+                    },
                 };
 
                 return Ok(Some(Hover {
@@ -484,19 +1018,71 @@ impl LanguageServer for VeyraLanguageServer {
         Ok(None)
     }
 
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> LspResult<Option<Vec<SymbolInformation>>> {
+        let query = params.query.to_lowercase();
+        let workspace = self.workspace.read().unwrap();
+
+        // Each symbol is labelled with its workspace folder, since several
+        // packages may define the same name
+        let symbols = workspace
+            .roots()
+            .iter()
+            .flat_map(|root| root.symbols().map(move |symbol| (root, symbol)))
+            .filter(|(_, symbol)| symbol.name.to_lowercase().contains(&query))
+            .map(|(root, symbol)| {
+                #[allow(deprecated)]
+                SymbolInformation {
+                    name: symbol.name.clone(),
+                    kind: symbol.kind,
+                    tags: None,
+                    deprecated: None,
+                    location: symbol.location.clone(),
+                    container_name: Some(root.name()),
+                }
+            })
+            .collect();
+
+        Ok(Some(symbols))
+    }
+
     async fn formatting(
         &self,
         params: DocumentFormattingParams,
     ) -> LspResult<Option<Vec<TextEdit>>> {
         let uri = params.text_document.uri;
 
-        if let Some(_document) = self.documents.get(&uri) {
-            // TODO: Implement actual formatting using the formatter tool
-            // For now, return empty (no changes)
+        // The document is copied out so that no lock on it is held while
+        // awaiting the client
+        let Some((text, end)) = self.documents.get(&uri).map(|document| {
+            let end = document.char_position(document.rope.len_chars());
+            (document.rope.to_string(), end)
+        }) else {
+            return Ok(None);
+        };
+        let config = match self.format_config(&uri) {
+            Ok(config) => config,
+            Err(e) => {
+                self.client.log_message(MessageType::WARNING, e).await;
+                return Ok(None);
+            }
+        };
+        // A document that does not parse is left as it is
+        let Ok(formatted) = veyra_fmt::format_source(&text, &config) else {
+            return Ok(None);
+        };
+        if formatted == text {
             return Ok(Some(vec![]));
         }
-
-        Ok(None)
+        Ok(Some(vec![TextEdit {
+            range: Range {
+                start: Position::default(),
+                end,
+            },
+            new_text: formatted,
+        }]))
     }
 
     async fn semantic_tokens_full(
@@ -511,15 +1097,23 @@ impl LanguageServer for VeyraLanguageServer {
             let mut prev_char = 0;
 
             // Convert our tokens to semantic tokens
+            let mut previous_kind = None;
             for token in &document.tokens {
+                let after_at = previous_kind == Some(&TokenKind::At);
+                previous_kind = Some(&token.kind);
                 let token_type = match token.kind {
                     TokenKind::Fn
                     | TokenKind::Let
                     | TokenKind::If
                     | TokenKind::While
                     | TokenKind::For => 0, // KEYWORD
-                    TokenKind::String(_) => 1, // STRING
+                    TokenKind::String(_)
+                    | TokenKind::InterpolationStart(_)
+                    | TokenKind::InterpolationMiddle(_)
+                    | TokenKind::InterpolationEnd(_) => 1, // STRING
                     TokenKind::Integer(_) | TokenKind::Float(_) => 2, // NUMBER
+                    TokenKind::At => 6,                               // DECORATOR
+                    TokenKind::Identifier if after_at => 6,           // attribute name
                     TokenKind::Identifier => {
                         // Determine if it's a function or variable
                         // TODO: More sophisticated analysis
@@ -528,8 +1122,9 @@ impl LanguageServer for VeyraLanguageServer {
                     _ => continue, // Skip other tokens
                 };
 
-                let line = token.line as u32;
-                let character = token.column as u32;
+                // Tokens count lines and columns from 1, LSP from 0
+                let line = token.line.saturating_sub(1) as u32;
+                let character = token.column.saturating_sub(1) as u32;
                 let length = match &token.kind {
                     TokenKind::String(s) => s.len() + 2, // +2 for quotes
                     TokenKind::Integer(_) => token.lexeme.len(),
@@ -588,6 +1183,87 @@ impl LanguageServer for VeyraLanguageServer {
         }
     }
 
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> LspResult<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let word = match self.documents.get(&uri) {
+            Some(document) => {
+                if let Some((names, id)) = document.symbol_at(position) {
+                    if let (Some(definition), _) = document.occurrences(names, id) {
+                        return Ok(Some(GotoDefinitionResponse::Scalar(Location::new(
+                            uri.clone(),
+                            definition,
+                        ))));
+                    }
+                }
+                document.get_text_at_position(position)
+            }
+            None => None,
+        };
+
+        // Names not defined in the document may be defined elsewhere in its
+        // workspace folder
+        let Some(word) = word else {
+            return Ok(None);
+        };
+        let mut locations = self.workspace_definitions(&uri, &word);
+        Ok(match locations.len() {
+            0 => None,
+            1 => locations.pop().map(GotoDefinitionResponse::Scalar),
+            _ => Some(GotoDefinitionResponse::Array(locations)),
+        })
+    }
+
+    async fn references(&self, params: ReferenceParams) -> LspResult<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        if let Some(document) = self.documents.get(&uri) {
+            if let Some((names, id)) = document.symbol_at(position) {
+                let (definition, uses) = document.occurrences(names, id);
+                let definition = definition.filter(|_| params.context.include_declaration);
+                let locations = definition
+                    .into_iter()
+                    .chain(uses)
+                    .map(|range| Location::new(uri.clone(), range))
+                    .collect();
+                return Ok(Some(locations));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn rename(&self, params: RenameParams) -> LspResult<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+
+        if let Some(document) = self.documents.get(&uri) {
+            if let Some((names, id)) = document.symbol_at(position) {
+                // Implicit names have no definition to rename
+                if let (Some(definition), uses) = document.occurrences(names, id) {
+                    let edits = std::iter::once(definition)
+                        .chain(uses)
+                        .map(|range| TextEdit {
+                            range,
+                            new_text: params.new_name.clone(),
+                        })
+                        .collect();
+                    return Ok(Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(uri.clone(), edits)])),
+                        ..Default::default()
+                    }));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
     async fn document_highlight(
         &self,
         params: DocumentHighlightParams,
@@ -596,34 +1272,19 @@ impl LanguageServer for VeyraLanguageServer {
         let position = params.text_document_position_params.position;
 
         if let Some(document) = self.documents.get(&uri) {
-            if let Some(word) = document.get_text_at_position(position) {
-                let mut highlights = Vec::new();
-
-                // Find all occurrences of the word in the document
-                let text = document.rope.to_string();
-                let lines: Vec<&str> = text.lines().collect();
-
-                for (line_idx, line) in lines.iter().enumerate() {
-                    let mut start = 0;
-                    while let Some(pos) = line[start..].find(&word) {
-                        let char_start = start + pos;
-                        highlights.push(DocumentHighlight {
-                            range: Range {
-                                start: Position {
-                                    line: line_idx as u32,
-                                    character: char_start as u32,
-                                },
-                                end: Position {
-                                    line: line_idx as u32,
-                                    character: (char_start + word.len()) as u32,
-                                },
-                            },
-                            kind: Some(DocumentHighlightKind::TEXT),
-                        });
-                        start = char_start + word.len();
+            if let Some((names, id)) = document.symbol_at(position) {
+                let (definition, uses) = document.occurrences(names, id);
+                let highlight = |kind| {
+                    move |range| DocumentHighlight {
+                        range,
+                        kind: Some(kind),
                     }
-                }
-
+                };
+                let highlights = definition
+                    .into_iter()
+                    .map(highlight(DocumentHighlightKind::WRITE))
+                    .chain(uses.into_iter().map(highlight(DocumentHighlightKind::READ)))
+                    .collect();
                 return Ok(Some(highlights));
             }
         }
@@ -631,13 +1292,18 @@ impl LanguageServer for VeyraLanguageServer {
         Ok(None)
     }
 
-    async fn code_action(
-        &self,
-        _params: CodeActionParams,
-    ) -> LspResult<Option<CodeActionResponse>> {
-        // TODO: Implement code actions (quick fixes, refactoring, etc.)
-        // For now, return empty list
-        Ok(Some(vec![]))
+    async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
+        // Quick fixes for diagnostics in the requested range
+        // TODO: Refactoring actions
+        let uri = params.text_document.uri;
+        let actions = params
+            .context
+            .diagnostics
+            .iter()
+            .flat_map(|diagnostic| undefined_name_fixes(&uri, diagnostic))
+            .collect();
+
+        Ok(Some(actions))
     }
 }
 
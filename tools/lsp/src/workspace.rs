@@ -0,0 +1,251 @@
+// Workspace folders and their symbol indexes
+//
+// Every folder the editor opens is a `WorkspaceRoot` with the project
+// configuration from its veyra.toml and an index of the top-level
+// definitions in its .vey files. A document belongs to the innermost root
+// that contains it, so each package of a monorepo is navigated and checked
+// with its own files and settings.
+
+use ropey::Rope;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::*;
+use veyra_compiler::{lexer::Lexer, parser::Parser as VeyraParser, resolve};
+
+/// A top-level function, struct, actor or variable in an indexed file
+#[derive(Debug, Clone)]
+pub struct IndexedSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+}
+
+#[derive(Debug)]
+pub struct WorkspaceRoot {
+    pub uri: Url,
+    pub path: PathBuf,
+    pub project: Option<veyra_config::Project>,
+    /// Definitions in each .vey file under the root
+    index: HashMap<Url, Vec<IndexedSymbol>>,
+}
+
+impl WorkspaceRoot {
+    /// Load the folder's configuration and index its files. Folders that
+    /// are not on the local file system are skipped.
+    pub fn new(uri: Url) -> Option<Self> {
+        let path = uri.to_file_path().ok()?;
+        let project = veyra_config::Project::discover(&path).ok().flatten();
+        let mut root = Self {
+            uri,
+            path,
+            project,
+            index: HashMap::new(),
+        };
+        root.reindex();
+        Some(root)
+    }
+
+    /// Short name shown next to symbols from this root
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.display().to_string())
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.path)
+    }
+
+    /// Index every .vey file under the root again
+    pub fn reindex(&mut self) {
+        self.index.clear();
+        let mut files = Vec::new();
+        source_files(&self.path, &mut files);
+        for file in files {
+            if let (Ok(uri), Ok(text)) =
+                (Url::from_file_path(&file), std::fs::read_to_string(&file))
+            {
+                self.index_file(uri, &text);
+            }
+        }
+    }
+
+    /// Replace a file's definitions, e.g. when its document is edited
+    pub fn index_file(&mut self, uri: Url, text: &str) {
+        let symbols = definitions(&uri, text);
+        self.index.insert(uri, symbols);
+    }
+
+    /// Index a file from disk, or forget it if it no longer exists
+    pub fn reindex_file(&mut self, uri: &Url) {
+        let text = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok());
+        match text {
+            Some(text) => self.index_file(uri.clone(), &text),
+            None => {
+                self.index.remove(uri);
+            }
+        }
+    }
+
+    /// Read veyra.toml again after it changed on disk
+    pub fn reload_project(&mut self) {
+        self.project = veyra_config::Project::discover(&self.path).ok().flatten();
+    }
+
+    /// Definitions of `name` in the root's files
+    pub fn definitions<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a IndexedSymbol> {
+        self.symbols().filter(move |symbol| symbol.name == name)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &IndexedSymbol> {
+        self.index.values().flatten()
+    }
+
+    /// Apply the `[lint]` section of veyra.toml to diagnostics: rules set to
+    /// "off" are dropped, others get the configured severity
+    pub fn configure(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let Some(project) = &self.project else {
+            return diagnostics;
+        };
+        let lint = &project.config.lint;
+
+        diagnostics
+            .into_iter()
+            .filter_map(|mut diagnostic| {
+                let rule = match &diagnostic.code {
+                    Some(NumberOrString::String(code)) => lint.rules.get(code),
+                    _ => None,
+                };
+                match rule.map(String::as_str) {
+                    Some("off") => return None,
+                    Some("error") => diagnostic.severity = Some(DiagnosticSeverity::ERROR),
+                    Some("warning") => diagnostic.severity = Some(DiagnosticSeverity::WARNING),
+                    Some("info") => diagnostic.severity = Some(DiagnosticSeverity::INFORMATION),
+                    _ => {}
+                }
+                if lint.warnings_as_errors == Some(true)
+                    && diagnostic.severity == Some(DiagnosticSeverity::WARNING)
+                {
+                    diagnostic.severity = Some(DiagnosticSeverity::ERROR);
+                }
+                Some(diagnostic)
+            })
+            .collect()
+    }
+}
+
+/// The workspace folders the editor has open
+#[derive(Debug, Default)]
+pub struct Workspace {
+    roots: Vec<WorkspaceRoot>,
+}
+
+impl Workspace {
+    pub fn add(&mut self, uri: Url) {
+        if self.roots.iter().any(|root| root.uri == uri) {
+            return;
+        }
+        if let Some(root) = WorkspaceRoot::new(uri) {
+            self.roots.push(root);
+        }
+    }
+
+    pub fn remove(&mut self, uri: &Url) {
+        self.roots.retain(|root| root.uri != *uri);
+    }
+
+    pub fn roots(&self) -> &[WorkspaceRoot] {
+        &self.roots
+    }
+
+    /// The innermost root containing a document
+    pub fn root_for(&self, uri: &Url) -> Option<&WorkspaceRoot> {
+        let index = self.root_index(uri)?;
+        Some(&self.roots[index])
+    }
+
+    pub fn root_for_mut(&mut self, uri: &Url) -> Option<&mut WorkspaceRoot> {
+        let index = self.root_index(uri)?;
+        Some(&mut self.roots[index])
+    }
+
+    fn root_index(&self, uri: &Url) -> Option<usize> {
+        let path = uri.to_file_path().ok()?;
+        self.roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| root.contains(&path))
+            .max_by_key(|(_, root)| root.path.components().count())
+            .map(|(index, _)| index)
+    }
+}
+
+/// .vey files under a directory, skipping hidden directories and build output
+fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                source_files(&path, files);
+            }
+        } else if path.extension().is_some_and(|ext| ext == "vey") {
+            files.push(path);
+        }
+    }
+}
+
+/// Top-level definitions of a source file, including those around syntax
+/// errors; none if it cannot be tokenized
+fn definitions(uri: &Url, text: &str) -> Vec<IndexedSymbol> {
+    let Ok(tokens) = Lexer::new(text).tokenize() else {
+        return Vec::new();
+    };
+    let (program, _) = VeyraParser::new(tokens).parse_recovering();
+    let (ranged, _) = Lexer::new(text).tokenize_with_ranges();
+    let names = resolve::resolve(&program, &ranged);
+    let rope = Rope::from_str(text);
+
+    names.scopes[resolve::SymbolTable::GLOBAL]
+        .symbols
+        .iter()
+        .map(|id| names.symbol(*id))
+        .filter_map(|symbol| {
+            let kind = match symbol.kind {
+                resolve::SymbolKind::Function => SymbolKind::FUNCTION,
+                resolve::SymbolKind::Struct => SymbolKind::STRUCT,
+                resolve::SymbolKind::Trait => SymbolKind::INTERFACE,
+                resolve::SymbolKind::Actor => SymbolKind::CLASS,
+                resolve::SymbolKind::Variable => SymbolKind::VARIABLE,
+                _ => return None,
+            };
+            let span = symbol.span.as_ref()?;
+            let range = Range::new(position(&rope, span.start), position(&rope, span.end));
+            Some(IndexedSymbol {
+                name: symbol.name.clone(),
+                kind,
+                location: Location::new(uri.clone(), range),
+            })
+        })
+        .collect()
+}
+
+/// The LSP position of a char index. LSP counts a position's character in
+/// UTF-16 code units, so a char outside the BMP is two.
+pub(crate) fn position(rope: &Rope, char_idx: usize) -> Position {
+    let char_idx = char_idx.min(rope.len_chars());
+    let line = rope.char_to_line(char_idx);
+    let line_start = rope.line_to_char(line);
+    Position::new(
+        line as u32,
+        (rope.char_to_utf16_cu(char_idx) - rope.char_to_utf16_cu(line_start)) as u32,
+    )
+}
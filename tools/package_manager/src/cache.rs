@@ -0,0 +1,148 @@
+// The download cache for `veyra-pkg cache`
+//
+// Every package downloaded from a registry is kept under
+// `<cache dir>/packages/<name>/<version>/`, and later installs of the same
+// version copy it from there. Using an entry updates its modification time,
+// so `--older-than` removes the versions that have not been installed for a
+// while rather than the ones downloaded first.
+
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// One cached version of a package
+pub struct Entry {
+    pub name: String,
+    pub version: String,
+    pub path: PathBuf,
+    /// Size of its files
+    pub bytes: u64,
+    /// When it was last downloaded or installed
+    pub last_used: SystemTime,
+}
+
+/// Where a version of a package is cached; `@scope/name` is two levels
+pub fn package_dir(cache_dir: &Path, name: &str, version: &str) -> PathBuf {
+    cache_dir.join("packages").join(name).join(version)
+}
+
+/// Where a download is written until it is complete
+pub fn partial_dir(cache_dir: &Path, name: &str, version: &str) -> PathBuf {
+    cache_dir.join("partial").join(name).join(version)
+}
+
+/// Mark an entry as used now
+pub fn touch(dir: &Path) -> Result<()> {
+    fs::File::open(dir)?.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Every cached version, sorted by name and version
+pub fn entries(cache_dir: &Path) -> Result<Vec<Entry>> {
+    let packages = cache_dir.join("packages");
+    if !packages.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for dir in subdirectories(&packages)? {
+        let name = file_name(&dir);
+        if name.starts_with('@') {
+            for scoped in subdirectories(&dir)? {
+                names.push((format!("{}/{}", name, file_name(&scoped)), scoped));
+            }
+        } else {
+            names.push((name, dir));
+        }
+    }
+
+    let mut entries = Vec::new();
+    for (name, dir) in names {
+        for path in subdirectories(&dir)? {
+            entries.push(Entry {
+                name: name.clone(),
+                version: file_name(&path),
+                bytes: crate::dir_size(&path),
+                last_used: fs::metadata(&path)?.modified()?,
+                path,
+            });
+        }
+    }
+    entries.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    Ok(entries)
+}
+
+/// Remove an entry, and the directories of its package once they are empty
+pub fn remove(cache_dir: &Path, entry: &Entry) -> Result<()> {
+    fs::remove_dir_all(&entry.path)?;
+    let packages = cache_dir.join("packages");
+    let mut dir = entry.path.parent();
+    while let Some(parent) = dir.filter(|dir| *dir != packages) {
+        // Fails, and stops, at the first directory that is not empty
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+    Ok(())
+}
+
+/// Parse an age such as `30d`: a whole number followed by `s`, `m`, `h`,
+/// `d` or `w`
+pub fn parse_age(text: &str) -> Result<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (count, unit) = text.split_at(split);
+    let count: u64 = count.parse().map_err(|_| {
+        anyhow!(
+            "'{}' is not an age; expected a number and a unit, like 30d",
+            text
+        )
+    })?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(anyhow!(
+                "Unknown unit '{}' in '{}'; use s, m, h, d or w",
+                unit,
+                text
+            ))
+        }
+    };
+    Ok(Duration::from_secs(count.saturating_mul(seconds)))
+}
+
+/// A duration the way `cache list` shows it, to the largest whole unit
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (count, unit) = match seconds {
+        0..=59 => (seconds, "second"),
+        60..=3_599 => (seconds / 60, "minute"),
+        3_600..=86_399 => (seconds / 3_600, "hour"),
+        _ => (seconds / 86_400, "day"),
+    };
+    format!("{} {}{} ago", count, unit, crate::plural(count as usize))
+}
+
+fn subdirectories(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
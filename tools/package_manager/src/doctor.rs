@@ -0,0 +1,194 @@
+// Environment checks for `veyra-pkg doctor`
+//
+// Each check passes, warns or fails, and anything but a pass says how to fix
+// it. The report is the first thing to ask for in a bug report, so it also
+// prints the versions and paths it found.
+
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    /// What was found
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warn(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn fail(
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Whether `veyc` is on PATH, and whether it is the compiler version this
+/// veyra-pkg was built with
+pub fn check_veyc() -> Vec<Check> {
+    let Some(path) = find_on_path("veyc") else {
+        return vec![Check::fail(
+            "veyc on PATH",
+            "not found",
+            "Build the compiler with `cargo build --release` in compiler/ and add \
+             compiler/target/release to PATH",
+        )];
+    };
+    let found = Check::pass("veyc on PATH", path.display().to_string());
+
+    let version = match Command::new(&path).arg("--version").output() {
+        Ok(output) if output.status.success() => {
+            let text = String::from_utf8_lossy(&output.stdout);
+            text.trim().trim_start_matches("veyc").trim().to_string()
+        }
+        Ok(output) => {
+            let fix = "Reinstall veyc; the one on PATH may be broken";
+            let detail = format!("`veyc --version` exited with {}", output.status);
+            return vec![found, Check::fail("veyc version", detail, fix)];
+        }
+        Err(e) => {
+            let fix = "Check that the veyc on PATH is executable";
+            let detail = format!("could not run veyc: {}", e);
+            return vec![found, Check::fail("veyc version", detail, fix)];
+        }
+    };
+
+    let check = if version == veyra_compiler::VERSION {
+        Check::pass("veyc version", version)
+    } else {
+        Check::warn(
+            "veyc version",
+            format!(
+                "veyc is {} but veyra-pkg was built with {}",
+                version,
+                veyra_compiler::VERSION
+            ),
+            "Rebuild veyc and veyra-pkg from the same checkout so they agree on the language",
+        )
+    };
+    vec![found, check]
+}
+
+/// Whether files can be created in `dir`, creating it if needed
+pub fn check_writable(name: &str, dir: &Path) -> Check {
+    let probe = dir.join(format!(".veyra-doctor-{}", std::process::id()));
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"probe"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => Check::pass(name, dir.display().to_string()),
+        Err(e) => Check::fail(
+            name,
+            format!("{}: {}", dir.display(), e),
+            format!(
+                "Make {} writable, or point XDG_CONFIG_HOME / XDG_CACHE_HOME somewhere writable",
+                dir.display()
+            ),
+        ),
+    }
+}
+
+/// Whether the registry answers at all; any HTTP response will do
+pub async fn check_registry(name: &str, url: &str) -> Check {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => return Check::fail(name, e.to_string(), "Report this as a bug"),
+    };
+    match client.get(url).send().await {
+        Ok(response) => Check::pass(name, format!("{} ({})", url, response.status())),
+        Err(e) => Check::fail(
+            name,
+            format!("{}: {}", url, e),
+            "Check the network connection and proxy settings, or choose another \
+             registry with --registry or [registries] in veyra.toml",
+        ),
+    }
+}
+
+/// Print the checks; returns whether none failed
+pub fn report(checks: &[Check]) -> bool {
+    let width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    for check in checks {
+        let mark = match check.status {
+            Status::Pass => "✓".green().bold(),
+            Status::Warn => "!".yellow().bold(),
+            Status::Fail => "✗".red().bold(),
+        };
+        println!(
+            "  {} {:width$}  {}",
+            mark,
+            check.name,
+            check.detail,
+            width = width
+        );
+        if let Some(fix) = &check.fix {
+            println!("    {} {}", "fix:".bold(), fix);
+        }
+    }
+
+    let count = |status| checks.iter().filter(|c| c.status == status).count();
+    let failed = count(Status::Fail);
+    println!();
+    println!(
+        "{} {} passed, {} warnings, {} failed",
+        "Doctor:".bold(),
+        count(Status::Pass).to_string().green().bold(),
+        count(Status::Warn).to_string().yellow().bold(),
+        failed.to_string().red().bold()
+    );
+    failed == 0
+}
+
+/// The first `name` executable in PATH
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+}
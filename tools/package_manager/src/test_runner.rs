@@ -0,0 +1,173 @@
+// Test discovery and execution for `veyra-pkg test`
+//
+// Every function annotated `@test` is a test, wherever its file lives in the
+// project. `@should_fail` expects the test to raise an error (one containing
+// the given text, if any) and `@ignore` skips it. Files in the tests
+// directory with no `@test` functions still run as one test each. With
+// leak checks on, a test that passes fails anyway if it leaves values
+// behind that nothing can reach.
+
+use std::path::{Path, PathBuf};
+use veyra_compiler::{
+    ast::*,
+    cfg::{self, CfgOptions},
+    include,
+    interpreter::Interpreter,
+};
+use walkdir::WalkDir;
+
+/// Directories that are never searched for tests
+const SKIPPED_DIRS: &[&str] = &["target", "veyra-modules"];
+
+/// A parsed source file and the tests found in it
+pub struct TestFile {
+    program: Program,
+    pub tests: Vec<TestCase>,
+}
+
+pub struct TestCase {
+    /// Display name, e.g. `tests/math.vey::adds_numbers`
+    pub name: String,
+    /// Display path of the file, e.g. `tests/math.vey`
+    pub file: String,
+    /// The `@test` function, or `None` to run the whole file
+    function: Option<String>,
+    /// `@should_fail`, with the text the error must contain
+    should_fail: Option<Option<String>>,
+    /// `@ignore`, with the reason
+    ignore: Option<Option<String>>,
+}
+
+pub enum Outcome {
+    Passed,
+    Failed { message: String, output: String },
+    Ignored(Option<String>),
+}
+
+/// Parse every source file under `root` and collect its tests. Files that
+/// fail to parse are returned as `(display path, error)` pairs.
+pub fn discover(
+    root: &Path,
+    tests_dir: &Path,
+    options: &CfgOptions,
+) -> (Vec<TestFile>, Vec<(String, String)>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    for path in source_files(root) {
+        let display = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+
+        let program = match include::tokenize_file(&path).and_then(veyra_compiler::parser::parse) {
+            Ok(program) => cfg::configure(program, options),
+            Err(e) => {
+                // Only report files that would have been tests
+                if path.starts_with(tests_dir) {
+                    errors.push((display, e.to_string()));
+                }
+                continue;
+            }
+        };
+
+        let mut tests: Vec<TestCase> = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Function(func) if func.has_attribute("test") => Some(TestCase {
+                    name: format!("{}::{}", display, func.name),
+                    file: display.clone(),
+                    function: Some(func.name.clone()),
+                    should_fail: func
+                        .attribute("should_fail")
+                        .map(|a| a.string_arg().map(str::to_string)),
+                    ignore: func
+                        .attribute("ignore")
+                        .map(|a| a.string_arg().map(str::to_string)),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        if tests.is_empty() && path.starts_with(tests_dir) {
+            tests.push(TestCase {
+                name: display.clone(),
+                file: display,
+                function: None,
+                should_fail: None,
+                ignore: None,
+            });
+        }
+
+        if !tests.is_empty() {
+            files.push(TestFile { program, tests });
+        }
+    }
+
+    (files, errors)
+}
+
+fn source_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+        })
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("vey"))
+        .collect();
+    files.sort();
+    files
+}
+
+impl TestFile {
+    /// Run one of this file's tests in a fresh interpreter, checking for
+    /// leaked values if `check_leaks` is set
+    pub fn run(&self, test: &TestCase, check_leaks: bool) -> Outcome {
+        if let Some(reason) = &test.ignore {
+            return Outcome::Ignored(reason.clone());
+        }
+
+        let mut interpreter = Interpreter::new();
+        interpreter.capture_output();
+        interpreter.set_echo_output(false);
+        if check_leaks {
+            interpreter.track_allocations();
+        }
+        // The returned value is dropped before looking for leaks, since
+        // nothing the program can reach holds it
+        let result = match &test.function {
+            Some(name) => interpreter
+                .load_definitions(&self.program)
+                .and_then(|_| interpreter.call(name, &[])),
+            None => interpreter.interpret(&self.program),
+        }
+        .map(drop);
+        let output = interpreter.take_output();
+
+        let failure = match (result, &test.should_fail) {
+            (Ok(_), None) => {
+                let leaks = interpreter.leaks();
+                if !check_leaks || leaks.is_empty() {
+                    return Outcome::Passed;
+                }
+                leaks.to_string()
+            }
+            (Err(e), None) => e.to_string(),
+            (Ok(_), Some(_)) => "expected the test to fail, but it passed".to_string(),
+            (Err(e), Some(Some(expected))) if !e.to_string().contains(expected.as_str()) => {
+                format!("expected an error containing '{}', got: {}", expected, e)
+            }
+            (Err(_), Some(_)) => return Outcome::Passed,
+        };
+
+        Outcome::Failed {
+            message: failure,
+            output,
+        }
+    }
+}
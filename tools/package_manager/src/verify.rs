@@ -0,0 +1,221 @@
+// Integrity and signature checks for installed packages
+//
+// After a package is installed, its files are hashed into a checksum. The
+// registry signs `name@version:checksum` with the publisher's ed25519 key,
+// and the signature is checked against the keys the registry lists for the
+// publisher. `veyra-lock.json` records the checksum and the fingerprint of
+// the key that signed it, so a later install of the same version that
+// differs in either is refused. Packages the registry has no signature for
+// are installed with a warning.
+//
+// Registry endpoints:
+//
+//   GET /api/v1/packages/<name>/<version>/signature  {publisher, signature}
+//   GET /api/v1/publishers/<publisher>/keys          {keys: [public key]}
+//
+// Keys and signatures are base64.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub const LOCKFILE: &str = "veyra-lock.json";
+
+/// `veyra-lock.json`: what was installed, and who signed it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub version: String,
+    /// `sha256:` and the hex digest of the package's files
+    pub checksum: String,
+    /// Fingerprint of the publisher key that signed the package, if it
+    /// was signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl Lockfile {
+    pub fn path(project_dir: &Path) -> PathBuf {
+        project_dir.join(LOCKFILE)
+    }
+
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let path = Self::path(project_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content).map_err(|e| anyhow!("Invalid {}: {}", LOCKFILE, e))
+    }
+
+    pub fn save(&self, project_dir: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(Self::path(project_dir), content + "\n")?;
+        Ok(())
+    }
+}
+
+/// Hash of every file under `dir` with its path relative to `dir`, so that
+/// renaming a file changes the checksum as much as editing it
+pub fn package_checksum(dir: &Path) -> Result<String> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        let relative = file.strip_prefix(dir)?;
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let content = fs::read(&file)?;
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+    Ok(format!("sha256:{}", hex(&hasher.finalize())))
+}
+
+/// The text a publisher signs for one version of a package
+pub fn signed_message(name: &str, version: &str, checksum: &str) -> String {
+    format!("{}@{}:{}", name, version, checksum)
+}
+
+/// `ed25519:` and the hex SHA-256 of a public key
+pub fn key_fingerprint(key: &[u8]) -> String {
+    format!("ed25519:{}", hex(&Sha256::digest(key)))
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureResponse {
+    publisher: String,
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeysResponse {
+    keys: Vec<String>,
+}
+
+/// The result of checking a package's signature
+pub enum Signature {
+    /// Signed by the key with this fingerprint
+    Valid(String),
+    /// The registry has no signature for the package, or could not be
+    /// asked; the reason says which
+    Missing(String),
+}
+
+/// Check the registry's signature for `name@version` over `checksum`. A
+/// signature that no key of the publisher verifies is an error.
+pub async fn check_signature(
+    registry: &str,
+    name: &str,
+    version: &str,
+    checksum: &str,
+) -> Result<Signature> {
+    let client = reqwest::Client::new();
+    let base = registry.trim_end_matches('/');
+
+    let url = format!("{}/api/v1/packages/{}/{}/signature", base, name, version);
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => return Ok(Signature::Missing(format!("registry unreachable: {}", e))),
+    };
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Signature::Missing("the package is not signed".to_string()));
+    }
+    let signed: SignatureResponse = response
+        .error_for_status()
+        .map_err(|e| anyhow!("Could not fetch the signature of {}: {}", name, e))?
+        .json()
+        .await?;
+    let signature = STANDARD
+        .decode(signed.signature.trim())
+        .map_err(|e| anyhow!("Malformed signature for {}: {}", name, e))?;
+
+    let url = format!("{}/api/v1/publishers/{}/keys", base, signed.publisher);
+    let keys: KeysResponse = client
+        .get(&url)
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|e| {
+            anyhow!(
+                "Could not fetch the keys of publisher '{}': {}",
+                signed.publisher,
+                e
+            )
+        })?
+        .json()
+        .await?;
+
+    let message = signed_message(name, version, checksum);
+    for key in &keys.keys {
+        let Ok(key) = STANDARD.decode(key.trim()) else {
+            continue;
+        };
+        if UnparsedPublicKey::new(&ED25519, &key)
+            .verify(message.as_bytes(), &signature)
+            .is_ok()
+        {
+            return Ok(Signature::Valid(key_fingerprint(&key)));
+        }
+    }
+    Err(anyhow!(
+        "Signature of {}@{} does not match any key of publisher '{}'",
+        name,
+        version,
+        signed.publisher
+    ))
+}
+
+/// Compare what was installed with the lockfile entry for the same
+/// version: the checksum must be the same, and a version signed before must
+/// be signed by the same key.
+pub fn check_locked(locked: &LockedPackage, installed: &LockedPackage, name: &str) -> Result<()> {
+    if locked.checksum != installed.checksum {
+        return Err(anyhow!(
+            "Checksum of {}@{} does not match {}\n  locked:    {}\n  installed: {}",
+            name,
+            installed.version,
+            LOCKFILE,
+            locked.checksum,
+            installed.checksum
+        ));
+    }
+    if let Some(key) = &locked.key {
+        if installed.key.as_ref() != Some(key) {
+            return Err(anyhow!(
+                "{}@{} was signed by {} when it was locked, but is now {}",
+                name,
+                installed.version,
+                key,
+                installed.key.as_deref().unwrap_or("unsigned")
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
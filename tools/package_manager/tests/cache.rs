@@ -0,0 +1,109 @@
+// Tests for the download cache and `veyra-pkg cache`
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+struct Project {
+    dir: TempDir,
+}
+
+impl Project {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("veyra.toml"),
+            "name = \"app\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        Self { dir }
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+            .args(args)
+            .current_dir(self.dir.path())
+            .env("XDG_CONFIG_HOME", self.dir.path().join(".config"))
+            .env("XDG_CACHE_HOME", self.dir.path().join(".cache"))
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+
+    fn stdout(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    fn cached(&self, name: &str, version: &str) -> PathBuf {
+        self.dir
+            .path()
+            .join(".cache")
+            .join("veyra")
+            .join("packages")
+            .join(name)
+            .join(version)
+    }
+}
+
+#[test]
+fn installs_from_the_cache_and_lists_it() {
+    let project = Project::new();
+    project.stdout(&["install", "demo@1.0.0", "@acme/tool@2.1.0", "--no-verify"]);
+    assert!(project.cached("demo", "1.0.0").is_dir());
+    assert!(project.cached("@acme/tool", "2.1.0").is_dir());
+
+    // A second install is served from the cache
+    std::fs::remove_dir_all(project.dir.path().join("veyra-modules")).unwrap();
+    let output = project.stdout(&["install", "demo@1.0.0", "--no-verify", "--verbose"]);
+    assert!(output.contains("Using cached demo v1.0.0"));
+    assert!(project
+        .dir
+        .path()
+        .join("veyra-modules/demo/main.vey")
+        .is_file());
+
+    let list = project.stdout(&["cache", "list"]);
+    let lines: Vec<&str> = list.lines().collect();
+    assert!(lines[0].trim_start().starts_with("@acme/tool@2.1.0"));
+    assert!(lines[1].trim_start().starts_with("demo@1.0.0"));
+    assert!(lines[1].ends_with("seconds ago"));
+    assert!(list.contains("Cache: 2 packages"));
+    assert!(project
+        .stdout(&["cache", "size"])
+        .starts_with("Cache: 2 packages"));
+}
+
+#[test]
+fn cleans_packages_by_last_use() {
+    let project = Project::new();
+    project.stdout(&["install", "old@1.0.0", "new@1.0.0", "--no-verify"]);
+    let month_ago = SystemTime::now() - Duration::from_secs(31 * 24 * 60 * 60);
+    std::fs::File::open(project.cached("old", "1.0.0"))
+        .unwrap()
+        .set_modified(month_ago)
+        .unwrap();
+
+    let output = project.stdout(&["cache", "clean", "--older-than", "30d"]);
+    assert!(output.contains("Removed 1 cached package"));
+    assert!(!project.cached("old", "1.0.0").parent().unwrap().exists());
+    assert!(project.cached("new", "1.0.0").is_dir());
+
+    let output = project.run(&["cache", "clean", "--older-than", "month"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not an age"));
+
+    project.stdout(&["cache", "clean"]);
+    assert!(project
+        .stdout(&["cache", "list"])
+        .contains("The cache is empty"));
+    assert!(project
+        .stdout(&["cache", "size"])
+        .starts_with("Cache: 0 packages"));
+}
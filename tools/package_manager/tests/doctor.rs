@@ -0,0 +1,118 @@
+// Tests for `veyra-pkg doctor`, with a veyc and registry of the test's own
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output};
+use std::thread;
+use tempfile::TempDir;
+
+/// A registry that answers every request with an empty 200
+fn registry() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            );
+        }
+    });
+    url
+}
+
+struct Environment {
+    dir: TempDir,
+}
+
+impl Environment {
+    /// A project directory with a `bin/veyc` that reports `version`
+    fn new(version: &str) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("bin")).unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src").join("main.vey"), "print(1)\n").unwrap();
+
+        let veyc = dir.path().join("bin").join("veyc");
+        std::fs::write(&veyc, format!("#!/bin/sh\necho \"veyc {}\"\n", version)).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&veyc, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        Self { dir }
+    }
+
+    fn manifest(&self, content: &str) {
+        std::fs::write(self.dir.path().join("veyra.toml"), content).unwrap();
+    }
+
+    fn doctor(&self, registry: &str) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+            .args(["doctor", "--registry", registry])
+            .current_dir(self.dir.path())
+            .env("PATH", self.dir.path().join("bin"))
+            .env("XDG_CONFIG_HOME", self.dir.path().join(".config"))
+            .env("XDG_CACHE_HOME", self.dir.path().join(".cache"))
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[cfg(unix)]
+#[test]
+fn passes_in_a_healthy_project() {
+    let env = Environment::new(veyra_compiler::VERSION);
+    env.manifest("name = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nmath = \"^1.2\"\n");
+
+    let output = env.doctor(&registry());
+    let report = stdout(&output);
+    assert!(output.status.success(), "{}", report);
+    assert!(report.contains(&format!("veyc version      {}", veyra_compiler::VERSION)));
+    assert!(report.contains("project manifest  app 0.1.0"));
+    assert!(report.contains("0 warnings, 0 failed"));
+    assert!(!report.contains("fix:"));
+}
+
+#[cfg(unix)]
+#[test]
+fn reports_problems_with_fixes() {
+    let env = Environment::new("0.0.1-old");
+    env.manifest(
+        "name = \"app\"\nversion = \"0.1.0\"\nmain = \"app.vey\"\n\n[dependencies]\nmath = \"one\"\n",
+    );
+
+    // Nothing listens on a port that was just released
+    let closed = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+    let output = env.doctor(&closed);
+    let report = stdout(&output);
+    assert!(!output.status.success());
+    assert!(report.contains(&format!(
+        "veyc is 0.0.1-old but veyra-pkg was built with {}",
+        veyra_compiler::VERSION
+    )));
+    assert!(report.contains("app.vey does not exist"));
+    assert!(report.contains("'one' is not a version requirement"));
+    assert!(report.contains(&format!("✗ registry          {}", closed)));
+    assert!(report.contains("1 warnings, 3 failed"));
+    assert_eq!(report.matches("fix:").count(), 4);
+
+    env.manifest("name = \"app\"\nversion = \n");
+    let report = stdout(&env.doctor(&registry()));
+    assert!(report.contains("✗ project manifest"));
+    assert!(report.contains("fix: Fix the error in veyra.toml"));
+}
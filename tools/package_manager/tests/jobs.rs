@@ -0,0 +1,115 @@
+// Tests for `veyra-pkg install --jobs`, which downloads and verifies several
+// packages at once, against a registry served from the test that counts how
+// many signature requests it is answering at the same time
+
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const PACKAGES: [&str; 4] = ["alpha", "beta", "gamma", "delta"];
+
+struct Registry {
+    url: String,
+    /// Most signature requests in flight at once
+    most_at_once: Arc<AtomicUsize>,
+}
+
+impl Registry {
+    /// A registry that has no signatures, and takes a while to say so
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most_at_once = Arc::new(AtomicUsize::new(0));
+        let most = most_at_once.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let (in_flight, most) = (in_flight.clone(), most.clone());
+                thread::spawn(move || {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut line = String::new();
+                    while reader.read_line(&mut line).unwrap() > 2 {
+                        line.clear();
+                    }
+                    thread::sleep(Duration::from_millis(300));
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    );
+                });
+            }
+        });
+        Self { url, most_at_once }
+    }
+}
+
+fn project() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("veyra.toml"),
+        "name = \"app\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn install(project: &TempDir, registry: &Registry, jobs: &str) -> Output {
+    let packages = PACKAGES.map(|name| format!("{}@1.0.0", name));
+    Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+        .arg("install")
+        .args(&packages)
+        .args(["--registry", &registry.url, "--jobs", jobs])
+        .current_dir(project.path())
+        .env("XDG_CONFIG_HOME", project.path().join(".config"))
+        .env("XDG_CACHE_HOME", project.path().join(".cache"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn installs_packages_in_parallel() {
+    let registry = Registry::start();
+    let project = project();
+    let output = install(&project, &registry, "4");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Installed 4 packages"));
+    assert!(registry.most_at_once.load(Ordering::SeqCst) > 1);
+
+    let lockfile: Value = serde_json::from_str(
+        &std::fs::read_to_string(project.path().join("veyra-lock.json")).unwrap(),
+    )
+    .unwrap();
+    for name in PACKAGES {
+        let main = project
+            .path()
+            .join("veyra-modules")
+            .join(name)
+            .join("main.vey");
+        assert!(main.is_file(), "{} is not installed", name);
+        assert_eq!(lockfile["packages"][name]["version"], "1.0.0");
+    }
+}
+
+#[test]
+fn one_job_installs_packages_one_at_a_time() {
+    let registry = Registry::start();
+    let project = project();
+    let output = install(&project, &registry, "1");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Installed 4 packages"));
+    assert_eq!(registry.most_at_once.load(Ordering::SeqCst), 1);
+}
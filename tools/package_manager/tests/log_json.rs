@@ -0,0 +1,63 @@
+// Tests for `veyra-pkg test --log-json`
+
+use std::path::PathBuf;
+use std::process::Command;
+use veyra_compiler::events::Event;
+use veyra_compiler::Severity;
+
+#[test]
+fn reports_each_test_as_an_event() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::create_dir_all(dir.join("tests")).unwrap();
+    std::fs::write(
+        dir.join("veyra.toml"),
+        "name = \"app\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("tests").join("math.vey"),
+        "@test\nfn adds() {\n    assert(1 + 1 == 2)\n}\n\
+         @test\nfn breaks() {\n    throw \"nope\"\n}\n\
+         @test\n@ignore\nfn later() {\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+        .args(["test", "--log-json"])
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let events: Vec<Event> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let summary: Vec<_> = events
+        .iter()
+        .map(|event| (event.file.clone(), event.severity, event.message.as_str()))
+        .collect();
+    let file = Some(
+        PathBuf::from("tests")
+            .join("math.vey")
+            .display()
+            .to_string(),
+    );
+    assert_eq!(
+        summary,
+        [
+            (file.clone(), Severity::Note, "tests/math.vey::adds passed"),
+            (
+                file.clone(),
+                Severity::Error,
+                "tests/math.vey::breaks failed: Uncaught exception: nope"
+            ),
+            (file, Severity::Note, "tests/math.vey::later ignored"),
+            (None, Severity::Error, "1 passed, 1 failed, 1 ignored"),
+        ]
+    );
+    assert!(events.iter().all(|event| event.tool == "veyra-pkg"));
+    assert!(events.iter().all(|event| event.duration_ms.is_some()));
+}
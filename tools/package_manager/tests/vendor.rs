@@ -0,0 +1,52 @@
+// Tests for `veyra-pkg vendor`
+
+use std::path::Path;
+use std::process::Command;
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn vendors_the_dependencies_of_dependencies() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(
+        &root.join("veyra.toml"),
+        "name = \"app\"\nversion = \"0.1.0\"\n\n[dependencies]\nweb = \"1.0.0\"\n",
+    );
+    write(
+        &root.join("veyra-modules/web/veyra.toml"),
+        "name = \"web\"\nversion = \"1.0.0\"\n\n[dependencies]\nhttp = \"2.0.0\"\n",
+    );
+    write(
+        &root.join("veyra-modules/web/src/main.vey"),
+        "import http\n",
+    );
+    write(
+        &root.join("veyra-modules/http/veyra.toml"),
+        "name = \"http\"\nversion = \"2.0.0\"\n",
+    );
+    write(
+        &root.join("veyra-modules/http/src/main.vey"),
+        "fn get() {\n}\n",
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+        .arg("vendor")
+        .current_dir(root)
+        .env("XDG_CONFIG_HOME", root.join(".config"))
+        .env("XDG_CACHE_HOME", root.join(".cache"))
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Vendored 2 packages"));
+    assert!(root.join("vendor/web/src/main.vey").is_file());
+    assert!(root.join("vendor/http/src/main.vey").is_file());
+}
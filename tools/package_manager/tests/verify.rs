@@ -0,0 +1,222 @@
+// Tests for signature and lockfile checks on `veyra-pkg install`, against a
+// registry served from the test
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tempfile::TempDir;
+
+/// What the registry answers for the package signature and publisher keys
+#[derive(Default)]
+struct Signing {
+    signature: Option<String>,
+    keys: Vec<String>,
+}
+
+struct Registry {
+    url: String,
+    signing: Arc<Mutex<Signing>>,
+}
+
+impl Registry {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let signing = Arc::new(Mutex::new(Signing::default()));
+        let served = signing.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+
+                let path = request.split_whitespace().nth(1).unwrap_or("");
+                let signing = served.lock().unwrap();
+                let body = if path.ends_with("/signature") {
+                    signing
+                        .signature
+                        .as_ref()
+                        .map(|signature| json!({ "publisher": "alice", "signature": signature }))
+                } else if path == "/api/v1/publishers/alice/keys" {
+                    Some(json!({ "keys": signing.keys }))
+                } else {
+                    None
+                };
+                let (status, body) = match body {
+                    Some(body) => ("200 OK", body.to_string()),
+                    None => ("404 Not Found", String::new()),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+        Self { url, signing }
+    }
+}
+
+struct Project {
+    dir: TempDir,
+}
+
+impl Project {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("veyra.toml"),
+            "name = \"app\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        Self { dir }
+    }
+
+    fn install(&self, registry: &Registry, extra: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_veyra-pkg"))
+            .args(["install", "demo@1.0.0", "--registry", &registry.url])
+            .args(extra)
+            .current_dir(self.dir.path())
+            .env("XDG_CONFIG_HOME", self.dir.path().join(".config"))
+            .env("XDG_CACHE_HOME", self.dir.path().join(".cache"))
+            .output()
+            .unwrap()
+    }
+
+    fn lockfile(&self) -> Value {
+        let content = std::fs::read_to_string(self.dir.path().join("veyra-lock.json")).unwrap();
+        serde_json::from_str(&content).unwrap()
+    }
+
+    fn installed(&self) -> bool {
+        self.dir.path().join("veyra-modules").join("demo").is_dir()
+    }
+}
+
+fn key_pair() -> Ed25519KeyPair {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+    Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn verifies_signatures_and_locks_them() {
+    let registry = Registry::start();
+    let project = Project::new();
+
+    // Unsigned packages install with a warning, and their checksum is locked
+    let output = project.install(&registry, &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("not verified"));
+    let locked = project.lockfile()["packages"]["demo"].clone();
+    assert_eq!(locked["version"], "1.0.0");
+    let checksum = locked["checksum"].as_str().unwrap().to_string();
+    assert!(checksum.starts_with("sha256:"));
+    assert_eq!(locked["key"], Value::Null);
+
+    let alice = key_pair();
+    let message = format!("demo@1.0.0:{}", checksum);
+    {
+        let mut signing = registry.signing.lock().unwrap();
+        signing.signature = Some(STANDARD.encode(alice.sign(message.as_bytes())));
+        signing.keys = vec![STANDARD.encode(alice.public_key())];
+    }
+    let output = project.install(&registry, &[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let key = project.lockfile()["packages"]["demo"]["key"].clone();
+    assert!(key.as_str().unwrap().starts_with("ed25519:"));
+
+    // A signature by a key the publisher does not have is refused
+    let mallory = key_pair();
+    registry.signing.lock().unwrap().signature =
+        Some(STANDARD.encode(mallory.sign(message.as_bytes())));
+    let output = project.install(&registry, &[]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("does not match any key of publisher 'alice'"));
+    assert!(!project.installed());
+    assert_eq!(project.lockfile()["packages"]["demo"]["key"], key);
+
+    let output = project.install(&registry, &["--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(project.installed());
+}
+
+#[test]
+fn refuses_packages_that_differ_from_the_lockfile() {
+    let registry = Registry::start();
+    let project = Project::new();
+    assert!(project.install(&registry, &[]).status.success());
+
+    let mut lockfile = project.lockfile();
+    lockfile["packages"]["demo"]["checksum"] = json!("sha256:0000");
+    std::fs::write(
+        project.dir.path().join("veyra-lock.json"),
+        lockfile.to_string(),
+    )
+    .unwrap();
+
+    let output = project.install(&registry, &[]);
+    assert!(!output.status.success());
+    let error = stderr(&output);
+    assert!(error.contains("Checksum of demo@1.0.0 does not match veyra-lock.json"));
+    assert!(error.contains("--no-verify"));
+
+    // The escape hatch installs it, but the lockfile keeps what it pinned
+    let output = project.install(&registry, &["--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(project.installed());
+    assert_eq!(
+        project.lockfile()["packages"]["demo"]["checksum"],
+        "sha256:0000"
+    );
+}
+
+#[test]
+fn unverified_installs_keep_locked_keys() {
+    let registry = Registry::start();
+    let project = Project::new();
+    assert!(project.install(&registry, &[]).status.success());
+    let checksum = project.lockfile()["packages"]["demo"]["checksum"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let alice = key_pair();
+    let message = format!("demo@1.0.0:{}", checksum);
+    {
+        let mut signing = registry.signing.lock().unwrap();
+        signing.signature = Some(STANDARD.encode(alice.sign(message.as_bytes())));
+        signing.keys = vec![STANDARD.encode(alice.public_key())];
+    }
+    assert!(project.install(&registry, &[]).status.success());
+    let locked = project.lockfile()["packages"]["demo"].clone();
+    assert!(locked["key"].as_str().unwrap().starts_with("ed25519:"));
+
+    let output = project.install(&registry, &["--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Keeping the locked entry of demo"));
+    assert_eq!(project.lockfile()["packages"]["demo"], locked);
+
+    // So a later verified install still refuses a package that is unsigned
+    registry.signing.lock().unwrap().signature = None;
+    let output = project.install(&registry, &[]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("was signed by ed25519:"));
+    assert!(stderr(&output).contains("but is now unsigned"));
+    assert!(!project.installed());
+}
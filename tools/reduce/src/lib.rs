@@ -0,0 +1,442 @@
+// Reducing programs to minimal reproductions
+//
+// `reduce` takes a program and a test it passes, usually "veyc crashes on
+// it", and looks for the smallest program that still passes. It parses the
+// program and tries ever smaller versions of its syntax tree: without some
+// of the top-level items, methods, parameters or statements of a block, with a
+// statement replaced by the statements of one of its blocks, and with an
+// expression replaced by one of its operands or a call by one with an
+// argument fewer. Each candidate is printed back to source and kept if it
+// is shorter and the test still holds, until no change makes progress.
+//
+// A program that does not parse, or whose printed form no longer passes
+// the test (a bug that depends on layout or comments, say), is reduced by
+// removing lines instead.
+
+use std::collections::HashSet;
+use std::iter;
+use std::ops::Range;
+use veyra_compiler::ast::{
+    walk_block_mut, walk_expression, walk_expression_mut, walk_function_mut, walk_item_mut,
+    walk_program_mut, Block, Expression, Function, Item, Parameter, Program, Statement, Visitor,
+    VisitorMut,
+};
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::printer::{ast_to_source, Config};
+
+/// The smallest program `reduce` found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reduction {
+    pub source: String,
+    /// How many times the test was run
+    pub runs: usize,
+    /// Whether the syntax tree was reduced, rather than lines of text
+    pub by_tree: bool,
+}
+
+/// Shrink `source` to a smaller program that `interesting` still holds for.
+/// `interesting` must hold for `source` itself; it is only called with
+/// programs shorter than the best found so far, so it holding for one means
+/// that one is the new best.
+pub fn reduce(source: &str, interesting: impl FnMut(&str) -> bool) -> Reduction {
+    let mut reducer = Reducer {
+        interesting,
+        best: source.to_string(),
+        tried: HashSet::new(),
+        runs: 0,
+    };
+    let by_tree = match tokenize(source).and_then(parse) {
+        Ok(program) => {
+            let printed = print(&program);
+            let keeps_bug = printed == source || {
+                reducer.runs += 1;
+                (reducer.interesting)(&printed)
+            };
+            if keeps_bug {
+                reducer.best = printed;
+                reducer.reduce_tree(program);
+            }
+            keeps_bug
+        }
+        Err(_) => false,
+    };
+    if !by_tree {
+        reducer.reduce_lines();
+    }
+    Reduction {
+        source: reducer.best,
+        runs: reducer.runs,
+        by_tree,
+    }
+}
+
+fn print(program: &Program) -> String {
+    ast_to_source(program, &Config::default())
+}
+
+struct Reducer<F> {
+    interesting: F,
+    best: String,
+    /// Sources already tested, which a later pass may produce again
+    tried: HashSet<String>,
+    runs: usize,
+}
+
+impl<F: FnMut(&str) -> bool> Reducer<F> {
+    /// Test a candidate and make it the best if it still holds
+    fn try_source(&mut self, source: String) -> bool {
+        if source.len() >= self.best.len() || !self.tried.insert(source.clone()) {
+            return false;
+        }
+        self.runs += 1;
+        if !(self.interesting)(&source) {
+            return false;
+        }
+        self.best = source;
+        true
+    }
+
+    /// Test a candidate tree, which must print as a program that parses
+    fn try_program(&mut self, program: &Program) -> bool {
+        let source = print(program);
+        tokenize(&source).and_then(parse).is_ok() && self.try_source(source)
+    }
+
+    fn reduce_tree(&mut self, mut program: Program) {
+        loop {
+            let before = self.best.len();
+            self.remove_from_lists(&mut program);
+            self.inline_blocks(&mut program);
+            self.simplify_expressions(&mut program);
+            if self.best.len() == before {
+                break;
+            }
+        }
+    }
+
+    /// Remove ever smaller runs of items, methods, parameters and
+    /// statements from each list of them
+    fn remove_from_lists(&mut self, program: &mut Program) {
+        let mut list = 0;
+        while list < list_count(program) {
+            let mut chunk = list_len(program, list);
+            while chunk > 0 {
+                let mut start = 0;
+                while start < list_len(program, list) {
+                    let end = (start + chunk).min(list_len(program, list));
+                    let mut candidate = program.clone();
+                    with_list(&mut candidate, list, |mut l| l.remove(start..end));
+                    if self.try_program(&candidate) {
+                        *program = candidate;
+                    } else {
+                        start = end;
+                    }
+                }
+                chunk /= 2;
+            }
+            list += 1;
+        }
+    }
+
+    /// Replace statements and functions with the statements of one of
+    /// their blocks: an `if` with its `then` branch, a loop with its body
+    fn inline_blocks(&mut self, program: &mut Program) {
+        let mut list = 0;
+        while list < list_count(program) {
+            let mut index = 0;
+            while index < list_len(program, list) {
+                let blocks = with_list(program, list, |l| l.blocks(index)).unwrap_or(0);
+                let inlined = (0..blocks).any(|block| {
+                    let mut candidate = program.clone();
+                    with_list(&mut candidate, list, |mut l| l.inline(index, block));
+                    self.try_program(&candidate) && {
+                        *program = candidate;
+                        true
+                    }
+                });
+                // An inlined block's first statement is now at `index`
+                if !inlined {
+                    index += 1;
+                }
+            }
+            list += 1;
+        }
+    }
+
+    /// Replace expressions with simpler ones, outermost first
+    fn simplify_expressions(&mut self, program: &mut Program) {
+        let mut target = 0;
+        while let Some(candidates) = with_expression(program, target, |e| replacements(e)) {
+            let replaced = candidates.into_iter().any(|replacement| {
+                let mut candidate = program.clone();
+                with_expression(&mut candidate, target, |e| *e = replacement);
+                self.try_program(&candidate) && {
+                    *program = candidate;
+                    true
+                }
+            });
+            if !replaced {
+                target += 1;
+            }
+        }
+    }
+
+    /// Remove ever smaller runs of lines, for programs that are not reduced
+    /// by their tree
+    fn reduce_lines(&mut self) {
+        let mut lines: Vec<String> = self.best.lines().map(str::to_string).collect();
+        let mut chunk = lines.len() / 2;
+        while chunk > 0 {
+            let mut start = 0;
+            while start < lines.len() {
+                let end = (start + chunk).min(lines.len());
+                let candidate: Vec<String> = lines[..start]
+                    .iter()
+                    .chain(&lines[end..])
+                    .cloned()
+                    .collect();
+                let source = candidate.iter().map(|line| format!("{}\n", line)).collect();
+                if self.try_source(source) {
+                    lines = candidate;
+                } else {
+                    start = end;
+                }
+            }
+            chunk /= 2;
+        }
+    }
+}
+
+/// A list whose elements can be removed without the rest of the program
+/// failing to parse
+enum List<'a> {
+    Items(&'a mut Vec<Item>),
+    Methods(&'a mut Vec<Function>),
+    Parameters(&'a mut Vec<Parameter>),
+    Statements(&'a mut Vec<Statement>),
+}
+
+impl List<'_> {
+    fn len(&self) -> usize {
+        match self {
+            List::Items(items) => items.len(),
+            List::Methods(methods) => methods.len(),
+            List::Parameters(parameters) => parameters.len(),
+            List::Statements(statements) => statements.len(),
+        }
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        match self {
+            List::Items(items) => drop(items.drain(range)),
+            List::Methods(methods) => drop(methods.drain(range)),
+            List::Parameters(parameters) => drop(parameters.drain(range)),
+            List::Statements(statements) => drop(statements.drain(range)),
+        }
+    }
+
+    /// How many blocks the element at `index` could be replaced by
+    fn blocks(&self, index: usize) -> usize {
+        match self {
+            List::Items(items) => item_blocks(&items[index]).len(),
+            List::Methods(_) | List::Parameters(_) => 0,
+            List::Statements(statements) => statement_blocks(&statements[index]).len(),
+        }
+    }
+
+    /// Replace the element at `index` with the statements of its `block`-th
+    /// block
+    fn inline(&mut self, index: usize, block: usize) {
+        match self {
+            List::Items(items) => {
+                let body = item_blocks(&items[index])[block].statements.clone();
+                items.splice(index..=index, body.into_iter().map(Item::Statement));
+            }
+            List::Methods(_) | List::Parameters(_) => {}
+            List::Statements(statements) => {
+                let body = statement_blocks(&statements[index])[block]
+                    .statements
+                    .clone();
+                statements.splice(index..=index, body);
+            }
+        }
+    }
+}
+
+fn item_blocks(item: &Item) -> Vec<&Block> {
+    match item {
+        Item::Function(function) => vec![&function.body],
+        Item::Statement(statement) => statement_blocks(statement),
+        _ => Vec::new(),
+    }
+}
+
+fn statement_blocks(statement: &Statement) -> Vec<&Block> {
+    match statement {
+        Statement::If(stmt) => iter::once(&stmt.then_branch)
+            .chain(stmt.elif_branches.iter().map(|(_, block)| block))
+            .chain(&stmt.else_branch)
+            .collect(),
+        Statement::While(stmt) => vec![&stmt.body],
+        Statement::For(stmt) => vec![&stmt.body],
+        Statement::Try(stmt) => iter::once(&stmt.body)
+            .chain(stmt.catch.as_ref().map(|catch| &catch.body))
+            .chain(&stmt.finally)
+            .collect(),
+        Statement::Block(block) | Statement::TaskGroup(block) => vec![block],
+        _ => Vec::new(),
+    }
+}
+
+/// The expressions that could stand in place of `expression`: its
+/// operands, and for calls and collections, itself with one element fewer
+fn replacements(expression: &Expression) -> Vec<Expression> {
+    let mut operands = Operands(Vec::new());
+    walk_expression(&mut operands, expression);
+    let mut replacements = operands.0;
+
+    let count = elements_mut(&mut expression.clone()).map_or(0, |elements| elements.len());
+    for index in 0..count {
+        let mut shorter = expression.clone();
+        if let Some(elements) = elements_mut(&mut shorter) {
+            elements.remove(index);
+        }
+        replacements.push(shorter);
+    }
+    replacements
+}
+
+fn elements_mut(expression: &mut Expression) -> Option<&mut Vec<Expression>> {
+    match expression {
+        Expression::Call(call) => Some(&mut call.arguments),
+        Expression::MethodCall(call) => Some(&mut call.arguments),
+        Expression::Array(array) => Some(&mut array.elements),
+        Expression::Set(set) => Some(&mut set.elements),
+        Expression::Tuple(tuple) => Some(&mut tuple.elements),
+        _ => None,
+    }
+}
+
+/// Collects the expressions directly inside one, leaving out those in the
+/// body of a block lambda
+struct Operands(Vec<Expression>);
+
+impl Visitor for Operands {
+    fn visit_expression(&mut self, expression: &Expression) {
+        self.0.push(expression.clone());
+    }
+
+    fn visit_block(&mut self, _block: &Block) {}
+}
+
+/// Calls `edit` with the `target`-th list of items, methods, parameters or
+/// statements,
+/// counting in the order a walk reaches them
+struct ListAt<E, R> {
+    target: usize,
+    seen: usize,
+    edit: Option<E>,
+    result: Option<R>,
+}
+
+impl<E: FnOnce(List) -> R, R> ListAt<E, R> {
+    fn reach(&mut self, list: List) {
+        if self.seen == self.target {
+            self.result = self.edit.take().map(|edit| edit(list));
+        }
+        self.seen += 1;
+    }
+}
+
+impl<E: FnOnce(List) -> R, R> VisitorMut for ListAt<E, R> {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        self.reach(List::Items(&mut program.items));
+        walk_program_mut(self, program);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        match item {
+            Item::Impl(imp) => self.reach(List::Methods(&mut imp.methods)),
+            Item::Actor(actor) => self.reach(List::Methods(&mut actor.methods)),
+            _ => {}
+        }
+        walk_item_mut(self, item);
+    }
+
+    fn visit_function_mut(&mut self, function: &mut Function) {
+        self.reach(List::Parameters(&mut function.parameters));
+        walk_function_mut(self, function);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if let Expression::Lambda(lambda) = expression {
+            self.reach(List::Parameters(&mut lambda.parameters));
+        }
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        self.reach(List::Statements(&mut block.statements));
+        walk_block_mut(self, block);
+    }
+}
+
+fn with_list<R>(program: &mut Program, target: usize, edit: impl FnOnce(List) -> R) -> Option<R> {
+    let mut visitor = ListAt {
+        target,
+        seen: 0,
+        edit: Some(edit),
+        result: None,
+    };
+    visitor.visit_program_mut(program);
+    visitor.result
+}
+
+fn list_count(program: &mut Program) -> usize {
+    let mut visitor = ListAt {
+        target: usize::MAX,
+        seen: 0,
+        edit: None::<fn(List)>,
+        result: None,
+    };
+    visitor.visit_program_mut(program);
+    visitor.seen
+}
+
+fn list_len(program: &mut Program, list: usize) -> usize {
+    with_list(program, list, |l| l.len()).unwrap_or(0)
+}
+
+/// Calls `edit` with the `target`-th expression, counting in pre-order
+struct ExpressionAt<E, R> {
+    target: usize,
+    seen: usize,
+    edit: Option<E>,
+    result: Option<R>,
+}
+
+impl<E: FnOnce(&mut Expression) -> R, R> VisitorMut for ExpressionAt<E, R> {
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        if self.seen == self.target {
+            self.result = self.edit.take().map(|edit| edit(expression));
+        }
+        self.seen += 1;
+        walk_expression_mut(self, expression);
+    }
+}
+
+fn with_expression<R>(
+    program: &mut Program,
+    target: usize,
+    edit: impl FnOnce(&mut Expression) -> R,
+) -> Option<R> {
+    let mut visitor = ExpressionAt {
+        target,
+        seen: 0,
+        edit: Some(edit),
+        result: None,
+    };
+    visitor.visit_program_mut(program);
+    visitor.result
+}
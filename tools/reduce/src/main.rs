@@ -0,0 +1,209 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use veyra_reduce::reduce;
+
+#[derive(Parser)]
+#[command(name = "veyra-reduce")]
+#[command(about = "Shrink a Veyra program to a minimal one that still reproduces a bug")]
+#[command(version = "0.1.0")]
+#[command(after_help = "Example: veyra-reduce crash.vey --crash -- veyc run")]
+struct Cli {
+    /// The program to reduce
+    input: PathBuf,
+
+    /// Where to write the reduced program [default: <input>.min.vey]
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// The bug reproduces when the command exits with this code [default: 0]
+    #[arg(long, conflicts_with = "crash")]
+    exit_code: Option<i32>,
+
+    /// The bug reproduces when the command panics (exit code 101) or is
+    /// killed by a signal
+    #[arg(long)]
+    crash: bool,
+
+    /// The bug reproduces only when the command prints this text, on
+    /// standard output or standard error
+    #[arg(long)]
+    contains: Option<String>,
+
+    /// Seconds a run may take; a run that takes longer does not reproduce
+    /// the bug
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// The command that checks a program. `{}` in it is replaced by the
+    /// program's path, which is otherwise added at the end
+    #[arg(last = true, required = true, value_name = "COMMAND")]
+    command: Vec<String>,
+}
+
+/// Whether a run of the command reproduces the bug
+struct Test {
+    command: Vec<String>,
+    exit_code: Option<i32>,
+    crash: bool,
+    contains: Option<String>,
+    timeout: Duration,
+}
+
+impl Test {
+    /// Run the command on the program at `path`; an error means it could
+    /// not be started
+    fn reproduces(&self, path: &Path) -> Result<bool> {
+        let path = path.to_string_lossy();
+        let mut args: Vec<String> = self.command[1..]
+            .iter()
+            .map(|arg| arg.replace("{}", &path))
+            .collect();
+        if !self.command[1..].iter().any(|arg| arg.contains("{}")) {
+            args.push(path.to_string());
+        }
+        let mut child = Command::new(&self.command[0])
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("could not run '{}'", self.command[0]))?;
+
+        // Read the output as it comes, so a chatty command never blocks on
+        // a full pipe
+        let stdout = child.stdout.take().map(read_all);
+        let stderr = child.stderr.take().map(read_all);
+        let Some(status) = wait(&mut child, self.timeout)? else {
+            // Whatever the command started may still hold the pipes open,
+            // so their readers are left behind rather than waited for
+            return Ok(false);
+        };
+        if !self.status_matches(status) {
+            return Ok(false);
+        }
+        let Some(text) = &self.contains else {
+            return Ok(true);
+        };
+        let output: Vec<u8> = [stdout, stderr]
+            .into_iter()
+            .flatten()
+            .flat_map(|reader| reader.join().unwrap_or_default())
+            .collect();
+        Ok(String::from_utf8_lossy(&output).contains(text.as_str()))
+    }
+
+    fn status_matches(&self, status: ExitStatus) -> bool {
+        if self.crash {
+            return status.code() == Some(101) || killed_by_signal(status);
+        }
+        status.code() == Some(self.exit_code.unwrap_or(0))
+    }
+}
+
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+/// Wait for the child to exit, killing it after `timeout`
+fn wait(child: &mut std::process::Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(unix)]
+fn killed_by_signal(status: ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal().is_some()
+}
+
+#[cfg(not(unix))]
+fn killed_by_signal(_status: ExitStatus) -> bool {
+    false
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let source = fs::read_to_string(&cli.input)
+        .with_context(|| format!("could not read {}", cli.input.display()))?;
+    let output = cli
+        .output
+        .clone()
+        .unwrap_or_else(|| cli.input.with_extension("min.vey"));
+    let test = Test {
+        command: cli.command,
+        exit_code: cli.exit_code,
+        crash: cli.crash,
+        contains: cli.contains,
+        timeout: Duration::from_secs(cli.timeout),
+    };
+
+    // Candidates are written next to the input, so its imports still
+    // resolve
+    let stem = cli
+        .input
+        .file_stem()
+        .ok_or_else(|| anyhow!("{} is not a file", cli.input.display()))?;
+    let candidate = cli
+        .input
+        .with_file_name(format!(".{}.reduce.vey", stem.to_string_lossy()));
+    let check = |program: &str| -> Result<bool> {
+        fs::write(&candidate, program)?;
+        test.reproduces(&candidate)
+    };
+
+    let result = check(&source).and_then(|reproduces| {
+        if !reproduces {
+            bail!(
+                "the command does not reproduce the bug on {}",
+                cli.input.display()
+            );
+        }
+        // Every program that still reproduces is the best so far, so the
+        // output is always the smallest yet, even if the run is cut short
+        Ok(reduce(&source, |program| {
+            let reproduces = check(program).unwrap_or(false);
+            if reproduces {
+                let _ = fs::write(&output, program);
+            }
+            reproduces
+        }))
+    });
+    let _ = fs::remove_file(&candidate);
+    let reduction = result?;
+    fs::write(&output, &reduction.source)?;
+
+    println!(
+        "Reduced {} from {} to {} bytes in {} runs{}",
+        cli.input.display(),
+        source.len(),
+        reduction.source.len(),
+        reduction.runs,
+        if reduction.by_tree {
+            ""
+        } else {
+            ", line by line"
+        }
+    );
+    println!("Wrote {}", output.display());
+    Ok(())
+}
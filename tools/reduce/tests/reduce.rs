@@ -0,0 +1,127 @@
+// Tests for reducing programs, with the test run in process and through
+// the veyra-reduce command
+
+use std::fs;
+use std::process::Command;
+use veyra_compiler::interpreter::Limits;
+use veyra_compiler::lexer::tokenize;
+use veyra_compiler::parser::parse;
+use veyra_compiler::Interpreter;
+use veyra_reduce::reduce;
+
+const PROGRAM: &str = r#"import std.math
+struct Point {
+    x: int
+    y: int
+}
+fn scale(p, by) {
+    return Point { x: p.x * by, y: p.y * by }
+}
+fn ratio(a, b) {
+    let total = a + b
+    return a / (b - 2)
+}
+let points = [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]
+for p in points {
+    print(scale(p, 2))
+}
+let mut i = 0
+while i < 3 {
+    if i == 2 {
+        print(ratio(i * 10, i))
+    } else {
+        print(i)
+    }
+    i += 1
+}
+print("done")
+"#;
+
+/// Whether the program stops with a division by zero; candidates that
+/// loop forever are cut off
+fn divides_by_zero(source: &str) -> bool {
+    let Ok(program) = tokenize(source).and_then(parse) else {
+        return false;
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.capture_output();
+    interpreter.set_echo_output(false);
+    interpreter.set_limits(Limits {
+        max_steps: Some(10_000),
+        ..Limits::default()
+    });
+    matches!(interpreter.interpret(&program), Err(e) if e.to_string().contains("Division by zero"))
+}
+
+#[test]
+fn reduces_a_program_to_what_reproduces() {
+    assert!(divides_by_zero(PROGRAM));
+    let reduction = reduce(PROGRAM, divides_by_zero);
+    assert!(reduction.by_tree);
+    assert_eq!(
+        reduction.source,
+        "fn ratio(a, b) {\n    return a / b\n}\nlet mut i = 0\nratio(i, i)\n"
+    );
+    assert!(divides_by_zero(&reduction.source));
+    assert!(reduction.runs > 0);
+}
+
+#[test]
+fn keeps_what_the_test_needs() {
+    let reduction = reduce(PROGRAM, |source| {
+        source.contains("fn scale") && source.contains("while")
+    });
+    assert_eq!(reduction.source, "fn scale() {\n}\nwhile i {\n}\n");
+}
+
+#[test]
+fn reduces_programs_that_do_not_parse_by_lines() {
+    let source = "let a = 1\nlet b = (\nprint(a)\nfn f( {\nprint(b)\n";
+    let fails = |source: &str| {
+        tokenize(source)
+            .and_then(parse)
+            .is_err_and(|e| e.to_string().contains("Expected ')'"))
+    };
+    assert!(fails(source));
+    let reduction = reduce(source, fails);
+    assert!(!reduction.by_tree);
+    assert_eq!(reduction.source, "let b = (\nprint(b)\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_a_command_on_each_candidate() {
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let input = dir.join("bug.vey");
+    fs::write(&input, PROGRAM).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-reduce"))
+        .arg(&input)
+        .args(["--", "grep", "-q", "ratio(i \\* 10", "{}"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("from {} to", PROGRAM.len())));
+    assert_eq!(
+        fs::read_to_string(dir.join("bug.min.vey")).unwrap(),
+        "ratio(i * 10)\n"
+    );
+
+    // The input must reproduce the bug to begin with
+    let output = Command::new(env!("CARGO_BIN_EXE_veyra-reduce"))
+        .arg(&input)
+        .args(["--exit-code", "1", "--", "true"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not reproduce the bug"));
+
+    let leftovers: Vec<_> = fs::read_dir(dir).unwrap().collect();
+    assert_eq!(leftovers.len(), 2, "only the input and output remain");
+}
@@ -12,21 +12,24 @@ pub fn init(no_color: bool) {
     INIT.set(()).ok();
     if no_color || std::env::var("NO_COLOR").is_ok() {
         COLORS_ENABLED.store(false, Ordering::SeqCst);
-        return;
+    } else {
+        #[cfg(windows)]
+        enable_virtual_terminal();
     }
-    #[cfg(windows)]
-    {
-        use windows_sys::Win32::System::Console::{
-            GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
-            STD_OUTPUT_HANDLE,
-        };
-        unsafe {
-            let handle = GetStdHandle(STD_OUTPUT_HANDLE);
-            if handle != 0 && handle != (-1isize) as isize {
-                let mut mode: u32 = 0;
-                if GetConsoleMode(handle, &mut mode) != 0 {
-                    let _ = SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
-                }
+}
+
+#[cfg(windows)]
+fn enable_virtual_terminal() {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_OUTPUT_HANDLE,
+    };
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE);
+        if handle != 0 && handle != (-1isize) as isize {
+            let mut mode: u32 = 0;
+            if GetConsoleMode(handle, &mut mode) != 0 {
+                let _ = SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
             }
         }
     }
@@ -54,7 +57,7 @@ fn _basic_strip(input: &str) -> String {
         if b == 0x1B {
             // ESC
             // consume until 'm' or end
-            while let Some(nb) = bytes.next() {
+            for nb in bytes.by_ref() {
                 if nb == b'm' {
                     break;
                 }
@@ -1,10 +1,12 @@
+use crate::config::{ColorScheme, ReplConfig, SETTINGS};
 use crate::state::ReplState;
 use crate::ui::{Table, Theme, UI};
 use anyhow::Result;
+use veyra_compiler::builtins::{self, BuiltinDoc};
 
 /// Handle REPL commands (starting with :)
 pub fn handle_command(cmd: &str, state: &mut ReplState, ui: &mut UI) -> Result<bool> {
-    let parts: Vec<&str> = cmd.trim().split_whitespace().collect();
+    let parts: Vec<&str> = cmd.split_whitespace().collect();
 
     if parts.is_empty() {
         return Ok(true);
@@ -58,6 +60,13 @@ pub fn handle_command(cmd: &str, state: &mut ReplState, ui: &mut UI) -> Result<b
                 ui.error("Usage: :save <filename>");
             }
         }
+        ":export" => {
+            if parts.len() > 1 {
+                export_session(parts[1], state, ui)?;
+            } else {
+                ui.error("Usage: :export <file.md>");
+            }
+        }
         ":load" => {
             if parts.len() > 1 {
                 load_file(parts[1], state, ui)?;
@@ -65,6 +74,31 @@ pub fn handle_command(cmd: &str, state: &mut ReplState, ui: &mut UI) -> Result<b
                 ui.error("Usage: :load <filename>");
             }
         }
+        ":edit" => {
+            if parts.len() > 1 {
+                edit_file(parts[1], state, ui)?;
+            } else {
+                ui.error("Usage: :edit <filename>");
+            }
+        }
+        ":watch" => {
+            if parts.len() > 1 {
+                watch_file(parts[1], state, ui)?;
+            } else {
+                show_watched(state, ui);
+            }
+        }
+        ":unwatch" => {
+            if parts.len() > 1 {
+                if state.unwatch(std::path::Path::new(parts[1])) {
+                    ui.success(&format!("Stopped watching {}", parts[1]));
+                } else {
+                    ui.error(&format!("Not watching {}", parts[1]));
+                }
+            } else {
+                ui.error("Usage: :unwatch <filename>");
+            }
+        }
         ":time" => {
             toggle_timing(state, ui);
         }
@@ -81,6 +115,16 @@ pub fn handle_command(cmd: &str, state: &mut ReplState, ui: &mut UI) -> Result<b
                 ui.error("Usage: :type <expression>");
             }
         }
+        ":doc" => {
+            if parts.len() > 1 {
+                show_doc(parts[1], state, ui);
+            } else {
+                ui.error("Usage: :doc <name>");
+            }
+        }
+        ":cycles" => {
+            show_cycles(state, ui);
+        }
         ":tips" => {
             show_tips(ui);
         }
@@ -123,14 +167,22 @@ fn show_help(ui: &UI) {
         (":vars, :variables", "Show defined variables"),
         (":funcs, :functions", "Show defined functions"),
         (":info", "Show REPL information"),
-        (":config [set key value]", "View or modify configuration"),
+        (":config [show]", "Show the configuration"),
+        (":config set <key> <value>", "Change a setting"),
+        (":config edit", "Edit the configuration file in $EDITOR"),
         (":theme [name]", "Change color theme"),
         (":save <file>", "Save session history to file"),
+        (":export <file.md>", "Export the session as Markdown"),
         (":load <file>", "Load and execute a file"),
+        (":edit <file>", "Open a file in $EDITOR and run it on save"),
+        (":watch [file]", "Reload a file whenever it changes"),
+        (":unwatch <file>", "Stop watching a file"),
         (":time", "Toggle execution timing"),
         (":verbose", "Toggle verbose output"),
         (":multiline", "Toggle multiline mode"),
         (":type <expr>", "Show type of an expression"),
+        (":doc <name>", "Show documentation for a function"),
+        (":cycles", "Find reference cycles that leak memory"),
         (":tips", "Show helpful tips"),
         (":themes", "List available color themes"),
     ];
@@ -205,6 +257,165 @@ fn show_functions(state: &ReplState, ui: &UI) {
     }
 }
 
+/// Editor from $VISUAL or $EDITOR, falling back to the platform default
+fn editor_command() -> String {
+    std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        })
+}
+
+/// Open a file in the user's editor and wait for it to be closed
+fn run_editor(path: &std::path::Path, ui: &UI) -> Result<()> {
+    // The editor may carry its own arguments, e.g. "code --wait"
+    let editor = editor_command();
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to launch editor '{}': {}", editor, e))?;
+
+    if !status.success() {
+        ui.warning(&format!("Editor exited with {}", status));
+    }
+    Ok(())
+}
+
+fn edit_file(filename: &str, state: &mut ReplState, ui: &UI) -> Result<()> {
+    let path = std::path::Path::new(filename);
+    let before = crate::state::modified_time(path);
+
+    run_editor(path, ui)?;
+
+    let after = crate::state::modified_time(path);
+    if after.is_none() {
+        ui.info("File was not saved");
+        return Ok(());
+    }
+    if after == before {
+        ui.info("No changes");
+        return Ok(());
+    }
+
+    reload_file(path, state, ui);
+    Ok(())
+}
+
+fn watch_file(filename: &str, state: &mut ReplState, ui: &UI) -> Result<()> {
+    let path = std::path::Path::new(filename);
+
+    if !path.exists() {
+        ui.error(&format!("File not found: {}", filename));
+        return Ok(());
+    }
+
+    if !state.watch(path) {
+        ui.info(&format!("Already watching {}", filename));
+        return Ok(());
+    }
+
+    reload_file(path, state, ui);
+    ui.info(&format!(
+        "Watching {} (changes are picked up at the next prompt)",
+        filename
+    ));
+    Ok(())
+}
+
+fn show_watched(state: &ReplState, ui: &UI) {
+    let watched = state.watched_files();
+    if watched.is_empty() {
+        ui.info("No files are being watched. Use ':watch <file>' to start");
+        return;
+    }
+
+    ui.section("Watched Files");
+    for path in watched {
+        println!(
+            "  • {}",
+            ui.theme.secondary.paint(path.display().to_string())
+        );
+    }
+}
+
+/// Evaluate a file again, reporting errors without leaving the REPL
+pub fn reload_file(path: &std::path::Path, state: &mut ReplState, ui: &UI) {
+    match state.load_file(path) {
+        Ok(()) => ui.success(&format!("Reloaded {}", path.display())),
+        Err(e) => ui.print_runtime_error(&format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn show_doc(name: &str, state: &ReplState, ui: &UI) {
+    if let Some(function) = state.function(name) {
+        ui.section(&builtins::signature(function));
+        match builtins::docstring(function) {
+            Some(doc) => {
+                for line in doc.lines() {
+                    println!("  {}", line.trim());
+                }
+            }
+            None => println!("  {}", ui.theme.muted.paint("No documentation available.")),
+        }
+        return;
+    }
+
+    match builtins::lookup(name) {
+        Some(doc) => show_builtin_doc(doc, ui),
+        None => {
+            ui.error(&format!("No documentation for '{}'", name));
+            ui.info("Builtins and functions with a docstring can be documented");
+        }
+    }
+}
+
+fn show_builtin_doc(doc: &BuiltinDoc, ui: &UI) {
+    ui.section(doc.signature);
+    println!("  {}", doc.summary);
+
+    if !doc.params.is_empty() {
+        println!();
+        let mut table = Table::new(vec!["Parameter".to_string(), "Description".to_string()]);
+        for (param, description) in doc.params {
+            table.add_row(vec![param.to_string(), description.to_string()]);
+        }
+        table.print(&ui.theme);
+    }
+
+    println!();
+    println!("  {}", ui.theme.primary.bold().paint("Example"));
+    println!("    {}", ui.theme.secondary.paint(doc.example));
+}
+
+fn show_cycles(state: &ReplState, ui: &UI) {
+    let cycles = state.memory_cycles();
+    if cycles.is_empty() {
+        ui.success("No leaked reference cycles");
+        return;
+    }
+
+    ui.section("Leaked Reference Cycles");
+    for (i, group) in cycles.iter().enumerate() {
+        println!(
+            "  {} {}",
+            ui.theme.muted.paint(format!("{}.", i + 1)),
+            group.join(" → ")
+        );
+    }
+    println!();
+    ui.tip("Hold back links with weak_ref() so a cycle does not keep itself alive");
+}
+
 fn show_info(state: &ReplState, ui: &UI) {
     ui.section("REPL Information");
 
@@ -242,10 +453,22 @@ fn show_info(state: &ReplState, ui: &UI) {
         "  Auto indent:       {}",
         if config.auto_indent { "ON" } else { "OFF" }
     );
+    println!(
+        "  Auto close:        {}",
+        if config.auto_close_brackets {
+            "ON"
+        } else {
+            "OFF"
+        }
+    );
     println!(
         "  Fancy errors:      {}",
         if config.fancy_errors { "ON" } else { "OFF" }
     );
+    println!(
+        "  Sandbox:           {}",
+        if config.sandbox { "ON" } else { "OFF" }
+    );
     println!("  Theme:             {:?}", config.color_scheme);
 
     if let Some(time) = state.last_timing() {
@@ -266,46 +489,16 @@ fn show_config(state: &ReplState, ui: &UI) {
     let config = state.config();
 
     let mut table = Table::new(vec!["Setting".to_string(), "Value".to_string()]);
-
-    table.add_row(vec![
-        "syntax_highlighting".to_string(),
-        config.syntax_highlighting.to_string(),
-    ]);
-    table.add_row(vec![
-        "auto_completion".to_string(),
-        config.auto_completion.to_string(),
-    ]);
-    table.add_row(vec![
-        "multiline_mode".to_string(),
-        config.multiline_mode.to_string(),
-    ]);
-    table.add_row(vec![
-        "show_timing".to_string(),
-        config.show_timing.to_string(),
-    ]);
-    table.add_row(vec![
-        "max_history".to_string(),
-        config.max_history.to_string(),
-    ]);
-    table.add_row(vec!["vi_mode".to_string(), config.vi_mode.to_string()]);
-    table.add_row(vec!["show_tips".to_string(), config.show_tips.to_string()]);
-    table.add_row(vec![
-        "auto_indent".to_string(),
-        config.auto_indent.to_string(),
-    ]);
-    table.add_row(vec![
-        "auto_insert_function_parens".to_string(),
-        config.auto_insert_function_parens.to_string(),
-    ]);
-    table.add_row(vec![
-        "fancy_errors".to_string(),
-        config.fancy_errors.to_string(),
-    ]);
-
+    for key in SETTINGS {
+        table.add_row(vec![key.to_string(), config.get(key).unwrap_or_default()]);
+    }
     table.print(&ui.theme);
 
     println!();
-    ui.info("Use ':config set <key> <value>' to change settings");
+    if let Ok(path) = ReplConfig::config_path() {
+        println!("  {}", ui.theme.muted.paint(path.display().to_string()));
+    }
+    ui.info("Use ':config set <key> <value>' or ':config edit' to change settings");
 }
 
 fn handle_config_command(args: &[&str], state: &mut ReplState, ui: &UI) -> Result<()> {
@@ -315,6 +508,7 @@ fn handle_config_command(args: &[&str], state: &mut ReplState, ui: &UI) -> Resul
     }
 
     match args[0] {
+        "show" => show_config(state, ui),
         "set" => {
             if args.len() < 3 {
                 ui.error("Usage: :config set <key> <value>");
@@ -322,88 +516,70 @@ fn handle_config_command(args: &[&str], state: &mut ReplState, ui: &UI) -> Resul
             }
 
             let key = args[1];
-            let value = args[2];
-
-            let config = state.config_mut();
-
-            match key {
-                "syntax_highlighting" => {
-                    config.syntax_highlighting = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set syntax_highlighting to {}", value));
-                }
-                "auto_completion" => {
-                    config.auto_completion = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set auto_completion to {}", value));
-                }
-                "multiline_mode" => {
-                    config.multiline_mode = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set multiline_mode to {}", value));
-                }
-                "show_timing" => {
-                    config.show_timing = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set show_timing to {}", value));
-                }
-                "vi_mode" => {
-                    config.vi_mode = value.parse().unwrap_or(false);
-                    ui.success(&format!("Set vi_mode to {}", value));
-                    ui.warning("Restart REPL for this change to take effect");
-                }
-                "show_tips" => {
-                    config.show_tips = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set show_tips to {}", value));
-                }
-                "auto_indent" => {
-                    config.auto_indent = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set auto_indent to {}", value));
-                }
-                "auto_insert_function_parens" => {
-                    config.auto_insert_function_parens = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set auto_insert_function_parens to {}", value));
-                }
-                "fancy_errors" => {
-                    config.fancy_errors = value.parse().unwrap_or(true);
-                    ui.success(&format!("Set fancy_errors to {}", value));
-                }
-                _ => {
-                    ui.error(&format!("Unknown config key: {}", key));
-                }
+            let value = args[2..].join(" ");
+            if let Err(e) = state.config_mut().set(key, &value) {
+                ui.error(&e.to_string());
+                return Ok(());
             }
-
-            config.save()?;
+            state.save_config()?;
+            ui.success(&format!("Set {} to {}", key, value));
         }
+        "edit" => edit_config(state, ui)?,
         "save" => {
-            state.config().save()?;
+            state.save_config()?;
             ui.success("Configuration saved");
         }
         _ => {
             ui.error(&format!("Unknown config command: {}", args[0]));
+            ui.info("Use ':config show', ':config set <key> <value>' or ':config edit'");
         }
     }
 
     Ok(())
 }
 
-fn change_theme(theme_name: &str, state: &mut ReplState, ui: &mut UI) -> Result<()> {
-    let new_theme = match theme_name.to_lowercase().as_str() {
-        "default" => Some((Theme::default(), crate::config::ColorScheme::Default)),
-        "monokai" => Some((Theme::monokai(), crate::config::ColorScheme::Monokai)),
-        "dracula" => Some((Theme::dracula(), crate::config::ColorScheme::Dracula)),
-        "nord" => Some((Theme::nord(), crate::config::ColorScheme::Nord)),
-        "solarized-dark" => Some((
-            Theme::solarized_dark(),
-            crate::config::ColorScheme::SolarizedDark,
-        )),
-        "solarized-light" => Some((
-            Theme::solarized_light(),
-            crate::config::ColorScheme::SolarizedLight,
+/// Open the configuration file in the user's editor and apply what was
+/// saved
+fn edit_config(state: &mut ReplState, ui: &UI) -> Result<()> {
+    let path = ReplConfig::config_path()?;
+    if !path.exists() {
+        state.save_config()?;
+    }
+
+    run_editor(&path, ui)?;
+
+    if state.config_file_changed() {
+        reload_config(state, ui);
+    } else {
+        ui.info("No changes");
+    }
+    Ok(())
+}
+
+/// Read the configuration file again, with the project's settings applied
+/// on top as at startup. A file that does not parse is reported and the
+/// current settings are kept.
+pub fn reload_config(state: &mut ReplState, ui: &UI) {
+    match ReplConfig::load() {
+        Ok(mut config) => {
+            if let Ok(Some(project)) = veyra_config::Project::discover_from_cwd() {
+                config.apply_project(&project);
+            }
+            state.replace_config(config);
+            ui.success("Configuration reloaded");
+        }
+        Err(e) => ui.error(&format!(
+            "Configuration not reloaded, keeping the current settings: {}",
+            e
         )),
-        _ => None,
-    };
+    }
+}
 
-    if let Some((theme, scheme)) = new_theme {
-        ui.theme = theme;
+fn change_theme(theme_name: &str, state: &mut ReplState, ui: &mut UI) -> Result<()> {
+    if let Some(scheme) = ColorScheme::from_name(theme_name) {
+        ui.theme = Theme::for_scheme(&scheme);
         state.config_mut().color_scheme = scheme;
-        state.config().save()?;
+        state.save_config()?;
         ui.success(&format!("Theme changed to '{}'", theme_name));
     } else {
         ui.error(&format!("Unknown theme: '{}'", theme_name));
@@ -426,6 +602,21 @@ fn save_session(filename: &str, state: &ReplState, ui: &UI) -> Result<()> {
     Ok(())
 }
 
+fn export_session(filename: &str, state: &ReplState, ui: &UI) -> Result<()> {
+    if state.session_len() == 0 {
+        ui.info("Nothing to export yet");
+        return Ok(());
+    }
+
+    state.export_markdown(std::path::Path::new(filename))?;
+    ui.success(&format!(
+        "Exported {} entries to {}",
+        state.session_len(),
+        filename
+    ));
+    Ok(())
+}
+
 fn load_file(filename: &str, state: &mut ReplState, ui: &UI) -> Result<()> {
     use crate::ui::with_spinner;
 
@@ -497,7 +688,7 @@ fn show_tips(ui: &UI) {
         "Save your work with :save <filename> before exiting",
         "Load Veyra files with :load <filename>",
         "Check variable types with :type <expression>",
-        "Use :config to customize your REPL experience",
+        "Use :config edit to change settings in your editor; they apply right away",
         "Arrow keys navigate through command history",
         "The REPL preserves state between commands",
         "Switch themes dynamically with :theme <name>",
@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 /// REPL configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ReplConfig {
     /// Enable syntax highlighting
     pub syntax_highlighting: bool,
@@ -42,6 +42,10 @@ pub struct ReplConfig {
     #[serde(default = "default_true")]
     pub auto_indent: bool,
 
+    /// Close brackets left open when an empty continuation line is submitted
+    #[serde(default = "default_true")]
+    pub auto_close_brackets: bool,
+
     /// When completing a function, auto insert trailing parentheses
     #[serde(default = "default_true")]
     pub auto_insert_function_parens: bool,
@@ -49,9 +53,32 @@ pub struct ReplConfig {
     /// Use fancy (miette) error reports
     #[serde(default = "default_true")]
     pub fancy_errors: bool,
+
+    /// Run code without file, network, process or environment access
+    #[serde(default)]
+    pub sandbox: bool,
+
+    /// What sandboxed code may access anyway, given on the command line
+    #[serde(skip)]
+    pub sandbox_allow: SandboxAllow,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Accesses the sandbox lets code make
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SandboxAllow {
+    /// Files and directories code may read
+    pub read: Vec<PathBuf>,
+    /// Files and directories code may create and write
+    pub write: Vec<PathBuf>,
+    /// Hosts code may connect to
+    pub net: Vec<String>,
+    /// Programs code may run, as it names them
+    pub run: Vec<String>,
+    /// Environment variables code may read
+    pub env: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ColorScheme {
     Default,
     Monokai,
@@ -63,7 +90,7 @@ pub enum ColorScheme {
     Custom,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PromptStyle {
     Simple,
     Minimal,
@@ -72,6 +99,53 @@ pub enum PromptStyle {
     Custom(String),
 }
 
+impl ColorScheme {
+    /// The scheme a theme name like "solarized-dark" stands for
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(Self::Default),
+            "monokai" => Some(Self::Monokai),
+            "dracula" => Some(Self::Dracula),
+            "nord" => Some(Self::Nord),
+            "solarized-dark" => Some(Self::SolarizedDark),
+            "solarized-light" => Some(Self::SolarizedLight),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Monokai => "monokai",
+            Self::Dracula => "dracula",
+            Self::Nord => "nord",
+            Self::SolarizedDark => "solarized-dark",
+            Self::SolarizedLight => "solarized-light",
+            Self::OneDark => "one-dark",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+/// Settings that `:config show` lists and `:config set` changes
+pub const SETTINGS: &[&str] = &[
+    "syntax_highlighting",
+    "auto_completion",
+    "multiline_mode",
+    "show_timing",
+    "max_history",
+    "color_scheme",
+    "vi_mode",
+    "auto_save_history",
+    "show_tips",
+    "startup_script",
+    "auto_indent",
+    "auto_close_brackets",
+    "auto_insert_function_parens",
+    "fancy_errors",
+    "sandbox",
+];
+
 impl Default for ReplConfig {
     fn default() -> Self {
         Self {
@@ -87,8 +161,11 @@ impl Default for ReplConfig {
             show_tips: true,
             startup_script: None,
             auto_indent: true,
+            auto_close_brackets: true,
             auto_insert_function_parens: true,
             fancy_errors: true,
+            sandbox: false,
+            sandbox_allow: SandboxAllow::default(),
         }
     }
 }
@@ -111,6 +188,93 @@ impl ReplConfig {
         }
     }
 
+    /// The value of a setting in `SETTINGS`, as `:config show` prints it
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "syntax_highlighting" => self.syntax_highlighting.to_string(),
+            "auto_completion" => self.auto_completion.to_string(),
+            "multiline_mode" => self.multiline_mode.to_string(),
+            "show_timing" => self.show_timing.to_string(),
+            "max_history" => self.max_history.to_string(),
+            "color_scheme" => self.color_scheme.name().to_string(),
+            "vi_mode" => self.vi_mode.to_string(),
+            "auto_save_history" => self.auto_save_history.to_string(),
+            "show_tips" => self.show_tips.to_string(),
+            "startup_script" => self
+                .startup_script
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            "auto_indent" => self.auto_indent.to_string(),
+            "auto_close_brackets" => self.auto_close_brackets.to_string(),
+            "auto_insert_function_parens" => self.auto_insert_function_parens.to_string(),
+            "fancy_errors" => self.fancy_errors.to_string(),
+            "sandbox" => self.sandbox.to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Change a setting in `SETTINGS` from the text of its new value
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let flag = || -> Result<bool> {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("{} must be true or false", key))
+        };
+        match key {
+            "syntax_highlighting" => self.syntax_highlighting = flag()?,
+            "auto_completion" => self.auto_completion = flag()?,
+            "multiline_mode" => self.multiline_mode = flag()?,
+            "show_timing" => self.show_timing = flag()?,
+            "max_history" => {
+                self.max_history = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("max_history must be a number"))?
+            }
+            "color_scheme" => {
+                self.color_scheme = ColorScheme::from_name(value).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown theme '{}' (default, monokai, dracula, nord, solarized-dark or solarized-light)",
+                        value
+                    )
+                })?
+            }
+            "vi_mode" => self.vi_mode = flag()?,
+            "auto_save_history" => self.auto_save_history = flag()?,
+            "show_tips" => self.show_tips = flag()?,
+            "startup_script" => {
+                self.startup_script = (value != "none").then(|| PathBuf::from(value))
+            }
+            "auto_indent" => self.auto_indent = flag()?,
+            "auto_close_brackets" => self.auto_close_brackets = flag()?,
+            "auto_insert_function_parens" => self.auto_insert_function_parens = flag()?,
+            "fancy_errors" => self.fancy_errors = flag()?,
+            // Turning the sandbox off from inside it would defeat it
+            "sandbox" => anyhow::bail!("sandbox can only be set when the REPL starts"),
+            _ => anyhow::bail!("Unknown config key: {}", key),
+        }
+        Ok(())
+    }
+
+    /// Apply the `[repl]` section of the enclosing project's veyra.toml
+    pub fn apply_project(&mut self, project: &veyra_config::Project) {
+        let repl = &project.config.repl;
+
+        if let Some(script) = &repl.startup_script {
+            self.startup_script = Some(project.resolve(script));
+        }
+        if let Some(enabled) = repl.syntax_highlighting {
+            self.syntax_highlighting = enabled;
+        }
+        if let Some(enabled) = repl.show_tips {
+            self.show_tips = enabled;
+        }
+        if let Some(enabled) = repl.show_timing {
+            self.show_timing = enabled;
+        }
+    }
+
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
@@ -1,12 +1,18 @@
+use nu_ansi_term::{Color, Style};
 use rustyline::completion::{Completer, Pair};
 use rustyline::highlight::Highlighter;
 use rustyline::hint::{Hinter, HistoryHinter};
 use rustyline::validate::{ValidationContext, ValidationResult, Validator};
-use rustyline::{Context, Helper};
+use rustyline::{
+    Cmd, ConditionalEventHandler, Context, Event, EventContext, Helper, Movement, RepeatCount,
+};
 use std::borrow::Cow;
 use std::collections::HashSet;
+use std::ops::Range;
+use veyra_compiler::lexer::{Lexer, Token, TokenClass, TokenKind};
 
 pub struct ReplHelper {
+    completion: bool,
     completer: CommandCompleter,
     hinter: HistoryHinter,
     highlighter: Box<dyn HighlighterAdapter + Send + Sync>,
@@ -14,19 +20,30 @@ pub struct ReplHelper {
 }
 
 impl ReplHelper {
-    pub fn new(enable_highlight: bool, auto_insert_function_parens: bool) -> Self {
-        let highlighter: Box<dyn HighlighterAdapter + Send + Sync> = if enable_highlight {
-            Box::new(SyntectHighlighter::new())
-        } else {
-            Box::new(NoColorHighlighter {})
-        };
+    pub fn new(
+        completion: bool,
+        enable_highlight: bool,
+        auto_insert_function_parens: bool,
+    ) -> Self {
         Self {
+            completion,
             completer: CommandCompleter::new(auto_insert_function_parens),
             hinter: HistoryHinter::new(),
-            highlighter,
+            highlighter: highlighter(enable_highlight),
             validator: BracketValidator::new(),
         }
     }
+    /// Apply changed settings, keeping the names collected for completion
+    pub fn configure(
+        &mut self,
+        completion: bool,
+        enable_highlight: bool,
+        auto_insert_function_parens: bool,
+    ) {
+        self.completion = completion;
+        self.highlighter = highlighter(enable_highlight);
+        self.completer.auto_insert_function_parens = auto_insert_function_parens;
+    }
     pub fn add_variable(&mut self, name: String) {
         self.completer.add_variable(name);
     }
@@ -35,6 +52,14 @@ impl ReplHelper {
     }
 }
 
+fn highlighter(enable: bool) -> Box<dyn HighlighterAdapter + Send + Sync> {
+    if enable {
+        Box::new(LexerHighlighter)
+    } else {
+        Box::new(NoColorHighlighter {})
+    }
+}
+
 impl Helper for ReplHelper {}
 impl Highlighter for ReplHelper {
     fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
@@ -50,7 +75,7 @@ impl Hinter for ReplHelper {
         self.hinter
             .hint(line, pos, ctx)
             .filter(|h| !h.is_empty())
-            .map(|h| format!("\x1b[90m{}\x1b[0m", h))
+            .map(|h| crate::ansi::maybe_strip(format!("\x1b[90m{}\x1b[0m", h)))
     }
 }
 impl Completer for ReplHelper {
@@ -61,6 +86,9 @@ impl Completer for ReplHelper {
         pos: usize,
         ctx: &Context<'_>,
     ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        if !self.completion {
+            return Ok((pos, Vec::new()));
+        }
         self.completer.complete(line, pos, ctx)
     }
 }
@@ -70,6 +98,14 @@ impl Validator for ReplHelper {
     }
 }
 
+/// Words offered for completion besides the names in scope
+pub const KEYWORDS: &[&str] = &[
+    "let", "const", "mut", "fn", "return", "if", "else", "elif", "match", "case", "default", "for",
+    "while", "loop", "break", "continue", "struct", "enum", "trait", "impl", "type", "use", "pub",
+    "mod", "async", "await", "defer", "try", "catch", "finally", "throw", "in", "is", "as", "new",
+    "self", "super", "true", "false", "None", "Some",
+];
+
 pub struct CommandCompleter {
     keywords: HashSet<String>,
     commands: HashSet<String>,
@@ -79,15 +115,7 @@ pub struct CommandCompleter {
 }
 impl CommandCompleter {
     pub fn new(auto_insert_function_parens: bool) -> Self {
-        let keywords: HashSet<String> = [
-            "let", "const", "mut", "fn", "return", "if", "else", "elif", "match", "case",
-            "default", "for", "while", "loop", "break", "continue", "struct", "enum", "trait",
-            "impl", "type", "use", "pub", "mod", "async", "await", "defer", "try", "catch",
-            "throw", "in", "is", "as", "new", "self", "super", "true", "false", "None", "Some",
-        ]
-        .iter()
-        .map(|s| s.to_string())
-        .collect();
+        let keywords: HashSet<String> = KEYWORDS.iter().map(|s| s.to_string()).collect();
         let commands: HashSet<String> = [
             ":help",
             ":exit",
@@ -100,9 +128,15 @@ impl CommandCompleter {
             ":info",
             ":config",
             ":save",
+            ":export",
             ":load",
+            ":edit",
+            ":watch",
+            ":unwatch",
             ":time",
             ":type",
+            ":doc",
+            ":cycles",
             ":ast",
             ":tokens",
             ":verbose",
@@ -215,119 +249,190 @@ impl HighlighterAdapter for NoColorHighlighter {
         Cow::Borrowed(line)
     }
 }
-pub struct SyntaxHighlighter {
-    keywords: HashSet<String>,
-}
-impl SyntaxHighlighter {
-    pub fn new() -> Self {
-        let mut k = HashSet::new();
-        for w in [
-            "let", "const", "mut", "fn", "return", "if", "else", "elif", "match", "case",
-            "default", "for", "while", "loop", "break", "continue", "struct", "enum", "trait",
-            "impl", "type", "use", "pub", "mod", "async", "await", "defer", "try", "catch",
-            "throw", "in", "is", "as", "new", "self", "super", "true", "false", "None", "Some",
-        ] {
-            k.insert(w.to_string());
+/// Highlights input using the compiler's lexer so colors follow the
+/// language's actual tokens.
+pub struct LexerHighlighter;
+impl LexerHighlighter {
+    fn token_style(kind: &TokenKind, next: Option<&TokenKind>) -> Option<Style> {
+        let class = TokenClass::of(kind, next)?;
+        Some(match class {
+            TokenClass::Boolean | TokenClass::Constant => Color::Yellow.normal(),
+            TokenClass::Number => Color::Magenta.normal(),
+            TokenClass::Text => Color::Green.normal(),
+            TokenClass::Comment => Color::DarkGray.italic(),
+            TokenClass::Attribute => Color::Purple.normal(),
+            TokenClass::Call => Color::Blue.normal(),
+            // Every other class is a keyword
+            _ => Color::Cyan.bold(),
+        })
+    }
+
+    /// Indices of closing brackets that do not match the innermost open one
+    fn mismatched_brackets(tokens: &[(Token, Range<usize>)]) -> HashSet<usize> {
+        let mut open = Vec::new();
+        let mut mismatched = HashSet::new();
+        for (i, (token, _)) in tokens.iter().enumerate() {
+            let expected = match token.kind {
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::LeftBrace => {
+                    open.push(&token.kind);
+                    continue;
+                }
+                TokenKind::RightParen => TokenKind::LeftParen,
+                TokenKind::RightBracket => TokenKind::LeftBracket,
+                TokenKind::RightBrace => TokenKind::LeftBrace,
+                _ => continue,
+            };
+            if open.last() == Some(&&expected) {
+                open.pop();
+            } else {
+                mismatched.insert(i);
+            }
         }
-        Self { keywords: k }
+        mismatched
     }
 }
-impl HighlighterAdapter for SyntaxHighlighter {
+impl HighlighterAdapter for LexerHighlighter {
     fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
-        use nu_ansi_term::Color;
-        let mut out = String::new();
-        let mut cur = String::new();
-        let mut in_str = false;
-        let mut q = '\0';
-        let mut str_buf = String::new();
-        for ch in line.chars() {
-            if in_str {
-                if ch == q {
-                    in_str = false;
-                    str_buf.push(ch);
-                    out.push_str(&Color::Green.paint(&str_buf).to_string());
-                    str_buf.clear();
-                } else {
-                    str_buf.push(ch);
-                }
-                continue;
-            }
-            if ch == '"' || ch == '\'' {
-                if !cur.is_empty() {
-                    self.color_word(&mut out, &cur);
-                    cur.clear();
-                }
-                in_str = true;
-                q = ch;
-                str_buf.push(ch);
-            } else if ch.is_alphanumeric() || ch == '_' {
-                cur.push(ch);
+        if line.trim().is_empty() || line.trim_start().starts_with(':') {
+            return Cow::Borrowed(line);
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let text = |range: Range<usize>| chars[range].iter().collect::<String>();
+
+        let (tokens, error) = Lexer::new(line).tokenize_with_ranges();
+        let mismatched = Self::mismatched_brackets(&tokens);
+
+        let mut out = String::with_capacity(line.len() * 2);
+        let mut last = 0;
+        for (i, (token, range)) in tokens.iter().enumerate() {
+            out.push_str(&text(last..range.start));
+            let lexeme = text(range.clone());
+            let next = tokens.get(i + 1).map(|(t, _)| &t.kind);
+            if mismatched.contains(&i) {
+                out.push_str(&Color::Red.bold().underline().paint(lexeme).to_string());
+            } else if let Some(style) = Self::token_style(&token.kind, next) {
+                out.push_str(&style.paint(lexeme).to_string());
             } else {
-                if !cur.is_empty() {
-                    self.color_word(&mut out, &cur);
-                    cur.clear();
-                }
-                out.push(ch);
+                out.push_str(&lexeme);
             }
+            last = range.end;
         }
-        if !cur.is_empty() {
-            self.color_word(&mut out, &cur);
-        }
-        if in_str {
-            out.push_str(&Color::Green.paint(&str_buf).to_string());
+
+        // Everything from a lexer error onwards (e.g. an unterminated string)
+        // is marked so the problem is visible before pressing Enter
+        if let Some((_, range)) = error {
+            out.push_str(&text(last..range.start));
+            let rest = text(range);
+            let style = if rest.starts_with('"') || rest.starts_with('\'') {
+                Color::Green.underline()
+            } else {
+                Color::Red.underline()
+            };
+            out.push_str(&style.paint(rest).to_string());
+        } else {
+            out.push_str(&text(last..chars.len()));
         }
         Cow::Owned(out)
     }
 }
-impl SyntaxHighlighter {
-    fn color_word(&self, out: &mut String, word: &str) {
-        use nu_ansi_term::Color;
-        if self.keywords.contains(word) {
-            out.push_str(&Color::Cyan.bold().paint(word).to_string());
-        } else if word == "true" || word == "false" {
-            out.push_str(&Color::Yellow.paint(word).to_string());
-        } else if word.chars().all(|c| c.is_numeric()) {
-            out.push_str(&Color::Magenta.paint(word).to_string());
-        } else {
-            out.push_str(word);
+
+/// Indentation inserted per open bracket
+pub const INDENT: &str = "    ";
+
+/// Brackets opened in `input` and not yet closed, innermost last. They are
+/// read from the lexer's tokens, so brackets in strings and comments are
+/// skipped, as is anything after an unclosed string.
+pub fn open_brackets(input: &str) -> Vec<char> {
+    let (tokens, _) = Lexer::new(input).tokenize_with_ranges();
+    let mut open = Vec::new();
+    for (token, _) in tokens {
+        match token.kind {
+            TokenKind::LeftParen => open.push('('),
+            TokenKind::LeftBracket => open.push('['),
+            TokenKind::LeftBrace => open.push('{'),
+            TokenKind::RightParen | TokenKind::RightBracket | TokenKind::RightBrace => {
+                open.pop();
+            }
+            _ => {}
         }
     }
+    open
 }
-pub struct SyntectHighlighter {
-    ps: syntect::parsing::SyntaxSet,
-    ts: syntect::highlighting::ThemeSet,
-    theme: syntect::highlighting::Theme,
+
+/// Append a closer for each bracket left open, one per line
+pub fn close_brackets(input: &str) -> String {
+    let mut open = open_brackets(input);
+    let mut out = input.trim_end().to_string();
+    while let Some(bracket) = open.pop() {
+        out.push('\n');
+        out.push_str(&INDENT.repeat(open.len()));
+        out.push(match bracket {
+            '(' => ')',
+            '[' => ']',
+            _ => '}',
+        });
+    }
+    out
 }
-impl SyntectHighlighter {
-    pub fn new() -> Self {
-        let ps = syntect::parsing::SyntaxSet::load_defaults_newlines();
-        let ts = syntect::highlighting::ThemeSet::load_defaults();
-        let theme = ts
-            .themes
-            .get("base16-ocean.dark")
-            .cloned()
-            .or_else(|| ts.themes.values().next().cloned())
-            .expect("theme");
-        Self { ps, ts, theme }
+
+/// Enter inside an unfinished block: indent the new line to the nesting
+/// depth, or submit with brackets auto-closed when the line is left empty.
+pub struct EnterHandler {
+    auto_indent: bool,
+    auto_close: bool,
+}
+impl EnterHandler {
+    pub fn new(auto_indent: bool, auto_close: bool) -> Self {
+        Self {
+            auto_indent,
+            auto_close,
+        }
     }
 }
-impl HighlighterAdapter for SyntectHighlighter {
-    fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
-        use syntect::easy::HighlightLines;
-        use syntect::util::as_24_bit_terminal_escaped;
-        if line.trim().is_empty() {
-            return Cow::Borrowed(line);
+impl ConditionalEventHandler for EnterHandler {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        let line = ctx.line();
+        if line.trim_start().starts_with(':') || ctx.pos() < line.len() {
+            return None;
+        }
+        let depth = open_brackets(line).len();
+        if depth == 0 {
+            return None;
+        }
+        let current = &line[line.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+        if self.auto_close && line.contains('\n') && current.trim().is_empty() {
+            // The REPL loop closes the brackets of accepted incomplete input
+            Some(Cmd::AcceptLine)
+        } else if self.auto_indent {
+            Some(Cmd::Insert(1, format!("\n{}", INDENT.repeat(depth))))
+        } else {
+            None
+        }
+    }
+}
+
+/// Typing a closing bracket on an otherwise blank line dedents it one level
+pub struct DedentHandler;
+impl ConditionalEventHandler for DedentHandler {
+    fn handle(&self, evt: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        let closer = match evt.get(0) {
+            Some(rustyline::KeyEvent(rustyline::KeyCode::Char(c), _)) => *c,
+            _ => return None,
+        };
+        let before = &ctx.line()[..ctx.pos()];
+        let current = &before[before.rfind('\n').map(|i| i + 1).unwrap_or(0)..];
+        if current.is_empty() || !current.trim().is_empty() {
+            return None;
         }
-        let syntax = self
-            .ps
-            .find_syntax_by_extension("rs")
-            .unwrap_or(self.ps.find_syntax_plain_text());
-        let mut h = HighlightLines::new(syntax, &self.theme);
-        let ranges = h.highlight_line(line, &self.ps).unwrap_or_default();
-        Cow::Owned(as_24_bit_terminal_escaped(&ranges[..], false))
+        let depth = open_brackets(before).len().saturating_sub(1);
+        Some(Cmd::Replace(
+            Movement::BeginningOfLine,
+            Some(format!("{}{}", INDENT.repeat(depth), closer)),
+        ))
     }
 }
 
+#[derive(Default)]
 pub struct BracketValidator;
 impl BracketValidator {
     pub fn new() -> Self {
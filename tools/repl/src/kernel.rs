@@ -0,0 +1,348 @@
+// Kernel mode: the REPL's evaluation engine served over a socket
+//
+// `veyra-repl --kernel` listens for frontends such as notebooks and editor
+// panes. Each line a frontend sends is a JSON-RPC 2.0 request, and each
+// request with an `id` gets a response line:
+//
+//   execute    {code}          run code in the session
+//   complete   {code, cursor}  names that complete the word before cursor
+//   inspect    {code, cursor}  documentation or value of the name at cursor
+//   interrupt  {}              stop the code that is running
+//   shutdown   {}              stop the kernel
+//
+// Cursors count characters. Frontends connect one at a time and share the
+// session, so a reconnecting frontend finds its variables still defined.
+//
+// The kernel runs code with its user's access, so it only takes requests
+// from whoever started it: it prints a random key next to its address, and
+// each request must give that key as its `key` member. Requests without it
+// are answered with an error and not run.
+
+use crate::helper::KEYWORDS;
+use crate::state::{format_value, type_name, ReplState};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::{json, Value as Json};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use veyra_compiler::{builtins, interpreter::Value};
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const UNAUTHORIZED: i64 = -32001;
+
+#[derive(Deserialize)]
+struct Request {
+    /// Absent for notifications, which get no response
+    #[serde(default)]
+    id: Option<Json>,
+    method: String,
+    #[serde(default)]
+    params: Json,
+    /// The kernel's key, which every request must give
+    #[serde(default)]
+    key: Option<String>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+type Response = std::result::Result<Json, RpcError>;
+
+/// Serve the session on `address` until a frontend asks for shutdown. The
+/// address actually bound, with any port 0 filled in, and the key requests
+/// must give are printed as one JSON line on stdout for the frontend that
+/// started the kernel.
+pub fn run(address: &str, mut state: ReplState) -> Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let interrupt = Arc::new(AtomicBool::new(false));
+    state.set_echo_output(false);
+    state.set_interrupt(interrupt.clone());
+
+    let key = new_key()?;
+    let address = listener.local_addr()?;
+    println!(
+        "{}",
+        json!({ "transport": "tcp", "address": address.to_string(), "key": key })
+    );
+    std::io::stdout().flush()?;
+
+    let mut kernel = Kernel {
+        state,
+        key: Arc::from(key),
+        interrupt,
+        busy: Arc::new(AtomicBool::new(false)),
+    };
+    for stream in listener.incoming() {
+        if !kernel.serve(stream?)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Kernel {
+    state: ReplState,
+    /// What requests must give as their `key`
+    key: Arc<str>,
+    /// Set to stop the running code
+    interrupt: Arc<AtomicBool>,
+    /// Whether code is running, so that an interrupt has something to stop
+    busy: Arc<AtomicBool>,
+}
+
+impl Kernel {
+    /// Answer one frontend's requests until it disconnects; returns false
+    /// once it asked for shutdown.
+    ///
+    /// Code runs on this thread, while a reader thread takes the requests
+    /// off the socket. It answers `interrupt` itself, since the request
+    /// has to get through while code is running.
+    fn serve(&mut self, stream: TcpStream) -> Result<bool> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        let (requests, received) = mpsc::channel();
+
+        let replies = writer.clone();
+        let key = self.key.clone();
+        let interrupt = self.interrupt.clone();
+        let busy = self.busy.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let request = match parse_request(&line) {
+                    Ok(request) => request,
+                    Err(error) => {
+                        send(&replies, &Some(Json::Null), Err(error));
+                        continue;
+                    }
+                };
+                if !request
+                    .key
+                    .as_deref()
+                    .is_some_and(|given| same_key(given, &key))
+                {
+                    let error = RpcError::new(UNAUTHORIZED, "Missing or wrong kernel key");
+                    send(&replies, &request.id, Err(error));
+                    continue;
+                }
+                if request.method == "interrupt" {
+                    let running = busy.load(Ordering::SeqCst);
+                    if running {
+                        interrupt.store(true, Ordering::SeqCst);
+                    }
+                    send(&replies, &request.id, Ok(json!({ "interrupted": running })));
+                } else if requests.send(request).is_err() {
+                    break;
+                }
+            }
+        });
+
+        for request in received {
+            let response = self.handle(&request.method, &request.params);
+            send(&writer, &request.id, response);
+            if request.method == "shutdown" {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn handle(&mut self, method: &str, params: &Json) -> Response {
+        match method {
+            "execute" => Ok(self.execute(string_param(params, "code")?)),
+            "complete" => {
+                let code = string_param(params, "code")?;
+                Ok(self.complete(code, cursor_param(params, code)?))
+            }
+            "inspect" => {
+                let code = string_param(params, "code")?;
+                Ok(self.inspect(code, cursor_param(params, code)?))
+            }
+            "shutdown" => Ok(Json::Null),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            )),
+        }
+    }
+
+    /// Run code, with what it printed and the value it produced. Failing
+    /// code is a result too, with `status` "error".
+    fn execute(&mut self, code: &str) -> Json {
+        let entries = self.state.session_len();
+        // An interrupt sent while nothing ran must not stop this code
+        self.interrupt.store(false, Ordering::SeqCst);
+        self.busy.store(true, Ordering::SeqCst);
+        let start = Instant::now();
+        let result = self.state.execute(code);
+        let timing = start.elapsed().as_secs_f64() * 1000.0;
+        self.busy.store(false, Ordering::SeqCst);
+
+        let output = if self.state.session_len() > entries {
+            self.state.last_output()
+        } else {
+            ""
+        };
+        let mut reply = json!({
+            "status": "ok",
+            "execution_count": self.state.session_len(),
+            "output": output,
+            "value": null,
+            "type": null,
+            "timing_ms": timing,
+        });
+        match result {
+            Ok(Some(value)) if !matches!(value, Value::None) => {
+                reply["value"] = json!(format_value(&value));
+                reply["type"] = json!(type_name(&value));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                reply["status"] = json!("error");
+                reply["error"] = json!(e.to_string());
+            }
+        }
+        reply
+    }
+
+    /// Keywords, builtins and session names starting with the word that
+    /// ends at `cursor`
+    fn complete(&self, code: &str, cursor: usize) -> Json {
+        let (start, _) = word_at(code, cursor);
+        let prefix: String = code.chars().skip(start).take(cursor - start).collect();
+        let mut matches: Vec<&str> = if prefix.is_empty() {
+            Vec::new()
+        } else {
+            let interpreter = self.state.interpreter();
+            KEYWORDS
+                .iter()
+                .copied()
+                .chain(builtins::BUILTINS.iter().map(|doc| doc.name))
+                .chain(interpreter.function_names())
+                .chain(interpreter.globals().map(|(name, _)| name))
+                .filter(|name| name.starts_with(&prefix))
+                .collect()
+        };
+        matches.sort_unstable();
+        matches.dedup();
+        json!({ "matches": matches, "cursor_start": start, "cursor_end": cursor })
+    }
+
+    /// What the name under `cursor` is: a variable's type and value, or the
+    /// documentation of a function or builtin
+    fn inspect(&self, code: &str, cursor: usize) -> Json {
+        let (start, end) = word_at(code, cursor);
+        let name: String = code.chars().skip(start).take(end - start).collect();
+        let interpreter = self.state.interpreter();
+        let text = interpreter
+            .globals()
+            .find(|(global, _)| *global == name)
+            .map(|(_, value)| format!("{}: [{}] {}", name, type_name(value), format_value(value)))
+            .or_else(|| interpreter.describe(&name));
+        json!({ "found": text.is_some(), "name": name, "text": text })
+    }
+}
+
+/// 16 random bytes from the operating system, in hex
+fn new_key() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| anyhow::anyhow!("Cannot make a kernel key: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Whether a request gave the kernel's key, comparing every byte so the
+/// time taken does not tell how much of it was right
+fn same_key(given: &str, key: &str) -> bool {
+    given.len() == key.len()
+        && given
+            .bytes()
+            .zip(key.bytes())
+            .fold(0, |differ, (a, b)| differ | (a ^ b))
+            == 0
+}
+
+fn parse_request(line: &str) -> std::result::Result<Request, RpcError> {
+    let message: Json = serde_json::from_str(line)
+        .map_err(|e| RpcError::new(PARSE_ERROR, format!("Invalid JSON: {}", e)))?;
+    serde_json::from_value(message)
+        .map_err(|e| RpcError::new(INVALID_REQUEST, format!("Invalid request: {}", e)))
+}
+
+/// Write the response to a request, unless it was a notification
+fn send(writer: &Mutex<TcpStream>, id: &Option<Json>, response: Response) {
+    let Some(id) = id else { return };
+    let message = match response {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    };
+    let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+    // A frontend that went away is noticed by the reader thread
+    let _ = writeln!(writer, "{}", message).and_then(|_| writer.flush());
+}
+
+fn string_param<'a>(params: &'a Json, name: &str) -> std::result::Result<&'a str, RpcError> {
+    params[name].as_str().ok_or_else(|| {
+        RpcError::new(
+            INVALID_PARAMS,
+            format!("Missing string parameter '{}'", name),
+        )
+    })
+}
+
+/// The `cursor` parameter, which defaults to the end of the code
+fn cursor_param(params: &Json, code: &str) -> std::result::Result<usize, RpcError> {
+    let length = code.chars().count();
+    match &params["cursor"] {
+        Json::Null => Ok(length),
+        cursor => cursor
+            .as_u64()
+            .map(|cursor| cursor as usize)
+            .filter(|cursor| *cursor <= length)
+            .ok_or_else(|| {
+                RpcError::new(
+                    INVALID_PARAMS,
+                    format!("'cursor' must be a character offset from 0 to {}", length),
+                )
+            }),
+    }
+}
+
+/// The character range of the identifier around `cursor`
+fn word_at(code: &str, cursor: usize) -> (usize, usize) {
+    let chars: Vec<char> = code.chars().collect();
+    let is_word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let start = cursor
+        - chars[..cursor]
+            .iter()
+            .rev()
+            .take_while(|c| is_word(c))
+            .count();
+    let end = cursor + chars[cursor..].iter().take_while(|c| is_word(c)).count();
+    (start, end)
+}
@@ -0,0 +1,8 @@
+// Line editing for the Veyra REPL
+//
+// The rustyline helper that highlights, completes and validates input, and
+// the bracket handling behind auto-indent and auto-close, kept apart from
+// the REPL binary so they can be tested.
+
+pub mod ansi;
+pub mod helper;
@@ -1,20 +1,21 @@
-mod ansi;
 mod commands;
 mod config;
-mod helper;
+mod kernel;
 mod state;
 mod ui;
 
 use anyhow::Result;
 use clap::Parser;
-use config::ReplConfig;
-use helper::ReplHelper;
+use config::{ColorScheme, ReplConfig, SandboxAllow};
+use helper::{DedentHandler, EnterHandler, ReplHelper};
 use rustyline::config::Configurer;
 use rustyline::error::ReadlineError;
-use rustyline::{EditMode, Editor};
+use rustyline::history::FileHistory;
+use rustyline::{EditMode, Editor, EventHandler, KeyCode, KeyEvent, Modifiers};
 use state::{format_value, ReplState};
 use std::path::PathBuf;
 use ui::{Theme, UI};
+use veyra_repl::{ansi, helper};
 
 #[derive(Parser)]
 #[command(
@@ -59,17 +60,60 @@ struct Cli {
     /// Execute code and exit
     #[arg(short, long, value_name = "CODE")]
     execute: Option<String>,
+
+    /// Deny file, network, process and environment access to evaluated code
+    #[arg(long)]
+    sandbox: bool,
+
+    /// Let sandboxed code read files under PATH (implies --sandbox)
+    #[arg(long, value_name = "PATH")]
+    allow_read: Vec<PathBuf>,
+
+    /// Let sandboxed code write files under PATH (implies --sandbox)
+    #[arg(long, value_name = "PATH")]
+    allow_write: Vec<PathBuf>,
+
+    /// Let sandboxed code connect to HOST; `*.example.com` also matches
+    /// subdomains (implies --sandbox)
+    #[arg(long, value_name = "HOST")]
+    allow_net: Vec<String>,
+
+    /// Let sandboxed code run PROGRAM, named exactly as the code names it
+    /// (implies --sandbox)
+    #[arg(long, value_name = "PROGRAM")]
+    allow_run: Vec<String>,
+
+    /// Let sandboxed code read the environment variable NAME; `*` allows
+    /// every variable (implies --sandbox)
+    #[arg(long, value_name = "NAME")]
+    allow_env: Vec<String>,
+
+    /// Serve the session to a frontend over JSON-RPC instead of reading
+    /// input, listening on ADDRESS (by default a free local port)
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = "127.0.0.1:0"
+    )]
+    kernel: Option<String>,
 }
 
 fn main() -> Result<()> {
     // Parse CLI
     let cli = Cli::parse();
+    let no_highlight = cli.no_highlight;
     // Initialize ANSI/Color environment early
     ansi::init(cli.no_color);
 
     // Load or create configuration
     let mut config = ReplConfig::load().unwrap_or_default();
 
+    // Project settings from veyra.toml take precedence over user settings
+    if let Ok(Some(project)) = veyra_config::Project::discover_from_cwd() {
+        config.apply_project(&project);
+    }
+
     // Override config with CLI arguments
     if cli.no_highlight {
         config.syntax_highlighting = false;
@@ -83,29 +127,36 @@ fn main() -> Result<()> {
     if cli.no_tips {
         config.show_tips = false;
     }
+    config.sandbox_allow = SandboxAllow {
+        read: cli.allow_read,
+        write: cli.allow_write,
+        net: cli.allow_net,
+        run: cli.allow_run,
+        env: cli.allow_env,
+    };
+    if cli.sandbox || config.sandbox_allow != SandboxAllow::default() {
+        config.sandbox = true;
+    }
     if let Some(startup) = cli.startup {
         config.startup_script = Some(startup);
     }
 
-    // Initialize UI with theme from config or CLI
-    let theme = if let Some(ref theme_name) = cli.theme {
-        match theme_name.as_str() {
-            "monokai" => Theme::monokai(),
-            "dracula" => Theme::dracula(),
-            "nord" => Theme::nord(),
-            "solarized-dark" => Theme::solarized_dark(),
-            "solarized-light" => Theme::solarized_light(),
-            _ => Theme::default(),
-        }
-    } else {
-        match config.color_scheme {
-            crate::config::ColorScheme::Monokai => Theme::monokai(),
-            crate::config::ColorScheme::Dracula => Theme::dracula(),
-            crate::config::ColorScheme::Nord => Theme::nord(),
-            crate::config::ColorScheme::SolarizedDark => Theme::solarized_dark(),
-            crate::config::ColorScheme::SolarizedLight => Theme::solarized_light(),
-            _ => Theme::default(),
+    // Kernel mode prints nothing but the address it listens on
+    if let Some(address) = cli.kernel {
+        let mut state = ReplState::new(config.clone());
+        if let Some(startup_path) = config.startup_script.filter(|path| path.exists()) {
+            if let Err(e) = state.load_file(&startup_path) {
+                eprintln!("Failed to load startup script: {}", e);
+            }
         }
+        return kernel::run(&address, state);
+    }
+
+    // Initialize UI with theme from config or CLI
+    let theme = match cli.theme.as_deref().and_then(ColorScheme::from_name) {
+        Some(scheme) => Theme::for_scheme(&scheme),
+        None if cli.theme.is_some() => Theme::default(),
+        None => Theme::for_scheme(&config.color_scheme),
     };
     let mut ui = UI::new(theme);
 
@@ -153,23 +204,9 @@ fn main() -> Result<()> {
     }
 
     // Create rustyline editor with helper
-    let mut rl = Editor::<ReplHelper, rustyline::history::FileHistory>::new()?;
-
-    // Set edit mode
-    if config.vi_mode {
-        rl.set_edit_mode(EditMode::Vi);
-    } else {
-        rl.set_edit_mode(EditMode::Emacs);
-    }
+    let mut rl = Editor::<ReplHelper, FileHistory>::new()?;
 
-    // Set helper for completion and highlighting (advanced if syntect present)
-    if config.auto_completion || (config.syntax_highlighting && !cli.no_highlight) {
-        let enable_highlight = config.syntax_highlighting && !cli.no_highlight;
-        rl.set_helper(Some(ReplHelper::new(
-            enable_highlight,
-            config.auto_insert_function_parens,
-        )));
-    }
+    apply_config(&mut rl, &mut ui, &config, None, no_highlight)?;
 
     // Load history
     let history_path = ReplConfig::history_path()?;
@@ -180,6 +217,25 @@ fn main() -> Result<()> {
 
     // Main REPL loop
     loop {
+        // Pick up edits to files registered with :watch and to the
+        // configuration file
+        if !state.is_multiline() {
+            for path in state.changed_watched_files() {
+                commands::reload_file(&path, &mut state, &ui);
+            }
+            if state.config_file_changed() {
+                let previous = state.config().clone();
+                commands::reload_config(&mut state, &ui);
+                apply_config(
+                    &mut rl,
+                    &mut ui,
+                    state.config(),
+                    Some(&previous),
+                    no_highlight,
+                )?;
+            }
+        }
+
         // Determine prompt
         let prompt = if state.is_multiline() {
             ui.get_continuation_prompt()
@@ -187,9 +243,27 @@ fn main() -> Result<()> {
             ui.get_prompt("veyra")
         };
 
-        // Read line
-        match rl.readline(&prompt) {
+        // Read line, pre-indenting continuation lines collected by the REPL
+        let readline = if state.is_multiline() && state.config().auto_indent {
+            let indent =
+                helper::INDENT.repeat(helper::open_brackets(state.multiline_input()).len());
+            rl.readline_with_initial(&prompt, (&indent, ""))
+        } else {
+            rl.readline(&prompt)
+        };
+
+        match readline {
             Ok(line) => {
+                // Input accepted with brackets still open was submitted from an
+                // empty continuation line for auto-closing
+                let line = if rl.helper().is_some()
+                    && state.config().auto_close_brackets
+                    && needs_more_lines(&line)
+                {
+                    helper::close_brackets(&line)
+                } else {
+                    line
+                };
                 let input = line.trim();
 
                 // Skip empty lines
@@ -201,7 +275,16 @@ fn main() -> Result<()> {
                 if input.starts_with(':') && !state.is_multiline() {
                     rl.add_history_entry(&line)?;
 
-                    match commands::handle_command(input, &mut state, &mut ui) {
+                    let previous = state.config().clone();
+                    let result = commands::handle_command(input, &mut state, &mut ui);
+                    apply_config(
+                        &mut rl,
+                        &mut ui,
+                        state.config(),
+                        Some(&previous),
+                        no_highlight,
+                    )?;
+                    match result {
                         Ok(true) => continue,
                         Ok(false) => break,
                         Err(e) => {
@@ -283,7 +366,7 @@ fn main() -> Result<()> {
     }
 
     // Save history
-    if config.auto_save_history {
+    if state.config().auto_save_history {
         if let Err(e) = rl.save_history(&history_path) {
             eprintln!("Warning: Failed to save history: {}", e);
         }
@@ -292,8 +375,81 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-/// Check if input needs more lines (unclosed brackets)
+/// Set up the line editor for the configuration: at startup, and whenever
+/// it changed since `previous` through a command or an edit of the
+/// configuration file. The theme only changes with the color scheme, so one
+/// chosen with --theme stays until then.
+fn apply_config(
+    rl: &mut Editor<ReplHelper, FileHistory>,
+    ui: &mut UI,
+    config: &ReplConfig,
+    previous: Option<&ReplConfig>,
+    no_highlight: bool,
+) -> Result<()> {
+    if previous == Some(config) {
+        return Ok(());
+    }
+    if previous.is_some_and(|previous| previous.color_scheme != config.color_scheme) {
+        ui.theme = Theme::for_scheme(&config.color_scheme);
+    }
+
+    rl.set_edit_mode(if config.vi_mode {
+        EditMode::Vi
+    } else {
+        EditMode::Emacs
+    });
+    rl.set_max_history_size(config.max_history)?;
+
+    // Set helper for completion and highlighting
+    let highlight = config.syntax_highlighting && !no_highlight;
+    if config.auto_completion || highlight {
+        let colored = highlight && ansi::colors_enabled();
+        match rl.helper_mut() {
+            Some(helper) => helper.configure(
+                config.auto_completion,
+                colored,
+                config.auto_insert_function_parens,
+            ),
+            None => rl.set_helper(Some(ReplHelper::new(
+                config.auto_completion,
+                colored,
+                config.auto_insert_function_parens,
+            ))),
+        }
+    } else {
+        rl.set_helper(None);
+    }
+
+    // Smart indentation and bracket auto-closing for multiline input
+    let enter = KeyEvent(KeyCode::Enter, Modifiers::NONE);
+    if rl.helper().is_some() && (config.auto_indent || config.auto_close_brackets) {
+        rl.bind_sequence(
+            enter,
+            EventHandler::Conditional(Box::new(EnterHandler::new(
+                config.auto_indent,
+                config.auto_close_brackets,
+            ))),
+        );
+    } else {
+        rl.unbind_sequence(enter);
+    }
+    for closer in [')', ']', '}'] {
+        let key = KeyEvent(KeyCode::Char(closer), Modifiers::NONE);
+        if rl.helper().is_some() && config.auto_indent {
+            rl.bind_sequence(key, EventHandler::Conditional(Box::new(DedentHandler)));
+        } else {
+            rl.unbind_sequence(key);
+        }
+    }
+    Ok(())
+}
+
+/// Check if input needs more lines (unclosed brackets or multi-line string)
 fn needs_more_lines(input: &str) -> bool {
+    if input.matches("\"\"\"").count() % 2 == 1 {
+        return true;
+    }
+
     let mut parens = 0;
     let mut brackets = 0;
     let mut braces = 0;
@@ -342,7 +498,7 @@ fn needs_more_lines(input: &str) -> bool {
 
 /// Show a random tip on startup
 fn show_startup_tip(ui: &UI) {
-    let tips = vec![
+    let tips = [
         "Use Tab to auto-complete keywords and identifiers",
         "Type :help to see all available commands",
         "Press Ctrl+L to clear the screen",
@@ -1,11 +1,19 @@
 use crate::config::ReplConfig;
 use anyhow::Result;
 use std::collections::HashMap;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
 use veyra_compiler::{
+    ast::{Function, Program, Variant},
+    cfg::{self, CfgOptions},
+    include,
     interpreter::{Interpreter, Value},
     lexer::Lexer,
+    module_cache::ModuleCache,
     parser::Parser as VeyraParser,
+    sandbox::Policy,
 };
 
 /// REPL execution state
@@ -17,42 +25,137 @@ pub struct ReplState {
     functions: Vec<String>,
     pub(crate) multiline_buffer: String,
     last_execution_time: Option<f64>,
+    watched: Vec<WatchedFile>,
+    session: Vec<SessionEntry>,
+    /// When the configuration file was last read or saved
+    config_modified: Option<SystemTime>,
+    /// Whether program output is printed as well as captured
+    echo_output: bool,
+    /// Flag that interrupts the running code, kept across `reset`
+    interrupt: Option<Arc<AtomicBool>>,
+}
+
+/// One evaluated input with what it produced, for `:export`
+struct SessionEntry {
+    input: String,
+    output: String,
+    result: std::result::Result<Option<String>, String>,
+    timing: f64,
+}
+
+/// A file reloaded by `:watch` whenever it changes on disk
+struct WatchedFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// Last modification time of a file, if it can be read
+pub fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Last modification time of the configuration file
+fn config_modified() -> Option<SystemTime> {
+    ReplConfig::config_path()
+        .ok()
+        .and_then(|path| modified_time(&path))
+}
+
+/// An interpreter set up according to the config
+fn new_interpreter(config: &ReplConfig) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    if config.sandbox {
+        let allow = &config.sandbox_allow;
+        let mut policy = Policy::deny_all();
+        for path in &allow.read {
+            policy = policy.allow_read(path);
+        }
+        for path in &allow.write {
+            policy = policy.allow_write(path);
+        }
+        for host in &allow.net {
+            policy = policy.allow_host(host.clone());
+        }
+        for program in &allow.run {
+            policy = policy.allow_process(program.clone());
+        }
+        for name in &allow.env {
+            policy = policy.allow_env(name.clone());
+        }
+        interpreter.set_policy(policy);
+    }
+    interpreter
 }
 
 impl ReplState {
     pub fn new(config: ReplConfig) -> Self {
         Self {
-            interpreter: Interpreter::new(),
+            interpreter: new_interpreter(&config),
             history: Vec::new(),
             config,
             variables: HashMap::new(),
             functions: Vec::new(),
             multiline_buffer: String::new(),
             last_execution_time: None,
+            watched: Vec::new(),
+            session: Vec::new(),
+            config_modified: config_modified(),
+            echo_output: true,
+            interrupt: None,
         }
     }
 
+    /// Only capture program output instead of also printing it, for
+    /// frontends that show the output of each input themselves
+    pub fn set_echo_output(&mut self, echo: bool) {
+        self.echo_output = echo;
+        self.interpreter.set_echo_output(echo);
+    }
+
+    /// Stop running code whenever `flag` is set from another thread
+    pub fn set_interrupt(&mut self, flag: Arc<AtomicBool>) {
+        self.interpreter.set_interrupt(flag.clone());
+        self.interrupt = Some(flag);
+    }
+
     /// Execute Veyra code
     pub fn execute(&mut self, input: &str) -> Result<Option<Value>> {
+        self.execute_in(input, None)
+    }
+
+    /// Execute Veyra code, resolving include! against `base_dir` when given
+    fn execute_in(&mut self, input: &str, base_dir: Option<&Path>) -> Result<Option<Value>> {
         if input.trim().is_empty() {
             return Ok(None);
         }
 
         let start = Instant::now();
 
-        // Tokenize
-        let mut lexer = Lexer::new(input);
-        let tokens = lexer.tokenize()?;
+        self.interpreter.capture_output();
+        let result = self.run(input, base_dir);
+        let output = self.interpreter.take_output();
 
-        // Parse
-        let mut parser = VeyraParser::new(tokens);
-        let ast = parser.parse()?;
+        let duration = start.elapsed();
+        let timing = duration.as_secs_f64() * 1000.0;
 
-        // Execute
-        let result = self.interpreter.interpret(&ast)?;
+        // Record the entry for :export, including failed ones
+        self.session.push(SessionEntry {
+            input: input.to_string(),
+            output,
+            result: match &result {
+                Ok(Value::None) => Ok(None),
+                Ok(value) => Ok(Some(format!(
+                    "[{}] {}",
+                    type_name(value),
+                    format_value(value)
+                ))),
+                Err(e) => Err(e.to_string()),
+            },
+            timing,
+        });
 
-        let duration = start.elapsed();
-        self.last_execution_time = Some(duration.as_secs_f64() * 1000.0);
+        let result = result?;
+        self.last_execution_time = Some(timing);
 
         // Add to history
         self.history.push(input.to_string());
@@ -60,6 +163,18 @@ impl ReplState {
         Ok(Some(result))
     }
 
+    fn run(&mut self, input: &str, base_dir: Option<&Path>) -> Result<Value> {
+        // Files loaded from a project are only compiled again when they change
+        let ast = match base_dir.and_then(ModuleCache::for_path) {
+            Some(cache) => {
+                let options = format!("{:?}", CfgOptions::host());
+                cache.load_or_compile(input, &options, || compile(input, base_dir))?
+            }
+            None => compile(input, base_dir)?,
+        };
+        Ok(self.interpreter.interpret(&ast)?)
+    }
+
     /// Get execution timing
     pub fn last_timing(&self) -> Option<f64> {
         self.last_execution_time
@@ -78,6 +193,11 @@ impl ReplState {
         std::mem::take(&mut self.multiline_buffer)
     }
 
+    /// Input collected so far in multiline mode
+    pub fn multiline_input(&self) -> &str {
+        &self.multiline_buffer
+    }
+
     /// Check if in multiline mode
     pub fn is_multiline(&self) -> bool {
         !self.multiline_buffer.is_empty()
@@ -98,9 +218,43 @@ impl ReplState {
         &mut self.config
     }
 
+    /// Replace the configuration with one read again from disk. The
+    /// sandbox stays as it is: it is only chosen at startup.
+    pub fn replace_config(&mut self, config: ReplConfig) {
+        let sandbox = self.config.sandbox;
+        let sandbox_allow = std::mem::take(&mut self.config.sandbox_allow);
+        self.config = ReplConfig {
+            sandbox,
+            sandbox_allow,
+            ..config
+        };
+    }
+
+    /// Save the configuration file
+    pub fn save_config(&mut self) -> Result<()> {
+        self.config.save()?;
+        self.config_modified = config_modified();
+        Ok(())
+    }
+
+    /// Whether the configuration file was changed by something other than
+    /// this REPL since it was last checked, read or saved
+    pub fn config_file_changed(&mut self) -> bool {
+        let modified = config_modified();
+        if modified.is_none() || modified == self.config_modified {
+            return false;
+        }
+        self.config_modified = modified;
+        true
+    }
+
     /// Clear state
     pub fn reset(&mut self) {
-        self.interpreter = Interpreter::new();
+        self.interpreter = new_interpreter(&self.config);
+        self.interpreter.set_echo_output(self.echo_output);
+        if let Some(flag) = &self.interrupt {
+            self.interpreter.set_interrupt(flag.clone());
+        }
         self.variables.clear();
         self.functions.clear();
         self.multiline_buffer.clear();
@@ -116,19 +270,111 @@ impl ReplState {
         &self.functions
     }
 
+    /// Look up a user-defined function by name
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.interpreter.function(name)
+    }
+
+    /// The interpreter running the session
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// What the last input printed
+    pub fn last_output(&self) -> &str {
+        self.session
+            .last()
+            .map_or("", |entry| entry.output.as_str())
+    }
+
+    /// Groups of leaked reference targets that keep each other alive
+    pub fn memory_cycles(&self) -> Vec<Vec<String>> {
+        self.interpreter.memory_cycles()
+    }
+
     /// Load and execute a file
     pub fn load_file(&mut self, path: &std::path::Path) -> Result<()> {
         let content = std::fs::read_to_string(path)?;
-        self.execute(&content)?;
+        self.execute_in(&content, path.parent())?;
         Ok(())
     }
 
+    /// Start watching a file; returns false if it was already watched
+    pub fn watch(&mut self, path: &Path) -> bool {
+        if self.watched.iter().any(|w| w.path == path) {
+            return false;
+        }
+        self.watched.push(WatchedFile {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+        });
+        true
+    }
+
+    /// Stop watching a file; returns false if it was not watched
+    pub fn unwatch(&mut self, path: &Path) -> bool {
+        let before = self.watched.len();
+        self.watched.retain(|w| w.path != path);
+        self.watched.len() != before
+    }
+
+    /// Paths of all watched files
+    pub fn watched_files(&self) -> Vec<&Path> {
+        self.watched.iter().map(|w| w.path.as_path()).collect()
+    }
+
+    /// Watched files modified since they were last checked
+    pub fn changed_watched_files(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for watched in &mut self.watched {
+            let modified = modified_time(&watched.path);
+            if modified.is_some() && modified != watched.modified {
+                watched.modified = modified;
+                changed.push(watched.path.clone());
+            }
+        }
+        changed
+    }
+
     /// Save history to file
     pub fn save_history(&self, path: &std::path::Path) -> Result<()> {
         let content = self.history.join("\n");
         std::fs::write(path, content)?;
         Ok(())
     }
+
+    /// Write the session as Markdown, with each input followed by its output
+    pub fn export_markdown(&self, path: &std::path::Path) -> Result<()> {
+        let mut doc = String::from("# Veyra REPL session\n\n");
+        doc.push_str(&format!(
+            "_Exported {}_\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M")
+        ));
+
+        for entry in &self.session {
+            doc.push_str(&format!("\n```veyra\n{}\n```\n", entry.input.trim_end()));
+
+            let mut output = entry.output.clone();
+            match &entry.result {
+                Ok(Some(value)) => output.push_str(&format!("⇒ {}\n", value)),
+                Ok(None) => {}
+                Err(e) => output.push_str(&format!("Error: {}\n", e)),
+            }
+            if !output.is_empty() {
+                doc.push_str(&format!("\n```text\n{}```\n", output));
+            }
+
+            doc.push_str(&format!("\n_{:.3}ms_\n", entry.timing));
+        }
+
+        std::fs::write(path, doc)?;
+        Ok(())
+    }
+
+    /// Number of recorded session entries
+    pub fn session_len(&self) -> usize {
+        self.session.len()
+    }
 }
 
 /// Format a value for display
@@ -143,15 +389,7 @@ fn format_value_with_depth(value: &Value, depth: usize, max_depth: usize) -> Str
 
     match value {
         Value::Integer(n) => n.to_string(),
-        Value::Float(f) => {
-            if f.fract() == 0.0 && f.abs() < 1e10 {
-                format!("{:.1}", f)
-            } else {
-                f.to_string()
-            }
-        }
-        Value::String(s) => format!("\"{}\"", s.escape_default()),
-        Value::Char(c) => format!("'{}'", c.escape_default()),
+        Value::Float(_) | Value::String(_) | Value::Char(_) => value.repr(),
         Value::Boolean(b) => b.to_string(),
         Value::None => "None".to_string(),
         Value::Array(arr) => {
@@ -184,8 +422,8 @@ fn format_value_with_depth(value: &Value, depth: usize, max_depth: usize) -> Str
                     .take(10)
                     .map(|(k, v)| {
                         format!(
-                            "\"{}\": {}",
-                            k.escape_default(),
+                            "{}: {}",
+                            k.repr(),
                             format_value_with_depth(v, depth + 1, max_depth)
                         )
                     })
@@ -203,11 +441,7 @@ fn format_value_with_depth(value: &Value, depth: usize, max_depth: usize) -> Str
             if set.is_empty() {
                 "{}".to_string()
             } else {
-                let mut elements: Vec<String> = set
-                    .iter()
-                    .take(10)
-                    .map(|s| format!("\"{}\"", s.escape_default()))
-                    .collect();
+                let mut elements: Vec<String> = set.keys().take(10).map(Value::repr).collect();
                 elements.sort();
 
                 if set.len() > 10 {
@@ -243,6 +477,36 @@ fn format_value_with_depth(value: &Value, depth: usize, max_depth: usize) -> Str
             ),
             Err(_) => "&<borrowed>".to_string(),
         },
+        Value::Weak(w) => match w.value.upgrade() {
+            Some(target) => format!("<weak {}>", type_name(&target.borrow())),
+            None => "<weak, dropped>".to_string(),
+        },
+        Value::Task(task) => format!("<task {}>", task),
+        Value::Actor(actor) => format!("<actor {}>", actor),
+        Value::Function(_) => value.repr(),
+        Value::Variant(variant, inner) => format!(
+            "{}({})",
+            variant.name(),
+            format_value_with_depth(inner, depth + 1, max_depth)
+        ),
+        Value::Struct(instance) => {
+            if depth >= max_depth - 1 {
+                format!("{} {{ ... }}", instance.name)
+            } else {
+                let fields: Vec<String> = instance
+                    .fields
+                    .iter()
+                    .map(|(name, v)| {
+                        format!(
+                            "{}: {}",
+                            name,
+                            format_value_with_depth(v, depth + 1, max_depth)
+                        )
+                    })
+                    .collect();
+                format!("{} {{ {} }}", instance.name, fields.join(", "))
+            }
+        }
     }
 }
 
@@ -266,5 +530,26 @@ pub fn type_name(value: &Value) -> &'static str {
                 "&"
             }
         }
+        Value::Weak(_) => "weak",
+        Value::Task(_) => "task",
+        Value::Actor(_) => "actor",
+        Value::Struct(_) => "struct",
+        Value::Function(_) => "function",
+        Value::Variant(Variant::Some, _) => "option",
+        Value::Variant(Variant::Ok | Variant::Err, _) => "result",
     }
 }
+
+/// Tokenize and parse input, then drop items whose @cfg does not match this
+/// platform
+fn compile(input: &str, base_dir: Option<&Path>) -> veyra_compiler::error::Result<Program> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = lexer.tokenize()?;
+    if let Some(dir) = base_dir {
+        tokens = include::expand_includes(tokens, dir)?;
+    }
+
+    let mut parser = VeyraParser::new(tokens);
+    let ast = parser.parse()?;
+    Ok(cfg::configure(ast, &CfgOptions::host()))
+}
@@ -1,3 +1,4 @@
+use crate::config::ColorScheme;
 use nu_ansi_term::{Color, Style};
 
 /// UI theme and styling
@@ -90,6 +91,18 @@ impl Theme {
             muted: Color::Rgb(147, 161, 161),
         }
     }
+
+    /// The theme of a configured color scheme
+    pub fn for_scheme(scheme: &ColorScheme) -> Self {
+        match scheme {
+            ColorScheme::Monokai => Self::monokai(),
+            ColorScheme::Dracula => Self::dracula(),
+            ColorScheme::Nord => Self::nord(),
+            ColorScheme::SolarizedDark => Self::solarized_dark(),
+            ColorScheme::SolarizedLight => Self::solarized_light(),
+            _ => Self::default(),
+        }
+    }
 }
 
 pub struct UI {
@@ -103,8 +116,7 @@ impl UI {
 
     /// Print the welcome banner
     pub fn print_banner(&self) {
-        let banner = format!(
-            r#"
+        let banner = r#"
 ╔═══════════════════════════════════════════════════════════════════════╗
 ║                                                                       ║
 ║     ██╗   ██╗███████╗██╗   ██╗██████╗  █████╗                        ║
@@ -118,8 +130,7 @@ impl UI {
 ║            The Modern Programming Language                           ║
 ║                                                                       ║
 ╚═══════════════════════════════════════════════════════════════════════╝
-"#
-        );
+"#;
 
         println!("{}", self.theme.primary.paint(banner));
         println!(
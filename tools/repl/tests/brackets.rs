@@ -0,0 +1,27 @@
+// Tests for the bracket tracking behind auto-indent and auto-close
+
+use veyra_repl::helper::{close_brackets, open_brackets};
+
+#[test]
+fn brackets_are_counted_from_tokens() {
+    assert_eq!(
+        open_brackets("fn f(a) {\n    let xs = [1, (2"),
+        ['{', '[', '(']
+    );
+    assert_eq!(open_brackets("let p = {x: [1, 2]}"), Vec::<char>::new());
+
+    // Brackets in strings, interpolations and comments are not code
+    assert_eq!(open_brackets("print(\"(\" + ')')"), Vec::<char>::new());
+    assert_eq!(open_brackets("print(\"{name} [\")"), Vec::<char>::new());
+    assert_eq!(open_brackets("if x { # closes ) ]"), ['{']);
+    assert_eq!(open_brackets("#[[ { ]]# f("), ['(']);
+}
+
+#[test]
+fn open_brackets_are_closed_innermost_first() {
+    assert_eq!(
+        close_brackets("fn f() {\n    g([1, 2\n"),
+        "fn f() {\n    g([1, 2\n        ]\n    )\n}"
+    );
+    assert_eq!(close_brackets("# {\nlet x = 1"), "# {\nlet x = 1");
+}
@@ -0,0 +1,229 @@
+// Tests for `veyra-repl --kernel`, talking to it as a frontend would
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct Kernel {
+    process: Child,
+    address: String,
+    key: String,
+    _dir: TempDir,
+}
+
+impl Kernel {
+    /// Start a kernel with its own empty configuration directory
+    fn start() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let mut process = Command::new(env!("CARGO_BIN_EXE_veyra-repl"))
+            .arg("--kernel")
+            .current_dir(dir.path())
+            .env("XDG_CONFIG_HOME", dir.path())
+            .env("XDG_DATA_HOME", dir.path())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(process.stdout.as_mut().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let info: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(info["transport"], "tcp");
+        Self {
+            process,
+            address: info["address"].as_str().unwrap().to_string(),
+            key: info["key"].as_str().unwrap().to_string(),
+            _dir: dir,
+        }
+    }
+
+    fn connect(&self) -> Frontend {
+        let stream = TcpStream::connect(&self.address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(20)))
+            .unwrap();
+        Frontend {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            stream,
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl Drop for Kernel {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+struct Frontend {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    /// The key requests give
+    key: String,
+}
+
+impl Frontend {
+    fn send(&mut self, id: u64, method: &str, params: Value) {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+            "key": self.key,
+        });
+        writeln!(self.stream, "{}", request).unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.send(1, method, params);
+        let response = self.receive();
+        assert_eq!(response["id"], 1);
+        response
+    }
+}
+
+#[test]
+fn executes_code_in_one_session() {
+    let kernel = Kernel::start();
+    let mut frontend = kernel.connect();
+
+    let result = &frontend.request(
+        "execute",
+        json!({ "code": "let total = 40\nprint(total + 2)" }),
+    )["result"];
+    assert_eq!(result["status"], "ok");
+    assert_eq!(result["output"], "42\n");
+    assert_eq!(result["execution_count"], 1);
+
+    let result = &frontend.request("execute", json!({ "code": "print(missing)" }))["result"];
+    assert_eq!(result["status"], "error");
+    assert!(result["error"].as_str().unwrap().contains("missing"));
+
+    // The session outlives the connection
+    drop(frontend);
+    let mut frontend = kernel.connect();
+    let result = &frontend.request("execute", json!({ "code": "print(total)" }))["result"];
+    assert_eq!(result["output"], "40\n");
+    assert_eq!(result["execution_count"], 3);
+
+    assert_eq!(
+        frontend.request("shutdown", json!({}))["result"],
+        Value::Null
+    );
+}
+
+#[test]
+fn completes_and_inspects_names() {
+    let kernel = Kernel::start();
+    let mut frontend = kernel.connect();
+    frontend.request(
+        "execute",
+        json!({ "code": "let counter = 1\nfn count_up() {\n    return 1\n}" }),
+    );
+
+    let result =
+        &frontend.request("complete", json!({ "code": "print(cou", "cursor": 9 }))["result"];
+    assert_eq!(result["matches"], json!(["count_up", "counter"]));
+    assert_eq!(result["cursor_start"], 6);
+    assert_eq!(result["cursor_end"], 9);
+
+    let result =
+        &frontend.request("inspect", json!({ "code": "counter + 1", "cursor": 3 }))["result"];
+    assert_eq!(result["found"], true);
+    assert_eq!(result["text"], "counter: [int] 1");
+
+    // Values are shown with repr, so strings are quoted and escaped
+    frontend.request("execute", json!({ "code": "let greeting = \"hi\\n\"" }));
+    let result = &frontend.request("inspect", json!({ "code": "greeting" }))["result"];
+    assert_eq!(result["text"], "greeting: [string] \"hi\\n\"");
+
+    let result = &frontend.request("inspect", json!({ "code": "len" }))["result"];
+    assert!(result["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("len(collection)"));
+
+    let result = &frontend.request("inspect", json!({ "code": "nothing" }))["result"];
+    assert_eq!(result["found"], false);
+}
+
+#[test]
+fn interrupts_running_code() {
+    let kernel = Kernel::start();
+    let mut frontend = kernel.connect();
+
+    frontend.send(1, "execute", json!({ "code": "while true {\n}" }));
+    thread::sleep(Duration::from_millis(200));
+    frontend.send(2, "interrupt", json!({}));
+
+    let response = frontend.receive();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["interrupted"], true);
+    let response = frontend.receive();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["error"], "Runtime Error: Interrupted");
+
+    // Nothing is running, so the next code is not interrupted
+    frontend.send(3, "interrupt", json!({}));
+    assert_eq!(frontend.receive()["result"]["interrupted"], false);
+    let result = &frontend.request("execute", json!({ "code": "print(1)" }))["result"];
+    assert_eq!(result["status"], "ok");
+}
+
+#[test]
+fn reports_protocol_errors() {
+    let kernel = Kernel::start();
+    let mut frontend = kernel.connect();
+
+    assert_eq!(
+        frontend.request("evaluate", json!({}))["error"]["code"],
+        -32601
+    );
+    assert_eq!(
+        frontend.request("execute", json!({}))["error"]["code"],
+        -32602
+    );
+    assert_eq!(
+        frontend.request("complete", json!({ "code": "x", "cursor": 5 }))["error"]["code"],
+        -32602
+    );
+
+    writeln!(frontend.stream, "not json").unwrap();
+    let response = frontend.receive();
+    assert_eq!(response["id"], Value::Null);
+    assert_eq!(response["error"]["code"], -32700);
+}
+
+#[test]
+fn refuses_requests_without_the_key() {
+    let kernel = Kernel::start();
+    assert_eq!(kernel.key.len(), 32);
+    let mut frontend = kernel.connect();
+    let code = json!({ "code": "let ran = true" });
+
+    for key in [Value::Null, json!(""), json!("0".repeat(32))] {
+        let request =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "execute", "params": code, "key": key });
+        writeln!(frontend.stream, "{}", request).unwrap();
+        let response = frontend.receive();
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], -32001);
+    }
+
+    // None of the refused code ran
+    let result = &frontend.request("execute", json!({ "code": "print(ran)" }))["result"];
+    assert_eq!(result["status"], "error");
+    assert_eq!(result["execution_count"], 1);
+}
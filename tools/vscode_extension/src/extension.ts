@@ -750,6 +750,7 @@ function parseCompilerErrors(errorOutput: string, document: vscode.TextDocument)
     console.log('Raw compiler output:', errorOutput);
     
     // Match patterns like:
+    // "error: Expected parameter name\n --> main.vey:1:7"
     // "Lexer error at line 135, column 38: Unexpected character '&'"
     // "Parser error at line 5, column 13: Expected expression"
     // "Error: ParseError { line: 15, column: 1, message: "Expected '}' after block, found ''" }"
@@ -757,6 +758,8 @@ function parseCompilerErrors(errorOutput: string, document: vscode.TextDocument)
     // "Syntax Error at line 3, column 5: Unexpected token"
     
     const patterns = [
+        // Pattern 0: veyc's rendered diagnostics, message first, then where
+        /^error:\s*(.+)\n\s*-->\s*.*?:(\d+):(\d+)/gm,
         // Pattern 1: "Lexer/Parser error at line X, column Y: message" (most common)
         /(?:Lexer|Parser|Syntax|Parse)\s+error\s+at\s+line\s+(\d+),\s+column\s+(\d+):\s+(.+?)(?:\n|$)/gi,
         // Pattern 2: ParseError/LexError with struct format
@@ -771,9 +774,12 @@ function parseCompilerErrors(errorOutput: string, document: vscode.TextDocument)
         let match;
         pattern.lastIndex = 0; // Reset regex state
         while ((match = pattern.exec(errorOutput)) !== null) {
-            const line = parseInt(match[1]) - 1; // Convert to 0-based
-            const column = parseInt(match[2]) - 1; // Convert to 0-based
-            const message = match[3].trim();
+            const [lineNumber, columnNumber, messageText] = pattern === patterns[0]
+                ? [match[2], match[3], match[1]]
+                : [match[1], match[2], match[3]];
+            const line = parseInt(lineNumber) - 1; // Convert to 0-based
+            const column = parseInt(columnNumber) - 1; // Convert to 0-based
+            const message = messageText.trim();
 
             console.log(`Parsed error: line ${line + 1}, column ${column + 1}, message: ${message}`);
 